@@ -0,0 +1,411 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::types::*;
+
+const APP_IDENTIFIER: &str = "com.shauryagupta.stud";
+const DISCOVERY_FILENAME: &str = "bridge.json";
+const SECRET_FILENAME: &str = "bridge-secret";
+const SECRET_HEADER: &str = "X-Stud-Secret";
+const DEFAULT_BRIDGE_URL: &str = "http://localhost:3001";
+/// The protocol version this client speaks, and its routes
+const PROTOCOL_VERSION: u32 = 2;
+const API_PREFIX: &str = "/stud/v2";
+/// Bodies larger than this are sent to /respond/chunk in parts, like the plugin does
+pub const RESPONSE_CHUNK_SIZE: usize = 200_000;
+/// A little longer than the bridge waits for Studio, so its timeout error arrives first
+const REQUEST_TIMEOUT_SECS: u64 = 20;
+
+/// A request for Studio, built up before sending with [`BridgeClient::send`]
+#[derive(Debug, Clone)]
+pub struct Request {
+    inner: StudioRequest,
+    idempotency_key: Option<String>,
+}
+
+impl Request {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            inner: StudioRequest {
+                protocol_version: None,
+                id: None,
+                path: path.into(),
+                body: None,
+                target_session: None,
+                attachment: None,
+                priority: Priority::Normal,
+                tool_call_id: None,
+                chat_id: None,
+                turn_id: None,
+                trace_id: None,
+                allowed_paths: None,
+            },
+            idempotency_key: None,
+        }
+    }
+
+    /// JSON body for the plugin's handler
+    pub fn body(mut self, body: &serde_json::Value) -> Self {
+        self.inner.body = Some(body.to_string());
+        self
+    }
+
+    /// Id to cancel the request by; one is generated if unset
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.inner.id = Some(id.into());
+        self
+    }
+
+    /// Studio session that should handle the request (any session if unset)
+    pub fn session(mut self, session: impl Into<String>) -> Self {
+        self.inner.target_session = Some(session.into());
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.inner.priority = priority;
+        self
+    }
+
+    /// Tag the request with a conversation, so it can be cancelled along with it
+    pub fn chat(mut self, chat_id: impl Into<String>, turn_id: Option<String>) -> Self {
+        self.inner.chat_id = Some(chat_id.into());
+        self.inner.turn_id = turn_id;
+        self
+    }
+
+    /// Trace id to look the request's timeline up by in the app; one is
+    /// generated if unset
+    pub fn trace_id(mut self, trace_id: impl Into<String>) -> Self {
+        self.inner.trace_id = Some(trace_id.into());
+        self
+    }
+
+    /// Bytes sent to the plugin alongside the body
+    pub fn attachment(mut self, content_type: impl Into<String>, data: &[u8]) -> Self {
+        self.inner.attachment = Some(Attachment {
+            content_type: content_type.into(),
+            data: BASE64.encode(data),
+        });
+        self
+    }
+
+    /// Retrying with the same key returns the first attempt's result instead
+    /// of running the request twice
+    pub fn idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+}
+
+/// Cancels a sent request on the bridge if the caller stops waiting for it
+struct CancelOnDrop {
+    client: Option<BridgeClient>,
+    id: String,
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(client) = self.client.take() else {
+            return;
+        };
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let id = std::mem::take(&mut self.id);
+            runtime.spawn(async move {
+                let _ = client
+                    .cancel(CancelScope {
+                        id: Some(id),
+                        ..Default::default()
+                    })
+                    .await;
+            });
+        }
+    }
+}
+
+/// Talks to a running Stud bridge over HTTP
+#[derive(Debug, Clone)]
+pub struct BridgeClient {
+    base_url: String,
+    secret: String,
+    http: reqwest::Client,
+}
+
+fn app_data_dir() -> Result<PathBuf, Error> {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| Error::Discovery("Could not determine the app data folder".to_string()))
+}
+
+impl BridgeClient {
+    /// A client for the bridge at `base_url` (e.g. "http://localhost:3001")
+    pub fn new(base_url: impl Into<String>, secret: impl Into<String>) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            secret: secret.into(),
+            http,
+        }
+    }
+
+    /// Find the bridge of the Stud running for this user, from the discovery
+    /// file and secret Stud keeps in its app data folder
+    pub fn discover() -> Result<Self, Error> {
+        let dir = app_data_dir()?;
+        let secret = std::fs::read_to_string(dir.join(SECRET_FILENAME))
+            .map_err(|e| Error::Discovery(format!("Failed to read the bridge secret: {}", e)))?;
+        // Stud writes the discovery file once its bridge is listening; before
+        // that (or for older versions) the default port is the best guess
+        let base_url = std::fs::read_to_string(dir.join(DISCOVERY_FILENAME))
+            .ok()
+            .and_then(|json| serde_json::from_str::<Discovery>(&json).ok())
+            .and_then(|discovery| discovery.bridge_url)
+            .unwrap_or_else(|| DEFAULT_BRIDGE_URL.to_string());
+        Ok(Self::new(base_url, secret.trim()))
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}{}", self.base_url, API_PREFIX, path)
+    }
+
+    fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .get(self.url(path))
+            .header(SECRET_HEADER, &self.secret)
+    }
+
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .post(self.url(path))
+            .header(SECRET_HEADER, &self.secret)
+    }
+
+    /// Whether a Stud bridge answers at the base URL; needs no secret
+    pub async fn health(&self) -> Result<Health, Error> {
+        let response = self
+            .http
+            .get(format!("{}/stud/health", self.base_url))
+            .send()
+            .await?;
+        read_json(response).await
+    }
+
+    pub async fn status(&self) -> Result<Status, Error> {
+        read_json(self.get("/status").send().await?).await
+    }
+
+    /// Send a request to Studio and wait for its answer. If the returned
+    /// future is dropped first, the request is cancelled on the bridge.
+    pub async fn send(&self, request: Request) -> Result<Payload, Error> {
+        let mut inner = request.inner;
+        let id = inner
+            .id
+            .get_or_insert_with(|| uuid::Uuid::new_v4().to_string())
+            .clone();
+        let mut guard = CancelOnDrop {
+            client: Some(self.clone()),
+            id,
+        };
+
+        let mut builder = self.post("/request").json(&inner);
+        if let Some(key) = &request.idempotency_key {
+            builder = builder.header("Idempotency-Key", key);
+        }
+        let result = builder.send().await;
+        // The bridge has answered (or is gone); nothing is left to cancel
+        guard.client = None;
+        let response = result?;
+
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("application/json")
+            .to_string();
+        if status.is_success() && !content_type.starts_with("application/json") {
+            let data = response.bytes().await?.to_vec();
+            return Ok(Payload::Binary { content_type, data });
+        }
+        let value: serde_json::Value = read_json(response).await?;
+        // Handlers that fail in Studio come back as 200 with an `error` field
+        if let Some(message) = value.get("error").and_then(|e| e.as_str()) {
+            return Err(Error::Studio {
+                status: status.as_u16(),
+                message: message.to_string(),
+                violations: Vec::new(),
+            });
+        }
+        Ok(Payload::Json(value))
+    }
+
+    /// Send a request with a JSON body and expect a JSON answer
+    pub async fn request(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<serde_json::Value, Error> {
+        match self.send(Request::new(path).body(body)).await? {
+            Payload::Json(value) => Ok(value),
+            Payload::Binary { content_type, .. } => Err(Error::InvalidResponse(format!(
+                "Studio returned binary data ({}) where JSON was expected",
+                content_type
+            ))),
+        }
+    }
+
+    /// Cancel queued requests; returns the ids that were cancelled
+    pub async fn cancel(&self, scope: CancelScope) -> Result<Vec<String>, Error> {
+        #[derive(serde::Deserialize)]
+        struct Cancelled {
+            cancelled: Vec<String>,
+        }
+        let response = self.post("/cancel").json(&scope).send().await?;
+        Ok(read_json::<Cancelled>(response).await?.cancelled)
+    }
+
+    /// Poll for requests as the Studio plugin does, for simulated plugins in tests
+    pub async fn poll(&self, options: &PollOptions) -> Result<PollResponse, Error> {
+        let mut query: Vec<(&str, String)> = vec![
+            ("wait", options.wait_secs.to_string()),
+            ("max", options.max.max(1).to_string()),
+        ];
+        if let Some(session) = &options.session {
+            query.push(("session", session.clone()));
+        }
+        if let Some(place_id) = options.place_id {
+            query.push(("place_id", place_id.to_string()));
+        }
+        if let Some(place_name) = &options.place_name {
+            query.push(("place_name", place_name.clone()));
+        }
+        if let Some(plugin_version) = &options.plugin_version {
+            query.push(("plugin_version", plugin_version.clone()));
+        }
+        // Long polls outlast the default request timeout
+        let timeout = Duration::from_secs(options.wait_secs + REQUEST_TIMEOUT_SECS);
+        let response = self
+            .get("/poll")
+            .query(&query)
+            .timeout(timeout)
+            .send()
+            .await?;
+        read_json(response).await
+    }
+
+    /// Answer a polled request, splitting large bodies into parts
+    pub async fn respond(
+        &self,
+        id: &str,
+        status: u16,
+        body: &serde_json::Value,
+    ) -> Result<(), Error> {
+        let body = body.to_string();
+        if body.len() <= RESPONSE_CHUNK_SIZE {
+            let response = self
+                .post("/respond")
+                .json(&RespondRequest {
+                    id,
+                    response: StudioResponse {
+                        protocol_version: PROTOCOL_VERSION,
+                        status,
+                        body: &body,
+                        content_type: None,
+                    },
+                })
+                .send()
+                .await?;
+            let value: serde_json::Value = read_json(response).await?;
+            return match value.get("error").and_then(|e| e.as_str()) {
+                Some(message) => Err(Error::InvalidResponse(message.to_string())),
+                None => Ok(()),
+            };
+        }
+
+        let parts = split_chunks(&body, RESPONSE_CHUNK_SIZE);
+        let total = parts.len() as u32;
+        for (seq, data) in parts.into_iter().enumerate() {
+            let seq = seq as u32;
+            let response = self
+                .post("/respond/chunk")
+                .json(&ResponseChunk {
+                    id,
+                    seq,
+                    last: seq + 1 == total,
+                    status,
+                    content_type: None,
+                    total,
+                    data,
+                })
+                .send()
+                .await?;
+            let value: serde_json::Value = read_json(response).await?;
+            if let Some(message) = value.get("error").and_then(|e| e.as_str()) {
+                return Err(Error::InvalidResponse(message.to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Split text into parts of at most `size` bytes without breaking a character
+fn split_chunks(text: &str, size: usize) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (part, tail) = rest.split_at(end);
+        parts.push(part);
+        rest = tail;
+    }
+    parts
+}
+
+/// Decode a JSON answer, turning bridge error statuses into typed errors
+async fn read_json<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+) -> Result<T, Error> {
+    let status = response.status();
+    let retry_after_secs = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok()?.parse().ok());
+    let text = response.text().await?;
+    if status.is_success() {
+        return serde_json::from_str(&text).map_err(|e| Error::InvalidResponse(e.to_string()));
+    }
+
+    let value: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
+    match status.as_u16() {
+        401 => Err(Error::Unauthorized),
+        429 => Err(Error::QueueFull { retry_after_secs }),
+        413 => Err(Error::PayloadTooLarge {
+            limit_bytes: value.get("limit_bytes").and_then(|v| v.as_u64()),
+        }),
+        code => Err(Error::Studio {
+            status: code,
+            message: value
+                .get("error")
+                .and_then(|e| e.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("Bridge error {}: {}", code, text)),
+            violations: value
+                .get("violations")
+                .and_then(|v| v.as_array())
+                .cloned()
+                .unwrap_or_default(),
+        }),
+    }
+}
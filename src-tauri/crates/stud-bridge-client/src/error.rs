@@ -0,0 +1,76 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The bridge couldn't be located or its secret couldn't be read
+    Discovery(String),
+    /// The bridge couldn't be reached, or the connection dropped
+    Http(reqwest::Error),
+    /// The bridge refused the secret
+    Unauthorized,
+    /// Too many requests are waiting for Studio; try again after the delay
+    QueueFull { retry_after_secs: Option<u64> },
+    /// The body was over the bridge's `bridge.max_body_bytes`
+    PayloadTooLarge { limit_bytes: Option<u64> },
+    /// The bridge or Studio answered with an error
+    Studio {
+        status: u16,
+        message: String,
+        /// Naming or attribute rules the request broke, as the bridge reported them
+        violations: Vec<serde_json::Value>,
+    },
+    /// The bridge sent something this client doesn't understand
+    InvalidResponse(String),
+}
+
+impl Error {
+    /// Whether sending the same request again later may succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::QueueFull { .. } | Error::Http(_) => true,
+            Error::Studio { status, .. } => *status == 503 || *status == 504,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Discovery(message) => write!(f, "Could not find the Stud bridge: {}", message),
+            Error::Http(e) => write!(f, "Bridge request failed: {}", e),
+            Error::Unauthorized => write!(f, "The bridge rejected the secret"),
+            Error::QueueFull {
+                retry_after_secs: Some(secs),
+            } => write!(f, "Bridge is busy; retry in {}s", secs),
+            Error::QueueFull { .. } => write!(f, "Bridge is busy"),
+            Error::PayloadTooLarge {
+                limit_bytes: Some(limit),
+            } => write!(
+                f,
+                "Payload too large; the bridge accepts up to {} bytes",
+                limit
+            ),
+            Error::PayloadTooLarge { .. } => write!(f, "Payload too large"),
+            Error::Studio {
+                status, message, ..
+            } => write!(f, "{} ({})", message, status),
+            Error::InvalidResponse(message) => write!(f, "Unexpected bridge response: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
@@ -0,0 +1,32 @@
+//! Client for the Stud bridge
+//!
+//! Stud runs a local HTTP bridge that queues requests for the Roblox Studio
+//! plugin. This crate is the Rust side of that protocol for tools outside the
+//! app (CLIs, test harnesses, other services), so they don't hand-roll calls
+//! against localhost: it finds the running bridge and its secret, sends
+//! requests with priorities and conversation tags, cancels them when the
+//! caller gives up, and can stand in for the plugin (polling and chunked
+//! responses) in tests.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), stud_bridge_client::Error> {
+//! use stud_bridge_client::{BridgeClient, Priority, Request};
+//!
+//! let client = BridgeClient::discover()?;
+//! let selection = client
+//!     .send(Request::new("/selection/get").priority(Priority::High))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod client;
+mod error;
+mod types;
+
+pub use client::{BridgeClient, Request, RESPONSE_CHUNK_SIZE};
+pub use error::Error;
+pub use types::{
+    Attachment, CancelScope, Health, Payload, PollOptions, PollResponse, PolledRequest, Priority,
+    Status, StudioRequest, StudioSession,
+};
@@ -0,0 +1,185 @@
+//! Wire types shared with the bridge. These mirror the structs in the app's
+//! `bridge.rs`; fields the bridge may add later are ignored when reading.
+
+use serde::{Deserialize, Serialize};
+
+/// Interactive requests (selection, single scripts) should be `High`; heavy
+/// background work like full-tree dumps should be `Low`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub content_type: String,
+    /// Base64-encoded bytes
+    pub data: String,
+}
+
+/// A request for Studio, as queued by the bridge and handed to the plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudioRequest {
+    /// Protocol version the bridge wrote the request in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub path: String,
+    /// JSON-encoded body for the plugin's handler
+    pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_session: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<Attachment>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// Set by the bridge from the tool's sandbox profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_paths: Option<Vec<String>>,
+}
+
+/// What Studio sent back
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Json(serde_json::Value),
+    Binary { content_type: String, data: Vec<u8> },
+}
+
+impl Payload {
+    /// The JSON value, or None for binary responses
+    pub fn into_json(self) -> Option<serde_json::Value> {
+        match self {
+            Payload::Json(value) => Some(value),
+            Payload::Binary { .. } => None,
+        }
+    }
+}
+
+/// Which queued requests to cancel; every field left unset matches anything
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CancelScope {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub turn_id: Option<String>,
+}
+
+/// Answer to GET /stud/health; needs no secret
+#[derive(Debug, Clone, Deserialize)]
+pub struct Health {
+    pub service: String,
+    pub version: String,
+}
+
+/// A Studio window registered with the bridge
+#[derive(Debug, Clone, Deserialize)]
+pub struct StudioSession {
+    pub id: String,
+    pub place_id: Option<u64>,
+    pub place_name: Option<String>,
+    #[serde(default)]
+    pub user_name: Option<String>,
+    #[serde(default)]
+    pub studio_version: Option<String>,
+    pub connected: bool,
+    #[serde(default)]
+    pub plugin_version: Option<String>,
+}
+
+/// Answer to GET /stud/status
+#[derive(Debug, Clone, Deserialize)]
+pub struct Status {
+    pub connected: bool,
+    pub pending_requests: usize,
+    #[serde(default)]
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub app_version: String,
+    #[serde(default)]
+    pub version_warnings: Vec<String>,
+    /// The connected session that polled last
+    #[serde(default)]
+    pub studio: Option<StudioSession>,
+}
+
+/// Identifies a simulated plugin when polling
+#[derive(Debug, Clone, Default)]
+pub struct PollOptions {
+    pub session: Option<String>,
+    pub place_id: Option<u64>,
+    pub place_name: Option<String>,
+    pub plugin_version: Option<String>,
+    /// Seconds to hold the poll open waiting for work
+    pub wait_secs: u64,
+    /// Requests to take at once
+    pub max: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PolledRequest {
+    pub id: String,
+    pub request: StudioRequest,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PollResponse {
+    #[serde(default)]
+    pub requests: Vec<PolledRequest>,
+    /// Requests cancelled since the session last polled
+    #[serde(default)]
+    pub cancelled: Vec<String>,
+    #[serde(default)]
+    pub port_hint: Option<u16>,
+    #[serde(default)]
+    pub queue_depth: usize,
+    #[serde(default)]
+    pub next_poll_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct StudioResponse<'a> {
+    pub protocol_version: u32,
+    pub status: u16,
+    pub body: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RespondRequest<'a> {
+    pub id: &'a str,
+    pub response: StudioResponse<'a>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ResponseChunk<'a> {
+    pub id: &'a str,
+    pub seq: u32,
+    pub last: bool,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<&'a str>,
+    pub total: u32,
+    pub data: &'a str,
+}
+
+/// Contents of bridge.json in Stud's app data folder
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Discovery {
+    pub bridge_url: Option<String>,
+}
@@ -0,0 +1,166 @@
+//! Animation Inspection
+//!
+//! Tools for debugging "why isn't my animation playing": list the animations
+//! and keyframe sequences on selected rigs, export keyframe data page by page,
+//! and check AnimationIds against the Roblox catalog.
+
+use serde::{Deserialize, Serialize};
+
+use crate::bridge;
+use crate::net;
+
+/// Keyframes fetched per request so large sequences stay under the bridge's body limits
+const KEYFRAME_PAGE_SIZE: usize = 50;
+const ASSET_DETAILS_URL: &str = "https://economy.roblox.com/v2/assets";
+const ANIMATION_ASSET_TYPE: u32 = 24;
+
+#[derive(Debug, Serialize)]
+pub struct KeyframeExport {
+    pub path: String,
+    pub total: usize,
+    pub keyframes: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct KeyframePage {
+    total: usize,
+    #[serde(default)]
+    keyframes: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnimationIdCheck {
+    pub id: String,
+    pub asset_id: Option<u64>,
+    pub valid: bool,
+    pub name: Option<String>,
+    /// Animations only play in games owned by the same user or group
+    pub creator: Option<String>,
+    pub reason: Option<String>,
+}
+
+// Subset of the economy API asset details
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AssetDetails {
+    name: String,
+    asset_type_id: u32,
+    creator: Option<AssetCreator>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AssetCreator {
+    name: String,
+    creator_type: String,
+}
+
+/// Accepts "rbxassetid://123", "http://www.roblox.com/asset/?id=123" or a bare number
+fn parse_asset_id(id: &str) -> Option<u64> {
+    let id = id.trim();
+    let digits = id
+        .strip_prefix("rbxassetid://")
+        .or_else(|| id.rsplit_once("id=").map(|(_, rest)| rest))
+        .unwrap_or(id);
+    digits.parse().ok()
+}
+
+async fn check_animation_id(client: &reqwest::Client, id: String) -> AnimationIdCheck {
+    let mut check = AnimationIdCheck {
+        id,
+        asset_id: None,
+        valid: false,
+        name: None,
+        creator: None,
+        reason: None,
+    };
+
+    let Some(asset_id) = parse_asset_id(&check.id) else {
+        check.reason = Some("Not a valid asset id".to_string());
+        return check;
+    };
+    check.asset_id = Some(asset_id);
+
+    let response = match client
+        .get(format!("{}/{}/details", ASSET_DETAILS_URL, asset_id))
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            check.reason = Some(format!("Failed to reach Roblox: {}", e));
+            return check;
+        }
+    };
+
+    if !response.status().is_success() {
+        check.reason = Some(format!(
+            "Asset not found or not accessible (HTTP {})",
+            response.status().as_u16()
+        ));
+        return check;
+    }
+
+    match response.json::<AssetDetails>().await {
+        Ok(details) => {
+            check.name = Some(details.name);
+            check.creator = details
+                .creator
+                .map(|creator| format!("{} ({})", creator.name, creator.creator_type));
+            if details.asset_type_id == ANIMATION_ASSET_TYPE {
+                check.valid = true;
+            } else {
+                check.reason = Some(format!(
+                    "Asset is not an animation (asset type {})",
+                    details.asset_type_id
+                ));
+            }
+        }
+        Err(e) => check.reason = Some(format!("Unexpected response from Roblox: {}", e)),
+    }
+    check
+}
+
+/// List rigs in the selection with their animators, Animation objects,
+/// playing tracks and KeyframeSequences
+#[tauri::command]
+pub async fn list_animations(session: Option<String>) -> Result<serde_json::Value, String> {
+    bridge::studio_request(session.as_deref(), "/animation/list", serde_json::json!({})).await
+}
+
+/// Export every keyframe and pose of a KeyframeSequence, fetched in pages
+#[tauri::command]
+pub async fn export_keyframes(path: String, session: Option<String>) -> Result<KeyframeExport, String> {
+    let mut keyframes = Vec::new();
+    loop {
+        let result = bridge::studio_request(
+            session.as_deref(),
+            "/animation/keyframes",
+            serde_json::json!({
+                "path": path,
+                "offset": keyframes.len(),
+                "limit": KEYFRAME_PAGE_SIZE,
+            }),
+        )
+        .await?;
+        let page: KeyframePage = serde_json::from_value(result)
+            .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+
+        let received = page.keyframes.len();
+        keyframes.extend(page.keyframes);
+        if received == 0 || keyframes.len() >= page.total {
+            return Ok(KeyframeExport {
+                path,
+                total: page.total,
+                keyframes,
+            });
+        }
+    }
+}
+
+/// Check that AnimationIds point at real, accessible animation assets
+#[tauri::command]
+pub async fn validate_animation_ids(ids: Vec<String>) -> Vec<AnimationIdCheck> {
+    let client = net::client();
+    futures_util::future::join_all(ids.into_iter().map(|id| check_animation_id(&client, id))).await
+}
@@ -0,0 +1,356 @@
+//! Attribute Schemas
+//!
+//! Projects can declare which attributes instances should carry, and their
+//! types, per CollectionService tag or class. Writes through /attributes/set
+//! are checked before they reach Studio, and `validate_attributes` audits the
+//! whole place, so a misspelled or wrongly typed attribute set by the AI shows
+//! up instead of silently breaking the scripts that read it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::bridge::{self, StudioRequest};
+use crate::config;
+use crate::naming::Severity;
+
+/// Expected attributes for instances with a tag and/or of a class
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributeSchema {
+    /// Applies to instances with this CollectionService tag
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Applies to instances of this class or a subclass
+    #[serde(default)]
+    pub class_name: Option<String>,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, AttributeSpec>,
+    /// Also flag attributes the schema doesn't list
+    #[serde(default)]
+    pub strict: bool,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttributeSpec {
+    /// Luau typeof() name: string, number, boolean, Vector3, Color3, ...
+    #[serde(rename = "type")]
+    pub value_type: String,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AttributeViolation {
+    pub path: String,
+    pub attribute: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct AttributeValue {
+    #[serde(rename = "type")]
+    value_type: String,
+}
+
+/// An instance as reported by /attributes/describe and /attributes/scan
+#[derive(Deserialize)]
+struct DescribedInstance {
+    path: String,
+    /// Schema classes the instance IsA
+    #[serde(rename = "isA", default)]
+    is_a: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    attributes: HashMap<String, AttributeValue>,
+}
+
+#[derive(Deserialize)]
+struct DescribeResponse {
+    #[serde(default)]
+    instances: Vec<DescribedInstance>,
+}
+
+#[derive(Deserialize)]
+struct SetOperation {
+    path: String,
+    name: String,
+    #[serde(rename = "type", default)]
+    value_type: Option<String>,
+    #[serde(default)]
+    value: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct SetRequest {
+    #[serde(default)]
+    operations: Vec<SetOperation>,
+}
+
+impl AttributeSchema {
+    /// Schemas without a tag or class apply to nothing
+    fn applies_to(&self, instance: &DescribedInstance) -> bool {
+        if self.tag.is_none() && self.class_name.is_none() {
+            return false;
+        }
+        let tag_matches = self
+            .tag
+            .as_ref()
+            .is_none_or(|tag| instance.tags.contains(tag));
+        let class_matches = self
+            .class_name
+            .as_ref()
+            .is_none_or(|class_name| instance.is_a.contains(class_name));
+        tag_matches && class_matches
+    }
+
+    fn describe(&self) -> String {
+        match (&self.tag, &self.class_name) {
+            (Some(tag), Some(class_name)) => format!("{} tagged \"{}\"", class_name, tag),
+            (Some(tag), None) => format!("instances tagged \"{}\"", tag),
+            (None, Some(class_name)) => class_name.clone(),
+            (None, None) => "instances".to_string(),
+        }
+    }
+}
+
+/// Type of a value being written; None removes the attribute
+fn value_type(op: &SetOperation) -> Option<String> {
+    if let Some(value_type) = &op.value_type {
+        return (!op.value.is_null()).then(|| value_type.clone());
+    }
+    match &op.value {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(_) => Some("string".to_string()),
+        serde_json::Value::Number(_) => Some("number".to_string()),
+        serde_json::Value::Bool(_) => Some("boolean".to_string()),
+        _ => Some("unknown".to_string()),
+    }
+}
+
+fn schema_lookups(schemas: &[AttributeSchema]) -> (Vec<String>, Vec<String>) {
+    let mut class_names: Vec<String> = schemas
+        .iter()
+        .filter_map(|s| s.class_name.clone())
+        .collect();
+    let mut tags: Vec<String> = schemas.iter().filter_map(|s| s.tag.clone()).collect();
+    class_names.sort();
+    class_names.dedup();
+    tags.sort();
+    tags.dedup();
+    (class_names, tags)
+}
+
+fn violation(
+    path: &str,
+    attribute: &str,
+    schema: &AttributeSchema,
+    expected: Option<&str>,
+    actual: Option<&str>,
+    message: String,
+) -> AttributeViolation {
+    AttributeViolation {
+        path: path.to_string(),
+        attribute: attribute.to_string(),
+        expected: expected.map(str::to_string),
+        actual: actual.map(str::to_string),
+        severity: schema.severity,
+        message,
+    }
+}
+
+/// A listed attribute whose name differs only by case, for "did you mean"
+fn similar_name<'a>(schema: &'a AttributeSchema, name: &str) -> Option<&'a str> {
+    schema
+        .attributes
+        .keys()
+        .find(|listed| listed.eq_ignore_ascii_case(name))
+        .map(String::as_str)
+}
+
+/// Check one attribute's (new) type against a schema
+fn check_attribute(
+    schema: &AttributeSchema,
+    path: &str,
+    name: &str,
+    actual: Option<&str>,
+) -> Option<AttributeViolation> {
+    match (schema.attributes.get(name), actual) {
+        (Some(spec), None) if spec.required => Some(violation(
+            path,
+            name,
+            schema,
+            Some(&spec.value_type),
+            None,
+            format!(
+                "{} requires attribute \"{}\" ({})",
+                schema.describe(),
+                name,
+                spec.value_type
+            ),
+        )),
+        (Some(spec), Some(actual)) if !spec.value_type.eq_ignore_ascii_case(actual) => {
+            Some(violation(
+                path,
+                name,
+                schema,
+                Some(&spec.value_type),
+                Some(actual),
+                format!(
+                    "Attribute \"{}\" should be {}, not {}",
+                    name, spec.value_type, actual
+                ),
+            ))
+        }
+        (None, Some(actual)) => {
+            if let Some(listed) = similar_name(schema, name) {
+                Some(violation(
+                    path,
+                    name,
+                    schema,
+                    None,
+                    Some(actual),
+                    format!(
+                        "Unknown attribute \"{}\", did you mean \"{}\"?",
+                        name, listed
+                    ),
+                ))
+            } else if schema.strict {
+                Some(violation(
+                    path,
+                    name,
+                    schema,
+                    None,
+                    Some(actual),
+                    format!(
+                        "Attribute \"{}\" isn't in the schema for {}",
+                        name,
+                        schema.describe()
+                    ),
+                ))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn audit_instance(
+    schemas: &[AttributeSchema],
+    instance: &DescribedInstance,
+) -> Vec<AttributeViolation> {
+    let mut violations = Vec::new();
+    for schema in schemas.iter().filter(|schema| schema.applies_to(instance)) {
+        let mut names: Vec<&String> = schema
+            .attributes
+            .keys()
+            .chain(instance.attributes.keys())
+            .collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            let actual = instance
+                .attributes
+                .get(name)
+                .map(|value| value.value_type.as_str());
+            violations.extend(check_attribute(schema, &instance.path, name, actual));
+        }
+    }
+    violations
+}
+
+fn parse_instances(result: serde_json::Value) -> Result<Vec<DescribedInstance>, String> {
+    let response: DescribeResponse = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+    Ok(response.instances)
+}
+
+/// Check the attributes an /attributes/set request would write. Only the
+/// attributes being written are checked, so existing problems elsewhere on the
+/// instance don't block unrelated edits.
+pub async fn review_request(request: &StudioRequest) -> Vec<AttributeViolation> {
+    if request.path != "/attributes/set" {
+        return Vec::new();
+    }
+    let schemas = config::current().attributes.schemas;
+    if schemas.is_empty() {
+        return Vec::new();
+    }
+    let Some(body) = request
+        .body
+        .as_deref()
+        .and_then(|body| serde_json::from_str::<SetRequest>(body).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<&str> = body.operations.iter().map(|op| op.path.as_str()).collect();
+    paths.sort();
+    paths.dedup();
+    let (class_names, _) = schema_lookups(&schemas);
+    let result = bridge::studio_request(
+        request.target_session.as_deref(),
+        "/attributes/describe",
+        serde_json::json!({ "paths": paths, "classNames": class_names }),
+    )
+    .await;
+    let instances = match result.and_then(parse_instances) {
+        Ok(instances) => instances,
+        Err(e) => {
+            // The write itself will fail the same way; let it report the error
+            println!("[Stud Attributes] Could not check write: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut violations = Vec::new();
+    for op in &body.operations {
+        let Some(instance) = instances.iter().find(|instance| instance.path == op.path) else {
+            continue;
+        };
+        let actual = value_type(op);
+        for schema in schemas.iter().filter(|schema| schema.applies_to(instance)) {
+            violations.extend(check_attribute(
+                schema,
+                &op.path,
+                &op.name,
+                actual.as_deref(),
+            ));
+        }
+    }
+    violations
+}
+
+/// Audit every instance under `root` (default `game`) against the attribute schemas
+#[tauri::command]
+pub async fn validate_attributes(
+    root: Option<String>,
+    session: Option<String>,
+) -> Result<Vec<AttributeViolation>, String> {
+    let schemas = config::current().attributes.schemas;
+    if schemas.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (class_names, tags) = schema_lookups(&schemas);
+    let result = bridge::studio_request_background(
+        session.as_deref(),
+        "/attributes/scan",
+        serde_json::json!({
+            "root": root.unwrap_or_else(|| "game".to_string()),
+            "classNames": class_names,
+            "tags": tags,
+        }),
+    )
+    .await?;
+
+    Ok(parse_instances(result)?
+        .iter()
+        .flat_map(|instance| audit_instance(&schemas, instance))
+        .collect())
+}
@@ -0,0 +1,262 @@
+//! Change Audit Log
+//!
+//! A short, human-readable line for every change the bridge made in Studio
+//! ("renamed game.Workspace.Part to Door"), kept in memory per session. Long
+//! conversations lose track of what they already changed once older turns fall
+//! out of the model's context, so `get_change_context` turns the most recent
+//! entries into a summary that fits a token budget, for the frontend to attach
+//! to each new turn.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+use crate::bridge::{chrono_lite_timestamp, StudioRequest};
+use crate::profiles;
+
+/// Entries kept across all sessions; the oldest are dropped past this
+const MAX_ENTRIES: usize = 2000;
+const DEFAULT_CONTEXT_ENTRIES: usize = 30;
+const DEFAULT_CONTEXT_TOKENS: usize = 600;
+/// Rough size of a token in English text and instance paths
+const CHARS_PER_TOKEN: usize = 4;
+/// Bullet, newline and repeat count on each summary line
+const LINE_OVERHEAD_TOKENS: usize = 3;
+/// Names listed for a bulk change before the rest are counted
+const MAX_LISTED: usize = 5;
+
+lazy_static::lazy_static! {
+    static ref LOG: Mutex<VecDeque<AuditEntry>> = Mutex::new(VecDeque::new());
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    /// Unix ms
+    pub at: u64,
+    pub session: Option<String>,
+    pub chat_id: Option<String>,
+    /// Bridge route that made the change
+    pub route: String,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeContext {
+    /// Ready to add to the prompt; empty when nothing has changed yet
+    pub text: String,
+    /// Entries summarized, out of the `total` that matched
+    pub included: usize,
+    pub total: usize,
+    pub estimated_tokens: usize,
+}
+
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(CHARS_PER_TOKEN)
+}
+
+fn str_field<'a>(value: &'a serde_json::Value, key: &str) -> &'a str {
+    value.get(key).and_then(|v| v.as_str()).unwrap_or("?")
+}
+
+/// "A, B, C and 4 more"
+fn list(items: &[String]) -> String {
+    let mut listed = items
+        .iter()
+        .take(MAX_LISTED)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if items.len() > MAX_LISTED {
+        listed.push_str(&format!(" and {} more", items.len() - MAX_LISTED));
+    }
+    listed
+}
+
+fn each<'a>(body: &'a serde_json::Value, key: &str) -> &'a [serde_json::Value] {
+    body.get(key)
+        .and_then(|items| items.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn describe_set(item: &serde_json::Value) -> String {
+    let path = str_field(item, "path");
+    match str_field(item, "property") {
+        "Name" => format!("renamed {} to {}", path, str_field(item, "value")),
+        property => format!("set {} on {}", property, path),
+    }
+}
+
+fn summarize(route: &str, body: &serde_json::Value) -> String {
+    let created = |item: &serde_json::Value| {
+        format!(
+            "{} {}.{}",
+            str_field(item, "className"),
+            str_field(item, "parent"),
+            str_field(item, "name")
+        )
+    };
+    match route {
+        "/instance/create" => format!("created {}", created(body)),
+        "/instance/bulk-create" => {
+            let items: Vec<String> = each(body, "instances").iter().map(created).collect();
+            format!("created {} instance(s): {}", items.len(), list(&items))
+        }
+        "/instance/set" => describe_set(body),
+        "/instance/bulk-set" => {
+            let items: Vec<String> = each(body, "operations").iter().map(describe_set).collect();
+            format!("made {} property change(s): {}", items.len(), list(&items))
+        }
+        "/instance/delete" => format!("deleted {}", str_field(body, "path")),
+        "/instance/bulk-delete" => {
+            let items: Vec<String> = each(body, "paths")
+                .iter()
+                .filter_map(|path| path.as_str().map(str::to_string))
+                .collect();
+            format!("deleted {} instance(s): {}", items.len(), list(&items))
+        }
+        "/instance/clone" => format!(
+            "cloned {} into {}",
+            str_field(body, "path"),
+            str_field(body, "parent")
+        ),
+        "/instance/move" => format!(
+            "moved {} to {}",
+            str_field(body, "path"),
+            str_field(body, "newParent")
+        ),
+        "/script/set" | "/script/edit" => format!("edited script {}", str_field(body, "path")),
+        "/scripts/batch-set" => {
+            let items: Vec<String> = each(body, "edits")
+                .iter()
+                .map(|edit| str_field(edit, "path").to_string())
+                .collect();
+            format!("edited {} script(s): {}", items.len(), list(&items))
+        }
+        "/asset/insert" => format!(
+            "inserted asset {} into {}",
+            body.get("assetId")
+                .map_or("?".to_string(), |id| id.to_string()),
+            str_field(body, "parent")
+        ),
+        "/template/insert" => format!("inserted a template into {}", str_field(body, "parent")),
+        "/code/run" => "ran Luau code in Studio".to_string(),
+        _ => format!("made a change with {}", route),
+    }
+}
+
+/// The audit entry for a request, if it changes the place. Made before the
+/// request is sent and recorded once it succeeds.
+pub(crate) fn describe(request: &StudioRequest) -> Option<AuditEntry> {
+    if !profiles::is_modifying(&request.path) {
+        return None;
+    }
+    let body: serde_json::Value = request
+        .body
+        .as_deref()
+        .and_then(|body| serde_json::from_str(body).ok())
+        .unwrap_or_default();
+    Some(AuditEntry {
+        at: 0,
+        session: request.target_session.clone(),
+        chat_id: request.chat_id.clone(),
+        summary: summarize(&request.path, &body),
+        route: request.path.clone(),
+    })
+}
+
+/// Add a change that went through to the log
+pub(crate) fn record(mut entry: AuditEntry) {
+    entry.at = chrono_lite_timestamp();
+    let mut log = LOG.lock();
+    if log.len() >= MAX_ENTRIES {
+        log.pop_front();
+    }
+    log.push_back(entry);
+}
+
+fn matching(session: Option<&str>, chat_id: Option<&str>) -> Vec<AuditEntry> {
+    LOG.lock()
+        .iter()
+        .filter(|entry| session.is_none() || entry.session.as_deref() == session)
+        .filter(|entry| chat_id.is_none() || entry.chat_id.as_deref() == chat_id)
+        .cloned()
+        .collect()
+}
+
+/// Recent changes, newest last, optionally for one session or chat
+#[tauri::command]
+pub fn list_audit_log(
+    session: Option<String>,
+    chat_id: Option<String>,
+    limit: Option<usize>,
+) -> Vec<AuditEntry> {
+    let mut entries = matching(session.as_deref(), chat_id.as_deref());
+    let start = entries.len().saturating_sub(limit.unwrap_or(MAX_ENTRIES));
+    entries.drain(..start);
+    entries
+}
+
+/// Summarize the last `limit` changes (default 30) for the model, newest
+/// first until `max_tokens` (default 600) is spent, listed oldest first
+#[tauri::command]
+pub fn get_change_context(
+    session: Option<String>,
+    chat_id: Option<String>,
+    limit: Option<usize>,
+    max_tokens: Option<usize>,
+) -> ChangeContext {
+    let entries = matching(session.as_deref(), chat_id.as_deref());
+    let total = entries.len();
+    let limit = limit.unwrap_or(DEFAULT_CONTEXT_ENTRIES);
+    let budget = max_tokens.unwrap_or(DEFAULT_CONTEXT_TOKENS);
+    let header = "Changes already made in Studio during this session (oldest first):";
+
+    let mut used = estimate_tokens(header);
+    // Newest first; repeats of the same change are counted instead of listed again
+    let mut groups: Vec<(&str, usize)> = Vec::new();
+    let mut included = 0;
+    for entry in entries.iter().rev().take(limit) {
+        if let Some((summary, count)) = groups.last_mut() {
+            if *summary == entry.summary {
+                *count += 1;
+                included += 1;
+                continue;
+            }
+        }
+        let cost = estimate_tokens(&entry.summary) + LINE_OVERHEAD_TOKENS;
+        if used + cost > budget {
+            break;
+        }
+        used += cost;
+        included += 1;
+        groups.push((&entry.summary, 1));
+    }
+    if groups.is_empty() {
+        return ChangeContext {
+            text: String::new(),
+            included: 0,
+            total,
+            estimated_tokens: 0,
+        };
+    }
+
+    let mut text = header.to_string();
+    let omitted = total.saturating_sub(included);
+    if omitted > 0 {
+        text.push_str(&format!("\n- ({} earlier change(s) not shown)", omitted));
+    }
+    for (summary, count) in groups.iter().rev() {
+        text.push_str("\n- ");
+        text.push_str(summary);
+        if *count > 1 {
+            text.push_str(&format!(" (x{})", count));
+        }
+    }
+    ChangeContext {
+        estimated_tokens: estimate_tokens(&text),
+        text,
+        included,
+        total,
+    }
+}
@@ -0,0 +1,587 @@
+//! OAuth Callback Service
+//!
+//! Receives the browser redirect at the end of an OAuth sign-in to any of the
+//! providers in `oauth_providers`, exchanges the code for tokens (see `tokens`)
+//! and tells the frontend how it went with an
+//! `oauth-complete` event. Outcomes are kept per flow, keyed by the OAuth
+//! `state` parameter, so two sign-ins running at once (e.g. two accounts) can't
+//! overwrite each other's results, and are dropped `CALLBACK_EXPIRY_SECS` after
+//! they arrive. `/auth/poll` still serves them for older frontends but is
+//! deprecated.
+//!
+//! Flows are started here too, so the webview never has to do the crypto or
+//! know a provider's endpoints: `start_auth` returns the URL to open, and each
+//! flow's PKCE verifier is kept in memory until its code is exchanged.
+//!
+//! The server only holds its port while a sign-in is waiting on it: starting a
+//! flow binds it, and it's released once no flow is left (or after
+//! `FLOW_EXPIRY_SECS` at the latest). When the port can't be bound, ChatGPT
+//! flows redirect to the `stud://auth/callback` deep link instead, which ends
+//! up in the same exchange.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, Json};
+use axum::routing::{get, post};
+use axum::Router;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+use crate::bridge::{self, chrono_lite_timestamp};
+use crate::oauth_providers::OAuthProvider;
+use crate::{config, tokens};
+
+/// Sign-ins not finished within this long are abandoned
+const FLOW_EXPIRY_SECS: u64 = 10 * 60;
+/// Finished flows' outcomes are dropped after this long
+const CALLBACK_EXPIRY_SECS: u64 = 60;
+/// Redirect for flows that can't use the localhost callback server
+const DEEP_LINK_REDIRECT: &str = "stud://auth/callback";
+/// Random bytes in a PKCE verifier; 32 encode to the 43 characters RFC 7636 asks for at least
+const VERIFIER_BYTES: usize = 32;
+const STATE_BYTES: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthCallbackData {
+    pub provider: OAuthProvider,
+    pub state: String,
+    /// Set when the provider redirected back with an error, or the code couldn't be exchanged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The account signed in to, on success
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<String>,
+    pub timestamp: u64,
+}
+
+struct CompletedFlow {
+    data: OAuthCallbackData,
+    received: Instant,
+}
+
+/// A sign-in for the frontend to open in the browser. The PKCE verifier stays
+/// in the backend.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthRequest {
+    pub provider: OAuthProvider,
+    pub url: String,
+    /// The OAuth `state` naming the flow, as `oauth-complete` reports it
+    pub state: String,
+    /// Where the provider will send the browser back to
+    pub redirect_uri: String,
+}
+
+struct PendingVerifier {
+    provider: OAuthProvider,
+    verifier: String,
+    /// The exchange has to name the same redirect the authorization did
+    redirect_uri: String,
+    created: Instant,
+}
+
+/// The callback server, while a sign-in needs it
+struct CallbackServer {
+    port: u16,
+    stop: oneshot::Sender<()>,
+}
+
+/// Shared between the callback server and Tauri commands
+#[derive(Default)]
+pub struct AuthService {
+    flows: Mutex<HashMap<String, CompletedFlow>>,
+    verifiers: Mutex<HashMap<String, PendingVerifier>>,
+    server: Mutex<Option<CallbackServer>>,
+}
+
+#[derive(Deserialize)]
+struct FlowQuery {
+    state: Option<String>,
+}
+
+impl AuthService {
+    /// Keep a flow's outcome for `CALLBACK_EXPIRY_SECS`
+    fn record(self: &Arc<Self>, data: OAuthCallbackData) {
+        let state = data.state.clone();
+        let received = Instant::now();
+        self.flows
+            .lock()
+            .insert(state.clone(), CompletedFlow { data, received });
+
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(CALLBACK_EXPIRY_SECS)).await;
+            let mut flows = service.flows.lock();
+            // Unless a newer callback for the same state replaced it
+            if flows.get(&state).is_some_and(|flow| flow.received == received) {
+                flows.remove(&state);
+            }
+        });
+    }
+
+    /// The callback for a flow, if it has arrived and not yet expired
+    pub fn peek(&self, state: &str) -> Option<OAuthCallbackData> {
+        let expiry = Duration::from_secs(CALLBACK_EXPIRY_SECS);
+        self.flows
+            .lock()
+            .get(state)
+            .filter(|flow| flow.received.elapsed() < expiry)
+            .map(|flow| flow.data.clone())
+    }
+
+    /// Forget a flow once the frontend has handled it (or given up)
+    pub fn clear(&self, state: &str) -> bool {
+        self.flows.lock().remove(state).is_some()
+    }
+
+    /// Drop every finished flow's outcome for a provider, e.g. on sign-out
+    pub fn forget_results(&self, provider: OAuthProvider) {
+        self.flows
+            .lock()
+            .retain(|_, flow| flow.data.provider != provider);
+    }
+
+    /// Drop everything kept for an abandoned flow, its verifier included
+    pub fn cancel(&self, state: &str) -> bool {
+        let verifier = self.verifiers.lock().remove(state).is_some();
+        let cancelled = self.clear(state) || verifier;
+        self.stop_if_idle();
+        cancelled
+    }
+
+    /// Release the callback server's port
+    pub fn stop_server(&self) {
+        if let Some(server) = self.server.lock().take() {
+            let _ = server.stop.send(());
+        }
+    }
+
+    /// Stop the callback server once no sign-in is waiting on it
+    fn stop_if_idle(&self) {
+        let expiry = Duration::from_secs(FLOW_EXPIRY_SECS);
+        let waiting = self
+            .verifiers
+            .lock()
+            .values()
+            .any(|pending| pending.created.elapsed() < expiry);
+        if !waiting {
+            self.stop_server();
+        }
+    }
+
+    /// Start a flow: a fresh PKCE pair whose verifier is kept under a new
+    /// state, and the provider's authorize URL for it
+    pub fn new_flow(
+        &self,
+        provider: OAuthProvider,
+        redirect_uri: String,
+    ) -> Result<AuthRequest, String> {
+        let verifier = random_token(VERIFIER_BYTES)?;
+        let state = random_token(STATE_BYTES)?;
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        let url = provider.authorize_url(&challenge, &state, &redirect_uri)?;
+
+        let mut verifiers = self.verifiers.lock();
+        let expiry = Duration::from_secs(FLOW_EXPIRY_SECS);
+        verifiers.retain(|_, pending| pending.created.elapsed() < expiry);
+        verifiers.insert(
+            state.clone(),
+            PendingVerifier {
+                provider,
+                verifier,
+                redirect_uri: redirect_uri.clone(),
+                created: Instant::now(),
+            },
+        );
+        Ok(AuthRequest {
+            provider,
+            url,
+            state,
+            redirect_uri,
+        })
+    }
+
+    /// The provider of the sign-in `state` names, if this app started it and
+    /// it hasn't finished
+    fn expects(&self, state: &str) -> Option<OAuthProvider> {
+        self.verifiers
+            .lock()
+            .get(state)
+            .filter(|pending| pending.created.elapsed() < Duration::from_secs(FLOW_EXPIRY_SECS))
+            .map(|pending| pending.provider)
+    }
+
+    /// The verifier for a flow, removed so it can only be used for one exchange
+    fn take_verifier(&self, state: &str) -> Option<PendingVerifier> {
+        self.verifiers
+            .lock()
+            .remove(state)
+            .filter(|pending| pending.created.elapsed() < Duration::from_secs(FLOW_EXPIRY_SECS))
+    }
+}
+
+/// URL-safe random string from the OS CSPRNG
+fn random_token(bytes: usize) -> Result<String, String> {
+    let mut buf = vec![0u8; bytes];
+    getrandom::fill(&mut buf).map_err(|e| format!("Failed to generate random bytes: {}", e))?;
+    Ok(URL_SAFE_NO_PAD.encode(buf))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn error_page(message: &str) -> String {
+    format!(r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Authentication Failed</title>
+    <style>
+        body {{ font-family: system-ui, sans-serif; display: flex; justify-content: center; align-items: center; height: 100vh; margin: 0; background: #fafafa; }}
+        .card {{ background: white; padding: 2rem; border-radius: 1rem; box-shadow: 0 4px 20px rgba(0,0,0,0.1); text-align: center; max-width: 400px; }}
+        h1 {{ color: #ef4444; margin-bottom: 0.5rem; }}
+        p {{ color: #666; }}
+    </style>
+</head>
+<body>
+    <div class="card">
+        <h1>Authentication Failed</h1>
+        <p>{}</p>
+        <p>You can close this window and try again.</p>
+    </div>
+</body>
+</html>"#, escape_html(message))
+}
+
+fn poll_reply(service: &AuthService, query: FlowQuery) -> Json<serde_json::Value> {
+    let Some(state) = query.state else {
+        return Json(serde_json::json!({
+            "pending": false,
+            "error": "Missing state parameter",
+        }));
+    };
+    match service.peek(&state) {
+        Some(data) => Json(serde_json::json!({
+            "pending": true,
+            "state": data.state,
+            "error": data.error,
+            "account_id": data.account_id,
+        })),
+        None => Json(serde_json::json!({ "pending": false })),
+    }
+}
+
+/// Exchange a flow's code, returning the account signed in to
+async fn exchange(service: &AuthService, code: &str, state: &str) -> Result<Option<String>, String> {
+    if code.is_empty() {
+        return Err("The sign-in page returned no code".to_string());
+    }
+    let pending = service
+        .take_verifier(state)
+        .ok_or_else(|| "This sign-in was already completed".to_string())?;
+    tokens::exchange_code(
+        pending.provider,
+        code,
+        &pending.verifier,
+        &pending.redirect_uri,
+        state,
+    )
+    .await
+        .map(|status| status.account_id)
+}
+
+/// Finish the flow a redirect names, whether it came over HTTP or the deep
+/// link: exchange its code, store the outcome for the flow named by `state`
+/// and tell the frontend with an `oauth-complete` event. Err if the redirect
+/// doesn't belong to a sign-in this app started.
+async fn finish_flow(
+    service: &Arc<AuthService>,
+    params: &HashMap<String, String>,
+) -> Result<OAuthCallbackData, String> {
+    let code = params.get("code").cloned().unwrap_or_default();
+    let state = params.get("state").cloned().unwrap_or_default();
+
+    // Only a state this app issued names a sign-in; anything else could be a
+    // code another site is trying to slip into the app, so it's dropped unseen
+    let Some(provider) = service.expects(&state) else {
+        println!("[Stud OAuth] Rejected a callback with an unknown or expired state");
+        return Err("This sign-in doesn't match one started from Stud, so it was ignored.".to_string());
+    };
+
+    let (error, account_id) = match params.get("error") {
+        Some(error) => {
+            // The flow is over either way; its verifier mustn't be reused
+            service.take_verifier(&state);
+            (Some(error.clone()), None)
+        }
+        None => match exchange(service, &code, &state).await {
+            Ok(account_id) => (None, account_id),
+            Err(e) => {
+                println!("[Stud OAuth] Sign-in failed: {}", e);
+                (Some(e), None)
+            }
+        },
+    };
+
+    let data = OAuthCallbackData {
+        provider,
+        state,
+        error,
+        account_id,
+        timestamp: chrono_lite_timestamp(),
+    };
+    service.record(data.clone());
+    bridge::emit_event("oauth-complete", data.clone());
+    // Graceful shutdown still lets the callback's response go out
+    service.stop_if_idle();
+    Ok(data)
+}
+
+/// OAuth callback endpoint - finishes the flow and shows how it went
+async fn callback(
+    State(service): State<Arc<AuthService>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> (StatusCode, Html<String>) {
+    let data = match finish_flow(&service, &params).await {
+        Ok(data) => data,
+        Err(e) => return (StatusCode::BAD_REQUEST, Html(error_page(&e))),
+    };
+
+    if let Some(err) = data.error {
+        // OAuth error - show error page
+        (StatusCode::OK, Html(error_page(&err)))
+    } else {
+        // Success - show checkmark and success message
+        let html = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Authentication Successful</title>
+    <style>
+        body { font-family: system-ui, sans-serif; display: flex; justify-content: center; align-items: center; height: 100vh; margin: 0; background: #fafafa; }
+        .card { background: white; padding: 2rem; border-radius: 1rem; box-shadow: 0 4px 20px rgba(0,0,0,0.1); text-align: center; max-width: 400px; }
+        h1 { color: #22c55e; margin-bottom: 0.5rem; }
+        p { color: #666; }
+        .checkmark { width: 56px; height: 56px; margin: 1rem auto; }
+        .checkmark circle { fill: #22c55e; }
+        .checkmark path { stroke: white; stroke-width: 3; fill: none; stroke-linecap: round; stroke-linejoin: round; }
+    </style>
+</head>
+<body>
+    <div class="card">
+        <h1>Authentication Successful!</h1>
+        <svg class="checkmark" viewBox="0 0 56 56">
+            <circle cx="28" cy="28" r="28"/>
+            <path d="M16 28 L24 36 L40 20"/>
+        </svg>
+        <p>Sign-in complete.</p>
+        <p>You can close this window now.</p>
+    </div>
+</body>
+</html>"#;
+        (StatusCode::OK, Html(html.to_string()))
+    }
+}
+
+/// Marks a reply from an endpoint the `oauth-complete` event replaced
+const DEPRECATED: [(&str, &str); 1] = [("Deprecation", "true")];
+
+/// Poll endpoint - returns a flow's outcome by its state.
+/// Deprecated: listen for the `oauth-complete` event instead.
+async fn poll(
+    State(service): State<Arc<AuthService>>,
+    Query(query): Query<FlowQuery>,
+) -> ([(&'static str, &'static str); 1], Json<serde_json::Value>) {
+    (DEPRECATED, poll_reply(&service, query))
+}
+
+/// Clear endpoint - forgets a flow's outcome before it expires.
+/// Deprecated along with `/auth/poll`.
+async fn clear(
+    State(service): State<Arc<AuthService>>,
+    Query(query): Query<FlowQuery>,
+) -> ([(&'static str, &'static str); 1], Json<serde_json::Value>) {
+    let cleared = query
+        .state
+        .as_deref()
+        .is_some_and(|state| service.clear(state));
+    (DEPRECATED, Json(serde_json::json!({ "ok": true, "cleared": cleared })))
+}
+
+/// Bind the OAuth callback server for ChatGPT Plus/Pro sign-in, if it isn't
+/// already up, and return its port
+pub async fn start_oauth_server(service: Arc<AuthService>) -> Result<u16, String> {
+    if let Some(server) = service.server.lock().as_ref() {
+        return Ok(server.port);
+    }
+
+    // No fallback here: the redirect URI registered with the provider names this exact port
+    let port = config::current().bridge.resolved().oauth_port;
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) if is_stud_callback_server(port).await => {
+            return Err(format!(
+                "Another copy of Stud is handling sign-in callbacks on port {} ({})",
+                port, e
+            ));
+        }
+        Err(e) => {
+            return Err(format!(
+                "Port {} is held by another app ({}); sign-in won't work until it's freed",
+                port, e
+            ));
+        }
+    };
+
+    let (stop, stopped) = oneshot::channel();
+    {
+        let mut server = service.server.lock();
+        // Another flow bound it while this one was waiting; this listener just drops
+        if let Some(server) = server.as_ref() {
+            return Ok(server.port);
+        }
+        *server = Some(CallbackServer { port, stop });
+    }
+    bridge::set_oauth_endpoint(port);
+    println!("[Stud OAuth] Callback server on http://localhost:{}", port);
+
+    let oauth_routes = bridge::with_local_layers(
+        Router::new()
+            .route("/auth/callback", get(callback))
+            // Anthropic's registered redirect
+            .route("/callback", get(callback))
+            .route("/auth/poll", get(poll))
+            .route("/auth/clear", post(clear))
+            .with_state(service.clone()),
+    );
+    tokio::spawn(async move {
+        let shutdown = async {
+            tokio::select! {
+                _ = stopped => {}
+                _ = bridge::shutdown_signal() => {}
+                _ = tokio::time::sleep(Duration::from_secs(FLOW_EXPIRY_SECS)) => {}
+            }
+        };
+        if let Err(e) = axum::serve(listener, oauth_routes)
+            .with_graceful_shutdown(shutdown)
+            .await
+        {
+            println!("[Stud OAuth] Callback server error: {}", e);
+        }
+
+        // Stopped on its own (not through stop_server): forget it
+        let mut server = service.server.lock();
+        if server.as_ref().is_some_and(|server| server.stop.is_closed()) {
+            *server = None;
+        }
+        if server.is_none() {
+            bridge::clear_oauth_endpoint();
+        }
+        println!("[Stud OAuth] Callback server stopped");
+    });
+    Ok(port)
+}
+
+/// Whether the server on this port answers like Stud's callback server
+async fn is_stud_callback_server(port: u16) -> bool {
+    let Ok(client) = reqwest::Client::builder()
+        .timeout(Duration::from_secs(1))
+        .build()
+    else {
+        return false;
+    };
+    let response = client
+        .get(format!("http://127.0.0.1:{}/auth/poll?state=probe", port))
+        .send()
+        .await;
+    match response {
+        Ok(response) => response
+            .json::<serde_json::Value>()
+            .await
+            .is_ok_and(|body| body.get("pending").is_some()),
+        Err(_) => false,
+    }
+}
+
+/// A `stud://auth/...` link the OS handed to the app: finish the sign-in it
+/// carries, the same way the HTTP callback would
+async fn handle_deep_link(service: Arc<AuthService>, url: String) {
+    if !url.starts_with("stud://auth") {
+        return;
+    }
+    let params = axum::http::Uri::try_from(url.as_str())
+        .ok()
+        .and_then(|uri| Query::<HashMap<String, String>>::try_from_uri(&uri).ok())
+        .map(|Query(params)| params)
+        .unwrap_or_default();
+    match finish_flow(&service, &params).await {
+        Ok(data) if data.error.is_none() => println!("[Stud OAuth] Signed in through the deep link"),
+        Ok(_) => {}
+        Err(e) => println!("[Stud OAuth] Ignored deep link: {}", e),
+    }
+}
+
+/// Route `stud://` links the OS opens the app with into the sign-in flow
+pub fn listen_for_deep_links(app: &tauri::AppHandle) {
+    use tauri::Manager;
+    use tauri_plugin_deep_link::DeepLinkExt;
+
+    // macOS registers the scheme from the app bundle; Linux and Windows need it at runtime
+    #[cfg(any(windows, target_os = "linux"))]
+    if let Err(e) = app.deep_link().register_all() {
+        println!("[Stud OAuth] Failed to register the stud:// link: {}", e);
+    }
+
+    let service = app.state::<Arc<AuthService>>().inner().clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            tauri::async_runtime::spawn(handle_deep_link(
+                service.clone(),
+                url.as_str().to_string(),
+            ));
+        }
+    });
+}
+
+/// Start a sign-in to `provider`: bind the callback server and set up the
+/// flow, returning the URL to open. If the port can't be had, ChatGPT flows
+/// redirect to the deep link instead.
+#[tauri::command]
+pub async fn start_auth(
+    provider: OAuthProvider,
+    service: tauri::State<'_, Arc<AuthService>>,
+) -> Result<AuthRequest, String> {
+    let service = service.inner().clone();
+    let redirect_uri = match start_oauth_server(service.clone()).await {
+        Ok(port) => format!("http://localhost:{}{}", port, provider.callback_path()),
+        Err(e) if provider.allows_deep_link() => {
+            println!("[Stud OAuth] {}; redirecting to {} instead", e, DEEP_LINK_REDIRECT);
+            DEEP_LINK_REDIRECT.to_string()
+        }
+        Err(e) => return Err(e),
+    };
+    service.new_flow(provider, redirect_uri)
+}
+
+/// Release the callback server's port now; sign-ins still in progress can't
+/// finish until another flow is started
+#[tauri::command]
+pub fn stop_oauth_server(service: tauri::State<'_, Arc<AuthService>>) {
+    service.stop_server();
+}
+
+/// Drop a sign-in the user abandoned, along with any callback it received
+#[tauri::command]
+pub fn cancel_oauth_flow(state: String, service: tauri::State<'_, Arc<AuthService>>) -> bool {
+    service.cancel(&state)
+}
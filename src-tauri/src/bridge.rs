@@ -8,55 +8,650 @@
 //! 3. Studio plugin responds to /stud/respond with results
 //! 4. The original request resolves with the result
 
-use parking_lot::Mutex;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::net::{Ipv4Addr, SocketAddr};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::Ipv4Addr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::oneshot;
-use warp::Filter;
+use tokio::sync::{mpsc, oneshot, watch, Notify};
+use tracing::Instrument;
+use tauri::Emitter;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::rejection::BytesRejection;
+use axum::extract::{DefaultBodyLimit, FromRequest, Path, Query, Request, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::de::DeserializeOwned;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 use bytes::Bytes;
-use futures_util::StreamExt;
+use futures_util::future::{BoxFuture, Shared};
+use futures_util::{FutureExt, SinkExt, StreamExt};
+use indexmap::IndexMap;
 
-const BRIDGE_PORT: u16 = 3001;
-const OAUTH_PORT: u16 = 1455;
+use crate::config;
+use crate::metrics::{BridgeMetrics, Gauges, Outcome};
+use crate::paths;
+use crate::profiles::{self, PlaceProfile};
+use crate::protocol::{WireRequest, WireResponse, CURRENT_PROTOCOL, PROTOCOL_VERSIONS};
+use crate::traces::{self, Stage};
+
+// How many ports after the preferred one to try when it's taken
+const PORT_FALLBACK_ATTEMPTS: u16 = 10;
+// How long to wait for whatever holds the bridge port to answer a health probe
+const HEALTH_PROBE_TIMEOUT_MS: u64 = 1000;
+/// Reported by /stud/health so a probe can tell a Stud bridge from another app
+const HEALTH_SERVICE: &str = "stud-bridge";
+/// Ports of the running servers, for local tools that need to find the bridge
+const DISCOVERY_FILENAME: &str = "bridge.json";
 const REQUEST_TIMEOUT_SECS: u64 = 15;
+// How long an old listener keeps serving after the bridge moves ports
+const STANDBY_GRACE_SECS: u64 = 30;
+const WS_HEARTBEAT_MS: u64 = 500;
+const MAX_LONG_POLL_SECS: u64 = 25;
+/// Requests queued within this long mean a conversation is active
+const ACTIVE_CONVERSATION_SECS: u64 = 60;
+/// After this long without requests the plugin can fall back to a slow heartbeat
+const DORMANT_AFTER_SECS: u64 = 10 * 60;
+/// Suggested pauses between polls while a conversation is active, idle, and dormant
+const ACTIVE_POLL_DELAY_MS: u64 = 100;
+const IDLE_POLL_DELAY_MS: u64 = 1000;
+const DORMANT_POLL_DELAY_MS: u64 = 3000;
+const MAX_POLL_BATCH: usize = 20;
+// Upper bound on parts in one chunked response, to cap memory per request
+const MAX_RESPONSE_CHUNKS: u32 = 1024;
+// Replies smaller than this aren't worth compressing
+const COMPRESSION_THRESHOLD: usize = 1024;
+// Guards against decompression bombs in compressed request bodies
+const MAX_DECOMPRESSED_BYTES: u64 = 64 * 1024 * 1024;
+// Sessions that haven't polled for this long are forgotten
+const SESSION_EXPIRY_SECS: u64 = 300;
+// How long a finished request's response is replayed for retries with the same Idempotency-Key
+const IDEMPOTENCY_WINDOW_SECS: u64 = 60;
+// Every /stud/* request must carry the per-install secret in this header
+const SECRET_HEADER: &str = "x-stud-secret";
+const SECRET_FILE: &str = "bridge-secret";
+/// Sessions with this prefix are in-app self-test probes playing the plugin.
+/// They only take requests addressed to them and never count as Studio.
+const PROBE_SESSION_PREFIX: &str = "stud-selftest-";
+/// Carries a request's trace id in and out of /stud/request
+const TRACE_HEADER: &str = "x-stud-trace-id";
+// How often the connection watcher checks for the plugin going away
+const CONNECTION_CHECK_MS: u64 = 500;
+// Pause between stopping and rebinding the listener on restart
+const RESTART_SETTLE_MS: u64 = 200;
+// Pause before a request the plugin nacked as retryable is handed out again, times the retry number
+const NACK_RETRY_DELAY_MS: u64 = 1000;
+// Suggested wait before retrying when the request queue is full
+const QUEUE_FULL_RETRY_SECS: u64 = 2;
+// Time for failed requests to reach their callers before the runtime is dropped on exit
+const SHUTDOWN_FLUSH_MS: u64 = 250;
+const CONNECTED_EVENT: &str = "studio-connected";
+const DISCONNECTED_EVENT: &str = "studio-disconnected";
+const TOOL_PARTIAL_RESULT_EVENT: &str = "tool-partial-result";
+const STUDIO_EVENT: &str = "studio-event";
+const PLUGIN_VERSION_WARNING_EVENT: &str = "plugin-version-warning";
+const PLACE_PROFILE_EVENT: &str = "place-profile";
+/// Sent with the new endpoints whenever a local server binds or moves
+const ENDPOINTS_EVENT: &str = "bridge-endpoints";
+/// The plugin is installed from this build, so it should report the same version
+const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+// Studio events kept for a frontend that starts listening late
+const MAX_BUFFERED_EVENTS: usize = 200;
+const MAX_EVENT_TYPE_LEN: usize = 64;
 
-// Global storage for OAuth callback data
 lazy_static::lazy_static! {
-    static ref OAUTH_CALLBACK_DATA: Arc<Mutex<Option<OAuthCallbackData>>> = Arc::new(Mutex::new(None));
+    static ref BRIDGE_ENDPOINTS: RwLock<BridgeEndpoints> = RwLock::new(BridgeEndpoints::default());
+    // The app's bridge, also managed by Tauri; backend features reach Studio through it
+    static ref BRIDGE: BridgeHandle = BridgeHandle::new();
+    static ref BRIDGE_SECRET: String = load_or_create_secret();
+    // Set once the Tauri app is up, for emitting events to the frontend
+    static ref APP_HANDLE: RwLock<Option<tauri::AppHandle>> = RwLock::new(None);
+    // Flipped to true when the app exits; every local server watches it
+    static ref SHUTDOWN: watch::Sender<bool> = watch::channel(false).0;
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OAuthCallbackData {
-    pub code: String,
-    pub state: String,
-    pub timestamp: u64,
+/// Read the per-install bridge secret, generating and saving it on first run
+fn load_or_create_secret() -> String {
+    let path = paths::app_data_dir().map(|dir| dir.join(SECRET_FILE));
+    if let Ok(Ok(existing)) = path.as_ref().map(std::fs::read_to_string) {
+        let secret = existing.trim();
+        if !secret.is_empty() {
+            return secret.to_string();
+        }
+    }
+
+    let secret = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let saved = path.and_then(|path| {
+        std::fs::write(&path, &secret).map_err(|e| format!("Failed to save bridge secret: {}", e))
+    });
+    if let Err(e) = saved {
+        // The plugin is installed with whatever secret this process uses, so
+        // this only costs a reinstall after the next restart
+        println!("[Stud Bridge] {}", e);
+    }
+    secret
+}
+
+/// Secret the plugin and app must send to use the bridge
+pub fn bridge_secret() -> &'static str {
+    &BRIDGE_SECRET
+}
+
+/// Compare without exiting early, so timing doesn't leak how much of a guess matched
+fn secrets_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Control messages for the running bridge
+enum BridgeCommand {
+    /// Bring up a listener on a new port, keeping the old one as a warm standby
+    Rebind {
+        port: u16,
+        reply: oneshot::Sender<Result<u16, String>>,
+    },
+    /// Stop the listener, drop all queued work and sessions, and bind again
+    Restart {
+        reply: oneshot::Sender<Result<BridgeRestart, String>>,
+    },
+}
+
+/// Outcome of `restart_bridge`
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeRestart {
+    pub port: u16,
+    /// Requests that were still queued and got cancelled
+    pub cancelled: usize,
+}
+
+/// Ports the local servers actually bound (None if a server isn't listening)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BridgeEndpoints {
+    pub bridge_port: Option<u16>,
+    pub oauth_port: Option<u16>,
+    pub codex_proxy_port: Option<u16>,
+    pub bridge_url: Option<String>,
+    pub oauth_callback_url: Option<String>,
+    pub codex_proxy_url: Option<String>,
+    /// Unix socket path or Windows pipe name serving the bridge API, when enabled
+    pub socket_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub service: String,
+    pub version: String,
+}
+
+/// Contents of the discovery file
+#[derive(Serialize)]
+struct Discovery<'a> {
+    pid: u32,
+    version: &'static str,
+    #[serde(flatten)]
+    endpoints: &'a BridgeEndpoints,
+}
+
+fn discovery_path() -> Result<std::path::PathBuf, String> {
+    Ok(paths::app_data_dir()?.join(DISCOVERY_FILENAME))
+}
+
+/// Change the advertised endpoints, tell the frontend (which caches them) and
+/// rewrite the discovery file. The file is left alone while this process
+/// doesn't own the bridge, since another copy of Stud may.
+fn update_endpoints(change: impl FnOnce(&mut BridgeEndpoints)) {
+    let mut endpoints = BRIDGE_ENDPOINTS.write();
+    change(&mut endpoints);
+    emit_event(ENDPOINTS_EVENT, endpoints.clone());
+    if endpoints.bridge_port.is_none() {
+        return;
+    }
+    let discovery = Discovery {
+        pid: std::process::id(),
+        version: APP_VERSION,
+        endpoints: &endpoints,
+    };
+    let written = discovery_path().and_then(|path| {
+        let json = serde_json::to_string_pretty(&discovery).map_err(|e| e.to_string())?;
+        std::fs::write(&path, json).map_err(|e| e.to_string())
+    });
+    if let Err(e) = written {
+        println!("[Stud Bridge] Failed to write discovery file: {}", e);
+    }
+}
+
+fn remove_discovery_file() {
+    if let Ok(path) = discovery_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Whether the server on this port is a Stud bridge (another copy of the app)
+/// rather than some other program
+pub(crate) async fn is_stud_bridge(port: u16) -> bool {
+    let Ok(client) = reqwest::Client::builder()
+        .timeout(Duration::from_millis(HEALTH_PROBE_TIMEOUT_MS))
+        .build()
+    else {
+        return false;
+    };
+    let Ok(response) = client
+        .get(format!("http://127.0.0.1:{}/stud/health", port))
+        .send()
+        .await
+    else {
+        return false;
+    };
+    response
+        .json::<HealthResponse>()
+        .await
+        .is_ok_and(|health| health.service == HEALTH_SERVICE)
+}
+
+/// Get the ports the bridge, OAuth, and Codex proxy servers are listening on
+#[tauri::command]
+pub fn get_bridge_endpoints() -> BridgeEndpoints {
+    BRIDGE_ENDPOINTS.read().clone()
+}
+
+/// Port the bridge is listening on, falling back to the configured one before it binds
+pub fn bridge_port() -> u16 {
+    BRIDGE_ENDPOINTS
+        .read()
+        .bridge_port
+        .unwrap_or_else(|| config::current().bridge.resolved().port)
+}
+
+/// Bind the preferred port, or the next free one after it. Ports the app's
+/// other servers are configured for are skipped, so a bridge pushed off 3001
+/// doesn't take the proxy's 3002 and push it along in turn.
+async fn bind_with_fallback(preferred: u16) -> std::io::Result<tokio::net::TcpListener> {
+    let ports = config::current().bridge.resolved();
+    let reserved = [ports.port, ports.oauth_port, ports.codex_proxy_port];
+    let last = preferred.saturating_add(PORT_FALLBACK_ATTEMPTS);
+    let fallbacks = (preferred.saturating_add(1)..=last).filter(|port| !reserved.contains(port));
+    let mut failure = None;
+    for port in std::iter::once(preferred).chain(fallbacks) {
+        match tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port)).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) => failure = Some(e),
+        }
+    }
+    Err(failure.unwrap_or_else(|| std::io::Error::from(std::io::ErrorKind::AddrInUse)))
+}
+
+fn local_port(listener: &tokio::net::TcpListener) -> Option<u16> {
+    listener.local_addr().ok().map(|addr| addr.port())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StudioRequest {
+    /// Caller-chosen id so the request can be cancelled with /stud/cancel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub path: String,
     pub body: Option<String>,
+    /// Studio session that should handle this request (any session if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_session: Option<String>,
+    /// Binary data sent alongside the JSON body (images, rbxm, audio)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<Attachment>,
+    /// Higher priority requests are handed to the plugin first
+    #[serde(default)]
+    pub priority: Priority,
+    /// Frontend tool call waiting on this request; parts of a chunked
+    /// response are streamed to it as they arrive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Conversation the request was made for, so it can be listed and cancelled with it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<String>,
+    /// AI turn within the chat that made the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_id: Option<String>,
+    /// Correlates the request's steps across the bridge and the plugin; set
+    /// when the request is received if the caller didn't pick one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    /// Folders the tool's sandbox profile confines it to, for the plugin to
+    /// enforce where the bridge can't (running code). Set by the bridge.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_paths: Option<Vec<String>>,
+}
+
+/// Interactive requests (selection, single scripts) should be `high`; heavy
+/// background work like full-tree dumps should be `low`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub content_type: String,
+    /// Base64-encoded bytes
+    pub data: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StudioResponse {
     pub status: u16,
     pub body: String,
+    /// When set, `body` is base64-encoded binary data of this type instead of JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+/// Result of a backend request to Studio
+pub enum StudioPayload {
+    Json(serde_json::Value),
+    Binary { content_type: String, data: Vec<u8> },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PollResponse {
     pub id: Option<String>,
-    pub request: Option<StudioRequest>,
+    /// In the session's protocol version
+    pub request: Option<WireRequest>,
+    /// Every request picked up by this poll, oldest first, when the plugin asked
+    /// for a batch with `max`. `id`/`request` still carry the first one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requests: Vec<PolledRequest>,
+    /// Requests cancelled since this session last polled; abort them if still running
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cancelled: Vec<String>,
+    /// Set after the bridge moves ports; the plugin should switch to this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port_hint: Option<u16>,
+    /// Requests for this session still queued after this poll
+    #[serde(default)]
+    pub queue_depth: usize,
+    /// How long the plugin should wait before polling again; 0 means right away
+    #[serde(default)]
+    pub next_poll_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolledRequest {
+    pub id: String,
+    pub request: WireRequest,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UploadQuery {
+    /// Where this part starts in the upload
+    #[serde(default)]
+    pub offset: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollQuery {
+    /// Seconds to hold the connection open waiting for a request (long polling)
+    pub wait: Option<u64>,
+    /// Return up to this many pending requests at once in `requests`
+    pub max: Option<usize>,
+    /// Identifies the Studio instance polling, so several can be connected at once
+    pub session: Option<String>,
+    pub place_id: Option<u64>,
+    pub place_name: Option<String>,
+    /// Handshake: the version of Stud that installed the plugin
+    pub plugin_version: Option<String>,
+    /// Roblox account signed in to Studio
+    pub user_id: Option<u64>,
+    pub user_name: Option<String>,
+    pub studio_version: Option<String>,
+}
+
+/// A Studio instance that has registered with the bridge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudioSession {
+    pub id: String,
+    pub place_id: Option<u64>,
+    pub place_name: Option<String>,
+    pub user_id: Option<u64>,
+    pub user_name: Option<String>,
+    pub studio_version: Option<String>,
+    pub connected: bool,
+    /// Bridge protocol the session's plugin talks; older ones should be reloaded
+    pub protocol_version: u32,
+    /// Reported by the plugin on its first poll; None for plugins from before the handshake
+    pub plugin_version: Option<String>,
+    /// Why the plugin should be reinstalled, if it should
+    pub version_warning: Option<String>,
+    /// Requests left queued for the session when it last polled
+    pub queue_depth: usize,
+    /// The place's profile marks it read-only; changes to it are refused
+    pub read_only: bool,
+    /// Milliseconds since the session last polled
+    pub last_seen_ms: u64,
+}
+
+struct SessionState {
+    place_id: Option<u64>,
+    place_name: Option<String>,
+    user_id: Option<u64>,
+    user_name: Option<String>,
+    studio_version: Option<String>,
+    protocol_version: u32,
+    plugin_version: Option<String>,
+    version_warning: Option<String>,
+    queue_depth: usize,
+    last_seen: Instant,
+    active_long_polls: usize,
+}
+
+/// Payload of the place-profile event, sent when a session reports its place
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaceProfileEvent {
+    pub session: String,
+    pub place_id: u64,
+    pub place_name: Option<String>,
+    /// None for places without a saved profile
+    pub profile: Option<PlaceProfile>,
+}
+
+/// Payload of the plugin-version-warning event
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginVersionWarning {
+    pub session: String,
+    pub plugin_version: Option<String>,
+    pub app_version: &'static str,
+    pub protocol_version: u32,
+    /// False when requests are likely to fail, not just miss newer features
+    pub compatible: bool,
+    pub message: String,
+}
+
+/// Compare what a plugin reported in its handshake with this build. Returns
+/// whether it can work with the bridge and, if it's out of date, why.
+fn check_plugin_version(plugin_version: Option<&str>, protocol_version: u32) -> (bool, Option<String>) {
+    match plugin_version {
+        None => (
+            protocol_version == CURRENT_PROTOCOL,
+            Some("The Studio plugin is from an older version of Stud. Reinstall it from Stud.".to_string()),
+        ),
+        Some(_) if protocol_version < CURRENT_PROTOCOL => (
+            false,
+            Some(format!(
+                "The Studio plugin is using bridge protocol v{} but Stud uses v{}. Reinstall it from Stud.",
+                protocol_version, CURRENT_PROTOCOL
+            )),
+        ),
+        Some(version) if version != APP_VERSION => (
+            true,
+            Some(format!(
+                "The Studio plugin was installed by Stud {} (this is {}). Reinstall it to pick up fixes.",
+                version, APP_VERSION
+            )),
+        ),
+        Some(_) => (true, None),
+    }
+}
+
+impl SessionState {
+    fn is_connected(&self) -> bool {
+        self.active_long_polls > 0 || self.last_seen.elapsed() < Duration::from_secs(2)
+    }
+}
+
+/// Which pending requests to cancel. Set fields must all match; with none
+/// set, every pending request is cancelled.
+#[derive(Debug, Default, Deserialize)]
+pub struct CancelRequest {
+    pub id: Option<String>,
+    /// Only requests made for this chat
+    #[serde(default)]
+    pub chat_id: Option<String>,
+    /// Only requests made during this turn
+    #[serde(default)]
+    pub turn_id: Option<String>,
+}
+
+impl CancelRequest {
+    fn matches(&self, id: &str, request: &StudioRequest) -> bool {
+        fn field_matches(wanted: &Option<String>, actual: Option<&str>) -> bool {
+            wanted.as_deref().is_none_or(|wanted| actual == Some(wanted))
+        }
+        field_matches(&self.id, Some(id))
+            && field_matches(&self.chat_id, request.chat_id.as_deref())
+            && field_matches(&self.turn_id, request.turn_id.as_deref())
+    }
+}
+
+/// A queued request as shown in pending-request listings
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingRequestInfo {
+    pub id: String,
+    pub path: String,
+    pub priority: Priority,
+    pub chat_id: Option<String>,
+    pub turn_id: Option<String>,
+    pub target_session: Option<String>,
+    /// Milliseconds since the request was queued
+    pub age_ms: u64,
+    /// Times it has been handed to the plugin
+    pub deliveries: u32,
+    /// Handed out and waiting for the plugin's response
+    pub leased: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RespondRequest {
     pub id: String,
-    pub response: StudioResponse,
+    pub response: WireResponse,
+    /// The request's trace id, echoed back by the plugin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+/// One part of a response too large for a single /stud/respond body.
+/// Parts may arrive in any order; the response completes once the `last`
+/// part and everything before it have arrived.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResponseChunk {
+    pub id: String,
+    pub seq: u32,
+    #[serde(default)]
+    pub last: bool,
+    /// HTTP-style status, taken from the last part
+    #[serde(default)]
+    pub status: u16,
+    /// Set on the last part of a binary response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Number of parts in the response (older plugins don't send it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u32>,
+    pub data: String,
+    /// The request's trace id, echoed back by the plugin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+/// The plugin refusing a request instead of answering it. Retryable refusals
+/// ("place not loaded") are handed out again a few times; permanent ones
+/// ("operation not permitted") fail the request straight away.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NackRequest {
+    pub id: String,
+    pub reason: String,
+    #[serde(default)]
+    pub retryable: bool,
+    /// Machine-readable reason, e.g. `place_not_loaded`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+/// Sent by the plugin to /lease while a request it took is still running
+#[derive(Debug, Deserialize)]
+pub struct LeaseRequest {
+    pub id: String,
+}
+
+/// Payload of the tool-partial-result event, sent for each part of a chunked response
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolPartialResult {
+    pub tool_call_id: String,
+    pub request_id: String,
+    pub seq: u32,
+    pub total: Option<u32>,
+    pub received_parts: usize,
+    pub received_bytes: usize,
+    /// This part's raw text (a slice of the JSON body, or base64 for binary responses)
+    pub data: String,
+}
+
+/// Something that happened in Studio, posted by the plugin to /stud/event
+#[derive(Debug, Deserialize)]
+pub struct StudioEventPost {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub session: Option<String>,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+/// Payload of the studio-event event (selection changes, script errors, playtests)
+#[derive(Debug, Clone, Serialize)]
+pub struct StudioEvent {
+    /// Increases by one per event, for catching up with `get_studio_events`
+    pub seq: u64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub session: Option<String>,
+    pub data: serde_json::Value,
+    pub timestamp: u64,
+}
+
+/// Messages the plugin sends over the WebSocket
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PluginMessage {
+    Respond(RespondRequest),
+    Chunk(ResponseChunk),
+    Nack(NackRequest),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -64,26 +659,245 @@ pub struct StatusResponse {
     pub connected: bool,
     pub pending_requests: usize,
     pub last_poll_time: u64,
+    /// Newest protocol version this bridge speaks
+    #[serde(default)]
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub compatibility: Vec<ProtocolSupport>,
+    /// Version of this build, which connected plugins should match
+    #[serde(default)]
+    pub app_version: String,
+    /// Connected sessions whose plugin reported a problem in its handshake
+    #[serde(default)]
+    pub version_warnings: Vec<String>,
+    /// Place, user and Studio version of the connected session that polled last
+    #[serde(default)]
+    pub studio: Option<StudioSession>,
+}
+
+/// One row of the compatibility matrix reported in status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtocolSupport {
+    pub version: u32,
+    /// Route prefix for this version, e.g. "/stud/v2"
+    pub prefix: String,
+    pub features: Vec<String>,
+    /// Known Studio sessions talking this version
+    pub sessions: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct InspectQuery {
+    /// Only requests whose path contains this
+    path: Option<String>,
+}
+
+/// Sent by the plugin to /stud/v2/hello to agree on a protocol version
+#[derive(Debug, Deserialize)]
+pub struct HelloRequest {
+    /// Versions the plugin speaks
+    pub versions: Vec<u32>,
+    #[serde(default)]
+    pub plugin_version: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HelloResponse {
+    pub version: u32,
+    pub prefix: String,
+    pub features: Vec<String>,
+    /// Seconds a delivered request stays leased; the plugin renews it sooner
+    /// while a handler is still running
+    pub lease_secs: u64,
+}
+
+fn protocol_prefix(version: u32) -> String {
+    if version <= 1 {
+        "/stud".to_string()
+    } else {
+        format!("/stud/v{}", version)
+    }
+}
+
+fn protocol_features(version: u32) -> Vec<String> {
+    let mut features = vec![
+        "long-poll",
+        "websocket",
+        "chunked-responses",
+        "cancellation",
+        "sessions",
+        "events",
+    ];
+    if version >= 2 {
+        // Polls and pushed messages always list requests in `requests`, and
+        // requests and responses name their protocol version
+        features.extend(["negotiation", "batched-polls", "versioned-messages", "uploads", "nack", "lease"]);
+    } else {
+        features.push("opt-in-batching");
+    }
+    features.into_iter().map(str::to_string).collect()
 }
 
 struct PendingRequest {
     request: StudioRequest,
     sender: oneshot::Sender<StudioResponse>,
     timestamp: Instant,
+    /// How long it may wait for Studio, from its tool's sandbox profile
+    timeout: Duration,
+    /// Hidden from polls until then while the plugin that took it works on it
+    leased_until: Option<Instant>,
+    /// Times it has been handed to the plugin
+    deliveries: u32,
+    /// Times the plugin refused it with a retryable nack
+    nacks: u32,
+}
+
+impl PendingRequest {
+    /// Whether it can be handed to the plugin: never delivered, or its lease ran out
+    fn is_visible(&self, now: Instant) -> bool {
+        self.leased_until.is_none_or(|until| until <= now)
+    }
+}
+
+/// Pending requests in one FIFO queue per priority. Iteration yields every
+/// high priority request before normal ones, and normal before low.
+#[derive(Default)]
+struct RequestQueues {
+    high: IndexMap<String, PendingRequest>,
+    normal: IndexMap<String, PendingRequest>,
+    low: IndexMap<String, PendingRequest>,
+}
+
+impl RequestQueues {
+    fn queues(&self) -> [&IndexMap<String, PendingRequest>; 3] {
+        [&self.high, &self.normal, &self.low]
+    }
+
+    fn queues_mut(&mut self) -> [&mut IndexMap<String, PendingRequest>; 3] {
+        [&mut self.high, &mut self.normal, &mut self.low]
+    }
+
+    fn insert(&mut self, id: String, pending: PendingRequest) {
+        let queue = match pending.request.priority {
+            Priority::High => &mut self.high,
+            Priority::Normal => &mut self.normal,
+            Priority::Low => &mut self.low,
+        };
+        queue.insert(id, pending);
+    }
+
+    fn contains_key(&self, id: &str) -> bool {
+        self.queues().iter().any(|queue| queue.contains_key(id))
+    }
+
+    fn get(&self, id: &str) -> Option<&PendingRequest> {
+        self.queues().into_iter().find_map(|queue| queue.get(id))
+    }
+
+    fn get_mut(&mut self, id: &str) -> Option<&mut PendingRequest> {
+        self.queues_mut().into_iter().find_map(|queue| queue.get_mut(id))
+    }
+
+    fn shift_remove(&mut self, id: &str) -> Option<PendingRequest> {
+        self.queues_mut()
+            .into_iter()
+            .find_map(|queue| queue.shift_remove(id))
+    }
+
+    fn len(&self) -> usize {
+        self.queues().iter().map(|queue| queue.len()).sum()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&String, &PendingRequest)> {
+        self.high.iter().chain(self.normal.iter()).chain(self.low.iter())
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &String> {
+        self.iter().map(|(id, _)| id)
+    }
+
+    fn retain(&mut self, mut keep: impl FnMut(&String, &mut PendingRequest) -> bool) {
+        for queue in self.queues_mut() {
+            queue.retain(&mut keep);
+        }
+    }
+}
+
+#[derive(Default)]
+struct PartialResponse {
+    chunks: BTreeMap<u32, String>,
+    // Sequence number of the last part, once it has arrived
+    last_seq: Option<u32>,
+    status: u16,
+    content_type: Option<String>,
+}
+
+struct Cancellation {
+    at: Instant,
+    // Sessions already told about this cancellation ("" for plugins without a session id)
+    notified: HashSet<String>,
 }
 
 struct BridgeState {
-    pending_requests: HashMap<String, PendingRequest>,
+    // FIFO within each priority, higher priorities delivered first
+    pending_requests: RequestQueues,
     request_counter: u64,
     last_poll_time: Instant,
+    // When a request was last queued, to tell an active conversation from an idle app
+    last_request_time: Option<Instant>,
+    // Wakes WebSocket connections and long polls when a new request is queued
+    request_notify: Arc<Notify>,
+    // Long polls currently parked waiting for work; the plugin is connected while any are open
+    active_long_polls: usize,
+    // Port the bridge has moved to, advertised on every poll
+    port_hint: Option<u16>,
+    // Studio instances keyed by the session id they poll with
+    sessions: HashMap<String, SessionState>,
+    // Recently cancelled request ids, announced once to each polling session
+    cancellations: HashMap<String, Cancellation>,
+    // Chunked responses still being received, keyed by request id
+    partial_responses: HashMap<String, PartialResponse>,
+    // Outcomes of /stud/request calls made with an Idempotency-Key
+    idempotency: HashMap<String, IdempotentRequest>,
+    // Connection state last announced to the frontend
+    announced_connected: bool,
+    // Served from /stud/metrics; survives restarts so counters stay monotonic
+    metrics: BridgeMetrics,
+    // Recent events pushed by the plugin, oldest first
+    events: VecDeque<StudioEvent>,
+    event_counter: u64,
+}
+
+/// A request made with an Idempotency-Key. Concurrent retries wait on the same
+/// cell, and later retries within the window get its stored outcome.
+struct IdempotentRequest {
+    // Path and body, so a reused key with a different payload is refused
+    fingerprint: String,
+    // The first attempt, running detached; every retry with the key awaits it
+    outcome: Shared<BoxFuture<'static, Result<StudioResponse, DispatchError>>>,
+    created: Instant,
+    // The request's own timeout, from its sandbox profile
+    timeout: Duration,
 }
 
 impl BridgeState {
     fn new() -> Self {
         Self {
-            pending_requests: HashMap::new(),
+            pending_requests: RequestQueues::default(),
             request_counter: 0,
             last_poll_time: Instant::now() - Duration::from_secs(10),
+            last_request_time: None,
+            request_notify: Arc::new(Notify::new()),
+            active_long_polls: 0,
+            port_hint: None,
+            sessions: HashMap::new(),
+            cancellations: HashMap::new(),
+            partial_responses: HashMap::new(),
+            idempotency: HashMap::new(),
+            announced_connected: false,
+            metrics: BridgeMetrics::default(),
+            events: VecDeque::new(),
+            event_counter: 0,
         }
     }
 
@@ -93,403 +907,2616 @@ impl BridgeState {
     }
 
     fn is_connected(&self) -> bool {
-        self.last_poll_time.elapsed() < Duration::from_secs(2)
+        self.active_long_polls > 0 || self.last_poll_time.elapsed() < Duration::from_secs(2)
     }
 
-    fn cleanup_stale(&mut self) {
-        let timeout = Duration::from_secs(REQUEST_TIMEOUT_SECS);
-        self.pending_requests.retain(|_, pending| {
-            if pending.timestamp.elapsed() > timeout {
-                // Request timed out - sender will be dropped
-                false
-            } else {
-                true
-            }
+    /// Record a poll, registering or refreshing the polling session
+    fn touch(&mut self, query: &PollQuery, protocol_version: u32) -> Option<String> {
+        if is_probe_session(query.session.as_deref()) {
+            return query.session.clone();
+        }
+        self.last_poll_time = Instant::now();
+        let id = query.session.clone()?;
+        let is_new = !self.sessions.contains_key(&id);
+        let session = self.sessions.entry(id.clone()).or_insert_with(|| SessionState {
+            place_id: None,
+            place_name: None,
+            user_id: None,
+            user_name: None,
+            studio_version: None,
+            protocol_version,
+            plugin_version: query.plugin_version.clone(),
+            version_warning: None,
+            queue_depth: 0,
+            last_seen: Instant::now(),
+            active_long_polls: 0,
         });
+        session.last_seen = Instant::now();
+        let handshake_changed = is_new
+            || session.protocol_version != protocol_version
+            || (query.plugin_version.is_some() && query.plugin_version != session.plugin_version);
+        session.protocol_version = protocol_version;
+        if query.plugin_version.is_some() {
+            session.plugin_version = query.plugin_version.clone();
+        }
+        let place_changed = query.place_id.is_some() && query.place_id != session.place_id;
+        if query.place_id.is_some() {
+            session.place_id = query.place_id;
+        }
+        if query.place_name.is_some() {
+            session.place_name = query.place_name.clone();
+        }
+        if query.user_id.is_some() {
+            session.user_id = query.user_id;
+        }
+        if query.user_name.is_some() {
+            session.user_name = query.user_name.clone();
+        }
+        if query.studio_version.is_some() {
+            session.studio_version = query.studio_version.clone();
+        }
+        if let (true, Some(place_id)) = (place_changed, session.place_id) {
+            let profile = profiles::for_place(place_id);
+            if let Some(profile) = &profile {
+                println!(
+                    "[Stud Bridge] Session {} is place {} with a saved profile{}",
+                    id,
+                    place_id,
+                    if profile.read_only { " (read-only)" } else { "" }
+                );
+            }
+            emit_event(
+                PLACE_PROFILE_EVENT,
+                PlaceProfileEvent {
+                    session: id.clone(),
+                    place_id,
+                    place_name: session.place_name.clone(),
+                    profile,
+                },
+            );
+        }
+
+        if handshake_changed {
+            let (compatible, warning) =
+                check_plugin_version(session.plugin_version.as_deref(), protocol_version);
+            session.version_warning = warning.clone();
+            if let Some(message) = warning {
+                println!("[Stud Bridge] Session {}: {}", id, message);
+                emit_event(
+                    PLUGIN_VERSION_WARNING_EVENT,
+                    PluginVersionWarning {
+                        session: id.clone(),
+                        plugin_version: session.plugin_version.clone(),
+                        app_version: APP_VERSION,
+                        protocol_version,
+                        compatible,
+                        message,
+                    },
+                );
+            }
+        }
+        Some(id)
     }
-}
 
-fn chrono_lite_timestamp() -> u64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis() as u64
-}
+    /// Drop the pending requests a cancellation matches and queue cancellation notices
+    fn cancel(&mut self, scope: &CancelRequest) -> Vec<String> {
+        let ids: Vec<String> = self
+            .pending_requests
+            .iter()
+            .filter(|(id, pending)| scope.matches(id, &pending.request))
+            .map(|(id, _)| id.clone())
+            .collect();
 
-type SharedState = Arc<Mutex<BridgeState>>;
+        for id in &ids {
+            // Dropping the sender fails the waiting caller with "Request cancelled"
+            self.pending_requests.shift_remove(id);
+            self.cancellations.insert(
+                id.clone(),
+                Cancellation {
+                    at: Instant::now(),
+                    notified: HashSet::new(),
+                },
+            );
+        }
+        if !ids.is_empty() {
+            self.request_notify.notify_waiters();
+        }
+        ids
+    }
 
-fn with_state(
-    state: SharedState,
-) -> impl Filter<Extract = (SharedState,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || state.clone())
-}
-
-fn cors() -> warp::cors::Builder {
-    warp::cors()
-        .allow_any_origin()
-        .allow_methods(vec!["GET", "POST", "OPTIONS"])
-        .allow_headers(vec!["Content-Type", "Authorization", "ChatGPT-Account-Id"])
-}
-
-pub async fn start_bridge_server() {
-    let state: SharedState = Arc::new(Mutex::new(BridgeState::new()));
-
-    // Status endpoint
-    let status = warp::path!("stud" / "status")
-        .and(warp::get())
-        .and(with_state(state.clone()))
-        .map(|state: SharedState| {
-            let state = state.lock();
-            let response = StatusResponse {
-                connected: state.is_connected(),
-                pending_requests: state.pending_requests.len(),
-                last_poll_time: state.last_poll_time.elapsed().as_millis() as u64,
-            };
-            warp::reply::json(&response)
-        });
+    /// Forget everything but the request counter, failing any queued requests.
+    /// With a journaled queue they're kept for the plugin to pick up on the new listener.
+    fn reset(&mut self) -> usize {
+        let kept = if crate::queue_journal::enabled() {
+            std::mem::take(&mut self.pending_requests)
+        } else {
+            RequestQueues::default()
+        };
+        let cancelled = self.pending_requests.len();
+        let request_counter = self.request_counter;
+        let request_notify = self.request_notify.clone();
+        let announced_connected = self.announced_connected;
+        let metrics = std::mem::take(&mut self.metrics);
+        let events = std::mem::take(&mut self.events);
+        let event_counter = self.event_counter;
+        let last_request_time = self.last_request_time;
+        *self = Self::new();
+        self.pending_requests = kept;
+        self.last_request_time = last_request_time;
+        self.metrics = metrics;
+        self.events = events;
+        self.event_counter = event_counter;
+        self.request_counter = request_counter;
+        // So the connection watcher reports the disconnect
+        self.announced_connected = announced_connected;
+        // Wake parked long polls so they answer and move to the new listener
+        self.request_notify = request_notify;
+        self.request_notify.notify_waiters();
+        cancelled
+    }
 
-    // Request endpoint - Stud sends requests here
-    let request = warp::path!("stud" / "request")
-        .and(warp::post())
-        .and(warp::body::json())
-        .and(with_state(state.clone()))
-        .and_then(handle_request);
-
-    // Poll endpoint - Studio plugin polls here
-    let poll = warp::path!("stud" / "poll")
-        .and(warp::get())
-        .and(with_state(state.clone()))
-        .map(|state: SharedState| {
-            let mut state = state.lock();
-            state.last_poll_time = Instant::now();
+    /// Buffer an event from the plugin, dropping the oldest once the buffer is full
+    fn push_event(&mut self, post: StudioEventPost) -> StudioEvent {
+        self.event_counter += 1;
+        let event = StudioEvent {
+            seq: self.event_counter,
+            kind: post.kind,
+            session: post.session,
+            data: post.data,
+            timestamp: chrono_lite_timestamp(),
+        };
+        if self.events.len() >= MAX_BUFFERED_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+        event
+    }
 
-            // Return first pending request if any
-            if let Some((id, pending)) = state.pending_requests.iter().next() {
-                let response = PollResponse {
-                    id: Some(id.clone()),
-                    request: Some(pending.request.clone()),
-                };
-                warp::reply::json(&response)
-            } else {
-                let response = PollResponse {
-                    id: None,
-                    request: None,
-                };
-                warp::reply::json(&response)
-            }
-        });
+    fn compatibility(&self) -> Vec<ProtocolSupport> {
+        PROTOCOL_VERSIONS
+            .iter()
+            .map(|&version| ProtocolSupport {
+                version,
+                prefix: protocol_prefix(version),
+                features: protocol_features(version),
+                sessions: self
+                    .sessions
+                    .values()
+                    .filter(|session| session.protocol_version == version)
+                    .count(),
+            })
+            .collect()
+    }
 
-    // Respond endpoint - Studio plugin responds here
-    let respond = warp::path!("stud" / "respond")
-        .and(warp::post())
-        .and(warp::body::json())
-        .and(with_state(state.clone()))
-        .map(|body: RespondRequest, state: SharedState| {
-            let mut state = state.lock();
+    fn render_metrics(&self) -> String {
+        self.metrics.render(&Gauges {
+            queue_depth: self.pending_requests.len(),
+            long_polls: self.active_long_polls,
+            sessions: self.sessions.len(),
+            seconds_since_poll: self.last_poll_time.elapsed().as_secs_f64(),
+        })
+    }
 
-            if let Some(pending) = state.pending_requests.remove(&body.id) {
-                let _ = pending.sender.send(body.response);
-                warp::reply::json(&serde_json::json!({"ok": true}))
-            } else {
-                warp::reply::json(&serde_json::json!({"error": "Request not found"}))
+    /// Answer every queued request with a 503 so callers see why it failed
+    fn fail_pending(&mut self, message: &str) -> usize {
+        let ids: Vec<String> = self.pending_requests.keys().cloned().collect();
+        for id in &ids {
+            if let Some(pending) = self.pending_requests.shift_remove(id) {
+                let _ = pending.sender.send(StudioResponse {
+                    status: 503,
+                    body: serde_json::json!({ "error": message }).to_string(),
+                    content_type: None,
+                });
             }
-        });
+        }
+        ids.len()
+    }
 
-    let routes = status
-        .or(request)
-        .or(poll)
-        .or(respond)
-        .with(cors());
+    /// Cancellations this session hasn't been told about yet
+    fn take_cancellations(&mut self, session: Option<&str>) -> Vec<String> {
+        let key = session.unwrap_or_default();
+        self.cancellations
+            .iter_mut()
+            .filter_map(|(id, cancellation)| cancellation.notified.insert(key.to_string()).then(|| id.clone()))
+            .collect()
+    }
 
-    println!("[Stud Bridge] Starting on http://localhost:{}", BRIDGE_PORT);
-    println!("[Stud Bridge] Waiting for stud-bridge plugin to connect...");
+    /// Hand a request to the plugin: hide it from polls until the lease runs
+    /// out, after which it's delivered again unless the plugin has answered
+    fn lease(&mut self, id: &str, via: &str) {
+        let visibility = Duration::from_secs(config::current().bridge.visibility_timeout_secs);
+        if let Some(pending) = self.pending_requests.get_mut(id) {
+            pending.leased_until = Some(Instant::now() + visibility);
+            pending.deliveries += 1;
+            let detail = match pending.deliveries {
+                1 => via.to_string(),
+                n => format!("{}, redelivery {}", via, n - 1),
+            };
+            traces::mark_request(&pending.request, Stage::Delivered, Some(detail));
+        }
+    }
 
-    // Spawn cleanup task
-    let cleanup_state = state.clone();
-    tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(Duration::from_secs(5)).await;
-            cleanup_state.lock().cleanup_stale();
+    /// Fail requests whose lease ran out after their last allowed delivery;
+    /// the plugin that took them has most likely crashed or been reloaded
+    fn expire_leases(&mut self) {
+        let max_deliveries = config::current().bridge.max_deliveries.max(1);
+        let now = Instant::now();
+        let exhausted: Vec<String> = self
+            .pending_requests
+            .iter()
+            .filter(|(_, pending)| pending.deliveries >= max_deliveries && pending.is_visible(now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in exhausted {
+            let Some(pending) = self.pending_requests.shift_remove(&id) else {
+                continue;
+            };
+            self.partial_responses.remove(&id);
+            let message = format!(
+                "Studio took this request {} time(s) without answering",
+                pending.deliveries
+            );
+            println!("[Stud Bridge] Giving up on {} ({}): {}", id, pending.request.path, message);
+            traces::mark_request(&pending.request, Stage::TimedOut, Some(message.clone()));
+            let _ = pending.sender.send(StudioResponse {
+                status: StatusCode::GATEWAY_TIMEOUT.as_u16(),
+                body: serde_json::json!({ "error": message }).to_string(),
+                content_type: None,
+            });
         }
-    });
+    }
 
-    // Spawn OAuth callback server
-    tokio::spawn(async move {
-        start_oauth_server().await;
-    });
+    /// When the next of a session's leased requests can be delivered again
+    fn next_lease_expiry(&self, session: Option<&str>) -> Option<Instant> {
+        self.pending_requests
+            .iter()
+            .filter(|(_, pending)| is_for_session(&pending.request, session))
+            .filter_map(|(_, pending)| pending.leased_until)
+            .min()
+    }
 
-    // Spawn Codex API proxy server
-    tokio::spawn(async move {
-        start_codex_proxy().await;
-    });
+    /// Build the next poll response for a session: the highest priority, oldest
+    /// pending requests it may handle (up to `max`) plus any new cancellations,
+    /// in the session's protocol version. None if there's nothing to send.
+    fn next_poll_response(
+        &mut self,
+        session: Option<&str>,
+        max: Option<usize>,
+        protocol_version: u32,
+    ) -> Option<PollResponse> {
+        self.expire_leases();
+        let cancelled = self.take_cancellations(session);
+        let limit = max.unwrap_or(1).clamp(1, MAX_POLL_BATCH);
+        let now = Instant::now();
+        let batch: Vec<PolledRequest> = self
+            .pending_requests
+            .iter()
+            .filter(|(_, pending)| is_for_session(&pending.request, session) && pending.is_visible(now))
+            .take(limit)
+            .map(|(id, pending)| PolledRequest {
+                id: id.clone(),
+                request: WireRequest::new(&pending.request, protocol_version),
+            })
+            .collect();
 
-    // Try to bind, if port is in use, assume bridge is already running
-    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, BRIDGE_PORT));
-    match tokio::net::TcpListener::bind(addr).await {
-        Ok(listener) => {
-            warp::serve(routes)
-                .run_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
-                .await;
+        for polled in &batch {
+            self.lease(&polled.id, "poll");
         }
-        Err(e) => {
-            println!(
-                "[Stud Bridge] Port {} already in use ({}), assuming bridge is already running",
-                BRIDGE_PORT, e
-            );
+
+        let Some(first) = batch.first().cloned() else {
+            return (!cancelled.is_empty()).then(|| PollResponse {
+                cancelled,
+                ..self.empty_poll_response()
+            });
+        };
+        Some(PollResponse {
+            id: Some(first.id),
+            request: Some(first.request),
+            // Older plugins don't send `max` and only read id/request
+            requests: if max.is_some() { batch } else { Vec::new() },
+            cancelled,
+            port_hint: self.port_hint,
+            queue_depth: 0,
+            next_poll_ms: 0,
+        })
+    }
+
+    fn list_sessions(&self) -> Vec<StudioSession> {
+        self.sessions
+            .iter()
+            .map(|(id, session)| StudioSession {
+                id: id.clone(),
+                place_id: session.place_id,
+                place_name: session.place_name.clone(),
+                user_id: session.user_id,
+                user_name: session.user_name.clone(),
+                studio_version: session.studio_version.clone(),
+                connected: session.is_connected(),
+                protocol_version: session.protocol_version,
+                plugin_version: session.plugin_version.clone(),
+                version_warning: session.version_warning.clone(),
+                queue_depth: session.queue_depth,
+                read_only: session
+                    .place_id
+                    .and_then(profiles::for_place)
+                    .is_some_and(|profile| profile.read_only),
+                last_seen_ms: session.last_seen.elapsed().as_millis() as u64,
+            })
+            .collect()
+    }
+
+    /// The connected session that polled most recently
+    fn current_session(&self) -> Option<StudioSession> {
+        self.list_sessions()
+            .into_iter()
+            .filter(|session| session.connected)
+            .min_by_key(|session| session.last_seen_ms)
+    }
+
+    fn empty_poll_response(&self) -> PollResponse {
+        PollResponse {
+            id: None,
+            request: None,
+            requests: Vec::new(),
+            cancelled: Vec::new(),
+            port_hint: self.port_hint,
+            queue_depth: 0,
+            next_poll_ms: 0,
         }
     }
-}
 
-async fn handle_request(
-    body: StudioRequest,
-    state: SharedState,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    let (sender, receiver) = oneshot::channel();
+    /// Tell a polling session how much work is left and when to poll next:
+    /// straight away while requests are queued, quickly while a conversation
+    /// is sending requests, and progressively slower the longer it's been quiet
+    fn apply_poll_hints(&mut self, response: &mut PollResponse, session: Option<&str>) {
+        // Requests just handed out are leased, so only the rest are still waiting
+        let now = Instant::now();
+        response.queue_depth = self
+            .pending_requests
+            .iter()
+            .filter(|(_, pending)| is_for_session(&pending.request, session) && pending.is_visible(now))
+            .count();
+        let quiet_for = self.last_request_time.map(|at| at.elapsed());
+        response.next_poll_ms = match quiet_for {
+            _ if response.queue_depth > 0 => 0,
+            Some(quiet) if quiet < Duration::from_secs(ACTIVE_CONVERSATION_SECS) => {
+                ACTIVE_POLL_DELAY_MS
+            }
+            Some(quiet) if quiet < Duration::from_secs(DORMANT_AFTER_SECS) => IDLE_POLL_DELAY_MS,
+            _ => DORMANT_POLL_DELAY_MS,
+        };
+        if let Some(state) = session.and_then(|id| self.sessions.get_mut(id)) {
+            state.queue_depth = response.queue_depth;
+        }
+    }
+
+    /// Resolve a pending request with the plugin's response
+    fn complete(&mut self, body: RespondRequest) -> bool {
+        if let Some(pending) = self.pending_requests.shift_remove(&body.id) {
+            let response = body.response.into_response().unwrap_or_else(|e| {
+                println!("[Stud Bridge] Unreadable response to {}: {}", pending.request.path, e);
+                StudioResponse {
+                    status: 502,
+                    body: serde_json::json!({ "error": e }).to_string(),
+                    content_type: None,
+                }
+            });
+            // Plugins from before trace ids don't echo one
+            let detail = match (&body.trace_id, &pending.request.trace_id) {
+                (None, _) => Some("trace id not echoed".to_string()),
+                (Some(echoed), Some(sent)) if echoed != sent => {
+                    Some(format!("plugin echoed trace id {}", echoed))
+                }
+                _ => None,
+            };
+            traces::mark_request(&pending.request, Stage::Responded, detail);
+            let _ = pending.sender.send(response);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Handle the plugin refusing a request: hand it out again after a pause
+    /// if the refusal is retryable and retries are left, otherwise fail it
+    /// with the plugin's reason. False if the request isn't pending.
+    fn nack(&mut self, body: NackRequest) -> bool {
+        let max_retries = config::current().bridge.max_nack_retries;
+        let Some(pending) = self.pending_requests.get_mut(&body.id) else {
+            return false;
+        };
+        let detail = format!(
+            "{}{}",
+            body.code.as_deref().map(|code| format!("{}: ", code)).unwrap_or_default(),
+            body.reason
+        );
+        traces::mark_request(&pending.request, Stage::Nacked, Some(detail));
+        if body.retryable && pending.nacks < max_retries {
+            pending.nacks += 1;
+            // The plugin answered, so this delivery doesn't count towards giving up on a lost one
+            pending.deliveries = pending.deliveries.saturating_sub(1);
+            pending.leased_until = Some(Instant::now() + Duration::from_millis(NACK_RETRY_DELAY_MS * pending.nacks as u64));
+            println!(
+                "[Stud Bridge] Studio can't run {} yet ({}), retry {} of {}",
+                pending.request.path, body.reason, pending.nacks, max_retries
+            );
+            return true;
+        }
+
+        let Some(pending) = self.pending_requests.shift_remove(&body.id) else {
+            return false;
+        };
+        self.partial_responses.remove(&body.id);
+        println!("[Stud Bridge] Studio refused {}: {}", pending.request.path, body.reason);
+        let status = if body.retryable {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::UNPROCESSABLE_ENTITY
+        };
+        let _ = pending.sender.send(StudioResponse {
+            status: status.as_u16(),
+            body: serde_json::json!({
+                "error": body.reason,
+                "code": body.code,
+                "retryable": body.retryable,
+                "attempts": pending.nacks + 1,
+            })
+            .to_string(),
+            content_type: None,
+        });
+        true
+    }
+
+    /// Keep a delivered request hidden from polls while the plugin is still
+    /// working on it or sending its answer. Returns whether it's still pending.
+    fn renew_lease(&mut self, id: &str) -> bool {
+        let Some(pending) = self.pending_requests.get_mut(id) else {
+            return false;
+        };
+        if pending.leased_until.is_some() {
+            let visibility = Duration::from_secs(config::current().bridge.visibility_timeout_secs);
+            pending.leased_until = Some(Instant::now() + visibility);
+        }
+        true
+    }
+
+    /// Store one part of a chunked response, completing the request once every
+    /// part has arrived. Returns Ok(true) when the response was delivered.
+    fn add_chunk(&mut self, chunk: ResponseChunk) -> Result<bool, String> {
+        if !self.pending_requests.contains_key(&chunk.id) {
+            self.partial_responses.remove(&chunk.id);
+            return Err("Request not found".to_string());
+        }
+        if chunk.seq >= MAX_RESPONSE_CHUNKS {
+            self.partial_responses.remove(&chunk.id);
+            return Err(format!("Response has more than {} parts", MAX_RESPONSE_CHUNKS));
+        }
+
+        // Parts arriving show the plugin is still working on it
+        self.renew_lease(&chunk.id);
+        if let Some(pending) = self.pending_requests.get(&chunk.id) {
+            traces::mark_request(&pending.request, Stage::Chunk, Some(format!("part {}", chunk.seq)));
+        }
+        let tool_call_id = self
+            .pending_requests
+            .get(&chunk.id)
+            .and_then(|pending| pending.request.tool_call_id.clone());
+        let partial = self.partial_responses.entry(chunk.id.clone()).or_default();
+        // Once the last part is known, nothing may come after it
+        let last = if chunk.last { Some(chunk.seq) } else { partial.last_seq };
+        let beyond_last = last.is_some_and(|last| {
+            chunk.seq > last
+                || partial.last_seq.is_some_and(|known| known != last)
+                || partial.chunks.keys().next_back().is_some_and(|max| *max > last)
+        });
+        if beyond_last {
+            self.partial_responses.remove(&chunk.id);
+            return Err(format!("Part {} of a response comes after its last part", chunk.seq));
+        }
+        if let Some(tool_call_id) = tool_call_id {
+            let is_new = !partial.chunks.contains_key(&chunk.seq);
+            let received_bytes = partial
+                .chunks
+                .iter()
+                .filter(|(seq, _)| **seq != chunk.seq)
+                .map(|(_, data)| data.len())
+                .sum::<usize>()
+                + chunk.data.len();
+            emit_event(
+                TOOL_PARTIAL_RESULT_EVENT,
+                ToolPartialResult {
+                    tool_call_id,
+                    request_id: chunk.id.clone(),
+                    seq: chunk.seq,
+                    total: chunk.total,
+                    received_parts: partial.chunks.len() + usize::from(is_new),
+                    received_bytes,
+                    data: chunk.data.clone(),
+                },
+            );
+        }
+        partial.chunks.insert(chunk.seq, chunk.data);
+        if chunk.last {
+            partial.last_seq = Some(chunk.seq);
+            partial.status = chunk.status;
+            partial.content_type = chunk.content_type;
+        }
+
+        // Every part from 0 to the last one
+        let complete = partial.last_seq.is_some_and(|last| {
+            partial.chunks.len() as u32 == last + 1
+                && partial.chunks.keys().next_back() == Some(&last)
+        });
+        if !complete {
+            return Ok(false);
+        }
+
+        let Some(partial) = self.partial_responses.remove(&chunk.id) else {
+            return Ok(false);
+        };
+        let body: String = partial.chunks.into_values().collect();
+        Ok(self.complete(RespondRequest {
+            id: chunk.id,
+            response: StudioResponse {
+                status: partial.status,
+                body,
+                content_type: partial.content_type,
+            }
+            .into(),
+            trace_id: chunk.trace_id,
+        }))
+    }
+
+    fn cleanup_stale(&mut self) {
+        let expiry = Duration::from_secs(SESSION_EXPIRY_SECS);
+        self.sessions
+            .retain(|_, session| session.active_long_polls > 0 || session.last_seen.elapsed() < expiry);
+
+        let timeout = Duration::from_secs(REQUEST_TIMEOUT_SECS);
+        // Kept while the first attempt is pending, however long its profile lets it run
+        self.idempotency.retain(|_, request| {
+            request.outcome.peek().is_none()
+                || request.created.elapsed() < request.timeout + Duration::from_secs(IDEMPOTENCY_WINDOW_SECS)
+        });
+        self.cancellations
+            .retain(|_, cancellation| cancellation.at.elapsed() < timeout);
+        let pending = &self.pending_requests;
+        self.partial_responses.retain(|id, _| pending.contains_key(id));
+        self.pending_requests.retain(|_, pending| {
+            if pending.timestamp.elapsed() > pending.timeout {
+                // Request timed out - sender will be dropped
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+/// Untargeted requests go to whichever session polls first
+fn is_for_session(request: &StudioRequest, session: Option<&str>) -> bool {
+    match &request.target_session {
+        Some(target) => session == Some(target.as_str()),
+        None => !is_probe_session(session),
+    }
+}
+
+/// A new session id for a self-test probe
+pub(crate) fn probe_session_id() -> String {
+    format!("{}{}", PROBE_SESSION_PREFIX, uuid::Uuid::new_v4().simple())
+}
+
+fn is_probe_session(session: Option<&str>) -> bool {
+    session.is_some_and(|id| id.starts_with(PROBE_SESSION_PREFIX))
+}
+
+pub(crate) fn chrono_lite_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+type SharedState = Arc<Mutex<BridgeState>>;
+
+/// The bridge's queue and sessions plus the channel to its control loop. The
+/// app's handle is managed by Tauri so commands take it as `State`; clones
+/// share everything.
+#[derive(Clone)]
+pub struct BridgeHandle {
+    state: SharedState,
+    control: Arc<Mutex<Option<mpsc::UnboundedSender<BridgeCommand>>>>,
+    server: Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>,
+}
+
+impl Default for BridgeHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BridgeHandle {
+    /// A bridge with empty state that isn't serving yet
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(BridgeState::new())),
+            control: Arc::new(Mutex::new(None)),
+            server: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The app's bridge, the one `studio_request` sends through
+    pub fn shared() -> Self {
+        BRIDGE.clone()
+    }
+
+    /// Start the bridge and Codex proxy servers on Tauri's async runtime
+    pub fn spawn(&self) {
+        let server = tauri::async_runtime::spawn(start_bridge_server(self.clone()));
+        *self.server.lock() = Some(server);
+    }
+
+    /// Stop the servers and wait up to `timeout` for them to release their
+    /// ports. Returns false if they didn't finish in time.
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        request_shutdown();
+        let Some(server) = self.server.lock().take() else {
+            return true;
+        };
+        tauri::async_runtime::block_on(async move {
+            tokio::time::timeout(timeout, server).await.is_ok()
+        })
+    }
+
+    fn send(&self, command: BridgeCommand) -> Result<(), String> {
+        self.control
+            .lock()
+            .as_ref()
+            .ok_or_else(|| "Bridge is not running".to_string())?
+            .send(command)
+            .map_err(|_| "Bridge is not running".to_string())
+    }
+
+    /// Ask the running bridge to move to a new port. The old listener stays up as a
+    /// warm standby (telling the plugin where to go) so no queued requests are lost.
+    pub async fn rebind(&self, port: u16) -> Result<u16, String> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(BridgeCommand::Rebind { port, reply })?;
+        receiver
+            .await
+            .map_err(|_| "Bridge stopped while rebinding".to_string())?
+    }
+
+    /// Stop the listener, clear queued requests and sessions, and bind the
+    /// configured port again
+    pub async fn restart(&self) -> Result<BridgeRestart, String> {
+        let (reply, receiver) = oneshot::channel();
+        self.send(BridgeCommand::Restart { reply })?;
+        receiver
+            .await
+            .map_err(|_| "Bridge stopped while restarting".to_string())?
+    }
+}
+
+/// Shared by every bridge handler: the queue and sessions, plus the protocol
+/// version of the prefix the route was mounted under
+#[derive(Clone)]
+struct AppState {
+    bridge: SharedState,
+    protocol_version: u32,
+}
+
+/// Why a call was refused before reaching its handler. Answered with a JSON `error`.
+#[derive(Debug)]
+pub(crate) enum BridgeRejection {
+    ForbiddenHost(String),
+    ForbiddenOrigin(String),
+    Unauthorized,
+    InvalidBody(String),
+    /// Body over the configured limit, in bytes
+    PayloadTooLarge(u64),
+}
+
+impl IntoResponse for BridgeRejection {
+    fn into_response(self) -> Response {
+        let (status, body) = match self {
+            BridgeRejection::ForbiddenHost(host) => (
+                StatusCode::FORBIDDEN,
+                serde_json::json!({ "error": format!("Host not allowed: {}", host) }),
+            ),
+            BridgeRejection::ForbiddenOrigin(origin) => (
+                StatusCode::FORBIDDEN,
+                serde_json::json!({ "error": format!("Origin not allowed: {}", origin) }),
+            ),
+            BridgeRejection::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                serde_json::json!({
+                    "error": "Missing or invalid bridge secret. Reinstall the Studio plugin from Stud."
+                }),
+            ),
+            BridgeRejection::InvalidBody(message) => {
+                (StatusCode::BAD_REQUEST, serde_json::json!({ "error": message }))
+            }
+            BridgeRejection::PayloadTooLarge(limit) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                serde_json::json!({
+                    "error": format!("Payload too large: the bridge accepts bodies up to {} bytes (bridge.max_body_bytes)", limit),
+                    "limit_bytes": limit,
+                }),
+            ),
+        };
+        (status, Json(body)).into_response()
+    }
+}
+
+/// A header's value, if it's present and readable
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// JSON with a status code, the common shape of bridge replies
+fn json_status(status: StatusCode, value: serde_json::Value) -> Response {
+    (status, Json(value)).into_response()
+}
+
+/// Refuse callers that don't present the bridge secret
+async fn require_secret(request: Request, next: Next) -> Result<Response, BridgeRejection> {
+    match header_str(request.headers(), SECRET_HEADER) {
+        Some(secret) if secrets_match(secret, bridge_secret()) => Ok(next.run(request).await),
+        _ => Err(BridgeRejection::Unauthorized),
+    }
+}
+
+/// Undo a gzip or deflate Content-Encoding
+fn decode_body(encoding: Option<&str>, body: &[u8], limit: u64) -> Result<Vec<u8>, String> {
+    use flate2::read::{GzDecoder, ZlibDecoder};
+    use std::io::Read;
+
+    let mut data = Vec::new();
+    let result = match encoding.map(|e| e.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("") | Some("identity") => return Ok(body.to_vec()),
+        Some("gzip") => GzDecoder::new(body)
+            .take(limit)
+            .read_to_end(&mut data),
+        Some("deflate") => ZlibDecoder::new(body)
+            .take(limit)
+            .read_to_end(&mut data),
+        Some(other) => return Err(format!("Unsupported Content-Encoding: {}", other)),
+    };
+    result.map_err(|e| format!("Failed to decompress body: {}", e))?;
+    Ok(data)
+}
+
+/// JSON request body, transparently decompressed when sent with Content-Encoding.
+/// Unlike axum's `Json`, it doesn't insist on a Content-Type.
+struct JsonBody<T>(T);
+
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for JsonBody<T> {
+    type Rejection = BridgeRejection;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let encoding = header_str(request.headers(), "content-encoding").map(str::to_string);
+        let body = Bytes::from_request(request, state)
+            .await
+            .map_err(|e| BridgeRejection::InvalidBody(e.body_text()))?;
+        decode_body(encoding.as_deref(), &body, MAX_DECOMPRESSED_BYTES)
+            .and_then(|data| {
+                serde_json::from_slice(&data).map_err(|e| format!("Invalid JSON body: {}", e))
+            })
+            .map(JsonBody)
+            .map_err(BridgeRejection::InvalidBody)
+    }
+}
+
+/// JSON request body like `JsonBody`, refused with 413 when it's larger than
+/// `bridge.max_body_bytes` either on the wire or once decompressed
+struct LimitedJsonBody<T>(T);
+
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for LimitedJsonBody<T> {
+    type Rejection = BridgeRejection;
+
+    async fn from_request(request: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        let limit = config::current().bridge.max_body_bytes;
+        // Checked against Content-Length first so oversized uploads aren't buffered
+        let length = header_str(request.headers(), "content-length").and_then(|length| length.parse::<u64>().ok());
+        if length.is_some_and(|length| length > limit) {
+            return Err(BridgeRejection::PayloadTooLarge(limit));
+        }
+        let encoding = header_str(request.headers(), "content-encoding").map(str::to_string);
+        // Counted as it's read, for bodies sent without a Content-Length
+        let mut stream = request.into_body().into_data_stream();
+        let mut body = Vec::new();
+        while let Some(frame) = stream.next().await {
+            let frame = frame.map_err(|e| BridgeRejection::InvalidBody(format!("Failed to read body: {}", e)))?;
+            if (body.len() + frame.len()) as u64 > limit {
+                return Err(BridgeRejection::PayloadTooLarge(limit));
+            }
+            body.extend_from_slice(&frame);
+        }
+        // Read one byte past the limit to tell a body at the limit from one cut off by it
+        let data = decode_body(encoding.as_deref(), &body, limit.saturating_add(1))
+            .map_err(BridgeRejection::InvalidBody)?;
+        if data.len() as u64 > limit {
+            return Err(BridgeRejection::PayloadTooLarge(limit));
+        }
+        serde_json::from_slice(&data)
+            .map(LimitedJsonBody)
+            .map_err(|e| BridgeRejection::InvalidBody(format!("Invalid JSON body: {}", e)))
+    }
+}
+
+/// JSON reply, gzip-compressed when the client accepts it and it's large enough to matter
+fn json_reply<T: Serialize>(value: &T, accept_encoding: Option<&str>) -> Response {
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    let json = serde_json::to_vec(value).unwrap_or_default();
+    let accepts_gzip = accept_encoding.is_some_and(|accept| {
+        accept
+            .split(',')
+            .any(|encoding| encoding.trim().starts_with("gzip"))
+    });
+
+    if accepts_gzip && json.len() >= COMPRESSION_THRESHOLD {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        if let Ok(compressed) = encoder.write_all(&json).and_then(|_| encoder.finish()) {
+            return (
+                [
+                    (header::CONTENT_TYPE, "application/json"),
+                    (header::CONTENT_ENCODING, "gzip"),
+                ],
+                compressed,
+            )
+                .into_response();
+        }
+    }
+
+    ([(header::CONTENT_TYPE, "application/json")], json).into_response()
+}
+
+/// Whether a Host header names this machine. Anything else means a page on
+/// another domain resolved its name to 127.0.0.1 (DNS rebinding).
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "localhost" | "127.0.0.1" | "::1"
+    )
+}
+
+/// A configured origin that a browser's Origin header can match: `scheme://host[:port]`
+fn is_valid_origin(origin: &str) -> bool {
+    origin
+        .parse::<axum::http::Uri>()
+        .is_ok_and(|uri| {
+            uri.scheme().is_some()
+                && uri.host().is_some()
+                && uri.path_and_query().is_none_or(|path| path.as_str() == "/")
+                && !origin.ends_with('/')
+        })
+}
+
+/// The configured origins, without (and warning about) malformed entries
+fn allowed_origins() -> Vec<String> {
+    let mut origins = config::current().bridge.allowed_origins;
+    origins.retain(|origin| {
+        let valid = is_valid_origin(origin);
+        if !valid {
+            println!("[Stud Bridge] Ignoring invalid allowed origin: {}", origin);
+        }
+        valid
+    });
+    origins
+}
+
+/// Refuse requests whose Host header isn't a loopback name, and browser
+/// requests from origins that aren't allowed. The Studio plugin sends no Origin.
+pub(crate) async fn local_only(request: Request, next: Next) -> Result<Response, BridgeRejection> {
+    if let Some(host) = header_str(request.headers(), "host") {
+        if !is_loopback_host(host) {
+            return Err(BridgeRejection::ForbiddenHost(host.to_string()));
+        }
+    }
+    if let Some(origin) = header_str(request.headers(), "origin") {
+        if !allowed_origins().iter().any(|allowed| allowed == origin) {
+            return Err(BridgeRejection::ForbiddenOrigin(origin.to_string()));
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+/// Write refused and failed calls to the traffic log; requests to Studio are
+/// already logged by `dispatch`, and successful polls would drown everything else
+pub(crate) async fn log_failures(request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        tracing::info!(
+            target: crate::bridge_log::TARGET,
+            event = "http_error",
+            method = %method,
+            path = %path,
+            status = status.as_u16(),
+            duration_ms = started.elapsed().as_millis() as u64,
+        );
+    }
+    response
+}
+
+/// CORS for the local servers, limited to the configured origins
+pub(crate) fn cors() -> CorsLayer {
+    let origins: Vec<HeaderValue> = allowed_origins()
+        .iter()
+        .filter_map(|origin| origin.parse().ok())
+        .collect();
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            HeaderName::from_static("chatgpt-account-id"),
+            // Sent by provider SDKs calling the provider proxy
+            HeaderName::from_static("x-api-key"),
+            HeaderName::from_static("anthropic-version"),
+            HeaderName::from_static("anthropic-beta"),
+            HeaderName::from_static(crate::llm_proxy::GENERATION_HEADER),
+            HeaderName::from_static(crate::llm_proxy::PROJECT_HEADER),
+            HeaderName::from_static(SECRET_HEADER),
+            HeaderName::from_static("idempotency-key"),
+            HeaderName::from_static(TRACE_HEADER),
+        ])
+        .expose_headers([
+            HeaderName::from_static(TRACE_HEADER),
+            HeaderName::from_static(crate::llm_proxy::GENERATION_HEADER),
+            HeaderName::from_static(crate::response_cache::CACHE_HEADER),
+        ])
+}
+
+/// Middleware every local server shares, outermost first: CORS, then the
+/// host and origin checks, then logging of failed calls
+pub(crate) fn with_local_layers<S: Clone + Send + Sync + 'static>(router: Router<S>) -> Router<S> {
+    router
+        .layer(middleware::from_fn(log_failures))
+        .layer(middleware::from_fn(local_only))
+        .layer(cors())
+}
+
+/// Pick the newest version both sides speak
+async fn handle_hello(JsonBody(body): JsonBody<HelloRequest>) -> Response {
+    let agreed = body
+        .versions
+        .iter()
+        .copied()
+        .filter(|version| PROTOCOL_VERSIONS.contains(version))
+        .max();
+    let Some(version) = agreed else {
+        println!(
+            "[Stud Bridge] Plugin {} speaks protocol {:?}, bridge speaks {:?}",
+            body.plugin_version.as_deref().unwrap_or("(unknown version)"),
+            body.versions,
+            PROTOCOL_VERSIONS
+        );
+        return json_status(
+            StatusCode::UPGRADE_REQUIRED,
+            serde_json::json!({
+                "error": "No protocol version in common. Reinstall the Studio plugin from Stud.",
+                "supported": PROTOCOL_VERSIONS,
+            }),
+        );
+    };
+    Json(HelloResponse {
+        version,
+        prefix: protocol_prefix(version),
+        features: protocol_features(version),
+        lease_secs: config::current().bridge.visibility_timeout_secs,
+    })
+    .into_response()
+}
+
+fn bridge_routes(state: SharedState) -> Router {
+    // Negotiation endpoint - bridges from before versioning 404 here, telling the plugin to stay on v1
+    let authenticated = Router::new()
+        .route("/stud/v2/hello", post(handle_hello))
+        .nest("/stud/v2", api_routes(state.clone(), 2))
+        // Plugins installed before versioning keep using the unprefixed routes
+        .nest("/stud", api_routes(state, 1))
+        .route_layer(middleware::from_fn(require_secret));
+
+    // Health endpoint - unauthenticated, so the plugin and other copies of Stud can
+    // tell a Stud bridge from another app on the same port
+    with_local_layers(
+        Router::new()
+            .route("/stud/health", get(health))
+            .merge(authenticated),
+    )
+}
+
+async fn health() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        service: HEALTH_SERVICE.to_string(),
+        version: APP_VERSION.to_string(),
+    })
+}
+
+/// The bridge API for one protocol version, mounted under that version's prefix
+fn api_routes(bridge: SharedState, protocol_version: u32) -> Router {
+    Router::new()
+        .route("/status", get(status))
+        // Counters and latency histograms in Prometheus text format
+        .route("/metrics", get(metrics))
+        // Stud sends requests here
+        .route("/request", post(handle_request))
+        // Studio plugin polls here (optionally long-polling with ?wait=N)
+        .route("/poll", get(handle_poll))
+        // Studio plugin responds here, in one go or in parts for large responses
+        .route("/respond", post(respond))
+        .route("/respond/chunk", post(respond_chunk))
+        // Plugin refuses a request it can't run now (retryable) or at all
+        .route("/nack", post(nack))
+        // Plugin heartbeat for long-running handlers
+        .route("/lease", post(lease))
+        // Plugin uploads bodies too large for one POST in resumable parts,
+        // then responds with a reference to the upload
+        .route("/upload", post(upload_start))
+        .route(
+            "/upload/{id}",
+            get(upload_status)
+                .post(upload_chunk)
+                .layer(DefaultBodyLimit::max(crate::uploads::MAX_CHUNK_BYTES as usize)),
+        )
+        .route("/upload/{id}/finalize", post(upload_finalize))
+        // Drop a queued request (or all of them) before Studio runs it
+        .route("/cancel", post(cancel))
+        // Plugin reports things that happened in Studio, forwarded to the frontend
+        .route("/event", post(event))
+        // Plugin fetches watch expressions and posts sampled values during playtests
+        .route("/watch", get(watch_list))
+        .route("/watch/samples", post(watch_samples))
+        // Plugin forwards tagged debug print output from playtests
+        .route("/logs", post(logs))
+        // Recently recorded requests, for developing plugin tools
+        .route("/inspect", get(inspect_list))
+        .route("/inspect/{id}", get(inspect_get))
+        .route("/inspect/{id}/replay", post(inspect_replay))
+        // Plugin upgrades here to have requests pushed instead of polling
+        .route("/ws", get(ws_upgrade))
+        .with_state(AppState {
+            bridge,
+            protocol_version,
+        })
+}
+
+async fn status(State(app): State<AppState>) -> Json<StatusResponse> {
+    let state = app.bridge.lock();
+    Json(StatusResponse {
+        connected: state.is_connected(),
+        pending_requests: state.pending_requests.len(),
+        last_poll_time: state.last_poll_time.elapsed().as_millis() as u64,
+        protocol_version: CURRENT_PROTOCOL,
+        compatibility: state.compatibility(),
+        app_version: APP_VERSION.to_string(),
+        version_warnings: state
+            .sessions
+            .values()
+            .filter(|session| session.is_connected())
+            .filter_map(|session| session.version_warning.clone())
+            .collect(),
+        studio: state.current_session(),
+    })
+}
+
+async fn metrics(State(app): State<AppState>) -> Response {
+    let body = app.bridge.lock().render_metrics();
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+async fn respond(
+    State(app): State<AppState>,
+    LimitedJsonBody(body): LimitedJsonBody<RespondRequest>,
+) -> Json<serde_json::Value> {
+    if app.bridge.lock().complete(body) {
+        Json(serde_json::json!({ "ok": true }))
+    } else {
+        Json(serde_json::json!({ "error": "Request not found" }))
+    }
+}
+
+async fn nack(State(app): State<AppState>, JsonBody(body): JsonBody<NackRequest>) -> Json<serde_json::Value> {
+    if app.bridge.lock().nack(body) {
+        Json(serde_json::json!({ "ok": true }))
+    } else {
+        Json(serde_json::json!({ "error": "Request not found" }))
+    }
+}
+
+async fn lease(State(app): State<AppState>, JsonBody(body): JsonBody<LeaseRequest>) -> Json<serde_json::Value> {
+    if app.bridge.lock().renew_lease(&body.id) {
+        Json(serde_json::json!({ "ok": true }))
+    } else {
+        Json(serde_json::json!({ "error": "Request not found" }))
+    }
+}
+
+async fn respond_chunk(
+    State(app): State<AppState>,
+    LimitedJsonBody(chunk): LimitedJsonBody<ResponseChunk>,
+) -> Json<serde_json::Value> {
+    match app.bridge.lock().add_chunk(chunk) {
+        Ok(done) => Json(serde_json::json!({ "ok": true, "complete": done })),
+        Err(e) => Json(serde_json::json!({ "error": e })),
+    }
+}
+
+fn upload_reply(result: Result<crate::uploads::UploadInfo, crate::uploads::UploadError>) -> Response {
+    use crate::uploads::UploadError;
+    match result {
+        Ok(info) => Json(info).into_response(),
+        Err(e) => {
+            let status = match &e {
+                UploadError::NotFound => StatusCode::NOT_FOUND,
+                UploadError::WrongOffset(_) => StatusCode::CONFLICT,
+                UploadError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+                UploadError::Failed(_) => StatusCode::BAD_REQUEST,
+            };
+            let mut body = serde_json::json!({ "error": e.message() });
+            if let UploadError::WrongOffset(offset) = e {
+                body["offset"] = offset.into();
+            }
+            json_status(status, body)
+        }
+    }
+}
+
+async fn upload_start(JsonBody(body): JsonBody<crate::uploads::StartUpload>) -> Response {
+    upload_reply(crate::uploads::start(body))
+}
+
+async fn upload_status(Path(id): Path<String>) -> Response {
+    upload_reply(crate::uploads::status(&id))
+}
+
+async fn upload_chunk(
+    State(app): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<UploadQuery>,
+    body: Result<Bytes, BytesRejection>,
+) -> Response {
+    // The route's body limit stops an oversized part while it's read
+    let body = match body {
+        Ok(body) => body,
+        Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            return json_status(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                serde_json::json!({
+                    "error": format!("Upload parts are limited to {} bytes", crate::uploads::MAX_CHUNK_BYTES)
+                }),
+            );
+        }
+        Err(e) => return json_status(StatusCode::BAD_REQUEST, serde_json::json!({ "error": e.body_text() })),
+    };
+    let result = crate::uploads::append(&id, query.offset, &body);
+    if let Ok(info) = &result {
+        if let Some(request_id) = &info.request_id {
+            app.bridge.lock().renew_lease(request_id);
+        }
+    }
+    upload_reply(result)
+}
+
+async fn upload_finalize(Path(id): Path<String>) -> Response {
+    upload_reply(crate::uploads::finalize(&id))
+}
+
+async fn cancel(
+    State(app): State<AppState>,
+    JsonBody(body): JsonBody<CancelRequest>,
+) -> Json<serde_json::Value> {
+    let cancelled = app.bridge.lock().cancel(&body);
+    Json(serde_json::json!({ "cancelled": cancelled }))
+}
+
+async fn event(
+    State(app): State<AppState>,
+    JsonBody(post): JsonBody<StudioEventPost>,
+) -> Response {
+    if post.kind.is_empty() || post.kind.len() > MAX_EVENT_TYPE_LEN {
+        return json_status(
+            StatusCode::BAD_REQUEST,
+            serde_json::json!({ "error": "Invalid event type" }),
+        );
+    }
+    match post.kind.as_str() {
+        "script_error" => crate::diagnostics::record_runtime_error(&post.data),
+        "playtest_started" => crate::diagnostics::clear_runtime_errors(),
+        _ => {}
+    }
+    let event = app.bridge.lock().push_event(post);
+    let seq = event.seq;
+    emit_event(STUDIO_EVENT, event);
+    Json(serde_json::json!({ "ok": true, "seq": seq })).into_response()
+}
+
+async fn watch_list() -> Json<Vec<crate::watch::WatchInfo>> {
+    Json(crate::watch::active_watches())
+}
+
+async fn watch_samples(
+    JsonBody(batch): JsonBody<crate::watch::SampleBatch>,
+) -> Json<serde_json::Value> {
+    let recorded = crate::watch::record_samples(batch);
+    Json(serde_json::json!({ "ok": true, "recorded": recorded }))
+}
+
+async fn logs(JsonBody(batch): JsonBody<crate::print_debug::LogBatch>) -> Json<serde_json::Value> {
+    let recorded = crate::print_debug::record_output(batch);
+    Json(serde_json::json!({ "ok": true, "recorded": recorded }))
+}
+
+async fn inspect_list(Query(query): Query<InspectQuery>) -> Response {
+    Json(crate::inspector::list(query.path.as_deref())).into_response()
+}
+
+async fn inspect_get(Path(id): Path<String>) -> Response {
+    match crate::inspector::get(&id) {
+        Some(recorded) => Json(recorded).into_response(),
+        None => json_status(
+            StatusCode::NOT_FOUND,
+            serde_json::json!({ "error": "No recorded request with this id" }),
+        ),
+    }
+}
+
+async fn inspect_replay(Path(id): Path<String>) -> Response {
+    match crate::inspector::replay(&id).await {
+        Ok(recorded) => Json(recorded).into_response(),
+        Err(e) => json_status(StatusCode::BAD_REQUEST, serde_json::json!({ "error": e })),
+    }
+}
+
+async fn ws_upgrade(
+    State(app): State<AppState>,
+    Query(query): Query<PollQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, query, app.bridge, app.protocol_version))
+}
+
+/// The bridge routes over the shared queue, for listeners other than the TCP port
+pub(crate) fn local_routes() -> Router {
+    bridge_routes(BRIDGE.state.clone())
+}
+
+/// Secret for the app's own calls to the bridge
+#[tauri::command]
+pub fn get_bridge_secret() -> String {
+    bridge_secret().to_string()
+}
+
+/// Serve the bridge routes on a listener until the returned sender fires
+fn serve_bridge(listener: tokio::net::TcpListener, state: SharedState) -> oneshot::Sender<()> {
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = axum::serve(listener, bridge_routes(state)).with_graceful_shutdown(async {
+        let _ = shutdown_rx.await;
+    });
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            println!("[Stud Bridge] Server error: {}", e);
+        }
+    });
+    shutdown_tx
+}
+
+pub(crate) fn set_oauth_endpoint(port: u16) {
+    update_endpoints(|endpoints| {
+        endpoints.oauth_port = Some(port);
+        endpoints.oauth_callback_url = Some(format!("http://localhost:{}/auth/callback", port));
+    });
+}
+
+pub(crate) fn clear_oauth_endpoint() {
+    update_endpoints(|endpoints| {
+        endpoints.oauth_port = None;
+        endpoints.oauth_callback_url = None;
+    });
+}
+
+pub(crate) fn set_socket_endpoint(path: &str) {
+    update_endpoints(|endpoints| endpoints.socket_path = Some(path.to_string()));
+}
+
+fn set_bridge_endpoint(port: u16) {
+    update_endpoints(|endpoints| {
+        endpoints.bridge_port = Some(port);
+        endpoints.bridge_url = Some(format!("http://localhost:{}", port));
+    });
+}
+
+/// Requests waiting for Studio, in delivery order, optionally only those for one chat
+#[tauri::command]
+pub fn list_pending_requests(
+    bridge: tauri::State<'_, BridgeHandle>,
+    chat_id: Option<String>,
+) -> Vec<PendingRequestInfo> {
+    bridge
+        .state
+        .lock()
+        .pending_requests
+        .iter()
+        .filter(|(_, pending)| {
+            chat_id
+                .as_deref()
+                .is_none_or(|chat_id| pending.request.chat_id.as_deref() == Some(chat_id))
+        })
+        .map(|(id, pending)| PendingRequestInfo {
+            id: id.clone(),
+            path: pending.request.path.clone(),
+            priority: pending.request.priority,
+            chat_id: pending.request.chat_id.clone(),
+            turn_id: pending.request.turn_id.clone(),
+            target_session: pending.request.target_session.clone(),
+            age_ms: pending.timestamp.elapsed().as_millis() as u64,
+            deliveries: pending.deliveries,
+            leased: !pending.is_visible(Instant::now()),
+        })
+        .collect()
+}
+
+/// Buffered Studio events, oldest first; pass the last seen `seq` to get only newer ones
+#[tauri::command]
+pub fn get_studio_events(bridge: tauri::State<'_, BridgeHandle>, since: Option<u64>) -> Vec<StudioEvent> {
+    let since = since.unwrap_or(0);
+    bridge
+        .state
+        .lock()
+        .events
+        .iter()
+        .filter(|event| event.seq > since)
+        .cloned()
+        .collect()
+}
+
+/// List the Studio instances registered with the bridge
+#[tauri::command]
+pub fn list_studio_sessions(bridge: tauri::State<'_, BridgeHandle>) -> Vec<StudioSession> {
+    let mut sessions = bridge.state.lock().list_sessions();
+    sessions.sort_by_key(|session| session.last_seen_ms);
+    sessions
+}
+
+/// Place, user and Studio version of the connected Studio, for "Connected to" displays
+#[tauri::command]
+pub fn get_studio_metadata(bridge: tauri::State<'_, BridgeHandle>) -> Option<StudioSession> {
+    bridge.state.lock().current_session()
+}
+
+/// Cancel queued Studio requests by id and/or the chat and turn that made them,
+/// or every pending request when nothing is given. Returns the ids that were cancelled.
+#[tauri::command]
+pub fn cancel_bridge_request(
+    bridge: tauri::State<'_, BridgeHandle>,
+    id: Option<String>,
+    chat_id: Option<String>,
+    turn_id: Option<String>,
+) -> Vec<String> {
+    let cancelled = bridge.state.lock().cancel(&CancelRequest {
+        id,
+        chat_id,
+        turn_id,
+    });
+    if !cancelled.is_empty() {
+        println!("[Stud Bridge] Cancelled {} pending request(s)", cancelled.len());
+    }
+    cancelled
+}
+
+/// Move the bridge to a new port (defaults to the configured one)
+#[tauri::command]
+pub async fn rebind_bridge(
+    bridge: tauri::State<'_, BridgeHandle>,
+    port: Option<u16>,
+) -> Result<u16, String> {
+    let port = port.unwrap_or_else(|| config::current().bridge.resolved().port);
+    bridge.rebind(port).await
+}
+
+/// Restart a wedged bridge: stop the listener, clear queued requests and
+/// sessions, and bind the configured port again
+#[tauri::command]
+pub async fn restart_bridge(bridge: tauri::State<'_, BridgeHandle>) -> Result<BridgeRestart, String> {
+    bridge.restart().await
+}
+
+/// Run the bridge until the app shuts down, along with the OAuth callback
+/// server and the Codex proxy
+pub async fn start_bridge_server(bridge: BridgeHandle) {
+    let state: SharedState = bridge.state.clone();
+
+    // Spawn cleanup task
+    let cleanup_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            cleanup_state.lock().cleanup_stale();
+        }
+    });
+
+    // Announce connects and disconnects to the frontend so it doesn't have to poll
+    let watch_state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_millis(CONNECTION_CHECK_MS)).await;
+            publish_connection_state(&watch_state);
+        }
+    });
+
+    // Spawn Codex API proxy server
+    tokio::spawn(async move {
+        start_codex_proxy().await;
+    });
+
+    // Try the configured port. If it's taken by another copy of Stud, leave the
+    // bridge to it; if some other app has it, move to the next free port.
+    let preferred = config::current().bridge.resolved().port;
+    if tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, preferred)).await.is_err()
+        && is_stud_bridge(preferred).await
+    {
+        println!(
+            "[Stud Bridge] Another copy of Stud is serving the bridge on port {}, not starting a second one",
+            preferred
+        );
+        return;
+    }
+    let listener = match bind_with_fallback(preferred).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!(
+                "[Stud Bridge] No free port from {} ({}), bridge not started",
+                preferred, e
+            );
+            return;
+        }
+    };
+
+    let mut port = local_port(&listener).unwrap_or(preferred);
+    set_bridge_endpoint(port);
+    if port != preferred {
+        println!("[Stud Bridge] Port {} in use, using {} instead", preferred, port);
+    }
+    println!("[Stud Bridge] Starting on http://localhost:{}", port);
+    println!("[Stud Bridge] Waiting for stud-bridge plugin to connect...");
+
+    let mut shutdown = serve_bridge(listener, state.clone());
+    tokio::spawn(crate::local_socket::serve());
+    tokio::spawn(crate::queue_journal::recover());
+
+    let (control_tx, mut control_rx) = mpsc::unbounded_channel();
+    *bridge.control.lock() = Some(control_tx);
+
+    loop {
+        let command = tokio::select! {
+            command = control_rx.recv() => command,
+            _ = shutdown_signal() => None,
+        };
+        let Some(command) = command else {
+            break;
+        };
+        match command {
+            BridgeCommand::Rebind { port: requested, reply } => {
+                if requested == port {
+                    let _ = reply.send(Ok(port));
+                    continue;
+                }
+
+                // Bring the new listener up before touching the old one
+                let listener = match bind_with_fallback(requested).await {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        let _ = reply.send(Err(format!(
+                            "Could not bind bridge port {}: {}",
+                            requested, e
+                        )));
+                        continue;
+                    }
+                };
+
+                let new_port = local_port(&listener).unwrap_or(requested);
+                let new_shutdown = serve_bridge(listener, state.clone());
+                state.lock().port_hint = Some(new_port);
+                set_bridge_endpoint(new_port);
+                println!(
+                    "[Stud Bridge] Moved to http://localhost:{}, keeping {} as standby for {}s",
+                    new_port, port, STANDBY_GRACE_SECS
+                );
+
+                // Keep the old port answering (with the hint) until the plugin has moved over
+                let old_shutdown = std::mem::replace(&mut shutdown, new_shutdown);
+                let old_port = port;
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_secs(STANDBY_GRACE_SECS)).await;
+                    let _ = old_shutdown.send(());
+                    println!("[Stud Bridge] Standby listener on {} closed", old_port);
+                });
+
+                port = new_port;
+                let _ = reply.send(Ok(new_port));
+            }
+            BridgeCommand::Restart { reply } => {
+                // Stops accepting immediately; open connections finish in the background
+                let old_shutdown = std::mem::replace(&mut shutdown, oneshot::channel().0);
+                let _ = old_shutdown.send(());
+                let cancelled = state.lock().reset();
+                // Let the old server drop its listener so the port can be reused
+                tokio::time::sleep(Duration::from_millis(RESTART_SETTLE_MS)).await;
+
+                let preferred = config::current().bridge.resolved().port;
+                let listener = match bind_with_fallback(preferred).await {
+                    Ok(listener) => listener,
+                    Err(e) => {
+                        println!("[Stud Bridge] Restart failed, no free port from {}: {}", preferred, e);
+                        update_endpoints(|endpoints| endpoints.bridge_port = None);
+                        remove_discovery_file();
+                        let _ = reply.send(Err(format!(
+                            "Could not bind bridge port {}: {}",
+                            preferred, e
+                        )));
+                        continue;
+                    }
+                };
+
+                port = local_port(&listener).unwrap_or(preferred);
+                shutdown = serve_bridge(listener, state.clone());
+                set_bridge_endpoint(port);
+                println!(
+                    "[Stud Bridge] Restarted on http://localhost:{} ({} queued request(s) cancelled)",
+                    port, cancelled
+                );
+                let _ = reply.send(Ok(BridgeRestart { port, cancelled }));
+            }
+        }
+    }
+
+    // App is exiting: refuse new control commands, fail queued work, stop listening
+    *bridge.control.lock() = None;
+    let failed = state.lock().fail_pending("Stud is shutting down");
+    let _ = shutdown.send(());
+    remove_discovery_file();
+    println!(
+        "[Stud Bridge] Shutting down ({} queued request(s) failed)",
+        failed
+    );
+    tokio::time::sleep(Duration::from_millis(SHUTDOWN_FLUSH_MS)).await;
+}
+
+/// Why a queued request never got a response
+#[derive(Debug, Clone, Copy)]
+enum DispatchError {
+    Cancelled,
+    TimedOut,
+    DuplicateId,
+    IdempotencyKeyReused,
+    QueueFull,
+    ReadOnly,
+    PayloadTooLarge,
+    OutsideSandbox,
+}
+
+impl DispatchError {
+    fn message(&self) -> &'static str {
+        match self {
+            DispatchError::Cancelled => "Request cancelled",
+            DispatchError::TimedOut => "Request timed out waiting for Studio response",
+            DispatchError::DuplicateId => "A request with this id is already pending",
+            DispatchError::IdempotencyKeyReused => {
+                "Idempotency-Key was already used for a different request"
+            }
+            DispatchError::QueueFull => "Bridge is busy: too many requests waiting for Studio",
+            DispatchError::ReadOnly => {
+                "This place is read-only in Stud; change its place profile to allow edits"
+            }
+            DispatchError::PayloadTooLarge => "Request is larger than this tool's sandbox profile allows",
+            DispatchError::OutsideSandbox => {
+                "Request touches instances outside this tool's sandbox profile"
+            }
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            DispatchError::Cancelled => StatusCode::INTERNAL_SERVER_ERROR,
+            DispatchError::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+            DispatchError::DuplicateId => StatusCode::CONFLICT,
+            DispatchError::IdempotencyKeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+            DispatchError::QueueFull => StatusCode::TOO_MANY_REQUESTS,
+            DispatchError::ReadOnly => StatusCode::FORBIDDEN,
+            DispatchError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            DispatchError::OutsideSandbox => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+/// Queue a request for the plugin and wait for its response, inside a span
+/// carrying its trace id
+async fn dispatch(
+    state: &SharedState,
+    mut request: StudioRequest,
+) -> Result<StudioResponse, DispatchError> {
+    let trace_id = request.trace_id.get_or_insert_with(traces::new_id).clone();
+    let span = tracing::info_span!(
+        target: crate::bridge_log::TARGET,
+        "bridge_request",
+        trace_id = %trace_id,
+        path = %request.path,
+    );
+    dispatch_traced(state, request).instrument(span).await
+}
+
+async fn dispatch_traced(
+    state: &SharedState,
+    mut request: StudioRequest,
+) -> Result<StudioResponse, DispatchError> {
+    let (sender, receiver) = oneshot::channel();
+    let trace_id = request.trace_id.clone().unwrap_or_default();
+    let path = request.path.clone();
+    let profile = crate::sandbox::profile_for(&path);
+    if let Some(profile) = &profile {
+        if let Err(violation) = crate::sandbox::check(&request, profile) {
+            let (error, reason) = match violation {
+                crate::sandbox::Violation::PayloadTooLarge(reason) => (DispatchError::PayloadTooLarge, reason),
+                crate::sandbox::Violation::OutsideSandbox(reason) => (DispatchError::OutsideSandbox, reason),
+            };
+            println!("[Stud Bridge] Refused by sandbox profile: {}", reason);
+            state.lock().metrics.record_rejection(&path);
+            traces::mark(&trace_id, &path, Stage::Rejected, Some(reason));
+            return Err(error);
+        }
+    }
+    request.allowed_paths = profile
+        .as_ref()
+        .map(|profile| profile.allowed_paths.clone())
+        .filter(|paths| !paths.is_empty());
+    let timeout = crate::sandbox::timeout_for(profile.as_ref(), Duration::from_secs(REQUEST_TIMEOUT_SECS));
+    let started = Instant::now();
+    let recorded = crate::inspector::enabled().then(|| request.clone());
+    let journaled = crate::queue_journal::enabled().then(|| request.clone());
+    let reviewed = crate::patches::is_recorded(&request).then(|| request.clone());
+    let audited = crate::audit::describe(&request);
+    let max_pending = config::current().bridge.max_pending_requests;
 
     let id = {
         let mut state = state.lock();
         state.cleanup_stale();
-        let id = state.generate_id();
+        // A runaway tool loop shouldn't be able to grow the queue without bound
+        if state.pending_requests.len() >= max_pending {
+            state.metrics.record_rejection(&path);
+            traces::mark(&trace_id, &path, Stage::Rejected, Some("queue full".to_string()));
+            return Err(DispatchError::QueueFull);
+        }
+        // Untargeted requests can land on any session, so any read-only place refuses them
+        let read_only = state
+            .sessions
+            .iter()
+            .filter(|(id, session)| match &request.target_session {
+                Some(target) => *id == target,
+                None => session.is_connected(),
+            })
+            .filter_map(|(_, session)| session.place_id)
+            .any(|place_id| profiles::blocks(place_id, &path));
+        if read_only {
+            traces::mark(&trace_id, &path, Stage::Rejected, Some("read-only place".to_string()));
+            return Err(DispatchError::ReadOnly);
+        }
+        let id = match &request.id {
+            Some(id) if state.pending_requests.contains_key(id) => {
+                return Err(DispatchError::DuplicateId)
+            }
+            Some(id) => id.clone(),
+            None => state.generate_id(),
+        };
+        state.metrics.record_request(&path);
+        traces::set_request_id(&trace_id, &id);
+        traces::mark(&trace_id, &path, Stage::Queued, None);
+        tracing::info!(
+            target: crate::bridge_log::TARGET,
+            event = "request",
+            id = %id,
+            path = %path,
+            session = request.target_session.as_deref().unwrap_or("any"),
+            chat_id = request.chat_id.as_deref(),
+            turn_id = request.turn_id.as_deref(),
+            priority = ?request.priority,
+            body_bytes = request.body.as_ref().map_or(0, String::len),
+        );
         state.pending_requests.insert(
             id.clone(),
             PendingRequest {
-                request: body,
+                request,
                 sender,
                 timestamp: Instant::now(),
+                timeout,
+                leased_until: None,
+                deliveries: 0,
+                nacks: 0,
             },
         );
+        state.last_request_time = Some(Instant::now());
+        state.request_notify.notify_waiters();
         id
     };
+    if let Some(request) = &journaled {
+        crate::queue_journal::record(&id, request);
+    }
 
     // Wait for response with timeout
-    match tokio::time::timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS), receiver).await {
+    let result = tokio::time::timeout(timeout, receiver)
+        .await
+        .map(|received| received.map(crate::uploads::resolve));
+    let mut state = state.lock();
+    let elapsed = started.elapsed();
+    let duration_ms = elapsed.as_millis() as u64;
+    let outcome = match result {
         Ok(Ok(response)) => {
-            Ok(warp::reply::with_status(
-                warp::reply::json(&serde_json::from_str::<serde_json::Value>(&response.body).unwrap_or(serde_json::json!({"raw": response.body}))),
-                warp::http::StatusCode::from_u16(response.status).unwrap_or(warp::http::StatusCode::OK),
-            ))
+            state
+                .metrics
+                .record_outcome(&path, Outcome::Completed, elapsed.as_secs_f64());
+            tracing::info!(
+                target: crate::bridge_log::TARGET,
+                event = "response",
+                id = %id,
+                path = %path,
+                status = response.status,
+                duration_ms,
+                body_bytes = response.body.len(),
+                content_type = response.content_type.as_deref(),
+            );
+            traces::mark(&trace_id, &path, Stage::Completed, Some(format!("status {}", response.status)));
+            Ok(response)
         }
         Ok(Err(_)) => {
             // Channel closed
-            state.lock().pending_requests.remove(&id);
-            Ok(warp::reply::with_status(
-                warp::reply::json(&serde_json::json!({"error": "Request cancelled"})),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
+            state.pending_requests.shift_remove(&id);
+            state
+                .metrics
+                .record_outcome(&path, Outcome::Cancelled, elapsed.as_secs_f64());
+            tracing::info!(target: crate::bridge_log::TARGET, event = "cancelled", id = %id, path = %path, duration_ms);
+            traces::mark(&trace_id, &path, Stage::Cancelled, None);
+            Err(DispatchError::Cancelled)
         }
         Err(_) => {
-            // Timeout
-            state.lock().pending_requests.remove(&id);
-            Ok(warp::reply::with_status(
-                warp::reply::json(&serde_json::json!({"error": "Request timed out waiting for Studio response"})),
-                warp::http::StatusCode::GATEWAY_TIMEOUT,
-            ))
-        }
-    }
-}
-
-/// OAuth callback server for ChatGPT Plus/Pro authentication
-async fn start_oauth_server() {
-    // OAuth callback endpoint - stores auth code in memory for frontend to poll
-    let callback = warp::path!("auth" / "callback")
-        .and(warp::get())
-        .and(warp::query::<std::collections::HashMap<String, String>>())
-        .map(|params: std::collections::HashMap<String, String>| {
-            let code = params.get("code").cloned().unwrap_or_default();
-            let state = params.get("state").cloned().unwrap_or_default();
-            let error = params.get("error").cloned();
-            
-            if let Some(err) = error {
-                // OAuth error - show error page
-                let html = format!(r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>Authentication Failed</title>
-    <style>
-        body {{ font-family: system-ui, sans-serif; display: flex; justify-content: center; align-items: center; height: 100vh; margin: 0; background: #fafafa; }}
-        .card {{ background: white; padding: 2rem; border-radius: 1rem; box-shadow: 0 4px 20px rgba(0,0,0,0.1); text-align: center; max-width: 400px; }}
-        h1 {{ color: #ef4444; margin-bottom: 0.5rem; }}
-        p {{ color: #666; }}
-    </style>
-</head>
-<body>
-    <div class="card">
-        <h1>Authentication Failed</h1>
-        <p>{}</p>
-        <p>You can close this window and try again.</p>
-    </div>
-</body>
-</html>"#, err);
-                warp::reply::html(html)
-            } else {
-                // Store the callback data in memory for the frontend to poll
-                let callback_data = OAuthCallbackData {
-                    code: code.clone(),
-                    state: state.clone(),
-                    timestamp: chrono_lite_timestamp(),
-                };
-                *OAUTH_CALLBACK_DATA.lock() = Some(callback_data);
-                
-                // Success - show checkmark and success message
-                let html = r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>Authentication Successful</title>
-    <style>
-        body { font-family: system-ui, sans-serif; display: flex; justify-content: center; align-items: center; height: 100vh; margin: 0; background: #fafafa; }
-        .card { background: white; padding: 2rem; border-radius: 1rem; box-shadow: 0 4px 20px rgba(0,0,0,0.1); text-align: center; max-width: 400px; }
-        h1 { color: #22c55e; margin-bottom: 0.5rem; }
-        p { color: #666; }
-        .checkmark { width: 56px; height: 56px; margin: 1rem auto; }
-        .checkmark circle { fill: #22c55e; }
-        .checkmark path { stroke: white; stroke-width: 3; fill: none; stroke-linecap: round; stroke-linejoin: round; }
-    </style>
-</head>
-<body>
-    <div class="card">
-        <h1>Authentication Successful!</h1>
-        <svg class="checkmark" viewBox="0 0 56 56">
-            <circle cx="28" cy="28" r="28"/>
-            <path d="M16 28 L24 36 L40 20"/>
-        </svg>
-        <p>Sign-in complete.</p>
-        <p>You can close this window now.</p>
-    </div>
-</body>
-</html>"#;
-                warp::reply::html(html.to_string())
+            state.pending_requests.shift_remove(&id);
+            state
+                .metrics
+                .record_outcome(&path, Outcome::TimedOut, elapsed.as_secs_f64());
+            tracing::info!(target: crate::bridge_log::TARGET, event = "timeout", id = %id, path = %path, duration_ms);
+            traces::mark(&trace_id, &path, Stage::TimedOut, None);
+            Err(DispatchError::TimedOut)
+        }
+    };
+    drop(state);
+    // Requests failed because the app is exiting stay journaled for the next launch
+    if journaled.is_some() && !*SHUTDOWN.borrow() {
+        crate::queue_journal::remove(&id);
+    }
+    if let Ok(response) = &outcome {
+        if (200..300).contains(&response.status) {
+            if let Some(request) = &reviewed {
+                crate::patches::record_request(request);
             }
-        });
-    
-    // Poll endpoint - frontend polls this to get the OAuth callback data
-    let poll = warp::path!("auth" / "poll")
-        .and(warp::get())
-        .map(|| {
-            let data = OAUTH_CALLBACK_DATA.lock();
-            if let Some(ref callback_data) = *data {
-                warp::reply::json(&serde_json::json!({
-                    "pending": true,
-                    "code": callback_data.code,
-                    "state": callback_data.state
-                }))
-            } else {
-                warp::reply::json(&serde_json::json!({
-                    "pending": false
-                }))
+            if let Some(entry) = audited {
+                crate::audit::record(entry);
             }
-        });
-    
-    // Clear endpoint - frontend calls this after successfully processing the callback
-    let clear = warp::path!("auth" / "clear")
-        .and(warp::post())
-        .map(|| {
-            *OAUTH_CALLBACK_DATA.lock() = None;
-            warp::reply::json(&serde_json::json!({ "ok": true }))
-        });
+        }
+    }
+    if let Some(request) = recorded {
+        let result = outcome.as_ref().map_err(DispatchError::message);
+        crate::inspector::record(&id, request, result, duration_ms);
+    }
+    outcome
+}
 
-    let oauth_routes = callback.or(poll).or(clear).with(cors());
+/// Send a recorded request again; the inspector records the outcome
+pub(crate) async fn replay_request(request: StudioRequest) {
+    let _ = dispatch(&BRIDGE.state, request).await;
+}
 
-    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, OAUTH_PORT));
-    match tokio::net::TcpListener::bind(addr).await {
-        Ok(listener) => {
-            println!("[Stud OAuth] Callback server on http://localhost:{}", OAUTH_PORT);
-            warp::serve(oauth_routes)
-                .run_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
-                .await;
-        }
-        Err(e) => {
-            println!("[Stud OAuth] Port {} already in use ({})", OAUTH_PORT, e);
+/// Queue a request left over from the last run, keeping its id
+pub(crate) async fn redeliver(request: StudioRequest) -> Result<StudioResponse, &'static str> {
+    dispatch(&BRIDGE.state, request).await.map_err(|e| e.message())
+}
+
+/// Whether any Studio session is polling the app's bridge
+pub(crate) fn is_studio_connected() -> bool {
+    BRIDGE.state.lock().is_connected()
+}
+
+/// Dispatch at most once per Idempotency-Key. Retries while the first attempt
+/// is running wait for it; retries after it finishes get the same outcome.
+/// Returns the outcome and whether it was replayed rather than freshly run.
+async fn dispatch_idempotent(
+    state: &SharedState,
+    key: String,
+    request: StudioRequest,
+) -> (Result<StudioResponse, DispatchError>, bool) {
+    let fingerprint = format!("{}\n{}", request.path, request.body.as_deref().unwrap_or_default());
+    let timeout = crate::sandbox::timeout_for(
+        crate::sandbox::profile_for(&request.path).as_ref(),
+        Duration::from_secs(REQUEST_TIMEOUT_SECS),
+    );
+    let (outcome, replayed) = {
+        let mut locked = state.lock();
+        locked.cleanup_stale();
+        match locked.idempotency.get(&key) {
+            Some(entry) if entry.fingerprint != fingerprint => {
+                return (Err(DispatchError::IdempotencyKeyReused), false);
+            }
+            Some(entry) => (entry.outcome.clone(), true),
+            None => {
+                // Detached, so a caller hanging up doesn't drop the attempt (the
+                // plugin would still run it) and leave the next retry to send it again
+                let task_state = state.clone();
+                let task_key = key.clone();
+                let task = tokio::spawn(async move {
+                    let outcome = dispatch(&task_state, request).await;
+                    // Not kept: the queue may have drained by the time the caller retries
+                    if let Err(DispatchError::QueueFull) = outcome {
+                        task_state.lock().idempotency.remove(&task_key);
+                    }
+                    outcome
+                });
+                let outcome = async move { task.await.unwrap_or(Err(DispatchError::Cancelled)) }
+                    .boxed()
+                    .shared();
+                locked.idempotency.insert(
+                    key,
+                    IdempotentRequest {
+                        fingerprint,
+                        outcome: outcome.clone(),
+                        created: Instant::now(),
+                        timeout,
+                    },
+                );
+                (outcome, false)
+            }
         }
+    };
+    (outcome.await, replayed)
+}
+
+fn parse_response_body(body: &str) -> serde_json::Value {
+    serde_json::from_str(body).unwrap_or_else(|_| serde_json::json!({ "raw": body }))
+}
+
+async fn handle_request(
+    State(app): State<AppState>,
+    headers: HeaderMap,
+    LimitedJsonBody(mut body): LimitedJsonBody<StudioRequest>,
+) -> Response {
+    let state = app.bridge;
+    // Callers can pass their own trace id to correlate with their logs
+    let trace_id = body
+        .trace_id
+        .clone()
+        .or_else(|| header_str(&headers, TRACE_HEADER).map(str::to_string))
+        .filter(|trace_id| !trace_id.trim().is_empty())
+        .unwrap_or_else(traces::new_id);
+    body.trace_id = Some(trace_id.clone());
+    traces::mark(&trace_id, &body.path, Stage::Received, None);
+
+    let mut reply = handle_traced_request(state, &headers, body).await;
+    if let Ok(value) = HeaderValue::from_str(&trace_id) {
+        reply.headers_mut().insert(TRACE_HEADER, value);
     }
+    reply
 }
 
-const CODEX_PROXY_PORT: u16 = 3002;
-const CODEX_API_ENDPOINT: &str = "https://chatgpt.com/backend-api/codex/responses";
+async fn handle_traced_request(state: SharedState, headers: &HeaderMap, body: StudioRequest) -> Response {
+    if let Some(target) = &body.target_session {
+        if !state.lock().sessions.contains_key(target) && !is_probe_session(Some(target)) {
+            traces::mark_request(&body, Stage::Rejected, Some("session not found".to_string()));
+            return json_status(
+                StatusCode::NOT_FOUND,
+                serde_json::json!({
+                    "error": format!("Studio session not found: {}", target)
+                }),
+            );
+        }
+    }
 
-/// Codex API proxy - bypasses CORS by proxying requests through the Rust backend
-async fn start_codex_proxy() {
-    let client = reqwest::Client::new();
-
-    // Proxy endpoint for Codex API calls with streaming support
-    let proxy = warp::path!("codex" / "responses")
-        .and(warp::post())
-        .and(warp::header::optional::<String>("authorization"))
-        .and(warp::header::optional::<String>("chatgpt-account-id"))
-        .and(warp::body::bytes())
-        .and_then(move |auth: Option<String>, account_id: Option<String>, body: Bytes| {
-            let client = client.clone();
-            async move {
-                // Build the request to Codex API
-                let mut req = client
-                    .post(CODEX_API_ENDPOINT)
-                    .header("Content-Type", "application/json")
-                    .body(body.to_vec());
-
-                // Forward authorization header
-                if let Some(auth_header) = auth {
-                    req = req.header("Authorization", auth_header);
-                }
+    // Changes that break an `error` naming or attribute rule never reach Studio
+    let (naming_errors, naming_warnings): (Vec<_>, Vec<_>) = crate::naming::review_request(&body)
+        .into_iter()
+        .partition(|violation| violation.severity == crate::naming::Severity::Error);
+    let (attribute_errors, attribute_warnings): (Vec<_>, Vec<_>) =
+        crate::attributes::review_request(&body)
+            .await
+            .into_iter()
+            .partition(|violation| violation.severity == crate::naming::Severity::Error);
+    if !naming_errors.is_empty() || !attribute_errors.is_empty() {
+        println!(
+            "[Stud Bridge] Blocked {}: {} naming and {} attribute violation(s)",
+            body.path,
+            naming_errors.len(),
+            attribute_errors.len()
+        );
+        traces::mark_request(&body, Stage::Rejected, Some("naming or attribute rules".to_string()));
+        let violations: Vec<serde_json::Value> = naming_errors
+            .iter()
+            .map(|violation| serde_json::json!(violation))
+            .chain(attribute_errors.iter().map(|violation| serde_json::json!(violation)))
+            .collect();
+        return json_status(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            serde_json::json!({
+                "error": "Proposed change breaks the project's naming or attribute rules",
+                "violations": violations,
+            }),
+        );
+    }
+    for warning in &naming_warnings {
+        println!("[Stud Bridge] Naming warning at {}: {}", warning.path, warning.message);
+    }
+    for warning in &attribute_warnings {
+        println!("[Stud Bridge] Attribute warning at {}: {}", warning.path, warning.message);
+    }
+    // Stop runaway agent loops before they reach Studio
+    if let Err(refused) = crate::guardrails::check(&body) {
+        println!("[Stud Bridge] Guardrail stopped {}: {}", body.path, refused.error);
+        traces::mark_request(&body, Stage::Rejected, Some(format!("guardrail {:?}", refused.guardrail)));
+        return json_status(StatusCode::FORBIDDEN, serde_json::json!(refused));
+    }
+    traces::mark_request(&body, Stage::Checked, None);
 
-                // Forward ChatGPT Account ID if present
-                if let Some(acc_id) = account_id {
-                    req = req.header("ChatGPT-Account-Id", acc_id);
+    let idempotency_key = header_str(headers, "idempotency-key").filter(|key| !key.trim().is_empty());
+    let (outcome, replayed) = match idempotency_key {
+        Some(key) => dispatch_idempotent(&state, key.to_string(), body).await,
+        None => (dispatch(&state, body).await, false),
+    };
+    let mut reply = match outcome {
+        Ok(response) => {
+            let status = StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK);
+            // Binary responses go back to the caller as raw bytes with their content type
+            let binary = response
+                .content_type
+                .as_deref()
+                .and_then(|content_type| {
+                    let data = BASE64.decode(response.body.as_bytes()).ok()?;
+                    Some((content_type, data))
+                });
+            match binary {
+                Some((content_type, data)) => {
+                    (status, [(header::CONTENT_TYPE, content_type)], data).into_response()
                 }
-
-                // Execute request and stream response back
-                match req.send().await {
-                    Ok(response) => {
-                        let status = response.status();
-
-                        if !status.is_success() {
-                            let error_body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-                            let res = warp::http::Response::builder()
-                                .status(warp::http::StatusCode::from_u16(status.as_u16())
-                                    .unwrap_or(warp::http::StatusCode::INTERNAL_SERVER_ERROR))
-                                .header("Content-Type", "text/plain")
-                                .body(warp::hyper::Body::from(error_body))
-                                .unwrap();
-                            return Ok::<_, warp::Rejection>(res);
+                None => {
+                    let mut value = parse_response_body(&response.body);
+                    if let Some(object) = value.as_object_mut() {
+                        if !naming_warnings.is_empty() {
+                            object.insert(
+                                "naming_warnings".to_string(),
+                                serde_json::json!(naming_warnings),
+                            );
                         }
+                        if !attribute_warnings.is_empty() {
+                            object.insert(
+                                "attribute_warnings".to_string(),
+                                serde_json::json!(attribute_warnings),
+                            );
+                        }
+                    }
+                    (status, json_reply(&value, header_str(headers, "accept-encoding")))
+                        .into_response()
+                }
+            }
+        }
+        Err(DispatchError::QueueFull) => (
+            DispatchError::QueueFull.status(),
+            [(header::RETRY_AFTER, QUEUE_FULL_RETRY_SECS.to_string())],
+            Json(serde_json::json!({
+                "error": DispatchError::QueueFull.message(),
+                "retry_after_secs": QUEUE_FULL_RETRY_SECS,
+            })),
+        )
+            .into_response(),
+        Err(e) => json_status(e.status(), serde_json::json!({ "error": e.message() })),
+    };
+    if replayed {
+        reply
+            .headers_mut()
+            .insert("Idempotent-Replayed", HeaderValue::from_static("true"));
+    }
+    reply
+}
 
-                        // Stream the response body for SSE support
-                        let stream = response.bytes_stream().map(|result| {
-                            result.map(|bytes| bytes.to_vec())
-                                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
-                        });
+/// Send a request to Studio from the backend and return the plugin's JSON result.
+/// `session` targets a specific Studio instance; any connected one is used otherwise.
+pub async fn studio_request(
+    session: Option<&str>,
+    path: &str,
+    body: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    match studio_request_binary(session, path, body, None).await? {
+        StudioPayload::Json(value) => Ok(value),
+        StudioPayload::Binary { content_type, .. } => Err(format!(
+            "Studio returned binary data ({}) where JSON was expected",
+            content_type
+        )),
+    }
+}
 
-                        let body = warp::hyper::Body::wrap_stream(stream);
+/// Like `studio_request`, but queued behind interactive work. For heavy
+/// background requests such as full-tree dumps.
+pub async fn studio_request_background(
+    session: Option<&str>,
+    path: &str,
+    body: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    match send_studio_request(session, path, body, None, Priority::Low).await? {
+        StudioPayload::Json(value) => Ok(value),
+        StudioPayload::Binary { content_type, .. } => Err(format!(
+            "Studio returned binary data ({}) where JSON was expected",
+            content_type
+        )),
+    }
+}
 
-                        let res = warp::http::Response::builder()
-                            .status(warp::http::StatusCode::OK)
-                            .header("Content-Type", "text/event-stream")
-                            .body(body)
-                            .unwrap();
-                        Ok(res)
-                    }
-                    Err(e) => {
-                        let res = warp::http::Response::builder()
-                            .status(warp::http::StatusCode::BAD_GATEWAY)
-                            .header("Content-Type", "text/plain")
-                            .body(warp::hyper::Body::from(format!("Proxy error: {}", e)))
-                            .unwrap();
-                        Ok(res)
+/// Like `studio_request`, but can send bytes along with the body (as
+/// `(content_type, data)`) and accepts binary responses.
+pub async fn studio_request_binary(
+    session: Option<&str>,
+    path: &str,
+    body: serde_json::Value,
+    attachment: Option<(&str, &[u8])>,
+) -> Result<StudioPayload, String> {
+    send_studio_request(session, path, body, attachment, Priority::Normal).await
+}
+
+async fn send_studio_request(
+    session: Option<&str>,
+    path: &str,
+    body: serde_json::Value,
+    attachment: Option<(&str, &[u8])>,
+    priority: Priority,
+) -> Result<StudioPayload, String> {
+    {
+        let state = BRIDGE.state.lock();
+        let connected = match session {
+            Some(id) => state.sessions.get(id).is_some_and(|s| s.is_connected()),
+            None => state.is_connected(),
+        };
+        if !connected {
+            return Err(match session {
+                Some(id) => format!("Studio session {} is not connected", id),
+                None => "Roblox Studio is not connected".to_string(),
+            });
+        }
+    }
+
+    let request = StudioRequest {
+        id: None,
+        path: path.to_string(),
+        body: Some(body.to_string()),
+        target_session: session.map(str::to_string),
+        attachment: attachment.map(|(content_type, data)| Attachment {
+            content_type: content_type.to_string(),
+            data: BASE64.encode(data),
+        }),
+        priority,
+        tool_call_id: None,
+        chat_id: None,
+        turn_id: None,
+        trace_id: None,
+        allowed_paths: None,
+    };
+    let response = dispatch(&BRIDGE.state, request)
+        .await
+        .map_err(|e| e.message().to_string())?;
+
+    if let (Some(content_type), true) = (response.content_type, response.status < 400) {
+        let data = BASE64
+            .decode(response.body.as_bytes())
+            .map_err(|e| format!("Studio sent invalid binary data: {}", e))?;
+        return Ok(StudioPayload::Binary { content_type, data });
+    }
+
+    let value = parse_response_body(&response.body);
+    if response.status >= 400 {
+        let error = value
+            .get("error")
+            .and_then(|e| e.as_str())
+            .unwrap_or("Studio request failed");
+        return Err(format!("{} ({})", error, response.status));
+    }
+
+    Ok(StudioPayload::Json(value))
+}
+
+/// Payload of the studio-connected / studio-disconnected events
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionEvent {
+    pub connected: bool,
+    pub sessions: Vec<StudioSession>,
+}
+
+pub fn set_app_handle(handle: tauri::AppHandle) {
+    *APP_HANDLE.write() = Some(handle);
+}
+
+/// Emit studio-connected / studio-disconnected when the plugin's presence changes
+fn publish_connection_state(state: &SharedState) {
+    let event = {
+        let mut state = state.lock();
+        let connected = state.is_connected();
+        if connected == state.announced_connected {
+            return;
+        }
+        state.announced_connected = connected;
+        ConnectionEvent {
+            connected,
+            sessions: state.list_sessions(),
+        }
+    };
+
+    println!(
+        "[Stud Bridge] Studio {}",
+        if event.connected { "connected" } else { "disconnected" }
+    );
+    let name = if event.connected { CONNECTED_EVENT } else { DISCONNECTED_EVENT };
+    emit_event(name, event);
+}
+
+/// Ask the bridge, OAuth and Codex proxy servers to stop
+pub fn request_shutdown() {
+    SHUTDOWN.send_replace(true);
+}
+
+/// Resolves once the app has started shutting down
+pub(crate) async fn shutdown_signal() {
+    let mut receiver = SHUTDOWN.subscribe();
+    let _ = receiver.wait_for(|stopping| *stopping).await;
+}
+
+/// Send an event to the frontend, if the app is up
+pub(crate) fn emit_event<S: Serialize + Clone>(name: &str, payload: S) {
+    if let Some(app) = APP_HANDLE.read().as_ref() {
+        if let Err(e) = app.emit(name, payload) {
+            println!("[Stud Bridge] Failed to emit {}: {}", name, e);
+        }
+    }
+}
+
+/// Keeps the long-poll counts accurate even if the plugin hangs up mid-wait
+struct LongPollGuard {
+    state: SharedState,
+    session: Option<String>,
+}
+
+impl LongPollGuard {
+    fn new(state: SharedState, session: Option<String>) -> Self {
+        {
+            let mut state = state.lock();
+            state.active_long_polls += 1;
+            if let Some(session) = session.as_ref().and_then(|id| state.sessions.get_mut(id)) {
+                session.active_long_polls += 1;
+            }
+        }
+        Self { state, session }
+    }
+}
+
+impl Drop for LongPollGuard {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        state.active_long_polls = state.active_long_polls.saturating_sub(1);
+        state.last_poll_time = Instant::now();
+        if let Some(session) = self.session.as_ref().and_then(|id| state.sessions.get_mut(id)) {
+            session.active_long_polls = session.active_long_polls.saturating_sub(1);
+            session.last_seen = Instant::now();
+        }
+    }
+}
+
+async fn handle_poll(
+    State(app): State<AppState>,
+    Query(mut query): Query<PollQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let AppState {
+        bridge: state,
+        protocol_version,
+    } = app;
+    let accept_encoding = header_str(&headers, "accept-encoding");
+    if protocol_version >= 2 {
+        // v2 always answers with the `requests` list
+        query.max.get_or_insert(1);
+    }
+    let wait = Duration::from_secs(query.wait.unwrap_or(0).min(MAX_LONG_POLL_SECS));
+    let deadline = tokio::time::Instant::now() + wait;
+    let (notify, session) = {
+        let mut state = state.lock();
+        state.metrics.record_poll();
+        (state.request_notify.clone(), state.touch(&query, protocol_version))
+    };
+    publish_connection_state(&state);
+    // A probe's long poll mustn't make Studio look connected
+    let _guard = (!wait.is_zero() && !is_probe_session(session.as_deref()))
+        .then(|| LongPollGuard::new(state.clone(), session.clone()));
+
+    loop {
+        // Register for wakeups before checking the queue so no insert is missed
+        let notified = notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        {
+            let mut state = state.lock();
+            if let Some(mut response) = state.next_poll_response(session.as_deref(), query.max, protocol_version) {
+                state.apply_poll_hints(&mut response, session.as_deref());
+                return json_reply(&response, accept_encoding);
+            }
+        }
+
+        // Also wake when a leased request can be delivered again
+        let wake = state
+            .lock()
+            .next_lease_expiry(session.as_deref())
+            .map_or(deadline, |at| deadline.min(at.into()));
+        if tokio::time::timeout_at(wake, notified).await.is_err() {
+            if tokio::time::Instant::now() < deadline {
+                continue;
+            }
+            let mut state = state.lock();
+            let mut response = state.empty_poll_response();
+            state.apply_poll_hints(&mut response, session.as_deref());
+            return json_reply(&response, accept_encoding);
+        }
+    }
+}
+
+/// Push pending requests to a WebSocket-connected plugin and accept its responses.
+/// Uses the same message shapes as /stud/poll and /stud/respond.
+async fn handle_socket(
+    socket: WebSocket,
+    query: PollQuery,
+    state: SharedState,
+    protocol_version: u32,
+) {
+    let (mut tx, mut rx) = socket.split();
+    let notify = state.lock().request_notify.clone();
+    let mut heartbeat = tokio::time::interval(Duration::from_millis(WS_HEARTBEAT_MS));
+
+    println!("[Stud Bridge] Plugin connected over WebSocket");
+    {
+        let mut state = state.lock();
+        state.metrics.record_websocket_connection();
+        state.touch(&query, protocol_version);
+    }
+    publish_connection_state(&state);
+
+    // Greet with an empty poll response so the plugin knows the upgrade worked
+    let hello = state.lock().empty_poll_response();
+    if tx
+        .send(Message::text(serde_json::to_string(&hello).unwrap_or_default()))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    loop {
+        // Register for wakeups before reading the queue so no insert is missed
+        let notified = notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        let outgoing: Vec<PollResponse> = {
+            let mut state = state.lock();
+            // An open socket counts as an active poller
+            let session = state.touch(&query, protocol_version);
+            state.expire_leases();
+            // Leased requests come round again if the plugin doesn't answer in time
+            let now = Instant::now();
+            let mut outgoing: Vec<PollResponse> = state
+                .pending_requests
+                .iter()
+                .filter(|(_, pending)| pending.is_visible(now))
+                .filter(|(_, pending)| is_for_session(&pending.request, session.as_deref()))
+                .map(|(id, pending)| PollResponse {
+                    id: Some(id.clone()),
+                    request: Some(WireRequest::new(&pending.request, protocol_version)),
+                    requests: if protocol_version >= 2 {
+                        vec![PolledRequest {
+                            id: id.clone(),
+                            request: WireRequest::new(&pending.request, protocol_version),
+                        }]
+                    } else {
+                        Vec::new()
+                    },
+                    cancelled: Vec::new(),
+                    port_hint: state.port_hint,
+                    queue_depth: 0,
+                    next_poll_ms: 0,
+                })
+                .collect();
+            // Leased under the same lock that picked them, so a poll running
+            // alongside this socket can't be handed the same requests
+            for id in outgoing.iter().filter_map(|message| message.id.as_deref()) {
+                state.lease(id, "websocket");
+            }
+
+            let cancelled = state.take_cancellations(session.as_deref());
+            if !cancelled.is_empty() {
+                outgoing.insert(
+                    0,
+                    PollResponse {
+                        cancelled,
+                        ..state.empty_poll_response()
+                    },
+                );
+            }
+            outgoing
+        };
+
+        for message in outgoing {
+            let text = serde_json::to_string(&message).unwrap_or_default();
+            if tx.send(Message::text(text)).await.is_err() {
+                println!("[Stud Bridge] WebSocket send failed, plugin will fall back to polling");
+                return;
+            }
+        }
+
+        tokio::select! {
+            _ = &mut notified => {}
+            _ = heartbeat.tick() => {}
+            incoming = rx.next() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<PluginMessage>(text.as_str()) {
+                        Ok(PluginMessage::Respond(body)) => {
+                            state.lock().complete(body);
+                        }
+                        Ok(PluginMessage::Chunk(chunk)) => {
+                            if let Err(e) = state.lock().add_chunk(chunk) {
+                                println!("[Stud Bridge] Dropping response chunk: {}", e);
+                            }
+                        }
+                        Ok(PluginMessage::Nack(body)) => {
+                            state.lock().nack(body);
+                        }
+                        Err(e) => println!("[Stud Bridge] Ignoring malformed WebSocket message: {}", e),
                     }
                 }
-            }
-        });
+                Some(Ok(Message::Close(_))) => break,
+                Some(Ok(_)) => {}
+                _ => break,
+            },
+        }
+    }
+
+    println!("[Stud Bridge] WebSocket connection closed");
+}
 
-    let proxy_routes = proxy.with(cors());
+/// Codex API proxy - bypasses CORS by proxying requests through the Rust backend
+async fn start_codex_proxy() {
+    // Provider endpoints (see llm_proxy), streamed for SSE; they spend the
+    // user's credentials, so callers need the secret here too
+    let proxy_routes =
+        with_local_layers(crate::llm_proxy::routes().route_layer(middleware::from_fn(require_secret)));
 
-    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, CODEX_PROXY_PORT));
-    match tokio::net::TcpListener::bind(addr).await {
+    let preferred = config::current().bridge.resolved().codex_proxy_port;
+    match bind_with_fallback(preferred).await {
         Ok(listener) => {
-            println!("[Stud Codex] Proxy server on http://localhost:{}", CODEX_PROXY_PORT);
-            warp::serve(proxy_routes)
-                .run_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
-                .await;
+            let port = local_port(&listener).unwrap_or(preferred);
+            update_endpoints(|endpoints| {
+                endpoints.codex_proxy_port = Some(port);
+                endpoints.codex_proxy_url = Some(format!("http://localhost:{}", port));
+            });
+            println!("[Stud Codex] Proxy server on http://localhost:{}", port);
+            if let Err(e) = axum::serve(listener, proxy_routes)
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+            {
+                println!("[Stud Codex] Proxy server error: {}", e);
+            }
+            println!("[Stud Codex] Proxy server stopped");
         }
         Err(e) => {
-            println!("[Stud Codex] Port {} already in use ({})", CODEX_PROXY_PORT, e);
+            println!("[Stud Codex] No free port from {} ({})", preferred, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(path: &str) -> StudioRequest {
+        StudioRequest {
+            id: None,
+            path: path.to_string(),
+            body: None,
+            target_session: None,
+            attachment: None,
+            priority: Priority::default(),
+            tool_call_id: None,
+            chat_id: None,
+            turn_id: None,
+            trace_id: None,
+            allowed_paths: None,
+        }
+    }
+
+    /// State with one request waiting on Studio, and where its response arrives
+    fn pending(id: &str) -> (BridgeState, oneshot::Receiver<StudioResponse>) {
+        let mut state = BridgeState::new();
+        let (sender, receiver) = oneshot::channel();
+        state.pending_requests.insert(
+            id.to_string(),
+            PendingRequest {
+                request: request("/test"),
+                sender,
+                timestamp: Instant::now(),
+                timeout: Duration::from_secs(REQUEST_TIMEOUT_SECS),
+                leased_until: None,
+                deliveries: 0,
+                nacks: 0,
+            },
+        );
+        (state, receiver)
+    }
+
+    fn chunk(seq: u32, last: bool, data: &str) -> ResponseChunk {
+        ResponseChunk {
+            id: "req".to_string(),
+            seq,
+            last,
+            status: 200,
+            content_type: None,
+            total: None,
+            data: data.to_string(),
+            trace_id: None,
+        }
+    }
+
+    #[test]
+    fn chunks_out_of_order() {
+        let (mut state, mut receiver) = pending("req");
+        assert_eq!(state.add_chunk(chunk(2, true, "c")), Ok(false));
+        assert_eq!(state.add_chunk(chunk(0, false, "a")), Ok(false));
+        assert_eq!(state.add_chunk(chunk(1, false, "b")), Ok(true));
+        assert_eq!(receiver.try_recv().unwrap().body, "abc");
+        assert!(state.partial_responses.is_empty());
+    }
+
+    #[test]
+    fn duplicate_chunks_count_once() {
+        let (mut state, mut receiver) = pending("req");
+        assert_eq!(state.add_chunk(chunk(0, false, "a")), Ok(false));
+        assert_eq!(state.add_chunk(chunk(0, false, "a")), Ok(false));
+        assert_eq!(state.add_chunk(chunk(1, true, "b")), Ok(true));
+        assert_eq!(receiver.try_recv().unwrap().body, "ab");
+    }
+
+    #[test]
+    fn chunk_past_last_is_refused() {
+        let (mut state, mut receiver) = pending("req");
+        assert_eq!(state.add_chunk(chunk(1, true, "b")), Ok(false));
+        assert!(state.add_chunk(chunk(2, false, "c")).is_err());
+        assert!(state.partial_responses.is_empty());
+        assert!(receiver.try_recv().is_err());
+
+        // A part already past the one that turns out to be last
+        let (mut state, _receiver) = pending("req");
+        assert_eq!(state.add_chunk(chunk(3, false, "d")), Ok(false));
+        assert!(state.add_chunk(chunk(1, true, "b")).is_err());
+
+        // A second last part that disagrees with the first
+        let (mut state, _receiver) = pending("req");
+        assert_eq!(state.add_chunk(chunk(2, true, "c")), Ok(false));
+        assert!(state.add_chunk(chunk(1, true, "b")).is_err());
+    }
+
+    #[test]
+    fn missing_chunk_holds_completion() {
+        let (mut state, mut receiver) = pending("req");
+        assert_eq!(state.add_chunk(chunk(0, false, "a")), Ok(false));
+        assert_eq!(state.add_chunk(chunk(2, true, "c")), Ok(false));
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(state.add_chunk(chunk(1, false, "b")), Ok(true));
+        assert_eq!(receiver.try_recv().unwrap().body, "abc");
+    }
+
+    #[test]
+    fn chunk_for_unknown_request() {
+        let (mut state, _receiver) = pending("other");
+        assert!(state.add_chunk(chunk(0, true, "a")).is_err());
+    }
+
+    /// Wait until the bridge holds `count` requests for Studio, and return their ids
+    async fn queued(state: &SharedState, count: usize) -> Vec<String> {
+        loop {
+            let ids: Vec<String> = state.lock().pending_requests.keys().cloned().collect();
+            if ids.len() >= count {
+                return ids;
+            }
+            tokio::task::yield_now().await;
         }
     }
+
+    fn answer(state: &SharedState, id: &str, body: &str) {
+        let response = StudioResponse {
+            status: 200,
+            body: body.to_string(),
+            content_type: None,
+        };
+        assert!(state.lock().complete(RespondRequest {
+            id: id.to_string(),
+            response: response.into(),
+            trace_id: None,
+        }));
+    }
+
+    /// Send a request with an Idempotency-Key, as a caller that can hang up
+    fn idempotent(
+        state: &SharedState,
+        key: &str,
+        body: &str,
+    ) -> tokio::task::JoinHandle<(Result<StudioResponse, DispatchError>, bool)> {
+        let (state, key) = (state.clone(), key.to_string());
+        let mut request = request("/test");
+        request.body = Some(body.to_string());
+        tokio::spawn(async move { dispatch_idempotent(&state, key, request).await })
+    }
+
+    #[tokio::test]
+    async fn idempotent_retries_share_one_attempt() {
+        let state: SharedState = Arc::new(Mutex::new(BridgeState::new()));
+        let first = idempotent(&state, "key", "{}");
+        let ids = queued(&state, 1).await;
+        // A retry while the first attempt waits on Studio doesn't queue another
+        let retry = idempotent(&state, "key", "{}");
+        tokio::task::yield_now().await;
+        assert_eq!(state.lock().pending_requests.len(), 1);
+
+        answer(&state, &ids[0], "done");
+        let (outcome, replayed) = first.await.unwrap();
+        assert_eq!(outcome.ok().map(|response| response.body).as_deref(), Some("done"));
+        assert!(!replayed);
+        let (outcome, replayed) = retry.await.unwrap();
+        assert_eq!(outcome.ok().map(|response| response.body).as_deref(), Some("done"));
+        assert!(replayed);
+
+        // A retry after it finished gets the stored outcome
+        let (outcome, replayed) = idempotent(&state, "key", "{}").await.unwrap();
+        assert_eq!(outcome.ok().map(|response| response.body).as_deref(), Some("done"));
+        assert!(replayed);
+        assert_eq!(state.lock().pending_requests.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn idempotent_first_attempt_outlives_its_caller() {
+        let state: SharedState = Arc::new(Mutex::new(BridgeState::new()));
+        let first = idempotent(&state, "key", "{}");
+        let ids = queued(&state, 1).await;
+        // The caller hangs up while Studio works on it
+        first.abort();
+        let retry = idempotent(&state, "key", "{}");
+        answer(&state, &ids[0], "done");
+        let (outcome, replayed) = retry.await.unwrap();
+        assert_eq!(outcome.ok().map(|response| response.body).as_deref(), Some("done"));
+        assert!(replayed);
+    }
+
+    #[tokio::test]
+    async fn idempotency_key_reused_for_another_request() {
+        let state: SharedState = Arc::new(Mutex::new(BridgeState::new()));
+        let _first = idempotent(&state, "key", "{}");
+        queued(&state, 1).await;
+        let (outcome, _) = idempotent(&state, "key", "{\"other\": true}").await.unwrap();
+        assert!(matches!(outcome, Err(DispatchError::IdempotencyKeyReused)));
+        assert_eq!(state.lock().pending_requests.len(), 1);
+    }
 }
@@ -0,0 +1,127 @@
+//! Bridge Traffic Log
+//!
+//! Every request the bridge hands to Studio, and how it ended, is written as a
+//! JSON line to a daily log file in the app data folder, so plugin problems can
+//! be debugged after the fact. Old files are deleted once there are more than
+//! a week's worth.
+
+use serde::Serialize;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use tauri_plugin_opener::OpenerExt;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use crate::paths;
+
+/// tracing target for bridge traffic events
+pub const TARGET: &str = "stud::bridge";
+const LOG_DIR: &str = "logs";
+const LOG_PREFIX: &str = "bridge";
+const LOG_SUFFIX: &str = "log";
+const LOG_FILES_KEPT: usize = 7;
+const DEFAULT_ENTRIES: usize = 200;
+const MAX_ENTRIES: usize = 5000;
+
+/// Where the log files live
+pub fn log_dir() -> Result<PathBuf, String> {
+    let dir = paths::app_data_dir()?.join(LOG_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create log folder: {}", e))?;
+    Ok(dir)
+}
+
+/// Start writing bridge traffic to the log files. Failures only disable logging.
+pub fn init() {
+    let appender = log_dir().and_then(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_PREFIX)
+            .filename_suffix(LOG_SUFFIX)
+            .max_log_files(LOG_FILES_KEPT)
+            .build(dir)
+            .map_err(|e| e.to_string())
+    });
+    let appender = match appender {
+        Ok(appender) => appender,
+        Err(e) => {
+            println!("[Stud Log] Traffic logging disabled: {}", e);
+            return;
+        }
+    };
+
+    let layer = tracing_subscriber::fmt::layer()
+        .json()
+        .flatten_event(true)
+        // Events inside a request carry its trace id from the span
+        .with_current_span(true)
+        .with_span_list(false)
+        .with_writer(appender)
+        .with_filter(Targets::new().with_target(TARGET, LevelFilter::INFO));
+    if let Err(e) = tracing_subscriber::registry().with(layer).try_init() {
+        println!("[Stud Log] Traffic logging disabled: {}", e);
+    }
+}
+
+/// Log files, oldest first (the date in the name sorts chronologically)
+fn log_files() -> Result<Vec<PathBuf>, String> {
+    let mut files: Vec<PathBuf> = fs::read_dir(log_dir()?)
+        .map_err(|e| format!("Failed to read log folder: {}", e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(LOG_PREFIX))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[derive(Debug, Serialize)]
+pub struct BridgeLog {
+    /// Newest last
+    pub entries: Vec<serde_json::Value>,
+    pub folder: String,
+}
+
+/// The most recent `limit` traffic entries (default 200)
+#[tauri::command]
+pub fn get_bridge_log(limit: Option<usize>) -> Result<BridgeLog, String> {
+    let limit = limit.unwrap_or(DEFAULT_ENTRIES).clamp(1, MAX_ENTRIES);
+    let mut entries = Vec::new();
+    // Walk back from the newest file until there are enough entries
+    for path in log_files()?.iter().rev() {
+        let file = fs::File::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let mut lines: Vec<serde_json::Value> = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect();
+        let needed = limit - entries.len();
+        let start = lines.len().saturating_sub(needed);
+        lines.drain(..start);
+        lines.append(&mut entries);
+        entries = lines;
+        if entries.len() >= limit {
+            break;
+        }
+    }
+    Ok(BridgeLog {
+        entries,
+        folder: log_dir()?.display().to_string(),
+    })
+}
+
+/// Show the log folder in the system file manager
+#[tauri::command]
+pub fn open_log_folder(app: tauri::AppHandle) -> Result<(), String> {
+    let dir = log_dir()?;
+    app.opener()
+        .open_path(dir.display().to_string(), None::<&str>)
+        .map_err(|e| format!("Failed to open log folder: {}", e))
+}
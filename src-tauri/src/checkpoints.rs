@@ -0,0 +1,371 @@
+//! Script Checkpoints
+//!
+//! Saved copies of script sources to roll back to when AI edits go wrong.
+//! Edit checkpoints hold a script's source from just before the AI overwrote
+//! it. Auto snapshots are taken on a timer while the AI is editing and hold
+//! every script it has touched, so a run of bad edits can be undone in one
+//! step even when each edit on its own looked fine. Both are kept in the app
+//! data folder and pruned to the limits in the config.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::bridge::{self, chrono_lite_timestamp};
+use crate::config;
+use crate::paths;
+
+const CHECKPOINTS_DIR: &str = "checkpoints";
+/// How often the scheduler checks whether a snapshot is due
+const CHECK_INTERVAL_SECS: u64 = 30;
+/// Scripts tracked per session for auto snapshots
+const MAX_TOUCHED_SCRIPTS: usize = 200;
+
+lazy_static::lazy_static! {
+    static ref ACTIVITY: Mutex<Activity> = Mutex::new(Activity::default());
+}
+
+#[derive(Default)]
+struct Activity {
+    /// Scripts the AI has written, per Studio session
+    touched: HashMap<Option<String>, BTreeSet<String>>,
+    /// Set by each write, cleared by each snapshot
+    dirty: bool,
+    last_snapshot: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckpointKind {
+    /// One script, from just before the AI wrote to it
+    Edit,
+    /// Every script touched so far, taken on a timer
+    Auto,
+    /// The sources a restore replaced, so the restore can be undone
+    Backup,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptSnapshot {
+    pub path: String,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub id: String,
+    pub kind: CheckpointKind,
+    pub label: String,
+    pub session: Option<String>,
+    /// Unix ms
+    pub created: u64,
+    pub scripts: Vec<ScriptSnapshot>,
+}
+
+/// A checkpoint without its sources, for listing
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckpointSummary {
+    pub id: String,
+    pub kind: CheckpointKind,
+    pub label: String,
+    pub created: u64,
+    pub paths: Vec<String>,
+}
+
+impl From<&Checkpoint> for CheckpointSummary {
+    fn from(checkpoint: &Checkpoint) -> Self {
+        Self {
+            id: checkpoint.id.clone(),
+            kind: checkpoint.kind,
+            label: checkpoint.label.clone(),
+            created: checkpoint.created,
+            paths: checkpoint
+                .scripts
+                .iter()
+                .map(|script| script.path.clone())
+                .collect(),
+        }
+    }
+}
+
+fn checkpoints_dir() -> Result<PathBuf, String> {
+    let dir = paths::app_data_dir()?.join(CHECKPOINTS_DIR);
+    if !dir.exists() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create checkpoints folder: {}", e))?;
+    }
+    Ok(dir)
+}
+
+/// Every saved checkpoint, newest first
+fn load_all() -> Result<Vec<Checkpoint>, String> {
+    let dir = checkpoints_dir()?;
+    let mut checkpoints: Vec<Checkpoint> = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read checkpoints: {}", e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect();
+    checkpoints.sort_by_key(|checkpoint| std::cmp::Reverse(checkpoint.created));
+    Ok(checkpoints)
+}
+
+/// Enough of a checkpoint file to decide whether it's worth keeping
+#[derive(Debug, Deserialize)]
+pub(crate) struct CheckpointHeader {
+    pub kind: CheckpointKind,
+    pub created: u64,
+}
+
+/// Every checkpoint file with its header, or None for files that can't be read
+pub(crate) fn stored_files() -> Result<Vec<(PathBuf, Option<CheckpointHeader>)>, String> {
+    let dir = checkpoints_dir()?;
+    Ok(fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read checkpoints: {}", e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| {
+            let header = fs::read_to_string(&path)
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok());
+            (path, header)
+        })
+        .collect())
+}
+
+fn load(id: &str) -> Result<Checkpoint, String> {
+    // Ids are generated here; anything else can't name a checkpoint file
+    if uuid::Uuid::parse_str(id).is_err() {
+        return Err(format!("Checkpoint not found: {}", id));
+    }
+    let json = fs::read_to_string(checkpoints_dir()?.join(format!("{}.json", id)))
+        .map_err(|_| format!("Checkpoint not found: {}", id))?;
+    serde_json::from_str(&json).map_err(|e| format!("Checkpoint {} is corrupt: {}", id, e))
+}
+
+/// Drop the oldest checkpoints of a kind beyond the configured limit
+fn prune(kind: CheckpointKind) -> Result<(), String> {
+    let limits = config::current().checkpoints;
+    let keep = match kind {
+        CheckpointKind::Edit | CheckpointKind::Backup => limits.max_edit_checkpoints,
+        CheckpointKind::Auto => limits.max_auto_snapshots,
+    };
+    let dir = checkpoints_dir()?;
+    for checkpoint in load_all()?
+        .iter()
+        .filter(|checkpoint| checkpoint.kind == kind)
+        .skip(keep)
+    {
+        let _ = fs::remove_file(dir.join(format!("{}.json", checkpoint.id)));
+    }
+    Ok(())
+}
+
+fn save(
+    kind: CheckpointKind,
+    label: String,
+    session: Option<String>,
+    scripts: Vec<ScriptSnapshot>,
+) -> Result<Checkpoint, String> {
+    let checkpoint = Checkpoint {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        label,
+        session,
+        created: chrono_lite_timestamp(),
+        scripts,
+    };
+    let json = serde_json::to_string(&checkpoint)
+        .map_err(|e| format!("Failed to serialize checkpoint: {}", e))?;
+    fs::write(
+        checkpoints_dir()?.join(format!("{}.json", checkpoint.id)),
+        json,
+    )
+    .map_err(|e| format!("Failed to write checkpoint: {}", e))?;
+    prune(kind)?;
+    Ok(checkpoint)
+}
+
+/// Read the current sources of scripts, skipping any that no longer exist
+async fn read_scripts(session: Option<&str>, paths: &[String]) -> Vec<ScriptSnapshot> {
+    let mut scripts = Vec::new();
+    for path in paths {
+        let result = bridge::studio_request_background(
+            session,
+            "/script/get",
+            serde_json::json!({ "path": path }),
+        )
+        .await;
+        if let Some(source) = result
+            .ok()
+            .and_then(|result| result.get("source")?.as_str().map(str::to_string))
+        {
+            scripts.push(ScriptSnapshot {
+                path: path.clone(),
+                source,
+            });
+        }
+    }
+    scripts
+}
+
+/// Note that the AI wrote a script, saving its previous source if known
+pub fn record_edit(session: Option<&str>, path: &str, previous: Option<&str>) {
+    {
+        let mut activity = ACTIVITY.lock();
+        let touched = activity
+            .touched
+            .entry(session.map(str::to_string))
+            .or_default();
+        if touched.len() < MAX_TOUCHED_SCRIPTS {
+            touched.insert(path.to_string());
+        }
+        activity.dirty = true;
+    }
+
+    if let Some(previous) = previous {
+        let scripts = vec![ScriptSnapshot {
+            path: path.to_string(),
+            source: previous.to_string(),
+        }];
+        let label = format!("Before editing {}", path);
+        if let Err(e) = save(
+            CheckpointKind::Edit,
+            label,
+            session.map(str::to_string),
+            scripts,
+        ) {
+            println!("[Stud Checkpoints] {}", e);
+        }
+    }
+}
+
+/// Snapshot every touched script if the AI has written anything since the last one
+async fn snapshot_if_due() -> Result<(), String> {
+    let interval = config::current().checkpoints.auto_snapshot_mins;
+    if interval == 0 {
+        return Ok(());
+    }
+    let sessions = {
+        let mut activity = ACTIVITY.lock();
+        let due = activity
+            .last_snapshot
+            .is_none_or(|last| last.elapsed() >= Duration::from_secs(interval * 60));
+        if !activity.dirty || !due {
+            return Ok(());
+        }
+        activity.dirty = false;
+        activity.last_snapshot = Some(Instant::now());
+        activity.touched.clone()
+    };
+
+    for (session, paths) in sessions {
+        let paths: Vec<String> = paths.into_iter().collect();
+        let scripts = read_scripts(session.as_deref(), &paths).await;
+        if scripts.is_empty() {
+            continue;
+        }
+        let label = format!("Auto snapshot of {} script(s)", scripts.len());
+        let checkpoint = save(CheckpointKind::Auto, label, session, scripts)?;
+        println!(
+            "[Stud Checkpoints] Saved auto snapshot {} ({} script(s))",
+            checkpoint.id,
+            checkpoint.scripts.len()
+        );
+    }
+    Ok(())
+}
+
+/// Take auto snapshots while the AI is editing scripts
+pub async fn run_scheduler() {
+    loop {
+        tokio::time::sleep(Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+        if let Err(e) = snapshot_if_due().await {
+            println!("[Stud Checkpoints] {}", e);
+        }
+    }
+}
+
+/// Saved checkpoints, newest first, optionally of one kind
+#[tauri::command]
+pub fn list_checkpoints(kind: Option<CheckpointKind>) -> Result<Vec<CheckpointSummary>, String> {
+    Ok(load_all()?
+        .iter()
+        .filter(|checkpoint| kind.is_none_or(|kind| checkpoint.kind == kind))
+        .map(CheckpointSummary::from)
+        .collect())
+}
+
+/// A checkpoint with its sources
+#[tauri::command]
+pub fn get_checkpoint(id: String) -> Result<Checkpoint, String> {
+    load(&id)
+}
+
+/// Snapshot every script the AI has touched now, without waiting for the timer
+#[tauri::command]
+pub async fn create_checkpoint(session: Option<String>) -> Result<CheckpointSummary, String> {
+    let paths: Vec<String> = ACTIVITY
+        .lock()
+        .touched
+        .get(&session)
+        .map(|paths| paths.iter().cloned().collect())
+        .unwrap_or_default();
+    if paths.is_empty() {
+        return Err("No scripts have been edited yet".to_string());
+    }
+    let scripts = read_scripts(session.as_deref(), &paths).await;
+    let label = format!("Snapshot of {} script(s)", scripts.len());
+    let checkpoint = save(CheckpointKind::Auto, label, session, scripts)?;
+    Ok(CheckpointSummary::from(&checkpoint))
+}
+
+/// Write a checkpoint's sources back to Studio as one undoable change. The
+/// current sources are saved first, so the restore can itself be undone.
+#[tauri::command]
+pub async fn restore_checkpoint(id: String) -> Result<usize, String> {
+    let checkpoint = load(&id)?;
+    let session = checkpoint.session.as_deref();
+    let paths: Vec<String> = checkpoint
+        .scripts
+        .iter()
+        .map(|script| script.path.clone())
+        .collect();
+    let current = read_scripts(session, &paths).await;
+    if !current.is_empty() {
+        let label = format!("Before restoring \"{}\"", checkpoint.label);
+        save(
+            CheckpointKind::Backup,
+            label,
+            checkpoint.session.clone(),
+            current,
+        )?;
+    }
+
+    bridge::studio_request(
+        session,
+        "/scripts/batch-set",
+        serde_json::json!({ "edits": checkpoint.scripts }),
+    )
+    .await?;
+    println!(
+        "[Stud Checkpoints] Restored {} ({} script(s))",
+        checkpoint.id,
+        checkpoint.scripts.len()
+    );
+    Ok(checkpoint.scripts.len())
+}
+
+/// Delete a saved checkpoint
+#[tauri::command]
+pub fn delete_checkpoint(id: String) -> Result<(), String> {
+    let checkpoint = load(&id)?;
+    fs::remove_file(checkpoints_dir()?.join(format!("{}.json", checkpoint.id)))
+        .map_err(|e| format!("Failed to delete checkpoint: {}", e))
+}
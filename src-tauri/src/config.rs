@@ -0,0 +1,455 @@
+// User configuration for backend features
+// Stored as JSON in the app data folder; missing fields fall back to defaults
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::attributes::AttributeSchema;
+use crate::llm_proxy::EndpointProfile;
+use crate::naming::NamingRule;
+use crate::paths;
+use crate::sandbox::ToolProfile;
+
+const CONFIG_FILENAME: &str = "config.json";
+
+lazy_static::lazy_static! {
+    static ref CONFIG: RwLock<StudConfig> = RwLock::new(load_config());
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StudConfig {
+    pub bridge: BridgeConfig,
+    pub routing: RoutingConfig,
+    pub digest: DigestConfig,
+    pub naming: NamingConfig,
+    pub attributes: AttributeConfig,
+    pub history: HistoryConfig,
+    pub checkpoints: CheckpointConfig,
+    pub sandbox: SandboxConfig,
+    pub guardrails: GuardrailConfig,
+    pub oauth: OAuthConfig,
+    pub llm: LlmConfig,
+    pub network: NetworkConfig,
+}
+
+/// Preferred ports for the local servers. Each can be overridden with an
+/// environment variable (STUD_BRIDGE_PORT, STUD_OAUTH_PORT, STUD_CODEX_PROXY_PORT).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BridgeConfig {
+    pub port: u16,
+    pub oauth_port: u16,
+    pub codex_proxy_port: u16,
+    /// Origins allowed to call the local servers from a browser context.
+    /// Requests without an Origin (the Studio plugin) aren't affected.
+    pub allowed_origins: Vec<String>,
+    /// Requests allowed to wait for Studio at once; more are refused with 429
+    pub max_pending_requests: usize,
+    /// Keep recent requests and responses for inspection and replay (always on in debug builds)
+    pub record_requests: bool,
+    /// Largest body accepted on /stud/request and /stud/respond, after decompression
+    pub max_body_bytes: u64,
+    /// Journal queued requests to disk so they're sent again after a restart
+    pub persist_queue: bool,
+    /// Also serve the bridge API on a Unix socket (macOS/Linux) or named pipe (Windows)
+    pub local_socket: bool,
+    /// Seconds a delivered request stays hidden from polls before it's handed
+    /// out again, in case the plugin that took it crashed. Plugins that support
+    /// it renew the lease while a handler runs, so slow tools aren't redelivered.
+    pub visibility_timeout_secs: u64,
+    /// Deliveries before a request the plugin never answers is failed
+    pub max_deliveries: u32,
+    /// Times a request the plugin refuses as retryable is handed out again
+    pub max_nack_retries: u32,
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        Self {
+            port: 3001,
+            // Must match the redirect URI registered with OpenAI
+            oauth_port: 1455,
+            codex_proxy_port: 3002,
+            allowed_origins: vec![
+                // The app's own webview (macOS/Linux, then Windows)
+                "tauri://localhost".to_string(),
+                "http://tauri.localhost".to_string(),
+                "https://tauri.localhost".to_string(),
+                // Vite dev server
+                "http://localhost:1430".to_string(),
+            ],
+            max_pending_requests: 500,
+            record_requests: false,
+            // Whole-place script dumps can run to tens of megabytes
+            max_body_bytes: 256 * 1024 * 1024,
+            persist_queue: false,
+            local_socket: false,
+            // Well past typical tool runtimes, for plugins that don't renew leases
+            visibility_timeout_secs: 60,
+            max_deliveries: 3,
+            max_nack_retries: 2,
+        }
+    }
+}
+
+impl BridgeConfig {
+    /// Apply environment variable overrides on top of the saved config
+    pub fn resolved(&self) -> Self {
+        Self {
+            port: env_port("STUD_BRIDGE_PORT").unwrap_or(self.port),
+            oauth_port: env_port("STUD_OAUTH_PORT").unwrap_or(self.oauth_port),
+            codex_proxy_port: env_port("STUD_CODEX_PROXY_PORT").unwrap_or(self.codex_proxy_port),
+            allowed_origins: self.allowed_origins.clone(),
+            max_pending_requests: self.max_pending_requests,
+            record_requests: self.record_requests,
+            max_body_bytes: self.max_body_bytes,
+            persist_queue: self.persist_queue,
+            local_socket: self.local_socket,
+            visibility_timeout_secs: self.visibility_timeout_secs,
+            max_deliveries: self.max_deliveries,
+            max_nack_retries: self.max_nack_retries,
+        }
+    }
+}
+
+fn env_port(name: &str) -> Option<u16> {
+    std::env::var(name).ok()?.trim().parse().ok()
+}
+
+/// Thresholds for picking a model per request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoutingConfig {
+    pub enabled: bool,
+    /// Used for short, focused requests like small code fixes
+    pub fast_model: String,
+    /// Used for large refactors, big contexts, and images
+    pub large_model: String,
+    /// Prompts shorter than this (in characters) count as quick tasks
+    pub short_prompt_chars: usize,
+    /// Contexts larger than this (in tokens) go to the large model
+    pub large_context_tokens: u64,
+    /// Touching at least this many scripts counts as a refactor
+    pub refactor_script_count: u32,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fast_model: "gpt-5-mini".to_string(),
+            large_model: "gpt-5".to_string(),
+            short_prompt_chars: 400,
+            large_context_tokens: 32_000,
+            refactor_script_count: 3,
+        }
+    }
+}
+
+/// Weekly activity digest written to the app data folder every Monday
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestConfig {
+    pub enabled: bool,
+    /// Slack/Discord-style incoming webhook to post each digest to
+    pub webhook_url: Option<String>,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            webhook_url: None,
+        }
+    }
+}
+
+/// Instance naming rules checked against the tree and AI-proposed changes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamingConfig {
+    pub rules: Vec<NamingRule>,
+}
+
+/// Expected attributes per tag or class, checked on writes and by audits
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttributeConfig {
+    pub schemas: Vec<AttributeSchema>,
+}
+
+/// Where chat usage history is kept
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Keep history in memory only; nothing is written to disk and it's gone on restart
+    pub ephemeral: bool,
+}
+
+/// Script checkpoints kept as a safety net against bad AI edits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CheckpointConfig {
+    /// Minutes between auto snapshots while the AI is editing; 0 turns them off
+    pub auto_snapshot_mins: u64,
+    pub max_auto_snapshots: usize,
+    /// Per-edit checkpoints, each holding one script's previous source
+    pub max_edit_checkpoints: usize,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self {
+            auto_snapshot_mins: 5,
+            max_auto_snapshots: 24,
+            max_edit_checkpoints: 200,
+        }
+    }
+}
+
+/// Per-tool execution limits the bridge enforces
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxConfig {
+    /// Keyed by bridge route, e.g. `/code/run`
+    pub tools: BTreeMap<String, ToolProfile>,
+}
+
+/// Limits on how fast an AI run may change a place; 0 turns a limit off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuardrailConfig {
+    /// Mutating tool calls one turn may make
+    pub max_mutations_per_turn: usize,
+    /// Distinct scripts a conversation may edit before the user approves more
+    pub max_scripts_per_session: usize,
+    /// Instances one delete may remove
+    pub max_deletes_per_operation: usize,
+}
+
+impl Default for GuardrailConfig {
+    fn default() -> Self {
+        Self {
+            max_mutations_per_turn: 100,
+            max_scripts_per_session: 30,
+            max_deletes_per_operation: 50,
+        }
+    }
+}
+
+/// OAuth clients for sign-in providers that don't have a built-in one
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OAuthConfig {
+    /// A desktop-app OAuth client from the Google Cloud console
+    pub google_client_id: Option<String>,
+    pub google_client_secret: Option<String>,
+}
+
+/// Model providers the proxy forwards to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmConfig {
+    /// Local Ollama server
+    pub ollama_url: String,
+    /// OpenAI-compatible endpoints, by name
+    pub endpoints: BTreeMap<String, EndpointProfile>,
+    /// Endpoint the `openai` provider uses instead of api.openai.com
+    pub openai_endpoint: Option<String>,
+    pub retry: RetryConfig,
+    pub cache: ResponseCacheConfig,
+    /// Seconds an SSE response can go quiet before the proxy sends a keep-alive; 0 turns them off
+    pub heartbeat_secs: u64,
+    pub timeouts: TimeoutConfig,
+}
+
+/// How long the proxy waits on an upstream; 0 turns a limit off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    /// Connecting, for every request to the internet (see `net`)
+    pub connect_secs: u64,
+    /// From sending a request to its response starting, per attempt
+    pub first_byte_secs: u64,
+    /// Silence while a response streams back
+    pub idle_secs: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            connect_secs: 10,
+            // Non-streamed requests to reasoning models can think for minutes
+            first_byte_secs: 300,
+            idle_secs: 180,
+        }
+    }
+}
+
+/// Retries for requests that fail before anything is streamed back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Retries after the first attempt; 0 turns retrying off
+    pub max_retries: u32,
+    /// First backoff, doubled on each retry
+    pub base_delay_ms: u64,
+    /// Longest wait before a retry; a longer Retry-After is not waited out
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+/// Replaying responses to repeated requests, for prompt development (see `response_cache`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseCacheConfig {
+    pub enabled: bool,
+    /// How long a response can be replayed for
+    pub ttl_secs: u64,
+    /// Responses kept; the oldest are dropped past this
+    pub max_entries: usize,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: 60 * 60,
+            max_entries: 100,
+        }
+    }
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            ollama_url: "http://localhost:11434".to_string(),
+            endpoints: BTreeMap::new(),
+            openai_endpoint: None,
+            retry: RetryConfig::default(),
+            cache: ResponseCacheConfig::default(),
+            heartbeat_secs: 15,
+            timeouts: TimeoutConfig::default(),
+        }
+    }
+}
+
+impl LlmConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, profile) in &self.endpoints {
+            profile.validate(name)?;
+        }
+        match &self.openai_endpoint {
+            Some(name) if !self.endpoints.contains_key(name) => {
+                Err(format!("openai_endpoint names an unknown endpoint '{}'", name))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How requests to the internet get out; see `net`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub proxy: ProxyMode,
+    /// Proxy for `manual`, like http://proxy.corp:8080
+    pub proxy_url: Option<String>,
+    /// Sent to the proxy with the password from the keychain
+    pub proxy_username: Option<String>,
+    /// Hosts that skip a manual proxy, comma separated like NO_PROXY
+    pub no_proxy: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyMode {
+    /// Environment variables and the OS proxy settings
+    #[default]
+    System,
+    Manual,
+    /// Always connect directly
+    None,
+}
+
+impl NetworkConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.proxy != ProxyMode::Manual {
+            return Ok(());
+        }
+        let url = self
+            .proxy_url
+            .as_deref()
+            .ok_or("A manual proxy needs network.proxy_url")?;
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| format!("network.proxy_url '{}' isn't a valid URL: {}", url, e))?;
+        match parsed.scheme() {
+            "http" | "https" => Ok(()),
+            scheme => Err(format!(
+                "network.proxy_url uses {}://, but only http:// and https:// proxies are supported",
+                scheme
+            )),
+        }
+    }
+}
+
+fn load_config() -> StudConfig {
+    paths::app_data_dir()
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(CONFIG_FILENAME)).ok())
+        .and_then(|source| serde_json::from_str(&source).ok())
+        .unwrap_or_default()
+}
+
+/// Snapshot of the current configuration
+pub fn current() -> StudConfig {
+    CONFIG.read().clone()
+}
+
+/// Get the current backend configuration
+#[tauri::command]
+pub fn get_config() -> StudConfig {
+    current()
+}
+
+/// Replace the backend configuration and persist it
+#[tauri::command]
+pub fn set_config(
+    bridge: tauri::State<'_, crate::bridge::BridgeHandle>,
+    config: StudConfig,
+) -> Result<(), String> {
+    config.llm.validate()?;
+    config.network.validate()?;
+    let path = paths::app_data_dir()?.join(CONFIG_FILENAME);
+    let json = serde_json::to_string_pretty(&config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write config: {}", e))?;
+
+    let port = config.bridge.resolved().port;
+    let port_changed = port != CONFIG.read().bridge.resolved().port;
+    *CONFIG.write() = config;
+    crate::net::reset();
+
+    // Move the running bridge without dropping the plugin's session
+    if port_changed {
+        let bridge = bridge.inner().clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = bridge.rebind(port).await {
+                println!("[Stud Bridge] Failed to move to port {}: {}", port, e);
+            }
+        });
+    }
+    Ok(())
+}
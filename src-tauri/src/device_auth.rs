@@ -0,0 +1,220 @@
+//! Device Code Sign-in
+//!
+//! For machines where a browser redirect back to the app is awkward (remote
+//! desktop, locked-down school PCs). The backend asks the provider for a short
+//! code, the UI shows it along with the page to enter it on, from any device,
+//! and the backend polls until the user approves. The tokens are stored like a
+//! browser sign-in's and the outcome is reported with the same `oauth-complete`
+//! event, with the flow's id as its `state`.
+//!
+//! ChatGPT's device flow hands the approved device an authorization code and
+//! PKCE verifier, which are exchanged as usual.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use crate::auth::OAuthCallbackData;
+use crate::bridge::{self, chrono_lite_timestamp};
+use crate::net;
+use crate::oauth_providers::OAuthProvider;
+use crate::tokens;
+
+/// Codes not entered within this long are given up on
+const DEVICE_CODE_EXPIRY: Duration = Duration::from_secs(15 * 60);
+/// Seconds between polls when the provider doesn't say
+const DEFAULT_INTERVAL_SECS: u64 = 5;
+
+lazy_static::lazy_static! {
+    /// Flows still polling, keyed by flow id, with a way to stop each
+    static ref FLOWS: Mutex<HashMap<String, oneshot::Sender<()>>> = Mutex::new(HashMap::new());
+}
+
+/// What the UI shows the user while a device sign-in waits for approval
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceCode {
+    pub provider: OAuthProvider,
+    /// Names the flow in `oauth-complete` and `cancel_device_auth`
+    pub flow_id: String,
+    pub user_code: String,
+    /// Page to enter the code on
+    pub verification_uri: String,
+    /// Seconds until the code stops working
+    pub expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct UserCodeResponse {
+    device_auth_id: String,
+    #[serde(alias = "usercode")]
+    user_code: String,
+    /// Sent as a number or a numeric string
+    #[serde(default)]
+    interval: Option<serde_json::Value>,
+}
+
+/// What the token endpoint hands an approved device
+#[derive(Deserialize)]
+struct ApprovedDevice {
+    authorization_code: String,
+    code_verifier: String,
+}
+
+fn interval_secs(value: Option<&serde_json::Value>) -> u64 {
+    value
+        .and_then(|value| {
+            value
+                .as_u64()
+                .or_else(|| value.as_str()?.trim().parse().ok())
+        })
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_INTERVAL_SECS)
+}
+
+async fn request_user_code(issuer: &str, client_id: &str) -> Result<UserCodeResponse, String> {
+    let response = net::client()
+        .post(format!("{}/api/accounts/deviceauth/usercode", issuer))
+        .json(&serde_json::json!({ "client_id": client_id }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to request a device code. {}", net::describe(&e)))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Device code request failed ({}): {}", status, body));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid device code response: {}", e))
+}
+
+/// One poll of the token endpoint: None while the user hasn't approved yet
+async fn poll_once(
+    client: &reqwest::Client,
+    issuer: &str,
+    code: &UserCodeResponse,
+) -> Result<Option<ApprovedDevice>, String> {
+    let response = client
+        .post(format!("{}/api/accounts/deviceauth/token", issuer))
+        .json(&serde_json::json!({
+            "device_auth_id": code.device_auth_id,
+            "user_code": code.user_code,
+        }))
+        .send()
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to reach the device token endpoint. {}",
+                net::describe(&e)
+            )
+        })?;
+    let status = response.status();
+    if status == reqwest::StatusCode::FORBIDDEN || status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Device sign-in failed ({}): {}", status, body));
+    }
+    response
+        .json()
+        .await
+        .map(Some)
+        .map_err(|e| format!("Invalid device token response: {}", e))
+}
+
+/// Poll until the user approves, the code expires or the flow is cancelled,
+/// then exchange the code. Returns the account signed in to.
+async fn wait_for_approval(
+    provider: OAuthProvider,
+    issuer: &str,
+    code: UserCodeResponse,
+    mut cancelled: oneshot::Receiver<()>,
+) -> Result<Option<String>, String> {
+    let client = net::client();
+    let interval = Duration::from_secs(interval_secs(code.interval.as_ref()));
+    let started = Instant::now();
+    let approved = loop {
+        tokio::select! {
+            _ = &mut cancelled => return Err("Sign-in was cancelled".to_string()),
+            _ = tokio::time::sleep(interval) => {}
+        }
+        if started.elapsed() >= DEVICE_CODE_EXPIRY {
+            return Err("The code expired before it was entered".to_string());
+        }
+        if let Some(approved) = poll_once(&client, issuer, &code).await? {
+            break approved;
+        }
+    };
+    let redirect_uri = format!("{}/deviceauth/callback", issuer);
+    tokens::exchange_code(
+        provider,
+        &approved.authorization_code,
+        &approved.code_verifier,
+        &redirect_uri,
+        &code.device_auth_id,
+    )
+    .await
+    .map(|status| status.account_id)
+}
+
+/// Start a device code sign-in to `provider` (ChatGPT if not given). The code
+/// is returned right away; the outcome follows as an `oauth-complete` event.
+#[tauri::command]
+pub async fn start_device_auth(provider: Option<OAuthProvider>) -> Result<DeviceCode, String> {
+    let provider = provider.unwrap_or_default();
+    let issuer = provider
+        .device_auth_issuer()
+        .ok_or_else(|| format!("{} doesn't support signing in with a code", provider.name()))?;
+    let code = request_user_code(issuer, &provider.client_id()?).await?;
+
+    let flow_id = uuid::Uuid::new_v4().simple().to_string();
+    let (stop, cancelled) = oneshot::channel();
+    FLOWS.lock().insert(flow_id.clone(), stop);
+    let device_code = DeviceCode {
+        provider,
+        flow_id: flow_id.clone(),
+        user_code: code.user_code.clone(),
+        verification_uri: format!("{}/codex/device", issuer),
+        expires_in: DEVICE_CODE_EXPIRY.as_secs(),
+    };
+    println!(
+        "[Stud OAuth] Waiting for device code {} to be entered",
+        code.user_code
+    );
+
+    tokio::spawn(async move {
+        let result = wait_for_approval(provider, issuer, code, cancelled).await;
+        FLOWS.lock().remove(&flow_id);
+        let (error, account_id) = match result {
+            Ok(account_id) => (None, account_id),
+            Err(e) => {
+                println!("[Stud OAuth] Device sign-in failed: {}", e);
+                (Some(e), None)
+            }
+        };
+        bridge::emit_event(
+            "oauth-complete",
+            OAuthCallbackData {
+                provider,
+                state: flow_id,
+                error,
+                account_id,
+                timestamp: chrono_lite_timestamp(),
+            },
+        );
+    });
+    Ok(device_code)
+}
+
+/// Stop waiting on a device code the user gave up on
+#[tauri::command]
+pub fn cancel_device_auth(flow_id: String) -> bool {
+    match FLOWS.lock().remove(&flow_id) {
+        Some(stop) => stop.send(()).is_ok(),
+        None => false,
+    }
+}
@@ -0,0 +1,496 @@
+//! Diagnostics Store
+//!
+//! One place for every problem the analysis passes find, keyed by script or
+//! instance path, so the UI can show a problems panel that doesn't depend on
+//! a chat turn. Each pass replaces its own diagnostics when it runs and every
+//! path whose problems changed is pushed to the frontend as a `diagnostics`
+//! event carrying that path's full list, like an LSP publishDiagnostics.
+//!
+//! Passes: Luau syntax (compiled in Studio by the plugin), lint and
+//! type-check (`luau-analyze`, when installed), naming rules, attribute
+//! schemas, and runtime errors reported by playtests.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::bridge::{self, emit_event};
+use crate::{attributes, naming};
+
+const DIAGNOSTICS_EVENT: &str = "diagnostics";
+const ANALYZER: &str = "luau-analyze";
+/// Runtime errors kept per script; a loop erroring every frame shouldn't grow the store
+const MAX_RUNTIME_PER_SCRIPT: usize = 20;
+
+lazy_static::lazy_static! {
+    /// Pass to path to that pass's diagnostics for it
+    static ref STORE: Mutex<HashMap<Pass, BTreeMap<String, Vec<Diagnostic>>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pass {
+    Parse,
+    Lint,
+    TypeCheck,
+    Naming,
+    Attributes,
+    /// Script errors raised during playtests
+    Runtime,
+}
+
+/// Ordered most to least severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub pass: Pass,
+    pub severity: DiagnosticSeverity,
+    /// Script or instance path, e.g. `game.ServerScriptService.Main`
+    pub path: String,
+    /// 1-based, for diagnostics inside a script
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+    /// Lint or rule name, when the pass has one
+    pub code: Option<String>,
+}
+
+/// Payload of the diagnostics event: everything now known for one path
+#[derive(Debug, Clone, Serialize)]
+struct PathDiagnostics {
+    path: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PassOutcome {
+    pub pass: Pass,
+    /// Diagnostics found, or None if the pass didn't run
+    pub found: Option<usize>,
+    /// Why the pass didn't run
+    pub skipped: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyntaxReport {
+    errors: Vec<SyntaxError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyntaxError {
+    path: String,
+    line: Option<u32>,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScriptIndex {
+    scripts: Vec<IndexedScript>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexedScript {
+    path: String,
+    source: String,
+}
+
+fn is_within(path: &str, root: Option<&str>) -> bool {
+    root.is_none_or(|root| {
+        path == root
+            || path
+                .strip_prefix(root)
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// Everything known for a path across passes, most severe first
+fn for_path(
+    store: &HashMap<Pass, BTreeMap<String, Vec<Diagnostic>>>,
+    path: &str,
+) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = store
+        .values()
+        .filter_map(|paths| paths.get(path))
+        .flatten()
+        .cloned()
+        .collect();
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.severity, diagnostic.line));
+    diagnostics
+}
+
+/// Replace a pass's diagnostics under `root` (everywhere if None) and push
+/// every path whose problems changed
+fn publish(pass: Pass, root: Option<&str>, diagnostics: Vec<Diagnostic>) {
+    let mut fresh: BTreeMap<String, Vec<Diagnostic>> = BTreeMap::new();
+    for diagnostic in diagnostics {
+        fresh
+            .entry(diagnostic.path.clone())
+            .or_default()
+            .push(diagnostic);
+    }
+
+    let updates: Vec<PathDiagnostics> = {
+        let mut store = STORE.lock();
+        let current = store.entry(pass).or_default();
+        let stale: Vec<String> = current
+            .keys()
+            .filter(|path| is_within(path, root) && !fresh.contains_key(*path))
+            .cloned()
+            .collect();
+        let mut changed = stale.clone();
+        for path in stale {
+            current.remove(&path);
+        }
+        for (path, diagnostics) in fresh {
+            if current.get(&path) != Some(&diagnostics) {
+                changed.push(path.clone());
+                current.insert(path, diagnostics);
+            }
+        }
+        changed
+            .into_iter()
+            .map(|path| PathDiagnostics {
+                diagnostics: for_path(&store, &path),
+                path,
+            })
+            .collect()
+    };
+    for update in updates {
+        emit_event(DIAGNOSTICS_EVENT, update);
+    }
+}
+
+fn severity_of(severity: naming::Severity) -> DiagnosticSeverity {
+    match severity {
+        naming::Severity::Error => DiagnosticSeverity::Error,
+        naming::Severity::Warn => DiagnosticSeverity::Warning,
+    }
+}
+
+async fn run_parse(session: Option<&str>, root: Option<&str>) -> Result<Vec<Diagnostic>, String> {
+    let result = bridge::studio_request_background(
+        session,
+        "/scripts/syntax",
+        serde_json::json!({ "root": root }),
+    )
+    .await?;
+    let report: SyntaxReport = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+    Ok(report
+        .errors
+        .into_iter()
+        .map(|error| Diagnostic {
+            pass: Pass::Parse,
+            severity: DiagnosticSeverity::Error,
+            path: error.path,
+            line: error.line,
+            column: None,
+            message: error.message,
+            code: None,
+        })
+        .collect())
+}
+
+/// One line of luau-analyze's default output: `file(line,col): Kind: message`
+fn parse_analyzer_line(line: &str, scripts: &HashMap<String, String>) -> Option<Diagnostic> {
+    let (location, rest) = line.split_once("): ")?;
+    let (file, position) = location.rsplit_once('(')?;
+    let (row, column) = position.split_once(',')?;
+    let (kind, message) = rest.split_once(": ")?;
+    let name = Path::new(file.trim()).file_name()?.to_str()?;
+    let path = scripts.get(name)?.clone();
+    // Scripts are checked alone without Roblox's type definitions, so its
+    // globals and types are unknown to the analyzer
+    if message.starts_with("Unknown global") || message.starts_with("Unknown type") {
+        return None;
+    }
+    let (pass, severity, code) = match kind {
+        // Studio's compile already reports these
+        "SyntaxError" => return None,
+        "TypeError" => (Pass::TypeCheck, DiagnosticSeverity::Error, None),
+        lint => (
+            Pass::Lint,
+            DiagnosticSeverity::Warning,
+            Some(lint.to_string()),
+        ),
+    };
+    Some(Diagnostic {
+        pass,
+        severity,
+        path,
+        line: row.trim().parse().ok(),
+        column: column.trim().parse().ok(),
+        message: message.trim().to_string(),
+        code,
+    })
+}
+
+/// Lint and type-check every script with luau-analyze
+async fn run_analyzer(
+    session: Option<&str>,
+    root: Option<&str>,
+) -> Result<Vec<Diagnostic>, String> {
+    let result = bridge::studio_request_background(
+        session,
+        "/scripts/index",
+        serde_json::json!({ "root": root }),
+    )
+    .await?;
+    let index: ScriptIndex = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+
+    let dir = std::env::temp_dir().join(format!(
+        "stud-diagnostics-{}",
+        uuid::Uuid::new_v4().simple()
+    ));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let mut scripts = HashMap::new();
+    let mut files = Vec::new();
+    for (i, script) in index.scripts.into_iter().enumerate() {
+        let name = format!("{}.luau", i);
+        let file = dir.join(&name);
+        if std::fs::write(&file, &script.source).is_ok() {
+            files.push(file);
+            scripts.insert(name, script.path);
+        }
+    }
+    let output = tokio::process::Command::new(ANALYZER)
+        .args(&files)
+        .output()
+        .await;
+    let _ = std::fs::remove_dir_all(&dir);
+    let output = output.map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!("{} isn't installed", ANALYZER),
+        _ => format!("Failed to run {}: {}", ANALYZER, e),
+    })?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .chain(String::from_utf8_lossy(&output.stderr).lines())
+        .filter_map(|line| parse_analyzer_line(line, &scripts))
+        .collect())
+}
+
+async fn run_naming(session: Option<&str>, root: Option<&str>) -> Result<Vec<Diagnostic>, String> {
+    let violations =
+        naming::check_naming(root.map(str::to_string), session.map(str::to_string)).await?;
+    Ok(violations
+        .into_iter()
+        .map(|violation| Diagnostic {
+            pass: Pass::Naming,
+            severity: severity_of(violation.severity),
+            path: violation.path,
+            line: None,
+            column: None,
+            message: violation.message,
+            code: None,
+        })
+        .collect())
+}
+
+async fn run_attributes(
+    session: Option<&str>,
+    root: Option<&str>,
+) -> Result<Vec<Diagnostic>, String> {
+    let violations =
+        attributes::validate_attributes(root.map(str::to_string), session.map(str::to_string))
+            .await?;
+    Ok(violations
+        .into_iter()
+        .map(|violation| Diagnostic {
+            pass: Pass::Attributes,
+            severity: severity_of(violation.severity),
+            path: violation.path,
+            line: None,
+            column: None,
+            message: violation.message,
+            code: Some(violation.attribute),
+        })
+        .collect())
+}
+
+/// Add a script error reported by a playtest (the plugin's `script_error` event)
+pub(crate) fn record_runtime_error(data: &serde_json::Value) {
+    let Some(message) = data.get("message").and_then(|v| v.as_str()) else {
+        return;
+    };
+    // GetFullName() leaves out `game`
+    let path = match data.get("script").and_then(|v| v.as_str()) {
+        Some(script) => format!("game.{}", script),
+        None => "game".to_string(),
+    };
+    // Messages look like `Workspace.Script:12: attempt to index nil`
+    let (line, message) = message
+        .split_once(": ")
+        .and_then(|(location, rest)| {
+            let line = location.rsplit_once(':')?.1.parse::<u32>().ok()?;
+            Some((Some(line), rest))
+        })
+        .unwrap_or((None, message));
+    let diagnostic = Diagnostic {
+        pass: Pass::Runtime,
+        severity: DiagnosticSeverity::Error,
+        path: path.clone(),
+        line,
+        column: None,
+        message: message.to_string(),
+        code: None,
+    };
+
+    let update = {
+        let mut store = STORE.lock();
+        let errors = store
+            .entry(Pass::Runtime)
+            .or_default()
+            .entry(path.clone())
+            .or_default();
+        if errors.contains(&diagnostic) {
+            return;
+        }
+        if errors.len() >= MAX_RUNTIME_PER_SCRIPT {
+            errors.remove(0);
+        }
+        errors.push(diagnostic);
+        PathDiagnostics {
+            diagnostics: for_path(&store, &path),
+            path,
+        }
+    };
+    emit_event(DIAGNOSTICS_EVENT, update);
+}
+
+/// Forget the last playtest's runtime errors when a new one starts
+pub(crate) fn clear_runtime_errors() {
+    publish(Pass::Runtime, None, Vec::new());
+}
+
+/// Run analysis passes over the scripts and instances under `root` (the
+/// whole place if unset), all passes unless `passes` picks some. Each pass
+/// publishes its diagnostics as soon as it finishes.
+#[tauri::command]
+pub async fn run_diagnostics(
+    session: Option<String>,
+    root: Option<String>,
+    passes: Option<Vec<Pass>>,
+) -> Vec<PassOutcome> {
+    let passes = passes.unwrap_or_else(|| {
+        vec![
+            Pass::Parse,
+            Pass::Lint,
+            Pass::TypeCheck,
+            Pass::Naming,
+            Pass::Attributes,
+        ]
+    });
+    let (session, root) = (session.as_deref(), root.as_deref());
+    let mut outcomes = Vec::new();
+
+    let mut record = |pass: Pass, result: Result<Vec<Diagnostic>, String>| {
+        outcomes.push(match result {
+            Ok(diagnostics) => {
+                let found = diagnostics.len();
+                publish(pass, root, diagnostics);
+                PassOutcome {
+                    pass,
+                    found: Some(found),
+                    skipped: None,
+                }
+            }
+            Err(e) => PassOutcome {
+                pass,
+                found: None,
+                skipped: Some(e),
+            },
+        });
+    };
+
+    if passes.contains(&Pass::Parse) {
+        record(Pass::Parse, run_parse(session, root).await);
+    }
+    // One analyzer run covers both lint and type-check
+    let lint = passes.contains(&Pass::Lint);
+    let type_check = passes.contains(&Pass::TypeCheck);
+    if lint || type_check {
+        match run_analyzer(session, root).await {
+            Ok(diagnostics) => {
+                let (type_errors, lints): (Vec<_>, Vec<_>) = diagnostics
+                    .into_iter()
+                    .partition(|diagnostic| diagnostic.pass == Pass::TypeCheck);
+                if lint {
+                    record(Pass::Lint, Ok(lints));
+                }
+                if type_check {
+                    record(Pass::TypeCheck, Ok(type_errors));
+                }
+            }
+            Err(e) => {
+                if lint {
+                    record(Pass::Lint, Err(e.clone()));
+                }
+                if type_check {
+                    record(Pass::TypeCheck, Err(e));
+                }
+            }
+        }
+    }
+    if passes.contains(&Pass::Naming) {
+        record(Pass::Naming, run_naming(session, root).await);
+    }
+    if passes.contains(&Pass::Attributes) {
+        record(Pass::Attributes, run_attributes(session, root).await);
+    }
+    if passes.contains(&Pass::Runtime) {
+        outcomes.push(PassOutcome {
+            pass: Pass::Runtime,
+            found: None,
+            skipped: Some("Runtime errors are collected during playtests".to_string()),
+        });
+    }
+    outcomes
+}
+
+/// Known diagnostics, most severe first: for one script or instance (`path`),
+/// everything under a `root`, or everything. `min_severity` drops less severe ones.
+#[tauri::command]
+pub fn get_diagnostics(
+    path: Option<String>,
+    root: Option<String>,
+    min_severity: Option<DiagnosticSeverity>,
+) -> Vec<Diagnostic> {
+    let store = STORE.lock();
+    let mut diagnostics: Vec<Diagnostic> = match &path {
+        Some(path) => for_path(&store, path),
+        None => store
+            .values()
+            .flat_map(|paths| paths.iter())
+            .filter(|(path, _)| is_within(path, root.as_deref()))
+            .flat_map(|(_, diagnostics)| diagnostics.iter().cloned())
+            .collect(),
+    };
+    if let Some(min) = min_severity {
+        diagnostics.retain(|diagnostic| diagnostic.severity <= min);
+    }
+    diagnostics.sort_by(|a, b| (a.severity, &a.path, a.line).cmp(&(b.severity, &b.path, b.line)));
+    diagnostics
+}
+
+/// Drop one pass's diagnostics, or all of them
+#[tauri::command]
+pub fn clear_diagnostics(pass: Option<Pass>) {
+    let passes = match pass {
+        Some(pass) => vec![pass],
+        None => STORE.lock().keys().copied().collect(),
+    };
+    for pass in passes {
+        publish(pass, None, Vec::new());
+    }
+}
@@ -0,0 +1,273 @@
+//! Weekly Activity Digest
+//!
+//! Once a week (weeks start Monday 00:00 UTC) the previous week's usage is
+//! rolled up per project from the history store: sessions, turns, changes
+//! applied, tests run and estimated cost. Each digest is saved as Markdown in
+//! the app data folder and optionally posted to a webhook, so leads can see
+//! how the assistant is being used without opening every chat.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::bridge::chrono_lite_timestamp;
+use crate::config;
+use crate::history::{self, ProjectActivity};
+use crate::net;
+use crate::paths;
+
+const DIGESTS_DIR: &str = "digests";
+const STATE_FILE: &str = "state.json";
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+const WEEK_MS: u64 = 7 * DAY_MS;
+const CHECK_INTERVAL_SECS: u64 = 60 * 60;
+const UNASSIGNED_PROJECT: &str = "Unassigned";
+
+#[derive(Default, Serialize, Deserialize)]
+struct DigestState {
+    /// End of the last week a digest was generated for (unix ms)
+    last_week_end: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Digest {
+    pub project: String,
+    pub week_start: String,
+    pub path: String,
+    pub markdown: String,
+}
+
+fn digests_dir() -> Result<PathBuf, String> {
+    let dir = paths::app_data_dir()?.join(DIGESTS_DIR);
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create digests folder: {}", e))?;
+    }
+    Ok(dir)
+}
+
+fn read_state() -> DigestState {
+    digests_dir()
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(STATE_FILE)).ok())
+        .and_then(|source| serde_json::from_str(&source).ok())
+        .unwrap_or_default()
+}
+
+fn write_state(state: &DigestState) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize digest state: {}", e))?;
+    fs::write(digests_dir()?.join(STATE_FILE), json)
+        .map_err(|e| format!("Failed to write digest state: {}", e))
+}
+
+/// Start of the week (Monday 00:00 UTC) containing `timestamp`
+fn week_start(timestamp: u64) -> u64 {
+    let days = timestamp / DAY_MS;
+    // 1970-01-01 was a Thursday, three days after a Monday
+    (days - (days + 3) % 7) * DAY_MS
+}
+
+/// YYYY-MM-DD for a unix ms timestamp (UTC)
+pub(crate) fn format_date(timestamp: u64) -> String {
+    // Civil-from-days, from Howard Hinnant's date algorithms
+    let z = (timestamp / DAY_MS) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// File-safe version of a project name
+fn slug(name: &str) -> String {
+    let slug: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    slug.split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn format_duration(ms: u64) -> String {
+    let minutes = ms / 60_000;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    }
+}
+
+fn render(project: &str, start: u64, activity: &ProjectActivity) -> String {
+    let mut markdown = format!(
+        "# {}: week of {}\n\n{} to {} (UTC)\n\n",
+        project,
+        format_date(start),
+        format_date(start),
+        format_date(start + WEEK_MS - DAY_MS)
+    );
+    markdown.push_str("| | |\n|---|---|\n");
+    for (label, value) in [
+        ("Sessions", activity.sessions.to_string()),
+        ("Turns", activity.turns.to_string()),
+        ("Tool calls", activity.tool_calls.to_string()),
+        ("Changes applied", activity.changes_applied.to_string()),
+        ("Tests run", activity.tests_run.to_string()),
+        ("Time generating", format_duration(activity.active_ms)),
+        (
+            "Tokens",
+            format!(
+                "{} in / {} out",
+                activity.input_tokens, activity.output_tokens
+            ),
+        ),
+        (
+            "Estimated cost",
+            format!("${:.2}", activity.estimated_cost_usd),
+        ),
+    ] {
+        markdown.push_str(&format!("| {} | {} |\n", label, value));
+    }
+
+    if !activity.models.is_empty() {
+        markdown.push_str("\n## Models\n\n| Model | Turns | Cost |\n|---|---|---|\n");
+        for model in &activity.models {
+            markdown.push_str(&format!(
+                "| {} | {} | ${:.2} |\n",
+                model.model, model.turns, model.estimated_cost_usd
+            ));
+        }
+    }
+    markdown
+}
+
+async fn post_webhook(url: &str, digest: &Digest) -> Result<(), String> {
+    // "text" for Slack-style hooks, "content" for Discord
+    let response = net::client()
+        .post(url)
+        .json(&serde_json::json!({
+            "text": digest.markdown,
+            "content": digest.markdown,
+            "project": digest.project,
+            "week_start": digest.week_start,
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to post digest. {}", net::describe(&e)))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "Digest webhook returned HTTP {}",
+            response.status().as_u16()
+        ));
+    }
+    Ok(())
+}
+
+/// Write (and optionally post) a digest per project for the week starting at `start`
+async fn generate_week(start: u64) -> Result<Vec<Digest>, String> {
+    let projects = history::project_activity(start, start + WEEK_MS)?;
+    let webhook = config::current().digest.webhook_url;
+    let week = format_date(start);
+
+    let mut digests = Vec::new();
+    for activity in projects.iter().filter(|activity| activity.turns > 0) {
+        let project = activity
+            .project
+            .clone()
+            .unwrap_or_else(|| UNASSIGNED_PROJECT.to_string());
+        let dir = digests_dir()?.join(match slug(&project) {
+            name if name.is_empty() => "project".to_string(),
+            name => name,
+        });
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create digest folder: {}", e))?;
+
+        let path = dir.join(format!("{}.md", week));
+        let markdown = render(&project, start, activity);
+        fs::write(&path, &markdown).map_err(|e| format!("Failed to write digest: {}", e))?;
+
+        let digest = Digest {
+            project,
+            week_start: week.clone(),
+            path: path.display().to_string(),
+            markdown,
+        };
+        if let Some(url) = webhook.as_deref().filter(|url| !url.trim().is_empty()) {
+            if let Err(e) = post_webhook(url, &digest).await {
+                println!("[Stud Digest] {}", e);
+            }
+        }
+        digests.push(digest);
+    }
+    Ok(digests)
+}
+
+/// Generate the digest for last week if it hasn't been done yet
+async fn run_if_due() -> Result<(), String> {
+    if !config::current().digest.enabled {
+        return Ok(());
+    }
+    let this_week = week_start(chrono_lite_timestamp());
+    let mut state = read_state();
+    if state.last_week_end >= this_week {
+        return Ok(());
+    }
+
+    let digests = generate_week(this_week - WEEK_MS).await?;
+    state.last_week_end = this_week;
+    write_state(&state)?;
+    println!("[Stud Digest] Wrote {} weekly digest(s)", digests.len());
+    Ok(())
+}
+
+/// Background loop; checks hourly so a digest is written soon after the week
+/// rolls over, or on the next launch if the app was closed at the time
+pub async fn run_scheduler() {
+    loop {
+        if let Err(e) = run_if_due().await {
+            println!("[Stud Digest] {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+    }
+}
+
+/// Generate digests now for a past week (0 = the last complete week)
+#[tauri::command]
+pub async fn generate_weekly_digest(weeks_ago: Option<u64>) -> Result<Vec<Digest>, String> {
+    let start = week_start(chrono_lite_timestamp()) - WEEK_MS * (weeks_ago.unwrap_or(0) + 1);
+    generate_week(start).await
+}
+
+/// Paths of saved digests, newest week first
+#[tauri::command]
+pub fn list_digests() -> Result<Vec<String>, String> {
+    let dir = digests_dir()?;
+    let mut digests: Vec<String> = fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read digests: {}", e))?
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|project| fs::read_dir(project.path()).ok())
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .map(|path| path.display().to_string())
+        .collect();
+    digests.sort_by(|a, b| {
+        let name = |path: &str| {
+            path.rsplit(['/', '\\'])
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        };
+        name(b).cmp(&name(a)).then_with(|| a.cmp(b))
+    });
+    Ok(digests)
+}
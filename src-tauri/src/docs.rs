@@ -0,0 +1,672 @@
+//! Place Documentation
+//!
+//! Walks the open place through the plugin and writes Markdown docs to disk:
+//! an overview per service, a page per ModuleScript with its doc comments and
+//! exported functions, and a list of remotes with the arguments they're fired
+//! and handled with. Pages are only rewritten when their content changes, and
+//! pages for deleted modules are removed, so the folder can be refreshed often
+//! and checked into version control or handed to the AI as context.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bridge::{self, chrono_lite_timestamp};
+use crate::moonwave;
+use crate::paths;
+
+const DOCS_DIR: &str = "docs";
+const MANIFEST_FILE: &str = ".stud-docs.json";
+const GENERATED_NOTICE: &str =
+    "_Generated by Stud from the open place. Edits are overwritten on the next refresh._";
+
+/// Calls that send a payload through a remote or bindable
+const FIRE_METHODS: &[&str] = &[
+    ":FireServer(",
+    ":FireClient(",
+    ":FireAllClients(",
+    ":InvokeServer(",
+    ":InvokeClient(",
+    ":Fire(",
+    ":Invoke(",
+];
+
+/// Signals and callbacks that receive a payload
+const HANDLER_PATTERNS: &[&str] = &[
+    ".OnServerEvent:Connect(function(",
+    ".OnClientEvent:Connect(function(",
+    ".Event:Connect(function(",
+    ".OnServerInvoke = function(",
+    ".OnClientInvoke = function(",
+    ".OnInvoke = function(",
+];
+
+// Response from the plugin's /docs/outline handler
+#[derive(Deserialize)]
+struct Outline {
+    place: String,
+    #[serde(default)]
+    services: Vec<ServiceOutline>,
+    #[serde(default)]
+    scripts: Vec<ScriptOutline>,
+    #[serde(default)]
+    remotes: Vec<RemoteOutline>,
+}
+
+#[derive(Deserialize)]
+struct ServiceOutline {
+    name: String,
+    #[serde(default)]
+    children: Vec<ChildOutline>,
+    // Luau encodes an empty table as [], so this isn't always an object
+    #[serde(default, rename = "classCounts")]
+    class_counts: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct ChildOutline {
+    name: String,
+    #[serde(rename = "className")]
+    class_name: String,
+    #[serde(default)]
+    descendants: usize,
+}
+
+#[derive(Deserialize)]
+struct ScriptOutline {
+    path: String,
+    #[serde(rename = "className")]
+    class_name: String,
+    #[serde(default)]
+    source: String,
+}
+
+#[derive(Deserialize)]
+struct RemoteOutline {
+    path: String,
+    name: String,
+    #[serde(rename = "className")]
+    class_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DocsReport {
+    pub output_dir: String,
+    pub pages: usize,
+    /// Pages created or changed by this refresh
+    pub written: Vec<String>,
+    pub unchanged: usize,
+    /// Pages from the previous run that no longer correspond to anything
+    pub removed: Vec<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Manifest {
+    files: Vec<String>,
+    generated_at: u64,
+}
+
+struct FunctionDoc {
+    signature: String,
+    doc: String,
+}
+
+struct RemoteUsage {
+    script: String,
+    line: usize,
+    call: String,
+}
+
+/// File-safe version of an instance path
+fn slug(path: &str) -> String {
+    let slug: String = path
+        .trim_start_matches("game.")
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    slug.split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn comment_text(line: &str) -> Option<&str> {
+    let text = line.trim().strip_prefix("--")?;
+    let text = text.trim_start_matches('-');
+    Some(text.strip_prefix(' ').unwrap_or(text))
+}
+
+/// The comment block at the top of a script, skipping `--!strict`-style directives
+fn leading_comment(source: &str) -> String {
+    let mut lines = source
+        .lines()
+        .skip_while(|line| line.trim().is_empty() || line.trim().starts_with("--!"))
+        .peekable();
+
+    let Some(first) = lines.peek().map(|line| line.trim()) else {
+        return String::new();
+    };
+
+    // Block comment: --[[ ... ]] or --[=[ ... ]=]
+    if let Some(rest) = first.strip_prefix("--[") {
+        let level = rest.chars().take_while(|c| *c == '=').count();
+        if rest[level..].starts_with('[') {
+            let close = format!("]{}]", "=".repeat(level));
+            let mut text = Vec::new();
+            for (index, line) in lines.enumerate() {
+                let line = if index == 0 {
+                    &line.trim()[level + 4..]
+                } else {
+                    line
+                };
+                if let Some((before, _)) = line.split_once(&close) {
+                    text.push(before.trim());
+                    break;
+                }
+                text.push(line.trim());
+            }
+            return text.join("\n").trim().to_string();
+        }
+    }
+
+    lines
+        .map_while(comment_text)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Name of the table a module returns, from its final `return X`
+fn module_table(source: &str) -> Option<&str> {
+    let last = source.lines().rev().find(|line| !line.trim().is_empty())?;
+    let name = last.trim().strip_prefix("return ")?.trim();
+    name.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        .then_some(name)
+}
+
+/// Functions defined on the returned module table, with the comments directly above them
+fn exported_functions(source: &str) -> Vec<FunctionDoc> {
+    let Some(table) = module_table(source) else {
+        return Vec::new();
+    };
+    let prefixes = [
+        format!("function {}.", table),
+        format!("function {}:", table),
+    ];
+    let assignment = format!("{}.", table);
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut functions = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        let signature = if prefixes
+            .iter()
+            .any(|prefix| trimmed.starts_with(prefix.as_str()))
+        {
+            trimmed.trim_start_matches("function ").to_string()
+        } else if let Some((lhs, rhs)) = trimmed.split_once('=') {
+            let lhs = lhs.trim();
+            let rhs = rhs.trim();
+            if lhs.starts_with(&assignment) && rhs.starts_with("function(") {
+                format!("{}{}", lhs, &rhs["function".len()..])
+            } else {
+                continue;
+            }
+        } else {
+            continue;
+        };
+
+        let mut doc: Vec<&str> = lines[..index]
+            .iter()
+            .rev()
+            .map_while(|line| comment_text(line))
+            .collect();
+        doc.reverse();
+
+        functions.push(FunctionDoc {
+            signature: signature
+                .split_once(')')
+                .map(|(head, _)| format!("{})", head))
+                .unwrap_or(signature),
+            doc: doc.join("\n").trim().to_string(),
+        });
+    }
+    functions
+}
+
+/// Arguments of the call starting at `open` (just past its `(`), on one line
+fn call_arguments(line: &str, open: usize) -> String {
+    let mut depth = 1;
+    for (offset, c) in line[open..].char_indices() {
+        match c {
+            '(' | '{' => depth += 1,
+            ')' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return line[open..open + offset].trim().to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+    format!("{} …", line[open..].trim())
+}
+
+/// The instance name an expression refers to: `a.b.Name`, `a:WaitForChild("Name")`
+fn last_segment(expression: &str) -> Option<String> {
+    let expression = expression.trim().trim_end_matches(')');
+    if let Some(quoted) = expression.strip_suffix('"') {
+        return quoted.rsplit('"').next().map(str::to_string);
+    }
+    let name: String = expression
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// `local Var = expr` assignments, so `Var:FireServer()` can be traced back to the instance name
+fn local_aliases(source: &str) -> HashMap<String, String> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let (lhs, rhs) = line.trim().strip_prefix("local ")?.split_once('=')?;
+            let var = lhs.split(':').next()?.trim();
+            Some((var.to_string(), last_segment(rhs)?))
+        })
+        .collect()
+}
+
+/// Which remote name the receiver before `index` refers to
+fn receiver_name(line: &str, index: usize, aliases: &HashMap<String, String>) -> Option<String> {
+    let receiver: String = line[..index]
+        .chars()
+        .rev()
+        .take_while(|c| !c.is_whitespace() && !matches!(c, '=' | ',' | '(' | '{'))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let name = last_segment(&receiver)?;
+    Some(aliases.get(&name).cloned().unwrap_or(name))
+}
+
+/// Calls that fire or handle each remote, keyed by remote name
+fn scan_remotes(
+    scripts: &[ScriptOutline],
+    names: &HashSet<&str>,
+) -> (
+    HashMap<String, Vec<RemoteUsage>>,
+    HashMap<String, Vec<RemoteUsage>>,
+) {
+    let mut fired: HashMap<String, Vec<RemoteUsage>> = HashMap::new();
+    let mut handled: HashMap<String, Vec<RemoteUsage>> = HashMap::new();
+
+    for script in scripts {
+        let aliases = local_aliases(&script.source);
+        for (number, line) in script.source.lines().enumerate() {
+            let patterns = FIRE_METHODS
+                .iter()
+                .map(|pattern| (pattern, false))
+                .chain(HANDLER_PATTERNS.iter().map(|pattern| (pattern, true)));
+            for (pattern, is_handler) in patterns {
+                let Some(index) = line.find(*pattern) else {
+                    continue;
+                };
+                let Some(name) = receiver_name(line, index, &aliases) else {
+                    continue;
+                };
+                if !names.contains(name.as_str()) {
+                    continue;
+                }
+                let method = pattern.trim_start_matches(['.', ':']).trim_end_matches('(');
+                let args = call_arguments(line, index + pattern.len());
+                let usages = if is_handler { &mut handled } else { &mut fired };
+                usages.entry(name).or_default().push(RemoteUsage {
+                    script: script.path.clone(),
+                    line: number + 1,
+                    call: format!("{}({})", method, args),
+                });
+            }
+        }
+    }
+    (fired, handled)
+}
+
+fn module_page(script: &ScriptOutline) -> String {
+    let mut page = format!("# {}\n\n{}\n\n", script.path, GENERATED_NOTICE);
+
+    let doc = leading_comment(&script.source);
+    if !doc.is_empty() {
+        page.push_str(&doc);
+        page.push_str("\n\n");
+    }
+
+    let requires: Vec<String> = script
+        .source
+        .lines()
+        .filter_map(|line| {
+            let index = line.find("require(")?;
+            Some(call_arguments(line, index + "require(".len()))
+        })
+        .collect();
+    if !requires.is_empty() {
+        page.push_str("## Requires\n\n");
+        for require in requires {
+            page.push_str(&format!("- `{}`\n", require));
+        }
+        page.push('\n');
+    }
+
+    // Prefer moonwave doc comments when the module has them
+    let api = moonwave::parse_module(&script.path, &script.source);
+    if api
+        .items
+        .iter()
+        .any(|item| item.documented && !item.private)
+    {
+        page.push_str("## API\n\n");
+        for item in api.items.iter().filter(|item| !item.private) {
+            page.push_str(&format!(
+                "### `{}`\n\n",
+                moonwave::item_signature(&api, item)
+            ));
+            if let Some(deprecated) = &item.deprecated {
+                page.push_str(&format!("**Deprecated** {}\n\n", deprecated));
+            }
+            if !item.description.is_empty() {
+                page.push_str(&item.description);
+                page.push_str("\n\n");
+            }
+            for param in item
+                .params
+                .iter()
+                .filter(|param| !param.description.is_empty())
+            {
+                page.push_str(&format!("- `{}`: {}\n", param.name, param.description));
+            }
+            for ret in item
+                .returns
+                .iter()
+                .filter(|ret| !ret.description.is_empty())
+            {
+                page.push_str(&format!("- returns `{}`: {}\n", ret.ty, ret.description));
+            }
+            page.push('\n');
+        }
+        return page;
+    }
+
+    let functions = exported_functions(&script.source);
+    if !functions.is_empty() {
+        page.push_str("## API\n\n");
+        for function in functions {
+            page.push_str(&format!("### `{}`\n\n", function.signature));
+            if !function.doc.is_empty() {
+                page.push_str(&function.doc);
+                page.push_str("\n\n");
+            }
+        }
+    }
+    page
+}
+
+fn service_page(service: &ServiceOutline, scripts: &[&ScriptOutline]) -> String {
+    let mut page = format!("# {}\n\n{}\n\n", service.name, GENERATED_NOTICE);
+
+    if !service.children.is_empty() {
+        page.push_str("## Children\n\n| Name | Class | Descendants |\n|---|---|---|\n");
+        for child in &service.children {
+            page.push_str(&format!(
+                "| {} | {} | {} |\n",
+                escape_cell(&child.name),
+                child.class_name,
+                child.descendants
+            ));
+        }
+        page.push('\n');
+    }
+
+    if let Some(counts) = service.class_counts.as_object() {
+        let mut counts: Vec<(&String, u64)> = counts
+            .iter()
+            .map(|(class, count)| (class, count.as_u64().unwrap_or_default()))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        if !counts.is_empty() {
+            page.push_str("## Contents\n\n");
+            for (class, count) in counts {
+                page.push_str(&format!("- {} × {}\n", count, class));
+            }
+            page.push('\n');
+        }
+    }
+
+    if !scripts.is_empty() {
+        page.push_str("## Scripts\n\n");
+        for script in scripts {
+            page.push_str(&format!("- `{}` ({})\n", script.path, script.class_name));
+        }
+        page.push('\n');
+    }
+    page
+}
+
+fn remotes_page(outline: &Outline) -> String {
+    let mut page = format!("# Remotes\n\n{}\n\n", GENERATED_NOTICE);
+    if outline.remotes.is_empty() {
+        page.push_str("No remotes or bindables in this place.\n");
+        return page;
+    }
+
+    let names: HashSet<&str> = outline
+        .remotes
+        .iter()
+        .map(|remote| remote.name.as_str())
+        .collect();
+    let (fired, handled) = scan_remotes(&outline.scripts, &names);
+
+    for remote in &outline.remotes {
+        page.push_str(&format!(
+            "## {}\n\n`{}` ({})\n\n",
+            remote.name, remote.path, remote.class_name
+        ));
+        for (heading, usages) in [("Fired from", &fired), ("Handled in", &handled)] {
+            let Some(usages) = usages.get(&remote.name) else {
+                continue;
+            };
+            page.push_str(&format!("**{}**\n\n", heading));
+            for usage in usages {
+                page.push_str(&format!(
+                    "- `{}:{}` `{}`\n",
+                    usage.script, usage.line, usage.call
+                ));
+            }
+            page.push('\n');
+        }
+    }
+    page
+}
+
+/// Every page keyed by its path relative to the docs folder
+fn render(outline: &Outline) -> BTreeMap<String, String> {
+    let mut pages = BTreeMap::new();
+    let mut module_links = Vec::new();
+    let mut used_slugs = HashSet::new();
+
+    for script in outline
+        .scripts
+        .iter()
+        .filter(|script| script.class_name == "ModuleScript")
+    {
+        let mut file = format!("modules/{}.md", slug(&script.path));
+        let mut suffix = 2;
+        while !used_slugs.insert(file.clone()) {
+            file = format!("modules/{}-{}.md", slug(&script.path), suffix);
+            suffix += 1;
+        }
+        let summary = leading_comment(&script.source)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        module_links.push((script.path.clone(), file.clone(), summary));
+        pages.insert(file, module_page(script));
+    }
+
+    let mut index = format!(
+        "# {}\n\n{}\n\n## Services\n\n",
+        outline.place, GENERATED_NOTICE
+    );
+    index.push_str("| Service | Children | Scripts |\n|---|---|---|\n");
+    for service in &outline.services {
+        let prefix = format!("game.{}.", service.name);
+        let scripts: Vec<&ScriptOutline> = outline
+            .scripts
+            .iter()
+            .filter(|script| script.path.starts_with(&prefix))
+            .collect();
+        let file = format!("services/{}.md", service.name);
+        index.push_str(&format!(
+            "| [{}]({}) | {} | {} |\n",
+            service.name,
+            file,
+            service.children.len(),
+            scripts.len()
+        ));
+        pages.insert(file, service_page(service, &scripts));
+    }
+
+    if !module_links.is_empty() {
+        index.push_str("\n## Modules\n\n");
+        for (path, file, summary) in &module_links {
+            if summary.is_empty() {
+                index.push_str(&format!("- [`{}`]({})\n", path, file));
+            } else {
+                index.push_str(&format!("- [`{}`]({}) - {}\n", path, file, summary));
+            }
+        }
+    }
+
+    index.push_str(&format!(
+        "\n## Remotes\n\n{} remotes and bindables, see [remotes.md](remotes.md).\n",
+        outline.remotes.len()
+    ));
+    pages.insert("README.md".to_string(), index);
+    pages.insert("remotes.md".to_string(), remotes_page(outline));
+    pages
+}
+
+fn read_manifest(dir: &Path) -> Manifest {
+    fs::read_to_string(dir.join(MANIFEST_FILE))
+        .ok()
+        .and_then(|source| serde_json::from_str(&source).ok())
+        .unwrap_or_default()
+}
+
+fn default_output_dir(place: &str) -> Result<PathBuf, String> {
+    let name = match slug(place) {
+        name if name.is_empty() => "place".to_string(),
+        name => name,
+    };
+    Ok(paths::app_data_dir()?.join(DOCS_DIR).join(name))
+}
+
+/// Write pages that changed and delete pages left over from the previous run
+fn sync_pages(dir: &Path, pages: &BTreeMap<String, String>) -> Result<DocsReport, String> {
+    let previous = read_manifest(dir);
+    let mut written = Vec::new();
+    let mut unchanged = 0;
+
+    for (file, content) in pages {
+        let path = dir.join(file);
+        if fs::read_to_string(&path).is_ok_and(|existing| &existing == content) {
+            unchanged += 1;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", file, e))?;
+        written.push(file.clone());
+    }
+
+    let mut removed = Vec::new();
+    for file in previous.files {
+        if !pages.contains_key(&file) && fs::remove_file(dir.join(&file)).is_ok() {
+            removed.push(file);
+        }
+    }
+
+    let manifest = Manifest {
+        files: pages.keys().cloned().collect(),
+        generated_at: chrono_lite_timestamp(),
+    };
+    let json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize docs manifest: {}", e))?;
+    fs::write(dir.join(MANIFEST_FILE), json)
+        .map_err(|e| format!("Failed to write docs manifest: {}", e))?;
+
+    Ok(DocsReport {
+        output_dir: dir.display().to_string(),
+        pages: pages.len(),
+        written,
+        unchanged,
+        removed,
+    })
+}
+
+/// Generate (or refresh) Markdown documentation for the open place.
+/// Defaults to a per-place folder under the app data directory.
+#[tauri::command]
+pub async fn generate_place_docs(
+    output_dir: Option<String>,
+    session: Option<String>,
+) -> Result<DocsReport, String> {
+    let result = bridge::studio_request_background(
+        session.as_deref(),
+        "/docs/outline",
+        serde_json::json!({}),
+    )
+    .await?;
+    let outline: Outline = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+
+    let dir = match output_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => default_output_dir(&outline.place)?,
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create docs folder: {}", e))?;
+
+    let pages = render(&outline);
+    moonwave::store_index(
+        outline
+            .scripts
+            .iter()
+            .filter(|script| script.class_name == "ModuleScript")
+            .map(|script| moonwave::parse_module(&script.path, &script.source))
+            .collect(),
+    );
+    let report = sync_pages(&dir, &pages)?;
+    println!(
+        "[Stud Docs] {} pages in {} ({} written, {} removed)",
+        report.pages,
+        report.output_dir,
+        report.written.len(),
+        report.removed.len()
+    );
+    Ok(report)
+}
@@ -0,0 +1,294 @@
+//! Workspace Find and Replace
+//!
+//! Runs a find-and-replace over every script in the place (or under one root)
+//! without the model rewriting each file. The plugin sends the current sources,
+//! the replacement is computed here, and the result is held as a transaction
+//! with per-file match counts that can be previewed before it's applied. Applying
+//! writes every changed script in a single request, so it lands as one undo step
+//! and is refused as a whole if any script changed since the preview.
+
+use parking_lot::Mutex;
+use regex::{NoExpand, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::bridge;
+use crate::checkpoints;
+use crate::patches;
+
+/// Previewed transactions that weren't applied are dropped after this long
+const TRANSACTION_EXPIRY_SECS: u64 = 10 * 60;
+/// Changed lines shown per file in a preview
+const MAX_PREVIEW_LINES: usize = 5;
+const MAX_PREVIEW_LINE_CHARS: usize = 200;
+
+lazy_static::lazy_static! {
+    static ref TRANSACTIONS: Mutex<HashMap<String, Transaction>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Deserialize)]
+struct ScriptIndex {
+    scripts: Vec<IndexedScript>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexedScript {
+    path: String,
+    source: String,
+}
+
+struct Transaction {
+    session: Option<String>,
+    edits: Vec<ScriptEdit>,
+    created: Instant,
+}
+
+#[derive(Debug, Serialize)]
+struct ScriptEdit {
+    path: String,
+    source: String,
+    /// Source at preview time; Studio refuses the batch if it no longer matches
+    expected: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LineChange {
+    /// 1-based
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub matches: usize,
+    /// The first few changed lines
+    pub preview: Vec<LineChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FindReplaceTransaction {
+    /// Pass to `apply_find_replace`; unset when nothing matched
+    pub id: Option<String>,
+    pub pattern: String,
+    pub replacement: String,
+    pub scope: Option<String>,
+    pub scripts_searched: usize,
+    pub total_matches: usize,
+    pub files: Vec<FileChange>,
+    pub applied: bool,
+}
+
+fn compile(pattern: &str, regex: bool, case_sensitive: bool) -> Result<Regex, String> {
+    if pattern.is_empty() {
+        return Err("Pattern is empty".to_string());
+    }
+    let source = if regex {
+        pattern.to_string()
+    } else {
+        regex::escape(pattern)
+    };
+    regex::RegexBuilder::new(&source)
+        .case_insensitive(!case_sensitive)
+        .multi_line(true)
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))
+}
+
+fn shorten(line: &str) -> String {
+    if line.chars().count() <= MAX_PREVIEW_LINE_CHARS {
+        return line.to_string();
+    }
+    let mut short: String = line.chars().take(MAX_PREVIEW_LINE_CHARS).collect();
+    short.push('…');
+    short
+}
+
+/// Replace in one script, returning the new source and what changed
+fn replace_in(
+    re: &Regex,
+    replacement: &str,
+    regex: bool,
+    source: &str,
+) -> Option<(String, usize, Vec<LineChange>)> {
+    let matches = re.find_iter(source).count();
+    if matches == 0 {
+        return None;
+    }
+    let replace = |text: &str| -> String {
+        if regex {
+            re.replace_all(text, replacement).into_owned()
+        } else {
+            re.replace_all(text, NoExpand(replacement)).into_owned()
+        }
+    };
+    let new_source = replace(source);
+    if new_source == source {
+        return None;
+    }
+    let preview = source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| re.is_match(line))
+        .take(MAX_PREVIEW_LINES)
+        .map(|(index, line)| LineChange {
+            line: index + 1,
+            before: shorten(line),
+            after: shorten(&replace(line)),
+        })
+        .collect();
+    Some((new_source, matches, preview))
+}
+
+/// Search the place's scripts and stage the replacement as a transaction.
+/// `regex` enables `$1`-style capture groups in the replacement.
+#[tauri::command]
+pub async fn preview_find_replace(
+    pattern: String,
+    replacement: String,
+    scope: Option<String>,
+    regex: Option<bool>,
+    case_sensitive: Option<bool>,
+    session: Option<String>,
+) -> Result<FindReplaceTransaction, String> {
+    let regex = regex.unwrap_or(false);
+    let re = compile(&pattern, regex, case_sensitive.unwrap_or(true))?;
+
+    let result = bridge::studio_request_background(
+        session.as_deref(),
+        "/scripts/index",
+        serde_json::json!({ "root": scope }),
+    )
+    .await?;
+    let index: ScriptIndex = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+
+    let mut files = Vec::new();
+    let mut edits = Vec::new();
+    for script in &index.scripts {
+        if let Some((source, matches, preview)) =
+            replace_in(&re, &replacement, regex, &script.source)
+        {
+            files.push(FileChange {
+                path: script.path.clone(),
+                matches,
+                preview,
+            });
+            edits.push(ScriptEdit {
+                path: script.path.clone(),
+                source,
+                expected: script.source.clone(),
+            });
+        }
+    }
+
+    let id = if edits.is_empty() {
+        None
+    } else {
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut transactions = TRANSACTIONS.lock();
+        let expiry = Duration::from_secs(TRANSACTION_EXPIRY_SECS);
+        transactions.retain(|_, transaction| transaction.created.elapsed() < expiry);
+        transactions.insert(
+            id.clone(),
+            Transaction {
+                session,
+                edits,
+                created: Instant::now(),
+            },
+        );
+        Some(id)
+    };
+
+    Ok(FindReplaceTransaction {
+        id,
+        pattern,
+        replacement,
+        scope,
+        scripts_searched: index.scripts.len(),
+        total_matches: files.iter().map(|file| file.matches).sum(),
+        files,
+        applied: false,
+    })
+}
+
+/// Write a previewed transaction to Studio as one undoable change
+#[tauri::command]
+pub async fn apply_find_replace(id: String) -> Result<usize, String> {
+    let transaction = TRANSACTIONS
+        .lock()
+        .remove(&id)
+        .ok_or_else(|| "Transaction not found or expired; preview it again".to_string())?;
+    bridge::studio_request(
+        transaction.session.as_deref(),
+        "/scripts/batch-set",
+        serde_json::json!({ "edits": transaction.edits }),
+    )
+    .await?;
+    for edit in &transaction.edits {
+        checkpoints::record_edit(
+            transaction.session.as_deref(),
+            &edit.path,
+            Some(&edit.expected),
+        );
+        patches::record_script(
+            transaction.session.as_deref(),
+            &edit.path,
+            Some(&edit.expected),
+            &edit.source,
+        );
+    }
+    println!(
+        "[Stud FindReplace] Applied transaction {} to {} script(s)",
+        id,
+        transaction.edits.len()
+    );
+    Ok(transaction.edits.len())
+}
+
+/// Preview and apply in one step
+#[tauri::command]
+pub async fn find_replace_all(
+    pattern: String,
+    replacement: String,
+    scope: Option<String>,
+    regex: Option<bool>,
+    case_sensitive: Option<bool>,
+    session: Option<String>,
+) -> Result<FindReplaceTransaction, String> {
+    let mut transaction =
+        preview_find_replace(pattern, replacement, scope, regex, case_sensitive, session).await?;
+    if let Some(id) = transaction.id.clone() {
+        apply_find_replace(id).await?;
+        transaction.applied = true;
+    }
+    Ok(transaction)
+}
+
+/// Previewed edits for a session that haven't been applied, as (path, expected, source)
+pub(crate) fn staged_edits(session: Option<&str>) -> Vec<(String, String, String)> {
+    let expiry = Duration::from_secs(TRANSACTION_EXPIRY_SECS);
+    TRANSACTIONS
+        .lock()
+        .values()
+        .filter(|transaction| transaction.created.elapsed() < expiry)
+        .filter(|transaction| transaction.session.as_deref() == session)
+        .flat_map(|transaction| {
+            transaction.edits.iter().map(|edit| {
+                (
+                    edit.path.clone(),
+                    edit.expected.clone(),
+                    edit.source.clone(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Drop a previewed transaction without applying it
+#[tauri::command]
+pub fn discard_find_replace(id: String) -> bool {
+    TRANSACTIONS.lock().remove(&id).is_some()
+}
@@ -0,0 +1,565 @@
+//! Folder Sync
+//!
+//! Links a local folder of scripts to the live scripts in Studio, a
+//! lightweight Rojo-style sync for people who want to edit AI output in their
+//! own editor. Files map to scripts by Rojo's naming convention under a chosen
+//! instance, or by a `sourcemap.json` in the folder when there is one. Saved
+//! files are pushed through the bridge after a short debounce, each checked
+//! against the source Studio had at the last sync; if the script was changed
+//! in Studio since (by the user or the AI), the file is held as a conflict
+//! until it's resolved one way or the other.
+
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::bridge;
+
+const DEBOUNCE_MS: u64 = 300;
+const SOURCEMAP_FILENAME: &str = "sourcemap.json";
+const SYNC_EVENT: &str = "folder-sync";
+/// Folders never searched for scripts in convention mode
+const SKIPPED_DIRS: &[&str] = &["node_modules", "Packages", "DevPackages"];
+
+lazy_static::lazy_static! {
+    static ref LINK: Mutex<Option<Link>> = Mutex::new(None);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingMode {
+    /// `Foo.server.luau` under the folder is `<root>.Foo`, `init.luau` is its folder
+    Convention,
+    /// Rojo's sourcemap.json names the instance for each file
+    Sourcemap,
+}
+
+struct Link {
+    folder: PathBuf,
+    instance_root: String,
+    session: Option<String>,
+    mode: MappingMode,
+    /// File to script instance path
+    files: HashMap<PathBuf, String>,
+    /// Studio's source for each script as of the last sync
+    synced: HashMap<String, String>,
+    /// Scripts changed on both sides; not pushed until resolved
+    conflicts: BTreeSet<String>,
+    pushed: usize,
+    _watcher: Debouncer<RecommendedWatcher>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderSyncStatus {
+    pub folder: String,
+    pub instance_root: String,
+    pub mode: MappingMode,
+    pub scripts: usize,
+    /// Files whose script doesn't exist in Studio
+    pub missing: Vec<String>,
+    pub conflicts: Vec<String>,
+    /// Saves pushed to Studio since linking
+    pub pushed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SyncEvent {
+    file: String,
+    path: String,
+    /// pushed, pulled, conflict or failed
+    outcome: &'static str,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScriptIndex {
+    scripts: Vec<IndexedScript>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexedScript {
+    path: String,
+    source: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourcemapNode {
+    name: String,
+    #[serde(default)]
+    class_name: String,
+    #[serde(default)]
+    file_paths: Vec<String>,
+    #[serde(default)]
+    children: Vec<SourcemapNode>,
+}
+
+impl Link {
+    fn status(&self) -> FolderSyncStatus {
+        let mut missing: Vec<String> = self
+            .files
+            .iter()
+            .filter(|(_, path)| !self.synced.contains_key(*path))
+            .map(|(file, _)| self.relative(file))
+            .collect();
+        missing.sort();
+        FolderSyncStatus {
+            folder: self.folder.display().to_string(),
+            instance_root: self.instance_root.clone(),
+            mode: self.mode,
+            scripts: self.files.len() - missing.len(),
+            missing,
+            conflicts: self.conflicts.iter().cloned().collect(),
+            pushed: self.pushed,
+        }
+    }
+
+    fn relative(&self, file: &Path) -> String {
+        file.strip_prefix(&self.folder)
+            .unwrap_or(file)
+            .display()
+            .to_string()
+    }
+
+    fn file_for(&self, path: &str) -> Option<PathBuf> {
+        self.files
+            .iter()
+            .find(|(_, script)| *script == path)
+            .map(|(file, _)| file.clone())
+    }
+}
+
+fn is_script_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "lua" || ext == "luau")
+}
+
+/// Instance path for a file under the folder by Rojo's naming convention
+fn convention_path(instance_root: &str, relative: &Path) -> Option<String> {
+    let mut names: Vec<String> = relative
+        .parent()
+        .map(|parent| {
+            parent
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let stem = relative.file_stem()?.to_string_lossy();
+    let name = stem
+        .strip_suffix(".server")
+        .or_else(|| stem.strip_suffix(".client"))
+        .unwrap_or(&stem);
+    // init scripts stand for the folder they're in
+    if name != "init" {
+        names.push(name.to_string());
+    }
+    // The plugin splits paths on dots, so such names can't be addressed
+    if names.is_empty() || names.iter().any(|name| name.contains('.')) {
+        return None;
+    }
+    Some(format!("{}.{}", instance_root, names.join(".")))
+}
+
+fn collect_scripts(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+                collect_scripts(&path, files);
+            }
+        } else if is_script_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn sourcemap_files(
+    folder: &Path,
+    node: &SourcemapNode,
+    path: &str,
+    files: &mut HashMap<PathBuf, String>,
+) {
+    if matches!(
+        node.class_name.as_str(),
+        "Script" | "LocalScript" | "ModuleScript"
+    ) {
+        let file = node
+            .file_paths
+            .iter()
+            .map(|file| folder.join(file))
+            .find(|file| is_script_file(file));
+        if let Some(file) = file {
+            files.insert(canonical(&file), path.to_string());
+        }
+    }
+    for child in &node.children {
+        if !child.name.contains('.') {
+            sourcemap_files(folder, child, &format!("{}.{}", path, child.name), files);
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Map the folder's script files to instance paths
+fn map_files(
+    folder: &Path,
+    instance_root: &str,
+) -> Result<(MappingMode, HashMap<PathBuf, String>), String> {
+    let sourcemap = folder.join(SOURCEMAP_FILENAME);
+    if sourcemap.exists() {
+        let json = fs::read_to_string(&sourcemap)
+            .map_err(|e| format!("Failed to read {}: {}", SOURCEMAP_FILENAME, e))?;
+        let root: SourcemapNode = serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid {}: {}", SOURCEMAP_FILENAME, e))?;
+        let mut files = HashMap::new();
+        // The root node is the DataModel, whatever the project calls it
+        sourcemap_files(folder, &root, "game", &mut files);
+        return Ok((MappingMode::Sourcemap, files));
+    }
+
+    let mut scripts = Vec::new();
+    collect_scripts(folder, &mut scripts);
+    let files = scripts
+        .into_iter()
+        .filter_map(|file| {
+            let relative = file.strip_prefix(folder).ok()?.to_path_buf();
+            Some((canonical(&file), convention_path(instance_root, &relative)?))
+        })
+        .collect();
+    Ok((MappingMode::Convention, files))
+}
+
+/// Current Studio sources of every script under the root
+async fn studio_sources(
+    session: Option<&str>,
+    instance_root: &str,
+) -> Result<HashMap<String, String>, String> {
+    let root = (instance_root != "game").then_some(instance_root);
+    let result = bridge::studio_request_background(
+        session,
+        "/scripts/index",
+        serde_json::json!({ "root": root }),
+    )
+    .await?;
+    let index: ScriptIndex = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+    Ok(index
+        .scripts
+        .into_iter()
+        .map(|script| (script.path, script.source))
+        .collect())
+}
+
+fn emit(link: &Link, file: &Path, path: &str, outcome: &'static str, error: Option<String>) {
+    bridge::emit_event(
+        SYNC_EVENT,
+        SyncEvent {
+            file: link.relative(file),
+            path: path.to_string(),
+            outcome,
+            error,
+        },
+    );
+}
+
+/// Push saved files to Studio
+async fn push_changes(changed: Vec<PathBuf>) {
+    let mut remap = false;
+    let edits: Vec<(PathBuf, String, String, String)> = {
+        let link = LINK.lock();
+        let Some(link) = link.as_ref() else {
+            return;
+        };
+        changed
+            .iter()
+            .filter_map(|file| {
+                if file
+                    .file_name()
+                    .is_some_and(|name| name == SOURCEMAP_FILENAME)
+                {
+                    remap = true;
+                    return None;
+                }
+                let file = canonical(file);
+                let path = link.files.get(&file)?;
+                if link.conflicts.contains(path) {
+                    return None;
+                }
+                // Deleted files and files that match Studio (e.g. just pulled) are left alone
+                let source = fs::read_to_string(&file).ok()?;
+                let expected = link.synced.get(path)?;
+                (*expected != source).then(|| (file, path.clone(), source, expected.clone()))
+            })
+            .collect()
+    };
+    if remap {
+        remap_files();
+    }
+
+    let session = LINK.lock().as_ref().and_then(|link| link.session.clone());
+    for (file, path, source, expected) in edits {
+        let result = bridge::studio_request(
+            session.as_deref(),
+            "/scripts/batch-set",
+            serde_json::json!({
+                "edits": [{ "path": path, "source": source, "expected": expected }]
+            }),
+        )
+        .await;
+        let mut link = LINK.lock();
+        let Some(link) = link.as_mut() else {
+            return;
+        };
+        match result {
+            Ok(_) => {
+                link.synced.insert(path.clone(), source);
+                link.pushed += 1;
+                emit(link, &file, &path, "pushed", None);
+            }
+            Err(e) if e.contains("changed since") => {
+                println!("[Stud Sync] {} changed in Studio since the last sync", path);
+                link.conflicts.insert(path.clone());
+                emit(link, &file, &path, "conflict", None);
+            }
+            Err(e) => {
+                println!("[Stud Sync] Failed to push {}: {}", path, e);
+                emit(link, &file, &path, "failed", Some(e));
+            }
+        }
+    }
+}
+
+/// Rebuild the mapping after the sourcemap changes
+fn remap_files() {
+    let mut link = LINK.lock();
+    let Some(link) = link.as_mut() else {
+        return;
+    };
+    match map_files(&link.folder, &link.instance_root) {
+        Ok((mode, files)) => {
+            link.mode = mode;
+            link.files = files;
+        }
+        Err(e) => println!("[Stud Sync] {}", e),
+    }
+}
+
+/// Write Studio's sources into the linked files that differ. Files with
+/// unpushed local changes aren't overwritten; they become conflicts instead.
+async fn pull(link_session: Option<String>, instance_root: String) -> Result<usize, String> {
+    let sources = studio_sources(link_session.as_deref(), &instance_root).await?;
+    let mut link = LINK.lock();
+    let link = link.as_mut().ok_or("No folder is linked")?;
+    let mut pulled = 0;
+    let files: Vec<(PathBuf, String)> = link
+        .files
+        .iter()
+        .map(|(file, path)| (file.clone(), path.clone()))
+        .collect();
+    for (file, path) in files {
+        let Some(studio) = sources.get(&path) else {
+            continue;
+        };
+        let local = fs::read_to_string(&file).unwrap_or_default();
+        let base = link.synced.get(&path);
+        if local == *studio {
+            link.synced.insert(path, studio.clone());
+            continue;
+        }
+        if base.is_some_and(|base| *base != local) {
+            if base != Some(studio) {
+                link.conflicts.insert(path);
+            }
+            continue;
+        }
+        // Recorded before writing, so the watcher sees nothing to push
+        link.synced.insert(path.clone(), studio.clone());
+        match fs::write(&file, studio) {
+            Ok(()) => {
+                pulled += 1;
+                emit(link, &file, &path, "pulled", None);
+            }
+            Err(e) => emit(link, &file, &path, "failed", Some(e.to_string())),
+        }
+    }
+    Ok(pulled)
+}
+
+fn watch(folder: &Path) -> Result<Debouncer<RecommendedWatcher>, String> {
+    let mut debouncer = new_debouncer(
+        Duration::from_millis(DEBOUNCE_MS),
+        |result: DebounceEventResult| match result {
+            Ok(events) => {
+                let changed: Vec<PathBuf> = events.into_iter().map(|event| event.path).collect();
+                tauri::async_runtime::spawn(push_changes(changed));
+            }
+            Err(e) => println!("[Stud Sync] Watch error: {}", e),
+        },
+    )
+    .map_err(|e| format!("Failed to watch folder: {}", e))?;
+    debouncer
+        .watcher()
+        .watch(folder, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch folder: {}", e))?;
+    Ok(debouncer)
+}
+
+/// Link a local folder to Studio's scripts and push saved files from now on.
+/// Scripts map by `sourcemap.json` if the folder has one, otherwise by file
+/// name under `instance_root` (default `game`). Local files that already
+/// differ from Studio count as changed; `pull` overwrites them with Studio's
+/// sources first.
+#[tauri::command]
+pub async fn start_folder_sync(
+    folder: String,
+    instance_root: Option<String>,
+    session: Option<String>,
+    pull: Option<bool>,
+) -> Result<FolderSyncStatus, String> {
+    let folder = fs::canonicalize(&folder).map_err(|e| format!("Folder not found: {}", e))?;
+    if !folder.is_dir() {
+        return Err(format!("Not a folder: {}", folder.display()));
+    }
+    let instance_root = instance_root.unwrap_or_else(|| "game".to_string());
+    let (mode, files) = map_files(&folder, &instance_root)?;
+    if files.is_empty() {
+        return Err("No .lua or .luau scripts found in the folder".to_string());
+    }
+    // Sourcemap paths can point anywhere in the place
+    let index_root = match mode {
+        MappingMode::Convention => instance_root.clone(),
+        MappingMode::Sourcemap => "game".to_string(),
+    };
+    let studio = studio_sources(session.as_deref(), &index_root).await?;
+    let synced = files
+        .values()
+        .filter_map(|path| Some((path.clone(), studio.get(path)?.clone())))
+        .collect();
+
+    let watcher = watch(&folder)?;
+    let status = {
+        let mut link = LINK.lock();
+        let linked = Link {
+            folder,
+            instance_root,
+            session: session.clone(),
+            mode,
+            files,
+            synced,
+            conflicts: BTreeSet::new(),
+            pushed: 0,
+            _watcher: watcher,
+        };
+        println!(
+            "[Stud Sync] Linked {} ({} script(s), {:?})",
+            linked.folder.display(),
+            linked.files.len(),
+            linked.mode
+        );
+        *link = Some(linked);
+        link.as_ref().map(Link::status)
+    };
+    if pull.unwrap_or(false) {
+        self::pull(session, index_root).await?;
+        return get_folder_sync_status().ok_or_else(|| "Folder sync stopped".to_string());
+    }
+    status.ok_or_else(|| "Folder sync stopped".to_string())
+}
+
+/// Stop watching the linked folder
+#[tauri::command]
+pub fn stop_folder_sync() -> bool {
+    let stopped = LINK.lock().take();
+    if let Some(link) = &stopped {
+        println!("[Stud Sync] Unlinked {}", link.folder.display());
+    }
+    stopped.is_some()
+}
+
+#[tauri::command]
+pub fn get_folder_sync_status() -> Option<FolderSyncStatus> {
+    LINK.lock().as_ref().map(Link::status)
+}
+
+/// Bring Studio's current sources (e.g. after AI edits) into the linked files
+#[tauri::command]
+pub async fn pull_folder_sync() -> Result<usize, String> {
+    let (session, root) = {
+        let link = LINK.lock();
+        let link = link.as_ref().ok_or("No folder is linked")?;
+        let root = match link.mode {
+            MappingMode::Convention => link.instance_root.clone(),
+            MappingMode::Sourcemap => "game".to_string(),
+        };
+        (link.session.clone(), root)
+    };
+    pull(session, root).await
+}
+
+/// Settle a conflict by pushing the local file over Studio's version, or by
+/// overwriting the file with Studio's
+#[tauri::command]
+pub async fn resolve_folder_sync_conflict(path: String, keep_local: bool) -> Result<(), String> {
+    let (file, session) = {
+        let link = LINK.lock();
+        let link = link.as_ref().ok_or("No folder is linked")?;
+        if !link.conflicts.contains(&path) {
+            return Err(format!("No conflict for {}", path));
+        }
+        let file = link
+            .file_for(&path)
+            .ok_or_else(|| format!("No file maps to {}", path))?;
+        (file, link.session.clone())
+    };
+
+    let source = if keep_local {
+        let source =
+            fs::read_to_string(&file).map_err(|e| format!("Failed to read file: {}", e))?;
+        bridge::studio_request(
+            session.as_deref(),
+            "/script/set",
+            serde_json::json!({ "path": path, "source": source }),
+        )
+        .await?;
+        source
+    } else {
+        let result = bridge::studio_request(
+            session.as_deref(),
+            "/script/get",
+            serde_json::json!({ "path": path }),
+        )
+        .await?;
+        let source = result
+            .get("source")
+            .and_then(|source| source.as_str())
+            .ok_or_else(|| format!("Studio returned no source for {}", path))?
+            .to_string();
+        // Recorded before writing, so the watcher sees nothing to push
+        if let Some(link) = LINK.lock().as_mut() {
+            link.synced.insert(path.clone(), source.clone());
+        }
+        fs::write(&file, &source).map_err(|e| format!("Failed to write file: {}", e))?;
+        source
+    };
+
+    if let Some(link) = LINK.lock().as_mut() {
+        link.synced.insert(path.clone(), source);
+        link.conflicts.remove(&path);
+    }
+    Ok(())
+}
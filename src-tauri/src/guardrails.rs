@@ -0,0 +1,178 @@
+//! Agent Guardrails
+//!
+//! Limits on how fast an AI run may change a place, so a tool loop gone wrong
+//! is stopped before it does much damage: mutating tool calls per turn,
+//! distinct scripts edited per conversation until the user approves more, and
+//! instances deleted in one operation. Checked on every request to
+//! `/stud/request` that carries a chat id; a refused request comes back with
+//! the limit that was hit so the model can stop and explain instead of retrying.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::bridge::StudioRequest;
+use crate::{config, profiles};
+
+lazy_static::lazy_static! {
+    static ref RUNS: Mutex<HashMap<String, RunState>> = Mutex::new(HashMap::new());
+}
+
+/// What one conversation has done so far
+#[derive(Default)]
+struct RunState {
+    turn_id: Option<String>,
+    mutations_this_turn: usize,
+    /// Scripts edited since the user last approved more
+    scripts: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Guardrail {
+    MutationsPerTurn,
+    ScriptsPerSession,
+    DeletesPerOperation,
+}
+
+/// Why a request was refused, sent back to the model as is
+#[derive(Debug, Clone, Serialize)]
+pub struct GuardrailError {
+    pub guardrail: Guardrail,
+    pub limit: usize,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GuardrailStatus {
+    pub mutations_this_turn: usize,
+    pub max_mutations_per_turn: usize,
+    pub scripts_modified: usize,
+    pub max_scripts_per_session: usize,
+}
+
+fn body_of(request: &StudioRequest) -> serde_json::Value {
+    request
+        .body
+        .as_deref()
+        .and_then(|body| serde_json::from_str(body).ok())
+        .unwrap_or_default()
+}
+
+/// Scripts a request would write to
+fn scripts_edited(route: &str, body: &serde_json::Value) -> Vec<String> {
+    let path = |value: &serde_json::Value| {
+        value
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    match route {
+        "/script/set" | "/script/edit" => path(body).into_iter().collect(),
+        "/scripts/batch-set" => body
+            .get("edits")
+            .and_then(|edits| edits.as_array())
+            .map(|edits| edits.iter().filter_map(path).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn deletes(route: &str, body: &serde_json::Value) -> usize {
+    match route {
+        "/instance/delete" => 1,
+        "/instance/bulk-delete" => body
+            .get("paths")
+            .and_then(|paths| paths.as_array())
+            .map_or(0, Vec::len),
+        _ => 0,
+    }
+}
+
+/// Check a request against the guardrails and count it if it passes.
+/// Requests from outside a chat only have the per-operation limit applied.
+pub(crate) fn check(request: &StudioRequest) -> Result<(), GuardrailError> {
+    if !profiles::is_modifying(&request.path) {
+        return Ok(());
+    }
+    let limits = config::current().guardrails;
+    let body = body_of(request);
+
+    let deleted = deletes(&request.path, &body);
+    if limits.max_deletes_per_operation > 0 && deleted > limits.max_deletes_per_operation {
+        return Err(GuardrailError {
+            guardrail: Guardrail::DeletesPerOperation,
+            limit: limits.max_deletes_per_operation,
+            error: format!(
+                "Refused to delete {} instances in one operation; the limit is {}. Delete fewer at a time, or ask the user to confirm first.",
+                deleted, limits.max_deletes_per_operation
+            ),
+        });
+    }
+
+    let Some(chat_id) = &request.chat_id else {
+        return Ok(());
+    };
+    let mut runs = RUNS.lock();
+    let run = runs.entry(chat_id.clone()).or_default();
+    if run.turn_id != request.turn_id {
+        run.turn_id = request.turn_id.clone();
+        run.mutations_this_turn = 0;
+    }
+
+    if limits.max_mutations_per_turn > 0 && run.mutations_this_turn >= limits.max_mutations_per_turn
+    {
+        return Err(GuardrailError {
+            guardrail: Guardrail::MutationsPerTurn,
+            limit: limits.max_mutations_per_turn,
+            error: format!(
+                "This turn has already made {} changes in Studio, the most allowed per turn. Stop here and summarize what was done so the user can review it.",
+                limits.max_mutations_per_turn
+            ),
+        });
+    }
+
+    let new_scripts: HashSet<String> = scripts_edited(&request.path, &body)
+        .into_iter()
+        .filter(|script| !run.scripts.contains(script))
+        .collect();
+    if limits.max_scripts_per_session > 0
+        && run.scripts.len() + new_scripts.len() > limits.max_scripts_per_session
+    {
+        return Err(GuardrailError {
+            guardrail: Guardrail::ScriptsPerSession,
+            limit: limits.max_scripts_per_session,
+            error: format!(
+                "This conversation has already edited {} of the {} scripts allowed before the user approves more. Ask the user to approve further script edits before continuing.",
+                run.scripts.len(),
+                limits.max_scripts_per_session
+            ),
+        });
+    }
+
+    run.mutations_this_turn += 1;
+    run.scripts.extend(new_scripts);
+    Ok(())
+}
+
+/// Where a conversation stands against the guardrails
+#[tauri::command]
+pub fn get_guardrail_status(chat_id: String) -> GuardrailStatus {
+    let limits = config::current().guardrails;
+    let runs = RUNS.lock();
+    let run = runs.get(&chat_id);
+    GuardrailStatus {
+        mutations_this_turn: run.map_or(0, |run| run.mutations_this_turn),
+        max_mutations_per_turn: limits.max_mutations_per_turn,
+        scripts_modified: run.map_or(0, |run| run.scripts.len()),
+        max_scripts_per_session: limits.max_scripts_per_session,
+    }
+}
+
+/// The user approved more script edits for a conversation; its count starts over
+#[tauri::command]
+pub fn approve_script_edits(chat_id: String) {
+    if let Some(run) = RUNS.lock().get_mut(&chat_id) {
+        run.scripts.clear();
+    }
+}
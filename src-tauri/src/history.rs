@@ -0,0 +1,612 @@
+//! Chat History Store
+//!
+//! Records usage for every AI turn (tokens, estimated cost, tool calls, timing)
+//! and rolls those turns up into per-chat session totals so users can see what
+//! each conversation actually cost.
+//!
+//! Storage goes through `HistoryBackend`: a local SQLite database by default,
+//! or memory when history is configured as ephemeral. Incognito chats always
+//! use a memory backend, so they're never written to disk.
+
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::bridge::chrono_lite_timestamp;
+use crate::config;
+use crate::paths;
+
+pub(crate) const DB_FILENAME: &str = "history.db";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS chat_turns (
+    chat_id TEXT NOT NULL,
+    turn_id TEXT NOT NULL,
+    model TEXT NOT NULL,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    cached_tokens INTEGER NOT NULL DEFAULT 0,
+    cost_usd REAL NOT NULL DEFAULT 0,
+    tool_calls INTEGER NOT NULL DEFAULT 0,
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER NOT NULL,
+    PRIMARY KEY (chat_id, turn_id)
+);
+
+CREATE TABLE IF NOT EXISTS chat_sessions (
+    chat_id TEXT PRIMARY KEY,
+    turns INTEGER NOT NULL DEFAULT 0,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    cached_tokens INTEGER NOT NULL DEFAULT 0,
+    cost_usd REAL NOT NULL DEFAULT 0,
+    tool_calls INTEGER NOT NULL DEFAULT 0,
+    active_ms INTEGER NOT NULL DEFAULT 0,
+    started_at INTEGER NOT NULL,
+    last_activity_at INTEGER NOT NULL,
+    ended_at INTEGER
+);
+";
+
+// Columns added after the first release. SQLite has no ADD COLUMN IF NOT EXISTS,
+// so "duplicate column" errors just mean the database is already up to date.
+const MIGRATIONS: &[&str] = &[
+    "ALTER TABLE chat_turns ADD COLUMN project TEXT",
+    "ALTER TABLE chat_turns ADD COLUMN changes_applied INTEGER NOT NULL DEFAULT 0",
+    "ALTER TABLE chat_turns ADD COLUMN tests_run INTEGER NOT NULL DEFAULT 0",
+];
+
+lazy_static::lazy_static! {
+    static ref HISTORY: Mutex<HistoryStore> = Mutex::new(HistoryStore::new());
+}
+
+/// Where turns and session totals are kept
+pub trait HistoryBackend: Send {
+    /// Store a turn, replacing any with the same ids, and refresh the chat's totals
+    fn record_turn(&mut self, stats: &TurnStats, cost_usd: f64) -> Result<(), String>;
+    /// Mark a chat as ended and return its final summary
+    fn end_session(
+        &mut self,
+        chat_id: &str,
+        ended_at: u64,
+    ) -> Result<Option<ChatSummaryStats>, String>;
+    fn summary(&mut self, chat_id: &str) -> Result<Option<ChatSummaryStats>, String>;
+    /// Per-project totals for turns that ended in `[start, end)` (unix ms)
+    fn project_activity(&mut self, start: u64, end: u64) -> Result<Vec<ProjectActivity>, String>;
+}
+
+/// Routes each chat to the persistent backend, or to memory if it's incognito
+struct HistoryStore {
+    persistent: Box<dyn HistoryBackend>,
+    incognito: MemoryBackend,
+    incognito_chats: HashSet<String>,
+}
+
+impl HistoryStore {
+    fn new() -> Self {
+        let persistent: Box<dyn HistoryBackend> = if config::current().history.ephemeral {
+            Box::new(MemoryBackend::default())
+        } else {
+            Box::new(SqliteBackend::default())
+        };
+        Self {
+            persistent,
+            incognito: MemoryBackend::default(),
+            incognito_chats: HashSet::new(),
+        }
+    }
+
+    fn backend_for(&mut self, chat_id: &str) -> &mut dyn HistoryBackend {
+        if self.incognito_chats.contains(chat_id) {
+            &mut self.incognito
+        } else {
+            self.persistent.as_mut()
+        }
+    }
+}
+
+/// SQLite database in the app data folder. Opened lazily on first use so the
+/// app still starts if the data dir is unavailable.
+#[derive(Default)]
+pub struct SqliteBackend {
+    conn: Option<Connection>,
+}
+
+impl SqliteBackend {
+    fn with_db<T>(
+        &mut self,
+        f: impl FnOnce(&Connection) -> rusqlite::Result<T>,
+    ) -> Result<T, String> {
+        if self.conn.is_none() {
+            self.conn = Some(open_db()?);
+        }
+        let conn = self
+            .conn
+            .as_ref()
+            .expect("history database was just opened");
+        f(conn).map_err(|e| format!("History database error: {}", e))
+    }
+}
+
+fn open_db() -> Result<Connection, String> {
+    let path = paths::app_data_dir()?.join(DB_FILENAME);
+    let conn =
+        Connection::open(&path).map_err(|e| format!("Failed to open history database: {}", e))?;
+    conn.execute_batch(SCHEMA)
+        .map_err(|e| format!("Failed to initialize history database: {}", e))?;
+    for migration in MIGRATIONS {
+        if let Err(e) = conn.execute(migration, []) {
+            if !e.to_string().contains("duplicate column") {
+                return Err(format!("Failed to migrate history database: {}", e));
+            }
+        }
+    }
+    Ok(conn)
+}
+
+/// Model pricing in USD per million tokens (same shape as models.dev `cost`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_read: Option<f64>,
+}
+
+/// Usage for a single completed AI turn, reported by the frontend
+#[derive(Debug, Clone, Deserialize)]
+pub struct TurnStats {
+    pub chat_id: String,
+    pub turn_id: String,
+    pub model: String,
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cached_tokens: u64,
+    #[serde(default)]
+    pub tool_calls: u32,
+    pub started_at: u64,
+    pub ended_at: u64,
+    #[serde(default)]
+    pub pricing: Option<ModelPricing>,
+    /// Place or project the turn worked on, for per-project reporting
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Edits the turn applied to the game (script writes, instance changes)
+    #[serde(default)]
+    pub changes_applied: u32,
+    #[serde(default)]
+    pub tests_run: u32,
+    /// Incognito chats are kept in memory only and never written to disk
+    #[serde(default)]
+    pub incognito: bool,
+}
+
+impl TurnStats {
+    fn estimated_cost(&self) -> f64 {
+        let Some(pricing) = &self.pricing else {
+            return 0.0;
+        };
+
+        // Cached input is billed at the cache rate when the model has one
+        let cached = self.cached_tokens.min(self.input_tokens);
+        let (uncached, cached_rate) = match pricing.cache_read {
+            Some(rate) => (self.input_tokens - cached, rate),
+            None => (self.input_tokens, 0.0),
+        };
+
+        (uncached as f64 * pricing.input
+            + cached as f64 * cached_rate
+            + self.output_tokens as f64 * pricing.output)
+            / 1_000_000.0
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub turns: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub estimated_cost_usd: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChatSummaryStats {
+    pub chat_id: String,
+    pub turns: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cached_tokens: u64,
+    pub estimated_cost_usd: f64,
+    pub tool_calls: u32,
+    pub started_at: u64,
+    pub ended_at: Option<u64>,
+    /// Wall-clock time from the first turn until the session ended (or the latest turn)
+    pub duration_ms: u64,
+    /// Time actually spent generating, summed over turns
+    pub active_ms: u64,
+    pub models: Vec<ModelUsage>,
+}
+
+/// Recompute the session totals for a chat from its recorded turns
+fn roll_up(conn: &Connection, chat_id: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO chat_sessions (
+            chat_id, turns, input_tokens, output_tokens, cached_tokens,
+            cost_usd, tool_calls, active_ms, started_at, last_activity_at
+        )
+        SELECT chat_id, COUNT(*), SUM(input_tokens), SUM(output_tokens), SUM(cached_tokens),
+            SUM(cost_usd), SUM(tool_calls), SUM(ended_at - started_at),
+            MIN(started_at), MAX(ended_at)
+        FROM chat_turns WHERE chat_id = ?1 GROUP BY chat_id
+        ON CONFLICT(chat_id) DO UPDATE SET
+            turns = excluded.turns,
+            input_tokens = excluded.input_tokens,
+            output_tokens = excluded.output_tokens,
+            cached_tokens = excluded.cached_tokens,
+            cost_usd = excluded.cost_usd,
+            tool_calls = excluded.tool_calls,
+            active_ms = excluded.active_ms,
+            started_at = excluded.started_at,
+            last_activity_at = excluded.last_activity_at,
+            ended_at = NULL",
+        params![chat_id],
+    )?;
+    Ok(())
+}
+
+fn load_summary(conn: &Connection, chat_id: &str) -> rusqlite::Result<Option<ChatSummaryStats>> {
+    let summary = conn
+        .query_row(
+            "SELECT turns, input_tokens, output_tokens, cached_tokens, cost_usd,
+                tool_calls, active_ms, started_at, last_activity_at, ended_at
+            FROM chat_sessions WHERE chat_id = ?1",
+            params![chat_id],
+            |row| {
+                let started_at: i64 = row.get(7)?;
+                let last_activity_at: i64 = row.get(8)?;
+                let ended_at: Option<i64> = row.get(9)?;
+                let end = ended_at.unwrap_or(last_activity_at).max(started_at);
+                Ok(ChatSummaryStats {
+                    chat_id: chat_id.to_string(),
+                    turns: row.get(0)?,
+                    input_tokens: row.get::<_, i64>(1)? as u64,
+                    output_tokens: row.get::<_, i64>(2)? as u64,
+                    cached_tokens: row.get::<_, i64>(3)? as u64,
+                    estimated_cost_usd: row.get(4)?,
+                    tool_calls: row.get(5)?,
+                    active_ms: row.get::<_, i64>(6)? as u64,
+                    started_at: started_at as u64,
+                    ended_at: ended_at.map(|t| t as u64),
+                    duration_ms: (end - started_at) as u64,
+                    models: Vec::new(),
+                })
+            },
+        )
+        .optional()?;
+
+    let Some(mut summary) = summary else {
+        return Ok(None);
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT model, COUNT(*), SUM(input_tokens), SUM(output_tokens), SUM(cost_usd)
+        FROM chat_turns WHERE chat_id = ?1 GROUP BY model ORDER BY SUM(cost_usd) DESC",
+    )?;
+    summary.models = stmt
+        .query_map(params![chat_id], |row| {
+            Ok(ModelUsage {
+                model: row.get(0)?,
+                turns: row.get(1)?,
+                input_tokens: row.get::<_, i64>(2)? as u64,
+                output_tokens: row.get::<_, i64>(3)? as u64,
+                estimated_cost_usd: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(Some(summary))
+}
+
+/// Activity for one project over a time window
+#[derive(Debug, Serialize)]
+pub struct ProjectActivity {
+    /// None for turns recorded without a project
+    pub project: Option<String>,
+    pub sessions: u32,
+    pub turns: u32,
+    pub tool_calls: u32,
+    pub changes_applied: u32,
+    pub tests_run: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub estimated_cost_usd: f64,
+    pub active_ms: u64,
+    pub models: Vec<ModelUsage>,
+}
+
+fn sqlite_project_activity(
+    conn: &Connection,
+    start: u64,
+    end: u64,
+) -> rusqlite::Result<Vec<ProjectActivity>> {
+    let mut stmt = conn.prepare(
+        "SELECT project, COUNT(DISTINCT chat_id), COUNT(*), SUM(tool_calls),
+            SUM(changes_applied), SUM(tests_run), SUM(input_tokens), SUM(output_tokens),
+            SUM(cost_usd), SUM(ended_at - started_at)
+        FROM chat_turns WHERE ended_at >= ?1 AND ended_at < ?2
+        GROUP BY project ORDER BY SUM(cost_usd) DESC",
+    )?;
+    let mut projects = stmt
+        .query_map(params![start as i64, end as i64], |row| {
+            Ok(ProjectActivity {
+                project: row.get(0)?,
+                sessions: row.get(1)?,
+                turns: row.get(2)?,
+                tool_calls: row.get(3)?,
+                changes_applied: row.get(4)?,
+                tests_run: row.get(5)?,
+                input_tokens: row.get::<_, i64>(6)? as u64,
+                output_tokens: row.get::<_, i64>(7)? as u64,
+                estimated_cost_usd: row.get(8)?,
+                active_ms: row.get::<_, i64>(9)? as u64,
+                models: Vec::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT model, COUNT(*), SUM(input_tokens), SUM(output_tokens), SUM(cost_usd)
+        FROM chat_turns WHERE ended_at >= ?1 AND ended_at < ?2 AND project IS ?3
+        GROUP BY model ORDER BY SUM(cost_usd) DESC",
+    )?;
+    for project in &mut projects {
+        project.models = stmt
+            .query_map(params![start as i64, end as i64, project.project], |row| {
+                Ok(ModelUsage {
+                    model: row.get(0)?,
+                    turns: row.get(1)?,
+                    input_tokens: row.get::<_, i64>(2)? as u64,
+                    output_tokens: row.get::<_, i64>(3)? as u64,
+                    estimated_cost_usd: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+    }
+    Ok(projects)
+}
+
+impl HistoryBackend for SqliteBackend {
+    fn record_turn(&mut self, stats: &TurnStats, cost_usd: f64) -> Result<(), String> {
+        self.with_db(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO chat_turns (
+                    chat_id, turn_id, model, input_tokens, output_tokens, cached_tokens,
+                    cost_usd, tool_calls, started_at, ended_at, project, changes_applied, tests_run
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    stats.chat_id,
+                    stats.turn_id,
+                    stats.model,
+                    stats.input_tokens as i64,
+                    stats.output_tokens as i64,
+                    stats.cached_tokens as i64,
+                    cost_usd,
+                    stats.tool_calls,
+                    stats.started_at as i64,
+                    stats.ended_at.max(stats.started_at) as i64,
+                    stats.project,
+                    stats.changes_applied,
+                    stats.tests_run,
+                ],
+            )?;
+            roll_up(conn, &stats.chat_id)
+        })
+    }
+
+    fn end_session(
+        &mut self,
+        chat_id: &str,
+        ended_at: u64,
+    ) -> Result<Option<ChatSummaryStats>, String> {
+        self.with_db(|conn| {
+            roll_up(conn, chat_id)?;
+            conn.execute(
+                "UPDATE chat_sessions SET ended_at = ?2 WHERE chat_id = ?1",
+                params![chat_id, ended_at as i64],
+            )?;
+            load_summary(conn, chat_id)
+        })
+    }
+
+    fn summary(&mut self, chat_id: &str) -> Result<Option<ChatSummaryStats>, String> {
+        self.with_db(|conn| load_summary(conn, chat_id))
+    }
+
+    fn project_activity(&mut self, start: u64, end: u64) -> Result<Vec<ProjectActivity>, String> {
+        self.with_db(|conn| sqlite_project_activity(conn, start, end))
+    }
+}
+
+struct StoredTurn {
+    stats: TurnStats,
+    cost_usd: f64,
+}
+
+impl StoredTurn {
+    fn ended_at(&self) -> u64 {
+        self.stats.ended_at.max(self.stats.started_at)
+    }
+}
+
+/// Keeps everything in memory, for ephemeral mode and incognito chats
+#[derive(Default)]
+pub struct MemoryBackend {
+    /// Keyed by (chat_id, turn_id)
+    turns: BTreeMap<(String, String), StoredTurn>,
+    ended: HashMap<String, u64>,
+}
+
+/// Per-model totals, most expensive first
+fn usage_by_model<'a>(turns: impl Iterator<Item = &'a StoredTurn>) -> Vec<ModelUsage> {
+    let mut models: BTreeMap<&str, ModelUsage> = BTreeMap::new();
+    for turn in turns {
+        let usage = models
+            .entry(turn.stats.model.as_str())
+            .or_insert_with(|| ModelUsage {
+                model: turn.stats.model.clone(),
+                turns: 0,
+                input_tokens: 0,
+                output_tokens: 0,
+                estimated_cost_usd: 0.0,
+            });
+        usage.turns += 1;
+        usage.input_tokens += turn.stats.input_tokens;
+        usage.output_tokens += turn.stats.output_tokens;
+        usage.estimated_cost_usd += turn.cost_usd;
+    }
+    let mut models: Vec<ModelUsage> = models.into_values().collect();
+    models.sort_by(|a, b| b.estimated_cost_usd.total_cmp(&a.estimated_cost_usd));
+    models
+}
+
+impl MemoryBackend {
+    fn chat_turns<'a>(&'a self, chat_id: &'a str) -> impl Iterator<Item = &'a StoredTurn> {
+        self.turns
+            .values()
+            .filter(move |turn| turn.stats.chat_id == chat_id)
+    }
+}
+
+impl HistoryBackend for MemoryBackend {
+    fn record_turn(&mut self, stats: &TurnStats, cost_usd: f64) -> Result<(), String> {
+        self.turns.insert(
+            (stats.chat_id.clone(), stats.turn_id.clone()),
+            StoredTurn {
+                stats: stats.clone(),
+                cost_usd,
+            },
+        );
+        // A new turn reopens an ended session, as in SQLite
+        self.ended.remove(&stats.chat_id);
+        Ok(())
+    }
+
+    fn end_session(
+        &mut self,
+        chat_id: &str,
+        ended_at: u64,
+    ) -> Result<Option<ChatSummaryStats>, String> {
+        if self.chat_turns(chat_id).next().is_some() {
+            self.ended.insert(chat_id.to_string(), ended_at);
+        }
+        self.summary(chat_id)
+    }
+
+    fn summary(&mut self, chat_id: &str) -> Result<Option<ChatSummaryStats>, String> {
+        let turns: Vec<&StoredTurn> = self.chat_turns(chat_id).collect();
+        let Some(started_at) = turns.iter().map(|turn| turn.stats.started_at).min() else {
+            return Ok(None);
+        };
+        let last_activity_at = turns
+            .iter()
+            .map(|turn| turn.ended_at())
+            .max()
+            .unwrap_or(started_at);
+        let ended_at = self.ended.get(chat_id).copied();
+        let end = ended_at.unwrap_or(last_activity_at).max(started_at);
+        Ok(Some(ChatSummaryStats {
+            chat_id: chat_id.to_string(),
+            turns: turns.len() as u32,
+            input_tokens: turns.iter().map(|turn| turn.stats.input_tokens).sum(),
+            output_tokens: turns.iter().map(|turn| turn.stats.output_tokens).sum(),
+            cached_tokens: turns.iter().map(|turn| turn.stats.cached_tokens).sum(),
+            estimated_cost_usd: turns.iter().map(|turn| turn.cost_usd).sum(),
+            tool_calls: turns.iter().map(|turn| turn.stats.tool_calls).sum(),
+            started_at,
+            ended_at,
+            duration_ms: end - started_at,
+            active_ms: turns
+                .iter()
+                .map(|turn| turn.ended_at() - turn.stats.started_at)
+                .sum(),
+            models: usage_by_model(turns.into_iter()),
+        }))
+    }
+
+    fn project_activity(&mut self, start: u64, end: u64) -> Result<Vec<ProjectActivity>, String> {
+        let mut by_project: BTreeMap<Option<&str>, Vec<&StoredTurn>> = BTreeMap::new();
+        for turn in self.turns.values() {
+            if (start..end).contains(&turn.ended_at()) {
+                by_project
+                    .entry(turn.stats.project.as_deref())
+                    .or_default()
+                    .push(turn);
+            }
+        }
+
+        let mut projects: Vec<ProjectActivity> = by_project
+            .into_iter()
+            .map(|(project, turns)| {
+                let sessions: HashSet<&str> = turns
+                    .iter()
+                    .map(|turn| turn.stats.chat_id.as_str())
+                    .collect();
+                ProjectActivity {
+                    project: project.map(str::to_string),
+                    sessions: sessions.len() as u32,
+                    turns: turns.len() as u32,
+                    tool_calls: turns.iter().map(|turn| turn.stats.tool_calls).sum(),
+                    changes_applied: turns.iter().map(|turn| turn.stats.changes_applied).sum(),
+                    tests_run: turns.iter().map(|turn| turn.stats.tests_run).sum(),
+                    input_tokens: turns.iter().map(|turn| turn.stats.input_tokens).sum(),
+                    output_tokens: turns.iter().map(|turn| turn.stats.output_tokens).sum(),
+                    estimated_cost_usd: turns.iter().map(|turn| turn.cost_usd).sum(),
+                    active_ms: turns
+                        .iter()
+                        .map(|turn| turn.ended_at() - turn.stats.started_at)
+                        .sum(),
+                    models: usage_by_model(turns.into_iter()),
+                }
+            })
+            .collect();
+        projects.sort_by(|a, b| b.estimated_cost_usd.total_cmp(&a.estimated_cost_usd));
+        Ok(projects)
+    }
+}
+
+/// Per-project totals for turns that ended in `[start, end)` (unix ms).
+/// Incognito chats are never included.
+pub fn project_activity(start: u64, end: u64) -> Result<Vec<ProjectActivity>, String> {
+    HISTORY.lock().persistent.project_activity(start, end)
+}
+
+/// Record usage for a finished turn and update the chat's session totals
+#[tauri::command]
+pub fn record_turn_stats(stats: TurnStats) -> Result<(), String> {
+    let cost = stats.estimated_cost();
+    let mut store = HISTORY.lock();
+    if stats.incognito {
+        store.incognito_chats.insert(stats.chat_id.clone());
+    }
+    store.backend_for(&stats.chat_id).record_turn(&stats, cost)
+}
+
+/// Mark a chat session as ended and return its final summary
+#[tauri::command]
+pub fn end_chat_session(chat_id: String) -> Result<Option<ChatSummaryStats>, String> {
+    HISTORY
+        .lock()
+        .backend_for(&chat_id)
+        .end_session(&chat_id, chrono_lite_timestamp())
+}
+
+/// Get token usage, estimated cost, tool calls, and duration for a chat
+#[tauri::command]
+pub fn get_chat_summary_stats(chat_id: String) -> Result<Option<ChatSummaryStats>, String> {
+    HISTORY.lock().backend_for(&chat_id).summary(&chat_id)
+}
@@ -0,0 +1,189 @@
+//! Request Inspector
+//!
+//! A dev-mode ring buffer of the most recent bridge requests and what Studio
+//! answered, for people building new plugin tools. Entries can be listed,
+//! inspected and sent to Studio again through Tauri commands or the bridge's
+//! `/stud/inspect` endpoints, instead of crafting curl calls by hand.
+//! Recording is on in debug builds and otherwise behind
+//! `bridge.record_requests` in the config.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+use crate::bridge::{self, chrono_lite_timestamp, StudioRequest, StudioResponse};
+use crate::config;
+
+const MAX_RECORDED: usize = 100;
+/// Response bodies are cut to this size; the full body went to the caller
+const MAX_RECORDED_BODY_BYTES: usize = 64 * 1024;
+
+lazy_static::lazy_static! {
+    static ref RECORDED: Mutex<VecDeque<RecordedRequest>> = Mutex::new(VecDeque::new());
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub body: String,
+    pub content_type: Option<String>,
+    /// The body was longer than the inspector keeps
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedRequest {
+    pub id: String,
+    pub request: StudioRequest,
+    /// None when the request was cancelled or timed out
+    pub response: Option<RecordedResponse>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Unix ms when the request was queued
+    pub timestamp: u64,
+    /// Set on replays: the recorded request this one re-sent
+    pub replay_of: Option<String>,
+}
+
+/// A recorded request without bodies, for listing
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedSummary {
+    pub id: String,
+    pub path: String,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub timestamp: u64,
+    pub replay_of: Option<String>,
+}
+
+impl From<&RecordedRequest> for RecordedSummary {
+    fn from(recorded: &RecordedRequest) -> Self {
+        Self {
+            id: recorded.id.clone(),
+            path: recorded.request.path.clone(),
+            status: recorded.response.as_ref().map(|response| response.status),
+            error: recorded.error.clone(),
+            duration_ms: recorded.duration_ms,
+            timestamp: recorded.timestamp,
+            replay_of: recorded.replay_of.clone(),
+        }
+    }
+}
+
+/// Whether requests are being recorded
+pub fn enabled() -> bool {
+    cfg!(debug_assertions) || config::current().bridge.record_requests
+}
+
+fn truncate(body: &str) -> (String, bool) {
+    if body.len() <= MAX_RECORDED_BODY_BYTES {
+        return (body.to_string(), false);
+    }
+    let mut end = MAX_RECORDED_BODY_BYTES;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    (body[..end].to_string(), true)
+}
+
+/// Keep a finished request, dropping the oldest once the buffer is full
+pub(crate) fn record(
+    id: &str,
+    request: StudioRequest,
+    outcome: Result<&StudioResponse, &str>,
+    duration_ms: u64,
+) {
+    let (response, error) = match outcome {
+        Ok(response) => {
+            let (body, truncated) = truncate(&response.body);
+            let recorded = RecordedResponse {
+                status: response.status,
+                body,
+                content_type: response.content_type.clone(),
+                truncated,
+            };
+            (Some(recorded), None)
+        }
+        Err(message) => (None, Some(message.to_string())),
+    };
+    let mut recorded = RECORDED.lock();
+    if recorded.len() >= MAX_RECORDED {
+        recorded.pop_front();
+    }
+    recorded.push_back(RecordedRequest {
+        id: id.to_string(),
+        request,
+        response,
+        error,
+        duration_ms,
+        timestamp: chrono_lite_timestamp().saturating_sub(duration_ms),
+        replay_of: None,
+    });
+}
+
+/// Recorded requests, newest first, optionally only those whose path contains `path`
+pub fn list(path: Option<&str>) -> Vec<RecordedSummary> {
+    RECORDED
+        .lock()
+        .iter()
+        .rev()
+        .filter(|recorded| path.is_none_or(|path| recorded.request.path.contains(path)))
+        .map(RecordedSummary::from)
+        .collect()
+}
+
+pub fn get(id: &str) -> Option<RecordedRequest> {
+    RECORDED
+        .lock()
+        .iter()
+        .find(|recorded| recorded.id == id)
+        .cloned()
+}
+
+/// Send a recorded request to Studio again and return the new recording
+pub async fn replay(id: &str) -> Result<RecordedRequest, String> {
+    if !enabled() {
+        return Err(
+            "Request recording is off; set bridge.record_requests in the config".to_string(),
+        );
+    }
+    let original = get(id).ok_or_else(|| format!("No recorded request {}", id))?;
+    let replay_id = format!("replay_{}", uuid::Uuid::new_v4());
+    let mut request = original.request;
+    request.id = Some(replay_id.clone());
+    // Partial results belong to the original tool call, which is long finished
+    request.tool_call_id = None;
+    bridge::replay_request(request).await;
+
+    let mut recorded = RECORDED.lock();
+    let entry = recorded
+        .iter_mut()
+        .find(|recorded| recorded.id == replay_id)
+        .ok_or_else(|| "The replay finished but wasn't recorded".to_string())?;
+    entry.replay_of = Some(id.to_string());
+    Ok(entry.clone())
+}
+
+/// Recently recorded bridge requests, newest first
+#[tauri::command]
+pub fn list_recorded_requests(path: Option<String>) -> Vec<RecordedSummary> {
+    list(path.as_deref())
+}
+
+/// A recorded request with its body and Studio's response
+#[tauri::command]
+pub fn get_recorded_request(id: String) -> Result<RecordedRequest, String> {
+    get(&id).ok_or_else(|| format!("No recorded request {}", id))
+}
+
+/// Send a recorded request to Studio again
+#[tauri::command]
+pub async fn replay_recorded_request(id: String) -> Result<RecordedRequest, String> {
+    replay(&id).await
+}
+
+#[tauri::command]
+pub fn clear_recorded_requests() {
+    RECORDED.lock().clear();
+}
@@ -1,9 +1,65 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
+mod animation;
+mod attributes;
+mod audit;
+mod auth;
 mod bridge;
+mod bridge_log;
+mod checkpoints;
+mod config;
+mod device_auth;
+mod diagnostics;
+mod digest;
+mod docs;
+mod find_replace;
+mod folder_sync;
+mod guardrails;
+mod history;
+mod inspector;
+mod llm_proxy;
+mod local_socket;
+mod merge;
+mod metrics;
+mod models;
+mod moonwave;
+mod naming;
+mod net;
+mod oauth_providers;
+mod ollama;
+mod palette;
+mod pathfinding;
+mod patches;
+mod paths;
 mod plugin;
+mod print_debug;
+mod procgen;
+mod profiles;
+mod protocol;
+mod queue_journal;
+mod rate_limits;
+mod response_cache;
+mod router;
+mod sandbox;
+mod scaffold;
+mod selftest;
+mod storage;
+mod tags;
+mod templates;
+mod tokens;
+mod traces;
+mod uploads;
+mod usage;
+mod vault;
+mod watch;
+mod whats_new;
 
-use std::thread;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::Manager;
+
+/// How long exit waits for the local servers to stop and release their ports
+const SHUTDOWN_TIMEOUT_SECS: u64 = 3;
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -18,23 +74,185 @@ fn get_bridge_status() -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Start the bridge server in a separate thread with its own tokio runtime
-    thread::spawn(|| {
-        let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
-        rt.block_on(bridge::start_bridge_server());
-    });
+    bridge_log::init();
+
+    // Packaging pipelines run `stud --selftest` to check a build without the UI
+    if selftest::requested() {
+        std::process::exit(selftest::run());
+    }
+
+    whats_new::init();
+
+    let auth_service = Arc::new(auth::AuthService::default());
+
+    // The bridge runs on Tauri's runtime; commands reach it through managed state
+    let bridge = bridge::BridgeHandle::shared();
+    bridge.spawn();
+    tauri::async_runtime::spawn(digest::run_scheduler());
+    tauri::async_runtime::spawn(checkpoints::run_scheduler());
+    tauri::async_runtime::spawn(tokens::run_refresher());
 
     tauri::Builder::default()
+        // Must come first: a second launch (say, from a stud:// link) passes its
+        // arguments to the running app and exits
+        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+            }
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_http::init())
+        .manage(auth_service)
+        .manage(bridge)
+        .setup(|app| {
+            bridge::set_app_handle(app.handle().clone());
+            auth::listen_for_deep_links(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             get_bridge_status,
+            bridge::get_bridge_endpoints,
+            bridge::rebind_bridge,
+            bridge::restart_bridge,
+            bridge::list_studio_sessions,
+            bridge::get_studio_metadata,
+            bridge::get_studio_events,
+            bridge::list_pending_requests,
+            bridge::cancel_bridge_request,
+            bridge::get_bridge_secret,
+            bridge_log::get_bridge_log,
+            bridge_log::open_log_folder,
+            traces::get_request_trace,
+            selftest::run_bridge_selftest,
+            diagnostics::run_diagnostics,
+            diagnostics::get_diagnostics,
+            diagnostics::clear_diagnostics,
+            inspector::list_recorded_requests,
+            inspector::get_recorded_request,
+            inspector::replay_recorded_request,
+            inspector::clear_recorded_requests,
+            auth::start_auth,
+            auth::stop_oauth_server,
+            auth::cancel_oauth_flow,
+            device_auth::start_device_auth,
+            device_auth::cancel_device_auth,
+            tokens::get_oauth_status,
+            tokens::list_accounts,
+            tokens::switch_account,
+            tokens::remove_account,
+            tokens::sign_out,
+            tokens::import_tokens,
+            vault::set_api_key,
+            vault::has_api_key,
+            vault::delete_api_key,
+            vault::set_endpoint_key,
+            vault::has_endpoint_key,
+            vault::set_proxy_password,
+            vault::has_proxy_password,
+            net::test_connectivity,
+            llm_proxy::test_endpoint,
+            llm_proxy::cancel_generation,
+            usage::get_usage_stats,
+            rate_limits::get_rate_limit_status,
+            response_cache::clear_response_cache,
+            ollama::detect_ollama,
+            ollama::list_ollama_models,
             plugin::check_plugin_installed,
             plugin::install_plugin,
             plugin::get_plugins_path,
-            plugin::check_roblox_studio_installed
+            plugin::check_roblox_studio_installed,
+            history::record_turn_stats,
+            history::end_chat_session,
+            history::get_chat_summary_stats,
+            models::get_model_capabilities,
+            models::check_model_request,
+            models::refresh_model_capabilities,
+            config::get_config,
+            config::set_config,
+            router::route_model,
+            router::get_routing_trace,
+            templates::list_templates,
+            templates::save_selection_as_template,
+            templates::insert_template,
+            templates::delete_template,
+            templates::export_instance,
+            procgen::start_procgen_job,
+            procgen::cancel_procgen_job,
+            procgen::list_procgen_jobs,
+            palette::extract_palette,
+            palette::preview_palette_transform,
+            palette::apply_palette_transform,
+            animation::list_animations,
+            animation::export_keyframes,
+            animation::validate_animation_ids,
+            pathfinding::debug_paths,
+            pathfinding::clear_path_debug,
+            scaffold::list_scaffold_recipes,
+            scaffold::preview_scaffold,
+            scaffold::run_scaffold,
+            watch::add_watch,
+            watch::remove_watch,
+            watch::list_watches,
+            watch::get_watch_history,
+            watch::clear_watch_history,
+            print_debug::inject_debug_prints,
+            print_debug::list_debug_injections,
+            print_debug::get_debug_output,
+            print_debug::remove_debug_prints,
+            docs::generate_place_docs,
+            moonwave::get_module_api_context,
+            digest::generate_weekly_digest,
+            digest::list_digests,
+            naming::check_naming,
+            tags::list_tags,
+            tags::get_instance_tags,
+            tags::update_tags,
+            attributes::validate_attributes,
+            audit::list_audit_log,
+            audit::get_change_context,
+            merge::record_script_base,
+            merge::merge_script_edit,
+            merge::resolve_script_merge,
+            merge::discard_script_merge,
+            find_replace::preview_find_replace,
+            find_replace::apply_find_replace,
+            find_replace::find_replace_all,
+            find_replace::discard_find_replace,
+            folder_sync::start_folder_sync,
+            folder_sync::stop_folder_sync,
+            folder_sync::get_folder_sync_status,
+            folder_sync::pull_folder_sync,
+            folder_sync::resolve_folder_sync_conflict,
+            checkpoints::list_checkpoints,
+            checkpoints::get_checkpoint,
+            checkpoints::create_checkpoint,
+            checkpoints::restore_checkpoint,
+            checkpoints::delete_checkpoint,
+            patches::export_patch_bundle,
+            patches::load_patch_bundle,
+            patches::apply_patch_bundle,
+            patches::discard_patch_changes,
+            profiles::list_place_profiles,
+            profiles::get_place_profile,
+            profiles::save_place_profile,
+            profiles::delete_place_profile,
+            guardrails::get_guardrail_status,
+            guardrails::approve_script_edits,
+            whats_new::get_whats_new,
+            whats_new::mark_whats_new_read,
+            storage::clean_storage
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                let bridge = app.state::<bridge::BridgeHandle>();
+                if !bridge.shutdown(Duration::from_secs(SHUTDOWN_TIMEOUT_SECS)) {
+                    println!("[Stud Bridge] Servers did not stop in time");
+                }
+            }
+        });
 }
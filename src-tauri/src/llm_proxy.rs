@@ -0,0 +1,1057 @@
+//! Provider Proxy
+//!
+//! Forwards model requests from the webview and adds the credentials there,
+//! so the webview never holds them. Served by the proxy server at
+//! `/llm/{provider}/{path}`, where each provider has its own upstream, headers
+//! and auth:
+//!
+//! - `openai`: `https://api.openai.com/{path}`, API key from the vault
+//! - `anthropic`: `https://api.anthropic.com/{path}`, API key from the vault
+//! - `gemini`: `https://generativelanguage.googleapis.com/{path}`, API key from the vault
+//! - `chatgpt`: the Codex API, with the ChatGPT sign-in (see `tokens`)
+//! - `openrouter`: `https://openrouter.ai/api/{path}`, API key from the vault
+//! - `ollama`: a local Ollama server, with no credentials (see `ollama`)
+//!
+//! `/llm/openai/v1/chat/completions` goes to
+//! `https://api.openai.com/v1/chat/completions`. Only the headers a provider
+//! needs are passed on, and credentials the caller sends are dropped, except
+//! that a Codex request may bring its own Authorization. Responses stream back
+//! as they arrive. `/codex/responses` is kept as an alias for
+//! `/llm/chatgpt/responses`. Every route wants the bridge secret in
+//! `X-Stud-Secret`, like the bridge's own, so other local programs can't
+//! spend the user's keys.
+//!
+//! OpenAI-compatible endpoints like Azure OpenAI or a vLLM gateway are set up
+//! as profiles in the config's `llm` section and served at
+//! `/llm/custom/{name}/{path}`. Naming one as `openai_endpoint` sends the
+//! `openai` provider's requests there instead of to api.openai.com.
+//!
+//! Each forwarded request belongs to a generation, named by the caller's
+//! `X-Stud-Generation-Id` (or made up and sent back in the same header).
+//! `cancel_generation`, or `DELETE /llm/generations/{id}`, drops its upstream
+//! connections so a stopped generation stops costing tokens.
+//!
+//! While an SSE response goes quiet, `: keep-alive` comments are sent between
+//! events every `llm.heartbeat_secs`, so proxies and load balancers on the way
+//! don't drop a long generation. If the upstream connection breaks partway,
+//! the stream ends with an `error` event instead of being cut off.
+//!
+//! Waits on an upstream are bounded by `llm.timeouts`: connecting, getting a
+//! response started (per attempt) and silence while it streams. A wait that
+//! runs out is reported as a JSON error, with a 504 before the response has
+//! started or as an SSE `error` event after:
+//! `{"type": "error", "error": {"type": "timeout", "timeout": "idle", "seconds": 180, ...}}`
+
+use axum::body::Body;
+use axum::extract::{DefaultBodyLimit, Path};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{any, delete, post};
+use axum::Router;
+use bytes::Bytes;
+use futures_util::future::{AbortHandle, Abortable};
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::bridge;
+use crate::config::{self, RetryConfig, TimeoutConfig};
+use crate::net;
+use crate::oauth_providers::OAuthProvider;
+use crate::ollama;
+use crate::rate_limits;
+use crate::response_cache::{self, Recording};
+use crate::tokens;
+use crate::usage::UsageMeter;
+use crate::vault::{self, KeyProvider};
+
+/// Anthropic rejects requests without a version; used when the caller sends none
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const OPENROUTER_REFERER: &str = "https://github.com/madebyshaurya/stud";
+/// Request headers passed on to OpenAI-compatible endpoints
+const OPENAI_HEADERS: &[&str] = &["content-type", "accept"];
+const TEST_TIMEOUT_SECS: u64 = 15;
+/// Largest request the proxy takes; prompts with images run well past axum's 2 MB default
+const MAX_REQUEST_BYTES: usize = 64 * 1024 * 1024;
+pub(crate) const GENERATION_HEADER: &str = "x-stud-generation-id";
+/// Project (the connected place) a request's token usage is counted under
+pub(crate) const PROJECT_HEADER: &str = "x-stud-project";
+/// Returned for requests cancelled before a response came back (nginx's
+/// "client closed request")
+const CANCELLED_STATUS: u16 = 499;
+/// An SSE comment, which clients skip
+const KEEP_ALIVE: &[u8] = b": keep-alive\n\n";
+
+static NEXT_REQUEST: AtomicU64 = AtomicU64::new(1);
+
+lazy_static::lazy_static! {
+    /// Requests being forwarded, by a serial of their own; a generation can
+    /// make several (tool call rounds, retries from the frontend)
+    static ref IN_FLIGHT: Mutex<HashMap<u64, InFlight>> = Mutex::new(HashMap::new());
+}
+
+struct InFlight {
+    generation: String,
+    /// Stops the request while it waits for a response
+    send: AbortHandle,
+    /// Stops the response while it streams back
+    stream: AbortHandle,
+}
+
+/// Takes a request out of `IN_FLIGHT` once its response is done with
+struct InFlightGuard(u64);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.lock().remove(&self.0);
+    }
+}
+
+/// Upstream statuses worth another try: rate limits and transient server errors
+const RETRY_STATUSES: &[u16] = &[429, 500, 502, 503, 504];
+
+/// Vendors OpenRouter files bare model names under, by name prefix
+const OPENROUTER_VENDORS: &[(&str, &str)] = &[
+    ("gpt-", "openai"),
+    ("o1", "openai"),
+    ("o3", "openai"),
+    ("o4", "openai"),
+    ("claude-", "anthropic"),
+    ("gemini-", "google"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LlmProvider {
+    OpenAi,
+    Anthropic,
+    Gemini,
+    /// ChatGPT Plus/Pro through the Codex API
+    ChatGpt,
+    /// Many vendors' models behind one OpenAI-compatible API
+    OpenRouter,
+    Ollama,
+}
+
+impl LlmProvider {
+    fn base_url(self) -> String {
+        match self {
+            LlmProvider::OpenAi => "https://api.openai.com".to_string(),
+            LlmProvider::Anthropic => "https://api.anthropic.com".to_string(),
+            LlmProvider::Gemini => "https://generativelanguage.googleapis.com".to_string(),
+            LlmProvider::ChatGpt => "https://chatgpt.com/backend-api/codex".to_string(),
+            LlmProvider::OpenRouter => "https://openrouter.ai/api".to_string(),
+            LlmProvider::Ollama => ollama::base_url(),
+        }
+    }
+
+    /// Request headers passed on to the provider; everything else is dropped
+    fn forwarded_headers(self) -> &'static [&'static str] {
+        match self {
+            LlmProvider::Anthropic => &[
+                "content-type",
+                "accept",
+                "anthropic-version",
+                "anthropic-beta",
+            ],
+            _ => OPENAI_HEADERS,
+        }
+    }
+
+    fn key_provider(self) -> Option<KeyProvider> {
+        match self {
+            LlmProvider::OpenAi => Some(KeyProvider::OpenAi),
+            LlmProvider::Anthropic => Some(KeyProvider::Anthropic),
+            LlmProvider::Gemini => Some(KeyProvider::Gemini),
+            LlmProvider::OpenRouter => Some(KeyProvider::OpenRouter),
+            LlmProvider::ChatGpt | LlmProvider::Ollama => None,
+        }
+    }
+
+    /// Add the provider's credentials and required headers to a request
+    async fn authorize(
+        self,
+        req: reqwest::RequestBuilder,
+        headers: &HeaderMap,
+    ) -> Result<reqwest::RequestBuilder, Response> {
+        if self == LlmProvider::ChatGpt {
+            return codex_credentials(req, headers).await;
+        }
+        // Local servers don't need any
+        let Some(key_provider) = self.key_provider() else {
+            return Ok(req);
+        };
+        let key = match vault::api_key(key_provider) {
+            Ok(Some(key)) => key,
+            Ok(None) => {
+                return Err(error(
+                    StatusCode::UNAUTHORIZED,
+                    format!("No {:?} API key saved", key_provider),
+                ))
+            }
+            Err(e) => return Err(error(StatusCode::INTERNAL_SERVER_ERROR, e)),
+        };
+        Ok(match self {
+            LlmProvider::Anthropic if !headers.contains_key("anthropic-version") => req
+                .header("x-api-key", key)
+                .header("anthropic-version", ANTHROPIC_VERSION),
+            LlmProvider::Anthropic => req.header("x-api-key", key),
+            LlmProvider::Gemini => req.header("x-goog-api-key", key),
+            // OpenRouter credits requests to the app these name
+            LlmProvider::OpenRouter => req
+                .bearer_auth(key)
+                .header("HTTP-Referer", OPENROUTER_REFERER)
+                .header("X-Title", "Stud"),
+            _ => req.bearer_auth(key),
+        })
+    }
+}
+
+pub(crate) fn error(status: StatusCode, message: String) -> Response {
+    (status, [(header::CONTENT_TYPE, "text/plain")], message).into_response()
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Codex requests with their own Authorization are sent as they are. The rest
+/// use the backend's ChatGPT sign-in: the account their ChatGPT-Account-Id
+/// names, or the active one.
+async fn codex_credentials(
+    req: reqwest::RequestBuilder,
+    headers: &HeaderMap,
+) -> Result<reqwest::RequestBuilder, Response> {
+    let account = header_str(headers, "chatgpt-account-id");
+    if let Some(auth_header) = header_str(headers, "authorization") {
+        let req = req.header("Authorization", auth_header);
+        return Ok(match account {
+            Some(account) => req.header("ChatGPT-Account-Id", account),
+            None => req,
+        });
+    }
+    let credentials = tokens::credentials(OAuthProvider::ChatGpt, account)
+        .await
+        .map_err(|e| error(StatusCode::UNAUTHORIZED, e))?;
+    let req = req.bearer_auth(credentials.access);
+    Ok(match credentials.account_id {
+        Some(account) => req.header("ChatGPT-Account-Id", account),
+        None => req,
+    })
+}
+
+/// An OpenAI-compatible endpoint, such as Azure OpenAI or a self-hosted gateway.
+/// Its API key is kept in the vault under the profile's name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EndpointProfile {
+    /// Stands in for `https://api.openai.com`, e.g.
+    /// `https://my-resource.openai.azure.com/openai` or `http://gpu-box:8000`
+    pub base_url: String,
+    /// Sent with every request
+    pub headers: BTreeMap<String, String>,
+    /// Added to every request's query, e.g. Azure's `api-version`
+    pub query: BTreeMap<String, String>,
+    /// Header the API key goes in (Azure wants `api-key`); by default it's
+    /// sent as a bearer token
+    pub auth_header: Option<String>,
+}
+
+impl EndpointProfile {
+    pub fn validate(&self, name: &str) -> Result<(), String> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Endpoint name '{}' may only use letters, digits, '-' and '_'",
+                name
+            ));
+        }
+        let url = reqwest::Url::parse(&self.base_url)
+            .map_err(|e| format!("Endpoint '{}' has an invalid base URL: {}", name, e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("Endpoint '{}' must use http or https", name));
+        }
+        if url.query().is_some() {
+            return Err(format!(
+                "Endpoint '{}': put query parameters in `query`, not the base URL",
+                name
+            ));
+        }
+        for (header, value) in &self.headers {
+            let header = HeaderName::from_bytes(header.as_bytes()).map_err(|_| {
+                format!(
+                    "Endpoint '{}' has an invalid header name '{}'",
+                    name, header
+                )
+            })?;
+            if header == header::HOST || header == header::CONTENT_LENGTH {
+                return Err(format!(
+                    "Endpoint '{}' can't set the {} header",
+                    name, header
+                ));
+            }
+            HeaderValue::from_str(value).map_err(|_| {
+                format!(
+                    "Endpoint '{}' has an invalid value for header '{}'",
+                    name, header
+                )
+            })?;
+        }
+        if let Some(header) = &self.auth_header {
+            HeaderName::from_bytes(header.as_bytes()).map_err(|_| {
+                format!(
+                    "Endpoint '{}' has an invalid auth header '{}'",
+                    name, header
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        name: &str,
+        method: Method,
+        path: &str,
+        query: Option<&str>,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        let mut url = reqwest::Url::parse(&format!(
+            "{}/{}",
+            self.base_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        ))
+        .map_err(|e| format!("Endpoint '{}' has an invalid base URL: {}", name, e))?;
+        url.set_query(query);
+        if !self.query.is_empty() {
+            url.query_pairs_mut().extend_pairs(&self.query);
+        }
+
+        let mut req = client.request(method, url);
+        for (header, value) in &self.headers {
+            req = req.header(header, value);
+        }
+        if let Some(key) = vault::endpoint_key(name)? {
+            req = match self.auth_header.as_deref() {
+                Some(header) if !header.eq_ignore_ascii_case("authorization") => {
+                    req.header(header, key)
+                }
+                _ => req.bearer_auth(key),
+            };
+        }
+        Ok(req)
+    }
+}
+
+/// What a connection test found
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointTest {
+    pub status: u16,
+    pub latency_ms: u64,
+    /// Models the endpoint lists, if it lists any
+    pub models: Vec<String>,
+}
+
+/// Check that an endpoint profile is reachable and accepts its key, by
+/// listing its models
+#[tauri::command]
+pub async fn test_endpoint(name: String) -> Result<EndpointTest, String> {
+    let profile = config::current()
+        .llm
+        .endpoints
+        .remove(&name)
+        .ok_or_else(|| format!("No endpoint profile named '{}'", name))?;
+    profile.validate(&name)?;
+
+    let started = std::time::Instant::now();
+    let response = profile
+        .request(&net::client(), &name, Method::GET, "v1/models", None)?
+        .timeout(Duration::from_secs(TEST_TIMEOUT_SECS))
+        .send()
+        .await
+        .map_err(|e| format!("Endpoint '{}': {}", name, net::describe(&e)))?;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(format!("'{}' rejected the API key ({})", name, status));
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "'{}' returned {}: {}",
+            name,
+            status,
+            body.chars().take(200).collect::<String>()
+        ));
+    }
+    let models = response
+        .json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|body| {
+            body["data"].as_array().map(|models| {
+                models
+                    .iter()
+                    .filter_map(|model| model["id"].as_str().map(str::to_string))
+                    .collect()
+            })
+        })
+        .unwrap_or_default();
+    Ok(EndpointTest {
+        status: status.as_u16(),
+        latency_ms,
+        models,
+    })
+}
+
+/// OpenRouter names models `vendor/model`: qualify the bare names the other
+/// providers use, and ask for usage (with cost) in the last streamed chunk
+fn openrouter_body(body: Bytes) -> Bytes {
+    let Ok(mut request) = serde_json::from_slice::<serde_json::Value>(&body) else {
+        return body;
+    };
+    let Some(object) = request.as_object_mut() else {
+        return body;
+    };
+    if let Some(model) = object
+        .get("model")
+        .and_then(|model| model.as_str())
+        .and_then(openrouter_model)
+    {
+        object.insert("model".to_string(), model.into());
+    }
+    object
+        .entry("usage")
+        .or_insert_with(|| serde_json::json!({ "include": true }));
+    serde_json::to_vec(&request)
+        .map(Bytes::from)
+        .unwrap_or(body)
+}
+
+fn openrouter_model(model: &str) -> Option<String> {
+    if model.contains('/') {
+        return None;
+    }
+    let (_, vendor) = OPENROUTER_VENDORS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))?;
+    // OpenRouter only lists Anthropic's undated IDs
+    let model = match model.rsplit_once('-') {
+        Some((name, date))
+            if *vendor == "anthropic"
+                && date.len() == 8
+                && date.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            name
+        }
+        _ => model,
+    };
+    Some(format!("{}/{}", vendor, model))
+}
+
+async fn send(
+    client: &reqwest::Client,
+    provider: LlmProvider,
+    path: &str,
+    method: Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Response {
+    if provider == LlmProvider::Ollama && path == "v1/chat/completions" {
+        return ollama::chat(client, body).await;
+    }
+    let body = if provider == LlmProvider::OpenRouter && path == "v1/chat/completions" {
+        openrouter_body(body)
+    } else {
+        body
+    };
+    if provider == LlmProvider::OpenAi {
+        if let Some(name) = config::current().llm.openai_endpoint {
+            return send_to_endpoint(client, &name, path, method, uri, headers, body).await;
+        }
+    }
+    let mut url = format!("{}/{}", provider.base_url(), path);
+    if let Some(query) = uri.query() {
+        url.push('?');
+        url.push_str(query);
+    }
+    let req = client.request(method, url).body(body);
+    let req = forward_headers(req, headers, provider.forwarded_headers());
+    let req = match provider.authorize(req, headers).await {
+        Ok(req) => req,
+        Err(response) => return response,
+    };
+    relay(req, &format!("{:?}", provider).to_lowercase(), headers).await
+}
+
+async fn send_to_endpoint(
+    client: &reqwest::Client,
+    name: &str,
+    path: &str,
+    method: Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(profile) = config::current().llm.endpoints.remove(name) else {
+        return error(
+            StatusCode::NOT_FOUND,
+            format!("No endpoint profile named '{}'", name),
+        );
+    };
+    let req = match profile.request(client, name, method, path, uri.query()) {
+        Ok(req) => req.body(body),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+    relay(
+        forward_headers(req, headers, OPENAI_HEADERS),
+        &format!("custom/{}", name),
+        headers,
+    )
+    .await
+}
+
+fn forward_headers(
+    mut req: reqwest::RequestBuilder,
+    headers: &HeaderMap,
+    names: &[&str],
+) -> reqwest::RequestBuilder {
+    for name in names {
+        if let Some(value) = headers.get(*name) {
+            req = req.header(*name, value);
+        }
+    }
+    req
+}
+
+/// Sent before each retry so the UI can show that a generation is retrying
+#[derive(Debug, Clone, Serialize)]
+struct RetryEvent {
+    upstream: String,
+    /// The retry about to be made, from 1
+    attempt: u32,
+    max_retries: u32,
+    delay_ms: u64,
+    reason: String,
+}
+
+/// How long the upstream asked to be left alone: OpenAI's `retry-after-ms`,
+/// or `Retry-After` in seconds
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name)?.to_str().ok();
+    if let Some(ms) = header("retry-after-ms").and_then(|ms| ms.trim().parse::<f64>().ok()) {
+        return Some(Duration::from_millis(ms.max(0.0) as u64));
+    }
+    header("retry-after")
+        .and_then(|secs| secs.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Exponential backoff with jitter, so parallel requests don't retry in step
+fn backoff(retry: &RetryConfig, attempt: u32) -> Duration {
+    let delay = retry
+        .base_delay_ms
+        .saturating_mul(1 << attempt.min(16))
+        .min(retry.max_delay_ms);
+    let mut buf = [0u8; 8];
+    let random = getrandom::fill(&mut buf)
+        .map(|_| u64::from_le_bytes(buf))
+        .unwrap_or_default();
+    Duration::from_millis(delay / 2 + random % (delay / 2 + 1))
+}
+
+/// Which wait on an upstream ran out
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Timeout {
+    Connect,
+    FirstByte,
+    Idle,
+}
+
+impl Timeout {
+    fn secs(self, timeouts: &TimeoutConfig) -> u64 {
+        match self {
+            Timeout::Connect => timeouts.connect_secs,
+            Timeout::FirstByte => timeouts.first_byte_secs,
+            Timeout::Idle => timeouts.idle_secs,
+        }
+    }
+
+    /// Error body for it, shaped like the error events streams end with
+    fn error(self, upstream: &str) -> serde_json::Value {
+        let secs = self.secs(&config::current().llm.timeouts);
+        let message = match self {
+            Timeout::Connect => format!("Couldn't connect to {} within {}s", upstream, secs),
+            Timeout::FirstByte => format!("{} didn't start responding within {}s", upstream, secs),
+            Timeout::Idle => format!("{} sent nothing for {}s", upstream, secs),
+        };
+        println!("[Stud Proxy] {}", message);
+        serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": "timeout",
+                "timeout": self,
+                "seconds": secs,
+                "upstream": upstream,
+                "message": message,
+            },
+        })
+    }
+}
+
+/// Why a request got no response
+enum SendError {
+    Request(reqwest::Error),
+    TimedOut(Timeout),
+}
+
+/// Send one attempt, giving up if the response doesn't start in time
+async fn send_once(req: reqwest::RequestBuilder) -> Result<reqwest::Response, SendError> {
+    let first_byte_secs = config::current().llm.timeouts.first_byte_secs;
+    if first_byte_secs == 0 {
+        return req.send().await.map_err(SendError::Request);
+    }
+    match tokio::time::timeout(Duration::from_secs(first_byte_secs), req.send()).await {
+        Ok(result) => result.map_err(SendError::Request),
+        Err(_) => Err(SendError::TimedOut(Timeout::FirstByte)),
+    }
+}
+
+/// Send a request upstream. Rate limits, transient server errors and refused
+/// connections are retried, since nothing has been streamed back yet. A
+/// response that's slow to start isn't, as the upstream may still be working on it.
+async fn send_with_retries(
+    req: reqwest::RequestBuilder,
+    upstream: &str,
+) -> Result<reqwest::Response, SendError> {
+    let retry = config::current().llm.retry;
+    let mut attempt = 0;
+    let result = loop {
+        // The last attempt (or a body that can't be sent twice) goes as it is
+        let Some(next) = req.try_clone().filter(|_| attempt < retry.max_retries) else {
+            break send_once(req).await;
+        };
+        let (reason, delay) = match send_once(next).await {
+            Ok(response) if RETRY_STATUSES.contains(&response.status().as_u16()) => {
+                let delay = match retry_after(response.headers()) {
+                    Some(delay) if delay.as_millis() as u64 > retry.max_delay_ms => {
+                        break Ok(response)
+                    }
+                    Some(delay) => delay,
+                    None => backoff(&retry, attempt),
+                };
+                (response.status().to_string(), delay)
+            }
+            Err(SendError::Request(e)) if e.is_connect() => {
+                (e.to_string(), backoff(&retry, attempt))
+            }
+            result => break result,
+        };
+        attempt += 1;
+        println!(
+            "[Stud Proxy] {} failed ({}), retry {}/{} in {}ms",
+            upstream,
+            reason,
+            attempt,
+            retry.max_retries,
+            delay.as_millis()
+        );
+        bridge::emit_event(
+            "llm-retry",
+            RetryEvent {
+                upstream: upstream.to_string(),
+                attempt,
+                max_retries: retry.max_retries,
+                delay_ms: delay.as_millis() as u64,
+                reason,
+            },
+        );
+        tokio::time::sleep(delay).await;
+    };
+    result
+}
+
+/// Send a request upstream and stream its response back, as part of the
+/// generation the caller's headers name
+async fn relay(req: reqwest::RequestBuilder, upstream: &str, headers: &HeaderMap) -> Response {
+    let generation = header_str(headers, GENERATION_HEADER)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let cache_key = response_cache::key(&req);
+    if let Some(response) = cache_key
+        .as_deref()
+        .and_then(|key| response_cache::replay(key, generation.clone()))
+    {
+        return response;
+    }
+    let serial = NEXT_REQUEST.fetch_add(1, Ordering::Relaxed);
+    let (send, send_registration) = AbortHandle::new_pair();
+    let (stream, stream_registration) = AbortHandle::new_pair();
+    IN_FLIGHT.lock().insert(
+        serial,
+        InFlight {
+            generation: generation.clone(),
+            send,
+            stream,
+        },
+    );
+    let guard = InFlightGuard(serial);
+
+    let Ok(result) = Abortable::new(send_with_retries(req, upstream), send_registration).await
+    else {
+        let status = StatusCode::from_u16(CANCELLED_STATUS).unwrap_or(StatusCode::BAD_REQUEST);
+        return error(status, "Generation cancelled".to_string());
+    };
+    match result {
+        Ok(response) => {
+            rate_limits::record(upstream, response.headers());
+            let status =
+                StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+            let content_type = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("application/json")
+                .to_string();
+            let llm = config::current().llm;
+            let sse = content_type.starts_with("text/event-stream");
+            let cached = cache_key.is_some();
+            let downstream = Downstream {
+                upstream: upstream.to_string(),
+                meter: UsageMeter::new(
+                    upstream,
+                    header_str(headers, PROJECT_HEADER),
+                    &content_type,
+                ),
+                recording: cache_key
+                    .filter(|_| status.is_success())
+                    .map(|key| Recording::new(key, status.as_u16(), &content_type)),
+                heartbeat: Some(Duration::from_secs(llm.heartbeat_secs))
+                    .filter(|_| sse && llm.heartbeat_secs > 0),
+                idle: Some(Duration::from_secs(llm.timeouts.idle_secs))
+                    .filter(|_| llm.timeouts.idle_secs > 0),
+                last_chunk: Instant::now(),
+                sse,
+                at_boundary: true,
+                done: false,
+                // Cancelling ends the stream early, which drops the upstream connection
+                chunks: Abortable::new(response.bytes_stream().boxed(), stream_registration),
+                _guard: guard,
+            };
+            let chunks = futures_util::stream::unfold(downstream, |mut downstream| async move {
+                let chunk = downstream.next().await?;
+                Some((chunk, downstream))
+            });
+            let mut response = (
+                status,
+                [
+                    (header::CONTENT_TYPE, content_type),
+                    (HeaderName::from_static(GENERATION_HEADER), generation),
+                ],
+                Body::from_stream(chunks),
+            )
+                .into_response();
+            if cached {
+                response.headers_mut().insert(
+                    HeaderName::from_static(response_cache::CACHE_HEADER),
+                    HeaderValue::from_static("miss"),
+                );
+            }
+            response
+        }
+        Err(SendError::Request(e)) if e.is_connect() && e.is_timeout() => {
+            timeout_error(upstream, Timeout::Connect)
+        }
+        Err(SendError::Request(e)) => error(StatusCode::BAD_GATEWAY, net::describe(&e)),
+        Err(SendError::TimedOut(timeout)) => timeout_error(upstream, timeout),
+    }
+}
+
+fn timeout_error(upstream: &str, timeout: Timeout) -> Response {
+    (
+        StatusCode::GATEWAY_TIMEOUT,
+        axum::Json(timeout.error(upstream)),
+    )
+        .into_response()
+}
+
+/// A response on its way back to the caller
+struct Downstream {
+    upstream: String,
+    chunks: Abortable<BoxStream<'static, reqwest::Result<Bytes>>>,
+    meter: UsageMeter,
+    recording: Option<Recording>,
+    /// For SSE streams, how long upstream can go quiet before a keep-alive is sent
+    heartbeat: Option<Duration>,
+    /// How long upstream can go quiet before the response is given up on
+    idle: Option<Duration>,
+    last_chunk: Instant,
+    /// Errors are sent as SSE events rather than by breaking the connection
+    sse: bool,
+    /// Whether what's been sent ends between events, where a comment can go
+    at_boundary: bool,
+    done: bool,
+    _guard: InFlightGuard,
+}
+
+impl Downstream {
+    async fn next(&mut self) -> Option<std::io::Result<Bytes>> {
+        if self.done {
+            return None;
+        }
+        let next = loop {
+            let idle_left = self
+                .idle
+                .map(|idle| idle.saturating_sub(self.last_chunk.elapsed()));
+            let Some(wait) = [self.heartbeat, idle_left].into_iter().flatten().min() else {
+                break self.chunks.next().await;
+            };
+            match tokio::time::timeout(wait, self.chunks.next()).await {
+                Ok(next) => break next,
+                Err(_) if idle_left.is_some_and(|left| left <= wait) => {
+                    return self.fail(
+                        Timeout::Idle.error(&self.upstream),
+                        std::io::ErrorKind::TimedOut,
+                    );
+                }
+                Err(_) if self.heartbeat.is_some() && self.at_boundary => {
+                    return Some(Ok(Bytes::from_static(KEEP_ALIVE)))
+                }
+                // Partway through an event a comment would corrupt it
+                Err(_) => {}
+            }
+        };
+        match next {
+            Some(Ok(chunk)) => {
+                self.last_chunk = Instant::now();
+                self.meter.feed(&chunk);
+                if let Some(recording) = &mut self.recording {
+                    recording.push(&chunk);
+                }
+                if !chunk.is_empty() {
+                    self.at_boundary = chunk.ends_with(b"\n\n") || chunk.ends_with(b"\r\n\r\n");
+                }
+                Some(Ok(chunk))
+            }
+            Some(Err(e)) => {
+                println!("[Stud Proxy] {} stream broke: {}", self.upstream, e);
+                let error = serde_json::json!({
+                    "type": "error",
+                    "error": { "type": "upstream_error", "message": net::describe(&e) },
+                });
+                self.fail(error, std::io::ErrorKind::ConnectionAborted)
+            }
+            None => {
+                self.done = true;
+                // Only responses that came back whole are cached
+                if let Some(recording) = self.recording.take().filter(|_| !self.chunks.is_aborted())
+                {
+                    recording.finish();
+                }
+                None
+            }
+        }
+    }
+
+    /// End the response early: with an SSE `error` event, in the shape
+    /// Anthropic uses (OpenAI-style clients read `error.message`), or for
+    /// other responses by breaking the connection
+    fn fail(
+        &mut self,
+        error: serde_json::Value,
+        kind: std::io::ErrorKind,
+    ) -> Option<std::io::Result<Bytes>> {
+        self.recording = None;
+        self.done = true;
+        if !self.sse {
+            return Some(Err(std::io::Error::new(kind, error.to_string())));
+        }
+        // A half-sent event is dropped by the client when the stream ends
+        self.at_boundary
+            .then(|| Ok(Bytes::from(format!("event: error\ndata: {}\n\n", error))))
+    }
+}
+
+/// Abort a generation's requests; returns how many were still running
+fn cancel(generation: &str) -> usize {
+    let in_flight = IN_FLIGHT.lock();
+    let mut cancelled = 0;
+    for request in in_flight
+        .values()
+        .filter(|request| request.generation == generation)
+    {
+        request.send.abort();
+        request.stream.abort();
+        cancelled += 1;
+    }
+    cancelled
+}
+
+/// Stop a generation's upstream requests. Returns whether any were running.
+#[tauri::command]
+pub fn cancel_generation(id: String) -> bool {
+    let cancelled = cancel(&id);
+    if cancelled > 0 {
+        println!(
+            "[Stud Proxy] Cancelled generation {} ({} requests)",
+            id, cancelled
+        );
+    }
+    cancelled > 0
+}
+
+async fn cancel_generation_route(Path(id): Path<String>) -> StatusCode {
+    if cancel_generation(id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+async fn forward(
+    Path((provider, path)): Path<(LlmProvider, String)>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    send(
+        &net::client(),
+        provider,
+        &path,
+        method,
+        &uri,
+        &headers,
+        body,
+    )
+    .await
+}
+
+async fn forward_endpoint(
+    Path((name, path)): Path<(String, String)>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    send_to_endpoint(&net::client(), &name, &path, method, &uri, &headers, body).await
+}
+
+/// The route the Codex proxy had before it served other providers
+async fn codex_responses(uri: Uri, headers: HeaderMap, body: Bytes) -> Response {
+    send(
+        &net::client(),
+        LlmProvider::ChatGpt,
+        "responses",
+        Method::POST,
+        &uri,
+        &headers,
+        body,
+    )
+    .await
+}
+
+/// Routes for the proxy server
+pub(crate) fn routes() -> Router {
+    Router::new()
+        .route("/llm/{provider}/{*path}", any(forward))
+        .route("/llm/custom/{name}/{*path}", any(forward_endpoint))
+        .route("/llm/generations/{id}", delete(cancel_generation_route))
+        .route("/codex/responses", post(codex_responses))
+        .layer(DefaultBodyLimit::max(MAX_REQUEST_BYTES))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn retry_after_prefers_milliseconds() {
+        let both = headers(&[("retry-after-ms", "1500"), ("retry-after", "9")]);
+        assert_eq!(retry_after(&both), Some(Duration::from_millis(1500)));
+        let fractional = headers(&[("retry-after-ms", "250.7")]);
+        assert_eq!(retry_after(&fractional), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        let secs = headers(&[("retry-after", " 20 ")]);
+        assert_eq!(retry_after(&secs), Some(Duration::from_secs(20)));
+        // HTTP dates aren't waited on
+        let date = headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")]);
+        assert_eq!(retry_after(&date), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn backoff_doubles_within_jitter_and_cap() {
+        let retry = RetryConfig {
+            max_retries: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+        };
+        for (attempt, full) in [
+            (0, 100),
+            (1, 200),
+            (2, 400),
+            (3, 800),
+            (4, 1000),
+            (30, 1000),
+        ] {
+            for _ in 0..20 {
+                let delay = backoff(&retry, attempt).as_millis() as u64;
+                assert!(
+                    (full / 2..=full).contains(&delay),
+                    "attempt {}: {}ms not within {}..={}",
+                    attempt,
+                    delay,
+                    full / 2,
+                    full
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn openrouter_model_ids() {
+        assert_eq!(openrouter_model("gpt-4o").as_deref(), Some("openai/gpt-4o"));
+        assert_eq!(
+            openrouter_model("o3-mini").as_deref(),
+            Some("openai/o3-mini")
+        );
+        assert_eq!(
+            openrouter_model("claude-sonnet-4-20250514").as_deref(),
+            Some("anthropic/claude-sonnet-4")
+        );
+        assert_eq!(
+            openrouter_model("gemini-2.5-pro").as_deref(),
+            Some("google/gemini-2.5-pro")
+        );
+        // Already namespaced, or a vendor OpenRouter isn't mapped for
+        assert_eq!(openrouter_model("anthropic/claude-sonnet-4"), None);
+        assert_eq!(openrouter_model("llama-3-70b"), None);
+    }
+}
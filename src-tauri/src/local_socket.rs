@@ -0,0 +1,135 @@
+//! Local Socket Transport
+//!
+//! Serves the `/stud/*` API on a Unix domain socket (macOS/Linux) or a named
+//! pipe (Windows) alongside the TCP listener, for CLIs and MCP clients on the
+//! same machine that shouldn't need a TCP port or trip a firewall prompt. It's
+//! opt-in (`bridge.local_socket`); the routes, secret and limits are the same
+//! as over TCP, and the address is published in the discovery file.
+
+use crate::bridge;
+
+#[cfg(unix)]
+const SOCKET_FILENAME: &str = "stud-bridge.sock";
+#[cfg(windows)]
+const PIPE_NAME: &str = r"\\.\pipe\stud-bridge";
+
+/// Whether the local socket is served
+pub fn enabled() -> bool {
+    crate::config::current().bridge.local_socket
+}
+
+/// Serve the bridge on the local socket until the app exits
+#[cfg(unix)]
+pub async fn serve() {
+    use std::os::unix::fs::PermissionsExt;
+
+    if !enabled() {
+        return;
+    }
+    let path = match crate::paths::app_data_dir() {
+        Ok(dir) => dir.join(SOCKET_FILENAME),
+        Err(e) => {
+            println!("[Stud Socket] {}", e);
+            return;
+        }
+    };
+    // A socket file left by a crash would make the bind fail
+    let _ = std::fs::remove_file(&path);
+    let listener = match tokio::net::UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!("[Stud Socket] Failed to bind {}: {}", path.display(), e);
+            return;
+        }
+    };
+    // Only this user may connect; the bridge secret is still required
+    if let Err(e) = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)) {
+        println!("[Stud Socket] Failed to restrict {}: {}", path.display(), e);
+    }
+
+    let address = path.display().to_string();
+    bridge::set_socket_endpoint(&address);
+    println!("[Stud Socket] Bridge API on unix:{}", address);
+    if let Err(e) = axum::serve(listener, bridge::local_routes())
+        .with_graceful_shutdown(bridge::shutdown_signal())
+        .await
+    {
+        println!("[Stud Socket] Server error: {}", e);
+    }
+    let _ = std::fs::remove_file(&path);
+    println!("[Stud Socket] Stopped");
+}
+
+/// Accepts clients on a named pipe. Each instance of the pipe carries one
+/// client, so a fresh one is created as soon as the current one connects.
+#[cfg(windows)]
+struct PipeListener {
+    next: tokio::net::windows::named_pipe::NamedPipeServer,
+}
+
+#[cfg(windows)]
+impl axum::serve::Listener for PipeListener {
+    type Io = tokio::net::windows::named_pipe::NamedPipeServer;
+    type Addr = &'static str;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        loop {
+            let connected = self.next.connect().await;
+            let next = match ServerOptions::new()
+                .reject_remote_clients(true)
+                .create(PIPE_NAME)
+            {
+                Ok(next) => next,
+                Err(e) => {
+                    println!("[Stud Socket] Failed to open another pipe instance: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+            let current = std::mem::replace(&mut self.next, next);
+            match connected {
+                Ok(()) => return (current, PIPE_NAME),
+                // The client went away before it was accepted; its instance is dropped
+                Err(e) => println!("[Stud Socket] Pipe connection failed: {}", e),
+            }
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(PIPE_NAME)
+    }
+}
+
+/// Serve the bridge on the named pipe until the app exits
+#[cfg(windows)]
+pub async fn serve() {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    if !enabled() {
+        return;
+    }
+    // Fails if another process already owns the pipe name
+    let first = match ServerOptions::new()
+        .first_pipe_instance(true)
+        .reject_remote_clients(true)
+        .create(PIPE_NAME)
+    {
+        Ok(first) => first,
+        Err(e) => {
+            println!("[Stud Socket] Failed to create {}: {}", PIPE_NAME, e);
+            return;
+        }
+    };
+
+    bridge::set_socket_endpoint(PIPE_NAME);
+    println!("[Stud Socket] Bridge API on {}", PIPE_NAME);
+    if let Err(e) = axum::serve(PipeListener { next: first }, bridge::local_routes())
+        .with_graceful_shutdown(bridge::shutdown_signal())
+        .await
+    {
+        println!("[Stud Socket] Server error: {}", e);
+    }
+    println!("[Stud Socket] Stopped");
+}
@@ -0,0 +1,489 @@
+//! Script Merging
+//!
+//! When the AI replaces a script it last read a while ago, the user may have
+//! edited that script in Studio in the meantime. Instead of overwriting their
+//! work, the AI's version is three-way merged with the current source, using
+//! the source the AI read as the common base. Clean merges are written
+//! straight away; overlapping changes are held as a pending merge until the
+//! user picks a side for each conflict.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::bridge;
+use crate::checkpoints;
+use crate::patches;
+
+/// Pending merges the user hasn't resolved are dropped after this long
+const PENDING_MERGE_EXPIRY_SECS: u64 = 30 * 60;
+/// Scripts whose base is remembered; the oldest is forgotten beyond this
+const MAX_BASES: usize = 500;
+
+lazy_static::lazy_static! {
+    // Source of each script as the AI last saw it, keyed by instance path
+    static ref BASES: Mutex<HashMap<String, Base>> = Mutex::new(HashMap::new());
+    static ref PENDING: Mutex<HashMap<String, PendingMerge>> = Mutex::new(HashMap::new());
+}
+
+struct Base {
+    source: String,
+    recorded: Instant,
+}
+
+/// One region of a merge: text both sides agree on, or a conflict
+#[derive(Debug, Clone)]
+enum Chunk {
+    Clean(String),
+    Conflict(MergeConflict),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeConflict {
+    /// The region before either side changed it
+    pub base: String,
+    /// The user's version, as currently in Studio
+    pub user: String,
+    /// The AI's version
+    pub ai: String,
+}
+
+struct PendingMerge {
+    path: String,
+    session: Option<String>,
+    // Studio's source when the merge was computed, to detect further edits
+    user_source: String,
+    chunks: Vec<Chunk>,
+    created: Instant,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStatus {
+    /// Studio still had the base source; the AI's version was written as is
+    Applied,
+    /// Both sides changed the script in different places; the merge was written
+    Merged,
+    /// Studio already had the AI's version
+    Unchanged,
+    /// Overlapping edits; nothing was written until the user resolves them
+    Conflict,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScriptMergeResult {
+    pub status: MergeStatus,
+    pub path: String,
+    /// Set when status is `conflict`; pass to `resolve_script_merge`
+    pub merge_id: Option<String>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// How to settle one conflict
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "choice", rename_all = "lowercase")]
+pub enum ConflictResolution {
+    User,
+    Ai,
+    /// The user's lines followed by the AI's
+    Both,
+    Custom {
+        text: String,
+    },
+}
+
+/// Split into lines, keeping line endings so joining them gives the text back
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// For each base line, the line it matches on the other side (if unchanged)
+fn matches(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut matched = vec![None; base.len()];
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = op
+        {
+            for offset in 0..len {
+                matched[old_index + offset] = Some(new_index + offset);
+            }
+        }
+    }
+    matched
+}
+
+/// diff3: walk the base, user and AI versions together, emitting the regions
+/// all three share as they are and resolving the regions between them
+fn merge(base: &str, user: &str, ai: &str) -> Vec<Chunk> {
+    let (base, user, ai) = (lines(base), lines(user), lines(ai));
+    let (in_user, in_ai) = (matches(&base, &user), matches(&base, &ai));
+    let mut chunks = Vec::new();
+    let (mut b, mut u, mut a) = (0, 0, 0);
+
+    loop {
+        // Lines unchanged on both sides
+        let mut stable = 0;
+        while b + stable < base.len()
+            && in_user[b + stable] == Some(u + stable)
+            && in_ai[b + stable] == Some(a + stable)
+        {
+            stable += 1;
+        }
+        if stable > 0 {
+            chunks.push(Chunk::Clean(base[b..b + stable].concat()));
+            b += stable;
+            u += stable;
+            a += stable;
+            continue;
+        }
+
+        // The next base line both sides kept bounds the changed region
+        let next = (b..base.len()).find(|&i| in_user[i].is_some() && in_ai[i].is_some());
+        let (b_end, u_end, a_end) = match next {
+            Some(i) => (i, in_user[i].unwrap_or(u), in_ai[i].unwrap_or(a)),
+            None => (base.len(), user.len(), ai.len()),
+        };
+        let (base_part, user_part, ai_part) = (
+            base[b..b_end].concat(),
+            user[u..u_end].concat(),
+            ai[a..a_end].concat(),
+        );
+        if user_part == base_part || user_part == ai_part {
+            chunks.push(Chunk::Clean(ai_part));
+        } else if ai_part == base_part {
+            chunks.push(Chunk::Clean(user_part));
+        } else {
+            chunks.push(Chunk::Conflict(MergeConflict {
+                base: base_part,
+                user: user_part,
+                ai: ai_part,
+            }));
+        }
+        if next.is_none() {
+            break;
+        }
+        (b, u, a) = (b_end, u_end, a_end);
+    }
+
+    chunks.retain(|chunk| !matches!(chunk, Chunk::Clean(text) if text.is_empty()));
+    chunks
+}
+
+fn conflicts(chunks: &[Chunk]) -> Vec<MergeConflict> {
+    chunks
+        .iter()
+        .filter_map(|chunk| match chunk {
+            Chunk::Conflict(conflict) => Some(conflict.clone()),
+            Chunk::Clean(_) => None,
+        })
+        .collect()
+}
+
+/// The merged text, settling each conflict with the next resolution
+fn resolve(chunks: &[Chunk], resolutions: Vec<ConflictResolution>) -> String {
+    let mut resolutions = resolutions.into_iter();
+    let mut text = String::new();
+    for chunk in chunks {
+        match chunk {
+            Chunk::Clean(clean) => text.push_str(clean),
+            Chunk::Conflict(conflict) => match resolutions.next() {
+                Some(ConflictResolution::User) => text.push_str(&conflict.user),
+                Some(ConflictResolution::Ai) => text.push_str(&conflict.ai),
+                Some(ConflictResolution::Both) => {
+                    text.push_str(&conflict.user);
+                    if !conflict.user.is_empty() && !conflict.user.ends_with('\n') {
+                        text.push('\n');
+                    }
+                    text.push_str(&conflict.ai);
+                }
+                Some(ConflictResolution::Custom { text: custom }) => text.push_str(&custom),
+                None => {}
+            },
+        }
+    }
+    text
+}
+
+/// Bases are keyed by the full path, as the plugin reports it ("game.Workspace.Script")
+fn base_key(path: &str) -> String {
+    if path == "game" || path.starts_with("game.") {
+        path.to_string()
+    } else {
+        format!("game.{}", path)
+    }
+}
+
+fn record(path: &str, source: &str) {
+    let key = base_key(path);
+    let mut bases = BASES.lock();
+    if bases.len() >= MAX_BASES && !bases.contains_key(&key) {
+        let oldest = bases
+            .iter()
+            .min_by_key(|(_, base)| base.recorded)
+            .map(|(path, _)| path.clone());
+        if let Some(oldest) = oldest {
+            bases.remove(&oldest);
+        }
+    }
+    bases.insert(
+        key,
+        Base {
+            source: source.to_string(),
+            recorded: Instant::now(),
+        },
+    );
+}
+
+async fn read_script(session: Option<&str>, path: &str) -> Result<String, String> {
+    let result =
+        bridge::studio_request(session, "/script/get", serde_json::json!({ "path": path })).await?;
+    result
+        .get("source")
+        .and_then(|source| source.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| format!("Studio returned no source for {}", path))
+}
+
+/// `previous` is the source being replaced, when known, to keep as a checkpoint
+async fn write_script(
+    session: Option<&str>,
+    path: &str,
+    source: &str,
+    previous: Option<&str>,
+) -> Result<(), String> {
+    bridge::studio_request(
+        session,
+        "/script/set",
+        serde_json::json!({ "path": path, "source": source }),
+    )
+    .await?;
+    record(path, source);
+    checkpoints::record_edit(session, path, previous);
+    patches::record_script(session, path, previous, source);
+    Ok(())
+}
+
+/// Remember a script's source as the AI just read it, as the base for its next edit
+#[tauri::command]
+pub fn record_script_base(path: String, source: String) {
+    record(&path, &source);
+}
+
+/// Write the AI's version of a script, merging in any edits the user made
+/// since the AI read it. Scripts the AI hasn't read are written as is.
+#[tauri::command]
+pub async fn merge_script_edit(
+    path: String,
+    source: String,
+    session: Option<String>,
+) -> Result<ScriptMergeResult, String> {
+    let session = session.as_deref();
+    let base = BASES
+        .lock()
+        .get(&base_key(&path))
+        .map(|base| base.source.clone());
+    let Some(base) = base else {
+        write_script(session, &path, &source, None).await?;
+        return Ok(ScriptMergeResult {
+            status: MergeStatus::Applied,
+            path,
+            merge_id: None,
+            conflicts: Vec::new(),
+        });
+    };
+
+    let current = read_script(session, &path).await?;
+    let (status, merged) = if current == source {
+        (MergeStatus::Unchanged, None)
+    } else if current == base {
+        (MergeStatus::Applied, Some(source))
+    } else {
+        let chunks = merge(&base, &current, &source);
+        let found = conflicts(&chunks);
+        if !found.is_empty() {
+            let id = uuid::Uuid::new_v4().to_string();
+            let mut pending = PENDING.lock();
+            let expiry = Duration::from_secs(PENDING_MERGE_EXPIRY_SECS);
+            pending.retain(|_, merge| merge.created.elapsed() < expiry);
+            pending.insert(
+                id.clone(),
+                PendingMerge {
+                    path: path.clone(),
+                    session: session.map(str::to_string),
+                    user_source: current,
+                    chunks,
+                    created: Instant::now(),
+                },
+            );
+            println!(
+                "[Stud Merge] {} conflict(s) merging edit to {}",
+                found.len(),
+                path
+            );
+            return Ok(ScriptMergeResult {
+                status: MergeStatus::Conflict,
+                path,
+                merge_id: Some(id),
+                conflicts: found,
+            });
+        }
+        let text: String = chunks
+            .into_iter()
+            .map(|chunk| match chunk {
+                Chunk::Clean(text) => text,
+                Chunk::Conflict(_) => String::new(),
+            })
+            .collect();
+        (MergeStatus::Merged, Some(text))
+    };
+
+    match merged {
+        Some(text) => write_script(session, &path, &text, Some(&current)).await?,
+        None => record(&path, &current),
+    }
+    Ok(ScriptMergeResult {
+        status,
+        path,
+        merge_id: None,
+        conflicts: Vec::new(),
+    })
+}
+
+/// Finish a conflicted merge with one resolution per conflict, in order
+#[tauri::command]
+pub async fn resolve_script_merge(
+    merge_id: String,
+    resolutions: Vec<ConflictResolution>,
+) -> Result<ScriptMergeResult, String> {
+    let merge = PENDING
+        .lock()
+        .remove(&merge_id)
+        .ok_or_else(|| "Merge not found or expired".to_string())?;
+    let conflict_count = conflicts(&merge.chunks).len();
+    if resolutions.len() != conflict_count {
+        let path = merge.path.clone();
+        PENDING.lock().insert(merge_id, merge);
+        return Err(format!(
+            "Expected {} resolution(s) for {}, got {}",
+            conflict_count,
+            path,
+            resolutions.len()
+        ));
+    }
+
+    let session = merge.session.as_deref();
+    let current = read_script(session, &merge.path).await?;
+    if current != merge.user_source {
+        return Err(format!(
+            "{} changed again in Studio; make the edit again to merge with the latest version",
+            merge.path
+        ));
+    }
+
+    let text = resolve(&merge.chunks, resolutions);
+    write_script(session, &merge.path, &text, Some(&current)).await?;
+    Ok(ScriptMergeResult {
+        status: MergeStatus::Merged,
+        path: merge.path,
+        merge_id: None,
+        conflicts: Vec::new(),
+    })
+}
+
+/// Drop a conflicted merge without writing anything
+#[tauri::command]
+pub fn discard_script_merge(merge_id: String) -> bool {
+    PENDING.lock().remove(&merge_id).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The merged text, if the merge was clean
+    fn clean(chunks: &[Chunk]) -> Option<String> {
+        chunks
+            .iter()
+            .map(|chunk| match chunk {
+                Chunk::Clean(text) => Some(text.as_str()),
+                Chunk::Conflict(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn disjoint_edits_merge() {
+        let base = "a\nb\nc\nd\ne\n";
+        let user = "A\nb\nc\nd\ne\n";
+        let ai = "a\nb\nc\nd\nE\n";
+        assert_eq!(
+            clean(&merge(base, user, ai)).as_deref(),
+            Some("A\nb\nc\nd\nE\n")
+        );
+    }
+
+    #[test]
+    fn identical_changes_merge_cleanly() {
+        let base = "a\nb\nc\n";
+        let changed = "a\nB\nc\n";
+        assert_eq!(
+            clean(&merge(base, changed, changed)).as_deref(),
+            Some(changed)
+        );
+    }
+
+    #[test]
+    fn overlapping_edits_conflict() {
+        let base = "a\nb\nc\n";
+        let user = "a\nuser\nc\n";
+        let ai = "a\nai\nc\n";
+        let chunks = merge(base, user, ai);
+        assert!(clean(&chunks).is_none());
+        let found = conflicts(&chunks);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].base, "b\n");
+        assert_eq!(found[0].user, "user\n");
+        assert_eq!(found[0].ai, "ai\n");
+        assert!(matches!(&chunks[0], Chunk::Clean(text) if text == "a\n"));
+        assert!(matches!(&chunks[2], Chunk::Clean(text) if text == "c\n"));
+    }
+
+    #[test]
+    fn insertions_at_start_and_end() {
+        let base = "a\nb\n";
+        let user = "first\na\nb\n";
+        let ai = "a\nb\nlast\n";
+        assert_eq!(
+            clean(&merge(base, user, ai)).as_deref(),
+            Some("first\na\nb\nlast\n")
+        );
+    }
+
+    #[test]
+    fn no_trailing_newline() {
+        let base = "a\nb\nc";
+        let user = "A\nb\nc";
+        let ai = "a\nb\nC";
+        assert_eq!(clean(&merge(base, user, ai)).as_deref(), Some("A\nb\nC"));
+    }
+
+    #[test]
+    fn both_keeps_user_then_ai() {
+        let chunks = merge("a\nb\nc\n", "a\nuser\nc\n", "a\nai\nc\n");
+        assert_eq!(
+            resolve(&chunks, vec![ConflictResolution::Both]),
+            "a\nuser\nai\nc\n"
+        );
+        // A last line without a newline isn't run into the AI's
+        let chunks = merge("a\nb", "a\nuser", "a\nai");
+        assert_eq!(
+            resolve(&chunks, vec![ConflictResolution::Both]),
+            "a\nuser\nai"
+        );
+    }
+}
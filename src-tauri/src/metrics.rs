@@ -0,0 +1,215 @@
+//! Bridge Metrics
+//!
+//! Counters and latency histograms for the bridge, kept in `BridgeState` and
+//! served from /stud/metrics in the Prometheus text format, so bridge behavior
+//! can be graphed while developing tools. Scrapers must send the bridge secret
+//! like any other /stud/* caller.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Upper bounds of the request latency buckets, in seconds
+const LATENCY_BUCKETS: [f64; 10] = [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct Histogram {
+    // Cumulative counts are computed at render time; these are per bucket
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[index] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// How a dispatched request ended
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Outcome {
+    Completed,
+    TimedOut,
+    Cancelled,
+}
+
+/// Gauges read from the bridge state when metrics are rendered
+pub(crate) struct Gauges {
+    pub queue_depth: usize,
+    pub long_polls: usize,
+    pub sessions: usize,
+    pub seconds_since_poll: f64,
+}
+
+#[derive(Default)]
+pub(crate) struct BridgeMetrics {
+    requests: BTreeMap<String, u64>,
+    timeouts: BTreeMap<String, u64>,
+    cancellations: BTreeMap<String, u64>,
+    rejections: BTreeMap<String, u64>,
+    latency: BTreeMap<String, Histogram>,
+    polls: u64,
+    websocket_connections: u64,
+}
+
+impl BridgeMetrics {
+    pub fn record_request(&mut self, path: &str) {
+        *self.requests.entry(path.to_string()).or_default() += 1;
+    }
+
+    pub fn record_outcome(&mut self, path: &str, outcome: Outcome, seconds: f64) {
+        match outcome {
+            Outcome::Completed => self
+                .latency
+                .entry(path.to_string())
+                .or_default()
+                .observe(seconds),
+            Outcome::TimedOut => *self.timeouts.entry(path.to_string()).or_default() += 1,
+            Outcome::Cancelled => *self.cancellations.entry(path.to_string()).or_default() += 1,
+        }
+    }
+
+    /// A request refused because the queue was full
+    pub fn record_rejection(&mut self, path: &str) {
+        *self.rejections.entry(path.to_string()).or_default() += 1;
+    }
+
+    pub fn record_poll(&mut self) {
+        self.polls += 1;
+    }
+
+    pub fn record_websocket_connection(&mut self) {
+        self.websocket_connections += 1;
+    }
+
+    /// Everything in the Prometheus text exposition format
+    pub fn render(&self, gauges: &Gauges) -> String {
+        let mut out = String::new();
+
+        per_path(
+            &mut out,
+            "stud_bridge_requests_total",
+            "Requests queued for Studio, by path",
+            &self.requests,
+        );
+        per_path(
+            &mut out,
+            "stud_bridge_request_timeouts_total",
+            "Requests Studio didn't answer in time, by path",
+            &self.timeouts,
+        );
+        per_path(
+            &mut out,
+            "stud_bridge_request_cancellations_total",
+            "Requests cancelled before Studio answered, by path",
+            &self.cancellations,
+        );
+        per_path(
+            &mut out,
+            "stud_bridge_requests_rejected_total",
+            "Requests refused because the queue was full, by path",
+            &self.rejections,
+        );
+
+        let name = "stud_bridge_request_duration_seconds";
+        header(
+            &mut out,
+            name,
+            "histogram",
+            "Time from queueing a request to Studio's response, by path",
+        );
+        for (path, histogram) in &self.latency {
+            let path = escape(path);
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{path=\"{}\",le=\"{}\"}} {}",
+                    name, path, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_bucket{{path=\"{}\",le=\"+Inf\"}} {}",
+                name, path, histogram.count
+            );
+            let _ = writeln!(out, "{}_sum{{path=\"{}\"}} {}", name, path, histogram.sum);
+            let _ = writeln!(
+                out,
+                "{}_count{{path=\"{}\"}} {}",
+                name, path, histogram.count
+            );
+        }
+
+        let values: [(&str, &str, &str, String); 6] = [
+            (
+                "stud_bridge_polls_total",
+                "counter",
+                "Polls received from the Studio plugin",
+                self.polls.to_string(),
+            ),
+            (
+                "stud_bridge_websocket_connections_total",
+                "counter",
+                "WebSocket connections opened by the Studio plugin",
+                self.websocket_connections.to_string(),
+            ),
+            (
+                "stud_bridge_seconds_since_last_poll",
+                "gauge",
+                "Seconds since the plugin last polled",
+                format!("{:.3}", gauges.seconds_since_poll),
+            ),
+            (
+                "stud_bridge_queue_depth",
+                "gauge",
+                "Requests waiting for Studio to pick them up or answer",
+                gauges.queue_depth.to_string(),
+            ),
+            (
+                "stud_bridge_long_polls",
+                "gauge",
+                "Long polls currently parked",
+                gauges.long_polls.to_string(),
+            ),
+            (
+                "stud_bridge_sessions",
+                "gauge",
+                "Known Studio sessions",
+                gauges.sessions.to_string(),
+            ),
+        ];
+        for (name, kind, help, value) in values {
+            header(&mut out, name, kind, help);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// A counter with one series per request path
+fn per_path(out: &mut String, name: &str, help: &str, counts: &BTreeMap<String, u64>) {
+    header(out, name, "counter", help);
+    for (path, count) in counts {
+        let _ = writeln!(out, "{}{{path=\"{}\"}} {}", name, escape(path), count);
+    }
+}
+
+/// Escape a label value per the exposition format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,229 @@
+//! Model Capability Registry
+//!
+//! Knows what each model can do (vision, tool calls, streaming, context size)
+//! so requests can be rejected up front with a clear error instead of failing
+//! halfway through a generation.
+//!
+//! Capabilities come from a manifest bundled in the binary, overlaid with a
+//! cached copy refreshed from models.dev when the user asks for it.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::paths;
+
+const BUNDLED_MANIFEST: &str = include_str!("../resources/model-capabilities.json");
+pub(crate) const CACHE_FILENAME: &str = "model-capabilities.json";
+const MODELS_DEV_URL: &str = "https://models.dev/api.json";
+const MANIFEST_VERSION: u32 = 1;
+
+lazy_static::lazy_static! {
+    static ref MODEL_REGISTRY: RwLock<HashMap<String, ModelCapabilities>> = RwLock::new(load_registry());
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    pub vision: bool,
+    pub tool_call: bool,
+    #[serde(default = "default_true")]
+    pub streaming: bool,
+    #[serde(default)]
+    pub reasoning: bool,
+    pub max_context: u64,
+    #[serde(default)]
+    pub max_output: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CapabilityManifest {
+    version: u32,
+    models: HashMap<String, ModelCapabilities>,
+}
+
+/// What a request is about to use, so it can be checked against the model
+#[derive(Debug, Default, Deserialize)]
+pub struct RequestFeatures {
+    #[serde(default)]
+    pub image_count: u32,
+    #[serde(default)]
+    pub tools: bool,
+    #[serde(default)]
+    pub streaming: bool,
+    #[serde(default)]
+    pub estimated_input_tokens: u64,
+}
+
+// Subset of the models.dev schema we care about
+#[derive(Deserialize)]
+struct ModelsDevProvider {
+    #[serde(default)]
+    models: HashMap<String, ModelsDevModel>,
+}
+
+#[derive(Deserialize)]
+struct ModelsDevModel {
+    #[serde(default)]
+    attachment: bool,
+    #[serde(default)]
+    reasoning: bool,
+    #[serde(default = "default_true")]
+    tool_call: bool,
+    #[serde(default)]
+    limit: Option<ModelsDevLimit>,
+    #[serde(default)]
+    modalities: Option<ModelsDevModalities>,
+}
+
+#[derive(Deserialize)]
+struct ModelsDevLimit {
+    context: u64,
+    #[serde(default)]
+    output: u64,
+}
+
+#[derive(Deserialize)]
+struct ModelsDevModalities {
+    input: Vec<String>,
+}
+
+impl From<ModelsDevModel> for ModelCapabilities {
+    fn from(model: ModelsDevModel) -> Self {
+        let vision = match &model.modalities {
+            Some(modalities) => modalities.input.iter().any(|m| m == "image"),
+            None => model.attachment,
+        };
+        let (max_context, max_output) = model
+            .limit
+            .map(|limit| (limit.context, limit.output))
+            .unwrap_or((0, 0));
+
+        ModelCapabilities {
+            vision,
+            tool_call: model.tool_call,
+            streaming: true,
+            reasoning: model.reasoning,
+            max_context,
+            max_output,
+        }
+    }
+}
+
+fn parse_manifest(source: &str) -> Option<HashMap<String, ModelCapabilities>> {
+    serde_json::from_str::<CapabilityManifest>(source)
+        .ok()
+        .filter(|manifest| manifest.version == MANIFEST_VERSION)
+        .map(|manifest| manifest.models)
+}
+
+/// Bundled manifest first, then the refreshed cache on top of it
+fn load_registry() -> HashMap<String, ModelCapabilities> {
+    let mut models = parse_manifest(BUNDLED_MANIFEST).unwrap_or_default();
+
+    let cached = paths::app_data_dir()
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(CACHE_FILENAME)).ok())
+        .and_then(|source| parse_manifest(&source));
+
+    if let Some(cached) = cached {
+        models.extend(cached);
+    }
+
+    models
+}
+
+/// Look up a model, accepting provider-prefixed ids like "openai/gpt-4o"
+pub fn lookup(model: &str) -> Option<ModelCapabilities> {
+    let registry = MODEL_REGISTRY.read();
+    registry
+        .get(model)
+        .or_else(|| {
+            model
+                .rsplit_once('/')
+                .and_then(|(_, bare)| registry.get(bare))
+        })
+        .cloned()
+}
+
+/// Check a request against the model's capabilities.
+/// Unknown models are let through since we have nothing to gate on.
+pub fn check_request(model: &str, features: &RequestFeatures) -> Result<(), String> {
+    let Some(caps) = lookup(model) else {
+        return Ok(());
+    };
+
+    if features.image_count > 0 && !caps.vision {
+        return Err(format!(
+            "{} can't read images. Remove the attachment or switch to a vision-capable model.",
+            model
+        ));
+    }
+
+    if features.tools && !caps.tool_call {
+        return Err(format!(
+            "{} doesn't support tool calls, so it can't work with Roblox Studio. Pick a different model.",
+            model
+        ));
+    }
+
+    if features.streaming && !caps.streaming {
+        return Err(format!("{} doesn't support streaming responses.", model));
+    }
+
+    if caps.max_context > 0 && features.estimated_input_tokens > caps.max_context {
+        return Err(format!(
+            "This conversation is about {} tokens, which exceeds {}'s {} token context window. Start a new chat or pick a larger model.",
+            features.estimated_input_tokens, model, caps.max_context
+        ));
+    }
+
+    Ok(())
+}
+
+/// Get the known capabilities of a model, if any
+#[tauri::command]
+pub fn get_model_capabilities(model: String) -> Option<ModelCapabilities> {
+    lookup(&model)
+}
+
+/// Validate a request before it is sent to the model
+#[tauri::command]
+pub fn check_model_request(model: String, features: RequestFeatures) -> Result<(), String> {
+    check_request(&model, &features)
+}
+
+/// Refresh capabilities from models.dev and cache them for future launches
+#[tauri::command]
+pub async fn refresh_model_capabilities() -> Result<usize, String> {
+    let providers: HashMap<String, ModelsDevProvider> = reqwest::get(MODELS_DEV_URL)
+        .await
+        .map_err(|e| format!("Failed to fetch model list: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse model list: {}", e))?;
+
+    let models: HashMap<String, ModelCapabilities> = providers
+        .into_values()
+        .flat_map(|provider| provider.models)
+        .map(|(id, model)| (id, model.into()))
+        .collect();
+
+    let manifest = CapabilityManifest {
+        version: MANIFEST_VERSION,
+        models,
+    };
+
+    let path = paths::app_data_dir()?.join(CACHE_FILENAME);
+    let json = serde_json::to_string(&manifest)
+        .map_err(|e| format!("Failed to serialize model list: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to cache model list: {}", e))?;
+
+    let count = manifest.models.len();
+    MODEL_REGISTRY.write().extend(manifest.models);
+    Ok(count)
+}
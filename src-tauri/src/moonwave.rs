@@ -0,0 +1,612 @@
+//! Luau API Index
+//!
+//! Parses moonwave-style doc comments (`--[=[ ... ]=]` blocks and `---` lines
+//! with `@class`, `@param`, `@return` and friends) out of ModuleScripts and
+//! keeps a per-module API summary. The summary is a compact context provider:
+//! the AI gets signatures and one-line descriptions for a team's internal
+//! libraries instead of reading whole implementations. Functions without doc
+//! comments still show up, typed from their Luau annotations.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::bridge::{self, chrono_lite_timestamp};
+
+// Keep the summary small enough to sit next to a prompt
+const MAX_CONTEXT_CHARS: usize = 24_000;
+
+lazy_static::lazy_static! {
+    static ref MODULE_INDEX: Mutex<ModuleIndex> = Mutex::new(ModuleIndex::default());
+}
+
+#[derive(Default)]
+struct ModuleIndex {
+    modules: Vec<ModuleApi>,
+    indexed_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleApi {
+    pub path: String,
+    /// `@class` name, or the table the module returns
+    pub class: String,
+    pub description: String,
+    pub items: Vec<ApiItem>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiItem {
+    pub name: String,
+    /// "function", "method", "property" or "type"
+    pub kind: String,
+    pub params: Vec<ApiParam>,
+    pub returns: Vec<ApiReturn>,
+    /// Property or type alias type
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+    pub description: String,
+    pub yields: bool,
+    /// "server", "client" or "plugin"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realm: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+    pub private: bool,
+    /// Whether this came from a doc comment rather than just the code
+    pub documented: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiParam {
+    pub name: String,
+    pub ty: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiReturn {
+    pub ty: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiContext {
+    pub modules: Vec<ModuleApi>,
+    /// Compact text summary for prompts
+    pub summary: String,
+    pub indexed_at: Option<u64>,
+    pub truncated: bool,
+}
+
+// Response from the plugin's /docs/modules handler
+#[derive(Deserialize)]
+struct ModuleSources {
+    #[serde(default)]
+    modules: Vec<ModuleSource>,
+}
+
+#[derive(Deserialize)]
+struct ModuleSource {
+    path: String,
+    #[serde(default)]
+    source: String,
+}
+
+/// A function defined in code: name, whether it's a method, params and return type
+struct Definition {
+    name: String,
+    method: bool,
+    params: Vec<ApiParam>,
+    returns: Option<String>,
+}
+
+/// Split on commas that aren't nested inside brackets (`{string}`, `(number) -> ()`)
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' | '{' | '<' | '[' => depth += 1,
+            ')' | '}' | '>' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Parse `function A.b(x: T): R`, `local function b(...)` or `A.b = function(...)`
+fn parse_definition(line: &str) -> Option<Definition> {
+    let trimmed = line.trim();
+    let (name, rest) = if let Some(rest) = trimmed
+        .strip_prefix("local function ")
+        .or_else(|| trimmed.strip_prefix("function "))
+    {
+        rest.split_once('(')?
+    } else {
+        let (lhs, rhs) = trimmed.split_once('=')?;
+        let rhs = rhs.trim().strip_prefix("function")?.trim_start();
+        (lhs, rhs.strip_prefix('(')?)
+    };
+
+    let name = name.trim();
+    if name.is_empty() || name.contains(' ') {
+        return None;
+    }
+
+    // Find the matching close paren so function-typed params don't end the list early
+    let mut depth = 1;
+    let close = rest.char_indices().find_map(|(index, c)| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        (depth == 0).then_some(index)
+    })?;
+
+    let params = split_top_level(&rest[..close])
+        .into_iter()
+        .map(|param| {
+            let (name, ty) = param.split_once(':').unwrap_or((param, ""));
+            ApiParam {
+                name: name.trim().to_string(),
+                ty: ty.trim().to_string(),
+                description: String::new(),
+            }
+        })
+        .filter(|param| param.name != "self")
+        .collect();
+    let returns = rest[close + 1..]
+        .trim()
+        .strip_prefix(':')
+        .map(|ret| {
+            ret.split("--")
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        })
+        .filter(|ret| !ret.is_empty());
+
+    Some(Definition {
+        name: name.to_string(),
+        method: name.contains(':'),
+        params,
+        returns,
+    })
+}
+
+/// Doc comment blocks with the index of the first line after each one
+fn doc_blocks(source: &str) -> Vec<(Vec<String>, usize)> {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut blocks = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let trimmed = lines[index].trim();
+        if let Some(first) = trimmed.strip_prefix("--[=[") {
+            let mut text = Vec::new();
+            let mut line = first;
+            loop {
+                if let Some((before, _)) = line.split_once("]=]") {
+                    text.push(before.trim().to_string());
+                    break;
+                }
+                text.push(line.trim().to_string());
+                index += 1;
+                match lines.get(index) {
+                    Some(next) => line = next,
+                    None => break,
+                }
+            }
+            blocks.push((text, index + 1));
+        } else if trimmed.starts_with("---") && !trimmed.starts_with("----") {
+            let mut text = Vec::new();
+            while let Some(line) = lines.get(index).map(|line| line.trim()) {
+                let Some(rest) = line.strip_prefix("---") else {
+                    break;
+                };
+                text.push(rest.strip_prefix(' ').unwrap_or(rest).to_string());
+                index += 1;
+            }
+            blocks.push((text, index));
+            continue;
+        }
+        index += 1;
+    }
+    blocks
+}
+
+/// Split "name type -- description" style tag text at the ` -- `
+fn split_description(text: &str) -> (&str, String) {
+    match text.split_once("--") {
+        Some((head, description)) => (head.trim(), description.trim().to_string()),
+        None => (text.trim(), String::new()),
+    }
+}
+
+fn empty_item(name: String, kind: &str) -> ApiItem {
+    ApiItem {
+        name,
+        kind: kind.to_string(),
+        params: Vec::new(),
+        returns: Vec::new(),
+        ty: None,
+        description: String::new(),
+        yields: false,
+        realm: None,
+        deprecated: None,
+        private: false,
+        documented: true,
+    }
+}
+
+/// Name of the table a module returns, from its final `return X`
+fn returned_table(source: &str) -> Option<String> {
+    let last = source.lines().rev().find(|line| !line.trim().is_empty())?;
+    let name = last.trim().strip_prefix("return ")?.trim();
+    (!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .then(|| name.to_string())
+}
+
+/// Parse a ModuleScript's doc comments and exported functions
+pub fn parse_module(path: &str, source: &str) -> ModuleApi {
+    let table = returned_table(source);
+    let mut module = ModuleApi {
+        path: path.to_string(),
+        class: table
+            .clone()
+            .unwrap_or_else(|| path.rsplit('.').next().unwrap_or(path).to_string()),
+        description: String::new(),
+        items: Vec::new(),
+    };
+    let lines: Vec<&str> = source.lines().collect();
+    let mut documented_lines = Vec::new();
+
+    for (text, next_line) in doc_blocks(source) {
+        let mut description = Vec::new();
+        let mut item: Option<ApiItem> = None;
+        let mut is_class = false;
+        let mut params = Vec::new();
+        let mut returns = Vec::new();
+        let mut yields = false;
+        let mut realm = None;
+        let mut deprecated = None;
+        let mut private = false;
+
+        for line in &text {
+            let Some(tag) = line.strip_prefix('@') else {
+                description.push(line.as_str());
+                continue;
+            };
+            let (name, rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+            let rest = rest.trim();
+            match name {
+                "class" => {
+                    is_class = true;
+                    if !rest.is_empty() {
+                        module.class = rest.to_string();
+                    }
+                }
+                "function" | "method" => {
+                    let kind = if name == "method" {
+                        "method"
+                    } else {
+                        "function"
+                    };
+                    item = Some(empty_item(rest.to_string(), kind));
+                }
+                "prop" | "type" | "interface" => {
+                    let (head, _) = split_description(rest);
+                    let (prop, ty) = head.split_once(char::is_whitespace).unwrap_or((head, ""));
+                    let kind = if name == "prop" { "property" } else { "type" };
+                    let mut entry = empty_item(prop.to_string(), kind);
+                    entry.ty = (!ty.trim().is_empty()).then(|| ty.trim().to_string());
+                    item = Some(entry);
+                }
+                "param" => {
+                    let (head, description) = split_description(rest);
+                    let (param, ty) = head.split_once(char::is_whitespace).unwrap_or((head, ""));
+                    params.push(ApiParam {
+                        name: param.to_string(),
+                        ty: ty.trim().to_string(),
+                        description,
+                    });
+                }
+                "return" => {
+                    let (ty, description) = split_description(rest);
+                    returns.push(ApiReturn {
+                        ty: ty.to_string(),
+                        description,
+                    });
+                }
+                "yields" => yields = true,
+                "server" | "client" | "plugin" => realm = Some(name.to_string()),
+                "deprecated" => {
+                    let (version, description) = split_description(rest);
+                    deprecated = Some(if description.is_empty() {
+                        version.to_string()
+                    } else {
+                        format!("{} - {}", version, description)
+                    });
+                }
+                "private" | "ignore" => private = true,
+                _ => {}
+            }
+        }
+        let description = description.join("\n").trim().to_string();
+
+        if is_class {
+            module.description = description;
+            continue;
+        }
+
+        // No @function/@prop tag: the block documents the definition right below it
+        let definition = lines
+            .get(next_line..)
+            .and_then(|rest| rest.iter().position(|line| !line.trim().is_empty()))
+            .map(|offset| next_line + offset)
+            .and_then(|line| parse_definition(lines[line]).map(|definition| (line, definition)));
+        let mut item = match (item, definition) {
+            (Some(item), _) => item,
+            (None, Some((line, definition))) => {
+                documented_lines.push(line);
+                let short = definition
+                    .name
+                    .rsplit(['.', ':'])
+                    .next()
+                    .unwrap_or(&definition.name)
+                    .to_string();
+                let item = empty_item(
+                    short,
+                    if definition.method {
+                        "method"
+                    } else {
+                        "function"
+                    },
+                );
+                if params.is_empty() {
+                    params = definition.params;
+                }
+                if returns.is_empty() {
+                    returns = definition
+                        .returns
+                        .into_iter()
+                        .map(|ty| ApiReturn {
+                            ty,
+                            description: String::new(),
+                        })
+                        .collect();
+                }
+                item
+            }
+            (None, None) => {
+                // A leading block with no tags describes the module itself
+                if module.description.is_empty() && module.items.is_empty() {
+                    module.description = description;
+                }
+                continue;
+            }
+        };
+
+        item.description = description;
+        item.params = params;
+        item.returns = returns;
+        item.yields = yields;
+        item.realm = realm;
+        item.deprecated = deprecated;
+        item.private = private;
+        module.items.push(item);
+    }
+
+    // Undocumented functions on the returned table, typed from their annotations
+    if let Some(table) = &table {
+        for (line, text) in lines.iter().enumerate() {
+            if documented_lines.contains(&line) {
+                continue;
+            }
+            let Some(definition) = parse_definition(text) else {
+                continue;
+            };
+            let Some(short) = definition
+                .name
+                .strip_prefix(table.as_str())
+                .and_then(|rest| rest.strip_prefix(['.', ':']))
+            else {
+                continue;
+            };
+            if module.items.iter().any(|item| item.name == short) {
+                continue;
+            }
+            let mut item = empty_item(
+                short.to_string(),
+                if definition.method {
+                    "method"
+                } else {
+                    "function"
+                },
+            );
+            item.params = definition.params;
+            item.returns = definition
+                .returns
+                .into_iter()
+                .map(|ty| ApiReturn {
+                    ty,
+                    description: String::new(),
+                })
+                .collect();
+            item.private = short.starts_with('_');
+            item.documented = false;
+            module.items.push(item);
+        }
+    }
+    module
+}
+
+/// How the item is called, e.g. `Inventory:Add(item: string, count: number?): boolean`
+pub fn item_signature(module: &ModuleApi, item: &ApiItem) -> String {
+    match item.kind.as_str() {
+        "property" | "type" => {
+            let prefix = if item.kind == "type" { "type " } else { "" };
+            match &item.ty {
+                Some(ty) => format!("{}{}.{}: {}", prefix, module.class, item.name, ty),
+                None => format!("{}{}.{}", prefix, module.class, item.name),
+            }
+        }
+        kind => {
+            let separator = if kind == "method" { ":" } else { "." };
+            let params = item
+                .params
+                .iter()
+                .map(|param| {
+                    if param.ty.is_empty() {
+                        param.name.clone()
+                    } else {
+                        format!("{}: {}", param.name, param.ty)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let returns = match item.returns.len() {
+                0 => String::new(),
+                1 => format!(": {}", item.returns[0].ty),
+                _ => format!(
+                    ": ({})",
+                    item.returns
+                        .iter()
+                        .map(|ret| ret.ty.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            };
+            format!(
+                "{}{}{}({}){}",
+                module.class, separator, item.name, params, returns
+            )
+        }
+    }
+}
+
+/// One line per public item, first sentence of each description
+fn summarize(module: &ModuleApi) -> String {
+    let mut text = format!("### {} ({})\n", module.class, module.path);
+    if let Some(line) = module
+        .description
+        .lines()
+        .next()
+        .filter(|line| !line.is_empty())
+    {
+        text.push_str(line);
+        text.push('\n');
+    }
+    for item in module.items.iter().filter(|item| !item.private) {
+        text.push_str(&format!("- {}", item_signature(module, item)));
+        let mut notes = Vec::new();
+        if item.yields {
+            notes.push("yields".to_string());
+        }
+        if let Some(realm) = &item.realm {
+            notes.push(format!("{} only", realm));
+        }
+        if let Some(deprecated) = &item.deprecated {
+            notes.push(format!("deprecated {}", deprecated));
+        }
+        if !notes.is_empty() {
+            text.push_str(&format!(" [{}]", notes.join(", ")));
+        }
+        if let Some(line) = item
+            .description
+            .lines()
+            .next()
+            .filter(|line| !line.is_empty())
+        {
+            text.push_str(" - ");
+            text.push_str(line);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Replace the index with freshly parsed modules (also called by the docs generator)
+pub fn store_index(modules: Vec<ModuleApi>) {
+    let mut index = MODULE_INDEX.lock();
+    index.modules = modules;
+    index.indexed_at = Some(chrono_lite_timestamp());
+}
+
+async fn refresh_index(session: Option<&str>) -> Result<(), String> {
+    let result =
+        bridge::studio_request_background(session, "/docs/modules", serde_json::json!({})).await?;
+    let sources: ModuleSources = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+    store_index(
+        sources
+            .modules
+            .iter()
+            .map(|module| parse_module(&module.path, &module.source))
+            .collect(),
+    );
+    Ok(())
+}
+
+/// Compact API summary of the place's ModuleScripts for use as AI context.
+/// `modules` filters by path substring; the index is built on first use or with `refresh`.
+#[tauri::command]
+pub async fn get_module_api_context(
+    modules: Option<Vec<String>>,
+    refresh: Option<bool>,
+    session: Option<String>,
+) -> Result<ApiContext, String> {
+    let stale = MODULE_INDEX.lock().indexed_at.is_none();
+    if stale || refresh.unwrap_or(false) {
+        refresh_index(session.as_deref()).await?;
+    }
+
+    let index = MODULE_INDEX.lock();
+    let filters: Vec<String> = modules
+        .unwrap_or_default()
+        .iter()
+        .map(|filter| filter.to_lowercase())
+        .collect();
+    let selected: Vec<ModuleApi> = index
+        .modules
+        .iter()
+        .filter(|module| {
+            filters.is_empty()
+                || filters
+                    .iter()
+                    .any(|filter| module.path.to_lowercase().contains(filter))
+        })
+        .filter(|module| module.items.iter().any(|item| !item.private))
+        .cloned()
+        .collect();
+
+    let mut summary = String::new();
+    let mut truncated = false;
+    for module in &selected {
+        let text = summarize(module);
+        if summary.len() + text.len() > MAX_CONTEXT_CHARS {
+            truncated = true;
+            break;
+        }
+        summary.push_str(&text);
+        summary.push('\n');
+    }
+
+    Ok(ApiContext {
+        modules: selected,
+        summary: summary.trim_end().to_string(),
+        indexed_at: index.indexed_at,
+        truncated,
+    })
+}
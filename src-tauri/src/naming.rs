@@ -0,0 +1,299 @@
+//! Naming Conventions
+//!
+//! Configurable rules (a case style and/or a regex, scoped by class and folder)
+//! that instance names must follow. Rules are checked against a snapshot of the
+//! live tree on demand, and against AI-proposed creates and renames before the
+//! bridge forwards them to Studio: `error` rules block the change, `warn` rules
+//! let it through and report the problem alongside the result.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::bridge::{self, StudioRequest};
+use crate::config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NameCase {
+    #[serde(rename = "PascalCase")]
+    Pascal,
+    #[serde(rename = "camelCase")]
+    Camel,
+    #[serde(rename = "snake_case")]
+    Snake,
+    #[serde(rename = "UPPER_SNAKE_CASE")]
+    UpperSnake,
+    #[serde(rename = "kebab-case")]
+    Kebab,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamingRule {
+    /// Exact ClassName the rule applies to; every class when unset
+    #[serde(default)]
+    pub class_name: Option<String>,
+    /// Only instances inside this path, e.g. "game.ReplicatedStorage.Remotes"
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub case: Option<NameCase>,
+    /// Regex the whole name must match
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Shown instead of the generated explanation
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NamingViolation {
+    pub path: String,
+    pub name: String,
+    pub class_name: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// An instance name to check: an existing instance or one a request would create
+struct Candidate {
+    parent: String,
+    name: String,
+    /// Unknown for renames; class-specific rules are skipped then
+    class_name: Option<String>,
+}
+
+struct CompiledRule<'a> {
+    rule: &'a NamingRule,
+    pattern: Option<Regex>,
+}
+
+// Entries returned by the plugin's /instance/children handler
+#[derive(Deserialize)]
+struct InstanceInfo {
+    path: String,
+    name: String,
+    #[serde(rename = "className")]
+    class_name: String,
+}
+
+fn compile(rules: &[NamingRule]) -> Result<Vec<CompiledRule<'_>>, String> {
+    rules
+        .iter()
+        .map(|rule| {
+            let pattern = rule
+                .pattern
+                .as_deref()
+                .map(|pattern| {
+                    // Anchor so the rule describes the whole name
+                    Regex::new(&format!("^(?:{})$", pattern))
+                        .map_err(|e| format!("Invalid naming pattern \"{}\": {}", pattern, e))
+                })
+                .transpose()?;
+            Ok(CompiledRule { rule, pattern })
+        })
+        .collect()
+}
+
+fn matches_case(name: &str, case: NameCase) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    let rest = chars.as_str();
+    match case {
+        NameCase::Pascal => {
+            first.is_ascii_uppercase() && rest.chars().all(|c| c.is_ascii_alphanumeric())
+        }
+        NameCase::Camel => {
+            first.is_ascii_lowercase() && rest.chars().all(|c| c.is_ascii_alphanumeric())
+        }
+        NameCase::Snake => {
+            first.is_ascii_lowercase()
+                && rest
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        }
+        NameCase::UpperSnake => {
+            first.is_ascii_uppercase()
+                && rest
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        }
+        NameCase::Kebab => {
+            first.is_ascii_lowercase()
+                && rest
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        }
+    }
+}
+
+fn case_label(case: NameCase) -> &'static str {
+    match case {
+        NameCase::Pascal => "PascalCase",
+        NameCase::Camel => "camelCase",
+        NameCase::Snake => "snake_case",
+        NameCase::UpperSnake => "UPPER_SNAKE_CASE",
+        NameCase::Kebab => "kebab-case",
+    }
+}
+
+fn in_folder(parent: &str, folder: &str) -> bool {
+    let folder = folder.trim_end_matches('.');
+    parent == folder || parent.starts_with(&format!("{}.", folder))
+}
+
+fn evaluate(rules: &[CompiledRule], candidate: &Candidate) -> Vec<NamingViolation> {
+    let mut violations = Vec::new();
+    for CompiledRule { rule, pattern } in rules {
+        if let Some(class_name) = &rule.class_name {
+            if candidate.class_name.as_ref() != Some(class_name) {
+                continue;
+            }
+        }
+        if let Some(folder) = &rule.folder {
+            if !in_folder(&candidate.parent, folder) {
+                continue;
+            }
+        }
+
+        let problem = match (rule.case, pattern) {
+            (Some(case), _) if !matches_case(&candidate.name, case) => {
+                Some(format!("should be {}", case_label(case)))
+            }
+            (_, Some(pattern)) if !pattern.is_match(&candidate.name) => Some(format!(
+                "should match /{}/",
+                rule.pattern.as_deref().unwrap_or_default()
+            )),
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            violations.push(NamingViolation {
+                path: format!("{}.{}", candidate.parent, candidate.name),
+                name: candidate.name.clone(),
+                class_name: candidate.class_name.clone(),
+                severity: rule.severity,
+                message: rule
+                    .message
+                    .clone()
+                    .unwrap_or_else(|| format!("\"{}\" {}", candidate.name, problem)),
+            });
+        }
+    }
+    violations
+}
+
+fn split_path(path: &str) -> Option<(String, String)> {
+    let (parent, name) = path.rsplit_once('.')?;
+    Some((parent.to_string(), name.to_string()))
+}
+
+/// Names a request would create or rename to
+fn proposed_names(request: &StudioRequest) -> Vec<Candidate> {
+    let body: serde_json::Value = request
+        .body
+        .as_deref()
+        .and_then(|body| serde_json::from_str(body).ok())
+        .unwrap_or_default();
+    let str_field = |value: &serde_json::Value, key: &str| {
+        value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+    };
+    let created = |item: &serde_json::Value| {
+        Some(Candidate {
+            parent: str_field(item, "parent")?,
+            name: str_field(item, "name")?,
+            class_name: str_field(item, "className"),
+        })
+    };
+    let renamed = |item: &serde_json::Value| {
+        if str_field(item, "property").as_deref() != Some("Name") {
+            return None;
+        }
+        let (parent, _) = split_path(&str_field(item, "path")?)?;
+        Some(Candidate {
+            parent,
+            name: str_field(item, "value")?,
+            class_name: None,
+        })
+    };
+    let each = |key: &str| {
+        body.get(key)
+            .and_then(|items| items.as_array())
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    match request.path.as_str() {
+        "/instance/create" => created(&body).into_iter().collect(),
+        "/instance/bulk-create" => each("instances").iter().filter_map(created).collect(),
+        "/scaffold/apply" => each("steps").iter().filter_map(created).collect(),
+        "/instance/set" => renamed(&body).into_iter().collect(),
+        "/instance/bulk-set" => each("operations").iter().filter_map(renamed).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Check the names a request would introduce against the configured rules.
+/// A misconfigured rule set is reported once and otherwise ignored, so it
+/// can't block every edit.
+pub fn review_request(request: &StudioRequest) -> Vec<NamingViolation> {
+    let rules = config::current().naming.rules;
+    if rules.is_empty() {
+        return Vec::new();
+    }
+    let compiled = match compile(&rules) {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            println!("[Stud Naming] {}", e);
+            return Vec::new();
+        }
+    };
+    proposed_names(request)
+        .iter()
+        .flat_map(|candidate| evaluate(&compiled, candidate))
+        .collect()
+}
+
+/// Check every instance under `root` (default `game`) against the naming rules
+#[tauri::command]
+pub async fn check_naming(
+    root: Option<String>,
+    session: Option<String>,
+) -> Result<Vec<NamingViolation>, String> {
+    let rules = config::current().naming.rules;
+    let compiled = compile(&rules)?;
+    if compiled.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let result = bridge::studio_request_background(
+        session.as_deref(),
+        "/instance/children",
+        serde_json::json!({ "path": root.unwrap_or_else(|| "game".to_string()), "recursive": true }),
+    )
+    .await?;
+    let instances: Vec<InstanceInfo> = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+
+    Ok(instances
+        .into_iter()
+        .filter_map(|instance| {
+            let (parent, _) = split_path(&instance.path)?;
+            Some(Candidate {
+                parent,
+                name: instance.name,
+                class_name: Some(instance.class_name),
+            })
+        })
+        .flat_map(|candidate| evaluate(&compiled, &candidate))
+        .collect())
+}
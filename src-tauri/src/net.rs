@@ -0,0 +1,217 @@
+//! Outbound Network
+//!
+//! The HTTP client for requests that leave the machine (model providers,
+//! sign-in, Roblox's web APIs), set up from the `network` config so they get
+//! through corporate proxies. By default the system's proxy settings are used:
+//! HTTP(S)_PROXY / ALL_PROXY and NO_PROXY, plus the OS settings on Windows and
+//! macOS. A proxy can also be set by hand, with its password kept in the
+//! keychain (see `vault`). Local hosts never go through the proxy.
+//!
+//! Failed requests are explained by `describe`, which says what to check
+//! rather than passing on hyper's error chain alone.
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::error::Error as _;
+use std::time::{Duration, Instant};
+
+use crate::config::{self, NetworkConfig, ProxyMode, StudConfig};
+use crate::vault;
+
+/// Hosts that skip a manually set proxy, on top of the configured ones
+const LOCAL_HOSTS: &str = "localhost,127.0.0.1,::1";
+const CHECK_TIMEOUT_SECS: u64 = 10;
+/// Hosts the connectivity test tries when it isn't given a URL
+const CHECK_URLS: &[&str] = &[
+    "https://auth.openai.com",
+    "https://chatgpt.com/backend-api/codex/models",
+    "https://api.openai.com/v1/models",
+    "https://api.anthropic.com/v1/models",
+    "https://openrouter.ai/api/v1/models",
+];
+
+lazy_static::lazy_static! {
+    /// Built on first use and again after the network config changes
+    static ref CLIENT: RwLock<Option<reqwest::Client>> = RwLock::new(None);
+}
+
+fn build(config: &StudConfig) -> Result<reqwest::Client, String> {
+    let network = &config.network;
+    let mut builder = reqwest::Client::builder();
+    let connect_secs = config.llm.timeouts.connect_secs;
+    if connect_secs > 0 {
+        builder = builder.connect_timeout(Duration::from_secs(connect_secs));
+    }
+    let builder = match network.proxy {
+        ProxyMode::System => builder,
+        ProxyMode::None => builder.no_proxy(),
+        ProxyMode::Manual => {
+            let url = network
+                .proxy_url
+                .as_deref()
+                .ok_or("A manual proxy needs network.proxy_url")?;
+            let mut proxy = reqwest::Proxy::all(url)
+                .map_err(|e| format!("Invalid proxy URL '{}': {}", url, e))?;
+            if let Some(username) = network.proxy_username.as_deref() {
+                let password = vault::proxy_password()?.unwrap_or_default();
+                proxy = proxy.basic_auth(username, &password);
+            }
+            let no_proxy = match network.no_proxy.as_deref() {
+                Some(hosts) if !hosts.trim().is_empty() => format!("{},{}", LOCAL_HOSTS, hosts),
+                _ => LOCAL_HOSTS.to_string(),
+            };
+            builder.proxy(proxy.no_proxy(reqwest::NoProxy::from_string(&no_proxy)))
+        }
+    };
+    builder
+        .build()
+        .map_err(|e| format!("Failed to set up the HTTP client: {}", e))
+}
+
+/// The shared client for requests to the internet
+pub(crate) fn client() -> reqwest::Client {
+    if let Some(client) = CLIENT.read().as_ref() {
+        return client.clone();
+    }
+    let client = build(&config::current()).unwrap_or_else(|e| {
+        println!(
+            "[Stud Net] {}; falling back to the system proxy settings",
+            e
+        );
+        reqwest::Client::new()
+    });
+    *CLIENT.write() = Some(client.clone());
+    client
+}
+
+/// Rebuild the client on next use, after the network config or proxy password changed
+pub(crate) fn reset() {
+    *CLIENT.write() = None;
+}
+
+/// Where requests go, for messages
+fn route(network: &NetworkConfig) -> String {
+    match network.proxy {
+        ProxyMode::Manual => format!(
+            "through the proxy at {}",
+            network.proxy_url.as_deref().unwrap_or("(not set)")
+        ),
+        ProxyMode::None => "directly (proxy turned off)".to_string(),
+        ProxyMode::System => ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+            .map(|url| format!("through the system proxy at {}", url))
+            .unwrap_or_else(|| "using the system proxy settings".to_string()),
+    }
+}
+
+/// Explain a failed request, with what to check
+pub(crate) fn describe(e: &reqwest::Error) -> String {
+    let mut detail = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        detail.push_str(": ");
+        detail.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    let host = e
+        .url()
+        .and_then(|url| url.host_str())
+        .unwrap_or("the server")
+        .to_string();
+    let network = config::current().network;
+    let lower = detail.to_lowercase();
+
+    let hint = if lower.contains("proxy authorization required") {
+        "the proxy wants a username and password. Set them in the network settings".to_string()
+    } else if lower.contains("tunnel error") {
+        format!(
+            "the proxy refused to connect to {}. Check the proxy address, and that the proxy allows this host",
+            host
+        )
+    } else if lower.contains("certificate") {
+        "its certificate wasn't trusted. Proxies that inspect HTTPS need their root certificate installed in the system's trust store".to_string()
+    } else if e.is_timeout() {
+        format!(
+            "the request timed out going {}. Check your connection, or set a proxy in the network settings",
+            route(&network)
+        )
+    } else if e.is_connect() {
+        match network.proxy {
+            ProxyMode::Manual => format!(
+                "couldn't connect {}. Check that the proxy is running and its address is right",
+                route(&network)
+            ),
+            _ => format!(
+                "couldn't connect going {}. If you're behind a corporate proxy, set it in the network settings",
+                route(&network)
+            ),
+        }
+    } else {
+        return format!("Request to {} failed: {}", host, detail);
+    };
+    format!("Couldn't reach {}: {} ({})", host, hint, detail)
+}
+
+/// One host the connectivity test tried
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectivityCheck {
+    pub url: String,
+    /// Any HTTP response counts, even an error status: the host was reached
+    pub ok: bool,
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectivityReport {
+    /// How requests were sent, e.g. "through the proxy at http://proxy:8080"
+    pub route: String,
+    pub checks: Vec<ConnectivityCheck>,
+}
+
+async fn check(client: &reqwest::Client, url: String) -> ConnectivityCheck {
+    let started = Instant::now();
+    let result = client
+        .get(&url)
+        .timeout(Duration::from_secs(CHECK_TIMEOUT_SECS))
+        .send()
+        .await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(response) => ConnectivityCheck {
+            url,
+            ok: true,
+            status: Some(response.status().as_u16()),
+            latency_ms,
+            error: None,
+        },
+        Err(e) => ConnectivityCheck {
+            url,
+            ok: false,
+            status: None,
+            latency_ms,
+            error: Some(describe(&e)),
+        },
+    }
+}
+
+/// Try reaching the model providers (or just `url`) with the current network settings
+#[tauri::command]
+pub async fn test_connectivity(url: Option<String>) -> Result<ConnectivityReport, String> {
+    let config = config::current();
+    let network = &config.network;
+    network.validate()?;
+    let client = build(&config)?;
+    let urls = match url {
+        Some(url) => vec![url],
+        None => CHECK_URLS.iter().map(|url| url.to_string()).collect(),
+    };
+    let checks =
+        futures_util::future::join_all(urls.into_iter().map(|url| check(&client, url))).await;
+    Ok(ConnectivityReport {
+        route: route(network),
+        checks,
+    })
+}
@@ -0,0 +1,214 @@
+//! OAuth Providers
+//!
+//! What differs between the services Stud can sign in to: where the browser is
+//! sent, how codes and refresh tokens are exchanged, and how the signed-in
+//! account is read from the token response. The flow itself (PKCE, callback,
+//! storage, refresh) is shared; see `auth` and `tokens`.
+//!
+//! Google wants every app to register its own OAuth client, so Google sign-in
+//! only works once one is set in the config's `oauth` section.
+
+use serde::{Deserialize, Serialize};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use crate::config;
+
+const OPENAI_CLIENT_ID: &str = "app_EMoamEEZ73f0CkXaXp7hrann";
+const ANTHROPIC_CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum OAuthProvider {
+    /// ChatGPT Plus/Pro, for the Codex API
+    #[default]
+    ChatGpt,
+    /// Claude Pro/Max
+    Anthropic,
+    Google,
+}
+
+/// Who a token response belongs to
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Identity {
+    pub account_id: Option<String>,
+    pub email: Option<String>,
+}
+
+impl OAuthProvider {
+    pub fn name(self) -> &'static str {
+        match self {
+            OAuthProvider::ChatGpt => "ChatGPT",
+            OAuthProvider::Anthropic => "Anthropic",
+            OAuthProvider::Google => "Google",
+        }
+    }
+
+    pub(crate) fn client_id(self) -> Result<String, String> {
+        match self {
+            OAuthProvider::ChatGpt => Ok(OPENAI_CLIENT_ID.to_string()),
+            OAuthProvider::Anthropic => Ok(ANTHROPIC_CLIENT_ID.to_string()),
+            OAuthProvider::Google => config::current()
+                .oauth
+                .google_client_id
+                .filter(|id| !id.is_empty())
+                .ok_or_else(|| {
+                    "Set oauth.google_client_id in the config to sign in with Google".to_string()
+                }),
+        }
+    }
+
+    /// Sent with token requests by providers that issue one to desktop apps
+    pub(crate) fn client_secret(self) -> Option<String> {
+        match self {
+            OAuthProvider::Google => config::current().oauth.google_client_secret,
+            _ => None,
+        }
+    }
+
+    /// Path on the callback server the provider's registered redirect names
+    pub(crate) fn callback_path(self) -> &'static str {
+        match self {
+            OAuthProvider::Anthropic => "/callback",
+            _ => "/auth/callback",
+        }
+    }
+
+    /// Whether the provider accepts the `stud://` deep link as a redirect
+    pub(crate) fn allows_deep_link(self) -> bool {
+        self == OAuthProvider::ChatGpt
+    }
+
+    /// Issuer serving the device code flow, for providers that have one.
+    /// ChatGPT's is the one the Codex CLI signs in with.
+    pub(crate) fn device_auth_issuer(self) -> Option<&'static str> {
+        match self {
+            OAuthProvider::ChatGpt => Some("https://auth.openai.com"),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn token_url(self) -> &'static str {
+        match self {
+            OAuthProvider::ChatGpt => "https://auth.openai.com/oauth/token",
+            OAuthProvider::Anthropic => "https://console.anthropic.com/v1/oauth/token",
+            OAuthProvider::Google => "https://oauth2.googleapis.com/token",
+        }
+    }
+
+    /// RFC 7009 endpoint refresh tokens are revoked at on sign-out, for
+    /// providers that have one
+    pub(crate) fn revoke_url(self) -> Option<&'static str> {
+        match self {
+            OAuthProvider::Google => Some("https://oauth2.googleapis.com/revoke"),
+            _ => None,
+        }
+    }
+
+    /// Anthropic takes token requests as JSON; the others as a form
+    pub(crate) fn json_token_requests(self) -> bool {
+        self == OAuthProvider::Anthropic
+    }
+
+    /// Anthropic also wants the flow's `state` when a code is exchanged
+    pub(crate) fn exchange_includes_state(self) -> bool {
+        self == OAuthProvider::Anthropic
+    }
+
+    /// Where to send the browser to start a sign-in
+    pub(crate) fn authorize_url(
+        self,
+        challenge: &str,
+        state: &str,
+        redirect_uri: &str,
+    ) -> Result<String, String> {
+        let client_id = self.client_id()?;
+        let (base, scope, extra): (&str, &str, &[(&str, &str)]) = match self {
+            OAuthProvider::ChatGpt => (
+                "https://auth.openai.com/oauth/authorize",
+                "openid profile email offline_access",
+                &[
+                    ("id_token_add_organizations", "true"),
+                    ("codex_cli_simplified_flow", "true"),
+                    ("originator", "stud"),
+                ],
+            ),
+            OAuthProvider::Anthropic => (
+                "https://claude.ai/oauth/authorize",
+                "org:create_api_key user:profile user:inference",
+                &[("code", "true")],
+            ),
+            OAuthProvider::Google => (
+                "https://accounts.google.com/o/oauth2/v2/auth",
+                "openid email profile https://www.googleapis.com/auth/cloud-platform",
+                // Without both, Google only issues a refresh token on the first sign-in
+                &[("access_type", "offline"), ("prompt", "consent")],
+            ),
+        };
+        let mut params = vec![
+            ("response_type", "code"),
+            ("client_id", client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("scope", scope),
+            ("code_challenge", challenge),
+            ("code_challenge_method", "S256"),
+            ("state", state),
+        ];
+        params.extend_from_slice(extra);
+        reqwest::Url::parse_with_params(base, &params)
+            .map(String::from)
+            .map_err(|e| format!("Failed to build the {} sign-in URL: {}", self.name(), e))
+    }
+
+    /// The account a token response is for. OpenAI and Google name it in the
+    /// ID token; Anthropic sends an `account` object alongside the tokens.
+    pub(crate) fn identity(
+        self,
+        id_token: Option<&str>,
+        account: Option<&serde_json::Value>,
+    ) -> Identity {
+        let field =
+            |value: &serde_json::Value, key: &str| value.get(key)?.as_str().map(str::to_string);
+        match self {
+            OAuthProvider::Anthropic => Identity {
+                account_id: account.and_then(|account| field(account, "uuid")),
+                email: account.and_then(|account| field(account, "email_address")),
+            },
+            OAuthProvider::ChatGpt | OAuthProvider::Google => {
+                let Some(claims) = id_token.and_then(claims) else {
+                    return Identity::default();
+                };
+                let account_id = if self == OAuthProvider::ChatGpt {
+                    chatgpt_account_id(&claims)
+                } else {
+                    field(&claims, "sub")
+                };
+                Identity {
+                    account_id,
+                    email: field(&claims, "email"),
+                }
+            }
+        }
+    }
+}
+
+fn claims(id_token: &str) -> Option<serde_json::Value> {
+    let payload = id_token.split('.').nth(1)?;
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?).ok()
+}
+
+/// The ChatGPT account an ID token belongs to, for the ChatGPT-Account-Id header
+fn chatgpt_account_id(claims: &serde_json::Value) -> Option<String> {
+    let id = claims
+        .get("chatgpt_account_id")
+        .or_else(|| {
+            claims
+                .get("https://api.openai.com/auth")?
+                .get("chatgpt_account_id")
+        })
+        .or_else(|| claims.get("organizations")?.get(0)?.get("id"))?;
+    id.as_str().map(str::to_string)
+}
@@ -0,0 +1,460 @@
+//! Ollama
+//!
+//! Local models served by Ollama, so simple Luau tasks can run offline and
+//! without an account. The provider proxy sends `/llm/ollama/...` to the
+//! server in the config's `llm` section. OpenAI-style chat completions are
+//! translated to Ollama's `/api/chat`, and its NDJSON stream back to OpenAI's
+//! SSE chunks, so the frontend can treat it like any OpenAI-compatible
+//! endpoint. Other paths are passed through unchanged.
+
+use axum::body::Body;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::config;
+use crate::llm_proxy::error;
+
+/// How long to wait for a local server before deciding it isn't running
+const DETECT_TIMEOUT_MS: u64 = 1500;
+const LIST_TIMEOUT_SECS: u64 = 10;
+
+/// OpenAI request fields and the Ollama options they map to
+const OPTIONS: &[(&str, &str)] = &[
+    ("temperature", "temperature"),
+    ("top_p", "top_p"),
+    ("seed", "seed"),
+    ("max_tokens", "num_predict"),
+    ("max_completion_tokens", "num_predict"),
+    ("frequency_penalty", "frequency_penalty"),
+    ("presence_penalty", "presence_penalty"),
+];
+
+pub(crate) fn base_url() -> String {
+    config::current()
+        .llm
+        .ollama_url
+        .trim_end_matches('/')
+        .to_string()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaStatus {
+    pub running: bool,
+    pub url: String,
+    pub version: Option<String>,
+}
+
+/// An installed model, as `ollama list` shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaModel {
+    pub name: String,
+    pub size: u64,
+    pub family: Option<String>,
+    pub parameter_size: Option<String>,
+    pub quantization: Option<String>,
+    pub modified_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+struct Tag {
+    name: String,
+    #[serde(default)]
+    size: u64,
+    modified_at: Option<String>,
+    #[serde(default)]
+    details: TagDetails,
+}
+
+#[derive(Default, Deserialize)]
+struct TagDetails {
+    family: Option<String>,
+    parameter_size: Option<String>,
+    quantization_level: Option<String>,
+}
+
+/// Whether an Ollama server is answering at the configured URL
+#[tauri::command]
+pub async fn detect_ollama() -> OllamaStatus {
+    let url = base_url();
+    let response = reqwest::Client::new()
+        .get(format!("{}/api/version", url))
+        .timeout(Duration::from_millis(DETECT_TIMEOUT_MS))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    let Ok(response) = response else {
+        return OllamaStatus {
+            running: false,
+            url,
+            version: None,
+        };
+    };
+    let version = response
+        .json::<Value>()
+        .await
+        .ok()
+        .and_then(|body| body.get("version")?.as_str().map(str::to_string));
+    OllamaStatus {
+        running: true,
+        url,
+        version,
+    }
+}
+
+/// Models installed on the local Ollama server
+#[tauri::command]
+pub async fn list_ollama_models() -> Result<Vec<OllamaModel>, String> {
+    let url = base_url();
+    let response = reqwest::Client::new()
+        .get(format!("{}/api/tags", url))
+        .timeout(Duration::from_secs(LIST_TIMEOUT_SECS))
+        .send()
+        .await
+        .map_err(|e| format!("Ollama isn't running at {}: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Ollama returned {}", response.status()));
+    }
+    let tags: TagsResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to read Ollama's model list: {}", e))?;
+    Ok(tags
+        .models
+        .into_iter()
+        .map(|tag| OllamaModel {
+            name: tag.name,
+            size: tag.size,
+            family: tag.details.family,
+            parameter_size: tag.details.parameter_size,
+            quantization: tag.details.quantization_level,
+            modified_at: tag.modified_at,
+        })
+        .collect())
+}
+
+/// Translate an OpenAI chat completion request to Ollama's `/api/chat`
+fn to_ollama_request(request: &Value) -> Value {
+    let mut options = Map::new();
+    for (from, to) in OPTIONS {
+        if let Some(value) = request.get(*from).filter(|value| !value.is_null()) {
+            options.insert(to.to_string(), value.clone());
+        }
+    }
+    match request.get("stop") {
+        Some(Value::String(stop)) => {
+            options.insert("stop".to_string(), json!([stop]));
+        }
+        Some(stop @ Value::Array(_)) => {
+            options.insert("stop".to_string(), stop.clone());
+        }
+        _ => {}
+    }
+
+    // Tool results name their call by ID; Ollama wants the tool's name
+    let mut tool_names = HashMap::new();
+    let messages: Vec<Value> = request["messages"]
+        .as_array()
+        .map(|messages| {
+            messages
+                .iter()
+                .map(|message| to_ollama_message(message, &mut tool_names))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut body = json!({
+        "model": request["model"],
+        "messages": messages,
+        "stream": request["stream"].as_bool().unwrap_or(false),
+        "options": options,
+    });
+    if let Some(tools) = request.get("tools").filter(|tools| tools.is_array()) {
+        body["tools"] = tools.clone();
+    }
+    match request["response_format"]["type"].as_str() {
+        Some("json_object") => body["format"] = json!("json"),
+        Some("json_schema") => {
+            body["format"] = request["response_format"]["json_schema"]["schema"].clone()
+        }
+        _ => {}
+    }
+    body
+}
+
+fn to_ollama_message<'a>(message: &'a Value, tool_names: &mut HashMap<&'a str, &'a str>) -> Value {
+    let mut text = String::new();
+    let mut images = Vec::new();
+    match &message["content"] {
+        Value::String(content) => text.push_str(content),
+        Value::Array(parts) => {
+            for part in parts {
+                match part["type"].as_str() {
+                    Some("text") => {
+                        if !text.is_empty() {
+                            text.push('\n');
+                        }
+                        text.push_str(part["text"].as_str().unwrap_or_default());
+                    }
+                    // Ollama takes images as bare base64, not data URLs
+                    Some("image_url") => {
+                        if let Some((_, data)) = part["image_url"]["url"]
+                            .as_str()
+                            .and_then(|url| url.split_once(";base64,"))
+                        {
+                            images.push(data);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let mut out = json!({ "role": message["role"], "content": text });
+    if !images.is_empty() {
+        out["images"] = json!(images);
+    }
+    if let Some(calls) = message["tool_calls"].as_array() {
+        let calls: Vec<Value> = calls
+            .iter()
+            .map(|call| {
+                let function = &call["function"];
+                if let (Some(id), Some(name)) = (call["id"].as_str(), function["name"].as_str()) {
+                    tool_names.insert(id, name);
+                }
+                // OpenAI sends arguments as a JSON string, Ollama as an object
+                let arguments = function["arguments"]
+                    .as_str()
+                    .and_then(|arguments| serde_json::from_str(arguments).ok())
+                    .unwrap_or_else(|| function["arguments"].clone());
+                json!({ "function": { "name": function["name"], "arguments": arguments } })
+            })
+            .collect();
+        out["tool_calls"] = json!(calls);
+    }
+    if let Some(name) = message["tool_call_id"]
+        .as_str()
+        .and_then(|id| tool_names.get(id))
+    {
+        out["tool_name"] = json!(name);
+    }
+    out
+}
+
+/// Turns Ollama's replies into OpenAI's, for one completion
+struct Translator {
+    id: String,
+    created: u64,
+    include_usage: bool,
+    /// Tool calls so far, which OpenAI numbers across the whole stream
+    tool_calls: usize,
+}
+
+impl Translator {
+    fn new(include_usage: bool) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+            created: crate::bridge::chrono_lite_timestamp() / 1000,
+            include_usage,
+            tool_calls: 0,
+        }
+    }
+
+    fn message(&mut self, message: &Value, delta: bool) -> Value {
+        let mut out = json!({
+            "role": "assistant",
+            "content": message["content"].as_str().unwrap_or_default(),
+        });
+        if let Some(calls) = message["tool_calls"]
+            .as_array()
+            .filter(|calls| !calls.is_empty())
+        {
+            let calls: Vec<Value> = calls
+                .iter()
+                .map(|call| {
+                    let index = self.tool_calls;
+                    self.tool_calls += 1;
+                    let mut call = json!({
+                        "id": format!("call_{}", uuid::Uuid::new_v4().simple()),
+                        "type": "function",
+                        "function": {
+                            "name": call["function"]["name"],
+                            "arguments": call["function"]["arguments"].to_string(),
+                        },
+                    });
+                    if delta {
+                        call["index"] = json!(index);
+                    }
+                    call
+                })
+                .collect();
+            out["tool_calls"] = json!(calls);
+        }
+        out
+    }
+
+    fn finish_reason(&self, response: &Value) -> &'static str {
+        if self.tool_calls > 0 {
+            "tool_calls"
+        } else if response["done_reason"] == "length" {
+            "length"
+        } else {
+            "stop"
+        }
+    }
+
+    fn usage(response: &Value) -> Value {
+        let prompt = response["prompt_eval_count"].as_u64().unwrap_or_default();
+        let completion = response["eval_count"].as_u64().unwrap_or_default();
+        json!({
+            "prompt_tokens": prompt,
+            "completion_tokens": completion,
+            "total_tokens": prompt + completion,
+        })
+    }
+
+    fn completion(&mut self, response: &Value) -> Value {
+        let message = self.message(&response["message"], false);
+        json!({
+            "id": self.id,
+            "object": "chat.completion",
+            "created": self.created,
+            "model": response["model"],
+            "choices": [{
+                "index": 0,
+                "message": message,
+                "finish_reason": self.finish_reason(response),
+            }],
+            "usage": Self::usage(response),
+        })
+    }
+
+    fn chunk(&self, model: &Value, choices: Value) -> String {
+        let chunk = json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": model,
+            "choices": choices,
+        });
+        format!("data: {}\n\n", chunk)
+    }
+
+    /// The SSE events for one line of Ollama's stream
+    fn events(&mut self, line: &Value) -> String {
+        if let Some(message) = line["error"].as_str() {
+            return format!("data: {}\n\n", json!({ "error": { "message": message } }));
+        }
+        let delta = self.message(&line["message"], true);
+        if line["done"] != true {
+            return self.chunk(
+                &line["model"],
+                json!([{ "index": 0, "delta": delta, "finish_reason": null }]),
+            );
+        }
+        let mut events = self.chunk(
+            &line["model"],
+            json!([{ "index": 0, "delta": delta, "finish_reason": self.finish_reason(line) }]),
+        );
+        if self.include_usage {
+            let usage = json!({
+                "id": self.id,
+                "object": "chat.completion.chunk",
+                "created": self.created,
+                "model": line["model"],
+                "choices": [],
+                "usage": Self::usage(line),
+            });
+            events.push_str(&format!("data: {}\n\n", usage));
+        }
+        events.push_str("data: [DONE]\n\n");
+        events
+    }
+}
+
+/// Answer an OpenAI chat completion request with the local server
+pub(crate) async fn chat(client: &reqwest::Client, body: Bytes) -> Response {
+    let request: Value = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            return error(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid chat request: {}", e),
+            )
+        }
+    };
+    let stream = request["stream"].as_bool().unwrap_or(false);
+    let mut translator =
+        Translator::new(request["stream_options"]["include_usage"].as_bool() == Some(true));
+
+    let url = base_url();
+    let response = match client
+        .post(format!("{}/api/chat", url))
+        .json(&to_ollama_request(&request))
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            return error(
+                StatusCode::BAD_GATEWAY,
+                format!("Ollama isn't running at {}: {}", url, e),
+            )
+        }
+    };
+    if !response.status().is_success() {
+        let status =
+            StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        let text = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&text)
+            .ok()
+            .and_then(|body| body["error"].as_str().map(str::to_string))
+            .unwrap_or(text);
+        return (status, Json(json!({ "error": { "message": message } }))).into_response();
+    }
+
+    if !stream {
+        return match response.json::<Value>().await {
+            Ok(response) => Json(translator.completion(&response)).into_response(),
+            Err(e) => error(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to read Ollama's reply: {}", e),
+            ),
+        };
+    }
+
+    // Lines can be split across chunks, so keep the unfinished one for the next
+    let mut pending = Vec::new();
+    let events = response.bytes_stream().map(move |chunk| {
+        chunk.map(|chunk| {
+            pending.extend_from_slice(&chunk);
+            let mut events = String::new();
+            while let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                if let Ok(line) = serde_json::from_slice::<Value>(&line) {
+                    events.push_str(&translator.events(&line));
+                }
+            }
+            Bytes::from(events)
+        })
+    });
+    (
+        [(header::CONTENT_TYPE, "text/event-stream")],
+        Body::from_stream(events),
+    )
+        .into_response()
+}
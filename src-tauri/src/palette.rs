@@ -0,0 +1,243 @@
+//! Color Palette Tool
+//!
+//! Reads every color used by the selected parts and GUI objects, computes a
+//! recolor in Rust (snap to a brand palette, or fix text contrast to meet
+//! WCAG), and sends the result back to Studio as one undoable change.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::bridge;
+
+/// WCAG AA for normal-size text
+const DEFAULT_MIN_CONTRAST: f64 = 4.5;
+
+/// One color property on one instance, as reported by the plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorUsage {
+    pub path: String,
+    pub property: String,
+    pub color: [u8; 3],
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Swatch {
+    pub hex: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PaletteReport {
+    /// Distinct colors, most used first
+    pub swatches: Vec<Swatch>,
+    pub usages: Vec<ColorUsage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PaletteTransform {
+    /// Replace every color with the closest one from `palette` (hex strings)
+    MapToPalette { palette: Vec<String> },
+    /// Lighten or darken text until it meets `min_ratio` against its background
+    EnsureContrast { min_ratio: Option<f64> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColorChange {
+    pub path: String,
+    pub property: String,
+    pub from: [u8; 3],
+    pub color: [u8; 3],
+}
+
+#[derive(Deserialize)]
+struct ExtractResponse {
+    #[serde(default)]
+    usages: Vec<ColorUsage>,
+}
+
+fn parse_hex(hex: &str) -> Result<[u8; 3], String> {
+    let digits = hex.trim().trim_start_matches('#');
+    if digits.len() != 6 {
+        return Err(format!("Invalid color \"{}\", expected #RRGGBB", hex));
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(&digits[i..i + 2], 16)
+            .map_err(|_| format!("Invalid color \"{}\", expected #RRGGBB", hex))
+    };
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn to_hex(color: [u8; 3]) -> String {
+    format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2])
+}
+
+/// "Redmean" weighted distance - cheap, and much closer to perception than plain RGB
+fn color_distance(a: [u8; 3], b: [u8; 3]) -> f64 {
+    let r_mean = (a[0] as f64 + b[0] as f64) / 2.0;
+    let dr = a[0] as f64 - b[0] as f64;
+    let dg = a[1] as f64 - b[1] as f64;
+    let db = a[2] as f64 - b[2] as f64;
+    ((2.0 + r_mean / 256.0) * dr * dr + 4.0 * dg * dg + (2.0 + (255.0 - r_mean) / 256.0) * db * db).sqrt()
+}
+
+/// WCAG relative luminance
+fn luminance(color: [u8; 3]) -> f64 {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color[0]) + 0.7152 * linear(color[1]) + 0.0722 * linear(color[2])
+}
+
+fn contrast_ratio(a: [u8; 3], b: [u8; 3]) -> f64 {
+    let (la, lb) = (luminance(a), luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+fn mix(from: [u8; 3], to: [u8; 3], t: f64) -> [u8; 3] {
+    let channel = |i: usize| (from[i] as f64 + (to[i] as f64 - from[i] as f64) * t).round() as u8;
+    [channel(0), channel(1), channel(2)]
+}
+
+/// Move `text` toward black or white (whichever contrasts more with `background`)
+/// just far enough to reach `min_ratio`
+fn fix_contrast(text: [u8; 3], background: [u8; 3], min_ratio: f64) -> [u8; 3] {
+    if contrast_ratio(text, background) >= min_ratio {
+        return text;
+    }
+    let target = if contrast_ratio([255, 255, 255], background) >= contrast_ratio([0, 0, 0], background) {
+        [255, 255, 255]
+    } else {
+        [0, 0, 0]
+    };
+    (1..=20)
+        .map(|step| mix(text, target, step as f64 / 20.0))
+        .find(|candidate| contrast_ratio(*candidate, background) >= min_ratio)
+        .unwrap_or(target)
+}
+
+fn is_text_property(property: &str) -> bool {
+    property == "TextColor3"
+}
+
+/// Compute the changes a transform would make, without touching Studio
+pub fn plan(usages: &[ColorUsage], transform: &PaletteTransform) -> Result<Vec<ColorChange>, String> {
+    let changes = match transform {
+        PaletteTransform::MapToPalette { palette } => {
+            let palette = palette
+                .iter()
+                .map(|hex| parse_hex(hex))
+                .collect::<Result<Vec<_>, _>>()?;
+            if palette.is_empty() {
+                return Err("Palette must contain at least one color".to_string());
+            }
+            usages
+                .iter()
+                .map(|usage| {
+                    let nearest = palette
+                        .iter()
+                        .copied()
+                        .min_by(|a, b| {
+                            color_distance(usage.color, *a).total_cmp(&color_distance(usage.color, *b))
+                        })
+                        .unwrap_or(usage.color);
+                    (usage, nearest)
+                })
+                .collect::<Vec<_>>()
+        }
+        PaletteTransform::EnsureContrast { min_ratio } => {
+            let min_ratio = min_ratio.unwrap_or(DEFAULT_MIN_CONTRAST);
+            let backgrounds: HashMap<&str, [u8; 3]> = usages
+                .iter()
+                .filter(|usage| usage.property == "BackgroundColor3")
+                .map(|usage| (usage.path.as_str(), usage.color))
+                .collect();
+            usages
+                .iter()
+                .filter(|usage| is_text_property(&usage.property))
+                .filter_map(|usage| {
+                    let background = backgrounds.get(usage.path.as_str())?;
+                    Some((usage, fix_contrast(usage.color, *background, min_ratio)))
+                })
+                .collect()
+        }
+    };
+
+    Ok(changes
+        .into_iter()
+        .filter(|(usage, color)| usage.color != *color)
+        .map(|(usage, color)| ColorChange {
+            path: usage.path.clone(),
+            property: usage.property.clone(),
+            from: usage.color,
+            color,
+        })
+        .collect())
+}
+
+async fn fetch_usages(session: Option<&str>) -> Result<Vec<ColorUsage>, String> {
+    let result = bridge::studio_request(session, "/palette/extract", serde_json::json!({})).await?;
+    let response: ExtractResponse = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+    if response.usages.is_empty() {
+        return Err("No colored parts or GUI objects in the selection".to_string());
+    }
+    Ok(response.usages)
+}
+
+/// Collect the colors used by the current Studio selection
+#[tauri::command]
+pub async fn extract_palette(session: Option<String>) -> Result<PaletteReport, String> {
+    let usages = fetch_usages(session.as_deref()).await?;
+
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for usage in &usages {
+        *counts.entry(usage.color).or_default() += 1;
+    }
+    let mut swatches: Vec<Swatch> = counts
+        .into_iter()
+        .map(|(color, count)| Swatch {
+            hex: to_hex(color),
+            count,
+        })
+        .collect();
+    swatches.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.hex.cmp(&b.hex)));
+
+    Ok(PaletteReport { swatches, usages })
+}
+
+/// Show what a palette transform would change in the current selection
+#[tauri::command]
+pub async fn preview_palette_transform(
+    transform: PaletteTransform,
+    session: Option<String>,
+) -> Result<Vec<ColorChange>, String> {
+    let usages = fetch_usages(session.as_deref()).await?;
+    plan(&usages, &transform)
+}
+
+/// Recolor the current selection in a single undoable step
+#[tauri::command]
+pub async fn apply_palette_transform(
+    transform: PaletteTransform,
+    session: Option<String>,
+) -> Result<Vec<ColorChange>, String> {
+    let usages = fetch_usages(session.as_deref()).await?;
+    let changes = plan(&usages, &transform)?;
+    if changes.is_empty() {
+        return Ok(changes);
+    }
+
+    bridge::studio_request(
+        session.as_deref(),
+        "/palette/apply",
+        serde_json::json!({ "changes": changes }),
+    )
+    .await?;
+    Ok(changes)
+}
@@ -0,0 +1,303 @@
+//! Patch Bundles
+//!
+//! Exports the changes the AI made in a Studio session as a file a teammate
+//! can review and apply on their own machine, so AI-assisted work can go
+//! through human sign-off. Script writes are kept as before/after sources and
+//! shown as unified diffs; every other change is kept as the bridge request
+//! that made it (the instance changeset) and replayed through the reviewer's
+//! bridge. Find-and-replace transactions that were previewed but not applied
+//! can be included as staged edits.
+
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::bridge::{self, chrono_lite_timestamp, StudioRequest};
+use crate::{find_replace, profiles};
+
+/// Bumped when the bundle layout changes incompatibly
+const BUNDLE_FORMAT: u32 = 1;
+const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Instance changes kept per session; the oldest are dropped past this
+const MAX_CHANGES: usize = 1000;
+const DIFF_CONTEXT_LINES: usize = 3;
+
+lazy_static::lazy_static! {
+    static ref SESSIONS: Mutex<HashMap<Option<String>, SessionChanges>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Default)]
+struct SessionChanges {
+    /// Per script: the source before the first AI write, if known, and after the last
+    scripts: IndexMap<String, (Option<String>, String)>,
+    changes: Vec<InstanceChange>,
+}
+
+/// A non-script change, as the bridge request that made it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceChange {
+    /// Bridge route, e.g. `/instance/create`
+    pub route: String,
+    pub body: serde_json::Value,
+    /// Unix ms
+    pub recorded: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptPatch {
+    pub path: String,
+    /// Unified diff for review
+    pub diff: String,
+    /// Source the patch was made against; applying refuses if the script differs.
+    /// None when the AI wrote the script without it being read first.
+    pub before: Option<String>,
+    pub after: String,
+    /// From a previewed find-and-replace that was never applied
+    #[serde(default)]
+    pub staged: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchBundle {
+    pub format: u32,
+    pub id: String,
+    /// Unix ms
+    pub created: u64,
+    pub app_version: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    pub scripts: Vec<ScriptPatch>,
+    pub changes: Vec<InstanceChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PatchApplyReport {
+    pub changes_applied: usize,
+    pub scripts_written: usize,
+    /// Why applying stopped, if it did; changes before it were already made
+    pub error: Option<String>,
+}
+
+/// Whether a request belongs in its session's changeset once it succeeds
+pub(crate) fn is_recorded(request: &StudioRequest) -> bool {
+    // Only chat tool calls are AI changes; running arbitrary code isn't carried over
+    request.chat_id.is_some()
+        && profiles::is_modifying(&request.path)
+        && request.path != "/code/run"
+}
+
+/// Note a change the bridge made in Studio on behalf of a chat
+pub(crate) fn record_request(request: &StudioRequest) {
+    let body = request
+        .body
+        .as_deref()
+        .and_then(|body| serde_json::from_str(body).ok())
+        .unwrap_or(serde_json::Value::Null);
+    let mut sessions = SESSIONS.lock();
+    let changes = &mut sessions
+        .entry(request.target_session.clone())
+        .or_default()
+        .changes;
+    if changes.len() >= MAX_CHANGES {
+        changes.remove(0);
+    }
+    changes.push(InstanceChange {
+        route: request.path.clone(),
+        body,
+        recorded: chrono_lite_timestamp(),
+    });
+}
+
+/// Note that the AI replaced a script's source
+pub(crate) fn record_script(session: Option<&str>, path: &str, before: Option<&str>, after: &str) {
+    let mut sessions = SESSIONS.lock();
+    let scripts = &mut sessions
+        .entry(session.map(str::to_string))
+        .or_default()
+        .scripts;
+    match scripts.get_mut(path) {
+        Some((_, latest)) => *latest = after.to_string(),
+        None => {
+            scripts.insert(
+                path.to_string(),
+                (before.map(str::to_string), after.to_string()),
+            );
+        }
+    }
+}
+
+fn unified_diff(path: &str, before: &str, after: &str) -> String {
+    similar::TextDiff::from_lines(before, after)
+        .unified_diff()
+        .context_radius(DIFF_CONTEXT_LINES)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string()
+}
+
+fn script_patch(path: String, before: Option<String>, after: String, staged: bool) -> ScriptPatch {
+    ScriptPatch {
+        diff: unified_diff(&path, before.as_deref().unwrap_or_default(), &after),
+        path,
+        before,
+        after,
+        staged,
+    }
+}
+
+fn read_bundle(path: &str) -> Result<PatchBundle, String> {
+    let json =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read patch bundle: {}", e))?;
+    let bundle: PatchBundle =
+        serde_json::from_str(&json).map_err(|e| format!("Not a patch bundle: {}", e))?;
+    if bundle.format > BUNDLE_FORMAT {
+        return Err(format!(
+            "Patch bundle format {} is newer than this version of Stud supports ({})",
+            bundle.format, BUNDLE_FORMAT
+        ));
+    }
+    Ok(bundle)
+}
+
+/// Bundle a session's AI changes for review, writing it to `path` if given.
+/// `include_staged` adds previewed find-and-replace edits that weren't applied.
+#[tauri::command]
+pub fn export_patch_bundle(
+    session: Option<String>,
+    path: Option<String>,
+    label: Option<String>,
+    include_staged: Option<bool>,
+) -> Result<PatchBundle, String> {
+    let (mut scripts, changes) = {
+        let sessions = SESSIONS.lock();
+        let recorded = sessions.get(&session);
+        let scripts: Vec<ScriptPatch> = recorded
+            .map(|recorded| {
+                recorded
+                    .scripts
+                    .iter()
+                    .filter(|(_, (before, after))| before.as_ref() != Some(after))
+                    .map(|(path, (before, after))| {
+                        script_patch(path.clone(), before.clone(), after.clone(), false)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let changes = recorded
+            .map(|recorded| recorded.changes.clone())
+            .unwrap_or_default();
+        (scripts, changes)
+    };
+    if include_staged.unwrap_or(false) {
+        for (path, expected, source) in find_replace::staged_edits(session.as_deref()) {
+            if !scripts.iter().any(|script| script.path == path) {
+                scripts.push(script_patch(path, Some(expected), source, true));
+            }
+        }
+    }
+    if scripts.is_empty() && changes.is_empty() {
+        return Err("No AI changes to export in this session".to_string());
+    }
+
+    let bundle = PatchBundle {
+        format: BUNDLE_FORMAT,
+        id: uuid::Uuid::new_v4().to_string(),
+        created: chrono_lite_timestamp(),
+        app_version: APP_VERSION.to_string(),
+        label,
+        scripts,
+        changes,
+    };
+    if let Some(path) = path {
+        let json = serde_json::to_string_pretty(&bundle)
+            .map_err(|e| format!("Failed to serialize patch bundle: {}", e))?;
+        fs::write(&path, json).map_err(|e| format!("Failed to write patch bundle: {}", e))?;
+        println!(
+            "[Stud Patches] Exported {} script(s) and {} change(s) to {}",
+            bundle.scripts.len(),
+            bundle.changes.len(),
+            path
+        );
+    }
+    Ok(bundle)
+}
+
+/// Read a patch bundle file for review without applying it
+#[tauri::command]
+pub fn load_patch_bundle(path: String) -> Result<PatchBundle, String> {
+    read_bundle(&path)
+}
+
+/// Apply a reviewed patch bundle through this machine's bridge. Instance
+/// changes are replayed in order first, so scripts they create exist, then
+/// every script is written as one undoable change that's refused if any
+/// script no longer matches the source the patch was made against.
+#[tauri::command]
+pub async fn apply_patch_bundle(
+    path: String,
+    session: Option<String>,
+) -> Result<PatchApplyReport, String> {
+    let bundle = read_bundle(&path)?;
+    let mut report = PatchApplyReport {
+        changes_applied: 0,
+        scripts_written: 0,
+        error: None,
+    };
+
+    for change in &bundle.changes {
+        if let Err(e) =
+            bridge::studio_request(session.as_deref(), &change.route, change.body.clone()).await
+        {
+            report.error = Some(format!("{} failed: {}", change.route, e));
+            return Ok(report);
+        }
+        report.changes_applied += 1;
+    }
+
+    if !bundle.scripts.is_empty() {
+        let edits: Vec<serde_json::Value> = bundle
+            .scripts
+            .iter()
+            .map(|script| {
+                serde_json::json!({
+                    "path": script.path,
+                    "source": script.after,
+                    "expected": script.before,
+                })
+            })
+            .collect();
+        match bridge::studio_request(
+            session.as_deref(),
+            "/scripts/batch-set",
+            serde_json::json!({ "edits": edits }),
+        )
+        .await
+        {
+            Ok(_) => report.scripts_written = bundle.scripts.len(),
+            Err(e) => report.error = Some(e),
+        }
+    }
+
+    println!(
+        "[Stud Patches] Applied bundle {}: {} change(s), {} script(s){}",
+        bundle.id,
+        report.changes_applied,
+        report.scripts_written,
+        report
+            .error
+            .as_deref()
+            .map(|e| format!(" (stopped: {})", e))
+            .unwrap_or_default()
+    );
+    Ok(report)
+}
+
+/// Forget a session's recorded changes, e.g. once they've been exported.
+/// Returns how many scripts and changes were dropped.
+#[tauri::command]
+pub fn discard_patch_changes(session: Option<String>) -> usize {
+    SESSIONS.lock().remove(&session).map_or(0, |recorded| {
+        recorded.scripts.len() + recorded.changes.len()
+    })
+}
@@ -0,0 +1,169 @@
+//! Pathfinding Debugging
+//!
+//! Asks the plugin to compute paths with PathfindingService between pairs of
+//! points, then summarizes the results (length, jumps, why a path failed) so
+//! NPC navigation problems can be debugged without reading raw waypoint dumps.
+//! Paths can optionally be drawn in the world with temporary parts.
+
+use serde::{Deserialize, Serialize};
+
+use crate::bridge;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathQuery {
+    pub start: [f64; 3],
+    pub goal: [f64; 3],
+}
+
+/// Mirrors PathfindingService agent parameters; unset fields use Roblox defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AgentParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_radius: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_height: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_can_jump: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_can_climb: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub waypoint_spacing: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Waypoint {
+    pub position: [f64; 3],
+    /// "Walk", "Jump" or "Custom"
+    pub action: String,
+    #[serde(default)]
+    pub label: String,
+}
+
+// Response from the plugin's /pathfinding/compute handler
+#[derive(Deserialize)]
+struct ComputedPath {
+    status: String,
+    #[serde(default)]
+    waypoints: Vec<Waypoint>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PathResult {
+    pub start: [f64; 3],
+    pub goal: [f64; 3],
+    pub status: String,
+    pub success: bool,
+    pub waypoints: Vec<Waypoint>,
+    pub length: f64,
+    pub jumps: usize,
+    /// Straight-line distance from the last waypoint to the goal
+    pub distance_to_goal: f64,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PathReport {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<PathResult>,
+}
+
+fn distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// Plain-language explanation for a non-success Enum.PathStatus
+fn explain_status(status: &str) -> Option<&'static str> {
+    match status {
+        "Success" => None,
+        "NoPath" => Some("No walkable route exists. Look for gaps, walls, or openings too narrow for the agent radius."),
+        "ClosestNoPath" => Some("The goal can't be reached; the path ends at the closest reachable point."),
+        "ClosestOutOfRange" => Some("The goal is too far away; the path ends at the closest point within range."),
+        "FailStartNotEmpty" => Some("The start point is inside an obstacle. Move it up or away from geometry."),
+        "FailFinishNotEmpty" => Some("The goal is inside an obstacle. Move it up or away from geometry."),
+        _ => Some("Pathfinding failed for an unknown reason."),
+    }
+}
+
+fn summarize(query: PathQuery, computed: ComputedPath) -> PathResult {
+    let length = computed
+        .waypoints
+        .windows(2)
+        .map(|pair| distance(pair[0].position, pair[1].position))
+        .sum();
+    let jumps = computed
+        .waypoints
+        .iter()
+        .filter(|waypoint| waypoint.action == "Jump")
+        .count();
+    let distance_to_goal = computed
+        .waypoints
+        .last()
+        .map(|waypoint| distance(waypoint.position, query.goal))
+        .unwrap_or_else(|| distance(query.start, query.goal));
+    let reason = computed
+        .error
+        .or_else(|| explain_status(&computed.status).map(str::to_string));
+
+    PathResult {
+        start: query.start,
+        goal: query.goal,
+        success: computed.status == "Success",
+        status: computed.status,
+        waypoints: computed.waypoints,
+        length,
+        jumps,
+        distance_to_goal,
+        reason,
+    }
+}
+
+/// Compute a path for each query and report lengths, jumps and failure reasons.
+/// With `visualize`, waypoints are drawn as temporary parts in Workspace.
+#[tauri::command]
+pub async fn debug_paths(
+    queries: Vec<PathQuery>,
+    agent: Option<AgentParams>,
+    visualize: Option<bool>,
+    session: Option<String>,
+) -> Result<PathReport, String> {
+    if queries.is_empty() {
+        return Err("Provide at least one start/goal pair".to_string());
+    }
+
+    let agent = agent.unwrap_or_default();
+    let mut results = Vec::with_capacity(queries.len());
+    for (index, query) in queries.into_iter().enumerate() {
+        let result = bridge::studio_request(
+            session.as_deref(),
+            "/pathfinding/compute",
+            serde_json::json!({
+                "start": query.start,
+                "goal": query.goal,
+                "agent": agent,
+                "visualize": visualize.unwrap_or(false),
+                "label": format!("Path{}", index + 1),
+            }),
+        )
+        .await?;
+        let computed: ComputedPath = serde_json::from_value(result)
+            .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+        results.push(summarize(query, computed));
+    }
+
+    let succeeded = results.iter().filter(|result| result.success).count();
+    Ok(PathReport {
+        succeeded,
+        failed: results.len() - succeeded,
+        results,
+    })
+}
+
+/// Remove path visualizations created by debug_paths
+#[tauri::command]
+pub async fn clear_path_debug(session: Option<String>) -> Result<serde_json::Value, String> {
+    bridge::studio_request(session.as_deref(), "/pathfinding/clear", serde_json::json!({})).await
+}
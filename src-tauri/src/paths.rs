@@ -0,0 +1,20 @@
+// Shared on-disk locations for Stud's local data
+// Everything lives under the platform data directory, keyed by the app identifier
+
+use std::fs;
+use std::path::PathBuf;
+
+const APP_IDENTIFIER: &str = "com.shauryagupta.stud";
+
+/// Get (and create if needed) the app data directory
+pub fn app_data_dir() -> Result<PathBuf, String> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| "Could not determine app data folder".to_string())?
+        .join(APP_IDENTIFIER);
+
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create app data folder: {}", e))?;
+    }
+
+    Ok(dir)
+}
@@ -7,6 +7,30 @@ use std::path::PathBuf;
 // Embed the plugin source directly in the binary
 const PLUGIN_SOURCE: &str = include_str!("../../studio-plugin/stud-bridge.server.lua");
 const PLUGIN_FILENAME: &str = "stud-bridge.server.lua";
+const DEFAULT_BRIDGE_HOST: &str = "localhost:3001";
+const DEFAULT_DISCOVERY_PORT: &str = "local DISCOVERY_FIRST_PORT = 3001";
+const BRIDGE_SECRET_PLACEHOLDER: &str = "__STUD_BRIDGE_SECRET__";
+const PLUGIN_VERSION_PLACEHOLDER: &str = "__STUD_PLUGIN_VERSION__";
+
+/// Plugin source pointed at the port the bridge is actually listening on,
+/// probing from the configured one if it moves, with this install's bridge
+/// secret and the app version baked in
+fn plugin_source() -> String {
+    PLUGIN_SOURCE
+        .replace(
+            DEFAULT_BRIDGE_HOST,
+            &format!("localhost:{}", crate::bridge::bridge_port()),
+        )
+        .replace(
+            DEFAULT_DISCOVERY_PORT,
+            &format!(
+                "local DISCOVERY_FIRST_PORT = {}",
+                crate::config::current().bridge.resolved().port
+            ),
+        )
+        .replace(BRIDGE_SECRET_PLACEHOLDER, crate::bridge::bridge_secret())
+        .replace(PLUGIN_VERSION_PLACEHOLDER, env!("CARGO_PKG_VERSION"))
+}
 
 /// Check if Roblox Studio is installed on the system
 #[tauri::command]
@@ -140,7 +164,7 @@ pub fn check_plugin_installed() -> Result<PluginStatus, String> {
     if plugin_path.exists() {
         // Check if it's the current version by comparing content
         if let Ok(existing_content) = fs::read_to_string(&plugin_path) {
-            let is_current = existing_content.trim() == PLUGIN_SOURCE.trim();
+            let is_current = existing_content.trim() == plugin_source().trim();
             Ok(PluginStatus {
                 installed: true,
                 path: plugin_path.to_string_lossy().to_string(),
@@ -180,7 +204,7 @@ pub fn install_plugin() -> Result<InstallResult, String> {
     let plugin_path = plugins_folder.join(PLUGIN_FILENAME);
 
     // Write the plugin file
-    fs::write(&plugin_path, PLUGIN_SOURCE)
+    fs::write(&plugin_path, plugin_source())
         .map_err(|e| format!("Failed to write plugin file: {}", e))?;
 
     Ok(InstallResult {
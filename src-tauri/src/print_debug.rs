@@ -0,0 +1,292 @@
+//! Print-Debug Injection
+//!
+//! Automates the print-debugging loop: tagged `print` calls are inserted at the
+//! top of chosen functions, their output is collected from the playtest server
+//! log, and afterwards every injected line is removed again. Injected lines end
+//! with a marker comment, so cleanup still works if the user edited the script
+//! in the meantime.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::bridge::{self, chrono_lite_timestamp};
+
+const MAX_OUTPUT_LINES: usize = 2000;
+
+lazy_static::lazy_static! {
+    static ref INJECTIONS: Mutex<HashMap<String, Injection>> = Mutex::new(HashMap::new());
+    static ref OUTPUT: Mutex<Vec<DebugOutputLine>> = Mutex::new(Vec::new());
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Injection {
+    pub id: String,
+    pub script: String,
+    pub functions: Vec<String>,
+    pub lines_added: usize,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugOutputLine {
+    pub injection_id: String,
+    pub t: u64,
+    pub message: String,
+}
+
+/// Log lines posted by the plugin to /stud/logs
+#[derive(Debug, Deserialize)]
+pub struct LogBatch {
+    pub lines: Vec<LogLine>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogLine {
+    pub t: u64,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct ScriptSource {
+    source: String,
+}
+
+fn marker(id: &str) -> String {
+    format!("-- stud-debug:{}", id)
+}
+
+fn print_tag(id: &str) -> String {
+    format!("[StudDebug:{}]", id)
+}
+
+/// If `line` defines function `name`, return its parameter names.
+/// Matches `function name(`, `local function name(`, `function Mod.name(`,
+/// `function Mod:name(` and `name = function(`.
+fn match_function(line: &str, name: &str) -> Option<Vec<String>> {
+    let trimmed = line.trim_start();
+    let (header, params) = trimmed.split_once('(')?;
+    let header = header.trim_end();
+
+    let defined = if let Some(rest) = header
+        .strip_prefix("local function ")
+        .or_else(|| header.strip_prefix("function "))
+    {
+        let rest = rest.trim();
+        rest == name
+            || rest.ends_with(&format!(".{}", name))
+            || rest.ends_with(&format!(":{}", name))
+    } else if let Some((lhs, rhs)) = header.split_once('=') {
+        rhs.trim() == "function" && {
+            let lhs = lhs.trim().trim_start_matches("local ").trim();
+            lhs == name || lhs.ends_with(&format!(".{}", name))
+        }
+    } else {
+        false
+    };
+    if !defined {
+        return None;
+    }
+
+    let params = params.split_once(')').map(|(p, _)| p).unwrap_or(params);
+    Some(
+        params
+            .split(',')
+            .map(|p| p.split(':').next().unwrap_or_default().trim().to_string())
+            .filter(|p| !p.is_empty() && p != "...")
+            .collect(),
+    )
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Insert a tagged print after each matching function header
+fn inject(source: &str, functions: &[String], id: &str) -> Result<(String, usize), String> {
+    let mut output = Vec::new();
+    let mut found: Vec<&String> = Vec::new();
+
+    for line in source.lines() {
+        output.push(line.to_string());
+        let Some((name, params)) = functions
+            .iter()
+            .find_map(|name| match_function(line, name).map(|params| (name, params)))
+        else {
+            continue;
+        };
+
+        let mut args = format!("\"{} {}\"", print_tag(id), name);
+        for param in &params {
+            args.push_str(&format!(", \"{} =\", {}", param, param));
+        }
+        output.push(format!(
+            "{}\tprint({}) {}",
+            indentation(line),
+            args,
+            marker(id)
+        ));
+        found.push(name);
+    }
+
+    let missing: Vec<&str> = functions
+        .iter()
+        .filter(|name| !found.contains(name))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("Couldn't find function(s): {}", missing.join(", ")));
+    }
+
+    let mut injected = output.join("\n");
+    if source.ends_with('\n') {
+        injected.push('\n');
+    }
+    Ok((injected, found.len()))
+}
+
+/// Remove every line carrying the injection's marker
+fn strip(source: &str, id: &str) -> (String, usize) {
+    let marker = marker(id);
+    let mut removed = 0;
+    let mut kept: Vec<&str> = Vec::new();
+    for line in source.lines() {
+        if line.trim_end().ends_with(&marker) {
+            removed += 1;
+        } else {
+            kept.push(line);
+        }
+    }
+    let mut stripped = kept.join("\n");
+    if source.ends_with('\n') {
+        stripped.push('\n');
+    }
+    (stripped, removed)
+}
+
+async fn read_script(path: &str, session: Option<&str>) -> Result<String, String> {
+    let result =
+        bridge::studio_request(session, "/script/get", serde_json::json!({ "path": path })).await?;
+    let script: ScriptSource = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+    Ok(script.source)
+}
+
+async fn write_script(path: &str, source: String, session: Option<&str>) -> Result<(), String> {
+    bridge::studio_request(
+        session,
+        "/script/set",
+        serde_json::json!({ "path": path, "source": source }),
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Store tagged print output forwarded by the plugin during a playtest
+pub fn record_output(batch: LogBatch) -> usize {
+    let injections = INJECTIONS.lock();
+    let mut output = OUTPUT.lock();
+    let mut recorded = 0;
+    for line in batch.lines {
+        let Some(id) = line
+            .message
+            .split_once("[StudDebug:")
+            .and_then(|(_, rest)| rest.split_once(']'))
+            .map(|(id, _)| id.to_string())
+        else {
+            continue;
+        };
+        if !injections.contains_key(&id) {
+            // Stale prints left over from an injection that was already removed
+            continue;
+        }
+        if output.len() >= MAX_OUTPUT_LINES {
+            output.remove(0);
+        }
+        output.push(DebugOutputLine {
+            injection_id: id,
+            t: line.t,
+            message: line.message,
+        });
+        recorded += 1;
+    }
+    recorded
+}
+
+/// Insert tagged debug prints (with argument values) at the start of the given functions
+#[tauri::command]
+pub async fn inject_debug_prints(
+    script: String,
+    functions: Vec<String>,
+    session: Option<String>,
+) -> Result<Injection, String> {
+    if functions.is_empty() {
+        return Err("Name at least one function to instrument".to_string());
+    }
+
+    let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+    let source = read_script(&script, session.as_deref()).await?;
+    let (injected, lines_added) = inject(&source, &functions, &id)?;
+    write_script(&script, injected, session.as_deref()).await?;
+
+    let injection = Injection {
+        id: id.clone(),
+        script,
+        functions,
+        lines_added,
+        created_at: chrono_lite_timestamp(),
+    };
+    INJECTIONS.lock().insert(id, injection.clone());
+    Ok(injection)
+}
+
+#[tauri::command]
+pub fn list_debug_injections() -> Vec<Injection> {
+    let mut injections: Vec<Injection> = INJECTIONS.lock().values().cloned().collect();
+    injections.sort_by_key(|injection| injection.created_at);
+    injections
+}
+
+/// Output collected from injected prints, optionally for one injection
+#[tauri::command]
+pub fn get_debug_output(id: Option<String>) -> Vec<DebugOutputLine> {
+    OUTPUT
+        .lock()
+        .iter()
+        .filter(|line| id.as_ref().is_none_or(|id| &line.injection_id == id))
+        .cloned()
+        .collect()
+}
+
+/// Remove injected prints (one injection, or all when `id` is None).
+/// Returns the number of lines removed.
+#[tauri::command]
+pub async fn remove_debug_prints(
+    id: Option<String>,
+    session: Option<String>,
+) -> Result<usize, String> {
+    let targets: Vec<Injection> = {
+        let injections = INJECTIONS.lock();
+        match &id {
+            Some(id) => vec![injections
+                .get(id)
+                .cloned()
+                .ok_or_else(|| format!("Injection not found: {}", id))?],
+            None => injections.values().cloned().collect(),
+        }
+    };
+
+    let mut removed = 0;
+    for injection in targets {
+        let source = read_script(&injection.script, session.as_deref()).await?;
+        let (stripped, count) = strip(&source, &injection.id);
+        if count > 0 {
+            write_script(&injection.script, stripped, session.as_deref()).await?;
+        }
+        removed += count;
+
+        // Collected output stays available after cleanup
+        INJECTIONS.lock().remove(&injection.id);
+    }
+    Ok(removed)
+}
@@ -0,0 +1,523 @@
+//! Procedural Generation Jobs
+//!
+//! Large generative tasks (mazes, terrain, town layouts) are computed here from
+//! a seed, so the same spec always produces the same result, then streamed to
+//! the plugin in batches. The model only has to describe what it wants instead
+//! of emitting thousands of instances as text.
+//!
+//! Jobs run in the background, report progress through `procgen-progress`
+//! events, and can be cancelled between batches.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
+use crate::bridge::{self, chrono_lite_timestamp};
+
+const BATCH_SIZE: usize = 200;
+const MAX_PARTS: usize = 20_000;
+const PROGRESS_EVENT: &str = "procgen-progress";
+
+lazy_static::lazy_static! {
+    static ref JOBS: Mutex<HashMap<String, JobEntry>> = Mutex::new(HashMap::new());
+}
+
+struct JobEntry {
+    info: JobInfo,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// SplitMix64 - tiny, fast, and stable across versions so seeds stay reproducible
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform float in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Generator {
+    Maze {
+        width: usize,
+        height: usize,
+        #[serde(default = "default_cell_size")]
+        cell_size: f64,
+        #[serde(default = "default_wall_height")]
+        wall_height: f64,
+    },
+    Terrain {
+        width: usize,
+        depth: usize,
+        #[serde(default = "default_cell_size")]
+        cell_size: f64,
+        #[serde(default = "default_max_height")]
+        max_height: f64,
+        /// Distance between random control points; larger means smoother hills
+        #[serde(default = "default_feature_size")]
+        feature_size: usize,
+    },
+    Town {
+        blocks_x: usize,
+        blocks_z: usize,
+        #[serde(default = "default_block_size")]
+        block_size: f64,
+        #[serde(default = "default_road_width")]
+        road_width: f64,
+        #[serde(default = "default_max_height")]
+        max_building_height: f64,
+    },
+}
+
+fn default_cell_size() -> f64 {
+    8.0
+}
+fn default_wall_height() -> f64 {
+    10.0
+}
+fn default_max_height() -> f64 {
+    40.0
+}
+fn default_feature_size() -> usize {
+    6
+}
+fn default_block_size() -> f64 {
+    60.0
+}
+fn default_road_width() -> f64 {
+    16.0
+}
+fn default_parent() -> String {
+    "game.Workspace".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenerationSpec {
+    #[serde(flatten)]
+    pub generator: Generator,
+    pub seed: u64,
+    /// Folder created under `parent` to hold the generated parts
+    pub name: String,
+    #[serde(default = "default_parent")]
+    pub parent: String,
+    #[serde(default)]
+    pub origin: [f64; 3],
+}
+
+/// A part to create in Studio
+#[derive(Debug, Clone, Serialize)]
+pub struct PartSpec {
+    pub name: String,
+    pub size: [f64; 3],
+    pub position: [f64; 3],
+    pub color: [u8; 3],
+    pub material: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub name: String,
+    pub seed: u64,
+    pub status: JobStatus,
+    pub total_parts: usize,
+    pub created_parts: usize,
+    pub error: Option<String>,
+    pub started_at: u64,
+}
+
+fn part(name: String, size: [f64; 3], position: [f64; 3], color: [u8; 3], material: &'static str) -> PartSpec {
+    PartSpec {
+        name,
+        size,
+        position,
+        color,
+        material,
+    }
+}
+
+/// Recursive-backtracker maze; walls are emitted as thin parts between cells
+fn generate_maze(rng: &mut Rng, width: usize, height: usize, cell: f64, wall_height: f64) -> Vec<PartSpec> {
+    // walls_h[y][x]: wall on the north side of cell (x, y); walls_v[y][x]: wall on the west side
+    let mut walls_h = vec![vec![true; width]; height + 1];
+    let mut walls_v = vec![vec![true; width + 1]; height];
+    let mut visited = vec![vec![false; width]; height];
+    let mut stack = vec![(0usize, 0usize)];
+    visited[0][0] = true;
+
+    while let Some(&(x, y)) = stack.last() {
+        let mut neighbors = Vec::with_capacity(4);
+        if y > 0 && !visited[y - 1][x] {
+            neighbors.push((x, y - 1));
+        }
+        if y + 1 < height && !visited[y + 1][x] {
+            neighbors.push((x, y + 1));
+        }
+        if x > 0 && !visited[y][x - 1] {
+            neighbors.push((x - 1, y));
+        }
+        if x + 1 < width && !visited[y][x + 1] {
+            neighbors.push((x + 1, y));
+        }
+
+        if neighbors.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let (nx, ny) = neighbors[rng.below(neighbors.len())];
+        if nx == x {
+            walls_h[y.max(ny)][x] = false;
+        } else {
+            walls_v[y][x.max(nx)] = false;
+        }
+        visited[ny][nx] = true;
+        stack.push((nx, ny));
+    }
+
+    // Openings at the entrance and exit
+    walls_h[0][0] = false;
+    walls_h[height][width - 1] = false;
+
+    let thickness = 1.0;
+    let color = [163, 162, 165];
+    let mut parts = vec![part(
+        "Floor".to_string(),
+        [width as f64 * cell, 1.0, height as f64 * cell],
+        [width as f64 * cell / 2.0, -0.5, height as f64 * cell / 2.0],
+        [99, 95, 98],
+        "Slate",
+    )];
+
+    for (y, row) in walls_h.iter().enumerate() {
+        for (x, &wall) in row.iter().enumerate() {
+            if wall {
+                parts.push(part(
+                    format!("WallH_{}_{}", x, y),
+                    [cell + thickness, wall_height, thickness],
+                    [(x as f64 + 0.5) * cell, wall_height / 2.0, y as f64 * cell],
+                    color,
+                    "Brick",
+                ));
+            }
+        }
+    }
+    for (y, row) in walls_v.iter().enumerate() {
+        for (x, &wall) in row.iter().enumerate() {
+            if wall {
+                parts.push(part(
+                    format!("WallV_{}_{}", x, y),
+                    [thickness, wall_height, cell + thickness],
+                    [x as f64 * cell, wall_height / 2.0, (y as f64 + 0.5) * cell],
+                    color,
+                    "Brick",
+                ));
+            }
+        }
+    }
+
+    parts
+}
+
+/// Heightmap from bilinearly interpolated value noise, one column part per cell
+fn generate_terrain(
+    rng: &mut Rng,
+    width: usize,
+    depth: usize,
+    cell: f64,
+    max_height: f64,
+    feature: usize,
+) -> Vec<PartSpec> {
+    let feature = feature.max(1);
+    let grid_w = width / feature + 2;
+    let grid_d = depth / feature + 2;
+    let control: Vec<Vec<f64>> = (0..grid_d)
+        .map(|_| (0..grid_w).map(|_| rng.next_f64()).collect())
+        .collect();
+
+    let mut parts = Vec::with_capacity(width * depth);
+    for z in 0..depth {
+        for x in 0..width {
+            let (gx, gz) = (x / feature, z / feature);
+            let tx = (x % feature) as f64 / feature as f64;
+            let tz = (z % feature) as f64 / feature as f64;
+            let top = control[gz][gx] * (1.0 - tx) + control[gz][gx + 1] * tx;
+            let bottom = control[gz + 1][gx] * (1.0 - tx) + control[gz + 1][gx + 1] * tx;
+            let h = top * (1.0 - tz) + bottom * tz;
+
+            let height = (h * max_height).max(1.0);
+            let (color, material) = match h {
+                h if h < 0.25 => ([194, 178, 128], "Sand"),
+                h if h < 0.65 => ([75, 151, 75], "Grass"),
+                h if h < 0.85 => ([110, 110, 110], "Rock"),
+                _ => ([240, 240, 240], "Snow"),
+            };
+
+            parts.push(part(
+                format!("Tile_{}_{}", x, z),
+                [cell, height, cell],
+                [(x as f64 + 0.5) * cell, height / 2.0, (z as f64 + 0.5) * cell],
+                color,
+                material,
+            ));
+        }
+    }
+    parts
+}
+
+/// Grid of city blocks separated by roads, each block split into building lots
+fn generate_town(
+    rng: &mut Rng,
+    blocks_x: usize,
+    blocks_z: usize,
+    block: f64,
+    road: f64,
+    max_height: f64,
+) -> Vec<PartSpec> {
+    let pitch = block + road;
+    let total_x = blocks_x as f64 * pitch + road;
+    let total_z = blocks_z as f64 * pitch + road;
+    let mut parts = vec![part(
+        "Ground".to_string(),
+        [total_x, 1.0, total_z],
+        [total_x / 2.0, -0.5, total_z / 2.0],
+        [40, 40, 40],
+        "Asphalt",
+    )];
+
+    let palette: [[u8; 3]; 6] = [
+        [196, 40, 28],
+        [13, 105, 172],
+        [245, 205, 48],
+        [161, 165, 162],
+        [124, 92, 70],
+        [218, 133, 65],
+    ];
+
+    for bz in 0..blocks_z {
+        for bx in 0..blocks_x {
+            let x0 = road + bx as f64 * pitch;
+            let z0 = road + bz as f64 * pitch;
+            parts.push(part(
+                format!("Sidewalk_{}_{}", bx, bz),
+                [block, 0.6, block],
+                [x0 + block / 2.0, 0.3, z0 + block / 2.0],
+                [200, 200, 200],
+                "Concrete",
+            ));
+
+            // 2x2 lots per block, each with a building of random footprint and height
+            let lot = block / 2.0;
+            for lz in 0..2 {
+                for lx in 0..2 {
+                    if rng.next_f64() < 0.15 {
+                        continue; // Leave the occasional empty lot
+                    }
+                    let w = rng.range(lot * 0.5, lot * 0.85);
+                    let d = rng.range(lot * 0.5, lot * 0.85);
+                    let h = rng.range(max_height * 0.25, max_height);
+                    let cx = x0 + lot * (lx as f64 + 0.5);
+                    let cz = z0 + lot * (lz as f64 + 0.5);
+                    parts.push(part(
+                        format!("Building_{}_{}_{}", bx, bz, lz * 2 + lx),
+                        [w, h, d],
+                        [cx, 0.6 + h / 2.0, cz],
+                        palette[rng.below(palette.len())],
+                        "SmoothPlastic",
+                    ));
+                }
+            }
+        }
+    }
+    parts
+}
+
+/// Deterministically compute the parts for a spec
+pub fn generate(spec: &GenerationSpec) -> Result<Vec<PartSpec>, String> {
+    let mut rng = Rng(spec.seed);
+    let mut parts = match spec.generator {
+        Generator::Maze {
+            width,
+            height,
+            cell_size,
+            wall_height,
+        } => {
+            if width == 0 || height == 0 {
+                return Err("Maze must be at least 1x1".to_string());
+            }
+            generate_maze(&mut rng, width, height, cell_size, wall_height)
+        }
+        Generator::Terrain {
+            width,
+            depth,
+            cell_size,
+            max_height,
+            feature_size,
+        } => generate_terrain(&mut rng, width, depth, cell_size, max_height, feature_size),
+        Generator::Town {
+            blocks_x,
+            blocks_z,
+            block_size,
+            road_width,
+            max_building_height,
+        } => generate_town(
+            &mut rng,
+            blocks_x,
+            blocks_z,
+            block_size,
+            road_width,
+            max_building_height,
+        ),
+    };
+
+    if parts.len() > MAX_PARTS {
+        return Err(format!(
+            "This would create {} parts (limit {}). Use a smaller size.",
+            parts.len(),
+            MAX_PARTS
+        ));
+    }
+
+    for part in &mut parts {
+        for (axis, offset) in part.position.iter_mut().zip(spec.origin) {
+            *axis += offset;
+        }
+    }
+    Ok(parts)
+}
+
+fn update_job(id: &str, update: impl FnOnce(&mut JobInfo)) -> Option<JobInfo> {
+    let mut jobs = JOBS.lock();
+    let entry = jobs.get_mut(id)?;
+    update(&mut entry.info);
+    Some(entry.info.clone())
+}
+
+async fn run_job(
+    app: AppHandle,
+    id: String,
+    spec: GenerationSpec,
+    parts: Vec<PartSpec>,
+    cancelled: Arc<AtomicBool>,
+    session: Option<String>,
+) {
+    for batch in parts.chunks(BATCH_SIZE) {
+        if cancelled.load(Ordering::SeqCst) {
+            if let Some(info) = update_job(&id, |info| info.status = JobStatus::Cancelled) {
+                let _ = app.emit(PROGRESS_EVENT, info);
+            }
+            return;
+        }
+
+        let result = bridge::studio_request(
+            session.as_deref(),
+            "/procgen/batch",
+            serde_json::json!({
+                "parent": spec.parent,
+                "folder": spec.name,
+                "parts": batch,
+            }),
+        )
+        .await;
+
+        let info = update_job(&id, |info| match &result {
+            Ok(_) => info.created_parts += batch.len(),
+            Err(e) => {
+                info.status = JobStatus::Failed;
+                info.error = Some(e.clone());
+            }
+        });
+        if let Some(info) = info {
+            let _ = app.emit(PROGRESS_EVENT, info);
+        }
+        if result.is_err() {
+            return;
+        }
+    }
+
+    if let Some(info) = update_job(&id, |info| info.status = JobStatus::Completed) {
+        let _ = app.emit(PROGRESS_EVENT, info);
+    }
+}
+
+/// Generate a structure from a seed and stream it into Studio in the background
+#[tauri::command]
+pub fn start_procgen_job(
+    app: AppHandle,
+    spec: GenerationSpec,
+    session: Option<String>,
+) -> Result<JobInfo, String> {
+    let parts = generate(&spec)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let info = JobInfo {
+        id: id.clone(),
+        name: spec.name.clone(),
+        seed: spec.seed,
+        status: JobStatus::Running,
+        total_parts: parts.len(),
+        created_parts: 0,
+        error: None,
+        started_at: chrono_lite_timestamp(),
+    };
+
+    JOBS.lock().insert(
+        id.clone(),
+        JobEntry {
+            info: info.clone(),
+            cancelled: cancelled.clone(),
+        },
+    );
+
+    tauri::async_runtime::spawn(run_job(app, id, spec, parts, cancelled, session));
+    Ok(info)
+}
+
+/// Stop a running job after the batch currently in flight
+#[tauri::command]
+pub fn cancel_procgen_job(id: String) -> Result<(), String> {
+    let jobs = JOBS.lock();
+    let entry = jobs
+        .get(&id)
+        .ok_or_else(|| format!("Job not found: {}", id))?;
+    entry.cancelled.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// List generation jobs started this session
+#[tauri::command]
+pub fn list_procgen_jobs() -> Vec<JobInfo> {
+    let mut jobs: Vec<JobInfo> = JOBS.lock().values().map(|entry| entry.info.clone()).collect();
+    jobs.sort_by_key(|info| info.started_at);
+    jobs
+}
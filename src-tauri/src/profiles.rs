@@ -0,0 +1,143 @@
+//! Place Profiles
+//!
+//! Preferences remembered per Roblox place, keyed by the placeId the plugin
+//! reports when it connects. When a session for a known place connects, its
+//! profile is sent to the UI, and a read-only profile makes the bridge refuse
+//! anything that would change the place, so reconnecting to a production
+//! place comes up in its safer configuration without the user remembering to
+//! switch.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::paths;
+
+const PROFILES_FILENAME: &str = "place_profiles.json";
+
+/// Requests that change the place, refused for read-only places. Mirrors the
+/// plugin's `modifyingPaths`.
+const MODIFYING_PATHS: &[&str] = &[
+    "/script/set",
+    "/script/edit",
+    "/instance/set",
+    "/instance/create",
+    "/instance/delete",
+    "/instance/clone",
+    "/instance/move",
+    "/instance/bulk-create",
+    "/instance/bulk-delete",
+    "/instance/bulk-set",
+    "/code/run",
+    "/asset/insert",
+    "/template/insert",
+    "/procgen/batch",
+    "/palette/apply",
+    "/scaffold/apply",
+    "/tags/apply",
+    "/attributes/set",
+    "/scripts/batch-set",
+];
+
+lazy_static::lazy_static! {
+    static ref PROFILES: RwLock<HashMap<u64, PlaceProfile>> = RwLock::new(load_profiles());
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelChoice {
+    pub model: String,
+    /// "openai", "anthropic" or "codex"
+    pub provider: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaceProfile {
+    pub place_id: u64,
+    /// Last name the place was seen with, for display
+    #[serde(default)]
+    pub place_name: Option<String>,
+    /// Model to switch to when this place connects
+    #[serde(default)]
+    pub default_model: Option<ModelChoice>,
+    /// Refuse every request that would change the place
+    #[serde(default)]
+    pub read_only: bool,
+    /// Tools the AI isn't offered while this place is connected
+    #[serde(default)]
+    pub disabled_tools: Vec<String>,
+    /// Context providers attached to every message, e.g. "selection" or "module_api"
+    #[serde(default)]
+    pub auto_context: Vec<String>,
+}
+
+fn load_profiles() -> HashMap<u64, PlaceProfile> {
+    paths::app_data_dir()
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(PROFILES_FILENAME)).ok())
+        .and_then(|source| serde_json::from_str::<Vec<PlaceProfile>>(&source).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|profile| (profile.place_id, profile))
+        .collect()
+}
+
+fn save_profiles(profiles: &HashMap<u64, PlaceProfile>) -> Result<(), String> {
+    let mut list: Vec<&PlaceProfile> = profiles.values().collect();
+    list.sort_by_key(|profile| profile.place_id);
+    let json = serde_json::to_string_pretty(&list)
+        .map_err(|e| format!("Failed to serialize place profiles: {}", e))?;
+    fs::write(paths::app_data_dir()?.join(PROFILES_FILENAME), json)
+        .map_err(|e| format!("Failed to write place profiles: {}", e))
+}
+
+/// The saved profile for a place, if any
+pub fn for_place(place_id: u64) -> Option<PlaceProfile> {
+    PROFILES.read().get(&place_id).cloned()
+}
+
+/// Whether requests to this bridge path change the place
+pub(crate) fn is_modifying(path: &str) -> bool {
+    MODIFYING_PATHS.contains(&path)
+}
+
+/// Whether a request to this path would be refused for the place
+pub fn blocks(place_id: u64, path: &str) -> bool {
+    is_modifying(path) && PROFILES.read().get(&place_id).is_some_and(|p| p.read_only)
+}
+
+/// Every saved place profile
+#[tauri::command]
+pub fn list_place_profiles() -> Vec<PlaceProfile> {
+    let mut profiles: Vec<PlaceProfile> = PROFILES.read().values().cloned().collect();
+    profiles.sort_by_key(|profile| profile.place_id);
+    profiles
+}
+
+/// The saved profile for a place, if any
+#[tauri::command]
+pub fn get_place_profile(place_id: u64) -> Option<PlaceProfile> {
+    for_place(place_id)
+}
+
+/// Create or replace the profile for a place
+#[tauri::command]
+pub fn save_place_profile(profile: PlaceProfile) -> Result<(), String> {
+    if profile.place_id == 0 {
+        return Err("Unpublished places (placeId 0) can't have a profile".to_string());
+    }
+    let mut profiles = PROFILES.write();
+    profiles.insert(profile.place_id, profile);
+    save_profiles(&profiles)
+}
+
+/// Forget a place's profile; returns whether one existed
+#[tauri::command]
+pub fn delete_place_profile(place_id: u64) -> Result<bool, String> {
+    let mut profiles = PROFILES.write();
+    let existed = profiles.remove(&place_id).is_some();
+    if existed {
+        save_profiles(&profiles)?;
+    }
+    Ok(existed)
+}
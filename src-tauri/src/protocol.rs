@@ -0,0 +1,154 @@
+//! Bridge Wire Protocol
+//!
+//! Requests and responses as they cross the wire to the plugin, one struct per
+//! protocol version. The bridge works with `StudioRequest` and `StudioResponse`
+//! internally and converts at the edge: each session is sent requests in the
+//! version it negotiated through `/stud/v2/hello`, and responses are read in
+//! whichever version the plugin wrote them in. Plugins installed before a field
+//! was added keep working while newer ones roll out.
+//!
+//! A released version's structs are frozen; new fields go into a new version.
+
+use serde::{Deserialize, Serialize};
+
+use crate::bridge::{Attachment, Priority, StudioRequest, StudioResponse};
+
+/// Bridge protocol versions, oldest first. v1 is served at /stud/*, later versions at /stud/vN/*
+pub(crate) const PROTOCOL_VERSIONS: [u32; 2] = [1, 2];
+pub(crate) const CURRENT_PROTOCOL: u32 = 2;
+
+/// A request as plugins from before negotiation read it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestV1 {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub path: String,
+    pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<Attachment>,
+}
+
+/// v2 names its version and adds routing, tracing and sandbox fields
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestV2 {
+    pub protocol_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub path: String,
+    pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_session: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<Attachment>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_paths: Option<Vec<String>>,
+}
+
+/// A request in the shape a session's protocol version expects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WireRequest {
+    V2(RequestV2),
+    V1(RequestV1),
+}
+
+impl WireRequest {
+    pub fn new(request: &StudioRequest, protocol_version: u32) -> Self {
+        if protocol_version <= 1 {
+            return WireRequest::V1(RequestV1 {
+                id: request.id.clone(),
+                path: request.path.clone(),
+                body: request.body.clone(),
+                attachment: request.attachment.clone(),
+            });
+        }
+        WireRequest::V2(RequestV2 {
+            protocol_version: protocol_version.min(CURRENT_PROTOCOL),
+            id: request.id.clone(),
+            path: request.path.clone(),
+            body: request.body.clone(),
+            target_session: request.target_session.clone(),
+            attachment: request.attachment.clone(),
+            priority: request.priority,
+            tool_call_id: request.tool_call_id.clone(),
+            chat_id: request.chat_id.clone(),
+            turn_id: request.turn_id.clone(),
+            trace_id: request.trace_id.clone(),
+            allowed_paths: request.allowed_paths.clone(),
+        })
+    }
+}
+
+/// A response from a plugin that doesn't name its version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseV1 {
+    pub status: u16,
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseV2 {
+    pub protocol_version: u32,
+    pub status: u16,
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+/// A response in whichever version the plugin wrote it; responses naming a
+/// version are tried first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WireResponse {
+    V2(ResponseV2),
+    V1(ResponseV1),
+}
+
+impl WireResponse {
+    /// The response in the bridge's own terms, or why it can't be read
+    pub fn into_response(self) -> Result<StudioResponse, String> {
+        match self {
+            WireResponse::V1(response) => Ok(StudioResponse {
+                status: response.status,
+                body: response.body,
+                content_type: response.content_type,
+            }),
+            WireResponse::V2(response)
+                if PROTOCOL_VERSIONS.contains(&response.protocol_version) =>
+            {
+                Ok(StudioResponse {
+                    status: response.status,
+                    body: response.body,
+                    content_type: response.content_type,
+                })
+            }
+            WireResponse::V2(response) => Err(format!(
+                "Plugin answered in protocol v{}, bridge speaks {:?}",
+                response.protocol_version, PROTOCOL_VERSIONS
+            )),
+        }
+    }
+}
+
+impl From<StudioResponse> for WireResponse {
+    fn from(response: StudioResponse) -> Self {
+        WireResponse::V2(ResponseV2 {
+            protocol_version: CURRENT_PROTOCOL,
+            status: response.status,
+            body: response.body,
+            content_type: response.content_type,
+        })
+    }
+}
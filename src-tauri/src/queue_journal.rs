@@ -0,0 +1,210 @@
+//! Pending Queue Journal
+//!
+//! Opt-in (`bridge.persist_queue`) record of the requests waiting for Studio,
+//! so a tool chain cut off by an app restart isn't silently lost. Each queued
+//! request is written to a small SQLite database and removed once it's
+//! answered, cancelled or timed out. On the next launch whatever is left is
+//! queued again once the plugin reconnects, and because the original caller
+//! is gone, each outcome is sent to the UI as a `bridge-request-recovered`
+//! event.
+
+use parking_lot::Mutex;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::bridge::{self, chrono_lite_timestamp, StudioRequest};
+use crate::config;
+use crate::paths;
+
+const DB_FILENAME: &str = "queue_journal.db";
+const RECOVERED_EVENT: &str = "bridge-request-recovered";
+/// Requests older than this are dropped at startup rather than replayed
+const MAX_REPLAY_AGE_SECS: u64 = 60 * 60;
+const CONNECTION_WAIT_MS: u64 = 500;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS pending_requests (
+    id TEXT PRIMARY KEY,
+    request TEXT NOT NULL,
+    queued_at INTEGER NOT NULL
+);
+";
+
+lazy_static::lazy_static! {
+    // Opened on first use so the bridge still runs if the data dir is unavailable
+    static ref JOURNAL: Mutex<Option<Connection>> = Mutex::new(None);
+    // Entries queued from here on belong to this run and aren't replayed by `recover`
+    static ref RUN_STARTED_AT: u64 = chrono_lite_timestamp();
+}
+
+/// Outcome of a request replayed from the journal, for the recovered event
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveredRequest {
+    pub id: String,
+    pub path: String,
+    pub chat_id: Option<String>,
+    pub tool_call_id: Option<String>,
+    /// Unix ms when the request was first queued, before the restart
+    pub queued_at: u64,
+    pub status: Option<u16>,
+    pub body: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Whether queued requests are journaled
+pub fn enabled() -> bool {
+    config::current().bridge.persist_queue
+}
+
+fn open_db() -> Result<Connection, String> {
+    let path = paths::app_data_dir()?.join(DB_FILENAME);
+    let conn =
+        Connection::open(&path).map_err(|e| format!("Failed to open queue journal: {}", e))?;
+    conn.execute_batch(SCHEMA)
+        .map_err(|e| format!("Failed to initialize queue journal: {}", e))?;
+    Ok(conn)
+}
+
+fn with_db<T>(f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+    let mut journal = JOURNAL.lock();
+    if journal.is_none() {
+        *journal = Some(open_db()?);
+    }
+    let conn = journal.as_ref().expect("queue journal was just opened");
+    f(conn).map_err(|e| format!("Queue journal error: {}", e))
+}
+
+/// Journal a request that was just queued
+pub(crate) fn record(id: &str, request: &StudioRequest) {
+    let json = match serde_json::to_string(request) {
+        Ok(json) => json,
+        Err(e) => {
+            println!("[Stud Journal] Failed to serialize request {}: {}", id, e);
+            return;
+        }
+    };
+    let queued_at = chrono_lite_timestamp().max(*RUN_STARTED_AT);
+    let result = with_db(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO pending_requests (id, request, queued_at) VALUES (?1, ?2, ?3)",
+            params![id, json, queued_at as i64],
+        )
+    });
+    if let Err(e) = result {
+        println!("[Stud Journal] {}", e);
+    }
+}
+
+/// Forget a request that no longer needs delivering
+pub(crate) fn remove(id: &str) {
+    if let Err(e) = with_db(|conn| conn.execute("DELETE FROM pending_requests WHERE id = ?1", [id]))
+    {
+        println!("[Stud Journal] {}", e);
+    }
+}
+
+/// Requests left by earlier runs that are young enough to replay, oldest
+/// first. Older ones are deleted.
+fn load() -> Result<Vec<(StudioRequest, u64)>, String> {
+    let run_started_at = *RUN_STARTED_AT;
+    let cutoff = run_started_at.saturating_sub(MAX_REPLAY_AGE_SECS * 1000);
+    let rows = with_db(|conn| {
+        conn.execute(
+            "DELETE FROM pending_requests WHERE queued_at < ?1",
+            [cutoff as i64],
+        )?;
+        let mut statement = conn.prepare(
+            "SELECT id, request, queued_at FROM pending_requests WHERE queued_at < ?1 ORDER BY queued_at",
+        )?;
+        let rows = statement
+            .query_map([run_started_at as i64], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    })?;
+
+    let mut requests = Vec::new();
+    for (id, json, queued_at) in rows {
+        match serde_json::from_str::<StudioRequest>(&json) {
+            Ok(mut request) => {
+                request.id = Some(id);
+                requests.push((request, queued_at as u64));
+            }
+            Err(e) => {
+                println!("[Stud Journal] Dropping unreadable request {}: {}", id, e);
+                remove(&id);
+            }
+        }
+    }
+    Ok(requests)
+}
+
+/// Queue whatever the last run left in the journal once the plugin reconnects,
+/// reporting each outcome to the UI. Runs until everything is resolved.
+pub async fn recover() {
+    if !enabled() {
+        return;
+    }
+    let requests = match load() {
+        Ok(requests) => requests,
+        Err(e) => {
+            println!("[Stud Journal] {}", e);
+            return;
+        }
+    };
+    if requests.is_empty() {
+        return;
+    }
+    println!(
+        "[Stud Journal] {} request(s) from the last run will be sent when Studio connects",
+        requests.len()
+    );
+    while !bridge::is_studio_connected() {
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(CONNECTION_WAIT_MS)) => {}
+            _ = bridge::shutdown_signal() => return,
+        }
+    }
+
+    let replays = requests.into_iter().map(|(request, queued_at)| async move {
+        let recovered = RecoveredRequest {
+            id: request.id.clone().unwrap_or_default(),
+            path: request.path.clone(),
+            chat_id: request.chat_id.clone(),
+            tool_call_id: request.tool_call_id.clone(),
+            queued_at,
+            status: None,
+            body: None,
+            error: None,
+        };
+        let recovered = match bridge::redeliver(request).await {
+            Ok(response) => RecoveredRequest {
+                status: Some(response.status),
+                body: Some(response.body),
+                ..recovered
+            },
+            Err(message) => RecoveredRequest {
+                error: Some(message.to_string()),
+                ..recovered
+            },
+        };
+        println!(
+            "[Stud Journal] Recovered {} {}: {}",
+            recovered.id,
+            recovered.path,
+            recovered
+                .status
+                .map(|status| status.to_string())
+                .or_else(|| recovered.error.clone())
+                .unwrap_or_default()
+        );
+        bridge::emit_event(RECOVERED_EVENT, recovered);
+    });
+    futures_util::future::join_all(replays).await;
+}
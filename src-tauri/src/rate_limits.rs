@@ -0,0 +1,248 @@
+//! Provider Rate Limits
+//!
+//! The latest rate-limit headers each upstream sent back through the provider
+//! proxy, so the UI can warn before a big request gets throttled. OpenAI and
+//! OpenRouter send `x-ratelimit-*`, Anthropic `anthropic-ratelimit-*`:
+//!
+//! - `x-ratelimit-remaining-requests`, `x-ratelimit-reset-tokens: 6m0s`
+//! - `x-ratelimit-remaining` (OpenRouter, counting requests), reset in epoch ms
+//! - `anthropic-ratelimit-input-tokens-remaining`, reset as an RFC 3339 time
+//!
+//! Each is filed under a bucket ("requests", "tokens", "input-tokens", ...).
+
+use axum::http::HeaderMap;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::bridge::chrono_lite_timestamp;
+
+const PREFIXES: &[&str] = &["x-ratelimit-", "anthropic-ratelimit-"];
+/// A bucket counts as running low below this share of its limit
+const LOW_FRACTION: f64 = 0.1;
+
+lazy_static::lazy_static! {
+    static ref RATE_LIMITS: Mutex<BTreeMap<String, RateLimitStatus>> = Mutex::new(BTreeMap::new());
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RateLimit {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    /// As the provider sent it
+    pub reset: Option<String>,
+    /// When the bucket refills (ms since the epoch), if `reset` could be read
+    pub reset_at: Option<u64>,
+}
+
+impl RateLimit {
+    fn low(&self) -> bool {
+        match (self.limit, self.remaining) {
+            (Some(limit), Some(remaining)) if limit > 0 => {
+                (remaining as f64) < limit as f64 * LOW_FRACTION
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitStatus {
+    pub upstream: String,
+    /// When the headers were received, ms since the epoch
+    pub updated_at: u64,
+    pub limits: BTreeMap<String, RateLimit>,
+    /// Whether any bucket is nearly used up
+    pub low: bool,
+}
+
+/// Read an RFC 3339 time ("2024-05-01T12:00:30Z", "...T12:00:30.5+02:00")
+/// as ms since the epoch
+fn parse_rfc3339(value: &str) -> Option<u64> {
+    let (date, time) = value.split_once(['T', 't', ' '])?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let (clock, offset_secs) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else {
+        let at = time.rfind(['+', '-'])?;
+        let (hours, minutes) = time[at + 1..].split_once(':')?;
+        let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+        (
+            &time[..at],
+            if time[at..].starts_with('-') {
+                -offset
+            } else {
+                offset
+            },
+        )
+    };
+    let (clock, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+    let mut clock = clock.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (clock.next()??, clock.next()??, clock.next()??);
+    let millis = match fraction {
+        "" => 0,
+        digits => format!("{:0<3}", &digits[..digits.len().min(3)])
+            .parse::<i64>()
+            .ok()?,
+    };
+
+    // Days since 1970-01-01 for the proleptic Gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let secs = days * 86_400 + hour * 3600 + minute * 60 + second - offset_secs;
+    u64::try_from(secs * 1000 + millis).ok()
+}
+
+/// Read a reset as an RFC 3339 time, a delay ("6m0s", "1.5s", "20ms") or an
+/// epoch time in ms
+fn reset_at(value: &str, now: u64) -> Option<u64> {
+    if let Some(at) = parse_rfc3339(value) {
+        return Some(at);
+    }
+    if let Ok(epoch) = value.parse::<u64>() {
+        // Seconds or milliseconds, whichever makes it a plausible date
+        return Some(if epoch < 10_000_000_000 {
+            epoch * 1000
+        } else {
+            epoch
+        });
+    }
+    let mut total_ms = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .filter(|at| *at > 0)?;
+        let number: f64 = rest[..split].parse().ok()?;
+        rest = &rest[split..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        total_ms += number
+            * match &rest[..unit_len] {
+                "h" => 3_600_000.0,
+                "m" => 60_000.0,
+                "s" => 1000.0,
+                "ms" => 1.0,
+                _ => return None,
+            };
+        rest = &rest[unit_len..];
+    }
+    Some(now + total_ms as u64)
+}
+
+/// Keep the rate-limit headers of a response from `upstream`, if it sent any
+pub(crate) fn record(upstream: &str, headers: &HeaderMap) {
+    let now = chrono_lite_timestamp();
+    let mut limits: BTreeMap<String, RateLimit> = BTreeMap::new();
+    for (name, value) in headers {
+        let Some(rest) = PREFIXES
+            .iter()
+            .find_map(|prefix| name.as_str().strip_prefix(prefix))
+        else {
+            continue;
+        };
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        // The field comes first (OpenAI) or last (Anthropic); OpenRouter has no bucket
+        let Some((field, bucket)) = ["limit", "remaining", "reset"].iter().find_map(|field| {
+            if rest == *field {
+                Some((*field, "requests"))
+            } else {
+                rest.strip_prefix(&format!("{}-", field))
+                    .or_else(|| rest.strip_suffix(&format!("-{}", field)))
+                    .map(|bucket| (*field, bucket))
+            }
+        }) else {
+            continue;
+        };
+        let limit = limits.entry(bucket.to_string()).or_default();
+        match field {
+            "limit" => limit.limit = value.trim().parse().ok(),
+            "remaining" => limit.remaining = value.trim().parse().ok(),
+            _ => {
+                limit.reset = Some(value.to_string());
+                limit.reset_at = reset_at(value.trim(), now);
+            }
+        }
+    }
+    if limits.is_empty() {
+        return;
+    }
+    let low = limits.values().any(RateLimit::low);
+    RATE_LIMITS.lock().insert(
+        upstream.to_string(),
+        RateLimitStatus {
+            upstream: upstream.to_string(),
+            updated_at: now,
+            limits,
+            low,
+        },
+    );
+}
+
+/// The latest rate limits each provider reported
+#[tauri::command]
+pub fn get_rate_limit_status() -> Vec<RateLimitStatus> {
+    RATE_LIMITS.lock().values().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000_000;
+
+    #[test]
+    fn reset_at_rfc3339() {
+        assert_eq!(reset_at("1970-01-01T00:00:00Z", NOW), Some(0));
+        assert_eq!(
+            reset_at("2024-05-01T12:00:30Z", NOW),
+            Some(1_714_564_830_000)
+        );
+        assert_eq!(
+            reset_at("2024-05-01T12:00:30.25Z", NOW),
+            Some(1_714_564_830_250)
+        );
+        assert_eq!(
+            reset_at("2024-05-01T14:00:30+02:00", NOW),
+            Some(1_714_564_830_000)
+        );
+        assert_eq!(
+            reset_at("2024-02-29T00:00:00Z", NOW),
+            Some(1_709_164_800_000)
+        );
+    }
+
+    #[test]
+    fn reset_at_durations() {
+        assert_eq!(reset_at("6m0s", NOW), Some(NOW + 360_000));
+        assert_eq!(reset_at("1.5s", NOW), Some(NOW + 1500));
+        assert_eq!(reset_at("20ms", NOW), Some(NOW + 20));
+        assert_eq!(reset_at("1h2m3s", NOW), Some(NOW + 3_723_000));
+    }
+
+    #[test]
+    fn reset_at_epochs() {
+        assert_eq!(reset_at("1714564830", NOW), Some(1_714_564_830_000));
+        assert_eq!(reset_at("1714564830000", NOW), Some(1_714_564_830_000));
+    }
+
+    #[test]
+    fn reset_at_rejects_garbage() {
+        assert_eq!(reset_at("soon", NOW), None);
+        assert_eq!(reset_at("5x", NOW), None);
+        assert_eq!(reset_at("2024-13-01T00:00:00Z", NOW), None);
+    }
+}
@@ -0,0 +1,170 @@
+//! Response Cache
+//!
+//! A development aid for iterating on prompts: with `llm.cache.enabled` set,
+//! the provider proxy keeps successful responses in memory, keyed on a hash of
+//! the request's method, URL, body and credentials, and replays them chunk by chunk when
+//! the same request comes in again. Replays cost no tokens and come back at
+//! once, marked with `X-Stud-Cache: hit`. Off by default, since real chats
+//! want a fresh answer each time.
+
+use axum::body::Body;
+use axum::http::{header, HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+use bytes::Bytes;
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+
+use crate::bridge::chrono_lite_timestamp;
+use crate::config;
+
+/// Response header saying whether a response was replayed ("hit") or fetched ("miss")
+pub(crate) const CACHE_HEADER: &str = "x-stud-cache";
+/// Headers that say whose account a request is made with, so one account's
+/// responses aren't replayed to another
+const CREDENTIAL_HEADERS: &[&str] = &[
+    "authorization",
+    "x-api-key",
+    "x-goog-api-key",
+    "chatgpt-account-id",
+];
+/// Bigger responses aren't kept
+const MAX_RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+
+lazy_static::lazy_static! {
+    /// Oldest first
+    static ref CACHE: Mutex<IndexMap<String, CachedResponse>> = Mutex::new(IndexMap::new());
+}
+
+struct CachedResponse {
+    status: u16,
+    content_type: String,
+    /// As they arrived, so streams replay event by event
+    chunks: Vec<Bytes>,
+    stored_at: u64,
+}
+
+/// The cache key for a request, if caching is on and its body can be read
+pub(crate) fn key(req: &reqwest::RequestBuilder) -> Option<String> {
+    if !config::current().llm.cache.enabled {
+        return None;
+    }
+    let request = req.try_clone()?.build().ok()?;
+    let body = match request.body() {
+        Some(body) => body.as_bytes()?,
+        None => &[],
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(request.method().as_str());
+    hasher.update(b" ");
+    hasher.update(request.url().as_str());
+    hasher.update(b"\n");
+    hasher.update(body);
+    for name in CREDENTIAL_HEADERS {
+        for value in request.headers().get_all(*name) {
+            hasher.update(b"\n");
+            hasher.update(name);
+            hasher.update(b": ");
+            hasher.update(value.as_bytes());
+        }
+    }
+    Some(
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    )
+}
+
+/// Replay a stored response, if there's a fresh one for `key`
+pub(crate) fn replay(key: &str, generation: String) -> Option<Response> {
+    let ttl_ms = config::current().llm.cache.ttl_secs * 1000;
+    let mut cache = CACHE.lock();
+    let cached = cache.get(key)?;
+    if chrono_lite_timestamp().saturating_sub(cached.stored_at) > ttl_ms {
+        cache.shift_remove(key);
+        return None;
+    }
+    let chunks = cached.chunks.clone();
+    let status = StatusCode::from_u16(cached.status).unwrap_or(StatusCode::OK);
+    println!("[Stud Proxy] Replaying cached response {}", &key[..12]);
+    Some(
+        (
+            status,
+            [
+                (header::CONTENT_TYPE, cached.content_type.clone()),
+                (
+                    HeaderName::from_static(crate::llm_proxy::GENERATION_HEADER),
+                    generation,
+                ),
+                (HeaderName::from_static(CACHE_HEADER), "hit".to_string()),
+            ],
+            Body::from_stream(futures_util::stream::iter(
+                chunks.into_iter().map(Ok::<_, std::convert::Infallible>),
+            )),
+        )
+            .into_response(),
+    )
+}
+
+/// Collects a response as it streams past, to be stored once it's complete
+pub(crate) struct Recording {
+    key: String,
+    status: u16,
+    content_type: String,
+    chunks: Vec<Bytes>,
+    size: usize,
+}
+
+impl Recording {
+    pub fn new(key: String, status: u16, content_type: &str) -> Self {
+        Self {
+            key,
+            status,
+            content_type: content_type.to_string(),
+            chunks: Vec::new(),
+            size: 0,
+        }
+    }
+
+    pub fn push(&mut self, chunk: &Bytes) {
+        self.size += chunk.len();
+        if self.size <= MAX_RESPONSE_BYTES {
+            self.chunks.push(chunk.clone());
+        } else {
+            self.chunks.clear();
+        }
+    }
+
+    /// Store the response; only call this once the whole of it has arrived
+    pub fn finish(self) {
+        if self.size > MAX_RESPONSE_BYTES {
+            return;
+        }
+        let max_entries = config::current().llm.cache.max_entries;
+        let mut cache = CACHE.lock();
+        cache.shift_remove(&self.key);
+        cache.insert(
+            self.key,
+            CachedResponse {
+                status: self.status,
+                content_type: self.content_type,
+                chunks: self.chunks,
+                stored_at: chrono_lite_timestamp(),
+            },
+        );
+        while cache.len() > max_entries {
+            cache.shift_remove_index(0);
+        }
+    }
+}
+
+/// Drop every cached response; returns how many there were
+#[tauri::command]
+pub fn clear_response_cache() -> usize {
+    let mut cache = CACHE.lock();
+    let count = cache.len();
+    cache.clear();
+    count
+}
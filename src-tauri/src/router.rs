@@ -0,0 +1,182 @@
+//! Smart Model Routing
+//!
+//! Optionally picks a model per request from simple task heuristics: short
+//! fixes go to a fast/cheap model, large refactors, big contexts and images go
+//! to a large model. Every decision is kept in a small trace so the user can
+//! see why a model was chosen.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::bridge::chrono_lite_timestamp;
+use crate::config::{self, RoutingConfig};
+use crate::models::{self, RequestFeatures};
+
+const MAX_TRACE_ENTRIES: usize = 100;
+
+const REFACTOR_KEYWORDS: &[&str] = &[
+    "refactor",
+    "rewrite",
+    "restructure",
+    "reorganize",
+    "architecture",
+    "entire game",
+    "whole game",
+    "every script",
+    "all scripts",
+];
+
+lazy_static::lazy_static! {
+    static ref ROUTING_TRACE: Mutex<VecDeque<RoutingTraceEntry>> = Mutex::new(VecDeque::new());
+}
+
+/// What the frontend knows about the request before sending it
+#[derive(Debug, Deserialize)]
+pub struct TaskDescriptor {
+    #[serde(default)]
+    pub chat_id: Option<String>,
+    pub prompt: String,
+    pub selected_model: String,
+    #[serde(default)]
+    pub image_count: u32,
+    #[serde(default)]
+    pub context_tokens: u64,
+    #[serde(default)]
+    pub scripts_in_scope: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    QuickFix,
+    Refactor,
+    Vision,
+    General,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutingDecision {
+    pub model: String,
+    pub task: TaskKind,
+    /// False when routing is off or the selected model was kept
+    pub routed: bool,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutingTraceEntry {
+    pub timestamp: u64,
+    pub chat_id: Option<String>,
+    pub selected_model: String,
+    pub decision: RoutingDecision,
+}
+
+fn classify(task: &TaskDescriptor, config: &RoutingConfig) -> (TaskKind, String) {
+    if task.image_count > 0 {
+        return (TaskKind::Vision, format!("{} image(s) attached", task.image_count));
+    }
+
+    if task.context_tokens > config.large_context_tokens {
+        return (
+            TaskKind::Refactor,
+            format!(
+                "context of {} tokens exceeds {}",
+                task.context_tokens, config.large_context_tokens
+            ),
+        );
+    }
+
+    if task.scripts_in_scope >= config.refactor_script_count {
+        return (
+            TaskKind::Refactor,
+            format!("{} scripts in scope", task.scripts_in_scope),
+        );
+    }
+
+    let prompt = task.prompt.to_lowercase();
+    if let Some(keyword) = REFACTOR_KEYWORDS.iter().find(|k| prompt.contains(*k)) {
+        return (TaskKind::Refactor, format!("prompt mentions \"{}\"", keyword));
+    }
+
+    if task.prompt.chars().count() < config.short_prompt_chars {
+        return (
+            TaskKind::QuickFix,
+            format!("short prompt (under {} chars)", config.short_prompt_chars),
+        );
+    }
+
+    (TaskKind::General, "no strong signal".to_string())
+}
+
+/// Pick a model for a task according to the routing config
+pub fn route(task: &TaskDescriptor, config: &RoutingConfig) -> RoutingDecision {
+    if !config.enabled {
+        return RoutingDecision {
+            model: task.selected_model.clone(),
+            task: TaskKind::General,
+            routed: false,
+            reason: "routing disabled".to_string(),
+        };
+    }
+
+    let (kind, reason) = classify(task, config);
+    let candidate = match kind {
+        TaskKind::QuickFix => &config.fast_model,
+        TaskKind::Refactor | TaskKind::Vision => &config.large_model,
+        TaskKind::General => &task.selected_model,
+    };
+
+    // Never route to a model that can't handle the request
+    let features = RequestFeatures {
+        image_count: task.image_count,
+        tools: true,
+        streaming: true,
+        estimated_input_tokens: task.context_tokens,
+    };
+    if let Err(e) = models::check_request(candidate, &features) {
+        return RoutingDecision {
+            model: task.selected_model.clone(),
+            task: kind,
+            routed: false,
+            reason: format!("{}; kept selected model because {}", reason, e),
+        };
+    }
+
+    RoutingDecision {
+        model: candidate.clone(),
+        task: kind,
+        routed: *candidate != task.selected_model,
+        reason,
+    }
+}
+
+/// Choose a model for the next request and record the decision in the trace
+#[tauri::command]
+pub fn route_model(task: TaskDescriptor) -> RoutingDecision {
+    let decision = route(&task, &config::current().routing);
+
+    let mut trace = ROUTING_TRACE.lock();
+    if trace.len() >= MAX_TRACE_ENTRIES {
+        trace.pop_front();
+    }
+    trace.push_back(RoutingTraceEntry {
+        timestamp: chrono_lite_timestamp(),
+        chat_id: task.chat_id,
+        selected_model: task.selected_model,
+        decision: decision.clone(),
+    });
+
+    decision
+}
+
+/// Recent routing decisions, newest last, optionally filtered to one chat
+#[tauri::command]
+pub fn get_routing_trace(chat_id: Option<String>) -> Vec<RoutingTraceEntry> {
+    ROUTING_TRACE
+        .lock()
+        .iter()
+        .filter(|entry| chat_id.is_none() || entry.chat_id == chat_id)
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,123 @@
+//! Tool Sandbox Profiles
+//!
+//! Per-tool execution limits from the config (`sandbox.tools`, keyed by bridge
+//! route): how long Studio gets to answer, how large a request may be, and
+//! which parts of the instance tree the tool may touch. The bridge checks
+//! every request against its tool's profile before queuing it. Instance paths
+//! named in the body are checked here; `/code/run` can't be checked ahead of
+//! time, so its allowed folders are sent along and the plugin runs the code
+//! with only those folders in reach.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::bridge::StudioRequest;
+use crate::config;
+
+/// Body fields that name instances, at any depth
+const PATH_FIELDS: [&str; 6] = ["path", "parent", "root", "paths", "newParent", "folder"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolProfile {
+    /// How long to wait for Studio; the bridge default if unset
+    pub timeout_secs: Option<u64>,
+    /// Largest request body plus attachment, in bytes
+    pub max_payload_bytes: Option<u64>,
+    /// Instance path prefixes the tool may touch, e.g. `game.Workspace.Scratch`.
+    /// Empty means anywhere.
+    pub allowed_paths: Vec<String>,
+}
+
+/// Why a request was refused by its tool's profile
+pub(crate) enum Violation {
+    PayloadTooLarge(String),
+    OutsideSandbox(String),
+}
+
+/// The profile configured for a bridge route, if any
+pub(crate) fn profile_for(route: &str) -> Option<ToolProfile> {
+    config::current().sandbox.tools.get(route).cloned()
+}
+
+/// How long a request to this route may wait for Studio
+pub(crate) fn timeout_for(profile: Option<&ToolProfile>, default: Duration) -> Duration {
+    profile
+        .and_then(|profile| profile.timeout_secs)
+        .map_or(default, Duration::from_secs)
+}
+
+fn is_within(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('.');
+    path == prefix
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+fn collect_paths<'a>(value: &'a serde_json::Value, found: &mut Vec<&'a str>) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (key, field) in fields {
+                if PATH_FIELDS.contains(&key.as_str()) {
+                    match field {
+                        serde_json::Value::String(path) => found.push(path),
+                        serde_json::Value::Array(paths) => {
+                            found.extend(paths.iter().filter_map(|path| path.as_str()));
+                        }
+                        _ => {}
+                    }
+                }
+                collect_paths(field, found);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_paths(item, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Check a request against its tool's profile
+pub(crate) fn check(request: &StudioRequest, profile: &ToolProfile) -> Result<(), Violation> {
+    if let Some(max) = profile.max_payload_bytes {
+        let size = request.body.as_ref().map_or(0, String::len)
+            + request
+                .attachment
+                .as_ref()
+                .map_or(0, |attachment| attachment.data.len());
+        if size as u64 > max {
+            return Err(Violation::PayloadTooLarge(format!(
+                "{} is limited to {} bytes per request, got {}",
+                request.path, max, size
+            )));
+        }
+    }
+
+    if profile.allowed_paths.is_empty() {
+        return Ok(());
+    }
+    let body: serde_json::Value = request
+        .body
+        .as_deref()
+        .and_then(|body| serde_json::from_str(body).ok())
+        .unwrap_or_default();
+    let mut paths = Vec::new();
+    collect_paths(&body, &mut paths);
+    match paths.into_iter().find(|path| {
+        !profile
+            .allowed_paths
+            .iter()
+            .any(|prefix| is_within(path, prefix))
+    }) {
+        Some(path) => Err(Violation::OutsideSandbox(format!(
+            "{} may only touch {}, not {}",
+            request.path,
+            profile.allowed_paths.join(", "),
+            path
+        ))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,875 @@
+//! System Scaffolding
+//!
+//! Parameterized recipes for common game systems (NPC patrol, shop, door,
+//! leaderboard). Rust renders the instances and scripts from the recipe, the
+//! plugin creates them in one undoable transaction, and the result is checked
+//! afterwards, so the model only has to pick a recipe and fill in parameters
+//! instead of writing the boilerplate itself.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::bridge;
+use crate::templates;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamKind {
+    Text,
+    Number,
+    Bool,
+    /// Instance path like "game.Workspace.Model"
+    Path,
+    /// Three comma-separated numbers
+    Vector,
+    /// Comma-separated names
+    List,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    pub description: &'static str,
+    /// None means the parameter is required
+    pub default: Option<&'static str>,
+}
+
+struct Recipe {
+    id: &'static str,
+    description: &'static str,
+    params: &'static [ParamSpec],
+    build: fn(&Params) -> Result<Vec<ScaffoldStep>, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecipeInfo {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub params: &'static [ParamSpec],
+}
+
+/// One instance for the plugin to create; scripts carry their source
+#[derive(Debug, Clone, Serialize)]
+pub struct ScaffoldStep {
+    #[serde(rename = "className")]
+    pub class_name: String,
+    pub name: String,
+    pub parent: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl ScaffoldStep {
+    fn path(&self) -> String {
+        format!("{}.{}", self.parent, self.name)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScaffoldResult {
+    pub recipe: String,
+    pub created: Vec<String>,
+    /// Instances inserted from the template library, if the recipe used one
+    pub inserted: Vec<String>,
+    /// Problems found by the validation pass after creation
+    pub issues: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ApplyResponse {
+    #[serde(default)]
+    created: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ChildInfo {
+    name: String,
+    #[serde(rename = "className")]
+    class_name: String,
+}
+
+/// Recipe parameters with defaults filled in and values validated by kind
+struct Params {
+    values: HashMap<String, String>,
+}
+
+impl Params {
+    fn resolve(specs: &[ParamSpec], mut given: HashMap<String, String>) -> Result<Self, String> {
+        if let Some(unknown) = given
+            .keys()
+            .find(|key| !specs.iter().any(|spec| spec.name == key.as_str()))
+        {
+            return Err(format!("Unknown parameter: {}", unknown));
+        }
+
+        let mut values = HashMap::new();
+        for spec in specs {
+            let value = match given.remove(spec.name) {
+                Some(value) => value.trim().to_string(),
+                None => spec
+                    .default
+                    .ok_or_else(|| format!("Missing required parameter: {}", spec.name))?
+                    .to_string(),
+            };
+            validate_param(spec, &value)?;
+            values.insert(spec.name.to_string(), value);
+        }
+        Ok(Self { values })
+    }
+
+    fn get(&self, name: &str) -> &str {
+        self.values
+            .get(name)
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+
+    fn number(&self, name: &str) -> f64 {
+        self.get(name).parse().unwrap_or_default()
+    }
+
+    fn bool(&self, name: &str) -> bool {
+        self.get(name) == "true"
+    }
+
+    fn vector(&self, name: &str) -> [f64; 3] {
+        let mut parts = self
+            .get(name)
+            .split(',')
+            .map(|p| p.trim().parse().unwrap_or_default());
+        [
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+        ]
+    }
+
+    fn list(&self, name: &str) -> Vec<String> {
+        self.get(name)
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+}
+
+/// Names end up in instance names and Lua string literals, so keep them plain
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ' ')
+}
+
+fn validate_param(spec: &ParamSpec, value: &str) -> Result<(), String> {
+    let valid = match spec.kind {
+        // Empty is only allowed for optional parameters that default to empty
+        ParamKind::Text => is_safe_name(value) || (value.is_empty() && spec.default == Some("")),
+        ParamKind::Number => value.parse::<f64>().is_ok_and(f64::is_finite),
+        ParamKind::Bool => value == "true" || value == "false",
+        ParamKind::Path => {
+            (value.starts_with("game.") && !value.contains('"'))
+                || (value.is_empty() && spec.default == Some(""))
+        }
+        ParamKind::Vector => {
+            let parts: Vec<&str> = value.split(',').collect();
+            parts.len() == 3 && parts.iter().all(|p| p.trim().parse::<f64>().is_ok())
+        }
+        ParamKind::List => value
+            .split(',')
+            .map(str::trim)
+            .all(|item| item.is_empty() || is_safe_name(item)),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid value for {} ({:?}): {}",
+            spec.name, spec.kind, value
+        ))
+    }
+}
+
+fn format_vector(v: [f64; 3]) -> String {
+    format!("{}, {}, {}", v[0], v[1], v[2])
+}
+
+/// Fill `{{key}}` placeholders; leftovers mean the recipe is broken
+fn render(template: &str, values: &[(&str, String)]) -> Result<String, String> {
+    let mut source = template.to_string();
+    for (key, value) in values {
+        source = source.replace(&format!("{{{{{}}}}}", key), value);
+    }
+    match source.find("{{") {
+        Some(index) => Err(format!(
+            "Recipe template has an unfilled placeholder near: {}",
+            &source[index..source.len().min(index + 30)]
+        )),
+        None => Ok(source),
+    }
+}
+
+fn instance(
+    class_name: &str,
+    name: &str,
+    parent: &str,
+    properties: &[(&str, String)],
+) -> ScaffoldStep {
+    ScaffoldStep {
+        class_name: class_name.to_string(),
+        name: name.to_string(),
+        parent: parent.to_string(),
+        properties: properties
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect(),
+        source: None,
+    }
+}
+
+fn script(class_name: &str, name: &str, parent: &str, source: String) -> ScaffoldStep {
+    ScaffoldStep {
+        source: Some(source),
+        ..instance(class_name, name, parent, &[])
+    }
+}
+
+const DOOR_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "name",
+        kind: ParamKind::Text,
+        description: "Name of the door part",
+        default: Some("Door"),
+    },
+    ParamSpec {
+        name: "parent",
+        kind: ParamKind::Path,
+        description: "Where to put the door",
+        default: Some("game.Workspace"),
+    },
+    ParamSpec {
+        name: "position",
+        kind: ParamKind::Vector,
+        description: "Door center",
+        default: Some("0, 4, 0"),
+    },
+    ParamSpec {
+        name: "open_time",
+        kind: ParamKind::Number,
+        description: "Seconds the door stays open",
+        default: Some("3"),
+    },
+];
+
+const DOOR_SCRIPT: &str = r#"-- Slides the door open when the prompt is triggered, then closes it again
+local TweenService = game:GetService("TweenService")
+
+local door = script.Parent
+local prompt = door:WaitForChild("OpenPrompt")
+local OPEN_TIME = {{open_time}}
+
+local closedCFrame = door.CFrame
+local openCFrame = closedCFrame * CFrame.new(door.Size.X, 0, 0)
+local tweenInfo = TweenInfo.new(0.4, Enum.EasingStyle.Quad)
+
+prompt.Triggered:Connect(function()
+	prompt.Enabled = false
+	door.CanCollide = false
+	TweenService:Create(door, tweenInfo, { CFrame = openCFrame }):Play()
+	task.wait(OPEN_TIME)
+
+	local close = TweenService:Create(door, tweenInfo, { CFrame = closedCFrame })
+	close:Play()
+	close.Completed:Wait()
+	door.CanCollide = true
+	prompt.Enabled = true
+end)
+"#;
+
+fn build_door(params: &Params) -> Result<Vec<ScaffoldStep>, String> {
+    let door = instance(
+        "Part",
+        params.get("name"),
+        params.get("parent"),
+        &[
+            ("Anchored", "true".to_string()),
+            ("Size", "4, 8, 1".to_string()),
+            ("Position", format_vector(params.vector("position"))),
+            ("Color", "#8B5A2B".to_string()),
+            ("Material", "Enum.Material.Wood".to_string()),
+        ],
+    );
+    let door_path = door.path();
+    let source = render(
+        DOOR_SCRIPT,
+        &[("open_time", params.number("open_time").to_string())],
+    )?;
+
+    Ok(vec![
+        door,
+        instance(
+            "ProximityPrompt",
+            "OpenPrompt",
+            &door_path,
+            &[
+                ("ActionText", "Open".to_string()),
+                ("HoldDuration", "0".to_string()),
+            ],
+        ),
+        script("Script", "DoorController", &door_path, source),
+    ])
+}
+
+const LEADERBOARD_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "stats",
+        kind: ParamKind::List,
+        description: "Stats to show, e.g. \"Coins, Kills\"",
+        default: Some("Coins"),
+    },
+    ParamSpec {
+        name: "starting_value",
+        kind: ParamKind::Number,
+        description: "Initial value for new players",
+        default: Some("0"),
+    },
+    ParamSpec {
+        name: "save",
+        kind: ParamKind::Bool,
+        description: "Persist stats with DataStoreService",
+        default: Some("false"),
+    },
+    ParamSpec {
+        name: "store_name",
+        kind: ParamKind::Text,
+        description: "DataStore name when saving",
+        default: Some("PlayerStats"),
+    },
+];
+
+const LEADERBOARD_SCRIPT: &str = r#"-- Creates leaderstats for each player and optionally saves them between sessions
+local Players = game:GetService("Players")
+local DataStoreService = game:GetService("DataStoreService")
+
+local STATS = { {{stats}} }
+local STARTING_VALUE = {{starting_value}}
+local store = {{save}} and DataStoreService:GetDataStore("{{store_name}}") or nil
+
+Players.PlayerAdded:Connect(function(player)
+	local saved
+	if store then
+		local ok, data = pcall(store.GetAsync, store, tostring(player.UserId))
+		if ok then
+			saved = data
+		end
+	end
+
+	local leaderstats = Instance.new("Folder")
+	leaderstats.Name = "leaderstats"
+	for _, statName in ipairs(STATS) do
+		local stat = Instance.new("IntValue")
+		stat.Name = statName
+		stat.Value = saved and saved[statName] or STARTING_VALUE
+		stat.Parent = leaderstats
+	end
+	leaderstats.Parent = player
+end)
+
+local function save(player)
+	local leaderstats = player:FindFirstChild("leaderstats")
+	if not store or not leaderstats then
+		return
+	end
+	local data = {}
+	for _, stat in ipairs(leaderstats:GetChildren()) do
+		data[stat.Name] = stat.Value
+	end
+	pcall(store.SetAsync, store, tostring(player.UserId), data)
+end
+
+Players.PlayerRemoving:Connect(save)
+game:BindToClose(function()
+	for _, player in ipairs(Players:GetPlayers()) do
+		save(player)
+	end
+end)
+"#;
+
+fn build_leaderboard(params: &Params) -> Result<Vec<ScaffoldStep>, String> {
+    let stats = params.list("stats");
+    if stats.is_empty() {
+        return Err("Leaderboard needs at least one stat".to_string());
+    }
+    let stats = stats
+        .iter()
+        .map(|stat| format!("\"{}\"", stat))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let source = render(
+        LEADERBOARD_SCRIPT,
+        &[
+            ("stats", stats),
+            (
+                "starting_value",
+                params.number("starting_value").to_string(),
+            ),
+            ("save", params.bool("save").to_string()),
+            ("store_name", params.get("store_name").to_string()),
+        ],
+    )?;
+    Ok(vec![script(
+        "Script",
+        "Leaderboard",
+        "game.ServerScriptService",
+        source,
+    )])
+}
+
+const SHOP_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "name",
+        kind: ParamKind::Text,
+        description: "Folder name in ReplicatedStorage",
+        default: Some("Shop"),
+    },
+    ParamSpec {
+        name: "items",
+        kind: ParamKind::Text,
+        description: "Items and prices, e.g. \"Sword:100, Shield:150\"",
+        default: None,
+    },
+    ParamSpec {
+        name: "currency",
+        kind: ParamKind::Text,
+        description: "leaderstats value used to pay",
+        default: Some("Coins"),
+    },
+];
+
+const SHOP_SERVER_SCRIPT: &str = r#"-- Handles purchases: checks the price, charges the player and records ownership
+local ReplicatedStorage = game:GetService("ReplicatedStorage")
+
+local shop = ReplicatedStorage:WaitForChild("{{name}}")
+local items = require(shop:WaitForChild("Items"))
+local purchase = shop:WaitForChild("Purchase")
+local CURRENCY = "{{currency}}"
+
+purchase.OnServerInvoke = function(player, itemName)
+	local price = items[itemName]
+	if type(price) ~= "number" then
+		return false, "Unknown item"
+	end
+
+	local leaderstats = player:FindFirstChild("leaderstats")
+	local balance = leaderstats and leaderstats:FindFirstChild(CURRENCY)
+	if not balance then
+		return false, "Missing " .. CURRENCY .. " stat"
+	end
+	if balance.Value < price then
+		return false, "Not enough " .. CURRENCY
+	end
+
+	local owned = player:FindFirstChild("OwnedItems")
+	if not owned then
+		owned = Instance.new("Folder")
+		owned.Name = "OwnedItems"
+		owned.Parent = player
+	end
+	if owned:FindFirstChild(itemName) then
+		return false, "Already owned"
+	end
+
+	balance.Value = balance.Value - price
+	local flag = Instance.new("BoolValue")
+	flag.Name = itemName
+	flag.Value = true
+	flag.Parent = owned
+	return true
+end
+"#;
+
+fn build_shop(params: &Params) -> Result<Vec<ScaffoldStep>, String> {
+    let name = params.get("name");
+    let mut entries = Vec::new();
+    for item in params
+        .get("items")
+        .split(',')
+        .map(str::trim)
+        .filter(|i| !i.is_empty())
+    {
+        let (item_name, price) = item
+            .split_once(':')
+            .ok_or_else(|| format!("Item \"{}\" should look like Name:Price", item))?;
+        let (item_name, price) = (item_name.trim(), price.trim());
+        if !is_safe_name(item_name) || price.parse::<u64>().is_err() {
+            return Err(format!("Item \"{}\" should look like Name:Price", item));
+        }
+        entries.push(format!("\t[\"{}\"] = {},", item_name, price));
+    }
+    if entries.is_empty() {
+        return Err("Shop needs at least one item".to_string());
+    }
+
+    let folder = instance("Folder", name, "game.ReplicatedStorage", &[]);
+    let folder_path = folder.path();
+    let items_source = format!(
+        "-- Item prices in {}\nreturn {{\n{}\n}}\n",
+        params.get("currency"),
+        entries.join("\n")
+    );
+    let server_source = render(
+        SHOP_SERVER_SCRIPT,
+        &[
+            ("name", name.to_string()),
+            ("currency", params.get("currency").to_string()),
+        ],
+    )?;
+
+    Ok(vec![
+        folder,
+        script("ModuleScript", "Items", &folder_path, items_source),
+        instance("RemoteFunction", "Purchase", &folder_path, &[]),
+        script(
+            "Script",
+            &format!("{}Server", name),
+            "game.ServerScriptService",
+            server_source,
+        ),
+    ])
+}
+
+const NPC_PATROL_PARAMS: &[ParamSpec] = &[
+    ParamSpec {
+        name: "npc",
+        kind: ParamKind::Path,
+        description: "Existing NPC model with a Humanoid",
+        default: Some(""),
+    },
+    ParamSpec {
+        name: "rig_template",
+        kind: ParamKind::Text,
+        description: "Template to insert as the NPC instead of using an existing one",
+        default: Some(""),
+    },
+    ParamSpec {
+        name: "center",
+        kind: ParamKind::Vector,
+        description: "Center of the patrol loop",
+        default: Some("0, 3, 0"),
+    },
+    ParamSpec {
+        name: "radius",
+        kind: ParamKind::Number,
+        description: "Distance of waypoints from the center",
+        default: Some("20"),
+    },
+    ParamSpec {
+        name: "waypoint_count",
+        kind: ParamKind::Number,
+        description: "Number of waypoints",
+        default: Some("4"),
+    },
+    ParamSpec {
+        name: "wait_time",
+        kind: ParamKind::Number,
+        description: "Seconds to pause at each waypoint",
+        default: Some("2"),
+    },
+    ParamSpec {
+        name: "walk_speed",
+        kind: ParamKind::Number,
+        description: "Humanoid WalkSpeed while patrolling",
+        default: Some("12"),
+    },
+];
+
+const NPC_PATROL_SCRIPT: &str = r#"-- Walks the NPC between its waypoints in order, using pathfinding around obstacles
+local PathfindingService = game:GetService("PathfindingService")
+
+local npc = script.Parent
+local humanoid = npc:WaitForChild("Humanoid")
+local root = npc:WaitForChild("HumanoidRootPart")
+local waypointFolder = workspace:WaitForChild("{{waypoints}}")
+local WAIT_TIME = {{wait_time}}
+
+humanoid.WalkSpeed = {{walk_speed}}
+
+local points = waypointFolder:GetChildren()
+table.sort(points, function(a, b)
+	return (tonumber(a.Name:match("%d+")) or 0) < (tonumber(b.Name:match("%d+")) or 0)
+end)
+
+local function walkTo(target)
+	local path = PathfindingService:CreatePath()
+	local ok = pcall(path.ComputeAsync, path, root.Position, target)
+	if not ok or path.Status ~= Enum.PathStatus.Success then
+		humanoid:MoveTo(target)
+		humanoid.MoveToFinished:Wait()
+		return
+	end
+
+	for _, waypoint in ipairs(path:GetWaypoints()) do
+		if waypoint.Action == Enum.PathWaypointAction.Jump then
+			humanoid.Jump = true
+		end
+		humanoid:MoveTo(waypoint.Position)
+		humanoid.MoveToFinished:Wait()
+	end
+end
+
+while true do
+	for _, point in ipairs(points) do
+		walkTo(point.Position)
+		task.wait(WAIT_TIME)
+	end
+end
+"#;
+
+fn build_npc_patrol(params: &Params) -> Result<Vec<ScaffoldStep>, String> {
+    let npc = params.get("npc");
+    if npc.is_empty() {
+        return Err("Set npc to an existing model or rig_template to insert one".to_string());
+    }
+    let npc_name = npc.rsplit('.').next().unwrap_or(npc);
+    let count = params.number("waypoint_count") as usize;
+    if !(2..=32).contains(&count) {
+        return Err("waypoint_count must be between 2 and 32".to_string());
+    }
+
+    let waypoints = format!("{}Waypoints", npc_name.replace(' ', ""));
+    let folder = instance("Folder", &waypoints, "game.Workspace", &[]);
+    let folder_path = folder.path();
+    let center = params.vector("center");
+    let radius = params.number("radius");
+
+    let mut steps = vec![folder];
+    for i in 0..count {
+        let angle = std::f64::consts::TAU * i as f64 / count as f64;
+        let position = [
+            center[0] + radius * angle.cos(),
+            center[1],
+            center[2] + radius * angle.sin(),
+        ];
+        steps.push(instance(
+            "Part",
+            &format!("Waypoint{}", i + 1),
+            &folder_path,
+            &[
+                ("Anchored", "true".to_string()),
+                ("CanCollide", "false".to_string()),
+                ("CanQuery", "false".to_string()),
+                ("Transparency", "1".to_string()),
+                ("Size", "1, 1, 1".to_string()),
+                ("Position", format_vector(position)),
+            ],
+        ));
+    }
+
+    let source = render(
+        NPC_PATROL_SCRIPT,
+        &[
+            ("waypoints", waypoints),
+            ("wait_time", params.number("wait_time").to_string()),
+            ("walk_speed", params.number("walk_speed").to_string()),
+        ],
+    )?;
+    steps.push(script("Script", "PatrolController", npc, source));
+    Ok(steps)
+}
+
+const RECIPES: &[Recipe] = &[
+    Recipe {
+        id: "door",
+        description: "A part that slides open when a ProximityPrompt is triggered",
+        params: DOOR_PARAMS,
+        build: build_door,
+    },
+    Recipe {
+        id: "leaderboard",
+        description: "leaderstats for every player, optionally saved with DataStores",
+        params: LEADERBOARD_PARAMS,
+        build: build_leaderboard,
+    },
+    Recipe {
+        id: "shop",
+        description: "Item price list, purchase RemoteFunction and server purchase handler",
+        params: SHOP_PARAMS,
+        build: build_shop,
+    },
+    Recipe {
+        id: "npc_patrol",
+        description: "Waypoint loop and pathfinding patrol script for an NPC",
+        params: NPC_PATROL_PARAMS,
+        build: build_npc_patrol,
+    },
+];
+
+fn find_recipe(id: &str) -> Result<&'static Recipe, String> {
+    RECIPES
+        .iter()
+        .find(|recipe| recipe.id == id)
+        .ok_or_else(|| format!("Unknown recipe: {}", id))
+}
+
+/// The NPC recipe needs a Humanoid and root part to move anything
+async fn check_npc(npc: &str, session: Option<&str>) -> Result<(), String> {
+    let result = bridge::studio_request(
+        session,
+        "/instance/children",
+        serde_json::json!({ "path": npc }),
+    )
+    .await?;
+    let children: Vec<ChildInfo> = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+    if !children.iter().any(|child| child.class_name == "Humanoid") {
+        return Err(format!("{} has no Humanoid", npc));
+    }
+    if !children
+        .iter()
+        .any(|child| child.name == "HumanoidRootPart")
+    {
+        return Err(format!("{} has no HumanoidRootPart", npc));
+    }
+    Ok(())
+}
+
+/// Confirm every script made it into Studio with the source we sent
+async fn verify(steps: &[ScaffoldStep], created: &[String], session: Option<&str>) -> Vec<String> {
+    let mut issues = Vec::new();
+    for step in steps {
+        let path = step.path();
+        if !created.contains(&path) {
+            issues.push(format!("{} was not created", path));
+            continue;
+        }
+        let Some(expected) = &step.source else {
+            continue;
+        };
+        match bridge::studio_request(session, "/script/get", serde_json::json!({ "path": path }))
+            .await
+        {
+            Ok(result) => {
+                let source = result
+                    .get("source")
+                    .and_then(|s| s.as_str())
+                    .unwrap_or_default();
+                if source != expected {
+                    issues.push(format!("{} source differs from the recipe", path));
+                }
+            }
+            Err(e) => issues.push(format!("Couldn't read back {}: {}", path, e)),
+        }
+    }
+    issues
+}
+
+/// List available scaffolding recipes and their parameters
+#[tauri::command]
+pub fn list_scaffold_recipes() -> Vec<RecipeInfo> {
+    RECIPES
+        .iter()
+        .map(|recipe| RecipeInfo {
+            id: recipe.id,
+            description: recipe.description,
+            params: recipe.params,
+        })
+        .collect()
+}
+
+/// Render a recipe without touching Studio
+#[tauri::command]
+pub fn preview_scaffold(
+    recipe: String,
+    params: HashMap<String, String>,
+) -> Result<Vec<ScaffoldStep>, String> {
+    let recipe = find_recipe(&recipe)?;
+    let params = Params::resolve(recipe.params, params)?;
+    (recipe.build)(&params)
+}
+
+/// Build a recipe in Studio as one undoable change, then verify the result
+#[tauri::command]
+pub async fn run_scaffold(
+    recipe: String,
+    params: HashMap<String, String>,
+    session: Option<String>,
+) -> Result<ScaffoldResult, String> {
+    let recipe = find_recipe(&recipe)?;
+    let mut params = Params::resolve(recipe.params, params)?;
+    let session = session.as_deref();
+
+    // Insert the rig first so the rest of the recipe can target it
+    let mut inserted = Vec::new();
+    let template = params.get("rig_template").to_string();
+    if !template.is_empty() {
+        let result = templates::insert_template(
+            template,
+            Some("game.Workspace".to_string()),
+            session.map(str::to_string),
+        )
+        .await?;
+        inserted = result
+            .get("inserted")
+            .and_then(|list| serde_json::from_value::<Vec<String>>(list.clone()).ok())
+            .unwrap_or_default();
+        let rig = inserted
+            .first()
+            .cloned()
+            .ok_or_else(|| "Template inserted no instances".to_string())?;
+        params.values.insert("npc".to_string(), rig);
+    }
+
+    let rollback_inserted = |inserted: Vec<String>| async move {
+        for path in inserted {
+            let _ = bridge::studio_request(
+                session,
+                "/instance/delete",
+                serde_json::json!({ "path": path }),
+            )
+            .await;
+        }
+    };
+
+    let steps = match (recipe.build)(&params) {
+        Ok(steps) => steps,
+        Err(e) => {
+            rollback_inserted(inserted).await;
+            return Err(e);
+        }
+    };
+
+    if recipe.id == "npc_patrol" {
+        if let Err(e) = check_npc(params.get("npc"), session).await {
+            rollback_inserted(inserted).await;
+            return Err(e);
+        }
+    }
+
+    let result = match bridge::studio_request(
+        session,
+        "/scaffold/apply",
+        serde_json::json!({ "steps": steps }),
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            rollback_inserted(inserted).await;
+            return Err(e);
+        }
+    };
+    let applied: ApplyResponse = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+
+    let issues = verify(&steps, &applied.created, session).await;
+    Ok(ScaffoldResult {
+        recipe: recipe.id.to_string(),
+        created: applied.created,
+        inserted,
+        issues,
+    })
+}
@@ -0,0 +1,444 @@
+//! Headless Self-Test
+//!
+//! `stud --selftest` starts the local servers without opening a window, drives
+//! the bridge end to end with a simulated Studio plugin, checks that the data
+//! directory is writable, prints a JSON report to stdout and exits with status
+//! 1 if anything failed. Meant for packaging pipelines and for users checking a
+//! build on their machine.
+//!
+//! `run_bridge_selftest` does the bridge part inside the running app: a probe
+//! session plays the plugin against the live bridge, so onboarding can tell a
+//! healthy bridge from a missing or broken Studio plugin. Probes only see
+//! requests addressed to them and never show up as a connected Studio.
+
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use stud_bridge_client::{BridgeClient, PollOptions, Request, RESPONSE_CHUNK_SIZE};
+
+use crate::bridge::{self, BridgeEndpoints};
+use crate::paths;
+
+const SELFTEST_FLAG: &str = "--selftest";
+/// How long to wait for the servers to bind
+const STARTUP_TIMEOUT_SECS: u64 = 10;
+/// Upper bound on any single check
+const CHECK_TIMEOUT_SECS: u64 = 10;
+const SIMULATED_SESSION: &str = "selftest";
+/// Echo round trips timed by the in-app self-test
+const ROUNDTRIPS: usize = 5;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+#[derive(Debug, Serialize)]
+struct CheckResult {
+    name: &'static str,
+    status: CheckStatus,
+    detail: String,
+    duration_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    ok: bool,
+    version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    endpoints: BridgeEndpoints,
+    checks: Vec<CheckResult>,
+}
+
+/// Whether the app was launched with `--selftest`
+pub fn requested() -> bool {
+    std::env::args().any(|arg| arg == SELFTEST_FLAG)
+}
+
+/// Run every check, print the report and return the process exit code
+pub fn run() -> i32 {
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("[Stud Selftest] Failed to create tokio runtime: {}", e);
+            return 1;
+        }
+    };
+    let report = runtime.block_on(run_checks());
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("[Stud Selftest] Failed to encode report: {}", e),
+    }
+    if report.ok {
+        0
+    } else {
+        1
+    }
+}
+
+async fn run_checks() -> Report {
+    let mut checks = vec![check("data_dir", data_dir_access()).await];
+
+    let server = tokio::spawn(bridge::start_bridge_server(bridge::BridgeHandle::shared()));
+    let endpoints = wait_for_endpoints().await;
+
+    match endpoints.bridge_port {
+        Some(port) => {
+            let base = format!("http://127.0.0.1:{}", port);
+            let client = BridgeClient::new(&base, bridge::bridge_secret());
+            let plugin = tokio::spawn(simulated_plugin(
+                client.clone(),
+                SIMULATED_SESSION.to_string(),
+            ));
+            checks.push(check("bridge_auth", bridge_auth(&base)).await);
+            checks.push(check("bridge_negotiation", bridge_negotiation(&base)).await);
+            checks.push(check("bridge_roundtrip", bridge_roundtrip(&client)).await);
+            checks.push(check("bridge_backend_request", backend_request()).await);
+            plugin.abort();
+        }
+        None => checks.push(failed("bridge_server", "Bridge did not bind a port")),
+    }
+
+    // The callback server only runs during a sign-in; start one to check it
+    let auth = Arc::new(crate::auth::AuthService::default());
+    checks.push(match crate::auth::start_oauth_server(auth.clone()).await {
+        Ok(port) => check("oauth_server", oauth_server(port)).await,
+        Err(e) => failed("oauth_server", &e),
+    });
+    auth.stop_server();
+    checks.push(match endpoints.codex_proxy_port {
+        Some(port) => check("codex_proxy", accepts_connections(port)).await,
+        None => failed("codex_proxy", "Codex proxy did not bind a port"),
+    });
+    checks.push(CheckResult {
+        name: "keychain",
+        status: CheckStatus::Skip,
+        detail: "This build doesn't store credentials in the system keychain".to_string(),
+        duration_ms: 0,
+    });
+
+    bridge::request_shutdown();
+    if tokio::time::timeout(Duration::from_secs(CHECK_TIMEOUT_SECS), server)
+        .await
+        .is_err()
+    {
+        checks.push(failed("shutdown", "Servers did not stop in time"));
+    }
+
+    Report {
+        ok: checks.iter().all(|check| check.status != CheckStatus::Fail),
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        endpoints,
+        checks,
+    }
+}
+
+async fn check(
+    name: &'static str,
+    test: impl Future<Output = Result<String, String>>,
+) -> CheckResult {
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(Duration::from_secs(CHECK_TIMEOUT_SECS), test)
+        .await
+        .unwrap_or_else(|_| Err(format!("Timed out after {}s", CHECK_TIMEOUT_SECS)));
+    let (status, detail) = match outcome {
+        Ok(detail) => (CheckStatus::Pass, detail),
+        Err(detail) => (CheckStatus::Fail, detail),
+    };
+    CheckResult {
+        name,
+        status,
+        detail,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn failed(name: &'static str, detail: &str) -> CheckResult {
+    CheckResult {
+        name,
+        status: CheckStatus::Fail,
+        detail: detail.to_string(),
+        duration_ms: 0,
+    }
+}
+
+/// The servers bind in the background; wait until both have reported in
+async fn wait_for_endpoints() -> BridgeEndpoints {
+    let deadline = Instant::now() + Duration::from_secs(STARTUP_TIMEOUT_SECS);
+    loop {
+        let endpoints = bridge::get_bridge_endpoints();
+        let ready = endpoints.bridge_port.is_some() && endpoints.codex_proxy_port.is_some();
+        if ready || Instant::now() >= deadline {
+            return endpoints;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+async fn data_dir_access() -> Result<String, String> {
+    let dir = paths::app_data_dir()?;
+    let probe = dir.join(format!(".selftest-{}", uuid::Uuid::new_v4()));
+    let contents = "stud selftest";
+    std::fs::write(&probe, contents)
+        .map_err(|e| format!("Cannot write to {}: {}", dir.display(), e))?;
+    let read = std::fs::read_to_string(&probe);
+    let _ = std::fs::remove_file(&probe);
+    match read {
+        Ok(read) if read == contents => Ok(dir.display().to_string()),
+        Ok(_) => Err(format!("Read back different data from {}", dir.display())),
+        Err(e) => Err(format!("Cannot read from {}: {}", dir.display(), e)),
+    }
+}
+
+/// Requests without the secret must be refused
+async fn bridge_auth(base: &str) -> Result<String, String> {
+    match BridgeClient::new(base, "not-the-secret").status().await {
+        Err(stud_bridge_client::Error::Unauthorized) => {
+            Ok("Unauthenticated requests are refused".to_string())
+        }
+        Err(e) => Err(e.to_string()),
+        Ok(_) => Err("Expected 401 without the bridge secret".to_string()),
+    }
+}
+
+async fn bridge_negotiation(base: &str) -> Result<String, String> {
+    let response: serde_json::Value = reqwest::Client::new()
+        .post(format!("{}/stud/v2/hello", base))
+        .header("X-Stud-Secret", bridge::bridge_secret())
+        .json(&serde_json::json!({ "versions": [1, 2] }))
+        .send()
+        .await
+        .map_err(|e| format!("Hello failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid hello response: {}", e))?;
+    match response.get("version").and_then(|v| v.as_u64()) {
+        Some(version) => Ok(format!("Negotiated protocol v{}", version)),
+        None => Err(format!("No version in hello response: {}", response)),
+    }
+}
+
+/// A request posted over HTTP reaches the simulated plugin and its answer comes back
+async fn bridge_roundtrip(client: &BridgeClient) -> Result<String, String> {
+    let nonce = uuid::Uuid::new_v4().to_string();
+    let response = client
+        .request("/selftest/echo", &serde_json::json!({ "nonce": nonce }))
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.pointer("/echo/nonce").and_then(|v| v.as_str()) != Some(nonce.as_str()) {
+        return Err(format!("Unexpected response: {}", response));
+    }
+    Ok("Request answered by the simulated plugin".to_string())
+}
+
+/// The in-process path backend features use to reach Studio
+async fn backend_request() -> Result<String, String> {
+    let result = bridge::studio_request(
+        Some(SIMULATED_SESSION),
+        "/selftest/echo",
+        serde_json::json!({ "from": "backend" }),
+    )
+    .await?;
+    if result.pointer("/echo/from").and_then(|v| v.as_str()) != Some("backend") {
+        return Err(format!("Unexpected response: {}", result));
+    }
+    Ok("Backend request answered".to_string())
+}
+
+async fn oauth_server(port: u16) -> Result<String, String> {
+    let response: serde_json::Value = reqwest::get(format!(
+        "http://127.0.0.1:{}/auth/poll?state=selftest",
+        port
+    ))
+    .await
+    .map_err(|e| format!("OAuth server unreachable: {}", e))?
+    .json()
+    .await
+    .map_err(|e| format!("Invalid poll response: {}", e))?;
+    if response.get("pending") != Some(&serde_json::Value::Bool(false)) {
+        return Err(format!("Unexpected poll response: {}", response));
+    }
+    Ok(format!("Listening on port {}", port))
+}
+
+async fn accepts_connections(port: u16) -> Result<String, String> {
+    tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .map_err(|e| format!("Cannot connect to port {}: {}", port, e))?;
+    Ok(format!("Listening on port {}", port))
+}
+
+/// Stands in for the Studio plugin: long-polls the bridge and echoes every
+/// request body back as `{ "echo": body }`
+async fn simulated_plugin(client: BridgeClient, session: String) {
+    let options = PollOptions {
+        session: Some(session),
+        place_name: Some("Selftest".to_string()),
+        plugin_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        wait_secs: 5,
+        max: 10,
+        ..Default::default()
+    };
+    loop {
+        let Ok(poll) = client.poll(&options).await else {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            continue;
+        };
+        for item in poll.requests {
+            let body: serde_json::Value = item
+                .request
+                .body
+                .as_deref()
+                .and_then(|body| serde_json::from_str(body).ok())
+                .unwrap_or_default();
+            let _ = client
+                .respond(&item.id, 200, &serde_json::json!({ "echo": body }))
+                .await;
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Latency {
+    samples: usize,
+    min_ms: f64,
+    avg_ms: f64,
+    max_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BridgeSelftestReport {
+    ok: bool,
+    bridge_url: Option<String>,
+    checks: Vec<CheckResult>,
+    /// Round trips from /stud/request through the simulated plugin and back
+    latency: Option<Latency>,
+}
+
+/// Echo requests addressed to the probe, timing each round trip
+async fn probe_roundtrips(
+    client: &BridgeClient,
+    session: &str,
+    samples: &mut Vec<f64>,
+) -> Result<String, String> {
+    for _ in 0..ROUNDTRIPS {
+        let nonce = uuid::Uuid::new_v4().to_string();
+        let started = Instant::now();
+        let response = client
+            .send(
+                Request::new("/selftest/echo")
+                    .session(session)
+                    .body(&serde_json::json!({ "nonce": nonce })),
+            )
+            .await
+            .map_err(|e| e.to_string())?
+            .into_json()
+            .ok_or("Expected a JSON response")?;
+        samples.push(started.elapsed().as_secs_f64() * 1000.0);
+        if response.pointer("/echo/nonce").and_then(|v| v.as_str()) != Some(nonce.as_str()) {
+            return Err(format!("Response doesn't match the request: {}", response));
+        }
+    }
+    Ok(format!("{} requests answered correctly", ROUNDTRIPS))
+}
+
+/// A response too large for one /stud/respond body arrives whole
+async fn probe_chunked_response(client: &BridgeClient, session: &str) -> Result<String, String> {
+    let payload = "x".repeat(RESPONSE_CHUNK_SIZE + RESPONSE_CHUNK_SIZE / 2);
+    let response = client
+        .send(
+            Request::new("/selftest/echo")
+                .session(session)
+                .body(&serde_json::json!({ "payload": payload })),
+        )
+        .await
+        .map_err(|e| e.to_string())?
+        .into_json()
+        .ok_or("Expected a JSON response")?;
+    match response.pointer("/echo/payload").and_then(|v| v.as_str()) {
+        Some(echoed) if echoed == payload => {
+            Ok(format!("{} byte response reassembled", payload.len()))
+        }
+        Some(echoed) => Err(format!(
+            "Sent {} bytes but {} came back",
+            payload.len(),
+            echoed.len()
+        )),
+        None => Err("Payload missing from the response".to_string()),
+    }
+}
+
+/// Play the Studio plugin against the running bridge and report whether
+/// requests make it there and back intact, and how long that takes
+#[tauri::command]
+pub async fn run_bridge_selftest() -> BridgeSelftestReport {
+    let endpoints = bridge::get_bridge_endpoints();
+    let Some(base) = endpoints.bridge_url else {
+        return BridgeSelftestReport {
+            ok: false,
+            bridge_url: None,
+            checks: vec![failed("bridge_server", "The bridge isn't running")],
+            latency: None,
+        };
+    };
+    let client = BridgeClient::new(&base, bridge::bridge_secret());
+    let session = bridge::probe_session_id();
+    let plugin = tokio::spawn(simulated_plugin(client.clone(), session.clone()));
+
+    let mut samples = Vec::new();
+    let mut checks = vec![
+        check("bridge_health", async {
+            client
+                .health()
+                .await
+                .map(|_| format!("Answering at {}", base))
+                .map_err(|e| e.to_string())
+        })
+        .await,
+        check("bridge_auth", bridge_auth(&base)).await,
+    ];
+    checks.push(
+        check(
+            "bridge_roundtrip",
+            probe_roundtrips(&client, &session, &mut samples),
+        )
+        .await,
+    );
+    checks.push(
+        check(
+            "bridge_chunked_response",
+            probe_chunked_response(&client, &session),
+        )
+        .await,
+    );
+    plugin.abort();
+
+    let latency = (!samples.is_empty()).then(|| Latency {
+        samples: samples.len(),
+        min_ms: samples.iter().copied().fold(f64::INFINITY, f64::min),
+        avg_ms: samples.iter().sum::<f64>() / samples.len() as f64,
+        max_ms: samples.iter().copied().fold(0.0, f64::max),
+    });
+    let ok = checks.iter().all(|check| check.status != CheckStatus::Fail);
+    println!(
+        "[Stud Selftest] Bridge self-test {}{}",
+        if ok { "passed" } else { "failed" },
+        latency
+            .as_ref()
+            .map(|latency| format!(", {:.1} ms average round trip", latency.avg_ms))
+            .unwrap_or_default()
+    );
+    BridgeSelftestReport {
+        ok,
+        bridge_url: Some(base),
+        checks,
+        latency,
+    }
+}
@@ -0,0 +1,261 @@
+//! Storage Cleanup
+//!
+//! Reports how much of the app data folder each kind of accumulated file
+//! takes and reclaims it on request. Long-term users build up rotated traffic
+//! logs, old checkpoints, template data whose metadata is gone and cached
+//! provider manifests; none of it is needed to run, but nothing else ever
+//! deletes it. Every run can be a dry run that only reports.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::bridge::chrono_lite_timestamp;
+use crate::checkpoints::{self, CheckpointKind};
+use crate::{bridge_log, models, paths, templates};
+
+/// Files younger than this are kept unless the caller picks another age
+const DEFAULT_MAX_AGE_DAYS: u64 = 30;
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCategory {
+    /// Rotated bridge traffic logs
+    Logs,
+    /// Per-edit checkpoints and auto snapshots, plus checkpoint files that can't be read
+    Snapshots,
+    /// Template data (rbxm) whose metadata file was deleted
+    Attachments,
+    /// Model capabilities refreshed from models.dev; the bundled list is used without it
+    ProviderCache,
+    /// Sources saved before a checkpoint restore
+    Backups,
+}
+
+const ALL_CATEGORIES: [StorageCategory; 5] = [
+    StorageCategory::Logs,
+    StorageCategory::Snapshots,
+    StorageCategory::Attachments,
+    StorageCategory::ProviderCache,
+    StorageCategory::Backups,
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CleanOptions {
+    /// Report what would be removed without deleting anything
+    pub dry_run: bool,
+    /// Categories to clean; every category when empty
+    pub categories: Vec<StorageCategory>,
+    /// Logs, snapshots and backups newer than this are kept (default 30)
+    pub older_than_days: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryReport {
+    pub category: StorageCategory,
+    /// Everything in the category, kept or not
+    pub total_bytes: u64,
+    pub reclaimable_bytes: u64,
+    pub reclaimable_files: usize,
+    /// Files actually deleted; 0 on a dry run
+    pub removed_files: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageReport {
+    pub dry_run: bool,
+    /// Size of the whole app data folder before cleaning
+    pub app_data_bytes: u64,
+    pub categories: Vec<CategoryReport>,
+    pub reclaimed_bytes: u64,
+    /// Files that couldn't be deleted
+    pub errors: Vec<String>,
+}
+
+/// A category's files and which of them can go
+struct Inventory {
+    files: Vec<(PathBuf, u64)>,
+    removable: Vec<PathBuf>,
+}
+
+impl Inventory {
+    fn new(files: Vec<PathBuf>, removable: impl Fn(&Path) -> bool) -> Self {
+        let removable = files
+            .iter()
+            .filter(|path| removable(path))
+            .cloned()
+            .collect();
+        let files = files
+            .into_iter()
+            .map(|path| {
+                let size = file_size(&path);
+                (path, size)
+            })
+            .collect();
+        Self { files, removable }
+    }
+
+    fn size_of(&self, path: &Path) -> u64 {
+        self.files
+            .iter()
+            .find(|(file, _)| file == path)
+            .map_or(0, |(_, size)| *size)
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map_or(0, |metadata| metadata.len())
+}
+
+/// Files directly inside a folder; none if it doesn't exist
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .map(|path| {
+                    if path.is_dir() {
+                        dir_size(&path)
+                    } else {
+                        file_size(&path)
+                    }
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn inventory(category: StorageCategory, cutoff_ms: u64) -> Result<Inventory, String> {
+    let cutoff = SystemTime::UNIX_EPOCH + Duration::from_millis(cutoff_ms);
+    match category {
+        StorageCategory::Logs => {
+            // The file being written today is always newer than the cutoff
+            let files = files_in(&bridge_log::log_dir()?);
+            Ok(Inventory::new(files, |path| {
+                fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .is_ok_and(|modified| modified < cutoff)
+            }))
+        }
+        StorageCategory::Snapshots | StorageCategory::Backups => {
+            let wanted = |kind: CheckpointKind| match category {
+                StorageCategory::Backups => kind == CheckpointKind::Backup,
+                _ => kind != CheckpointKind::Backup,
+            };
+            let stored = checkpoints::stored_files()?;
+            let files = stored
+                .iter()
+                .filter(|(_, header)| match header {
+                    Some(header) => wanted(header.kind),
+                    // Unreadable files are counted once, as snapshots
+                    None => category == StorageCategory::Snapshots,
+                })
+                .map(|(path, _)| path.clone())
+                .collect();
+            Ok(Inventory::new(files, |path| {
+                stored
+                    .iter()
+                    .find(|(file, _)| file == path)
+                    .and_then(|(_, header)| header.as_ref())
+                    .is_none_or(|header| header.created < cutoff_ms)
+            }))
+        }
+        StorageCategory::Attachments => {
+            let files: Vec<PathBuf> = files_in(&templates::templates_dir()?)
+                .into_iter()
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rbxm"))
+                .collect();
+            Ok(Inventory::new(files, |path| {
+                !path.with_extension("json").exists()
+            }))
+        }
+        StorageCategory::ProviderCache => {
+            let cache = paths::app_data_dir()?.join(models::CACHE_FILENAME);
+            let files = if cache.exists() {
+                vec![cache]
+            } else {
+                Vec::new()
+            };
+            Ok(Inventory::new(files, |_| true))
+        }
+    }
+}
+
+/// Report the space each category uses and delete what can go
+pub fn clean(options: &CleanOptions) -> Result<StorageReport, String> {
+    let app_data_bytes = dir_size(&paths::app_data_dir()?);
+    let categories = if options.categories.is_empty() {
+        ALL_CATEGORIES.to_vec()
+    } else {
+        options.categories.clone()
+    };
+    let max_age_days = options.older_than_days.unwrap_or(DEFAULT_MAX_AGE_DAYS);
+    let cutoff_ms = chrono_lite_timestamp().saturating_sub(max_age_days.saturating_mul(DAY_MS));
+
+    let mut reports = Vec::new();
+    let mut errors = Vec::new();
+    let mut reclaimed_bytes = 0;
+    for category in categories {
+        let inventory = inventory(category, cutoff_ms)?;
+        let mut report = CategoryReport {
+            category,
+            total_bytes: inventory.files.iter().map(|(_, size)| size).sum(),
+            reclaimable_bytes: inventory
+                .removable
+                .iter()
+                .map(|path| inventory.size_of(path))
+                .sum(),
+            reclaimable_files: inventory.removable.len(),
+            removed_files: 0,
+        };
+        if !options.dry_run {
+            for path in &inventory.removable {
+                match fs::remove_file(path) {
+                    Ok(()) => {
+                        report.removed_files += 1;
+                        reclaimed_bytes += inventory.size_of(path);
+                    }
+                    Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+                }
+            }
+        }
+        reports.push(report);
+    }
+
+    if !options.dry_run {
+        println!(
+            "[Stud Storage] Reclaimed {} bytes ({} file(s) could not be removed)",
+            reclaimed_bytes,
+            errors.len()
+        );
+    }
+    Ok(StorageReport {
+        dry_run: options.dry_run,
+        app_data_bytes,
+        categories: reports,
+        reclaimed_bytes,
+        errors,
+    })
+}
+
+/// Report disk use under app data and, unless it's a dry run, reclaim space
+#[tauri::command]
+pub fn clean_storage(options: Option<CleanOptions>) -> Result<StorageReport, String> {
+    clean(&options.unwrap_or_default())
+}
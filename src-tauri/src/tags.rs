@@ -0,0 +1,250 @@
+//! CollectionService Tags
+//!
+//! Lists, adds and removes tags through the bridge. Bulk operations ("tag every
+//! part under Workspace.Lava with Damage") are planned here: the plugin reports
+//! the candidates and their current tags, and only instances that actually need
+//! a change are sent back. A registry of the place's tags, with the scripts that
+//! look them up, is cached so the AI can see which systems are tag-driven.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::bridge::{self, chrono_lite_timestamp};
+
+/// Tag changes sent to Studio per request
+const APPLY_BATCH_SIZE: usize = 200;
+
+lazy_static::lazy_static! {
+    static ref TAG_REGISTRY: Mutex<Option<TagRegistry>> = Mutex::new(None);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagInfo {
+    pub tag: String,
+    pub count: usize,
+    #[serde(rename = "classCounts", default)]
+    pub class_counts: HashMap<String, usize>,
+    /// A few tagged instance paths
+    #[serde(default)]
+    pub samples: Vec<String>,
+    /// Scripts that reference the tag by name
+    #[serde(default)]
+    pub scripts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagRegistry {
+    pub tags: Vec<TagInfo>,
+    /// Compact text summary for prompts
+    pub summary: String,
+    pub refreshed_at: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TagOperation {
+    pub tag: String,
+    /// Explicit instances to change
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Also change `root` and everything under it
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Only instances that are (or inherit from) this class, e.g. "BasePart"
+    #[serde(default)]
+    pub class_name: Option<String>,
+    /// Only instances whose name contains this text (case-insensitive)
+    #[serde(default)]
+    pub name_contains: Option<String>,
+    #[serde(default)]
+    pub remove: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagResult {
+    pub tag: String,
+    pub changed: Vec<String>,
+    /// Matching instances that already had (or lacked) the tag
+    pub unchanged: usize,
+    pub missing: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ListResponse {
+    #[serde(default)]
+    tags: Vec<TagInfo>,
+}
+
+#[derive(Deserialize)]
+struct InstanceTags {
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Candidate {
+    path: String,
+    name: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CandidatesResponse {
+    #[serde(default)]
+    instances: Vec<Candidate>,
+}
+
+#[derive(Deserialize)]
+struct ApplyResponse {
+    #[serde(default)]
+    missing: Vec<String>,
+}
+
+fn summarize(tags: &[TagInfo]) -> String {
+    if tags.is_empty() {
+        return "No CollectionService tags in this place.".to_string();
+    }
+    let mut summary = String::from("CollectionService tags:\n");
+    for info in tags {
+        let mut classes: Vec<(&String, &usize)> = info.class_counts.iter().collect();
+        classes.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        let classes: Vec<String> = classes
+            .iter()
+            .map(|(class_name, count)| format!("{} {}", count, class_name))
+            .collect();
+        summary.push_str(&format!("- {}: {} tagged", info.tag, info.count));
+        if !classes.is_empty() {
+            summary.push_str(&format!(" ({})", classes.join(", ")));
+        }
+        if !info.scripts.is_empty() {
+            summary.push_str(&format!("; used by {}", info.scripts.join(", ")));
+        }
+        summary.push('\n');
+    }
+    summary
+}
+
+async fn refresh_registry(session: Option<&str>) -> Result<TagRegistry, String> {
+    let result =
+        bridge::studio_request_background(session, "/tags/list", serde_json::json!({})).await?;
+    let mut response: ListResponse = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+    response
+        .tags
+        .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+
+    let registry = TagRegistry {
+        summary: summarize(&response.tags),
+        tags: response.tags,
+        refreshed_at: chrono_lite_timestamp(),
+    };
+    *TAG_REGISTRY.lock() = Some(registry.clone());
+    Ok(registry)
+}
+
+/// Every tag in the place, from the cache unless `refresh` is set
+#[tauri::command]
+pub async fn list_tags(
+    refresh: Option<bool>,
+    session: Option<String>,
+) -> Result<TagRegistry, String> {
+    if !refresh.unwrap_or(false) {
+        if let Some(registry) = TAG_REGISTRY.lock().clone() {
+            return Ok(registry);
+        }
+    }
+    refresh_registry(session.as_deref()).await
+}
+
+/// Tags on a single instance
+#[tauri::command]
+pub async fn get_instance_tags(
+    path: String,
+    session: Option<String>,
+) -> Result<Vec<String>, String> {
+    let result = bridge::studio_request(
+        session.as_deref(),
+        "/tags/list",
+        serde_json::json!({ "path": path }),
+    )
+    .await?;
+    let response: InstanceTags = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+    Ok(response.tags)
+}
+
+/// Add or remove a tag on explicit paths and/or everything matching under a root
+#[tauri::command]
+pub async fn update_tags(
+    operation: TagOperation,
+    session: Option<String>,
+) -> Result<TagResult, String> {
+    let tag = operation.tag.trim().to_string();
+    if tag.is_empty() {
+        return Err("Tag name is required".to_string());
+    }
+    if operation.paths.is_empty() && operation.root.is_none() {
+        return Err("Specify paths or a root to tag".to_string());
+    }
+
+    let mut targets: Vec<String> = operation.paths.clone();
+    let mut unchanged = 0;
+    if let Some(root) = &operation.root {
+        let result = bridge::studio_request(
+            session.as_deref(),
+            "/tags/candidates",
+            serde_json::json!({ "root": root, "className": operation.class_name }),
+        )
+        .await?;
+        let response: CandidatesResponse = serde_json::from_value(result)
+            .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+
+        let name_filter = operation.name_contains.as_deref().map(str::to_lowercase);
+        for candidate in response.instances {
+            if let Some(filter) = &name_filter {
+                if !candidate.name.to_lowercase().contains(filter) {
+                    continue;
+                }
+            }
+            if candidate.tags.contains(&tag) != operation.remove {
+                unchanged += 1;
+                continue;
+            }
+            if !targets.contains(&candidate.path) {
+                targets.push(candidate.path);
+            }
+        }
+    }
+
+    let mut missing = Vec::new();
+    for batch in targets.chunks(APPLY_BATCH_SIZE) {
+        let operations: Vec<serde_json::Value> = batch
+            .iter()
+            .map(|path| serde_json::json!({ "path": path, "tag": tag, "remove": operation.remove }))
+            .collect();
+        let result = bridge::studio_request(
+            session.as_deref(),
+            "/tags/apply",
+            serde_json::json!({ "operations": operations }),
+        )
+        .await?;
+        let response: ApplyResponse = serde_json::from_value(result)
+            .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+        missing.extend(response.missing);
+    }
+
+    // Counts changed; rebuild on next use
+    *TAG_REGISTRY.lock() = None;
+
+    let changed = targets
+        .into_iter()
+        .filter(|path| !missing.contains(path))
+        .collect();
+    Ok(TagResult {
+        tag,
+        changed,
+        unchanged,
+        missing,
+    })
+}
@@ -0,0 +1,223 @@
+//! Instance Template Library
+//!
+//! A local prefab collection: instances serialized by Studio (rbxm) are stored
+//! in the app data folder with a small JSON metadata file next to each one,
+//! so templates can be shared by copying the folder.
+//!
+//! Saving serializes the current Studio selection through the bridge, and
+//! inserting sends the stored rbxm back to the plugin to deserialize.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::bridge::{self, chrono_lite_timestamp, StudioPayload};
+use crate::paths;
+
+const TEMPLATES_DIR: &str = "templates";
+const RBXM_CONTENT_TYPE: &str = "application/x-rbxm";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Names and classes of the top-level instances in the template
+    #[serde(default)]
+    pub instances: Vec<TemplateInstance>,
+    pub size_bytes: u64,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateInstance {
+    pub name: String,
+    #[serde(rename = "className")]
+    pub class_name: String,
+}
+
+// Response from the plugin's /template/serialize handler
+#[derive(Deserialize)]
+struct SerializedSelection {
+    data: String,
+    #[serde(default)]
+    instances: Vec<TemplateInstance>,
+}
+
+pub(crate) fn templates_dir() -> Result<PathBuf, String> {
+    let dir = paths::app_data_dir()?.join(TEMPLATES_DIR);
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create templates folder: {}", e))?;
+    }
+    Ok(dir)
+}
+
+/// File-safe version of a template name
+fn slug(name: &str) -> String {
+    let slug: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    slug.split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn template_paths(name: &str) -> Result<(PathBuf, PathBuf), String> {
+    let slug = slug(name);
+    if slug.is_empty() {
+        return Err("Template name must contain letters or numbers".to_string());
+    }
+    let dir = templates_dir()?;
+    Ok((
+        dir.join(format!("{}.json", slug)),
+        dir.join(format!("{}.rbxm", slug)),
+    ))
+}
+
+fn read_info(name: &str) -> Result<TemplateInfo, String> {
+    let (meta_path, _) = template_paths(name)?;
+    let source = fs::read_to_string(&meta_path)
+        .map_err(|_| format!("Template not found: {}", name))?;
+    serde_json::from_str(&source).map_err(|e| format!("Invalid template metadata: {}", e))
+}
+
+/// List saved templates, optionally only those with a given tag
+#[tauri::command]
+pub fn list_templates(tag: Option<String>) -> Result<Vec<TemplateInfo>, String> {
+    let entries =
+        fs::read_dir(templates_dir()?).map_err(|e| format!("Failed to read templates: {}", e))?;
+
+    let mut templates: Vec<TemplateInfo> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|source| serde_json::from_str::<TemplateInfo>(&source).ok())
+        .filter(|info| match &tag {
+            Some(tag) => info.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            None => true,
+        })
+        .collect();
+
+    templates.sort_by_key(|info| info.name.to_lowercase());
+    Ok(templates)
+}
+
+/// Serialize the current Studio selection and save it as a named template
+#[tauri::command]
+pub async fn save_selection_as_template(
+    name: String,
+    description: Option<String>,
+    tags: Option<Vec<String>>,
+    session: Option<String>,
+) -> Result<TemplateInfo, String> {
+    let (meta_path, data_path) = template_paths(&name)?;
+
+    let result = bridge::studio_request(
+        session.as_deref(),
+        "/template/serialize",
+        serde_json::json!({}),
+    )
+    .await?;
+    let selection: SerializedSelection = serde_json::from_value(result)
+        .map_err(|e| format!("Unexpected response from Studio: {}", e))?;
+    if selection.instances.is_empty() {
+        return Err("Nothing is selected in Studio".to_string());
+    }
+
+    let data = BASE64
+        .decode(selection.data.as_bytes())
+        .map_err(|e| format!("Studio sent invalid template data: {}", e))?;
+
+    let now = chrono_lite_timestamp();
+    let created_at = read_info(&name).map(|info| info.created_at).unwrap_or(now);
+    let info = TemplateInfo {
+        name: name.trim().to_string(),
+        description: description.unwrap_or_default(),
+        tags: tags.unwrap_or_default(),
+        instances: selection.instances,
+        size_bytes: data.len() as u64,
+        created_at,
+        updated_at: now,
+    };
+
+    fs::write(&data_path, &data).map_err(|e| format!("Failed to write template: {}", e))?;
+    let json = serde_json::to_string_pretty(&info)
+        .map_err(|e| format!("Failed to serialize template metadata: {}", e))?;
+    fs::write(&meta_path, json).map_err(|e| format!("Failed to write template metadata: {}", e))?;
+
+    Ok(info)
+}
+
+/// Insert a saved template into Studio (under `parent`, or Workspace by default)
+#[tauri::command]
+pub async fn insert_template(
+    name: String,
+    parent: Option<String>,
+    session: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let info = read_info(&name)?;
+    let (_, data_path) = template_paths(&name)?;
+    let data = fs::read(&data_path).map_err(|e| format!("Failed to read template: {}", e))?;
+
+    let result = bridge::studio_request_binary(
+        session.as_deref(),
+        "/template/insert",
+        serde_json::json!({
+            "name": info.name,
+            "parent": parent.unwrap_or_else(|| "game.Workspace".to_string()),
+        }),
+        Some((RBXM_CONTENT_TYPE, &data)),
+    )
+    .await?;
+
+    match result {
+        StudioPayload::Json(value) => Ok(value),
+        StudioPayload::Binary { .. } => Err("Unexpected binary response from Studio".to_string()),
+    }
+}
+
+/// Save a single instance from Studio to an .rbxm file
+#[tauri::command]
+pub async fn export_instance(
+    path: String,
+    destination: String,
+    session: Option<String>,
+) -> Result<u64, String> {
+    let result = bridge::studio_request_binary(
+        session.as_deref(),
+        "/instance/serialize",
+        serde_json::json!({ "path": path }),
+        None,
+    )
+    .await?;
+
+    let StudioPayload::Binary { data, .. } = result else {
+        return Err("Studio did not return model data".to_string());
+    };
+    fs::write(&destination, &data).map_err(|e| format!("Failed to write {}: {}", destination, e))?;
+    Ok(data.len() as u64)
+}
+
+/// Delete a saved template
+#[tauri::command]
+pub fn delete_template(name: String) -> Result<(), String> {
+    let (meta_path, data_path) = template_paths(&name)?;
+    if !meta_path.exists() {
+        return Err(format!("Template not found: {}", name));
+    }
+    fs::remove_file(&meta_path).map_err(|e| format!("Failed to delete template: {}", e))?;
+    if data_path.exists() {
+        fs::remove_file(&data_path).map_err(|e| format!("Failed to delete template: {}", e))?;
+    }
+    Ok(())
+}
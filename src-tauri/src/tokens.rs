@@ -0,0 +1,743 @@
+//! OAuth Tokens
+//!
+//! Sign-in tokens are handled here and never reach the webview. The callback
+//! server hands over the authorization code, the code is exchanged here with
+//! the flow's PKCE verifier, and the refresh token is saved to the OS keychain
+//! (see `vault`). Access tokens are only held in memory, so each account is
+//! refreshed once after launch; the accounts themselves (IDs, emails, which is
+//! active) are listed in the app data folder. The Codex proxy adds the tokens
+//! to the requests it forwards. A background task refreshes each access token
+//! shortly before it expires, so a long session doesn't hit a 401
+//! mid-generation. The frontend only learns whether it's signed in, to which
+//! account, and when the token was refreshed.
+//!
+//! Tokens are kept per provider (see `oauth_providers`), and within a provider
+//! several accounts (say, a personal and a team one) can be signed in at once,
+//! keyed by account ID. Codex requests use the active ChatGPT account unless
+//! they name another with the ChatGPT-Account-Id header.
+//!
+//! Earlier versions saved whole token sets to `oauth-tokens.json`; that file is
+//! moved into the keychain on first load and then deleted.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::auth::AuthService;
+use crate::bridge::{self, chrono_lite_timestamp};
+use crate::net;
+use crate::oauth_providers::OAuthProvider;
+use crate::paths;
+use crate::vault;
+
+const ACCOUNTS_FILENAME: &str = "oauth-accounts.json";
+/// Where earlier versions kept the tokens in plain text
+const LEGACY_TOKENS_FILENAME: &str = "oauth-tokens.json";
+/// Key for tokens whose response names no account
+const DEFAULT_ACCOUNT: &str = "default";
+/// Access tokens this close to expiring are refreshed before use
+const REFRESH_MARGIN_MS: u64 = 5 * 60 * 1000;
+/// Wait before trying a failed background refresh again
+const REFRESH_RETRY: Duration = Duration::from_secs(60);
+
+lazy_static::lazy_static! {
+    static ref TOKENS: Mutex<Providers> = Mutex::new(load());
+    /// Held while saving, so saves land in order without holding TOKENS
+    static ref SAVING: Mutex<()> = Mutex::new(());
+    /// Held while refreshing, so concurrent requests don't spend the same refresh token twice
+    static ref REFRESHING: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+    /// Wakes the background refresher when the tokens change
+    static ref CHANGED: tokio::sync::Notify = tokio::sync::Notify::new();
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct StoredTokens {
+    /// Memory only; empty until the first refresh after launch
+    #[serde(default, skip_serializing)]
+    access: String,
+    /// Kept in the keychain; only read from disk when migrating the legacy file
+    #[serde(default, skip_serializing)]
+    refresh: String,
+    /// Unix time in milliseconds
+    expires: u64,
+    #[serde(default)]
+    account_id: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+}
+
+/// Every signed-in account of one provider
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct TokenStore {
+    /// Used by requests that don't name an account
+    active: Option<String>,
+    accounts: BTreeMap<String, StoredTokens>,
+}
+
+impl TokenStore {
+    fn active(&self) -> Option<(&String, &StoredTokens)> {
+        let key = self.active.as_ref()?;
+        self.accounts.get_key_value(key)
+    }
+}
+
+/// Every provider's accounts; on disk without their tokens
+type Providers = BTreeMap<OAuthProvider, TokenStore>;
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    expires_in: u64,
+    #[serde(default)]
+    id_token: Option<String>,
+    /// Anthropic names the account here instead of in an ID token
+    #[serde(default)]
+    account: Option<serde_json::Value>,
+}
+
+/// What the frontend may know about a signed-in account
+#[derive(Debug, Clone, Serialize)]
+pub struct OAuthStatus {
+    pub provider: OAuthProvider,
+    pub account_id: Option<String>,
+    pub email: Option<String>,
+    /// When the current access token expires, in Unix milliseconds
+    pub expires: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OAuthAccount {
+    /// Key to switch to or remove the account by
+    pub key: String,
+    #[serde(flatten)]
+    pub status: OAuthStatus,
+    pub active: bool,
+}
+
+/// The token and account header to send with a Codex request
+pub(crate) struct Credentials {
+    pub access: String,
+    pub account_id: Option<String>,
+}
+
+fn accounts_path() -> Result<PathBuf, String> {
+    Ok(paths::app_data_dir()?.join(ACCOUNTS_FILENAME))
+}
+
+/// Keychain name for an account's refresh token
+fn vault_name(provider: OAuthProvider, key: &str) -> String {
+    format!("{:?}-{}", provider, key).to_lowercase()
+}
+
+fn load() -> Providers {
+    if let Some(providers) = migrate_legacy() {
+        return providers;
+    }
+    let Some(mut providers) = accounts_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|source| serde_json::from_str::<Providers>(&source).ok())
+    else {
+        return Providers::new();
+    };
+    for (provider, store) in providers.iter_mut() {
+        store.accounts.retain(|key, tokens| {
+            match vault::refresh_token(&vault_name(*provider, key)) {
+                Ok(Some(refresh)) => {
+                    tokens.refresh = refresh;
+                    // No access token until the first refresh
+                    tokens.expires = 0;
+                    true
+                }
+                Ok(None) => false,
+                Err(e) => {
+                    println!(
+                        "[Stud OAuth] Dropping {} account {}: {}",
+                        provider.name(),
+                        key,
+                        e
+                    );
+                    false
+                }
+            }
+        });
+        if !store
+            .active
+            .as_ref()
+            .is_some_and(|key| store.accounts.contains_key(key))
+        {
+            store.active = store.accounts.keys().next().cloned();
+        }
+    }
+    providers
+}
+
+/// Move tokens from the plain-text file earlier versions wrote into the
+/// keychain, then delete the file. None if there's no such file.
+fn migrate_legacy() -> Option<Providers> {
+    let path = paths::app_data_dir().ok()?.join(LEGACY_TOKENS_FILENAME);
+    migrate(&path, save)
+}
+
+fn migrate(path: &Path, save: impl FnOnce(&Providers) -> Result<(), String>) -> Option<Providers> {
+    let source = fs::read_to_string(path).ok()?;
+    let providers = parse_legacy(&source);
+    match save(&providers) {
+        Ok(()) => {
+            if let Err(e) = fs::remove_file(path) {
+                println!(
+                    "[Stud OAuth] Failed to remove {}: {}",
+                    LEGACY_TOKENS_FILENAME, e
+                );
+            } else {
+                println!("[Stud OAuth] Moved saved tokens into the keychain");
+            }
+        }
+        // Left in place to try again next launch
+        Err(e) => println!(
+            "[Stud OAuth] Failed to move saved tokens into the keychain: {}",
+            e
+        ),
+    }
+    Some(providers)
+}
+
+fn parse_legacy(source: &str) -> Providers {
+    if let Ok(providers) = serde_json::from_str(source) {
+        return providers;
+    }
+    // Saved before other providers: ChatGPT accounts only
+    if let Ok(store) = serde_json::from_str(source) {
+        return Providers::from([(OAuthProvider::ChatGpt, store)]);
+    }
+    // Saved before multiple accounts: one set of tokens
+    match serde_json::from_str::<StoredTokens>(source) {
+        Ok(tokens) => {
+            let key = account_key(tokens.account_id.as_deref());
+            let store = TokenStore {
+                active: Some(key.clone()),
+                accounts: BTreeMap::from([(key, tokens)]),
+            };
+            Providers::from([(OAuthProvider::ChatGpt, store)])
+        }
+        Err(_) => Providers::new(),
+    }
+}
+
+/// Save the account list, and each account's refresh token to the keychain.
+/// Removed accounts' refresh tokens are deleted by whoever removes them.
+fn save(providers: &Providers) -> Result<(), String> {
+    for (provider, store) in providers {
+        for (key, tokens) in &store.accounts {
+            vault::set_refresh_token(&vault_name(*provider, key), &tokens.refresh)?;
+        }
+    }
+    let path = accounts_path()?;
+    if providers.values().all(|store| store.accounts.is_empty()) {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to remove the account list: {}", e))
+            }
+            _ => Ok(()),
+        };
+    }
+    let json = serde_json::to_string(providers)
+        .map_err(|e| format!("Failed to serialize accounts: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to save accounts: {}", e))
+}
+
+/// Save the accounts as they are now. Keychain calls can wait on a prompt, so
+/// they're made on a copy rather than with TOKENS held; each save copies the
+/// latest accounts, so the last one to finish writes the newest.
+fn persist() -> Result<(), String> {
+    let _saving = SAVING.lock();
+    let providers = TOKENS.lock().clone();
+    save(&providers)
+}
+
+/// Delete a removed account's refresh token from the keychain
+fn forget(provider: OAuthProvider, key: &str) {
+    if let Err(e) = vault::delete_refresh_token(&vault_name(provider, key)) {
+        println!("[Stud OAuth] {}", e);
+    }
+}
+
+fn account_key(account_id: Option<&str>) -> String {
+    account_id.unwrap_or(DEFAULT_ACCOUNT).to_string()
+}
+
+fn status_of(provider: OAuthProvider, tokens: &StoredTokens) -> OAuthStatus {
+    OAuthStatus {
+        provider,
+        account_id: tokens.account_id.clone(),
+        email: tokens.email.clone(),
+        expires: tokens.expires,
+    }
+}
+
+/// Post a token request; the client's ID (and secret, if any) are added here
+async fn request_tokens(
+    provider: OAuthProvider,
+    params: &[(&str, &str)],
+) -> Result<TokenResponse, String> {
+    let client_id = provider.client_id()?;
+    let client_secret = provider.client_secret();
+    let mut params: BTreeMap<&str, &str> = params.iter().copied().collect();
+    params.insert("client_id", &client_id);
+    if let Some(secret) = &client_secret {
+        params.insert("client_secret", secret);
+    }
+
+    let request = net::client().post(provider.token_url());
+    let request = if provider.json_token_requests() {
+        request.json(&params)
+    } else {
+        request.form(&params)
+    };
+    let response = request.send().await.map_err(|e| {
+        format!(
+            "Failed to reach the {} token endpoint. {}",
+            provider.name(),
+            net::describe(&e)
+        )
+    })?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Token request failed ({}): {}", status, body));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid token response: {}", e))
+}
+
+/// Store a token response, keeping what it leaves out from the previous tokens.
+/// An account seen for the first time becomes the active one if none is. A
+/// refresh whose account was signed out while it ran is dropped.
+fn store(
+    provider: OAuthProvider,
+    response: TokenResponse,
+    previous: Option<&StoredTokens>,
+) -> Result<OAuthStatus, String> {
+    let refresh = response
+        .refresh_token
+        .or_else(|| previous.map(|tokens| tokens.refresh.clone()))
+        .ok_or_else(|| "Token response had no refresh token".to_string())?;
+    let identity = provider.identity(response.id_token.as_deref(), response.account.as_ref());
+    let tokens = StoredTokens {
+        access: response.access_token,
+        refresh,
+        expires: chrono_lite_timestamp() + response.expires_in * 1000,
+        account_id: identity
+            .account_id
+            .or_else(|| previous.and_then(|tokens| tokens.account_id.clone())),
+        email: identity
+            .email
+            .or_else(|| previous.and_then(|tokens| tokens.email.clone())),
+    };
+    let status = status_of(provider, &tokens);
+    let key = account_key(tokens.account_id.as_deref());
+
+    let mut providers = TOKENS.lock();
+    let store = providers.entry(provider).or_default();
+    if let Some(previous) = previous {
+        let previous_key = account_key(previous.account_id.as_deref());
+        let still_signed_in = store
+            .accounts
+            .get(&previous_key)
+            .is_some_and(|tokens| tokens.refresh == previous.refresh);
+        if !still_signed_in {
+            return Err(format!(
+                "{} was signed out during the refresh",
+                previous_key
+            ));
+        }
+    }
+    store.accounts.insert(key.clone(), tokens);
+    store.active.get_or_insert(key);
+    drop(providers);
+    persist()?;
+    CHANGED.notify_one();
+    Ok(status)
+}
+
+/// Exchange a sign-in's authorization code for tokens and keep them. The
+/// account signed in to becomes the active one.
+/// `redirect_uri` must be the one the authorization request named.
+pub(crate) async fn exchange_code(
+    provider: OAuthProvider,
+    code: &str,
+    verifier: &str,
+    redirect_uri: &str,
+    state: &str,
+) -> Result<OAuthStatus, String> {
+    let mut params = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", redirect_uri),
+        ("code_verifier", verifier),
+    ];
+    if provider.exchange_includes_state() {
+        params.push(("state", state));
+    }
+    let response = request_tokens(provider, &params).await?;
+    let status = store(provider, response, None)?;
+    TOKENS.lock().entry(provider).or_default().active =
+        Some(account_key(status.account_id.as_deref()));
+    persist()?;
+    println!("[Stud OAuth] Signed in to {}", provider.name());
+    Ok(status)
+}
+
+fn stored(provider: OAuthProvider, key: &str) -> Option<StoredTokens> {
+    TOKENS.lock().get(&provider)?.accounts.get(key).cloned()
+}
+
+async fn refresh(provider: OAuthProvider, key: &str) -> Result<(), String> {
+    let _refreshing = REFRESHING.lock().await;
+    // Another request may have refreshed while this one waited
+    let Some(previous) = stored(provider, key) else {
+        return Err(format!(
+            "{} account {} is not signed in",
+            provider.name(),
+            key
+        ));
+    };
+    if previous.expires > chrono_lite_timestamp() + REFRESH_MARGIN_MS {
+        return Ok(());
+    }
+    let response = request_tokens(
+        provider,
+        &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &previous.refresh),
+        ],
+    )
+    .await?;
+    let status = store(provider, response, Some(&previous))?;
+    println!(
+        "[Stud OAuth] Access token refreshed for {} account {}",
+        provider.name(),
+        key
+    );
+    bridge::emit_event("oauth-refreshed", status);
+    Ok(())
+}
+
+/// Refresh each account's access token in the background shortly before it
+/// expires. Runs for the life of the app; sleeps while signed out.
+pub async fn run_refresher() {
+    loop {
+        let next = TOKENS
+            .lock()
+            .iter()
+            .flat_map(|(provider, store)| {
+                store
+                    .accounts
+                    .iter()
+                    .map(move |(key, tokens)| (*provider, key.clone(), tokens.expires))
+            })
+            .min_by_key(|(_, _, expires)| *expires);
+        let Some((provider, key, expires)) = next else {
+            CHANGED.notified().await;
+            continue;
+        };
+        let due = expires.saturating_sub(REFRESH_MARGIN_MS + chrono_lite_timestamp());
+        tokio::select! {
+            _ = CHANGED.notified() => continue,
+            _ = tokio::time::sleep(Duration::from_millis(due)) => {}
+        }
+
+        if let Err(e) = refresh(provider, &key).await {
+            println!("[Stud OAuth] Background refresh failed: {}", e);
+            bridge::emit_event(
+                "oauth-refresh-failed",
+                serde_json::json!({
+                    "provider": provider,
+                    "account": key,
+                    "error": e,
+                    "expires": expires,
+                }),
+            );
+            tokio::select! {
+                _ = CHANGED.notified() => {}
+                _ = tokio::time::sleep(REFRESH_RETRY) => {}
+            }
+        }
+    }
+}
+
+/// A usable access token for a provider's `account` (the active account if
+/// None), refreshed first if it's about to expire
+pub(crate) async fn credentials(
+    provider: OAuthProvider,
+    account: Option<&str>,
+) -> Result<Credentials, String> {
+    let name = provider.name();
+    let (key, expired) = {
+        let providers = TOKENS.lock();
+        let found = providers.get(&provider).and_then(|store| match account {
+            Some(account) => store.accounts.get_key_value(account),
+            None => store.active(),
+        });
+        match found {
+            Some((key, tokens)) => (
+                key.clone(),
+                tokens.expires <= chrono_lite_timestamp() + REFRESH_MARGIN_MS,
+            ),
+            None => {
+                return Err(match account {
+                    Some(account) => format!("{} account {} is not signed in", name, account),
+                    None => format!("Not signed in to {}", name),
+                })
+            }
+        }
+    };
+    if expired {
+        refresh(provider, &key)
+            .await
+            .map_err(|e| format!("Failed to refresh {} sign-in: {}", name, e))?;
+    }
+    stored(provider, &key)
+        .map(|tokens| Credentials {
+            access: tokens.access,
+            account_id: tokens.account_id,
+        })
+        .ok_or_else(|| format!("Not signed in to {}", name))
+}
+
+/// Whether sign-in to a provider (ChatGPT if not given) is active, and for
+/// which account
+#[tauri::command]
+pub fn get_oauth_status(provider: Option<OAuthProvider>) -> Option<OAuthStatus> {
+    let provider = provider.unwrap_or_default();
+    let providers = TOKENS.lock();
+    let (_, tokens) = providers.get(&provider)?.active()?;
+    Some(status_of(provider, tokens))
+}
+
+/// Every account signed in to a provider (ChatGPT if not given)
+#[tauri::command]
+pub fn list_accounts(provider: Option<OAuthProvider>) -> Vec<OAuthAccount> {
+    let provider = provider.unwrap_or_default();
+    let providers = TOKENS.lock();
+    let Some(store) = providers.get(&provider) else {
+        return Vec::new();
+    };
+    store
+        .accounts
+        .iter()
+        .map(|(key, tokens)| OAuthAccount {
+            key: key.clone(),
+            status: status_of(provider, tokens),
+            active: store.active.as_ref() == Some(key),
+        })
+        .collect()
+}
+
+/// Make an account the one requests to its provider use by default
+#[tauri::command]
+pub fn switch_account(key: String, provider: Option<OAuthProvider>) -> Result<OAuthStatus, String> {
+    let provider = provider.unwrap_or_default();
+    let mut providers = TOKENS.lock();
+    let store = providers.entry(provider).or_default();
+    let status = store
+        .accounts
+        .get(&key)
+        .map(|tokens| status_of(provider, tokens))
+        .ok_or_else(|| format!("{} account {} is not signed in", provider.name(), key))?;
+    store.active = Some(key);
+    drop(providers);
+    persist()?;
+    Ok(status)
+}
+
+/// Revoke a refresh token with its provider, where the provider supports it.
+/// Best effort: the tokens are forgotten locally either way.
+async fn revoke(provider: OAuthProvider, tokens: &StoredTokens) {
+    let Some(url) = provider.revoke_url() else {
+        return;
+    };
+    let result = net::client()
+        .post(url)
+        .form(&[("token", tokens.refresh.as_str())])
+        .send()
+        .await;
+    match result {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => println!(
+            "[Stud OAuth] {} refused to revoke a token ({})",
+            provider.name(),
+            response.status()
+        ),
+        Err(e) => println!(
+            "[Stud OAuth] Failed to revoke a {} token: {}",
+            provider.name(),
+            e
+        ),
+    }
+}
+
+/// Forget one account's tokens, revoking them where the provider allows. If
+/// it was the active account, another one takes its place.
+#[tauri::command]
+pub async fn remove_account(key: String, provider: Option<OAuthProvider>) -> Result<(), String> {
+    let provider = provider.unwrap_or_default();
+    let removed = {
+        let mut providers = TOKENS.lock();
+        let store = providers.entry(provider).or_default();
+        let Some(removed) = store.accounts.remove(&key) else {
+            return Err(format!(
+                "{} account {} is not signed in",
+                provider.name(),
+                key
+            ));
+        };
+        if store.active.as_ref() == Some(&key) {
+            store.active = store.accounts.keys().next().cloned();
+        }
+        removed
+    };
+    persist()?;
+    forget(provider, &key);
+    CHANGED.notify_one();
+    revoke(provider, &removed).await;
+    println!("[Stud OAuth] Removed {} account {}", provider.name(), key);
+    Ok(())
+}
+
+/// Sign out of every account of a provider (ChatGPT if not given), so a shared
+/// machine keeps nothing behind: refresh tokens are revoked where the provider
+/// allows, the saved tokens are deleted, sign-in results still held for the
+/// frontend are dropped, and the background refresher stops tracking them.
+#[tauri::command]
+pub async fn sign_out(
+    provider: Option<OAuthProvider>,
+    auth: tauri::State<'_, Arc<AuthService>>,
+) -> Result<(), String> {
+    let provider = provider.unwrap_or_default();
+    let removed = TOKENS.lock().remove(&provider);
+    persist()?;
+    CHANGED.notify_one();
+    auth.forget_results(provider);
+    for (key, tokens) in removed.iter().flat_map(|store| &store.accounts) {
+        forget(provider, key);
+        revoke(provider, tokens).await;
+    }
+    println!("[Stud OAuth] Signed out of {}", provider.name());
+    Ok(())
+}
+
+/// Take over tokens an older version kept in the webview, so upgrading doesn't
+/// sign the user out. An expired or missing access token is refreshed on first
+/// use; an account signed in since is left as it is.
+#[tauri::command]
+pub fn import_tokens(
+    refresh: String,
+    access: Option<String>,
+    expires: Option<u64>,
+    account_id: Option<String>,
+    provider: Option<OAuthProvider>,
+) -> Result<OAuthStatus, String> {
+    let provider = provider.unwrap_or_default();
+    if refresh.trim().is_empty() {
+        return Err("No refresh token to import".to_string());
+    }
+    let (access, expires) = match access {
+        Some(access) if !access.is_empty() => (access, expires.unwrap_or(0)),
+        _ => (String::new(), 0),
+    };
+    let key = account_key(account_id.as_deref());
+    let status = {
+        let mut providers = TOKENS.lock();
+        let store = providers.entry(provider).or_default();
+        let tokens = store.accounts.entry(key.clone()).or_insert(StoredTokens {
+            access,
+            refresh,
+            expires,
+            account_id,
+            email: None,
+        });
+        let status = status_of(provider, tokens);
+        store.active.get_or_insert(key);
+        status
+    };
+    persist()?;
+    CHANGED.notify_one();
+    println!(
+        "[Stud OAuth] Imported {} tokens saved by an older version",
+        provider.name()
+    );
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chatgpt(providers: &Providers) -> &TokenStore {
+        &providers[&OAuthProvider::ChatGpt]
+    }
+
+    #[test]
+    fn parses_every_legacy_format() {
+        let providers = parse_legacy(
+            r#"{"chatgpt": {"active": "acct", "accounts": {"acct": {"refresh": "r1", "expires": 5, "account_id": "acct"}}}}"#,
+        );
+        assert_eq!(chatgpt(&providers).active.as_deref(), Some("acct"));
+        assert_eq!(chatgpt(&providers).accounts["acct"].refresh, "r1");
+
+        // ChatGPT accounts only
+        let providers = parse_legacy(
+            r#"{"active": "acct", "accounts": {"acct": {"refresh": "r2", "expires": 5}}}"#,
+        );
+        assert_eq!(chatgpt(&providers).accounts["acct"].refresh, "r2");
+
+        // One set of tokens
+        let providers =
+            parse_legacy(r#"{"access": "a", "refresh": "r3", "expires": 5, "account_id": "acct"}"#);
+        let store = chatgpt(&providers);
+        assert_eq!(store.active.as_deref(), Some("acct"));
+        assert_eq!(store.accounts["acct"].access, "a");
+        assert_eq!(store.accounts["acct"].refresh, "r3");
+
+        assert!(parse_legacy("not json").is_empty());
+    }
+
+    fn legacy_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("stud-{}-{}", name, uuid::Uuid::new_v4()));
+        fs::write(&path, r#"{"refresh": "r", "expires": 5}"#).unwrap();
+        path
+    }
+
+    #[test]
+    fn migrated_file_is_deleted() {
+        let path = legacy_file("migrated");
+        let mut saved = None;
+        let providers = migrate(&path, |providers| {
+            saved = Some(chatgpt(providers).accounts.len());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(saved, Some(1));
+        assert_eq!(chatgpt(&providers).accounts.len(), 1);
+        assert!(!path.exists());
+        assert!(migrate(&path, |_| panic!("nothing to migrate")).is_none());
+    }
+
+    #[test]
+    fn failed_migration_keeps_the_file() {
+        let path = legacy_file("failed");
+        let providers = migrate(&path, |_| Err("keychain locked".to_string())).unwrap();
+        assert_eq!(chatgpt(&providers).accounts.len(), 1);
+        assert!(path.exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
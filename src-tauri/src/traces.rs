@@ -0,0 +1,147 @@
+//! Request Traces
+//!
+//! Every bridge request carries a trace ID, generated when it arrives at
+//! `/stud/request` (or passed in by the caller), sent to the plugin with the
+//! request and echoed back with its response. Each step the request passes
+//! through is stamped here against that ID, so the timeline of a slow tool
+//! call shows whether it waited on the guardrail checks, in the queue, in
+//! Studio or on the way back. Traces are kept in memory for recent requests only.
+
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::time::Instant;
+
+use crate::bridge::{chrono_lite_timestamp, StudioRequest};
+
+/// Traces kept; the oldest are dropped past this
+const MAX_TRACES: usize = 500;
+/// Steps kept per trace, so a request that's redelivered over and over stays bounded
+const MAX_STEPS: usize = 100;
+
+lazy_static::lazy_static! {
+    static ref TRACES: Mutex<IndexMap<String, TraceRecord>> = Mutex::new(IndexMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Arrived at /stud/request
+    Received,
+    /// Naming and attribute checks passed
+    Checked,
+    /// Waiting in the queue for the plugin
+    Queued,
+    /// Handed to the plugin by a poll or over the WebSocket
+    Delivered,
+    /// One part of a chunked response arrived
+    Chunk,
+    /// The plugin refused it, for now or for good
+    Nacked,
+    /// The plugin's response arrived
+    Responded,
+    /// The caller was answered
+    Completed,
+    Cancelled,
+    TimedOut,
+    /// Refused before reaching Studio
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceStep {
+    pub stage: Stage,
+    /// Unix ms
+    pub at: u64,
+    /// Since the first step
+    pub elapsed_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestTrace {
+    pub trace_id: String,
+    pub request_id: Option<String>,
+    pub path: String,
+    pub steps: Vec<TraceStep>,
+    /// Total so far, from the first step to the last
+    pub duration_ms: u64,
+}
+
+struct TraceRecord {
+    trace: RequestTrace,
+    started: Instant,
+}
+
+/// A new trace ID
+pub fn new_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Stamp a step on a trace, starting the trace if it's new
+pub(crate) fn mark(trace_id: &str, path: &str, stage: Stage, detail: Option<String>) {
+    let mut traces = TRACES.lock();
+    if !traces.contains_key(trace_id) {
+        if traces.len() >= MAX_TRACES {
+            traces.shift_remove_index(0);
+        }
+        traces.insert(
+            trace_id.to_string(),
+            TraceRecord {
+                trace: RequestTrace {
+                    trace_id: trace_id.to_string(),
+                    request_id: None,
+                    path: path.to_string(),
+                    steps: Vec::new(),
+                    duration_ms: 0,
+                },
+                started: Instant::now(),
+            },
+        );
+    }
+    let Some(record) = traces.get_mut(trace_id) else {
+        return;
+    };
+    if record.trace.steps.len() >= MAX_STEPS {
+        return;
+    }
+    let elapsed_ms = record.started.elapsed().as_millis() as u64;
+    record.trace.duration_ms = elapsed_ms;
+    record.trace.steps.push(TraceStep {
+        stage,
+        at: chrono_lite_timestamp(),
+        elapsed_ms,
+        detail,
+    });
+}
+
+/// Stamp a step on a request's trace, if it has one
+pub(crate) fn mark_request(request: &StudioRequest, stage: Stage, detail: Option<String>) {
+    if let Some(trace_id) = &request.trace_id {
+        mark(trace_id, &request.path, stage, detail);
+    }
+}
+
+/// Note the queue id a traced request was given
+pub(crate) fn set_request_id(trace_id: &str, request_id: &str) {
+    if let Some(record) = TRACES.lock().get_mut(trace_id) {
+        record.trace.request_id = Some(request_id.to_string());
+    }
+}
+
+/// The timeline of a request, by trace ID or by its request ID
+#[tauri::command]
+pub fn get_request_trace(id: String) -> Result<RequestTrace, String> {
+    let traces = TRACES.lock();
+    traces
+        .get(&id)
+        .or_else(|| {
+            traces
+                .values()
+                .rev()
+                .find(|record| record.trace.request_id.as_deref() == Some(id.as_str()))
+        })
+        .map(|record| record.trace.clone())
+        .ok_or_else(|| format!("No trace for {}", id))
+}
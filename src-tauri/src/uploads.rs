@@ -0,0 +1,320 @@
+//! Chunked Uploads
+//!
+//! A whole-place dump can run to tens of megabytes, more than the plugin can
+//! reliably send in one POST. It uploads such bodies to `/stud/upload` in parts
+//! instead, picking up from the last part the bridge has if a POST fails, and
+//! then answers the request with a reference to the finished upload,
+//! `{"stud_upload": "<id>"}`. The bridge swaps the reference for the uploaded
+//! blob before the caller sees the response. Uploads are kept in a temp folder
+//! until they're used or expire.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+use crate::bridge::StudioResponse;
+use crate::config;
+
+const UPLOAD_DIR: &str = "stud-uploads";
+/// Largest part accepted in one POST
+pub(crate) const MAX_CHUNK_BYTES: u64 = 8 * 1024 * 1024;
+/// Uploads untouched for this long are deleted
+const UPLOAD_EXPIRY: Duration = Duration::from_secs(10 * 60);
+/// Response field that stands for an upload's contents
+const REFERENCE_FIELD: &str = "stud_upload";
+/// References are tiny; anything longer is an ordinary response
+const MAX_REFERENCE_LEN: usize = 256;
+
+lazy_static::lazy_static! {
+    static ref UPLOADS: Mutex<HashMap<String, Upload>> = Mutex::new(HashMap::new());
+}
+
+struct Upload {
+    file: PathBuf,
+    received: u64,
+    expected: Option<u64>,
+    content_type: Option<String>,
+    request_id: Option<String>,
+    finalized: bool,
+    touched: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartUpload {
+    /// Total size, when known up front; finalizing checks it
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// Set for binary data; JSON otherwise
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// The request being answered, so its lease is kept while parts arrive
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadInfo {
+    pub upload_id: String,
+    /// Bytes received so far; the next part starts here
+    pub offset: u64,
+    pub size: Option<u64>,
+    pub finalized: bool,
+    #[serde(skip)]
+    pub request_id: Option<String>,
+}
+
+pub(crate) enum UploadError {
+    NotFound,
+    /// The part doesn't start where the upload left off
+    WrongOffset(u64),
+    TooLarge(u64),
+    Failed(String),
+}
+
+impl UploadError {
+    pub(crate) fn message(&self) -> String {
+        match self {
+            UploadError::NotFound => "Upload not found".to_string(),
+            UploadError::WrongOffset(offset) => format!("Upload continues at offset {}", offset),
+            UploadError::TooLarge(limit) => format!("Uploads are limited to {} bytes", limit),
+            UploadError::Failed(e) => e.clone(),
+        }
+    }
+}
+
+fn info(id: &str, upload: &Upload) -> UploadInfo {
+    UploadInfo {
+        upload_id: id.to_string(),
+        offset: upload.received,
+        size: upload.expected,
+        finalized: upload.finalized,
+        request_id: upload.request_id.clone(),
+    }
+}
+
+fn remove_expired(uploads: &mut HashMap<String, Upload>) {
+    uploads.retain(|_, upload| {
+        let keep = upload.touched.elapsed() < UPLOAD_EXPIRY;
+        if !keep {
+            let _ = fs::remove_file(&upload.file);
+        }
+        keep
+    });
+}
+
+/// Begin an upload
+pub(crate) fn start(request: StartUpload) -> Result<UploadInfo, UploadError> {
+    let limit = config::current().bridge.max_body_bytes;
+    if request.size.is_some_and(|size| size > limit) {
+        return Err(UploadError::TooLarge(limit));
+    }
+    let dir = std::env::temp_dir().join(UPLOAD_DIR);
+    fs::create_dir_all(&dir)
+        .map_err(|e| UploadError::Failed(format!("Failed to create upload folder: {}", e)))?;
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let file = dir.join(&id);
+    fs::File::create(&file)
+        .map_err(|e| UploadError::Failed(format!("Failed to create upload: {}", e)))?;
+
+    let upload = Upload {
+        file,
+        received: 0,
+        expected: request.size,
+        content_type: request.content_type,
+        request_id: request.request_id,
+        finalized: false,
+        touched: Instant::now(),
+    };
+    let mut uploads = UPLOADS.lock();
+    remove_expired(&mut uploads);
+    let info = info(&id, &upload);
+    uploads.insert(id, upload);
+    Ok(info)
+}
+
+/// Where an upload stands, so an interrupted one can be resumed
+pub(crate) fn status(id: &str) -> Result<UploadInfo, UploadError> {
+    UPLOADS
+        .lock()
+        .get(id)
+        .map(|upload| info(id, upload))
+        .ok_or(UploadError::NotFound)
+}
+
+/// Add a part starting at `offset`. A part that was already stored (a retry
+/// after its reply got lost) is accepted without being written again.
+pub(crate) fn append(id: &str, offset: u64, data: &[u8]) -> Result<UploadInfo, UploadError> {
+    let limit = config::current().bridge.max_body_bytes;
+    let mut uploads = UPLOADS.lock();
+    let upload = uploads.get_mut(id).ok_or(UploadError::NotFound)?;
+    if upload.finalized {
+        return Err(UploadError::Failed(
+            "Upload is already finalized".to_string(),
+        ));
+    }
+    let end = offset + data.len() as u64;
+    if offset != upload.received {
+        return if end == upload.received {
+            Ok(info(id, upload))
+        } else {
+            Err(UploadError::WrongOffset(upload.received))
+        };
+    }
+    if end > limit {
+        return Err(UploadError::TooLarge(limit));
+    }
+
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&upload.file)
+        .and_then(|mut file| file.write_all(data))
+        .map_err(|e| UploadError::Failed(format!("Failed to store upload part: {}", e)))?;
+    upload.received = end;
+    upload.touched = Instant::now();
+    Ok(info(id, upload))
+}
+
+/// Mark an upload complete so responses can refer to it
+pub(crate) fn finalize(id: &str) -> Result<UploadInfo, UploadError> {
+    let mut uploads = UPLOADS.lock();
+    let upload = uploads.get_mut(id).ok_or(UploadError::NotFound)?;
+    if let Some(expected) = upload.expected {
+        if upload.received != expected {
+            return Err(UploadError::Failed(format!(
+                "Upload has {} of {} bytes",
+                upload.received, expected
+            )));
+        }
+    }
+    upload.finalized = true;
+    upload.touched = Instant::now();
+    Ok(info(id, upload))
+}
+
+fn take(id: &str, status: u16) -> Result<StudioResponse, String> {
+    let upload = UPLOADS
+        .lock()
+        .remove(id)
+        .ok_or_else(|| format!("Upload {} not found", id))?;
+    let data = fs::read(&upload.file);
+    let _ = fs::remove_file(&upload.file);
+    if !upload.finalized {
+        return Err(format!("Upload {} was never finalized", id));
+    }
+    let data = data.map_err(|e| format!("Failed to read upload {}: {}", id, e))?;
+    Ok(match upload.content_type {
+        Some(content_type) => StudioResponse {
+            status,
+            body: BASE64.encode(data),
+            content_type: Some(content_type),
+        },
+        None => StudioResponse {
+            status,
+            body: String::from_utf8_lossy(&data).into_owned(),
+            content_type: None,
+        },
+    })
+}
+
+/// Swap a response that refers to an upload for the uploaded contents
+pub(crate) fn resolve(response: StudioResponse) -> StudioResponse {
+    if response.content_type.is_some() || response.body.len() > MAX_REFERENCE_LEN {
+        return response;
+    }
+    let Some(id) = serde_json::from_str::<serde_json::Value>(&response.body)
+        .ok()
+        .and_then(|body| body.get(REFERENCE_FIELD)?.as_str().map(str::to_string))
+    else {
+        return response;
+    };
+    take(&id, response.status).unwrap_or_else(|e| {
+        println!("[Stud Bridge] {}", e);
+        StudioResponse {
+            status: 502,
+            body: serde_json::json!({ "error": e }).to_string(),
+            content_type: None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unwrap(result: Result<UploadInfo, UploadError>) -> UploadInfo {
+        result.unwrap_or_else(|e| panic!("{}", e.message()))
+    }
+
+    fn offset(result: Result<UploadInfo, UploadError>) -> u64 {
+        unwrap(result).offset
+    }
+
+    fn start_upload(size: Option<u64>) -> String {
+        let info = unwrap(start(StartUpload {
+            size,
+            content_type: None,
+            request_id: None,
+        }));
+        assert_eq!(info.offset, 0);
+        info.upload_id
+    }
+
+    #[test]
+    fn parts_advance_the_offset() {
+        let id = start_upload(Some(6));
+        assert_eq!(offset(append(&id, 0, b"abc")), 3);
+        assert_eq!(offset(append(&id, 3, b"def")), 6);
+        assert_eq!(offset(status(&id)), 6);
+        assert!(finalize(&id).is_ok());
+        let response = resolve(StudioResponse {
+            status: 200,
+            body: format!("{{\"stud_upload\": \"{}\"}}", id),
+            content_type: None,
+        });
+        assert_eq!(response.body, "abcdef");
+    }
+
+    #[test]
+    fn resent_part_is_not_written_twice() {
+        let id = start_upload(None);
+        assert_eq!(offset(append(&id, 0, b"abc")), 3);
+        // Its reply got lost, so the plugin sends it again
+        assert_eq!(offset(append(&id, 0, b"abc")), 3);
+        assert_eq!(offset(append(&id, 3, b"d")), 4);
+        assert!(finalize(&id).is_ok());
+        assert_eq!(take(&id, 200).unwrap().body, "abcd");
+    }
+
+    #[test]
+    fn part_at_the_wrong_offset_is_refused() {
+        let id = start_upload(None);
+        assert_eq!(offset(append(&id, 0, b"abc")), 3);
+        assert!(matches!(
+            append(&id, 5, b"x"),
+            Err(UploadError::WrongOffset(3))
+        ));
+        assert!(matches!(
+            append(&id, 1, b"x"),
+            Err(UploadError::WrongOffset(3))
+        ));
+        assert_eq!(offset(status(&id)), 3);
+    }
+
+    #[test]
+    fn finalize_checks_the_size() {
+        let id = start_upload(Some(4));
+        assert_eq!(offset(append(&id, 0, b"abc")), 3);
+        assert!(matches!(finalize(&id), Err(UploadError::Failed(_))));
+        assert_eq!(offset(append(&id, 3, b"d")), 4);
+        assert!(finalize(&id).is_ok());
+        assert!(matches!(append(&id, 4, b"e"), Err(UploadError::Failed(_))));
+        assert!(matches!(status("missing"), Err(UploadError::NotFound)));
+    }
+}
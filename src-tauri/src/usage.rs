@@ -0,0 +1,328 @@
+//! Token Usage
+//!
+//! Token counts the provider proxy reads from the responses it forwards,
+//! added up per day, project, provider and model in a small SQLite database.
+//! The webview names the project (the connected place) in `X-Stud-Project`;
+//! usage sent without one is kept under no project.
+//!
+//! Every provider reports usage its own way, so responses are scanned for any
+//! of the shapes below. Streams report it in an event near the end, or spread
+//! over several (Anthropic sends input tokens first and output tokens last),
+//! so each count keeps the largest value seen.
+//!
+//! - OpenAI chat completions and OpenRouter: `usage.prompt_tokens`
+//! - OpenAI Responses (Codex): `response.usage.input_tokens`
+//! - Anthropic: `message.usage` and `usage.output_tokens`
+//! - Gemini: `usageMetadata.promptTokenCount`
+
+use parking_lot::Mutex;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::bridge::chrono_lite_timestamp;
+use crate::digest::format_date;
+use crate::paths;
+
+const DB_FILENAME: &str = "usage.db";
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+/// Non-streamed responses are parsed whole; past this they aren't scanned
+const MAX_BUFFERED_BYTES: usize = 4 * 1024 * 1024;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS token_usage (
+    day TEXT NOT NULL,
+    project TEXT NOT NULL DEFAULT '',
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    requests INTEGER NOT NULL DEFAULT 0,
+    input_tokens INTEGER NOT NULL DEFAULT 0,
+    output_tokens INTEGER NOT NULL DEFAULT 0,
+    cached_tokens INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, project, provider, model)
+);
+";
+
+lazy_static::lazy_static! {
+    // Opened on first use so the proxy still runs if the data dir is unavailable
+    static ref USAGE_DB: Mutex<Option<Connection>> = Mutex::new(None);
+}
+
+fn open_db() -> Result<Connection, String> {
+    let path = paths::app_data_dir()?.join(DB_FILENAME);
+    let conn =
+        Connection::open(&path).map_err(|e| format!("Failed to open usage database: {}", e))?;
+    conn.execute_batch(SCHEMA)
+        .map_err(|e| format!("Failed to initialize usage database: {}", e))?;
+    Ok(conn)
+}
+
+fn with_db<T>(f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+    let mut db = USAGE_DB.lock();
+    if db.is_none() {
+        *db = Some(open_db()?);
+    }
+    let conn = db.as_ref().expect("usage database was just opened");
+    f(conn).map_err(|e| format!("Usage database error: {}", e))
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TokenCounts {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Input tokens served from the provider's prompt cache, included in `input_tokens`
+    pub cached_tokens: u64,
+}
+
+/// Reads usage out of a response as it streams past, and records it once the
+/// response is done with (finished, failed or cancelled)
+pub(crate) struct UsageMeter {
+    provider: String,
+    project: Option<String>,
+    streamed: bool,
+    pending: Vec<u8>,
+    model: Option<String>,
+    counts: TokenCounts,
+}
+
+impl UsageMeter {
+    pub fn new(provider: &str, project: Option<&str>, content_type: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            project: project.filter(|p| !p.is_empty()).map(str::to_string),
+            streamed: content_type.starts_with("text/event-stream")
+                || content_type.contains("ndjson"),
+            pending: Vec::new(),
+            model: None,
+            counts: TokenCounts::default(),
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if !self.streamed {
+            if self.pending.len() + chunk.len() <= MAX_BUFFERED_BYTES {
+                self.pending.extend_from_slice(chunk);
+            }
+            return;
+        }
+        self.pending.extend_from_slice(chunk);
+        while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.scan_line(&line);
+        }
+    }
+
+    fn scan_line(&mut self, line: &[u8]) {
+        let line = line.strip_prefix(b"data:").unwrap_or(line);
+        if let Ok(value) = serde_json::from_slice::<Value>(line) {
+            self.scan(&value);
+        }
+    }
+
+    fn scan(&mut self, value: &Value) {
+        let count = |usage: &Value, names: &[&str]| {
+            names.iter().find_map(|name| {
+                name.split('.')
+                    .try_fold(usage, |v, key| v.get(key))?
+                    .as_u64()
+            })
+        };
+        let usage = ["usage", "response.usage", "message.usage", "usageMetadata"]
+            .iter()
+            .find_map(|path| {
+                path.split('.')
+                    .try_fold(value, |v, key| v.get(key))
+                    .filter(|usage| usage.is_object())
+            });
+        if let Some(usage) = usage {
+            let fields: [(&mut u64, &[&str]); 3] = [
+                (
+                    &mut self.counts.input_tokens,
+                    &["prompt_tokens", "input_tokens", "promptTokenCount"],
+                ),
+                (
+                    &mut self.counts.output_tokens,
+                    &["completion_tokens", "output_tokens", "candidatesTokenCount"],
+                ),
+                (
+                    &mut self.counts.cached_tokens,
+                    &[
+                        "prompt_tokens_details.cached_tokens",
+                        "input_tokens_details.cached_tokens",
+                        "cache_read_input_tokens",
+                        "cachedContentTokenCount",
+                    ],
+                ),
+            ];
+            for (total, names) in fields {
+                if let Some(count) = count(usage, names) {
+                    *total = (*total).max(count);
+                }
+            }
+        }
+        if self.model.is_none() {
+            self.model = ["model", "response.model", "message.model", "modelVersion"]
+                .iter()
+                .find_map(|path| {
+                    path.split('.')
+                        .try_fold(value, |v, key| v.get(key))?
+                        .as_str()
+                        .map(str::to_string)
+                });
+        }
+    }
+}
+
+impl Drop for UsageMeter {
+    fn drop(&mut self) {
+        let rest = std::mem::take(&mut self.pending);
+        self.scan_line(&rest);
+        let counts = self.counts;
+        if counts.input_tokens == 0 && counts.output_tokens == 0 {
+            return;
+        }
+        let model = self.model.as_deref().unwrap_or("unknown");
+        let result = with_db(|conn| {
+            conn.execute(
+                "INSERT INTO token_usage
+                    (day, project, provider, model, requests, input_tokens, output_tokens, cached_tokens)
+                VALUES (?1, ?2, ?3, ?4, 1, ?5, ?6, ?7)
+                ON CONFLICT (day, project, provider, model) DO UPDATE SET
+                    requests = requests + 1,
+                    input_tokens = input_tokens + excluded.input_tokens,
+                    output_tokens = output_tokens + excluded.output_tokens,
+                    cached_tokens = cached_tokens + excluded.cached_tokens",
+                params![
+                    format_date(chrono_lite_timestamp()),
+                    self.project.as_deref().unwrap_or_default(),
+                    self.provider,
+                    model,
+                    counts.input_tokens as i64,
+                    counts.output_tokens as i64,
+                    counts.cached_tokens as i64,
+                ],
+            )
+        });
+        if let Err(e) = result {
+            println!("[Stud Usage] Failed to record usage: {}", e);
+        }
+    }
+}
+
+/// How far back `get_usage_stats` looks
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageRange {
+    Today,
+    /// The last 7 days, including today
+    #[default]
+    Week,
+    /// The last 30 days, including today
+    Month,
+    All,
+}
+
+impl UsageRange {
+    /// First day included, as YYYY-MM-DD
+    fn since(self) -> Option<String> {
+        let days = match self {
+            UsageRange::Today => 0,
+            UsageRange::Week => 6,
+            UsageRange::Month => 29,
+            UsageRange::All => return None,
+        };
+        Some(format_date(
+            chrono_lite_timestamp().saturating_sub(days * DAY_MS),
+        ))
+    }
+}
+
+/// Tokens for one day, project, provider and model
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageRow {
+    pub day: String,
+    pub project: Option<String>,
+    pub provider: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub counts: TokenCounts,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectUsage {
+    pub project: Option<String>,
+    #[serde(flatten)]
+    pub counts: TokenCounts,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageStats {
+    /// First day counted; None for all time
+    pub since: Option<String>,
+    pub totals: TokenCounts,
+    /// Heaviest projects first
+    pub projects: Vec<ProjectUsage>,
+    /// Newest day first
+    pub rows: Vec<UsageRow>,
+}
+
+/// Token usage recorded by the provider proxy over a range of days
+#[tauri::command]
+pub fn get_usage_stats(range: Option<UsageRange>) -> Result<UsageStats, String> {
+    let since = range.unwrap_or_default().since();
+    let rows = with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT day, project, provider, model, requests, input_tokens, output_tokens, cached_tokens
+            FROM token_usage WHERE day >= ?1
+            ORDER BY day DESC, input_tokens + output_tokens DESC",
+        )?;
+        let rows = stmt
+            .query_map(params![since.as_deref().unwrap_or_default()], |row| {
+                let project: String = row.get(1)?;
+                Ok(UsageRow {
+                    day: row.get(0)?,
+                    project: Some(project).filter(|p| !p.is_empty()),
+                    provider: row.get(2)?,
+                    model: row.get(3)?,
+                    counts: TokenCounts {
+                        requests: row.get::<_, i64>(4)? as u64,
+                        input_tokens: row.get::<_, i64>(5)? as u64,
+                        output_tokens: row.get::<_, i64>(6)? as u64,
+                        cached_tokens: row.get::<_, i64>(7)? as u64,
+                    },
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    })?;
+
+    let mut totals = TokenCounts::default();
+    let mut projects: Vec<ProjectUsage> = Vec::new();
+    for row in &rows {
+        let index = match projects.iter().position(|p| p.project == row.project) {
+            Some(index) => index,
+            None => {
+                projects.push(ProjectUsage {
+                    project: row.project.clone(),
+                    counts: TokenCounts::default(),
+                });
+                projects.len() - 1
+            }
+        };
+        for counts in [&mut totals, &mut projects[index].counts] {
+            counts.requests += row.counts.requests;
+            counts.input_tokens += row.counts.input_tokens;
+            counts.output_tokens += row.counts.output_tokens;
+            counts.cached_tokens += row.counts.cached_tokens;
+        }
+    }
+    projects.sort_by_key(|p| std::cmp::Reverse(p.counts.input_tokens + p.counts.output_tokens));
+
+    Ok(UsageStats {
+        since,
+        totals,
+        projects,
+        rows,
+    })
+}
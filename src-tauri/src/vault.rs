@@ -0,0 +1,188 @@
+//! API Key Vault
+//!
+//! Keys for bring-your-own-key providers, kept in the OS keychain (Keychain on
+//! macOS, Credential Manager on Windows, Secret Service on Linux) rather than
+//! in the webview's storage. The frontend can store, check and delete a key but
+//! never read one back; the provider proxy (see `llm_proxy`) adds keys to the
+//! requests it forwards. Endpoint profiles from the config have a key each,
+//! filed under the profile's name. So is the password for a manually set proxy,
+//! and the refresh token of each signed-in OAuth account (see `tokens`).
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Keychain service the keys are filed under, one entry per provider
+const SERVICE: &str = "stud";
+
+lazy_static::lazy_static! {
+    /// Keys already read from the keychain by account (None: there is none), so
+    /// requests don't hit the keychain every time
+    static ref CACHE: Mutex<HashMap<String, Option<String>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyProvider {
+    OpenAi,
+    Anthropic,
+    Gemini,
+    OpenRouter,
+}
+
+impl KeyProvider {
+    fn account(self) -> &'static str {
+        match self {
+            KeyProvider::OpenAi => "openai-api-key",
+            KeyProvider::Anthropic => "anthropic-api-key",
+            KeyProvider::Gemini => "gemini-api-key",
+            KeyProvider::OpenRouter => "openrouter-api-key",
+        }
+    }
+}
+
+fn endpoint_account(name: &str) -> String {
+    format!("endpoint-{}-api-key", name)
+}
+
+const PROXY_ACCOUNT: &str = "network-proxy-password";
+
+fn refresh_token_account(name: &str) -> String {
+    format!("oauth-{}-refresh-token", name)
+}
+
+fn entry(account: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(SERVICE, account).map_err(|e| format!("Failed to open the keychain: {}", e))
+}
+
+fn read(account: &str) -> Result<Option<String>, String> {
+    if let Some(key) = CACHE.lock().get(account) {
+        return Ok(key.clone());
+    }
+    let key = match entry(account)?.get_password() {
+        Ok(key) => Some(key),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => return Err(format!("Failed to read the API key: {}", e)),
+    };
+    CACHE.lock().insert(account.to_string(), key.clone());
+    Ok(key)
+}
+
+/// Store a key, or delete the stored one if it's empty. Returns whether a key was saved.
+fn write(account: &str, key: &str) -> Result<bool, String> {
+    let key = key.trim();
+    if key.is_empty() {
+        delete(account)?;
+        return Ok(false);
+    }
+    entry(account)?
+        .set_password(key)
+        .map_err(|e| format!("Failed to save the API key: {}", e))?;
+    CACHE
+        .lock()
+        .insert(account.to_string(), Some(key.to_string()));
+    Ok(true)
+}
+
+fn delete(account: &str) -> Result<(), String> {
+    match entry(account)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(format!("Failed to delete the API key: {}", e)),
+    }
+    CACHE.lock().insert(account.to_string(), None);
+    Ok(())
+}
+
+/// The stored key for a provider, if there is one
+pub(crate) fn api_key(provider: KeyProvider) -> Result<Option<String>, String> {
+    read(provider.account())
+}
+
+/// The stored key for an endpoint profile, if there is one
+pub(crate) fn endpoint_key(name: &str) -> Result<Option<String>, String> {
+    read(&endpoint_account(name))
+}
+
+/// The password for the manual proxy, if one is stored
+pub(crate) fn proxy_password() -> Result<Option<String>, String> {
+    read(PROXY_ACCOUNT)
+}
+
+/// The stored refresh token of an OAuth account, if there is one
+pub(crate) fn refresh_token(name: &str) -> Result<Option<String>, String> {
+    read(&refresh_token_account(name))
+}
+
+/// Store an OAuth account's refresh token, unless it's the one already stored
+pub(crate) fn set_refresh_token(name: &str, token: &str) -> Result<(), String> {
+    let account = refresh_token_account(name);
+    if read(&account)?.as_deref() != Some(token) {
+        write(&account, token)?;
+    }
+    Ok(())
+}
+
+/// Forget an OAuth account's refresh token
+pub(crate) fn delete_refresh_token(name: &str) -> Result<(), String> {
+    delete(&refresh_token_account(name))
+}
+
+/// Store a provider's API key, replacing any previous one
+#[tauri::command]
+pub fn set_api_key(provider: KeyProvider, key: String) -> Result<(), String> {
+    if write(provider.account(), &key)? {
+        println!("[Stud Vault] Saved {:?} API key", provider);
+    } else {
+        println!("[Stud Vault] Deleted {:?} API key", provider);
+    }
+    Ok(())
+}
+
+/// Whether a key is stored for a provider; the key itself stays in the backend
+#[tauri::command]
+pub fn has_api_key(provider: KeyProvider) -> Result<bool, String> {
+    api_key(provider).map(|key| key.is_some())
+}
+
+/// Forget a provider's API key
+#[tauri::command]
+pub fn delete_api_key(provider: KeyProvider) -> Result<(), String> {
+    delete(provider.account())?;
+    println!("[Stud Vault] Deleted {:?} API key", provider);
+    Ok(())
+}
+
+/// Store the API key for an endpoint profile; an empty key deletes it
+#[tauri::command]
+pub fn set_endpoint_key(name: String, key: String) -> Result<(), String> {
+    if write(&endpoint_account(&name), &key)? {
+        println!("[Stud Vault] Saved API key for endpoint '{}'", name);
+    } else {
+        println!("[Stud Vault] Deleted API key for endpoint '{}'", name);
+    }
+    Ok(())
+}
+
+/// Whether a key is stored for an endpoint profile
+#[tauri::command]
+pub fn has_endpoint_key(name: String) -> Result<bool, String> {
+    endpoint_key(&name).map(|key| key.is_some())
+}
+
+/// Store the password for the manual proxy; an empty one deletes it
+#[tauri::command]
+pub fn set_proxy_password(password: String) -> Result<(), String> {
+    if write(PROXY_ACCOUNT, &password)? {
+        println!("[Stud Vault] Saved proxy password");
+    } else {
+        println!("[Stud Vault] Deleted proxy password");
+    }
+    crate::net::reset();
+    Ok(())
+}
+
+/// Whether a proxy password is stored
+#[tauri::command]
+pub fn has_proxy_password() -> Result<bool, String> {
+    proxy_password().map(|password| password.is_some())
+}
@@ -0,0 +1,158 @@
+//! Watch Expressions
+//!
+//! The user or AI registers expressions like `workspace.Player1.Humanoid.Health`.
+//! During playtests the plugin fetches the list from the bridge, samples each
+//! expression on its interval and posts the values back in batches. Samples are
+//! kept here as a capped time series per watch for `get_watch_history`.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use crate::bridge::chrono_lite_timestamp;
+
+const MAX_SAMPLES_PER_WATCH: usize = 5000;
+const MIN_INTERVAL_MS: u64 = 100;
+const DEFAULT_INTERVAL_MS: u64 = 500;
+
+lazy_static::lazy_static! {
+    static ref WATCHES: Mutex<WatchRegistry> = Mutex::new(WatchRegistry::default());
+}
+
+#[derive(Default)]
+struct WatchRegistry {
+    watches: Vec<WatchInfo>,
+    history: HashMap<String, VecDeque<WatchSample>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchInfo {
+    pub id: String,
+    /// Dotted path from game or workspace, ending in a property or instance
+    pub expression: String,
+    pub interval_ms: u64,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchSample {
+    /// Unix milliseconds when the plugin took the sample
+    pub t: u64,
+    pub value: serde_json::Value,
+    /// Set instead of `value` when the expression couldn't be evaluated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Studio session that sampled it (server and client can both report)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+/// Samples posted by the plugin to /stud/watch/samples
+#[derive(Debug, Deserialize)]
+pub struct SampleBatch {
+    #[serde(default)]
+    pub session: Option<String>,
+    pub samples: Vec<PluginSample>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PluginSample {
+    pub watch_id: String,
+    pub t: u64,
+    #[serde(default)]
+    pub value: serde_json::Value,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Watches the plugin should be sampling
+pub fn active_watches() -> Vec<WatchInfo> {
+    WATCHES.lock().watches.clone()
+}
+
+/// Store a batch of samples from the plugin; samples for removed watches are dropped
+pub fn record_samples(batch: SampleBatch) -> usize {
+    let mut registry = WATCHES.lock();
+    let mut recorded = 0;
+    for sample in batch.samples {
+        let Some(series) = registry.history.get_mut(&sample.watch_id) else {
+            continue;
+        };
+        if series.len() >= MAX_SAMPLES_PER_WATCH {
+            series.pop_front();
+        }
+        series.push_back(WatchSample {
+            t: sample.t,
+            value: sample.value,
+            error: sample.error,
+            session: batch.session.clone(),
+        });
+        recorded += 1;
+    }
+    recorded
+}
+
+/// Start sampling an expression during playtests
+#[tauri::command]
+pub fn add_watch(expression: String, interval_ms: Option<u64>) -> Result<WatchInfo, String> {
+    let expression = expression.trim().to_string();
+    let root = expression.split('.').next().unwrap_or_default();
+    if !matches!(root, "game" | "workspace" | "Workspace") {
+        return Err("Watch expressions must start with game or workspace".to_string());
+    }
+
+    let info = WatchInfo {
+        id: uuid::Uuid::new_v4().to_string(),
+        expression,
+        interval_ms: interval_ms
+            .unwrap_or(DEFAULT_INTERVAL_MS)
+            .max(MIN_INTERVAL_MS),
+        created_at: chrono_lite_timestamp(),
+    };
+
+    let mut registry = WATCHES.lock();
+    registry.history.insert(info.id.clone(), VecDeque::new());
+    registry.watches.push(info.clone());
+    Ok(info)
+}
+
+/// Stop sampling a watch and discard its history
+#[tauri::command]
+pub fn remove_watch(id: String) -> Result<(), String> {
+    let mut registry = WATCHES.lock();
+    let before = registry.watches.len();
+    registry.watches.retain(|watch| watch.id != id);
+    if registry.watches.len() == before {
+        return Err(format!("Watch not found: {}", id));
+    }
+    registry.history.remove(&id);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_watches() -> Vec<WatchInfo> {
+    active_watches()
+}
+
+/// Sampled values for a watch, oldest first, optionally only those after `since` (unix ms)
+#[tauri::command]
+pub fn get_watch_history(id: String, since: Option<u64>) -> Result<Vec<WatchSample>, String> {
+    let registry = WATCHES.lock();
+    let series = registry
+        .history
+        .get(&id)
+        .ok_or_else(|| format!("Watch not found: {}", id))?;
+    Ok(series
+        .iter()
+        .filter(|sample| since.is_none_or(|since| sample.t > since))
+        .cloned()
+        .collect())
+}
+
+/// Forget recorded samples but keep the watches
+#[tauri::command]
+pub fn clear_watch_history() {
+    for series in WATCHES.lock().history.values_mut() {
+        series.clear();
+    }
+}
@@ -0,0 +1,241 @@
+//! What's New
+//!
+//! Release notes shown once after Stud updates. The changelog is bundled in
+//! the binary, and at startup the running version is compared with the one
+//! that ran last, so however the update arrived, the releases in between are
+//! offered to the UI together with the steps they need from the user:
+//! reinstalling the Studio plugin, signing in again, or a note about data
+//! that was migrated.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+
+use crate::{history, paths, plugin};
+
+const BUNDLED_CHANGELOG: &str = include_str!("../resources/changelog.json");
+const STATE_FILENAME: &str = "whats_new.json";
+const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+lazy_static::lazy_static! {
+    static ref STATE: Mutex<WhatsNewState> = Mutex::new(load_state().unwrap_or_default());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepKind {
+    /// The bundled Studio plugin changed and has to be reinstalled
+    ReloadPlugin,
+    /// Saved sign-ins no longer work and the user has to sign in again
+    Reauth,
+    /// Local data was converted; nothing to do, but worth knowing
+    Migration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeStep {
+    pub kind: StepKind,
+    pub message: String,
+    /// For reauth steps, the provider to sign in to again
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    #[serde(default)]
+    pub date: Option<String>,
+    pub notes: Vec<String>,
+    #[serde(default)]
+    pub steps: Vec<UpgradeStep>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Changelog {
+    releases: Vec<Release>,
+}
+
+/// Versions seen on this machine
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WhatsNewState {
+    /// Version that ran last; None before the first launch
+    last_run_version: Option<String>,
+    /// Version that ran before the current one, once an update is seen
+    previous_version: Option<String>,
+    /// Releases whose notes the user dismissed
+    read: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseEntry {
+    #[serde(flatten)]
+    pub release: Release,
+    pub read: bool,
+}
+
+/// A step from an unread release, with whether it's already been taken care of
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingStep {
+    #[serde(flatten)]
+    pub step: UpgradeStep,
+    pub version: String,
+    pub done: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WhatsNew {
+    pub current_version: &'static str,
+    /// None on a fresh install, or when updating from a version too old to record itself
+    pub previous_version: Option<String>,
+    /// Releases since the previous version, newest first
+    pub releases: Vec<ReleaseEntry>,
+    pub steps: Vec<PendingStep>,
+}
+
+fn bundled_releases() -> Vec<Release> {
+    match serde_json::from_str::<Changelog>(BUNDLED_CHANGELOG) {
+        Ok(changelog) => changelog.releases,
+        Err(e) => {
+            println!("[Stud WhatsNew] Bundled changelog is invalid: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Compare dotted versions numerically, ignoring any pre-release suffix
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    let (a, b) = (parts(a), parts(b));
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            a.get(i)
+                .copied()
+                .unwrap_or(0)
+                .cmp(&b.get(i).copied().unwrap_or(0))
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+fn load_state() -> Option<WhatsNewState> {
+    let source = fs::read_to_string(paths::app_data_dir().ok()?.join(STATE_FILENAME)).ok()?;
+    serde_json::from_str(&source).ok()
+}
+
+fn save_state(state: &WhatsNewState) -> Result<(), String> {
+    let path = paths::app_data_dir()?.join(STATE_FILENAME);
+    let json = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize what's new state: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to save what's new state: {}", e))
+}
+
+/// Record this launch's version; call once at startup before the UI asks for notes
+pub fn init() {
+    let mut state = STATE.lock();
+    match state.last_run_version.as_deref() {
+        Some(version) if version == APP_VERSION => return,
+        Some(version) => {
+            println!(
+                "[Stud WhatsNew] Updated from {} to {}",
+                version, APP_VERSION
+            );
+            state.previous_version = Some(version.to_string());
+        }
+        None => {
+            // Chat history means Stud ran before this file existed, so it's an update
+            // from an unknown version; otherwise a fresh install has nothing to catch up on
+            let used_before = paths::app_data_dir()
+                .map(|dir| dir.join(history::DB_FILENAME).exists())
+                .unwrap_or(false);
+            if !used_before {
+                state.read = bundled_releases()
+                    .into_iter()
+                    .map(|release| release.version)
+                    .collect();
+            }
+        }
+    }
+    state.last_run_version = Some(APP_VERSION.to_string());
+    if let Err(e) = save_state(&state) {
+        println!("[Stud WhatsNew] {}", e);
+    }
+}
+
+fn step_done(step: &UpgradeStep) -> bool {
+    match step.kind {
+        StepKind::ReloadPlugin => plugin::check_plugin_installed()
+            .map(|status| status.is_current_version)
+            .unwrap_or(false),
+        StepKind::Reauth | StepKind::Migration => false,
+    }
+}
+
+/// Release notes since the previous version and the steps they still need
+#[tauri::command]
+pub fn get_whats_new() -> WhatsNew {
+    let state = STATE.lock();
+    let mut releases: Vec<ReleaseEntry> = bundled_releases()
+        .into_iter()
+        .filter(|release| compare_versions(&release.version, APP_VERSION).is_le())
+        .filter(|release| {
+            state
+                .previous_version
+                .as_deref()
+                .is_none_or(|previous| compare_versions(&release.version, previous).is_gt())
+        })
+        .map(|release| ReleaseEntry {
+            read: state.read.contains(&release.version),
+            release,
+        })
+        .collect();
+    releases.sort_by(|a, b| compare_versions(&b.release.version, &a.release.version));
+
+    let steps = releases
+        .iter()
+        .filter(|entry| !entry.read)
+        .flat_map(|entry| {
+            entry.release.steps.iter().map(|step| PendingStep {
+                step: step.clone(),
+                version: entry.release.version.clone(),
+                done: step_done(step),
+            })
+        })
+        .collect();
+
+    WhatsNew {
+        current_version: APP_VERSION,
+        previous_version: state.previous_version.clone(),
+        releases,
+        steps,
+    }
+}
+
+/// Dismiss one release's notes, or every release when no version is given
+#[tauri::command]
+pub fn mark_whats_new_read(version: Option<String>) -> Result<(), String> {
+    let mut state = STATE.lock();
+    let versions = match version {
+        Some(version) => vec![version],
+        None => bundled_releases()
+            .into_iter()
+            .map(|release| release.version)
+            .collect(),
+    };
+    for version in versions {
+        if !state.read.contains(&version) {
+            state.read.push(version);
+        }
+    }
+    save_state(&state)
+}
@@ -3,6 +3,7 @@
  */
 
 import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { cn } from "@/lib/utils";
 import { isStudioConnected, isBridgeRunning } from "@/lib/roblox/client";
 import { Loader } from "@/components/ui/loader";
@@ -11,6 +12,29 @@ import { Button } from "@/components/ui/button";
 
 type ConnectionState = "connected" | "disconnected" | "bridge-only" | "checking";
 
+/** What the connected plugin reported about its Studio (get_studio_metadata) */
+interface StudioMetadata {
+  place_id: number | null;
+  place_name: string | null;
+  user_name: string | null;
+  studio_version: string | null;
+}
+
+function placeLabel(studio: StudioMetadata | null): string | null {
+  if (!studio?.place_name) return null;
+  return studio.place_id
+    ? `Connected to: ${studio.place_name} (place ${studio.place_id})`
+    : `Connected to: ${studio.place_name}`;
+}
+
+function studioDetails(studio: StudioMetadata | null): string | undefined {
+  const details = [
+    studio?.user_name && `Signed in as ${studio.user_name}`,
+    studio?.studio_version && `Studio ${studio.studio_version}`,
+  ].filter(Boolean);
+  return details.length > 0 ? details.join(" · ") : undefined;
+}
+
 interface ConnectionStatusProps {
   className?: string;
   showLabel?: boolean;
@@ -24,6 +48,7 @@ export function ConnectionStatus({
 }: ConnectionStatusProps) {
   const [state, setState] = useState<ConnectionState>("checking");
   const [checking, setChecking] = useState(false);
+  const [studio, setStudio] = useState<StudioMetadata | null>(null);
 
   const checkConnection = async () => {
     setChecking(true);
@@ -36,6 +61,7 @@ export function ConnectionStatus({
 
       const studioUp = await isStudioConnected();
       setState(studioUp ? "connected" : "bridge-only");
+      setStudio(studioUp ? await invoke<StudioMetadata | null>("get_studio_metadata") : null);
     } catch {
       setState("disconnected");
     } finally {
@@ -81,6 +107,7 @@ export function ConnectionStatus({
   };
 
   const current = config[state];
+  const label = (state === "connected" && placeLabel(studio)) || current.label;
 
   return (
     <div
@@ -95,6 +122,7 @@ export function ConnectionStatus({
           current.bg,
           current.color
         )}
+        title={state === "connected" ? studioDetails(studio) : undefined}
       >
         {/* Animated pulse dot */}
         <span className="relative flex h-2 w-2">
@@ -114,7 +142,7 @@ export function ConnectionStatus({
           />
         </span>
 
-        {showLabel && <span>{current.label}</span>}
+        {showLabel && <span>{label}</span>}
       </div>
 
       {showRefresh && (
@@ -26,15 +26,23 @@ interface SettingsPanelProps {
 }
 
 export function SettingsPanel({ trigger }: SettingsPanelProps) {
-  const { apiKeys, setApiKey, appSettings, updateAppSettings, resetAppSettings } = useSettingsStore();
+  const { hasApiKey, setApiKey, appSettings, updateAppSettings, resetAppSettings } = useSettingsStore();
   const [showOpenAIKey, setShowOpenAIKey] = useState(false);
   const [showAnthropicKey, setShowAnthropicKey] = useState(false);
-  const [localOpenAI, setLocalOpenAI] = useState(apiKeys.openai || "");
-  const [localAnthropic, setLocalAnthropic] = useState(apiKeys.anthropic || "");
+  // Saved keys stay in the backend's vault; these only hold new ones
+  const [localOpenAI, setLocalOpenAI] = useState("");
+  const [localAnthropic, setLocalAnthropic] = useState("");
   const [saved, setSaved] = useState<string | null>(null);
 
-  const handleSaveKey = (provider: "openai" | "anthropic", value: string) => {
-    setApiKey(provider, value);
+  const handleSaveKey = async (provider: "openai" | "anthropic", value: string) => {
+    try {
+      await setApiKey(provider, value);
+    } catch (error) {
+      console.error("[Settings] Failed to save API key:", error);
+      return;
+    }
+    if (provider === "openai") setLocalOpenAI("");
+    else setLocalAnthropic("");
     setSaved(provider);
     setTimeout(() => setSaved(null), 2000);
   };
@@ -94,7 +102,7 @@ export function SettingsPanel({ trigger }: SettingsPanelProps) {
                   <Input
                     id="openai-key"
                     type={showOpenAIKey ? "text" : "password"}
-                    placeholder="sk-..."
+                    placeholder={hasApiKey("openai") ? "Saved - enter a new key to replace it" : "sk-..."}
                     value={localOpenAI}
                     onChange={(e) => setLocalOpenAI(e.target.value)}
                     className="pr-10"
@@ -110,7 +118,7 @@ export function SettingsPanel({ trigger }: SettingsPanelProps) {
                 <Button
                   size="sm"
                   onClick={() => handleSaveKey("openai", localOpenAI)}
-                  disabled={localOpenAI === apiKeys.openai}
+                  disabled={!localOpenAI}
                   className="gap-1"
                 >
                   {saved === "openai" ? <Check className="w-3.5 h-3.5" /> : "Save"}
@@ -138,7 +146,7 @@ export function SettingsPanel({ trigger }: SettingsPanelProps) {
                   <Input
                     id="anthropic-key"
                     type={showAnthropicKey ? "text" : "password"}
-                    placeholder="sk-ant-..."
+                    placeholder={hasApiKey("anthropic") ? "Saved - enter a new key to replace it" : "sk-ant-..."}
                     value={localAnthropic}
                     onChange={(e) => setLocalAnthropic(e.target.value)}
                     className="pr-10"
@@ -154,7 +162,7 @@ export function SettingsPanel({ trigger }: SettingsPanelProps) {
                 <Button
                   size="sm"
                   onClick={() => handleSaveKey("anthropic", localAnthropic)}
-                  disabled={localAnthropic === apiKeys.anthropic}
+                  disabled={!localAnthropic}
                   className="gap-1"
                 >
                   {saved === "anthropic" ? <Check className="w-3.5 h-3.5" /> : "Save"}
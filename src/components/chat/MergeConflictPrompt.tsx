@@ -0,0 +1,92 @@
+import { useState } from "react";
+import { Button } from "@/components/ui/button";
+import { GitMerge } from "lucide-react";
+import type { ConflictResolution, ScriptMergeConflict } from "@/stores/chat";
+
+interface MergeConflictPromptProps {
+  path: string;
+  conflicts: ScriptMergeConflict[];
+  /** null keeps the user's whole version and drops the AI's edit */
+  onResolve: (resolutions: ConflictResolution[] | null) => void;
+}
+
+type Choice = "user" | "ai" | "both";
+
+const CHOICES: { value: Choice; label: string }[] = [
+  { value: "user", label: "Keep mine" },
+  { value: "ai", label: "Use AI's" },
+  { value: "both", label: "Keep both" },
+];
+
+function CodeBlock({ title, code }: { title: string; code: string }) {
+  return (
+    <div className="min-w-0 flex-1 space-y-1">
+      <p className="text-xs font-medium text-muted-foreground">{title}</p>
+      <pre className="max-h-48 overflow-auto rounded-md border bg-white p-2 text-xs font-mono whitespace-pre">
+        {code || <span className="italic text-muted-foreground">(removed)</span>}
+      </pre>
+    </div>
+  );
+}
+
+export function MergeConflictPrompt({ path, conflicts, onResolve }: MergeConflictPromptProps) {
+  const [choices, setChoices] = useState<(Choice | null)[]>(conflicts.map(() => null));
+
+  const choose = (index: number, choice: Choice) => {
+    setChoices((prev) => {
+      const next = [...prev];
+      next[index] = choice;
+      return next;
+    });
+  };
+
+  const isComplete = choices.every((choice) => choice !== null);
+
+  return (
+    <div className="rounded-xl border bg-amber-50/50 p-4 space-y-4">
+      <div className="flex items-center gap-2 text-amber-700">
+        <GitMerge className="w-4 h-4" />
+        <span className="text-sm font-medium">
+          You and the AI both changed {path}
+        </span>
+      </div>
+
+      <div className="space-y-4">
+        {conflicts.map((conflict, index) => (
+          <div key={index} className="space-y-2">
+            <div className="flex gap-2">
+              <CodeBlock title="Your version" code={conflict.user} />
+              <CodeBlock title="AI's version" code={conflict.ai} />
+            </div>
+            <div className="flex flex-wrap gap-2">
+              {CHOICES.map(({ value, label }) => (
+                <Button
+                  key={value}
+                  variant={choices[index] === value ? "default" : "outline"}
+                  size="sm"
+                  className="h-8"
+                  onClick={() => choose(index, value)}
+                >
+                  {label}
+                </Button>
+              ))}
+            </div>
+          </div>
+        ))}
+      </div>
+
+      <div className="flex justify-end gap-2">
+        <Button variant="ghost" size="sm" onClick={() => onResolve(null)}>
+          Keep my version
+        </Button>
+        <Button
+          size="sm"
+          disabled={!isComplete}
+          onClick={() => onResolve(choices.map((choice) => ({ choice: choice! })))}
+        >
+          Apply merge
+        </Button>
+      </div>
+    </div>
+  );
+}
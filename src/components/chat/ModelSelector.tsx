@@ -12,14 +12,17 @@ import { useSettingsStore, ProviderType } from "@/stores/settings";
 import { useAuthStore } from "@/stores/auth";
 import { useModelsStore } from "@/stores/models";
 import { cn } from "@/lib/utils";
-import { Check, ChevronDown, Search, Zap, Brain, Sparkles } from "lucide-react";
+import { Check, ChevronDown, Search, Zap, Brain, Sparkles, HardDrive } from "lucide-react";
 
-// Static models for API key providers (OpenAI/Anthropic direct API access)
+// Static models for API key providers (OpenAI/Anthropic direct API access, or any vendor through OpenRouter)
 const staticModels: { id: string; name: string; short: string; provider: ProviderType; description?: string }[] = [
   { id: "gpt-4o", name: "GPT-4o", short: "4o", provider: "openai", description: "Most capable" },
   { id: "gpt-4o-mini", name: "GPT-4o Mini", short: "4o mini", provider: "openai", description: "Fast & cheap" },
   { id: "claude-sonnet-4-20250514", name: "Claude Sonnet 4", short: "Sonnet 4", provider: "anthropic", description: "Best for code" },
   { id: "claude-3-5-haiku-20241022", name: "Claude Haiku", short: "Haiku", provider: "anthropic", description: "Fast" },
+  { id: "anthropic/claude-sonnet-4", name: "Claude Sonnet 4 (OpenRouter)", short: "Sonnet 4", provider: "openrouter", description: "Via OpenRouter" },
+  { id: "google/gemini-2.5-pro", name: "Gemini 2.5 Pro (OpenRouter)", short: "Gemini", provider: "openrouter", description: "Via OpenRouter" },
+  { id: "deepseek/deepseek-chat", name: "DeepSeek V3 (OpenRouter)", short: "DeepSeek", provider: "openrouter", description: "Via OpenRouter" },
 ];
 
 interface ModelSelectorProps {
@@ -32,7 +35,7 @@ export function ModelSelector({ className, disabled }: ModelSelectorProps) {
   const [search, setSearch] = useState("");
   const { selectedModel, selectedProvider, setSelectedModel, hasApiKey } = useSettingsStore();
   const { isOAuthAuthenticated } = useAuthStore();
-  const { codexModels, isLoading, fetchModels } = useModelsStore();
+  const { codexModels, localModels, isLoading, fetchModels, fetchLocalModels } = useModelsStore();
 
   const isAuthenticated = isOAuthAuthenticated();
 
@@ -43,6 +46,13 @@ export function ModelSelector({ className, disabled }: ModelSelectorProps) {
     }
   }, [isAuthenticated, fetchModels]);
 
+  // Look for newly pulled local models each time the list opens
+  useEffect(() => {
+    if (open) {
+      fetchLocalModels();
+    }
+  }, [open, fetchLocalModels]);
+
   // Get short display name for the button
   const getShortName = () => {
     // Check codex models
@@ -93,7 +103,7 @@ export function ModelSelector({ className, disabled }: ModelSelectorProps) {
 
     // Add static models
     staticModels.forEach((m) => {
-      const isDisabled = !hasApiKey(m.provider as "openai" | "anthropic");
+      const isDisabled = !hasApiKey(m.provider as "openai" | "anthropic" | "openrouter");
       models.push({
         id: m.id,
         name: m.name,
@@ -103,8 +113,18 @@ export function ModelSelector({ className, disabled }: ModelSelectorProps) {
       });
     });
 
+    // Add models on the local Ollama server
+    localModels.forEach((m) => {
+      models.push({
+        id: m.id,
+        name: m.name,
+        provider: "ollama",
+        description: m.description,
+      });
+    });
+
     return models;
-  }, [codexModels, isAuthenticated, hasApiKey]);
+  }, [codexModels, localModels, isAuthenticated, hasApiKey]);
 
   // Filter models by search
   const filteredModels = useMemo(() => {
@@ -122,8 +142,9 @@ export function ModelSelector({ className, disabled }: ModelSelectorProps) {
   const groupedModels = useMemo(() => {
     const codex = filteredModels.filter((m) => m.provider === "codex" && !m.reasoning);
     const reasoning = filteredModels.filter((m) => m.reasoning);
-    const api = filteredModels.filter((m) => m.provider !== "codex");
-    return { codex, reasoning, api };
+    const api = filteredModels.filter((m) => m.provider !== "codex" && m.provider !== "ollama");
+    const local = filteredModels.filter((m) => m.provider === "ollama");
+    return { codex, reasoning, api, local };
   }, [filteredModels]);
 
   const handleSelect = (modelId: string, provider: ProviderType) => {
@@ -229,6 +250,20 @@ export function ModelSelector({ className, disabled }: ModelSelectorProps) {
               ))}
             </ModelGroup>
           )}
+
+          {/* Local Models */}
+          {groupedModels.local.length > 0 && (
+            <ModelGroup label="Local" icon={<HardDrive className="w-3 h-3" />}>
+              {groupedModels.local.map((model) => (
+                <ModelRow
+                  key={model.id}
+                  model={model}
+                  isSelected={selectedModel === model.id && selectedProvider === "ollama"}
+                  onClick={() => handleSelect(model.id, "ollama")}
+                />
+              ))}
+            </ModelGroup>
+          )}
         </div>
       </PopoverContent>
     </Popover>
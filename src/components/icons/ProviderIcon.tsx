@@ -11,7 +11,8 @@ const providerIcons: Record<string, string> = {
 export type ProviderId = keyof typeof providerIcons;
 
 export interface ProviderIconProps extends Omit<ComponentProps<"svg">, "id"> {
-  id: ProviderId;
+  // Providers without an icon get a generic one
+  id: ProviderId | (string & {});
   size?: "xs" | "sm" | "md" | "lg";
 }
 
@@ -23,7 +24,7 @@ const sizeMap = {
 };
 
 export function ProviderIcon({ id, size = "md", className, ...props }: ProviderIconProps) {
-  const icon = providerIcons[id];
+  const icon = providerIcons[id as ProviderId];
   
   if (!icon) {
     // Fallback to a generic provider icon
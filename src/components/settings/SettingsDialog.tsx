@@ -12,7 +12,7 @@ import { Input } from "@/components/ui/input";
 import { Icon } from "@/components/icons/Icon";
 import { ProviderIcon } from "@/components/icons/ProviderIcon";
 import { Loader } from "@/components/ui/loader";
-import { useSettingsStore } from "@/stores/settings";
+import { useSettingsStore, type ApiKeys } from "@/stores/settings";
 import { useAuthStore } from "@/stores/auth";
 import { useModelsStore } from "@/stores/models";
 import { cn } from "@/lib/utils";
@@ -22,7 +22,7 @@ import { LogOut, Sparkles, Key, Copy, Check, X, RefreshCw, Bug } from "lucide-re
 function DebugPanel() {
   const [isOpen, setIsOpen] = useState(false);
   const { authMethod, isOAuthAuthenticated, oauthAuth } = useAuthStore();
-  const { selectedModel, selectedProvider, apiKeys } = useSettingsStore();
+  const { selectedModel, selectedProvider, hasApiKey } = useSettingsStore();
   const { codexModels, lastFetched, isLoading } = useModelsStore();
 
   const isOAuth = isOAuthAuthenticated();
@@ -56,8 +56,8 @@ function DebugPanel() {
         <div><span className="text-muted-foreground">Auth Method:</span> {authMethod}</div>
         <div><span className="text-muted-foreground">OAuth Authenticated:</span> {isOAuth ? "true" : "false"}</div>
         <div><span className="text-muted-foreground">OAuth Auth Object:</span> {oauthAuth ? "exists" : "null"}</div>
-        <div><span className="text-muted-foreground">Has OpenAI Key:</span> {apiKeys.openai ? "true" : "false"}</div>
-        <div><span className="text-muted-foreground">Has Anthropic Key:</span> {apiKeys.anthropic ? "true" : "false"}</div>
+        <div><span className="text-muted-foreground">Has OpenAI Key:</span> {hasApiKey("openai") ? "true" : "false"}</div>
+        <div><span className="text-muted-foreground">Has Anthropic Key:</span> {hasApiKey("anthropic") ? "true" : "false"}</div>
         <div><span className="text-muted-foreground">Selected Provider:</span> {selectedProvider}</div>
         <div><span className="text-muted-foreground">Selected Model:</span> {selectedModel}</div>
         <div><span className="text-muted-foreground">Models Count:</span> {codexModels.length}</div>
@@ -72,24 +72,37 @@ function DebugPanel() {
 }
 
 interface ApiKeyInputProps {
-  provider: "openai" | "anthropic";
+  provider: keyof ApiKeys;
   label: string;
   placeholder: string;
 }
 
 function ApiKeyInput({ provider, label, placeholder }: ApiKeyInputProps) {
-  const { apiKeys, setApiKey, hasApiKey } = useSettingsStore();
+  const { setApiKey, hasApiKey } = useSettingsStore();
   const [showKey, setShowKey] = useState(false);
-  const [value, setValue] = useState(apiKeys[provider] || "");
+  // Saved keys stay in the backend; the field only holds a new one
+  const [value, setValue] = useState("");
+  const [saveError, setSaveError] = useState<string | null>(null);
   const isConfigured = hasApiKey(provider);
 
-  const handleSave = () => {
-    setApiKey(provider, value);
+  const handleSave = async () => {
+    try {
+      await setApiKey(provider, value);
+      setValue("");
+      setSaveError(null);
+    } catch (error) {
+      setSaveError(String(error));
+    }
   };
 
-  const handleClear = () => {
+  const handleClear = async () => {
     setValue("");
-    setApiKey(provider, "");
+    try {
+      await setApiKey(provider, "");
+      setSaveError(null);
+    } catch (error) {
+      setSaveError(String(error));
+    }
   };
 
   return (
@@ -112,7 +125,7 @@ function ApiKeyInput({ provider, label, placeholder }: ApiKeyInputProps) {
             type={showKey ? "text" : "password"}
             value={value}
             onChange={(e) => setValue(e.target.value)}
-            placeholder={placeholder}
+            placeholder={isConfigured ? "Saved - enter a new key to replace it" : placeholder}
             className="pr-10 rounded-xl"
           />
           <button
@@ -123,7 +136,7 @@ function ApiKeyInput({ provider, label, placeholder }: ApiKeyInputProps) {
             <Icon name={showKey ? "eye-off" : "eye"} size="sm" />
           </button>
         </div>
-        {value !== (apiKeys[provider] || "") ? (
+        {value ? (
           <Button onClick={handleSave} size="sm" className="rounded-xl">
             Save
           </Button>
@@ -133,6 +146,7 @@ function ApiKeyInput({ provider, label, placeholder }: ApiKeyInputProps) {
           </Button>
         ) : null}
       </div>
+      {saveError && <p className="text-xs text-red-600">{saveError}</p>}
     </div>
   );
 }
@@ -144,35 +158,25 @@ function ChatGPTAuth() {
     loginError,
     loginUrl,
     startLogin,
+    startDeviceLogin,
+    deviceCode,
     logout,
     cancelLogin,
-    checkOAuthCallback,
     isOAuthAuthenticated,
+    accounts,
+    syncStatus,
+    switchAccount,
+    removeAccount,
   } = useAuthStore();
   const { codexModels, isLoading: isLoadingModels, refreshModels, lastFetched } = useModelsStore();
 
   const [copied, setCopied] = useState(false);
   const isAuthenticated = isOAuthAuthenticated();
 
-  // Poll for OAuth callback when logging in
+  // Load the signed-in accounts
   useEffect(() => {
-    if (!isLoggingIn) return;
-    
-    const interval = setInterval(async () => {
-      const completed = await checkOAuthCallback();
-      if (completed) {
-        clearInterval(interval);
-      }
-    }, 1000);
-    
-    // Cleanup after 5 minutes
-    const timeout = setTimeout(() => clearInterval(interval), 5 * 60 * 1000);
-    
-    return () => {
-      clearInterval(interval);
-      clearTimeout(timeout);
-    };
-  }, [isLoggingIn, checkOAuthCallback]);
+    syncStatus();
+  }, [syncStatus]);
 
   const handleCopyUrl = async () => {
     if (loginUrl) {
@@ -209,7 +213,7 @@ function ChatGPTAuth() {
         </p>
       )}
 
-      {isAuthenticated ? (
+      {isAuthenticated && !isLoggingIn ? (
         <div className="space-y-2">
           <div className="flex items-center justify-between p-3 bg-green-50 rounded-xl">
             <div className="flex items-center gap-2">
@@ -226,6 +230,50 @@ function ChatGPTAuth() {
               Sign Out
             </Button>
           </div>
+          {/* Signed-in accounts; requests use the active one */}
+          <div className="space-y-1">
+            {accounts.length > 1 &&
+              accounts.map((account) => (
+                <div
+                  key={account.key}
+                  className="flex items-center justify-between px-3 py-1.5 rounded-lg bg-muted/40"
+                >
+                  <span className="text-xs truncate">
+                    {account.email ?? account.account_id ?? account.key}
+                  </span>
+                  <div className="flex items-center gap-1">
+                    {account.active ? (
+                      <span className="text-xs text-green-600 px-2">Active</span>
+                    ) : (
+                      <Button
+                        variant="ghost"
+                        size="sm"
+                        onClick={() => switchAccount(account.key)}
+                        className="h-6 px-2 text-xs"
+                      >
+                        Use
+                      </Button>
+                    )}
+                    <Button
+                      variant="ghost"
+                      size="sm"
+                      onClick={() => removeAccount(account.key)}
+                      className="h-6 px-2 text-xs text-red-600 hover:text-red-700 hover:bg-red-50"
+                    >
+                      <X className="w-3 h-3" />
+                    </Button>
+                  </div>
+                </div>
+              ))}
+            <Button
+              variant="ghost"
+              size="sm"
+              onClick={startLogin}
+              className="h-6 px-2 text-xs"
+            >
+              Add another account
+            </Button>
+          </div>
           {/* Model info and refresh */}
           <div className="flex items-center justify-between px-1">
             <span className="text-xs text-muted-foreground">
@@ -266,6 +314,27 @@ function ChatGPTAuth() {
             </Button>
           </div>
 
+          {/* Device code to enter on another page */}
+          {deviceCode && (
+            <div className="space-y-2">
+              <p className="text-xs text-muted-foreground">
+                Open{" "}
+                <a
+                  href={deviceCode.verification_uri}
+                  target="_blank"
+                  rel="noreferrer"
+                  className="underline"
+                >
+                  {deviceCode.verification_uri}
+                </a>{" "}
+                on any device and enter this code:
+              </p>
+              <div className="p-3 text-center font-mono text-lg tracking-widest bg-muted rounded-xl select-all">
+                {deviceCode.user_code}
+              </div>
+            </div>
+          )}
+
           {/* URL fallback */}
           {loginUrl && (
             <div className="space-y-2">
@@ -299,14 +368,25 @@ function ChatGPTAuth() {
           </p>
         </div>
       ) : (
-        <Button 
-          onClick={startLogin}
-          disabled={isLoggingIn}
-          className="w-full rounded-xl bg-gradient-to-r from-[#10a37f] to-[#1a7f64] hover:from-[#0d8f6e] hover:to-[#166b55]"
-        >
-          <Sparkles className="w-4 h-4 mr-2" />
-          Sign in with ChatGPT
-        </Button>
+        <div className="space-y-1">
+          <Button 
+            onClick={startLogin}
+            disabled={isLoggingIn}
+            className="w-full rounded-xl bg-gradient-to-r from-[#10a37f] to-[#1a7f64] hover:from-[#0d8f6e] hover:to-[#166b55]"
+          >
+            <Sparkles className="w-4 h-4 mr-2" />
+            Sign in with ChatGPT
+          </Button>
+          <Button
+            variant="ghost"
+            size="sm"
+            onClick={startDeviceLogin}
+            disabled={isLoggingIn}
+            className="w-full h-7 text-xs text-muted-foreground"
+          >
+            Sign in with a code instead
+          </Button>
+        </div>
       )}
     </div>
   );
@@ -318,7 +398,7 @@ function AuthMethodTabs() {
   const { hasApiKey } = useSettingsStore();
   
   const isOAuth = isOAuthAuthenticated();
-  const hasKey = hasApiKey("openai") || hasApiKey("anthropic");
+  const hasKey = hasApiKey("openai") || hasApiKey("anthropic") || hasApiKey("openrouter");
 
   return (
     <div className="flex gap-2 p-1 bg-muted rounded-xl">
@@ -398,6 +478,11 @@ export function SettingsDialog({ children }: SettingsDialogProps) {
                   label="Anthropic"
                   placeholder="sk-ant-..."
                 />
+                <ApiKeyInput
+                  provider="openrouter"
+                  label="OpenRouter"
+                  placeholder="sk-or-..."
+                />
               </>
             )}
           </div>
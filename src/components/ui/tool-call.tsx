@@ -3,15 +3,29 @@ import { cn } from "@/lib/utils";
 import { Loader } from "./loader";
 import { ChevronDown, ChevronRight, Check, X, Wrench, HelpCircle } from "lucide-react";
 
+export interface ToolCallProgress {
+  receivedBytes: number;
+  parts: number;
+  total?: number;
+  preview: string;
+}
+
 export interface ToolCallProps {
   name: string;
   input?: Record<string, unknown>;
   output?: unknown;
   status: "pending" | "running" | "complete" | "error" | "waiting";
   error?: string;
+  progress?: ToolCallProgress;
   className?: string;
 }
 
+function formatBytes(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
+
 // Pretty print tool name (e.g., roblox_get_script -> Get Script)
 function formatToolName(name: string): string {
   return name
@@ -27,6 +41,7 @@ export function ToolCall({
   output,
   status,
   error,
+  progress,
   className,
 }: ToolCallProps) {
   const [isExpanded, setIsExpanded] = React.useState(false);
@@ -65,6 +80,7 @@ export function ToolCall({
   };
 
   const { icon, color, bgColor } = statusConfig[status];
+  const streaming = status === "running" && progress !== undefined;
 
   return (
     <div
@@ -98,6 +114,14 @@ export function ToolCall({
           {formatToolName(name)}
         </span>
 
+        {/* Streaming progress */}
+        {streaming && (
+          <span className="text-xs text-muted-foreground tabular-nums">
+            {formatBytes(progress.receivedBytes)}
+            {progress.total ? ` · ${progress.parts}/${progress.total}` : ""}
+          </span>
+        )}
+
         {/* Status indicator */}
         <span className={cn("flex items-center gap-1.5", color)}>
           {icon}
@@ -119,6 +143,18 @@ export function ToolCall({
             </div>
           )}
 
+          {/* Partial output while the result streams in */}
+          {streaming && progress.preview && (
+            <div className="space-y-1.5">
+              <p className="text-xs font-medium text-muted-foreground uppercase tracking-wide">
+                Receiving...
+              </p>
+              <pre className="text-xs bg-background/80 rounded-lg p-3 overflow-x-auto border max-h-48 overflow-y-auto whitespace-pre-wrap break-all">
+                {progress.preview}
+              </pre>
+            </div>
+          )}
+
           {/* Output */}
           {status === "complete" && output !== undefined && (
             <div className="space-y-1.5">
@@ -159,6 +195,7 @@ export interface ToolCallsProps {
     result?: unknown;
     status: "pending" | "running" | "complete" | "error" | "waiting";
     error?: string;
+    progress?: ToolCallProgress;
   }>;
   className?: string;
 }
@@ -176,6 +213,7 @@ export function ToolCalls({ toolCalls, className }: ToolCallsProps) {
           output={tc.result}
           status={tc.status}
           error={tc.error}
+          progress={tc.progress}
         />
       ))}
     </div>
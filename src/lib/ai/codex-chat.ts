@@ -3,13 +3,14 @@
  * Uses the Responses API format with full agentic loop support
  */
 
-import { fetch as tauriFetch } from "@tauri-apps/plugin-http";
-import { getValidAccessToken, getStoredAuth } from "@/lib/auth/codex";
-import { ROBLOX_SYSTEM_PROMPT } from "./providers";
-import { robloxTools } from "@/lib/roblox";
+import { getCodexEndpoint, isAuthenticated } from "@/lib/auth/codex";
+import { buildSystemPrompt } from "./providers";
+import { generationHeaders } from "./generation";
+import { bridgeHeaders } from "@/lib/secret";
+import { toolsForProfile } from "@/lib/roblox";
+import { useRobloxStore } from "@/stores/roblox";
 import { z } from "zod";
 
-const CODEX_API_ENDPOINT = "https://chatgpt.com/backend-api/codex/responses";
 const MAX_ITERATIONS = 10; // Prevent infinite loops
 
 export interface CodexMessage {
@@ -38,6 +39,13 @@ type InputItem =
   | { type: "function_call"; call_id: string; name: string; arguments: string }
   | { type: "function_call_output"; call_id: string; output: string };
 
+/**
+ * Tools allowed by the connected place's profile
+ */
+function availableTools() {
+  return toolsForProfile(useRobloxStore.getState().placeProfile);
+}
+
 /**
  * Convert robloxTools to OpenAI function format for Codex API
  */
@@ -49,7 +57,7 @@ function convertToolsToOpenAI() {
     parameters: Record<string, unknown>;
   }> = [];
 
-  for (const [name, tool] of Object.entries(robloxTools)) {
+  for (const [name, tool] of Object.entries(availableTools())) {
     // eslint-disable-next-line @typescript-eslint/no-explicit-any
     const toolObj = tool as any;
     const schema = toolObj.inputSchema;
@@ -98,7 +106,7 @@ async function executeTool(
   toolName: string,
   args: Record<string, unknown>
 ): Promise<unknown> {
-  const toolFn = robloxTools[toolName as keyof typeof robloxTools];
+  const toolFn = availableTools()[toolName as keyof ReturnType<typeof availableTools>];
 
   if (!toolFn) {
     console.error("[CodexChat] Unknown tool:", toolName);
@@ -134,35 +142,29 @@ async function makeCodexRequest(
   text: string;
   toolCalls: ToolCall[];
 }> {
-  const accessToken = await getValidAccessToken();
-  if (!accessToken) {
+  if (!isAuthenticated()) {
     throw new Error("Not authenticated with ChatGPT Plus/Pro");
   }
 
-  const auth = getStoredAuth();
-
   // Build request body - always send full input history
   const body = {
     model,
-    instructions: ROBLOX_SYSTEM_PROMPT,
+    instructions: await buildSystemPrompt(),
     input,
     tools,
     stream: true,
     store: false,
   };
 
-  const headers: Record<string, string> = {
-    Authorization: `Bearer ${accessToken}`,
+  const headers = await bridgeHeaders({
     "Content-Type": "application/json",
-  };
-
-  if (auth?.accountId) {
-    headers["ChatGPT-Account-Id"] = auth.accountId;
-  }
+    ...generationHeaders(),
+  });
 
   console.log("[CodexChat] Making request with", input.length, "input items");
 
-  const response = await tauriFetch(CODEX_API_ENDPOINT, {
+  // The backend proxy adds the ChatGPT credentials
+  const response = await fetch(await getCodexEndpoint(), {
     method: "POST",
     headers,
     body: JSON.stringify(body),
@@ -0,0 +1,23 @@
+import { invoke } from "@tauri-apps/api/core";
+import { useChatStore } from "@/stores/chat";
+import { useRobloxStore } from "@/stores/roblox";
+
+// The backend's provider proxy groups requests by this header, so Stop can cancel them
+export const GENERATION_HEADER = "X-Stud-Generation-Id";
+// Token usage is counted per project (the connected place) under this header
+export const PROJECT_HEADER = "X-Stud-Project";
+
+// Headers that tie provider requests to the current turn and place
+export function generationHeaders(): Record<string, string> {
+  const { turnId } = useChatStore.getState();
+  const { placeId } = useRobloxStore.getState();
+  return {
+    ...(turnId ? { [GENERATION_HEADER]: turnId } : {}),
+    ...(placeId ? { [PROJECT_HEADER]: String(placeId) } : {}),
+  };
+}
+
+// Drop a turn's upstream requests in the proxy; resolves to whether any were running
+export async function cancelGeneration(id: string): Promise<boolean> {
+  return invoke<boolean>("cancel_generation", { id }).catch(() => false);
+}
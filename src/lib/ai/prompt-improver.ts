@@ -6,7 +6,9 @@
  */
 
 import { fetch as tauriFetch } from "@tauri-apps/plugin-http";
-import { getValidAccessToken, getStoredAuth } from "@/lib/auth/codex";
+import { getCodexEndpoint, isAuthenticated } from "@/lib/auth/codex";
+import { getProxyBase } from "@/lib/ai/providers";
+import { bridgeHeaders } from "@/lib/secret";
 import { useSettingsStore } from "@/stores/settings";
 import { useAuthStore } from "@/stores/auth";
 
@@ -66,20 +68,15 @@ interface ImproveResult {
   error?: string;
 }
 
-const CODEX_API_ENDPOINT = "https://chatgpt.com/backend-api/codex/responses";
-
 /**
  * Improve a prompt using the Codex API (ChatGPT Plus/Pro)
  * Uses the same endpoint and format as the main chat
  */
 async function improveWithCodex(prompt: string): Promise<ImproveResult> {
-  const accessToken = await getValidAccessToken();
-  if (!accessToken) {
+  if (!isAuthenticated()) {
     return { improved: prompt, error: "Not authenticated with ChatGPT" };
   }
 
-  const auth = getStoredAuth();
-
   // Use the same model as the main chat - get from settings store
   const { selectedModel, selectedProvider } = useSettingsStore.getState();
   // Only use the selected model if it's a Codex provider, otherwise fall back to chatgpt-4o-latest
@@ -100,19 +97,15 @@ async function improveWithCodex(prompt: string): Promise<ImproveResult> {
     store: false,
   };
 
-  const headers: Record<string, string> = {
-    Authorization: `Bearer ${accessToken}`,
+  const headers = await bridgeHeaders({
     "Content-Type": "application/json",
-  };
-
-  if (auth?.accountId) {
-    headers["ChatGPT-Account-Id"] = auth.accountId;
-  }
+  });
 
   try {
     console.log("[PromptImprover] Making Codex request...");
 
-    const response = await tauriFetch(CODEX_API_ENDPOINT, {
+    // The backend proxy adds the ChatGPT credentials
+    const response = await fetch(await getCodexEndpoint(), {
       method: "POST",
       headers,
       body: JSON.stringify(body),
@@ -184,14 +177,14 @@ async function improveWithCodex(prompt: string): Promise<ImproveResult> {
 /**
  * Improve a prompt using OpenAI API
  */
-async function improveWithOpenAI(prompt: string, apiKey: string): Promise<ImproveResult> {
+async function improveWithOpenAI(prompt: string): Promise<ImproveResult> {
   try {
-    const response = await tauriFetch("https://api.openai.com/v1/chat/completions", {
+    // The backend's proxy adds the key from the vault
+    const response = await tauriFetch(`${await getProxyBase("openai")}/v1/chat/completions`, {
       method: "POST",
-      headers: {
-        Authorization: `Bearer ${apiKey}`,
+      headers: await bridgeHeaders({
         "Content-Type": "application/json",
-      },
+      }),
       body: JSON.stringify({
         model: "gpt-5-mini",
         messages: [
@@ -223,15 +216,14 @@ async function improveWithOpenAI(prompt: string, apiKey: string): Promise<Improv
 /**
  * Improve a prompt using Anthropic API
  */
-async function improveWithAnthropic(prompt: string, apiKey: string): Promise<ImproveResult> {
+async function improveWithAnthropic(prompt: string): Promise<ImproveResult> {
   try {
-    const response = await tauriFetch("https://api.anthropic.com/v1/messages", {
+    const response = await tauriFetch(`${await getProxyBase("anthropic")}/v1/messages`, {
       method: "POST",
-      headers: {
-        "x-api-key": apiKey,
+      headers: await bridgeHeaders({
         "Content-Type": "application/json",
         "anthropic-version": "2023-06-01",
-      },
+      }),
       body: JSON.stringify({
         model: "claude-3-5-haiku-20241022",
         max_tokens: 500,
@@ -266,19 +258,19 @@ export async function improvePrompt(prompt: string): Promise<ImproveResult> {
     return { improved: prompt, error: "Empty prompt" };
   }
 
-  const { apiKeys } = useSettingsStore.getState();
+  const { hasApiKey } = useSettingsStore.getState();
   const { isOAuthAuthenticated } = useAuthStore.getState();
 
   // Prefer direct API access (faster, more reliable)
-  if (apiKeys.openai) {
+  if (hasApiKey("openai")) {
     console.log("[PromptImprover] Using OpenAI API");
-    const result = await improveWithOpenAI(prompt, apiKeys.openai);
+    const result = await improveWithOpenAI(prompt);
     if (!result.error) return result;
   }
 
-  if (apiKeys.anthropic) {
+  if (hasApiKey("anthropic")) {
     console.log("[PromptImprover] Using Anthropic API");
-    const result = await improveWithAnthropic(prompt, apiKeys.anthropic);
+    const result = await improveWithAnthropic(prompt);
     if (!result.error) return result;
   }
 
@@ -295,7 +287,7 @@ export async function improvePrompt(prompt: string): Promise<ImproveResult> {
  * Check if prompt improvement is available
  */
 export function canImprovePrompt(): boolean {
-  const { apiKeys } = useSettingsStore.getState();
+  const { hasApiKey } = useSettingsStore.getState();
   const { isOAuthAuthenticated } = useAuthStore.getState();
-  return !!(apiKeys.openai || apiKeys.anthropic || isOAuthAuthenticated());
+  return hasApiKey("openai") || hasApiKey("anthropic") || isOAuthAuthenticated();
 }
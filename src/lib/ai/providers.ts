@@ -1,23 +1,46 @@
 import { createOpenAI } from "@ai-sdk/openai";
 import { createAnthropic } from "@ai-sdk/anthropic";
 import { streamText, stepCountIs } from "ai";
+import { invoke } from "@tauri-apps/api/core";
 import { useSettingsStore } from "@/stores/settings";
 import { useAuthStore } from "@/stores/auth";
-import { robloxTools } from "@/lib/roblox";
+import { toolsForProfile } from "@/lib/roblox";
+import { useRobloxStore } from "@/stores/roblox";
 import { isAuthenticated as isCodexAuthenticated } from "@/lib/auth/codex";
 import { codexChat } from "./codex-chat";
+import { generationHeaders } from "./generation";
+import { bridgeHeaders } from "@/lib/secret";
 
-export type ProviderType = "openai" | "anthropic" | "codex";
+export type ProviderType = "openai" | "anthropic" | "openrouter" | "codex" | "ollama";
 
-export function getProvider(type: ProviderType, apiKey: string) {
+// Placeholder for SDKs that insist on a key; the proxy drops it and adds the real one
+const VAULT_KEY = "stud-vault";
+
+export type ProxyProvider = "openai" | "anthropic" | "gemini" | "openrouter" | "chatgpt" | "ollama";
+
+// Base URL of the backend's proxy for a provider; it adds the credentials
+export async function getProxyBase(provider: ProxyProvider): Promise<string> {
+  const { codex_proxy_url } = await invoke<{ codex_proxy_url: string | null }>("get_bridge_endpoints");
+  if (!codex_proxy_url) {
+    throw new Error("Provider proxy is not running");
+  }
+  return `${codex_proxy_url}/llm/${provider}`;
+}
+
+export async function getProvider(type: ProviderType) {
   switch (type) {
     case "openai":
-      return createOpenAI({ apiKey });
-    case "anthropic":
-      return createAnthropic({ apiKey });
     case "codex":
       // Codex uses its own chat function, this is a fallback
-      return createOpenAI({ apiKey });
+      return createOpenAI({ apiKey: VAULT_KEY, baseURL: `${await getProxyBase("openai")}/v1`, headers: await bridgeHeaders(generationHeaders()) });
+    case "anthropic":
+      return createAnthropic({ apiKey: VAULT_KEY, baseURL: `${await getProxyBase("anthropic")}/v1`, headers: await bridgeHeaders(generationHeaders()) });
+    case "openrouter":
+      // OpenRouter serves every model through chat completions; the proxy qualifies bare model names
+      return createOpenAI({ apiKey: VAULT_KEY, baseURL: `${await getProxyBase("openrouter")}/v1`, headers: await bridgeHeaders(generationHeaders()) }).chat;
+    case "ollama":
+      // The proxy translates chat completions to Ollama's own API
+      return createOpenAI({ apiKey: VAULT_KEY, baseURL: `${await getProxyBase("ollama")}/v1`, headers: await bridgeHeaders() }).chat;
     default:
       throw new Error(`Unknown provider: ${type}`);
   }
@@ -140,6 +163,19 @@ When using tools:
 
 Always provide clean, well-commented code following Roblox conventions.`;
 
+interface ChangeContext {
+  text: string;
+  included: number;
+  total: number;
+  estimated_tokens: number;
+}
+
+/** The system prompt plus a summary of the changes already made in Studio, so long sessions don't lose track of them */
+export async function buildSystemPrompt(): Promise<string> {
+  const context = await invoke<ChangeContext>("get_change_context").catch(() => null);
+  return context?.text ? `${ROBLOX_SYSTEM_PROMPT}\n\n${context.text}` : ROBLOX_SYSTEM_PROMPT;
+}
+
 export interface ToolCallEvent {
   id: string;
   name: string;
@@ -162,12 +198,11 @@ export interface ChatCallbacks {
 export interface ChatOptions extends ChatCallbacks {
   model: string;
   provider: ProviderType;
-  apiKey: string;
   messages: Array<{ role: "user" | "assistant"; content: string }>;
 }
 
 export async function chat(options: ChatOptions) {
-  const { model, provider, apiKey, messages, onToken, onToolCall, onToolResult, onFinish, onError } = options;
+  const { model, provider, messages, onToken, onToolCall, onToolResult, onFinish, onError } = options;
 
   console.log("[Chat] Starting chat with:", { model, provider, messageCount: messages.length });
 
@@ -179,14 +214,14 @@ export async function chat(options: ChatOptions) {
     }
 
     // For OpenAI/Anthropic, use standard AI SDK
-    const providerInstance = getProvider(provider, apiKey);
+    const providerInstance = await getProvider(provider);
 
     console.log("[Chat] Created provider instance, starting stream...");
 
     const result = streamText({
       model: providerInstance(model),
-      system: ROBLOX_SYSTEM_PROMPT,
-      tools: robloxTools,
+      system: await buildSystemPrompt(),
+      tools: toolsForProfile(useRobloxStore.getState().placeProfile),
       stopWhen: stepCountIs(10), // Allow up to 10 steps for multi-step tool calls
       messages: messages.map((m) => ({
         role: m.role,
@@ -242,7 +277,7 @@ export async function chat(options: ChatOptions) {
 
 // Hook for using chat in components
 export function useChat() {
-  const { selectedModel, selectedProvider, getApiKey } = useSettingsStore();
+  const { selectedModel, selectedProvider, hasApiKey } = useSettingsStore();
   const { authMethod, isOAuthAuthenticated } = useAuthStore();
 
   const sendMessage = async (
@@ -251,7 +286,6 @@ export function useChat() {
   ) => {
     // Determine provider and auth method
     let provider: ProviderType;
-    let apiKey: string;
     let model: string;
 
     console.log("[useChat] Selected provider:", selectedProvider, "Model:", selectedModel);
@@ -265,37 +299,34 @@ export function useChat() {
       // Use Codex with OAuth
       console.log("[useChat] Using Codex with OAuth");
       provider = "codex";
-      apiKey = "codex-oauth"; // Dummy, actual auth handled in codexFetch
       model = selectedModel;
     } else if (useCodex && !isOAuthAuthenticated()) {
       // Codex selected but not authenticated - try OpenAI API key
       console.log("[useChat] Codex selected but not OAuth authenticated, trying OpenAI API key");
-      const openaiKey = getApiKey("openai");
-      if (openaiKey) {
+      if (hasApiKey("openai")) {
         provider = "openai";
-        apiKey = openaiKey;
         model = selectedModel;
       } else {
         throw new Error("Please sign in with ChatGPT Plus/Pro or add an OpenAI API key in settings");
       }
+    } else if (selectedProvider === "ollama") {
+      // Local models need no key
+      provider = "ollama";
+      model = selectedModel;
     } else {
       // Use API key
       provider = selectedProvider === "codex" ? "openai" : selectedProvider;
-      const key = getApiKey(provider as "openai" | "anthropic");
-
-      if (!key) {
+      if (!hasApiKey(provider as "openai" | "anthropic" | "openrouter")) {
         throw new Error(`No API key configured for ${provider}. Please add one in settings or sign in with ChatGPT Plus/Pro.`);
       }
 
       console.log("[useChat] Using API key for provider:", provider);
-      apiKey = key;
       model = selectedModel;
     }
 
     return chat({
       model,
       provider,
-      apiKey,
       messages,
       ...callbacks,
     });
@@ -306,8 +337,8 @@ export function useChat() {
 
 // Check if any auth is configured
 export function hasAnyAuth(): boolean {
-  const { apiKeys } = useSettingsStore.getState();
-  const hasApiKey = !!(apiKeys.openai || apiKeys.anthropic);
+  const { hasApiKey: hasKey } = useSettingsStore.getState();
+  const hasApiKey = hasKey("openai") || hasKey("anthropic") || hasKey("openrouter");
   const hasOAuth = isCodexAuthenticated();
   return hasApiKey || hasOAuth;
 }
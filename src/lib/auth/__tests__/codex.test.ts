@@ -1,14 +1,18 @@
-import { describe, it, expect, beforeEach } from "vitest";
+import { describe, it, expect, beforeEach, vi } from "vitest";
+import { invoke } from "@tauri-apps/api/core";
 import {
   getStoredAuth,
   saveAuth,
   clearAuth,
   isAuthenticated,
+  migrateLegacyAuth,
   extractAccountIdFromClaims,
   type OAuthAuth,
   type IdTokenClaims,
 } from "../codex";
 
+vi.mock("@tauri-apps/api/core", () => ({ invoke: vi.fn() }));
+
 describe("Codex Auth", () => {
   beforeEach(() => {
     clearAuth();
@@ -23,8 +27,6 @@ describe("Codex Auth", () => {
     it("should save and retrieve auth", () => {
       const auth: OAuthAuth = {
         type: "oauth",
-        access: "test-access-token",
-        refresh: "test-refresh-token",
         expires: Date.now() + 3600000,
         accountId: "test-account-id",
       };
@@ -38,8 +40,6 @@ describe("Codex Auth", () => {
     it("should clear auth", () => {
       const auth: OAuthAuth = {
         type: "oauth",
-        access: "test-access-token",
-        refresh: "test-refresh-token",
         expires: Date.now() + 3600000,
       };
 
@@ -56,11 +56,9 @@ describe("Codex Auth", () => {
       expect(isAuthenticated()).toBe(false);
     });
 
-    it("should return true when auth with refresh token is stored", () => {
+    it("should return true when sign-in status is stored", () => {
       const auth: OAuthAuth = {
         type: "oauth",
-        access: "test-access-token",
-        refresh: "test-refresh-token",
         expires: Date.now() + 3600000,
       };
 
@@ -83,6 +81,58 @@ describe("Codex Auth", () => {
     });
   });
 
+  describe("migrateLegacyAuth", () => {
+    const expires = Date.now() + 3600000;
+
+    beforeEach(() => {
+      vi.mocked(invoke).mockReset();
+      localStorage.setItem(
+        "stud_chatgpt_auth",
+        JSON.stringify({
+          type: "oauth",
+          access: "test-access-token",
+          refresh: "test-refresh-token",
+          expires,
+          accountId: "test-account-id",
+        })
+      );
+    });
+
+    it("should hand tokens stored by older versions to the backend", async () => {
+      const status = { account_id: "test-account-id", email: null, expires };
+      // Both import_tokens and get_oauth_status answer with the status
+      vi.mocked(invoke).mockResolvedValue(status);
+
+      await migrateLegacyAuth();
+
+      expect(invoke).toHaveBeenCalledWith("import_tokens", {
+        refresh: "test-refresh-token",
+        access: "test-access-token",
+        expires,
+        accountId: "test-account-id",
+      });
+      expect(localStorage.getItem("stud_chatgpt_auth")).not.toContain("test-refresh-token");
+      expect(isAuthenticated()).toBe(true);
+      expect(getStoredAuth()?.accountId).toBe("test-account-id");
+    });
+
+    it("should keep the stored tokens when the backend can't take them", async () => {
+      vi.mocked(invoke).mockRejectedValue(new Error("keychain locked"));
+
+      await migrateLegacyAuth();
+
+      expect(localStorage.getItem("stud_chatgpt_auth")).toContain("test-refresh-token");
+    });
+
+    it("should do nothing once migrated", async () => {
+      clearAuth();
+
+      await migrateLegacyAuth();
+
+      expect(invoke).not.toHaveBeenCalled();
+    });
+  });
+
   describe("extractAccountIdFromClaims", () => {
     it("should extract chatgpt_account_id", () => {
       const claims: IdTokenClaims = {
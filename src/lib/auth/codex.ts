@@ -3,35 +3,51 @@
  *
  * Uses OAuth 2.0 with PKCE to authenticate with OpenAI's auth server
  * and proxy requests through ChatGPT's Codex API endpoint.
+ *
+ * The backend exchanges the code and keeps the tokens; its Codex proxy adds
+ * them to each request. The webview only knows whether it's signed in.
  */
 
-import { fetch as tauriFetch } from "@tauri-apps/plugin-http";
+import { invoke } from "@tauri-apps/api/core";
+import { bridgeHeaders } from "@/lib/secret";
 
 // OAuth Configuration
-const CLIENT_ID = "app_EMoamEEZ73f0CkXaXp7hrann";
-const ISSUER = "https://auth.openai.com";
-// Use Tauri HTTP plugin to bypass CORS when calling Codex API
-const CODEX_API_ENDPOINT = "https://chatgpt.com/backend-api/codex/responses";
 const OAUTH_PORT = 1455;
 const REDIRECT_URI = `http://localhost:${OAUTH_PORT}/auth/callback`;
 
-// Token storage key
+// Sign-in status storage key
 const AUTH_STORAGE_KEY = "stud_chatgpt_auth";
 
-export interface TokenResponse {
-  access_token: string;
-  refresh_token: string;
-  expires_in: number;
-  token_type: string;
-  id_token?: string;
-}
-
+// What the webview knows about the sign-in; the tokens stay in the backend
 export interface OAuthAuth {
   type: "oauth";
-  access: string;
-  refresh: string;
   expires: number;
   accountId?: string;
+  email?: string;
+}
+
+// Status reported by the backend's get_oauth_status
+interface OAuthStatus {
+  account_id: string | null;
+  email: string | null;
+  expires: number;
+}
+
+// A signed-in ChatGPT account, as listed by the backend
+export interface OAuthAccount extends OAuthStatus {
+  key: string;
+  active: boolean;
+}
+
+// Services the backend can sign in to
+export type OAuthProvider = "chatgpt" | "anthropic" | "google";
+
+// Payload of the backend's "oauth-complete" event
+export interface OAuthResult {
+  provider?: OAuthProvider;
+  state: string;
+  error?: string;
+  account_id?: string;
 }
 
 export interface IdTokenClaims {
@@ -43,48 +59,13 @@ export interface IdTokenClaims {
   };
 }
 
-interface PkceCodes {
-  verifier: string;
-  challenge: string;
-}
-
-// PKCE Helper Functions
-function generateRandomString(length: number): string {
-  const chars = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
-  const array = new Uint8Array(length);
-  crypto.getRandomValues(array);
-  return Array.from(array, (byte) => chars[byte % chars.length]).join("");
-}
-
-function base64UrlEncode(buffer: ArrayBuffer): string {
-  const bytes = new Uint8Array(buffer);
-  let binary = "";
-  for (let i = 0; i < bytes.length; i++) {
-    binary += String.fromCharCode(bytes[i]);
-  }
-  return btoa(binary)
-    .replace(/\+/g, "-")
-    .replace(/\//g, "_")
-    .replace(/=+$/, "");
-}
-
-async function generatePKCE(): Promise<PkceCodes> {
-  const verifier = generateRandomString(43);
-  const encoder = new TextEncoder();
-  const data = encoder.encode(verifier);
-  const hash = await crypto.subtle.digest("SHA-256", data);
-  const challenge = base64UrlEncode(hash);
-  return { verifier, challenge };
-}
-
-function decodeJwt(token: string): IdTokenClaims {
-  const parts = token.split(".");
-  if (parts.length !== 3) {
-    throw new Error("Invalid JWT format");
-  }
-  const payload = parts[1];
-  const decoded = atob(payload.replace(/-/g, "+").replace(/_/g, "/"));
-  return JSON.parse(decoded);
+// A sign-in started by the backend, which keeps the PKCE verifier until the code is exchanged
+interface AuthRequest {
+  provider: OAuthProvider;
+  url: string;
+  state: string;
+  // The localhost callback, or the stud:// deep link when its port is taken
+  redirect_uri: string;
 }
 
 export function extractAccountIdFromClaims(claims: IdTokenClaims): string | undefined {
@@ -95,74 +76,15 @@ export function extractAccountIdFromClaims(claims: IdTokenClaims): string | unde
   );
 }
 
-// Build OAuth authorize URL
-function buildAuthorizeUrl(pkce: PkceCodes, state: string): string {
-  const params = new URLSearchParams({
-    response_type: "code",
-    client_id: CLIENT_ID,
-    redirect_uri: REDIRECT_URI,
-    scope: "openid profile email offline_access",
-    code_challenge: pkce.challenge,
-    code_challenge_method: "S256",
-    id_token_add_organizations: "true",
-    codex_cli_simplified_flow: "true",
-    state,
-    originator: "stud",
-  });
-  return `${ISSUER}/oauth/authorize?${params.toString()}`;
-}
-
-// Exchange authorization code for tokens
-async function exchangeCodeForTokens(
-  code: string,
-  pkce: PkceCodes
-): Promise<TokenResponse> {
-  const response = await fetch(`${ISSUER}/oauth/token`, {
-    method: "POST",
-    headers: { "Content-Type": "application/x-www-form-urlencoded" },
-    body: new URLSearchParams({
-      grant_type: "authorization_code",
-      code,
-      redirect_uri: REDIRECT_URI,
-      client_id: CLIENT_ID,
-      code_verifier: pkce.verifier,
-    }).toString(),
-  });
-
-  if (!response.ok) {
-    const error = await response.text();
-    throw new Error(`Token exchange failed: ${error}`);
-  }
-
-  return response.json();
-}
-
-// Refresh access token
-async function refreshAccessToken(refreshToken: string): Promise<TokenResponse> {
-  const response = await fetch(`${ISSUER}/oauth/token`, {
-    method: "POST",
-    headers: { "Content-Type": "application/x-www-form-urlencoded" },
-    body: new URLSearchParams({
-      grant_type: "refresh_token",
-      refresh_token: refreshToken,
-      client_id: CLIENT_ID,
-    }).toString(),
-  });
-
-  if (!response.ok) {
-    const error = await response.text();
-    throw new Error(`Token refresh failed: ${error}`);
-  }
-
-  return response.json();
-}
-
 // Storage functions
 export function getStoredAuth(): OAuthAuth | null {
   try {
     const stored = localStorage.getItem(AUTH_STORAGE_KEY);
     if (!stored) return null;
-    return JSON.parse(stored);
+    const auth = JSON.parse(stored);
+    // Older versions kept the tokens here; they count once migrateLegacyAuth hands them over
+    if ("access" in auth || "refresh" in auth) return null;
+    return auth;
   } catch {
     return null;
   }
@@ -178,139 +100,145 @@ export function clearAuth(): void {
 
 export function isAuthenticated(): boolean {
   const auth = getStoredAuth();
-  const result = auth !== null && auth.refresh !== undefined;
-  console.log("[Codex] isAuthenticated check:", { hasAuth: auth !== null, hasRefresh: auth?.refresh !== undefined, result });
+  const result = auth !== null && auth.type === "oauth";
+  console.log("[Codex] isAuthenticated check:", { hasAuth: auth !== null, result });
   return result;
 }
 
-// Get valid access token (refreshing if needed)
-export async function getValidAccessToken(): Promise<string | null> {
-  const auth = getStoredAuth();
-  if (!auth) return null;
-
-  // Check if token is expired (with 5 min buffer)
-  const bufferMs = 5 * 60 * 1000;
-  if (auth.expires - bufferMs > Date.now()) {
-    return auth.access;
+// Bring the stored status in line with the backend's sign-in
+export async function syncOAuthStatus(): Promise<OAuthAuth | null> {
+  const status = await invoke<OAuthStatus | null>("get_oauth_status");
+  if (!status) {
+    clearAuth();
+    return null;
   }
+  const auth: OAuthAuth = {
+    type: "oauth",
+    expires: status.expires,
+    accountId: status.account_id ?? undefined,
+    email: status.email ?? undefined,
+  };
+  saveAuth(auth);
+  return auth;
+}
 
-  // Refresh the token
+// Tokens as older versions stored them, before the backend kept them
+interface LegacyAuth {
+  access?: string;
+  refresh?: string;
+  expires?: number;
+  accountId?: string;
+}
+
+// Hand tokens stored by an older version to the backend, then keep only the
+// status here. Without a refresh token there's nothing to hand over, so sign in again.
+// Left in place if the backend can't take them, to try again next launch.
+export async function migrateLegacyAuth(): Promise<void> {
+  let legacy: LegacyAuth;
   try {
-    console.log("[Codex] Refreshing access token...");
-    const tokens = await refreshAccessToken(auth.refresh);
-    
-    let accountId = auth.accountId;
-    if (tokens.id_token) {
-      const claims = decodeJwt(tokens.id_token);
-      accountId = extractAccountIdFromClaims(claims) || accountId;
-    }
-
-    const newAuth: OAuthAuth = {
-      type: "oauth",
-      access: tokens.access_token,
-      refresh: tokens.refresh_token || auth.refresh,
-      expires: Date.now() + tokens.expires_in * 1000,
-      accountId,
-    };
-    
-    saveAuth(newAuth);
-    return newAuth.access;
-  } catch (error) {
-    console.error("[Codex] Token refresh failed:", error);
+    legacy = JSON.parse(localStorage.getItem(AUTH_STORAGE_KEY) ?? "null");
+  } catch {
+    return;
+  }
+  if (!legacy || !("access" in legacy || "refresh" in legacy)) return;
+  if (!legacy.refresh) {
     clearAuth();
-    return null;
+    return;
+  }
+  try {
+    await invoke("import_tokens", {
+      refresh: legacy.refresh,
+      access: legacy.access ?? null,
+      expires: legacy.expires ?? null,
+      accountId: legacy.accountId ?? null,
+    });
+  } catch (error) {
+    console.warn("[Codex] Failed to hand stored tokens to the backend:", error);
+    return;
   }
+  clearAuth();
+  await syncOAuthStatus();
 }
 
-// Start OAuth login flow
-export async function startOAuthLogin(): Promise<{ url: string; state: string }> {
-  const pkce = await generatePKCE();
-  const state = generateRandomString(32);
-  const url = buildAuthorizeUrl(pkce, state);
-
-  // Store for callback handling
-  sessionStorage.setItem("oauth_pkce", JSON.stringify(pkce));
-  sessionStorage.setItem("oauth_state", state);
-  
-  return { url, state };
+// Every ChatGPT account signed in to the backend
+export async function listAccounts(): Promise<OAuthAccount[]> {
+  return invoke<OAuthAccount[]>("list_accounts");
 }
 
-// Handle OAuth callback (called when redirect comes back)
-export async function handleOAuthCallback(
-  code: string,
-  state: string
-): Promise<OAuthAuth> {
-  const storedState = sessionStorage.getItem("oauth_state");
-  const storedPkce = sessionStorage.getItem("oauth_pkce");
+// Make an account the one Codex requests use, and return the new status
+export async function switchAccount(key: string): Promise<OAuthAuth | null> {
+  await invoke("switch_account", { key });
+  return syncOAuthStatus();
+}
 
-  if (!storedState || !storedPkce) {
-    throw new Error("No pending OAuth request found");
-  }
+// Forget one account; another signed-in account takes over if it was active
+export async function removeAccount(key: string): Promise<OAuthAuth | null> {
+  await invoke("remove_account", { key });
+  return syncOAuthStatus();
+}
 
-  if (state !== storedState) {
-    throw new Error("OAuth state mismatch - possible CSRF attack");
+// Codex requests go through the backend proxy, which adds the credentials
+export async function getCodexEndpoint(): Promise<string> {
+  const { codex_proxy_url } = await invoke<{ codex_proxy_url: string | null }>("get_bridge_endpoints");
+  if (!codex_proxy_url) {
+    throw new Error("Codex proxy is not running");
   }
+  return `${codex_proxy_url}/llm/chatgpt/responses`;
+}
 
-  const pkce: PkceCodes = JSON.parse(storedPkce);
+// Start OAuth login flow
+export async function startOAuthLogin(
+  provider: OAuthProvider = "chatgpt"
+): Promise<{ url: string; state: string }> {
+  // Binds the callback server for the length of the sign-in and builds the authorize URL
+  const { url, state } = await invoke<AuthRequest>("start_auth", { provider });
+  return { url, state };
+}
 
-  // Clean up session storage
-  sessionStorage.removeItem("oauth_state");
-  sessionStorage.removeItem("oauth_pkce");
+// A device code sign-in waiting for the user to enter the code
+export interface DeviceCode {
+  provider: OAuthProvider;
+  flow_id: string;
+  user_code: string;
+  verification_uri: string;
+  expires_in: number;
+}
 
-  // Exchange code for tokens
-  const tokens = await exchangeCodeForTokens(code, pkce);
+// Start a sign-in by code, for when the browser can't redirect back to the app;
+// the outcome arrives as "oauth-complete" with the flow id as its state
+export async function startDeviceLogin(provider: OAuthProvider = "chatgpt"): Promise<DeviceCode> {
+  return invoke<DeviceCode>("start_device_auth", { provider });
+}
 
-  // Extract account ID from ID token
-  let accountId: string | undefined;
-  if (tokens.id_token) {
-    const claims = decodeJwt(tokens.id_token);
-    accountId = extractAccountIdFromClaims(claims);
+// Handle the outcome of a sign-in once the backend has exchanged the code
+export async function handleOAuthResult(result: OAuthResult): Promise<OAuthAuth> {
+  if (result.error) {
+    throw new Error(`Sign-in failed: ${result.error}`);
+  }
+  const auth = await syncOAuthStatus();
+  if (!auth) {
+    throw new Error("Sign-in finished but the backend has no tokens");
   }
-
-  const auth: OAuthAuth = {
-    type: "oauth",
-    access: tokens.access_token,
-    refresh: tokens.refresh_token,
-    expires: Date.now() + tokens.expires_in * 1000,
-    accountId,
-  };
-
-  saveAuth(auth);
   return auth;
 }
 
-// Codex fetch wrapper - uses Tauri HTTP plugin to bypass CORS
+// Codex fetch wrapper - sends requests through the backend's Codex proxy
 export async function codexFetch(
   _input: RequestInfo | URL,
   init?: RequestInit
 ): Promise<Response> {
   console.log("[Codex] codexFetch called");
 
-  const accessToken = await getValidAccessToken();
-  if (!accessToken) {
-    console.error("[Codex] No valid access token available");
+  if (!isAuthenticated()) {
+    console.error("[Codex] Not signed in");
     throw new Error("Not authenticated with ChatGPT Plus/Pro");
   }
 
-  const auth = getStoredAuth();
-
-  console.log("[Codex] Making request to Codex API via Tauri HTTP plugin");
-
-  // Build headers
-  const headers: Record<string, string> = {
-    "Authorization": `Bearer ${accessToken}`,
-    "Content-Type": "application/json",
-  };
-
-  // Set ChatGPT Account ID for organization subscriptions
-  if (auth?.accountId) {
-    headers["ChatGPT-Account-Id"] = auth.accountId;
-  }
+  console.log("[Codex] Making request to Codex API via the backend proxy");
 
-  // Use Tauri's fetch which bypasses CORS
-  const response = await tauriFetch(CODEX_API_ENDPOINT, {
+  const response = await fetch(await getCodexEndpoint(), {
     ...init,
-    headers,
+    headers: await bridgeHeaders({ "Content-Type": "application/json" }),
   });
 
   console.log("[Codex] Response status:", response.status, response.statusText);
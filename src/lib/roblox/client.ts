@@ -1,38 +1,111 @@
 /**
  * HTTP Client for Roblox Studio communication via Bridge Server
  *
- * The bridge server runs on localhost:3001 and acts as an intermediary
- * between Stud and the Roblox Studio plugin.
+ * The bridge server runs on localhost (port 3001 unless configured or taken)
+ * and acts as an intermediary between Stud and the Roblox Studio plugin. Its
+ * URL comes from the backend and is cached until the bridge moves.
+ *
+ * Every bridge call carries the per-install secret in X-Stud-Secret.
  */
 
-const BRIDGE_URL = "http://localhost:3001"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import { useChatStore } from "@/stores/chat"
+import { bridgeHeaders } from "@/lib/secret"
+
 const TIMEOUT_MS = 15000
+// Extra tries when the connection to the bridge drops before a response
+const RETRY_ATTEMPTS = 2
+
+let bridgeUrl: Promise<string> | null = null
+
+// Forget the cached URL whenever the bridge binds or moves to another port
+listen("bridge-endpoints", () => {
+  bridgeUrl = null
+})
+
+async function getBridgeUrl(): Promise<string> {
+  bridgeUrl ??= invoke<{ bridge_url: string | null }>("get_bridge_endpoints").then(({ bridge_url }) => {
+    if (!bridge_url) {
+      throw new Error("Bridge server is not running")
+    }
+    return bridge_url
+  })
+  try {
+    return await bridgeUrl
+  } catch (e) {
+    // Ask again next time; the bridge may have started since
+    bridgeUrl = null
+    throw e
+  }
+}
 
 export type StudioResponse<T> = { success: true; data: T } | { success: false; error: string }
 
+/** Higher priority requests are handed to Studio before queued background work */
+export type RequestPriority = "high" | "normal" | "low"
+
 /**
- * Send a request to Roblox Studio via the bridge server
+ * Send a request to Roblox Studio via the bridge server.
+ * With a toolCallId, large (chunked) responses are streamed to that tool call
+ * as "tool-partial-result" events while they arrive.
  */
-export async function studioRequest<T>(endpoint: string, data?: object): Promise<StudioResponse<T>> {
+export async function studioRequest<T>(
+  endpoint: string,
+  data?: object,
+  priority: RequestPriority = "normal",
+  toolCallId?: string
+): Promise<StudioResponse<T>> {
   const controller = new AbortController()
   const timeout = setTimeout(() => controller.abort(), TIMEOUT_MS)
+  // Tag the request with its conversation so it can be listed and cancelled per chat
+  const chat = useChatStore.getState()
+  // One key for the whole call, so the bridge runs it in Studio once however often it's retried
+  const idempotencyKey = crypto.randomUUID()
 
   try {
-    const response = await fetch(`${BRIDGE_URL}/stud/request`, {
-      method: "POST",
-      headers: { "Content-Type": "application/json" },
-      body: JSON.stringify({
-        path: endpoint,
-        body: data ? JSON.stringify(data) : undefined,
-      }),
-      signal: controller.signal,
-    })
+    const send = async () =>
+      fetch(`${await getBridgeUrl()}/stud/request`, {
+        method: "POST",
+        headers: await bridgeHeaders({
+          "Content-Type": "application/json",
+          "Idempotency-Key": idempotencyKey,
+        }),
+        body: JSON.stringify({
+          path: endpoint,
+          body: data ? JSON.stringify(data) : undefined,
+          priority,
+          tool_call_id: toolCallId,
+          chat_id: chat.chatId,
+          turn_id: chat.turnId ?? undefined,
+        }),
+        signal: controller.signal,
+      })
+    let response: Response
+    for (let attempt = 0; ; attempt++) {
+      try {
+        response = await send()
+        break
+      } catch (e) {
+        if (controller.signal.aborted || attempt >= RETRY_ATTEMPTS) throw e
+      }
+    }
 
     if (!response.ok) {
       const text = await response.text()
       try {
         const json = JSON.parse(text)
-        return { success: false, error: json.error || `Error ${response.status}` }
+        const error = json.error || `Error ${response.status}`
+        // Rule violations (naming, attribute schema) explain why a change was refused
+        if (Array.isArray(json.violations) && json.violations.length > 0) {
+          const details = json.violations.map((v: { path: string; message: string }) => `${v.path}: ${v.message}`)
+          return { success: false, error: `${error}\n${details.join("\n")}` }
+        }
+        // Queue full: tell the caller (often the AI) to back off instead of retrying immediately
+        if (response.status === 429 && typeof json.retry_after_secs === "number") {
+          return { success: false, error: `${error}. Retry in ${json.retry_after_secs}s.` }
+        }
+        return { success: false, error }
       } catch {
         return { success: false, error: `Studio error ${response.status}: ${text}` }
       }
@@ -58,8 +131,9 @@ export async function studioRequest<T>(endpoint: string, data?: object): Promise
  */
 export async function isStudioConnected(): Promise<boolean> {
   try {
-    const response = await fetch(`${BRIDGE_URL}/stud/status`, {
+    const response = await fetch(`${await getBridgeUrl()}/stud/status`, {
       method: "GET",
+      headers: await bridgeHeaders(),
       signal: AbortSignal.timeout(1000),
     })
     if (!response.ok) return false
@@ -75,8 +149,9 @@ export async function isStudioConnected(): Promise<boolean> {
  */
 export async function isBridgeRunning(): Promise<boolean> {
   try {
-    const response = await fetch(`${BRIDGE_URL}/stud/status`, {
+    const response = await fetch(`${await getBridgeUrl()}/stud/status`, {
       method: "GET",
+      headers: await bridgeHeaders(),
       signal: AbortSignal.timeout(1000),
     })
     return response.ok
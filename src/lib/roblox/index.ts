@@ -1,5 +1,5 @@
 export { studioRequest, isStudioConnected, isBridgeRunning, notConnectedError } from "./client"
-export { robloxTools } from "./tools"
+export { robloxTools, toolsForProfile } from "./tools"
 export {
   robloxGetScript,
   robloxSetScript,
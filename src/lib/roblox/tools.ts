@@ -5,9 +5,12 @@
  */
 
 import { tool } from "ai"
+import { invoke } from "@tauri-apps/api/core"
 import { z } from "zod"
 import { studioRequest, isStudioConnected, notConnectedError } from "./client"
 import { searchToolbox, getAssetDetails, type AssetCategory } from "./toolbox"
+import type { ConflictResolution, ScriptMergeConflict } from "@/stores/chat"
+import type { PlaceProfile } from "@/stores/roblox"
 
 // ============================================================================
 // Types
@@ -19,6 +22,13 @@ interface ScriptContent {
   className: string
 }
 
+interface ScriptMergeResult {
+  status: "applied" | "merged" | "unchanged" | "conflict"
+  path: string
+  merge_id: string | null
+  conflicts: ScriptMergeConflict[]
+}
+
 interface InstanceInfo {
   path: string
   name: string
@@ -49,16 +59,19 @@ Examples:
   inputSchema: z.object({
     path: z.string().describe("Full instance path to the script (e.g. game.ServerScriptService.MainScript)"),
   }),
-  execute: async ({ path }: { path: string }) => {
+  execute: async ({ path }: { path: string }, { toolCallId }: { toolCallId: string }) => {
     if (!(await isStudioConnected())) {
       return { error: notConnectedError() }
     }
 
-    const result = await studioRequest<ScriptContent>("/script/get", { path })
+    const result = await studioRequest<ScriptContent>("/script/get", { path }, "high", toolCallId)
     if (!result.success) {
       return { error: result.error }
     }
 
+    // The base for merging this script with the user's edits when the AI rewrites it
+    invoke("record_script_base", { path: result.data.path, source: result.data.source }).catch(() => {})
+
     const lines = result.data.source.split("\n")
     const numbered = lines.map((line, i) => `${(i + 1).toString().padStart(5, "0")}| ${line}`).join("\n")
 
@@ -70,13 +83,23 @@ Examples:
   },
 })
 
+// Asks the user to settle conflicts between their edits and the AI's; null keeps their version
+let mergeConflictHandler:
+  | ((path: string, conflicts: ScriptMergeConflict[]) => Promise<ConflictResolution[] | null>)
+  | null = null
+
+export const setMergeConflictHandler = (handler: typeof mergeConflictHandler) => {
+  mergeConflictHandler = handler
+}
+
 export const robloxSetScript = tool({
   description: `Replace the entire source code of a script in Roblox Studio.
 
 Use this to completely replace a script's contents.
 For partial edits, consider using roblox_edit_script instead.
 
-The path should be the full instance path from game root.`,
+The path should be the full instance path from game root.
+If the user edited the script since you read it, their changes are merged with yours.`,
   inputSchema: z.object({
     path: z.string().describe("Full instance path to the script"),
     source: z.string().describe("The new source code for the script"),
@@ -86,13 +109,46 @@ The path should be the full instance path from game root.`,
       return { error: notConnectedError() }
     }
 
-    const result = await studioRequest<{ path: string }>("/script/set", { path, source })
-    if (!result.success) {
-      return { error: result.error }
+    let merge: ScriptMergeResult
+    try {
+      merge = await invoke<ScriptMergeResult>("merge_script_edit", { path, source })
+    } catch (e) {
+      return { error: e instanceof Error ? e.message : String(e) }
+    }
+
+    if (merge.status === "conflict" && merge.merge_id) {
+      const resolutions = mergeConflictHandler
+        ? await mergeConflictHandler(merge.path, merge.conflicts)
+        : null
+      if (!resolutions) {
+        await invoke("discard_script_merge", { mergeId: merge.merge_id }).catch(() => {})
+        return {
+          error: `The user edited ${merge.path} in the same places and kept their version. Read the script again before editing it.`,
+        }
+      }
+      try {
+        await invoke("resolve_script_merge", { mergeId: merge.merge_id, resolutions })
+      } catch (e) {
+        return { error: e instanceof Error ? e.message : String(e) }
+      }
+      return {
+        success: true,
+        path: merge.path,
+        merged: true,
+        conflictsResolved: resolutions.length,
+        note: "Merged with the user's edits; read the script again to see the result.",
+      }
     }
 
     const lines = source.split("\n").length
-    return { success: true, path: result.data.path, lines }
+    return {
+      success: true,
+      path: merge.path,
+      lines,
+      ...(merge.status === "merged"
+        ? { merged: true, note: "Merged with the user's edits; read the script again to see the result." }
+        : {}),
+    }
   },
 })
 
@@ -130,6 +186,80 @@ Example:
   },
 })
 
+interface FindReplaceTransaction {
+  id: string | null
+  scripts_searched: number
+  total_matches: number
+  files: { path: string; matches: number; preview: { line: number; before: string; after: string }[] }[]
+  applied: boolean
+}
+
+export const robloxFindReplaceAll = tool({
+  description: `Find and replace text across every script in the place in one step.
+
+Much cheaper and safer than editing scripts one by one for renames and API migrations.
+Set preview to true to see per-file match counts and sample lines first, then call again
+with the returned transactionId to apply exactly that change. All files are written as
+one undo step, and nothing is written if a script changed since the preview.
+
+Examples:
+  pattern: "OldModule", replacement: "NewModule"
+  pattern: "wait\\((\\d+)\\)", replacement: "task.wait($1)", regex: true`,
+  inputSchema: z.object({
+    pattern: z.string().describe("Text to find (a regex if regex is true)"),
+    replacement: z.string().describe("Replacement text; with regex, $1 etc. insert capture groups"),
+    scope: z.string().optional().describe("Only scripts under this instance path (e.g. game.ServerScriptService)"),
+    regex: z.boolean().optional().describe("Treat pattern as a regular expression"),
+    caseSensitive: z.boolean().optional().describe("Defaults to true"),
+    preview: z.boolean().optional().describe("Only report what would change"),
+    transactionId: z.string().optional().describe("Apply a previewed transaction"),
+  }),
+  execute: async ({
+    pattern,
+    replacement,
+    scope,
+    regex,
+    caseSensitive,
+    preview,
+    transactionId,
+  }: {
+    pattern: string
+    replacement: string
+    scope?: string
+    regex?: boolean
+    caseSensitive?: boolean
+    preview?: boolean
+    transactionId?: string
+  }) => {
+    if (!(await isStudioConnected())) {
+      return { error: notConnectedError() }
+    }
+
+    try {
+      if (transactionId) {
+        const applied = await invoke<number>("apply_find_replace", { id: transactionId })
+        return { success: true, applied: true, filesChanged: applied }
+      }
+
+      const args = { pattern, replacement, scope, regex, caseSensitive }
+      const transaction = await invoke<FindReplaceTransaction>(
+        preview ? "preview_find_replace" : "find_replace_all",
+        args
+      )
+      return {
+        success: true,
+        applied: transaction.applied,
+        transactionId: transaction.applied ? undefined : transaction.id,
+        scriptsSearched: transaction.scripts_searched,
+        totalMatches: transaction.total_matches,
+        files: transaction.files,
+      }
+    } catch (e) {
+      return { error: e instanceof Error ? e.message : String(e) }
+    }
+  },
+})
+
 // ============================================================================
 // Instance Tools
 // ============================================================================
@@ -148,12 +278,20 @@ Examples:
     path: z.string().describe("Full instance path (e.g. game.Workspace)"),
     recursive: z.boolean().optional().describe("If true, get all descendants recursively"),
   }),
-  execute: async ({ path, recursive = false }: { path: string; recursive?: boolean }) => {
+  execute: async (
+    { path, recursive = false }: { path: string; recursive?: boolean },
+    { toolCallId }: { toolCallId: string }
+  ) => {
     if (!(await isStudioConnected())) {
       return { error: notConnectedError() }
     }
 
-    const result = await studioRequest<InstanceInfo[]>("/instance/children", { path, recursive })
+    const result = await studioRequest<InstanceInfo[]>(
+      "/instance/children",
+      { path, recursive },
+      recursive ? "low" : "normal",
+      toolCallId
+    )
     if (!result.success) {
       return { error: result.error }
     }
@@ -357,7 +495,7 @@ Useful for operating on what the user has selected in the Explorer.`,
       return { error: notConnectedError() }
     }
 
-    const result = await studioRequest<InstanceInfo[]>("/selection/get")
+    const result = await studioRequest<InstanceInfo[]>("/selection/get", undefined, "high")
     if (!result.success) {
       return { error: result.error }
     }
@@ -386,12 +524,12 @@ Examples:
   inputSchema: z.object({
     code: z.string().describe("Luau code to execute"),
   }),
-  execute: async ({ code }: { code: string }) => {
+  execute: async ({ code }: { code: string }, { toolCallId }: { toolCallId: string }) => {
     if (!(await isStudioConnected())) {
       return { error: notConnectedError() }
     }
 
-    const result = await studioRequest<{ output: string; error?: string }>("/code/run", { code })
+    const result = await studioRequest<{ output: string; error?: string }>("/code/run", { code }, "normal", toolCallId)
     if (!result.success) {
       return { error: result.error }
     }
@@ -546,6 +684,215 @@ Example: Make all parts red and anchored
   },
 })
 
+// ============================================================================
+// Tag Tools
+// ============================================================================
+
+interface TagRegistry {
+  summary: string
+}
+
+interface TagResult {
+  tag: string
+  changed: string[]
+  unchanged: number
+  missing: string[]
+}
+
+export const robloxListTags = tool({
+  description: `List the CollectionService tags in the game.
+
+Shows how many instances carry each tag, their classes, and which scripts look the tag up.
+Check this before building tag-driven systems so you reuse existing tags.
+Pass a path to get the tags on a single instance instead.`,
+  inputSchema: z.object({
+    path: z.string().optional().describe("Instance path to read tags from"),
+    refresh: z.boolean().optional().describe("Re-scan the game instead of using the cached registry"),
+  }),
+  execute: async ({ path, refresh = false }: { path?: string; refresh?: boolean }) => {
+    if (!(await isStudioConnected())) {
+      return { error: notConnectedError() }
+    }
+
+    try {
+      if (path) {
+        return { path, tags: await invoke<string[]>("get_instance_tags", { path }) }
+      }
+      const registry = await invoke<TagRegistry>("list_tags", { refresh })
+      return { tags: registry.summary }
+    } catch (error) {
+      return { error: String(error) }
+    }
+  },
+})
+
+export const robloxUpdateTags = tool({
+  description: `Add or remove a CollectionService tag.
+
+Target explicit paths, or everything under a root (including the root) filtered by class and name.
+Example: tag every part under Workspace.Lava with "Damage"
+{ tag: "Damage", root: "game.Workspace.Lava", className: "BasePart" }
+
+className matches subclasses too (BasePart covers Part, MeshPart, ...).`,
+  inputSchema: z.object({
+    tag: z.string().describe("Tag name"),
+    paths: z.array(z.string()).optional().describe("Instance paths to change"),
+    root: z.string().optional().describe("Change this instance and all its descendants"),
+    className: z.string().optional().describe("Only instances of this class (with root)"),
+    nameContains: z.string().optional().describe("Only instances whose name contains this text (with root)"),
+    remove: z.boolean().optional().describe("Remove the tag instead of adding it"),
+  }),
+  execute: async ({
+    tag,
+    paths = [],
+    root,
+    className,
+    nameContains,
+    remove = false,
+  }: {
+    tag: string
+    paths?: string[]
+    root?: string
+    className?: string
+    nameContains?: string
+    remove?: boolean
+  }) => {
+    if (!(await isStudioConnected())) {
+      return { error: notConnectedError() }
+    }
+
+    try {
+      const result = await invoke<TagResult>("update_tags", {
+        operation: { tag, paths, root, class_name: className, name_contains: nameContains, remove },
+      })
+      return {
+        success: true,
+        count: result.changed.length,
+        unchanged: result.unchanged,
+        missing: result.missing.length > 0 ? result.missing : undefined,
+      }
+    } catch (error) {
+      return { error: String(error) }
+    }
+  },
+})
+
+// ============================================================================
+// Attribute Tools
+// ============================================================================
+
+interface AttributeValue {
+  type: string
+  value: unknown
+}
+
+interface AttributeViolation {
+  path: string
+  attribute: string
+  severity: "warn" | "error"
+  message: string
+}
+
+export const robloxGetAttributes = tool({
+  description: `Read the attributes on an instance.
+
+Each attribute is returned as { type, value }, where type is the Luau typeof() name.`,
+  inputSchema: z.object({
+    path: z.string().describe("Instance path"),
+  }),
+  execute: async ({ path }: { path: string }) => {
+    if (!(await isStudioConnected())) {
+      return { error: notConnectedError() }
+    }
+
+    const result = await studioRequest<{ path: string; attributes: Record<string, AttributeValue> }>(
+      "/attributes/get",
+      { path }
+    )
+    if (!result.success) {
+      return { error: result.error }
+    }
+
+    return result.data
+  },
+})
+
+export const robloxSetAttributes = tool({
+  description: `Set or remove attributes on one or more instances.
+
+Strings, numbers and booleans need no type. Other types take an array value:
+- { path, name: "Speed", value: 16 }
+- { path, name: "Spawn", type: "Vector3", value: [0, 5, 0] }
+- { path, name: "Tint", type: "Color3", value: [255, 0, 0] }
+- { path, name: "Old", value: null } removes the attribute
+
+Writes are checked against the project's attribute schema; rejected writes list the violations.`,
+  inputSchema: z.object({
+    operations: z
+      .array(
+        z.object({
+          path: z.string().describe("Instance path"),
+          name: z.string().describe("Attribute name"),
+          type: z.string().optional().describe("Value type for non-primitive values (Vector3, Color3, UDim2, ...)"),
+          value: z.unknown().describe("New value, or null to remove"),
+        })
+      )
+      .describe("Attribute writes"),
+  }),
+  execute: async ({
+    operations,
+  }: {
+    operations: Array<{ path: string; name: string; type?: string; value: unknown }>
+  }) => {
+    if (!(await isStudioConnected())) {
+      return { error: notConnectedError() }
+    }
+
+    const result = await studioRequest<{ updated: number; errors?: string[]; attribute_warnings?: AttributeViolation[] }>(
+      "/attributes/set",
+      { operations }
+    )
+    if (!result.success) {
+      return { error: result.error }
+    }
+
+    return {
+      success: true,
+      count: result.data.updated,
+      errors: result.data.errors,
+      warnings: result.data.attribute_warnings?.map((warning) => `${warning.path}: ${warning.message}`),
+    }
+  },
+})
+
+export const robloxValidateAttributes = tool({
+  description: `Audit attributes in the game against the project's attribute schema.
+
+Reports missing required attributes, wrong types, and misspelled or unexpected names.
+Run this after setting attributes on tagged instances.`,
+  inputSchema: z.object({
+    root: z.string().optional().describe("Only check this instance and its descendants (default: game)"),
+  }),
+  execute: async ({ root }: { root?: string }) => {
+    if (!(await isStudioConnected())) {
+      return { error: notConnectedError() }
+    }
+
+    try {
+      const violations = await invoke<AttributeViolation[]>("validate_attributes", { root })
+      if (violations.length === 0) {
+        return { message: "All attributes match the schema", violations: [] }
+      }
+      return {
+        count: violations.length,
+        violations: violations.map((v) => ({ path: v.path, attribute: v.attribute, severity: v.severity, message: v.message })),
+      }
+    } catch (error) {
+      return { error: String(error) }
+    }
+  },
+})
+
 // ============================================================================
 // Toolbox Tools
 // ============================================================================
@@ -744,6 +1091,7 @@ export const robloxTools = {
   roblox_get_script: robloxGetScript,
   roblox_set_script: robloxSetScript,
   roblox_edit_script: robloxEditScript,
+  roblox_find_replace_all: robloxFindReplaceAll,
 
   // Instance tools
   roblox_get_children: robloxGetChildren,
@@ -762,6 +1110,15 @@ export const robloxTools = {
   roblox_bulk_delete: robloxBulkDelete,
   roblox_bulk_set_property: robloxBulkSetProperty,
 
+  // Tag tools
+  roblox_list_tags: robloxListTags,
+  roblox_update_tags: robloxUpdateTags,
+
+  // Attribute tools
+  roblox_get_attributes: robloxGetAttributes,
+  roblox_set_attributes: robloxSetAttributes,
+  roblox_validate_attributes: robloxValidateAttributes,
+
   // Toolbox tools
   roblox_toolbox_search: robloxToolboxSearch,
   roblox_insert_asset: robloxInsertAsset,
@@ -769,3 +1126,33 @@ export const robloxTools = {
   // Agentic tools
   roblox_ask_user: robloxAskUser,
 }
+
+/** Tools that change the place; hidden while a read-only place is connected */
+const WRITE_TOOLS = new Set<string>([
+  "roblox_set_script",
+  "roblox_edit_script",
+  "roblox_find_replace_all",
+  "roblox_set_property",
+  "roblox_create",
+  "roblox_delete",
+  "roblox_clone",
+  "roblox_run_code",
+  "roblox_move",
+  "roblox_bulk_create",
+  "roblox_bulk_delete",
+  "roblox_bulk_set_property",
+  "roblox_update_tags",
+  "roblox_set_attributes",
+  "roblox_insert_asset",
+])
+
+/** The tools to offer the AI under a place's profile */
+export function toolsForProfile(profile: PlaceProfile | null): Partial<typeof robloxTools> {
+  if (!profile) return robloxTools
+  return Object.fromEntries(
+    Object.entries(robloxTools).filter(([name]) => {
+      if (profile.disabled_tools.includes(name)) return false
+      return !(profile.read_only && WRITE_TOOLS.has(name))
+    })
+  ) as Partial<typeof robloxTools>
+}
@@ -0,0 +1,13 @@
+/**
+ * The per-install secret the backend's local servers want in X-Stud-Secret:
+ * the bridge, and the provider proxy that adds the user's credentials.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+let bridgeSecret: Promise<string> | null = null;
+
+export async function bridgeHeaders(extra?: Record<string, string>): Promise<Record<string, string>> {
+  bridgeSecret ??= invoke<string>("get_bridge_secret");
+  return { ...extra, "X-Stud-Secret": await bridgeSecret };
+}
@@ -1,4 +1,6 @@
 import { useState, useCallback, useEffect, useRef } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { invoke } from "@tauri-apps/api/core";
 import {
   PromptInput,
   PromptInputTextarea,
@@ -23,17 +25,20 @@ import { SettingsDialog } from "@/components/settings/SettingsDialog";
 import { SettingsPanel } from "@/components/SettingsPanel";
 import { ContextChips, ChipAction } from "@/components/chat/ContextChips";
 import { QuestionPrompt } from "@/components/chat/QuestionPrompt";
+import { MergeConflictPrompt } from "@/components/chat/MergeConflictPrompt";
 import { InstancePicker } from "@/components/chat/InstancePicker";
 import { ChatActions } from "@/components/QuickActions";
 import { CommandPalette } from "@/components/CommandPalette";
 import { EmptyState } from "@/components/EmptyState";
-import { useChatStore } from "@/stores/chat";
+import { useChatStore, type ToolPartialResult } from "@/stores/chat";
 import { useSettingsStore } from "@/stores/settings";
 import { useRobloxStore, ConnectionStatus } from "@/stores/roblox";
 import { usePluginStore } from "@/stores/plugin";
 import { useAuthStore } from "@/stores/auth";
+import { useModelsStore } from "@/stores/models";
 import { useChat } from "@/lib/ai/providers";
-import { setAskUserHandler } from "@/lib/roblox/tools";
+import { cancelGeneration } from "@/lib/ai/generation";
+import { setAskUserHandler, setMergeConflictHandler } from "@/lib/roblox/tools";
 import { useAppShortcuts } from "@/hooks/useKeyboardShortcuts";
 import { improvePrompt } from "@/lib/ai/prompt-improver";
 import { cn } from "@/lib/utils";
@@ -118,6 +123,48 @@ function ConnectionStep({
   );
 }
 
+// Shown when the connected plugin's handshake says it's out of date
+function PluginWarningBanner() {
+  const { pluginWarning, dismissPluginWarning } = useRobloxStore();
+  const { installPlugin, isInstalling } = usePluginStore();
+  const [installed, setInstalled] = useState(false);
+
+  if (!pluginWarning) return null;
+
+  const handleReinstall = async () => {
+    try {
+      await installPlugin();
+      setInstalled(true);
+    } catch {
+      // The plugin store keeps the error
+    }
+  };
+
+  return (
+    <div
+      className={cn(
+        "border rounded-xl p-4 flex items-center gap-3",
+        pluginWarning.compatible
+          ? "bg-amber-50 border-amber-200 text-amber-800"
+          : "bg-red-50 border-red-200 text-red-700"
+      )}
+    >
+      <Download className="w-4 h-4 flex-shrink-0" />
+      <p className="text-sm flex-1">
+        {installed ? "Plugin reinstalled. Restart Roblox Studio to load it." : pluginWarning.message}
+      </p>
+      {!installed && (
+        <Button variant="outline" size="sm" onClick={handleReinstall} disabled={isInstalling}>
+          Reinstall plugin
+        </Button>
+      )}
+      <Button variant="ghost" size="sm" onClick={dismissPluginWarning}>
+        Dismiss
+      </Button>
+    </div>
+  );
+}
+
 // Connection screen shown when bridge is not connected
 function ConnectionScreen({ status }: { status: ConnectionStatus }) {
   const { 
@@ -130,6 +177,15 @@ function ConnectionScreen({ status }: { status: ConnectionStatus }) {
   
   const [installMessage, setInstallMessage] = useState<string | null>(null);
   const [showManualPath, setShowManualPath] = useState(false);
+  const restartBridge = useRobloxStore((state) => state.restartBridge);
+  const [restarting, setRestarting] = useState(false);
+  const [restartResult, setRestartResult] = useState<{ ok: boolean; message: string } | null>(null);
+
+  const handleRestartBridge = async () => {
+    setRestarting(true);
+    setRestartResult(await restartBridge());
+    setRestarting(false);
+  };
 
   // Check plugin status on mount
   useEffect(() => {
@@ -236,6 +292,25 @@ function ConnectionScreen({ status }: { status: ConnectionStatus }) {
             />
           </div>
 
+          {/* Bridge restart, for when the connection is stuck */}
+          <div className="flex flex-col items-center gap-2">
+            <Button
+              variant="ghost"
+              size="sm"
+              onClick={handleRestartBridge}
+              disabled={restarting}
+              className="text-muted-foreground"
+            >
+              <RefreshCw className={cn("w-4 h-4 mr-2", restarting && "animate-spin")} />
+              {restarting ? "Restarting bridge..." : "Stuck? Restart bridge"}
+            </Button>
+            {restartResult && (
+              <p className={cn("text-xs", restartResult.ok ? "text-green-600" : "text-red-600")}>
+                {restartResult.message}
+              </p>
+            )}
+          </div>
+
           {/* Plugin Installation Card */}
           <div className="bg-card rounded-2xl border border-border p-5 space-y-4">
             <div className="flex items-center justify-between">
@@ -369,24 +444,32 @@ export function Home() {
   const [activeChips, setActiveChips] = useState<ChipAction[]>([]);
   const [isImproving, setIsImproving] = useState(false);
   const [displayedSuggestions, setDisplayedSuggestions] = useState<string[]>([]);
+  // Set while the proxy waits to retry a failed provider request
+  const [retryNotice, setRetryNotice] = useState<string | null>(null);
   const {
+    chatId,
+    turnId,
     messages,
     isStreaming,
     error,
     pendingQuestion,
+    pendingMerge,
     addMessage,
     updateMessage,
     addToolCall,
     updateToolCall,
     setStreaming,
+    setTurnId,
     setError,
     setPendingQuestion,
     setQuestionResolver,
     answerQuestion,
+    setPendingMerge,
+    resolveMerge,
     clearMessages,
   } = useChatStore();
   const { hasApiKey } = useSettingsStore();
-  const { status: studioStatus, startPolling } = useRobloxStore();
+  const { status: studioStatus, lostConnectionAt, startPolling } = useRobloxStore();
   const { sendMessage } = useChat();
   const inputRef = useRef<HTMLTextAreaElement>(null);
 
@@ -408,6 +491,51 @@ export function Home() {
     return cleanup;
   }, [startPolling]);
 
+  // Show large tool results as they stream in from Studio
+  useEffect(() => {
+    const unlisten = listen<ToolPartialResult>("tool-partial-result", (event) => {
+      useChatStore.getState().applyToolProgress(event.payload);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Show when the provider proxy is retrying a request; cleared once the turn ends
+  useEffect(() => {
+    const unlisten = listen<{ attempt: number; max_retries: number; reason: string }>("llm-retry", (event) => {
+      const { attempt, max_retries, reason } = event.payload;
+      setRetryNotice(`Retrying (${attempt}/${max_retries}) after ${reason}…`);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  useEffect(() => {
+    if (!isStreaming) setRetryNotice(null);
+  }, [isStreaming]);
+
+  // Keep the ChatGPT sign-in status in step with the backend, which refreshes tokens itself
+  useEffect(() => {
+    const { syncStatus } = useAuthStore.getState();
+    syncStatus();
+    const unlisteners = [
+      listen("oauth-refreshed", () => syncStatus()),
+      listen<{ error: string }>("oauth-refresh-failed", (event) => {
+        console.warn("[OAuth] Token refresh failed:", event.payload.error);
+      }),
+    ];
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((fn) => fn()));
+    };
+  }, []);
+
+  // Which API keys the backend's vault holds
+  useEffect(() => {
+    useSettingsStore.getState().syncApiKeys();
+  }, []);
+
   // Shuffle and pick random suggestions on mount and when messages clear
   useEffect(() => {
     const shuffled = [...SUGGESTIONS].sort(() => Math.random() - 0.5);
@@ -433,7 +561,21 @@ export function Home() {
     };
   }, [setPendingQuestion, setQuestionResolver]);
 
-  const hasConfiguredProvider = hasApiKey("openai") || hasApiKey("anthropic") || useAuthStore.getState().isOAuthAuthenticated();
+  // Set up the handler for script edits that conflict with the user's own changes
+  useEffect(() => {
+    setMergeConflictHandler((path, conflicts) => {
+      return new Promise((resolve) => {
+        setPendingMerge({ path, conflicts }, resolve);
+      });
+    });
+
+    return () => {
+      setMergeConflictHandler(null);
+    };
+  }, [setPendingMerge]);
+
+  const hasLocalModels = useModelsStore((state) => state.localModels.length > 0);
+  const hasConfiguredProvider = hasApiKey("openai") || hasApiKey("anthropic") || hasApiKey("openrouter") || useAuthStore.getState().isOAuthAuthenticated() || hasLocalModels;
   const isConnected = studioStatus === "connected";
 
   // Improve prompt handler
@@ -489,6 +631,7 @@ export function Home() {
     // Add placeholder for assistant
     const assistantId = addMessage({ role: "assistant", content: "" });
 
+    setTurnId(assistantId);
     setStreaming(true);
     setError(null);
 
@@ -529,11 +672,13 @@ export function Home() {
         onFinish: () => {
           console.log("[Home] Stream finished, total length:", fullText.length);
           setStreaming(false);
+          setTurnId(null);
         },
         onError: (error) => {
           console.error("[Home] Stream error:", error);
           setError(error.message);
           setStreaming(false);
+          setTurnId(null);
         },
       });
     } catch (error) {
@@ -541,8 +686,9 @@ export function Home() {
       const errorMessage = error instanceof Error ? error.message : String(error);
       setError(errorMessage);
       setStreaming(false);
+      setTurnId(null);
     }
-  }, [input, isStreaming, messages, activeChips, addMessage, updateMessage, addToolCall, updateToolCall, setStreaming, setError, sendMessage]);
+  }, [input, isStreaming, messages, activeChips, addMessage, updateMessage, addToolCall, updateToolCall, setStreaming, setTurnId, setError, sendMessage]);
 
   const handleSuggestionClick = (suggestion: string) => {
     setInput(suggestion);
@@ -563,11 +709,21 @@ export function Home() {
   };
 
   const handleStop = () => {
+    // Drop this turn's Studio operations that haven't run yet
+    if (turnId) {
+      invoke("cancel_bridge_request", { chatId, turnId }).catch(() => {});
+      // Stop the model's stream too, so it stops spending tokens
+      cancelGeneration(turnId);
+    }
     setStreaming(false);
+    setTurnId(null);
   };
 
+  // Keep an ongoing chat on screen while Studio reconnects
+  const reconnecting = !isConnected && lostConnectionAt !== null && messages.length > 0;
+
   // Show connection screen if not connected
-  if (!isConnected) {
+  if (!isConnected && !reconnecting) {
     return <ConnectionScreen status={studioStatus} />;
   }
 
@@ -587,6 +743,8 @@ export function Home() {
         {/* Centered content */}
         <main className="flex-1 flex flex-col items-center justify-center px-6 pb-24">
           <div className="w-full max-w-2xl space-y-8">
+            <PluginWarningBanner />
+
             {/* Welcome message */}
             <div className="text-center space-y-2">
               <h1 className="text-3xl font-heading text-foreground">
@@ -747,6 +905,18 @@ export function Home() {
       {/* Chat messages */}
       <ChatContainerRoot className="flex-1 relative">
         <ChatContainerContent className="max-w-3xl mx-auto px-4 py-6 space-y-6">
+          {/* Reconnect banner */}
+          {reconnecting && (
+            <div className="bg-amber-50 border border-amber-200 text-amber-800 rounded-xl p-4 flex items-center gap-3">
+              <RefreshCw className="w-4 h-4 animate-spin flex-shrink-0" />
+              <p className="text-sm">
+                Lost connection to Roblox Studio. Waiting for the plugin to reconnect...
+              </p>
+            </div>
+          )}
+
+          <PluginWarningBanner />
+
           {/* Error alert */}
           {error && (
             <div className="bg-red-50 border border-red-200 text-red-700 rounded-xl p-4 flex items-start gap-3">
@@ -843,11 +1013,22 @@ export function Home() {
             </div>
           )}
 
+          {/* Script edit that overlaps the user's changes */}
+          {pendingMerge && (
+            <div className="max-w-2xl mx-auto">
+              <MergeConflictPrompt
+                path={pendingMerge.path}
+                conflicts={pendingMerge.conflicts}
+                onResolve={resolveMerge}
+              />
+            </div>
+          )}
+
           {/* Streaming indicator */}
-          {isStreaming && !pendingQuestion && (
+          {isStreaming && !pendingQuestion && !pendingMerge && (
             <div className="flex items-center gap-3 px-4 py-3 bg-muted/30 rounded-xl max-w-fit mx-auto">
               <Loader variant="wave" size="sm" />
-              <span className="text-sm text-muted-foreground">AI is working...</span>
+              <span className="text-sm text-muted-foreground">{retryNotice ?? "AI is working..."}</span>
             </div>
           )}
         </ChatContainerContent>
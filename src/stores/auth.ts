@@ -1,35 +1,60 @@
 import { create } from "zustand";
 import { persist } from "zustand/middleware";
 import { openUrl } from "@tauri-apps/plugin-opener";
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import {
+  OAuthAccount,
   OAuthAuth,
+  OAuthResult,
+  DeviceCode,
   getStoredAuth,
   clearAuth,
   startOAuthLogin,
-  handleOAuthCallback,
+  startDeviceLogin,
+  handleOAuthResult,
   isAuthenticated,
+  syncOAuthStatus,
+  migrateLegacyAuth,
+  listAccounts,
+  switchAccount,
+  removeAccount,
 } from "@/lib/auth/codex";
 import { useModelsStore } from "./models";
 
 export type AuthMethod = "api_key" | "oauth";
 
+// Listening for the backend's "oauth-complete" while a sign-in is in progress
+let stopListening: UnlistenFn | null = null;
+
+function stopListeningForResult() {
+  stopListening?.();
+  stopListening = null;
+}
+
 interface AuthState {
   // Current auth method
   authMethod: AuthMethod;
   
   // OAuth state
   oauthAuth: OAuthAuth | null;
+  accounts: OAuthAccount[]; // Every signed-in ChatGPT account
   isLoggingIn: boolean;
   loginError: string | null;
   loginUrl: string | null; // URL to show as fallback
+  loginState: string | null; // OAuth state of the flow in progress
+  deviceCode: DeviceCode | null; // Code to show while a device sign-in waits
   
   // Actions
   setAuthMethod: (method: AuthMethod) => void;
   startLogin: () => Promise<void>;
-  completeLogin: (code: string, state: string) => Promise<void>;
+  startDeviceLogin: () => Promise<void>;
+  completeLogin: (result: OAuthResult) => Promise<void>;
   logout: () => void;
-  checkOAuthCallback: () => Promise<boolean>;
   cancelLogin: () => void;
+  syncStatus: () => Promise<void>;
+  switchAccount: (key: string) => Promise<void>;
+  removeAccount: (key: string) => Promise<void>;
 
   // Getters
   isOAuthAuthenticated: () => boolean;
@@ -40,20 +65,30 @@ export const useAuthStore = create<AuthState>()(
     (set, get) => ({
       authMethod: "api_key",
       oauthAuth: getStoredAuth(),
+      accounts: [],
       isLoggingIn: false,
       loginError: null,
       loginUrl: null,
+      loginState: null,
+      deviceCode: null,
 
       setAuthMethod: (method) => {
         set({ authMethod: method });
       },
 
       startLogin: async () => {
-        set({ isLoggingIn: true, loginError: null, loginUrl: null });
+        set({ isLoggingIn: true, loginError: null, loginUrl: null, loginState: null });
         try {
-          const { url } = await startOAuthLogin();
+          // The backend exchanges the code itself and reports how it went
+          stopListeningForResult();
+          stopListening = await listen<OAuthResult>("oauth-complete", ({ payload }) => {
+            if (payload.state === get().loginState) {
+              get().completeLogin(payload).catch(() => {});
+            }
+          });
+          const { url, state } = await startOAuthLogin();
           // Store the URL for fallback display
-          set({ loginUrl: url });
+          set({ loginUrl: url, loginState: state });
           // Try to open in default browser using Tauri's opener
           await openUrl(url);
         } catch (error) {
@@ -64,17 +99,48 @@ export const useAuthStore = create<AuthState>()(
         }
       },
 
+      startDeviceLogin: async () => {
+        set({ isLoggingIn: true, loginError: null, loginUrl: null, loginState: null, deviceCode: null });
+        try {
+          stopListeningForResult();
+          stopListening = await listen<OAuthResult>("oauth-complete", ({ payload }) => {
+            if (payload.state === get().loginState) {
+              get().completeLogin(payload).catch(() => {});
+            }
+          });
+          const deviceCode = await startDeviceLogin();
+          set({ deviceCode, loginState: deviceCode.flow_id });
+        } catch (error) {
+          stopListeningForResult();
+          set({
+            loginError: error instanceof Error ? error.message : String(error),
+            isLoggingIn: false,
+          });
+        }
+      },
+
       cancelLogin: () => {
-        set({ isLoggingIn: false, loginUrl: null, loginError: null });
+        stopListeningForResult();
+        const { loginState, deviceCode } = get();
+        if (deviceCode) {
+          invoke("cancel_device_auth", { flowId: deviceCode.flow_id }).catch(() => {});
+        } else if (loginState) {
+          invoke("cancel_oauth_flow", { state: loginState }).catch(() => {});
+        }
+        set({ isLoggingIn: false, loginUrl: null, loginError: null, loginState: null, deviceCode: null });
       },
 
-      completeLogin: async (code: string, state: string) => {
+      completeLogin: async (result: OAuthResult) => {
+        stopListeningForResult();
         set({ isLoggingIn: true, loginError: null });
         try {
-          const auth = await handleOAuthCallback(code, state);
+          const auth = await handleOAuthResult(result);
           set({
             oauthAuth: auth,
+            accounts: await listAccounts(),
             isLoggingIn: false,
+            loginState: null,
+            deviceCode: null,
             authMethod: "oauth",
           });
           // Fetch models after successful login
@@ -82,7 +148,9 @@ export const useAuthStore = create<AuthState>()(
         } catch (error) {
           set({ 
             loginError: error instanceof Error ? error.message : String(error),
-            isLoggingIn: false 
+            isLoggingIn: false,
+            loginState: null,
+            deviceCode: null,
           });
           throw error;
         }
@@ -90,38 +158,47 @@ export const useAuthStore = create<AuthState>()(
 
       logout: () => {
         clearAuth();
+        invoke("sign_out").catch((error) => console.error("[Auth] Sign-out failed:", error));
         // Clear cached models on logout
         useModelsStore.getState().clearModels();
         set({
           oauthAuth: null,
+          accounts: [],
           authMethod: "api_key",
           loginError: null,
         });
       },
 
-      checkOAuthCallback: async () => {
-        // Poll the OAuth callback server for pending auth data
+      syncStatus: async () => {
+        // The backend refreshes tokens on its own; pick up the new expiry
+        try {
+          await migrateLegacyAuth();
+          set({ oauthAuth: await syncOAuthStatus(), accounts: await listAccounts() });
+        } catch (error) {
+          console.debug("[OAuth] Status sync failed:", error);
+        }
+      },
+
+      switchAccount: async (key: string) => {
+        try {
+          set({ oauthAuth: await switchAccount(key), accounts: await listAccounts(), loginError: null });
+        } catch (error) {
+          set({ loginError: error instanceof Error ? error.message : String(error) });
+        }
+      },
+
+      removeAccount: async (key: string) => {
         try {
-          const response = await fetch("http://localhost:1455/auth/poll");
-          if (!response.ok) return false;
-          
-          const data = await response.json();
-          if (!data.pending) return false;
-          
-          const { code, state } = data;
-          
-          // Clear the callback data from the server
-          await fetch("http://localhost:1455/auth/clear", { method: "POST" });
-          
-          if (code && state) {
-            await get().completeLogin(code, state);
-            return true;
+          const auth = await removeAccount(key);
+          set({ oauthAuth: auth, accounts: await listAccounts(), loginError: null });
+          if (!auth) {
+            // That was the last account
+            useModelsStore.getState().clearModels();
+            set({ authMethod: "api_key" });
           }
         } catch (error) {
-          // Server not running or network error - ignore
-          console.debug("[OAuth] Poll failed:", error);
+          set({ loginError: error instanceof Error ? error.message : String(error) });
         }
-        return false;
       },
 
       isOAuthAuthenticated: () => {
@@ -138,21 +215,3 @@ export const useAuthStore = create<AuthState>()(
     }
   )
 );
-
-// Poll for OAuth callback completion
-export function useOAuthCallbackPoller() {
-  const { checkOAuthCallback, isLoggingIn } = useAuthStore();
-  
-  // Check periodically while logging in
-  if (typeof window !== "undefined" && isLoggingIn) {
-    const interval = setInterval(async () => {
-      const completed = await checkOAuthCallback();
-      if (completed) {
-        clearInterval(interval);
-      }
-    }, 1000);
-    
-    // Cleanup after 5 minutes
-    setTimeout(() => clearInterval(interval), 5 * 60 * 1000);
-  }
-}
@@ -1,5 +1,25 @@
 import { create } from "zustand";
 
+/** Progress of a large tool result streaming in from Studio */
+export interface ToolProgress {
+  receivedBytes: number;
+  parts: number;
+  total?: number;
+  /** Tail of the text received so far */
+  preview: string;
+}
+
+/** Payload of the bridge's "tool-partial-result" event */
+export interface ToolPartialResult {
+  tool_call_id: string;
+  request_id: string;
+  seq: number;
+  total: number | null;
+  received_parts: number;
+  received_bytes: number;
+  data: string;
+}
+
 export interface ToolCall {
   id: string;
   name: string;
@@ -7,6 +27,23 @@ export interface ToolCall {
   result?: unknown;
   status: "pending" | "running" | "complete" | "error" | "waiting";
   error?: string;
+  progress?: ToolProgress;
+}
+
+const PREVIEW_CHARS = 2000;
+
+// Parts received per tool call, kept outside the store so renders stay cheap
+const partialParts = new Map<string, Map<number, string>>();
+
+function buildPreview(parts: Map<number, string>): string {
+  let text = "";
+  for (let seq = 0; parts.has(seq); seq++) {
+    text += parts.get(seq);
+    if (text.length > PREVIEW_CHARS * 4) {
+      text = text.slice(-PREVIEW_CHARS * 2);
+    }
+  }
+  return text.slice(-PREVIEW_CHARS);
 }
 
 export interface Message {
@@ -39,19 +76,46 @@ export interface PendingQuestion {
   answers?: (string | string[])[];
 }
 
+/** One overlapping edit found while merging the AI's script change with the user's */
+export interface ScriptMergeConflict {
+  base: string;
+  user: string;
+  ai: string;
+}
+
+export type ConflictResolution =
+  | { choice: "user" }
+  | { choice: "ai" }
+  | { choice: "both" }
+  | { choice: "custom"; text: string };
+
+export interface PendingMerge {
+  path: string;
+  conflicts: ScriptMergeConflict[];
+}
+
 export interface ChatState {
+  /** Identifies this conversation to the bridge; changes when the chat is cleared */
+  chatId: string;
+  /** Assistant message being generated, used as the turn id for bridge requests */
+  turnId: string | null;
   messages: Message[];
   isStreaming: boolean;
   error: string | null;
   pendingQuestion: PendingQuestion | null;
   questionResolver: ((answers: (string | string[])[]) => void) | null;
+  /** Script edit waiting for the user to settle conflicts with their own changes */
+  pendingMerge: PendingMerge | null;
+  mergeResolver: ((resolutions: ConflictResolution[] | null) => void) | null;
 
   // Actions
   addMessage: (message: Omit<Message, "id" | "createdAt">) => string;
   updateMessage: (id: string, content: string) => void;
   addToolCall: (messageId: string, toolCall: Omit<ToolCall, "status">) => void;
   updateToolCall: (messageId: string, toolCallId: string, update: Partial<ToolCall>) => void;
+  applyToolProgress: (event: ToolPartialResult) => void;
   setStreaming: (streaming: boolean) => void;
+  setTurnId: (turnId: string | null) => void;
   setError: (error: string | null) => void;
   clearMessages: () => void;
 
@@ -59,14 +123,26 @@ export interface ChatState {
   setPendingQuestion: (question: PendingQuestion | null) => void;
   setQuestionResolver: (resolver: ((answers: (string | string[])[]) => void) | null) => void;
   answerQuestion: (answers: (string | string[])[]) => void;
+
+  // Merge conflict handling
+  setPendingMerge: (
+    merge: PendingMerge | null,
+    resolver: ((resolutions: ConflictResolution[] | null) => void) | null
+  ) => void;
+  /** Pass null to keep the user's version and drop the AI's edit */
+  resolveMerge: (resolutions: ConflictResolution[] | null) => void;
 }
 
 export const useChatStore = create<ChatState>()((set, get) => ({
+  chatId: crypto.randomUUID(),
+  turnId: null,
   messages: [],
   isStreaming: false,
   error: null,
   pendingQuestion: null,
   questionResolver: null,
+  pendingMerge: null,
+  mergeResolver: null,
 
   addMessage: (message) => {
     const id = crypto.randomUUID();
@@ -115,11 +191,42 @@ export const useChatStore = create<ChatState>()((set, get) => ({
       ),
     })),
 
+  applyToolProgress: (event) => {
+    const parts = partialParts.get(event.tool_call_id) ?? new Map<number, string>();
+    parts.set(event.seq, event.data);
+    const progress: ToolProgress = {
+      receivedBytes: event.received_bytes,
+      parts: event.received_parts,
+      total: event.total ?? undefined,
+      preview: buildPreview(parts),
+    };
+    if (event.total !== null && event.received_parts >= event.total) {
+      partialParts.delete(event.tool_call_id);
+    } else {
+      partialParts.set(event.tool_call_id, parts);
+    }
+
+    set((state) => ({
+      messages: state.messages.map((msg) =>
+        msg.toolCalls?.some((tc) => tc.id === event.tool_call_id)
+          ? {
+              ...msg,
+              toolCalls: msg.toolCalls?.map((tc) =>
+                tc.id === event.tool_call_id ? { ...tc, progress } : tc
+              ),
+            }
+          : msg
+      ),
+    }));
+  },
+
   setStreaming: (streaming) => set({ isStreaming: streaming }),
+
+  setTurnId: (turnId) => set({ turnId }),
   
   setError: (error) => set({ error }),
 
-  clearMessages: () => set({ messages: [] }),
+  clearMessages: () => set({ messages: [], chatId: crypto.randomUUID(), turnId: null }),
 
   // Question handling
   setPendingQuestion: (question) => set({ pendingQuestion: question }),
@@ -133,4 +240,15 @@ export const useChatStore = create<ChatState>()((set, get) => ({
       set({ pendingQuestion: null, questionResolver: null });
     }
   },
+
+  // Merge conflict handling
+  setPendingMerge: (merge, resolver) => set({ pendingMerge: merge, mergeResolver: resolver }),
+
+  resolveMerge: (resolutions) => {
+    const { mergeResolver } = get();
+    if (mergeResolver) {
+      mergeResolver(resolutions);
+      set({ pendingMerge: null, mergeResolver: null });
+    }
+  },
 }));
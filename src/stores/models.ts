@@ -4,6 +4,7 @@
  */
 
 import { create } from "zustand";
+import { invoke } from "@tauri-apps/api/core";
 import type { ProvidersData, DisplayModel } from "@/lib/models/types";
 import {
   getModelsWithCache,
@@ -12,6 +13,16 @@ import {
   FALLBACK_CODEX_MODELS,
 } from "@/lib/models/fetcher";
 
+// Installed model, as the backend's list_ollama_models returns it
+interface OllamaModel {
+  name: string;
+  size: number;
+  family: string | null;
+  parameter_size: string | null;
+  quantization: string | null;
+  modified_at: string | null;
+}
+
 // Auto-refresh interval: 1 hour
 const AUTO_REFRESH_INTERVAL_MS = 60 * 60 * 1000;
 
@@ -19,6 +30,8 @@ interface ModelsState {
   // State
   providers: ProvidersData | null;
   codexModels: DisplayModel[];
+  // Models on a local Ollama server; empty when it isn't running
+  localModels: DisplayModel[];
   isLoading: boolean;
   lastFetched: number | null;
   error: string | null;
@@ -26,6 +39,7 @@ interface ModelsState {
   // Actions
   fetchModels: () => Promise<void>;
   refreshModels: () => Promise<void>;
+  fetchLocalModels: () => Promise<void>;
   clearModels: () => void;
   startAutoRefresh: () => void;
   stopAutoRefresh: () => void;
@@ -36,6 +50,7 @@ let autoRefreshTimer: ReturnType<typeof setInterval> | null = null;
 export const useModelsStore = create<ModelsState>((set, get) => ({
   providers: null,
   codexModels: FALLBACK_CODEX_MODELS,
+  localModels: [],
   isLoading: false,
   lastFetched: null,
   error: null,
@@ -78,6 +93,29 @@ export const useModelsStore = create<ModelsState>((set, get) => ({
     await get().fetchModels();
   },
 
+  fetchLocalModels: async () => {
+    try {
+      const { running } = await invoke<{ running: boolean }>("detect_ollama");
+      if (!running) {
+        set({ localModels: [] });
+        return;
+      }
+      const models = await invoke<OllamaModel[]>("list_ollama_models");
+      set({
+        localModels: models.map((m) => ({
+          id: m.name,
+          name: m.name,
+          description: [m.parameter_size, m.family].filter(Boolean).join(" ") || "Local",
+          provider: "ollama",
+        })),
+      });
+      console.log(`[Models] Found ${models.length} Ollama models`);
+    } catch (error) {
+      console.error("[Models] Failed to list Ollama models:", error);
+      set({ localModels: [] });
+    }
+  },
+
   clearModels: () => {
     clearModelsCache();
     set({
@@ -114,6 +152,7 @@ export const useModelsStore = create<ModelsState>((set, get) => ({
 setTimeout(() => {
   console.log("[Models] Initial fetch on load");
   useModelsStore.getState().fetchModels();
+  useModelsStore.getState().fetchLocalModels();
   // Start auto-refresh
   useModelsStore.getState().startAutoRefresh();
 }, 100);
@@ -22,6 +22,14 @@ export interface PrereqCheck {
   };
 }
 
+/** Result of the `run_bridge_selftest` command */
+interface BridgeSelftestReport {
+  ok: boolean;
+  bridge_url: string | null;
+  checks: Array<{ name: string; status: "pass" | "fail" | "skip"; detail: string; duration_ms: number }>;
+  latency: { samples: number; min_ms: number; avg_ms: number; max_ms: number } | null;
+}
+
 interface PrereqStore {
   checks: PrereqCheck[];
   isChecking: boolean;
@@ -144,13 +152,15 @@ export const usePrereqStore = create<PrereqStore>((set, get) => ({
     const { hasApiKey } = useSettingsStore.getState();
     const hasOpenAI = hasApiKey("openai");
     const hasAnthropic = hasApiKey("anthropic");
+    const hasOpenRouter = hasApiKey("openrouter");
     const hasOAuth = isAuthenticated();
 
-    if (hasOpenAI || hasAnthropic || hasOAuth) {
+    if (hasOpenAI || hasAnthropic || hasOpenRouter || hasOAuth) {
       const providers = [];
       if (hasOAuth) providers.push("ChatGPT Plus/Pro");
       if (hasOpenAI) providers.push("OpenAI");
       if (hasAnthropic) providers.push("Anthropic");
+      if (hasOpenRouter) providers.push("OpenRouter");
       updateCheck("api-provider", {
         status: "passed",
         message: `Configured: ${providers.join(", ")}`,
@@ -170,7 +180,19 @@ export const usePrereqStore = create<PrereqStore>((set, get) => ({
 
     const bridgeUp = await isBridgeRunning();
     if (bridgeUp) {
-      updateCheck("bridge-server", { status: "passed", message: "Bridge server is running" });
+      // Play the plugin against the bridge, so a broken bridge isn't blamed on Studio later
+      const selftest = await invoke<BridgeSelftestReport>("run_bridge_selftest").catch(() => null);
+      const failure = selftest?.checks.find((check) => check.status === "fail");
+      if (!selftest || selftest.ok) {
+        const latency = selftest?.latency ? ` (${Math.round(selftest.latency.avg_ms)} ms round trip)` : "";
+        updateCheck("bridge-server", { status: "passed", message: `Bridge server is running${latency}` });
+      } else {
+        updateCheck("bridge-server", {
+          status: "failed",
+          message: `Bridge self-test failed: ${failure?.detail ?? "unknown error"}`,
+          action: { label: "Restart App", handler: "restart-app" },
+        });
+      }
     } else {
       updateCheck("bridge-server", {
         status: "failed",
@@ -1,16 +1,75 @@
 import { create } from "zustand";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { isStudioConnected, isBridgeRunning } from "@/lib/roblox";
+import { useSettingsStore, type ProviderType } from "@/stores/settings";
 
 export type ConnectionStatus = "disconnected" | "bridge_only" | "connected";
 
+/** Something that happened in Studio, pushed by the plugin (the "studio-event" event) */
+export interface StudioEvent {
+  seq: number;
+  /** e.g. "selection_changed", "script_error", "playtest_started", "playtest_stopped" */
+  type: string;
+  session: string | null;
+  data: Record<string, unknown>;
+  timestamp: number;
+}
+
+const MAX_STUDIO_EVENTS = 50;
+
+/** Payload of the bridge's "plugin-version-warning" event */
+export interface PluginVersionWarning {
+  session: string;
+  plugin_version: string | null;
+  app_version: string;
+  protocol_version: number;
+  /** False when requests are likely to fail, not just miss newer features */
+  compatible: boolean;
+  message: string;
+}
+
+/** Preferences remembered for a Roblox place, keyed by its placeId */
+export interface PlaceProfile {
+  place_id: number;
+  place_name: string | null;
+  default_model: { model: string; provider: ProviderType } | null;
+  /** The bridge refuses every change to the place */
+  read_only: boolean;
+  /** Tools the AI isn't offered while the place is connected */
+  disabled_tools: string[];
+  auto_context: string[];
+}
+
+/** Payload of the bridge's "place-profile" event */
+interface PlaceProfileEvent {
+  session: string;
+  place_id: number;
+  place_name: string | null;
+  profile: PlaceProfile | null;
+}
+
 export interface RobloxState {
   status: ConnectionStatus;
   lastCheck: Date | null;
   error: string | null;
+  /** Set when Studio drops after having been connected, cleared when it comes back */
+  lostConnectionAt: Date | null;
+  /** Most recent Studio events, oldest first */
+  studioEvents: StudioEvent[];
+  /** Set when a connected plugin's handshake shows it's out of date */
+  pluginWarning: PluginVersionWarning | null;
+  /** Profile of the place that connected last; null if it has none */
+  placeProfile: PlaceProfile | null;
+  /** placeId of the place that connected last */
+  placeId: number | null;
   
   // Actions
   setStatus: (status: ConnectionStatus) => void;
+  dismissPluginWarning: () => void;
   checkConnection: () => Promise<void>;
+  /** Restart the local bridge server; resolves to a message for the UI */
+  restartBridge: () => Promise<{ ok: boolean; message: string }>;
   startPolling: () => () => void;
 }
 
@@ -18,8 +77,15 @@ export const useRobloxStore = create<RobloxState>()((set, get) => ({
   status: "disconnected",
   lastCheck: null,
   error: null,
+  lostConnectionAt: null,
+  studioEvents: [],
+  pluginWarning: null,
+  placeProfile: null,
+  placeId: null,
 
   setStatus: (status) => set({ status }),
+
+  dismissPluginWarning: () => set({ pluginWarning: null }),
   
   checkConnection: async () => {
     try {
@@ -35,7 +101,8 @@ export const useRobloxStore = create<RobloxState>()((set, get) => ({
       set({ 
         status: studioUp ? "connected" : "bridge_only", 
         lastCheck: new Date(),
-        error: null 
+        error: null,
+        ...(studioUp ? { lostConnectionAt: null } : {}),
       });
     } catch (e) {
       set({ 
@@ -46,16 +113,68 @@ export const useRobloxStore = create<RobloxState>()((set, get) => ({
     }
   },
   
+  restartBridge: async () => {
+    try {
+      const result = await invoke<{ port: number; cancelled: number }>("restart_bridge");
+      await get().checkConnection();
+      const dropped = result.cancelled > 0 ? ` (${result.cancelled} stuck request(s) cleared)` : "";
+      return { ok: true, message: `Bridge restarted on port ${result.port}${dropped}` };
+    } catch (e) {
+      return { ok: false, message: `Bridge restart failed: ${e instanceof Error ? e.message : String(e)}` };
+    }
+  },
+
   startPolling: () => {
     // Initial check
     get().checkConnection();
+
+    const addEvents = (events: StudioEvent[]) => {
+      set((state) => {
+        const lastSeq = state.studioEvents[state.studioEvents.length - 1]?.seq ?? 0;
+        const fresh = events.filter((event) => event.seq > lastSeq);
+        return { studioEvents: [...state.studioEvents, ...fresh].slice(-MAX_STUDIO_EVENTS) };
+      });
+    };
+    // Pick up anything Studio reported before we started listening
+    const known = get().studioEvents;
+    invoke<StudioEvent[]>("get_studio_events", { since: known[known.length - 1]?.seq ?? null })
+      .then(addEvents)
+      .catch(() => {});
+    
+    // The bridge pushes connect/disconnect events; polling is only a fallback
+    const unlisteners = [
+      listen("studio-connected", () => {
+        set({ status: "connected", lastCheck: new Date(), error: null, lostConnectionAt: null });
+      }),
+      listen("studio-disconnected", () => {
+        set((state) => ({
+          status: "bridge_only",
+          lastCheck: new Date(),
+          lostConnectionAt: state.status === "connected" ? new Date() : state.lostConnectionAt,
+        }));
+      }),
+      listen<StudioEvent>("studio-event", (event) => addEvents([event.payload])),
+      listen<PluginVersionWarning>("plugin-version-warning", (event) => {
+        set({ pluginWarning: event.payload });
+      }),
+      listen<PlaceProfileEvent>("place-profile", (event) => {
+        const { profile, place_id } = event.payload;
+        set({ placeProfile: profile, placeId: place_id });
+        if (profile?.default_model) {
+          const { model, provider } = profile.default_model;
+          useSettingsStore.getState().setSelectedModel(model, provider);
+        }
+      }),
+    ];
     
-    // Poll every 2 seconds
     const interval = setInterval(() => {
       get().checkConnection();
-    }, 2000);
+    }, 10000);
     
     // Return cleanup function
-    return () => clearInterval(interval);
+    return () => {
+      clearInterval(interval);
+      unlisteners.forEach((unlisten) => unlisten.then((fn) => fn()));
+    };
   },
 }));
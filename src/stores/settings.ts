@@ -1,12 +1,18 @@
 import { create } from "zustand";
 import { persist } from "zustand/middleware";
+import { invoke } from "@tauri-apps/api/core";
 
+// Providers a key can be saved for; the keys live in the backend's vault
 export interface ApiKeys {
   openai?: string;
   anthropic?: string;
+  openrouter?: string;
 }
 
-export type ProviderType = "openai" | "anthropic" | "codex";
+// Which providers have a key in the vault
+export type SavedKeys = Partial<Record<keyof ApiKeys, boolean>>;
+
+export type ProviderType = "openai" | "anthropic" | "openrouter" | "codex" | "ollama";
 
 export interface AppSettings {
   // UI Settings
@@ -23,16 +29,16 @@ export interface AppSettings {
 }
 
 export interface SettingsState {
-  apiKeys: ApiKeys;
+  savedKeys: SavedKeys;
   selectedModel: string;
   selectedProvider: ProviderType;
   appSettings: AppSettings;
 
   // Actions
-  setApiKey: (provider: keyof ApiKeys, key: string) => void;
+  setApiKey: (provider: keyof ApiKeys, key: string) => Promise<void>;
+  syncApiKeys: () => Promise<void>;
   setSelectedModel: (model: string, provider: ProviderType) => void;
   hasApiKey: (provider: keyof ApiKeys) => boolean;
-  getApiKey: (provider: keyof ApiKeys) => string | undefined;
   updateAppSettings: (settings: Partial<AppSettings>) => void;
   resetAppSettings: () => void;
 }
@@ -51,15 +57,26 @@ const DEFAULT_APP_SETTINGS: AppSettings = {
 export const useSettingsStore = create<SettingsState>()(
   persist(
     (set, get) => ({
-      apiKeys: {},
+      savedKeys: {},
       selectedModel: "gpt-4o",
       selectedProvider: "codex" as ProviderType,
       appSettings: DEFAULT_APP_SETTINGS,
 
-      setApiKey: (provider, key) =>
+      setApiKey: async (provider, key) => {
+        // An empty key deletes the saved one
+        await invoke(key.trim() ? "set_api_key" : "delete_api_key", { provider, key });
         set((state) => ({
-          apiKeys: { ...state.apiKeys, [provider]: key },
-        })),
+          savedKeys: { ...state.savedKeys, [provider]: !!key.trim() },
+        }));
+      },
+
+      syncApiKeys: async () => {
+        const providers: Array<keyof ApiKeys> = ["openai", "anthropic", "openrouter"];
+        const saved = await Promise.all(
+          providers.map((provider) => invoke<boolean>("has_api_key", { provider }).catch(() => false))
+        );
+        set({ savedKeys: Object.fromEntries(providers.map((provider, i) => [provider, saved[i]])) });
+      },
 
       setSelectedModel: (model, provider) =>
         set({
@@ -67,12 +84,7 @@ export const useSettingsStore = create<SettingsState>()(
           selectedProvider: provider,
         }),
 
-      hasApiKey: (provider) => {
-        const key = get().apiKeys[provider];
-        return !!key && key.length > 0;
-      },
-
-      getApiKey: (provider) => get().apiKeys[provider],
+      hasApiKey: (provider) => !!get().savedKeys[provider],
 
       updateAppSettings: (settings) =>
         set((state) => ({
@@ -84,6 +96,24 @@ export const useSettingsStore = create<SettingsState>()(
     }),
     {
       name: "stud-settings",
+      version: 1,
+      migrate: (persisted, version) => {
+        const state = persisted as Partial<SettingsState> & { apiKeys?: ApiKeys };
+        if (version < 1 && state.apiKeys) {
+          // Older versions kept the keys here; move them into the vault
+          const savedKeys: SavedKeys = {};
+          for (const [provider, key] of Object.entries(state.apiKeys)) {
+            if (!key) continue;
+            invoke("set_api_key", { provider, key }).catch((error) =>
+              console.error("[Settings] Failed to move API key to the vault:", error)
+            );
+            savedKeys[provider as keyof ApiKeys] = true;
+          }
+          delete state.apiKeys;
+          state.savedKeys = savedKeys;
+        }
+        return state as SettingsState;
+      },
     }
   )
 );
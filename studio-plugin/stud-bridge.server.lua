@@ -17,12 +17,71 @@ local HttpService = game:GetService("HttpService")
 local Selection = game:GetService("Selection")
 local ScriptEditorService = game:GetService("ScriptEditorService")
 local ChangeHistoryService = game:GetService("ChangeHistoryService")
+local CollectionService = game:GetService("CollectionService")
 local TweenService = game:GetService("TweenService")
 
 local PLUGIN_NAME = "stud-bridge"
 local PLUGIN_DISPLAY_NAME = "Stud"
-local POLL_URL = "http://localhost:3001/stud/poll"
-local RESPOND_URL = "http://localhost:3001/stud/respond"
+-- Stud fills in the real port when it installs the plugin; the bridge may also
+-- move us to a new port at runtime via port_hint
+local bridgeHost = "localhost:3001"
+-- Per-install secret, filled in by Stud at install time; the bridge rejects
+-- requests without it so other local processes can't drive Studio
+local BRIDGE_SECRET = "__STUD_BRIDGE_SECRET__"
+-- Version of Stud that installed this plugin, reported to the bridge so it can
+-- tell when the plugin is out of date
+local PLUGIN_VERSION = "__STUD_PLUGIN_VERSION__"
+-- Bridge protocol versions this plugin speaks. The version is agreed with the
+-- bridge on connect; bridges from before versioning only serve v1 at /stud
+local PROTOCOL_VERSIONS = { 1, 2 }
+local HELLO_PATH = "/stud/v2/hello"
+-- Unauthenticated; answers { service = "stud-bridge" } on any Stud bridge
+local HEALTH_PATH = "/stud/health"
+-- Plugins can't read Stud's discovery file, so when the bridge isn't where we
+-- expect it we probe the ports it falls back to when its own is taken
+local DISCOVERY_FIRST_PORT = 3001
+local DISCOVERY_PORT_COUNT = 11
+-- Agreed with the bridge on connect; v2 and later name it in every response
+local protocolVersion = 1
+-- Optional features the bridge announced when the version was agreed
+local bridgeFeatures = {}
+-- How long a request we took stays ours before the bridge hands it out again
+local leaseSecs = 60
+local apiPrefix = "/stud"
+-- Paths below are relative to apiPrefix
+-- Long poll: the bridge holds the request open until work arrives (up to 25s)
+-- and hands over up to 10 queued requests at once
+local POLL_PATH = "/poll?wait=25&max=10"
+-- Pause between polls when the bridge doesn't suggest one, and the longest it may ask for
+local DEFAULT_POLL_DELAY = 0.1
+local MAX_POLL_DELAY = 5
+local RESPOND_PATH = "/respond"
+local RESPOND_CHUNK_PATH = "/respond/chunk"
+-- Refusing a request instead of answering it, on bridges with the "nack" feature
+local NACK_PATH = "/nack"
+-- Renewing the lease on a request whose handler is still running ("lease" feature)
+local LEASE_PATH = "/lease"
+-- Responses larger than this are sent in parts
+local RESPONSE_CHUNK_SIZE = 200000
+-- JSON responses larger than this are uploaded in resumable parts (protocol v2+)
+-- and answered with a reference to the upload
+local UPLOAD_PATH = "/upload"
+local UPLOAD_THRESHOLD = 4 * 1024 * 1024
+local UPLOAD_PART_SIZE = 1024 * 1024
+local UPLOAD_MAX_RETRIES = 3
+-- Response bodies larger than this are gzipped; the bridge decompresses them
+local COMPRESS_THRESHOLD = 1024
+local WS_PATH = "/ws"
+local WATCH_PATH = "/watch"
+local WATCH_SAMPLES_PATH = "/watch/samples"
+local LOGS_PATH = "/logs"
+local EVENT_PATH = "/event"
+-- Selection changes closer together than this are reported once
+local SELECTION_EVENT_DELAY = 0.25
+local MAX_SELECTION_EVENT_PATHS = 50
+-- Identifies this Studio window so the bridge can tell several apart
+local SESSION_ID = HttpService:GenerateGUID(false)
+local WS_GREETING_TIMEOUT = 2
 local MAX_ACTIVITY_LOG = 10
 
 -- State
@@ -31,6 +90,13 @@ local isConnecting = false
 local pollingEnabled = false
 local isProcessing = false
 local projectInfo = nil
+-- Request ids the bridge told us were cancelled, so queued work is skipped
+local cancelledRequests = {}
+local cancelledCount = 0
+-- Request ids already handled; the bridge hands a request out again if its
+-- answer is slow to arrive, and it mustn't run twice
+local handledRequests = {}
+local handledCount = 0
 local activityLog = {}
 
 -- UI Elements
@@ -486,6 +552,53 @@ local function updateUI()
 	toggleButton:SetActive(isConnected or isConnecting)
 end
 
+-- Query string registering this Studio session with the bridge
+local function bridgeHeaders(contentType)
+	local headers = { ["X-Stud-Secret"] = BRIDGE_SECRET }
+	if contentType then
+		headers["Content-Type"] = contentType
+	end
+	return headers
+end
+
+local function apiUrl(path)
+	return "http://" .. bridgeHost .. apiPrefix .. path
+end
+
+-- Signed-in Roblox account, looked up once; the name needs a web request
+local studioUserId = nil
+local studioUserName = nil
+task.spawn(function()
+	local ok, userId = pcall(function()
+		return game:GetService("StudioService"):GetUserId()
+	end)
+	if not ok or not userId or userId <= 0 then
+		return
+	end
+	studioUserId = userId
+	local nameOk, name = pcall(function()
+		return game:GetService("Players"):GetNameFromUserIdAsync(userId)
+	end)
+	if nameOk then
+		studioUserName = name
+	end
+end)
+
+local function sessionQuery()
+	local query = "session=" .. SESSION_ID
+		.. "&place_id=" .. tostring(game.PlaceId)
+		.. "&place_name=" .. HttpService:UrlEncode(game.Name)
+		.. "&plugin_version=" .. HttpService:UrlEncode(PLUGIN_VERSION)
+		.. "&studio_version=" .. HttpService:UrlEncode(version())
+	if studioUserId then
+		query = query .. "&user_id=" .. tostring(studioUserId)
+	end
+	if studioUserName then
+		query = query .. "&user_name=" .. HttpService:UrlEncode(studioUserName)
+	end
+	return query
+end
+
 -- Utility functions
 local function jsonEncode(data)
 	return HttpService:JSONEncode(data)
@@ -495,6 +608,66 @@ local function jsonDecode(str)
 	return HttpService:JSONDecode(str)
 end
 
+-- Refuse the current request instead of failing it. Retryable refusals are
+-- handed back to us again a little later; others fail at once with `code`.
+local function nack(code, reason, retryable)
+	error({ nack = true, code = code, reason = reason, retryable = retryable == true }, 0)
+end
+
+-- Base64 (for moving binary data like rbxm blobs through JSON)
+local BASE64_CHARS = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"
+local BASE64_LOOKUP = {}
+for i = 1, #BASE64_CHARS do
+	BASE64_LOOKUP[string.byte(BASE64_CHARS, i)] = i - 1
+end
+
+local function base64Encode(buf)
+	local len = buffer.len(buf)
+	local out = table.create(math.ceil(len / 3))
+	for i = 0, len - 1, 3 do
+		local a = buffer.readu8(buf, i)
+		local b = i + 1 < len and buffer.readu8(buf, i + 1) or 0
+		local c = i + 2 < len and buffer.readu8(buf, i + 2) or 0
+		local n = bit32.bor(bit32.lshift(a, 16), bit32.lshift(b, 8), c)
+		local chunk = {}
+		for j = 0, 3 do
+			local index = bit32.band(bit32.rshift(n, 18 - j * 6), 63) + 1
+			chunk[j + 1] = string.sub(BASE64_CHARS, index, index)
+		end
+		if i + 1 >= len then
+			chunk[3] = "="
+		end
+		if i + 2 >= len then
+			chunk[4] = "="
+		end
+		table.insert(out, table.concat(chunk))
+	end
+	return table.concat(out)
+end
+
+local function base64Decode(str)
+	str = string.gsub(str, "[^%w%+/]", "")
+	local len = #str
+	local buf = buffer.create(math.floor(len * 3 / 4))
+	local offset = 0
+	for i = 1, len, 4 do
+		local n = 0
+		local count = 0
+		for j = 0, 3 do
+			local value = BASE64_LOOKUP[string.byte(str, i + j) or 0]
+			if value then
+				n = bit32.bor(n, bit32.lshift(value, 18 - j * 6))
+				count = count + 1
+			end
+		end
+		for j = 0, count - 2 do
+			buffer.writeu8(buf, offset, bit32.band(bit32.rshift(n, 16 - j * 8), 255))
+			offset = offset + 1
+		end
+	end
+	return buf
+end
+
 local function getInstanceFromPath(path)
 	local parts = string.split(path, ".")
 	if #parts < 2 or parts[1] ~= "game" then
@@ -673,25 +846,19 @@ handlers["/instance/properties"] = function(data)
 	return props
 end
 
-handlers["/instance/set"] = function(data)
-	local instance = getInstanceFromPath(data.path)
-	if not instance then
-		error("Instance not found: " .. data.path)
-	end
-	
-	local value = data.value
-	
+-- Convert a string property value from Stud into the matching Roblox type
+local function parseValue(value, property)
 	if value == "true" then
 		value = true
 	elseif value == "false" then
 		value = false
 	elseif tonumber(value) then
 		value = tonumber(value)
-	elseif string.match(value, "^%d+,%s*%d+,%s*%d+$") then
+	elseif string.match(value, "^%-?[%d%.]+,%s*%-?[%d%.]+,%s*%-?[%d%.]+$") then
 		local parts = string.split(value, ",")
 		local a, b, c = tonumber(parts[1]), tonumber(parts[2]), tonumber(parts[3])
 		if a and b and c then
-			if a <= 255 and b <= 255 and c <= 255 and string.find(data.property, "Color") then
+			if a <= 255 and b <= 255 and c <= 255 and string.find(property, "Color") then
 				value = Color3.fromRGB(a, b, c)
 			else
 				value = Vector3.new(a, b, c)
@@ -712,7 +879,16 @@ handlers["/instance/set"] = function(data)
 		end
 	end
 	
-	instance[data.property] = value
+	return value
+end
+
+handlers["/instance/set"] = function(data)
+	local instance = getInstanceFromPath(data.path)
+	if not instance then
+		error("Instance not found: " .. data.path)
+	end
+	
+	instance[data.property] = parseValue(data.value, data.property)
 	
 	return { path = getInstancePath(instance) }
 end
@@ -825,41 +1001,7 @@ handlers["/instance/bulk-set"] = function(data)
 			table.insert(errors, "Not found: " .. op.path)
 		else
 			local success, err = pcall(function()
-				local value = op.value
-				
-				-- Parse value based on type
-				if value == "true" then
-					value = true
-				elseif value == "false" then
-					value = false
-				elseif tonumber(value) then
-					value = tonumber(value)
-				elseif string.match(value, "^%d+,%s*%d+,%s*%d+$") then
-					local parts = string.split(value, ",")
-					local a, b, c = tonumber(parts[1]), tonumber(parts[2]), tonumber(parts[3])
-					if a and b and c then
-						if a <= 255 and b <= 255 and c <= 255 and string.find(op.property, "Color") then
-							value = Color3.fromRGB(a, b, c)
-						else
-							value = Vector3.new(a, b, c)
-						end
-					end
-				elseif string.match(value, "^#%x%x%x%x%x%x$") then
-					local r = tonumber(string.sub(value, 2, 3), 16)
-					local g = tonumber(string.sub(value, 4, 5), 16)
-					local b = tonumber(string.sub(value, 6, 7), 16)
-					value = Color3.fromRGB(r, g, b)
-				elseif string.match(value, "^Enum%.") then
-					local parts = string.split(value, ".")
-					if #parts == 3 then
-						local enumType = Enum[parts[2]]
-						if enumType then
-							value = enumType[parts[3]]
-						end
-					end
-				end
-				
-				instance[op.property] = value
+				instance[op.property] = parseValue(op.value, op.property)
 			end)
 			
 			if success then
@@ -916,8 +1058,47 @@ handlers["/selection/get"] = function()
 	return results
 end
 
+-- Globals code in a sandbox can't see; it reaches the tree only through its folders
+local SANDBOX_HIDDEN = {
+	game = true,
+	Game = true,
+	workspace = true,
+	Workspace = true,
+	plugin = true,
+	shared = true,
+	_G = true,
+	getfenv = true,
+	setfenv = true,
+	require = true,
+}
+
+-- Environment for code confined to the folders its tool's sandbox profile
+-- allows: the first is `scratch` and all of them are in `roots`
+local function sandboxEnv(allowedPaths)
+	local roots = {}
+	for _, rootPath in ipairs(allowedPaths) do
+		local root = getInstanceFromPath(rootPath)
+		if root then
+			table.insert(roots, root)
+		end
+	end
+	if #roots == 0 then
+		error("None of the sandbox folders exist: " .. table.concat(allowedPaths, ", "))
+	end
+	local outer = getfenv(1)
+	return setmetatable({ scratch = roots[1], roots = roots }, {
+		__index = function(_, key)
+			if SANDBOX_HIDDEN[key] then
+				return nil
+			end
+			return outer[key]
+		end,
+	})
+end
+
 handlers["/code/run"] = function(data)
 	local output = {}
+	local env = data.allowedPaths and sandboxEnv(data.allowedPaths)
 	
 	local oldPrint = print
 	print = function(...)
@@ -935,6 +1116,9 @@ handlers["/code/run"] = function(data)
 		if not fn then
 			error(err)
 		end
+		if env then
+			setfenv(fn, env)
+		end
 		return fn()
 	end)
 	
@@ -1002,173 +1186,1639 @@ handlers["/asset/insert"] = function(data)
 	}
 end
 
--- Paths that modify the game and should create undo waypoints
-local modifyingPaths = {
-	["/script/set"] = true,
-	["/script/edit"] = true,
-	["/instance/set"] = true,
-	["/instance/create"] = true,
-	["/instance/delete"] = true,
-	["/instance/clone"] = true,
-	["/instance/move"] = true,
-	["/instance/bulk-create"] = true,
-	["/instance/bulk-delete"] = true,
-	["/instance/bulk-set"] = true,
-	["/code/run"] = true,
-	["/asset/insert"] = true,
-}
+-- Templates: serialize the selection to rbxm, and insert rbxm back
+handlers["/template/serialize"] = function()
+	local SerializationService = game:GetService("SerializationService")
+	local selected = Selection:Get()
+	if #selected == 0 then
+		return { data = "", instances = {} }
+	end
 
--- Friendly names for activity log
-local actionNames = {
-	["/ping"] = "Ping",
-	["/script/get"] = "Read Script",
-	["/script/set"] = "Write Script",
-	["/script/edit"] = "Edit Script",
-	["/instance/children"] = "List Children",
-	["/instance/properties"] = "Get Properties",
-	["/instance/set"] = "Set Property",
-	["/instance/create"] = "Create Instance",
-	["/instance/delete"] = "Delete Instance",
-	["/instance/clone"] = "Clone Instance",
-	["/instance/move"] = "Move Instance",
-	["/instance/bulk-create"] = "Bulk Create",
-	["/instance/bulk-delete"] = "Bulk Delete",
-	["/instance/bulk-set"] = "Bulk Update",
-	["/instance/search"] = "Search",
-	["/selection/get"] = "Get Selection",
-	["/code/run"] = "Run Code",
-	["/asset/insert"] = "Insert Asset",
-}
+	local instances = {}
+	for _, instance in ipairs(selected) do
+		table.insert(instances, { name = instance.Name, className = instance.ClassName })
+	end
 
--- HTTP request handler
-local function handleRequest(request)
-	local path = request.path or request.Path
-	local body = request.body or request.Body
-	
-	local handler = handlers[path]
-	if not handler then
-		return {
-			status = 404,
-			body = jsonEncode({ error = "Not found: " .. path })
-		}
+	local data = SerializationService:SerializeInstancesAsync(selected)
+	return {
+		data = base64Encode(data),
+		instances = instances,
+	}
+end
+
+handlers["/instance/serialize"] = function(data)
+	local instance = getInstanceFromPath(data.path)
+	if not instance then
+		error("Instance not found: " .. tostring(data.path))
 	end
-	
-	local data = {}
-	if body and body ~= "" then
-		local success, parsed = pcall(jsonDecode, body)
-		if success then
-			data = parsed
-		end
+
+	local SerializationService = game:GetService("SerializationService")
+	return {
+		binary = SerializationService:SerializeInstancesAsync({ instance }),
+		contentType = "application/x-rbxm",
+	}
+end
+
+handlers["/template/insert"] = function(data)
+	local SerializationService = game:GetService("SerializationService")
+	local parent = getInstanceFromPath(data.parent)
+	if not parent then
+		error("Parent not found: " .. tostring(data.parent))
 	end
-	
-	-- Create undo waypoint for modifying operations
-	local isModifying = modifyingPaths[path]
-	if isModifying then
-		ChangeHistoryService:SetWaypoint("Stud: " .. path)
+
+	local rbxm = data.attachment and data.attachment.data or base64Decode(data.data)
+	local instances = SerializationService:DeserializeInstancesAsync(rbxm)
+	local inserted = {}
+	for _, instance in ipairs(instances) do
+		instance.Parent = parent
+		table.insert(inserted, getInstancePath(instance))
 	end
-	
-	-- Set processing state
-	isProcessing = true
-	updateUI()
-	
-	local success, result = pcall(handler, data)
-	
-	-- Update activity log
-	local actionName = actionNames[path] or path
-	if success then
-		addActivity(actionName, "success")
-	else
-		addActivity(actionName, "error", tostring(result))
+	Selection:Set(instances)
+
+	return {
+		success = true,
+		name = data.name,
+		inserted = inserted,
+	}
+end
+
+-- Procedural generation: create a batch of parts computed by Stud
+handlers["/procgen/batch"] = function(data)
+	local parent = getInstanceFromPath(data.parent)
+	if not parent then
+		error("Parent not found: " .. tostring(data.parent))
 	end
-	
-	isProcessing = false
-	updateUI()
-	
-	if not success then
-		return {
-			status = 500,
-			body = jsonEncode({ error = tostring(result) })
-		}
+
+	local folder = parent:FindFirstChild(data.folder)
+	if not folder then
+		folder = Instance.new("Folder")
+		folder.Name = data.folder
+		folder.Parent = parent
 	end
-	
-	-- Commit the change so it can be undone
-	if isModifying then
-		ChangeHistoryService:SetWaypoint("Stud: " .. path .. " (done)")
+
+	local created = 0
+	for _, spec in ipairs(data.parts or {}) do
+		local part = Instance.new("Part")
+		part.Name = spec.name
+		part.Anchored = true
+		part.Size = Vector3.new(spec.size[1], spec.size[2], spec.size[3])
+		part.Position = Vector3.new(spec.position[1], spec.position[2], spec.position[3])
+		part.Color = Color3.fromRGB(spec.color[1], spec.color[2], spec.color[3])
+		local ok, material = pcall(function()
+			return Enum.Material[spec.material]
+		end)
+		if ok and material then
+			part.Material = material
+		end
+		part.Parent = folder
+		created = created + 1
 	end
-	
+
 	return {
-		status = 200,
-		body = jsonEncode(result)
+		success = true,
+		folder = getInstancePath(folder),
+		created = created,
 	}
 end
 
--- Polling loop
-local function pollServer()
-	local failCount = 0
-	local maxFails = 3
-	
-	while pollingEnabled do
-		local success, response = pcall(function()
-			return HttpService:RequestAsync({
-				Url = POLL_URL,
-				Method = "GET",
+-- Palette: report every color in the selection, and apply a recolor from Stud
+local function colorProperties(instance)
+	if instance:IsA("BasePart") then
+		return { "Color" }
+	elseif instance:IsA("TextLabel") or instance:IsA("TextButton") or instance:IsA("TextBox") then
+		return { "BackgroundColor3", "TextColor3" }
+	elseif instance:IsA("ImageLabel") or instance:IsA("ImageButton") then
+		return { "BackgroundColor3", "ImageColor3" }
+	elseif instance:IsA("GuiObject") then
+		return { "BackgroundColor3" }
+	elseif instance:IsA("UIStroke") then
+		return { "Color" }
+	end
+	return {}
+end
+
+handlers["/palette/extract"] = function()
+	local usages = {}
+	local function collect(instance)
+		for _, property in ipairs(colorProperties(instance)) do
+			local color = instance[property]
+			table.insert(usages, {
+				path = getInstancePath(instance),
+				property = property,
+				color = {
+					math.floor(color.R * 255 + 0.5),
+					math.floor(color.G * 255 + 0.5),
+					math.floor(color.B * 255 + 0.5),
+				},
 			})
-		end)
-		
-		if success and response.Success then
-			-- Connected!
-			if not isConnected then
-				isConnected = true
-				isConnecting = false
-				failCount = 0
-				updateUI()
-				addActivity("Connected", "success")
-				print("[stud-bridge] Connected to Stud Desktop")
-			end
-			
-			local data = jsonDecode(response.Body)
-			
-			-- Extract project info if available
-			if data and data.project then
-				projectInfo = data.project
-				updateUI()
-			end
-			
-			if data and data.request then
-				local result = handleRequest(data.request)
-				pcall(function()
-					HttpService:RequestAsync({
-						Url = RESPOND_URL,
-						Method = "POST",
-						Headers = { ["Content-Type"] = "application/json" },
-						Body = jsonEncode({
-							id = data.id,
-							response = result,
-						}),
-					})
-				end)
+		end
+	end
+
+	for _, selected in ipairs(Selection:Get()) do
+		collect(selected)
+		for _, descendant in ipairs(selected:GetDescendants()) do
+			collect(descendant)
+		end
+	end
+
+	return { usages = usages }
+end
+
+handlers["/palette/apply"] = function(data)
+	local applied = 0
+	for _, change in ipairs(data.changes or {}) do
+		local instance = getInstanceFromPath(change.path)
+		if instance then
+			instance[change.property] = Color3.fromRGB(change.color[1], change.color[2], change.color[3])
+			applied = applied + 1
+		end
+	end
+
+	return {
+		success = true,
+		applied = applied,
+	}
+end
+
+-- Animation: inspect rigs and page through keyframe data
+handlers["/animation/list"] = function()
+	local rigs = {}
+	for _, selected in ipairs(Selection:Get()) do
+		local humanoid = selected:FindFirstChildWhichIsA("Humanoid", true)
+		local controller = selected:FindFirstChildWhichIsA("AnimationController", true)
+		local animator = selected:FindFirstChildWhichIsA("Animator", true)
+
+		local animations = {}
+		local sequences = {}
+		for _, descendant in ipairs(selected:GetDescendants()) do
+			if descendant:IsA("Animation") then
+				table.insert(animations, {
+					path = getInstancePath(descendant),
+					animationId = descendant.AnimationId,
+				})
+			elseif descendant:IsA("KeyframeSequence") then
+				table.insert(sequences, {
+					path = getInstancePath(descendant),
+					keyframes = #descendant:GetKeyframes(),
+					loop = descendant.Loop,
+					priority = descendant.Priority.Name,
+				})
 			end
-			failCount = 0
-		else
-			failCount = failCount + 1
-			if isConnected and failCount >= maxFails then
-				isConnected = false
-				isConnecting = true
-				projectInfo = nil
-				updateUI()
-				addActivity("Connection lost", "error")
-				print("[stud-bridge] Connection lost, retrying...")
+		end
+
+		local tracks = {}
+		if animator then
+			for _, track in ipairs(animator:GetPlayingAnimationTracks()) do
+				table.insert(tracks, {
+					name = track.Name,
+					animationId = track.Animation and track.Animation.AnimationId or "",
+					isPlaying = track.IsPlaying,
+					length = track.Length,
+					looped = track.Looped,
+					priority = track.Priority.Name,
+					speed = track.Speed,
+					weight = track.WeightCurrent,
+				})
 			end
 		end
-		
-		task.wait(0.1)
+
+		table.insert(rigs, {
+			path = getInstancePath(selected),
+			className = selected.ClassName,
+			hasHumanoid = humanoid ~= nil,
+			rigType = humanoid and humanoid.RigType.Name or nil,
+			hasAnimationController = controller ~= nil,
+			hasAnimator = animator ~= nil,
+			animations = animations,
+			keyframeSequences = sequences,
+			playingTracks = tracks,
+		})
+	end
+
+	return { rigs = rigs }
+end
+
+handlers["/animation/keyframes"] = function(data)
+	local sequence = getInstanceFromPath(data.path)
+	if not sequence or not sequence:IsA("KeyframeSequence") then
+		error("KeyframeSequence not found: " .. tostring(data.path))
+	end
+
+	local keyframes = sequence:GetKeyframes()
+	table.sort(keyframes, function(a, b)
+		return a.Time < b.Time
+	end)
+
+	local offset = data.offset or 0
+	local limit = data.limit or 50
+	local page = {}
+	for i = offset + 1, math.min(offset + limit, #keyframes) do
+		local keyframe = keyframes[i]
+		local poses = {}
+		for _, pose in ipairs(keyframe:GetDescendants()) do
+			if pose:IsA("Pose") then
+				table.insert(poses, {
+					name = pose.Name,
+					cframe = { pose.CFrame:GetComponents() },
+					weight = pose.Weight,
+					easingStyle = pose.EasingStyle.Name,
+					easingDirection = pose.EasingDirection.Name,
+				})
+			end
+		end
+		table.insert(page, {
+			name = keyframe.Name,
+			time = keyframe.Time,
+			poses = poses,
+		})
+	end
+
+	return {
+		total = #keyframes,
+		keyframes = page,
+	}
+end
+
+-- Pathfinding: compute paths for debugging NPC navigation, optionally drawing them
+local PATH_DEBUG_FOLDER = "StudPathDebug"
+
+handlers["/pathfinding/compute"] = function(data)
+	local PathfindingService = game:GetService("PathfindingService")
+	local start = Vector3.new(data.start[1], data.start[2], data.start[3])
+	local goal = Vector3.new(data.goal[1], data.goal[2], data.goal[3])
+
+	local path = PathfindingService:CreatePath(data.agent or {})
+	local ok, err = pcall(function()
+		path:ComputeAsync(start, goal)
+	end)
+	if not ok then
+		return { status = "NoPath", waypoints = {}, error = tostring(err) }
+	end
+
+	local waypoints = {}
+	for _, waypoint in ipairs(path:GetWaypoints()) do
+		table.insert(waypoints, {
+			position = { waypoint.Position.X, waypoint.Position.Y, waypoint.Position.Z },
+			action = waypoint.Action.Name,
+			label = waypoint.Label,
+		})
+	end
+
+	if data.visualize then
+		local folder = workspace:FindFirstChild(PATH_DEBUG_FOLDER)
+		if not folder then
+			folder = Instance.new("Folder")
+			folder.Name = PATH_DEBUG_FOLDER
+			folder.Archivable = false
+			folder.Parent = workspace
+		end
+		local success = path.Status == Enum.PathStatus.Success
+		for i, waypoint in ipairs(path:GetWaypoints()) do
+			local marker = Instance.new("Part")
+			marker.Name = (data.label or "Path") .. "_" .. i
+			marker.Shape = Enum.PartType.Ball
+			marker.Size = Vector3.new(0.6, 0.6, 0.6)
+			marker.Position = waypoint.Position
+			marker.Anchored = true
+			marker.CanCollide = false
+			marker.CanQuery = false
+			marker.Material = Enum.Material.Neon
+			if waypoint.Action == Enum.PathWaypointAction.Jump then
+				marker.Color = Color3.fromRGB(255, 170, 0)
+			elseif success then
+				marker.Color = Color3.fromRGB(0, 200, 120)
+			else
+				marker.Color = Color3.fromRGB(220, 50, 50)
+			end
+			marker.Parent = folder
+		end
+	end
+
+	return {
+		status = path.Status.Name,
+		waypoints = waypoints,
+	}
+end
+
+handlers["/pathfinding/clear"] = function()
+	local folder = workspace:FindFirstChild(PATH_DEBUG_FOLDER)
+	if folder then
+		folder:Destroy()
+	end
+	return { success = true }
+end
+
+-- Scaffolding: create a recipe's instances and scripts as one transaction,
+-- removing everything already created if any step fails
+handlers["/scaffold/apply"] = function(data)
+	local created = {}
+	local ok, err = pcall(function()
+		for _, step in ipairs(data.steps or {}) do
+			local parent = getInstanceFromPath(step.parent)
+			if not parent then
+				error("Parent not found: " .. tostring(step.parent))
+			end
+			if parent:FindFirstChild(step.name) then
+				error(step.parent .. "." .. step.name .. " already exists")
+			end
+
+			local instance = Instance.new(step.className)
+			instance.Name = step.name
+			for property, value in pairs(step.properties or {}) do
+				instance[property] = parseValue(value, property)
+			end
+			if step.source then
+				instance.Source = step.source
+			end
+			instance.Parent = parent
+			table.insert(created, instance)
+		end
+	end)
+
+	if not ok then
+		for i = #created, 1, -1 do
+			created[i]:Destroy()
+		end
+		error(err)
+	end
+
+	local paths = {}
+	for _, instance in ipairs(created) do
+		table.insert(paths, getInstancePath(instance))
+	end
+	return {
+		success = true,
+		created = paths,
+	}
+end
+
+-- Services covered by generated place documentation
+local DOC_SERVICES = {
+	"Workspace",
+	"ReplicatedFirst",
+	"ReplicatedStorage",
+	"ServerScriptService",
+	"ServerStorage",
+	"StarterGui",
+	"StarterPack",
+	"StarterPlayer",
+	"Lighting",
+	"SoundService",
+	"Teams",
+}
+
+local REMOTE_CLASSES = {
+	RemoteEvent = true,
+	RemoteFunction = true,
+	UnreliableRemoteEvent = true,
+	BindableEvent = true,
+	BindableFunction = true,
+}
+
+handlers["/docs/outline"] = function()
+	local services = {}
+	local scripts = {}
+	local remotes = {}
+
+	for _, serviceName in ipairs(DOC_SERVICES) do
+		local ok, service = pcall(function()
+			return game:GetService(serviceName)
+		end)
+		if ok and service then
+			local children = {}
+			for _, child in ipairs(service:GetChildren()) do
+				table.insert(children, {
+					name = child.Name,
+					className = child.ClassName,
+					descendants = #child:GetDescendants(),
+				})
+			end
+
+			local classCounts = {}
+			for _, descendant in ipairs(service:GetDescendants()) do
+				classCounts[descendant.ClassName] = (classCounts[descendant.ClassName] or 0) + 1
+				if descendant:IsA("LuaSourceContainer") then
+					table.insert(scripts, {
+						path = getInstancePath(descendant),
+						className = descendant.ClassName,
+						source = ScriptEditorService:GetEditorSource(descendant) or descendant.Source,
+					})
+				elseif REMOTE_CLASSES[descendant.ClassName] then
+					table.insert(remotes, {
+						path = getInstancePath(descendant),
+						name = descendant.Name,
+						className = descendant.ClassName,
+					})
+				end
+			end
+
+			table.insert(services, {
+				name = serviceName,
+				children = children,
+				classCounts = classCounts,
+			})
+		end
+	end
+
+	return {
+		place = game.Name,
+		placeId = game.PlaceId,
+		services = services,
+		scripts = scripts,
+		remotes = remotes,
+	}
+end
+
+handlers["/docs/modules"] = function()
+	local modules = {}
+	for _, serviceName in ipairs(DOC_SERVICES) do
+		local ok, service = pcall(function()
+			return game:GetService(serviceName)
+		end)
+		if ok and service then
+			for _, descendant in ipairs(service:GetDescendants()) do
+				if descendant:IsA("ModuleScript") then
+					table.insert(modules, {
+						path = getInstancePath(descendant),
+						source = ScriptEditorService:GetEditorSource(descendant) or descendant.Source,
+					})
+				end
+			end
+		end
+	end
+	return { modules = modules }
+end
+
+-- Script index for server-side find and replace: every script under a root
+-- (or the documented services) with its current source
+handlers["/scripts/index"] = function(data)
+	local roots = {}
+	if data.root then
+		local root = getInstanceFromPath(data.root)
+		if not root then
+			error("Instance not found: " .. data.root)
+		end
+		table.insert(roots, root)
+	else
+		for _, serviceName in ipairs(DOC_SERVICES) do
+			local ok, service = pcall(function()
+				return game:GetService(serviceName)
+			end)
+			if ok and service then
+				table.insert(roots, service)
+			end
+		end
+	end
+
+	local scripts = {}
+	for _, root in ipairs(roots) do
+		local candidates = root:GetDescendants()
+		table.insert(candidates, 1, root)
+		for _, instance in ipairs(candidates) do
+			if instance:IsA("LuaSourceContainer") then
+				table.insert(scripts, {
+					path = getInstancePath(instance),
+					className = instance.ClassName,
+					source = ScriptEditorService:GetEditorSource(instance) or instance.Source,
+				})
+			end
+		end
+	end
+	return { scripts = scripts }
+end
+
+-- Compile every script under root (default: the documented services) without
+-- running it and report syntax errors with their line numbers
+handlers["/scripts/syntax"] = function(data)
+	local index = handlers["/scripts/index"](data)
+	local errors = {}
+	for _, script in ipairs(index.scripts) do
+		local fn, err = loadstring(script.source, "=" .. script.path)
+		if not fn and err then
+			local line, message = string.match(err, ":(%d+): (.*)$")
+			table.insert(errors, {
+				path = script.path,
+				line = tonumber(line),
+				message = message or err,
+			})
+		end
+	end
+	return { checked = #index.scripts, errors = errors }
+end
+
+-- Replace the source of several scripts as one undoable change. Every edit is
+-- checked first; if any script is missing or no longer has the `expected`
+-- source, nothing is written.
+handlers["/scripts/batch-set"] = function(data)
+	local targets = {}
+	for _, edit in ipairs(data.edits or {}) do
+		local instance = getInstanceFromPath(edit.path)
+		if not instance then
+			error("Instance not found: " .. edit.path)
+		end
+		if not instance:IsA("LuaSourceContainer") then
+			error("Not a script: " .. edit.path)
+		end
+		if edit.expected then
+			local current = ScriptEditorService:GetEditorSource(instance) or instance.Source
+			if current ~= edit.expected then
+				error("Script changed since the preview: " .. edit.path)
+			end
+		end
+		table.insert(targets, { instance = instance, source = edit.source })
+	end
+
+	for _, target in ipairs(targets) do
+		ScriptEditorService:UpdateSourceAsync(target.instance, function()
+			return target.source
+		end)
+	end
+	return { applied = #targets }
+end
+
+-- Tags: CollectionService registry and bulk tagging
+local TAG_SAMPLE_LIMIT = 5
+local TAG_METHODS = {
+	GetTagged = true,
+	HasTag = true,
+	AddTag = true,
+	RemoveTag = true,
+	GetInstanceAddedSignal = true,
+	GetInstanceRemovedSignal = true,
+}
+
+handlers["/tags/list"] = function(data)
+	if data.path then
+		local instance = getInstanceFromPath(data.path)
+		if not instance then
+			error("Instance not found: " .. data.path)
+		end
+		return { path = data.path, tags = instance:GetTags() }
+	end
+
+	-- Scripts that look tags up by literal name, to show which systems a tag drives
+	local references = {}
+	for _, serviceName in ipairs(DOC_SERVICES) do
+		local ok, service = pcall(function()
+			return game:GetService(serviceName)
+		end)
+		if ok and service then
+			for _, descendant in ipairs(service:GetDescendants()) do
+				if descendant:IsA("LuaSourceContainer") then
+					local source = ScriptEditorService:GetEditorSource(descendant) or ""
+					local seen = {}
+					for method, tag in source:gmatch("[:%.](%a+)%(%s*[^,%)]-,?%s*[\"']([^\"']+)[\"']") do
+						if TAG_METHODS[method] and not seen[tag] then
+							seen[tag] = true
+							references[tag] = references[tag] or {}
+							table.insert(references[tag], getInstancePath(descendant))
+						end
+					end
+				end
+			end
+		end
+	end
+
+	local tags = {}
+	local listed = {}
+	for _, tag in ipairs(CollectionService:GetAllTags()) do
+		listed[tag] = true
+		local tagged = CollectionService:GetTagged(tag)
+		local classCounts = {}
+		local samples = {}
+		for _, instance in ipairs(tagged) do
+			classCounts[instance.ClassName] = (classCounts[instance.ClassName] or 0) + 1
+			if #samples < TAG_SAMPLE_LIMIT then
+				table.insert(samples, getInstancePath(instance))
+			end
+		end
+		table.insert(tags, {
+			tag = tag,
+			count = #tagged,
+			classCounts = classCounts,
+			samples = samples,
+			scripts = references[tag] or {},
+		})
+	end
+	for tag, scripts in pairs(references) do
+		if not listed[tag] then
+			table.insert(tags, {
+				tag = tag,
+				count = 0,
+				classCounts = {},
+				samples = {},
+				scripts = scripts,
+			})
+		end
+	end
+	return { tags = tags }
+end
+
+handlers["/tags/candidates"] = function(data)
+	local root = getInstanceFromPath(data.root or "game")
+	if not root then
+		error("Root not found: " .. (data.root or "game"))
+	end
+
+	local instances = {}
+	local function consider(instance)
+		if not data.className or instance:IsA(data.className) then
+			local info = instanceToInfo(instance, false)
+			info.tags = instance:GetTags()
+			table.insert(instances, info)
+		end
+	end
+
+	consider(root)
+	for _, descendant in ipairs(root:GetDescendants()) do
+		consider(descendant)
+	end
+	return { instances = instances }
+end
+
+handlers["/tags/apply"] = function(data)
+	local applied = 0
+	local missing = {}
+	for _, operation in ipairs(data.operations or {}) do
+		local instance = getInstanceFromPath(operation.path)
+		if not instance then
+			table.insert(missing, operation.path)
+		elseif operation.remove then
+			instance:RemoveTag(operation.tag)
+			applied = applied + 1
+		else
+			instance:AddTag(operation.tag)
+			applied = applied + 1
+		end
+	end
+
+	return {
+		success = true,
+		applied = applied,
+		missing = missing,
+	}
+end
+
+-- Attributes: typed values as { type = "Vector3", value = { 1, 2, 3 } }
+local function attributeToJson(value)
+	local valueType = typeof(value)
+	local encoded = value
+	if valueType == "Vector3" then
+		encoded = { value.X, value.Y, value.Z }
+	elseif valueType == "Vector2" then
+		encoded = { value.X, value.Y }
+	elseif valueType == "Color3" then
+		encoded = {
+			math.floor(value.R * 255 + 0.5),
+			math.floor(value.G * 255 + 0.5),
+			math.floor(value.B * 255 + 0.5),
+		}
+	elseif valueType == "UDim" then
+		encoded = { value.Scale, value.Offset }
+	elseif valueType == "UDim2" then
+		encoded = { value.X.Scale, value.X.Offset, value.Y.Scale, value.Y.Offset }
+	elseif valueType == "NumberRange" then
+		encoded = { value.Min, value.Max }
+	elseif valueType == "CFrame" then
+		encoded = { value:GetComponents() }
+	elseif valueType ~= "string" and valueType ~= "number" and valueType ~= "boolean" then
+		encoded = tostring(value)
+	end
+	return { type = valueType, value = encoded }
+end
+
+local function attributeFromJson(valueType, value)
+	if value == nil then
+		return nil
+	elseif valueType == "Vector3" then
+		return Vector3.new(value[1], value[2], value[3])
+	elseif valueType == "Vector2" then
+		return Vector2.new(value[1], value[2])
+	elseif valueType == "Color3" then
+		if type(value) == "string" then
+			return Color3.fromHex(value)
+		end
+		return Color3.fromRGB(value[1], value[2], value[3])
+	elseif valueType == "UDim" then
+		return UDim.new(value[1], value[2])
+	elseif valueType == "UDim2" then
+		return UDim2.new(value[1], value[2], value[3], value[4])
+	elseif valueType == "NumberRange" then
+		return NumberRange.new(value[1], value[2])
+	elseif valueType == "BrickColor" then
+		return BrickColor.new(value)
+	elseif valueType == "CFrame" then
+		return CFrame.new(table.unpack(value))
+	end
+	return value
+end
+
+local function encodeAttributes(instance)
+	local attributes = {}
+	for name, value in pairs(instance:GetAttributes()) do
+		attributes[name] = attributeToJson(value)
+	end
+	return attributes
+end
+
+-- Classes from `classNames` the instance is (or inherits from)
+local function matchingClasses(instance, classNames)
+	local matches = {}
+	for _, className in ipairs(classNames or {}) do
+		local ok, isA = pcall(function()
+			return instance:IsA(className)
+		end)
+		if ok and isA then
+			table.insert(matches, className)
+		end
+	end
+	return matches
+end
+
+local function describeForSchema(instance, classNames)
+	local info = instanceToInfo(instance, false)
+	info.isA = matchingClasses(instance, classNames)
+	info.tags = instance:GetTags()
+	info.attributes = encodeAttributes(instance)
+	return info
+end
+
+handlers["/attributes/get"] = function(data)
+	local instance = getInstanceFromPath(data.path)
+	if not instance then
+		error("Instance not found: " .. data.path)
+	end
+	return { path = getInstancePath(instance), attributes = encodeAttributes(instance) }
+end
+
+handlers["/attributes/describe"] = function(data)
+	local instances = {}
+	for _, path in ipairs(data.paths or {}) do
+		local instance = getInstanceFromPath(path)
+		if instance then
+			table.insert(instances, describeForSchema(instance, data.classNames))
+		end
+	end
+	return { instances = instances }
+end
+
+handlers["/attributes/scan"] = function(data)
+	local root = getInstanceFromPath(data.root or "game")
+	if not root then
+		error("Root not found: " .. (data.root or "game"))
+	end
+
+	local tags = {}
+	for _, tag in ipairs(data.tags or {}) do
+		tags[tag] = true
+	end
+
+	-- Only instances some schema applies to
+	local instances = {}
+	local function consider(instance)
+		local described = describeForSchema(instance, data.classNames)
+		local relevant = #described.isA > 0
+		for _, tag in ipairs(described.tags) do
+			relevant = relevant or tags[tag] == true
+		end
+		if relevant then
+			table.insert(instances, described)
+		end
+	end
+
+	consider(root)
+	for _, descendant in ipairs(root:GetDescendants()) do
+		consider(descendant)
+	end
+	return { instances = instances }
+end
+
+handlers["/attributes/set"] = function(data)
+	local updated = 0
+	local errors = {}
+	for _, op in ipairs(data.operations or {}) do
+		local instance = getInstanceFromPath(op.path)
+		if not instance then
+			table.insert(errors, "Not found: " .. op.path)
+		else
+			local success, err = pcall(function()
+				instance:SetAttribute(op.name, attributeFromJson(op.type, op.value))
+			end)
+			if success then
+				updated = updated + 1
+			else
+				table.insert(errors, op.path .. "@" .. op.name .. ": " .. tostring(err))
+			end
+		end
+	end
+	return { updated = updated, errors = errors }
+end
+
+-- Paths that modify the game and should create undo waypoints
+local modifyingPaths = {
+	["/script/set"] = true,
+	["/script/edit"] = true,
+	["/instance/set"] = true,
+	["/instance/create"] = true,
+	["/instance/delete"] = true,
+	["/instance/clone"] = true,
+	["/instance/move"] = true,
+	["/instance/bulk-create"] = true,
+	["/instance/bulk-delete"] = true,
+	["/instance/bulk-set"] = true,
+	["/code/run"] = true,
+	["/asset/insert"] = true,
+	["/template/insert"] = true,
+	["/procgen/batch"] = true,
+	["/palette/apply"] = true,
+	["/scaffold/apply"] = true,
+	["/tags/apply"] = true,
+	["/attributes/set"] = true,
+	["/scripts/batch-set"] = true,
+}
+
+-- Friendly names for activity log
+local actionNames = {
+	["/ping"] = "Ping",
+	["/script/get"] = "Read Script",
+	["/script/set"] = "Write Script",
+	["/script/edit"] = "Edit Script",
+	["/instance/children"] = "List Children",
+	["/instance/properties"] = "Get Properties",
+	["/instance/set"] = "Set Property",
+	["/instance/create"] = "Create Instance",
+	["/instance/delete"] = "Delete Instance",
+	["/instance/clone"] = "Clone Instance",
+	["/instance/move"] = "Move Instance",
+	["/instance/bulk-create"] = "Bulk Create",
+	["/instance/bulk-delete"] = "Bulk Delete",
+	["/instance/bulk-set"] = "Bulk Update",
+	["/instance/search"] = "Search",
+	["/selection/get"] = "Get Selection",
+	["/code/run"] = "Run Code",
+	["/asset/insert"] = "Insert Asset",
+	["/template/serialize"] = "Save Template",
+	["/template/insert"] = "Insert Template",
+	["/instance/serialize"] = "Export Instance",
+	["/procgen/batch"] = "Generate Parts",
+	["/palette/extract"] = "Read Colors",
+	["/palette/apply"] = "Apply Palette",
+	["/animation/list"] = "List Animations",
+	["/animation/keyframes"] = "Read Keyframes",
+	["/pathfinding/compute"] = "Compute Path",
+	["/pathfinding/clear"] = "Clear Paths",
+	["/scaffold/apply"] = "Scaffold System",
+	["/docs/outline"] = "Read Place Outline",
+	["/docs/modules"] = "Index Modules",
+	["/tags/list"] = "List Tags",
+	["/tags/candidates"] = "Find Tag Targets",
+	["/tags/apply"] = "Update Tags",
+	["/attributes/get"] = "Read Attributes",
+	["/attributes/describe"] = "Check Attribute Targets",
+	["/attributes/scan"] = "Audit Attributes",
+	["/attributes/set"] = "Set Attributes",
+	["/scripts/index"] = "Index Scripts",
+	["/scripts/syntax"] = "Check Syntax",
+	["/scripts/batch-set"] = "Edit Scripts",
+}
+
+-- HTTP request handler
+local function handleRequest(request)
+	local path = request.path or request.Path
+	local body = request.body or request.Body
+	
+	local handler = handlers[path]
+	if not handler then
+		return {
+			status = 404,
+			body = jsonEncode({ error = "Not found: " .. path })
+		}
+	end
+	
+	-- Nothing can run until the place has finished loading
+	if not game:IsLoaded() then
+		return { nack = { code = "place_not_loaded", reason = "The place is still loading in Studio", retryable = true } }
+	end
+	
+	local data = {}
+	if body and body ~= "" then
+		local success, parsed = pcall(jsonDecode, body)
+		if success then
+			data = parsed
+		end
+	end
+	
+	-- Set by the bridge when the tool's sandbox profile confines it to some folders
+	if request.allowed_paths then
+		data.allowedPaths = request.allowed_paths
+	end
+	
+	-- Binary data sent alongside the body arrives base64-encoded
+	if request.attachment then
+		data.attachment = {
+			contentType = request.attachment.content_type,
+			data = base64Decode(request.attachment.data),
+		}
+	end
+	
+	-- Create undo waypoint for modifying operations
+	local isModifying = modifyingPaths[path]
+	if isModifying then
+		ChangeHistoryService:SetWaypoint("Stud: " .. path)
+	end
+	
+	-- Set processing state
+	isProcessing = true
+	updateUI()
+	
+	local success, result = pcall(handler, data)
+	
+	-- Update activity log
+	local actionName = actionNames[path] or path
+	if success then
+		addActivity(actionName, "success")
+	else
+		local message = type(result) == "table" and result.nack and result.reason or tostring(result)
+		addActivity(actionName, "error", message)
+	end
+	
+	isProcessing = false
+	updateUI()
+	
+	if not success then
+		if type(result) == "table" and result.nack then
+			return { nack = result }
+		end
+		-- Writing script sources needs the Script Injection permission; retrying won't help
+		if string.find(tostring(result), "lacking capability", 1, true) then
+			return {
+				nack = {
+					code = "not_permitted",
+					reason = "Studio didn't allow this: " .. tostring(result),
+					retryable = false,
+				},
+			}
+		end
+		return {
+			status = 500,
+			body = jsonEncode({ error = tostring(result) })
+		}
+	end
+	
+	-- Commit the change so it can be undone
+	if isModifying then
+		ChangeHistoryService:SetWaypoint("Stud: " .. path .. " (done)")
+	end
+	
+	-- Handlers return { binary = buffer, contentType = ... } to send raw bytes
+	if type(result) == "table" and typeof(result.binary) == "buffer" then
+		return {
+			status = 200,
+			body = base64Encode(result.binary),
+			content_type = result.contentType or "application/octet-stream",
+		}
+	end
+	
+	return {
+		status = 200,
+		body = jsonEncode(result)
+	}
+end
+
+-- Run a request's handler, renewing its lease with the bridge while it runs
+-- so a slow tool isn't handed out again and failed
+local function runLeased(id, request)
+	if not bridgeFeatures.lease then
+		return handleRequest(request)
+	end
+	local done = false
+	task.spawn(function()
+		local interval = math.max(leaseSecs / 3, 1)
+		while true do
+			task.wait(interval)
+			if done then
+				break
+			end
+			pcall(function()
+				HttpService:RequestAsync({
+					Url = apiUrl(LEASE_PATH),
+					Method = "POST",
+					Headers = bridgeHeaders("application/json"),
+					Body = jsonEncode({ id = id }),
+				})
+			end)
+		end
+	end)
+	local result = handleRequest(request)
+	done = true
+	return result
+end
+
+-- Where a part of `body` starting at `start` should end so a multi-byte
+-- UTF-8 character isn't split across parts
+local function partEnd(body, start, size)
+	local finish = math.min(start + size - 1, #body)
+	while finish < #body and finish > start do
+		local nextByte = string.byte(body, finish + 1)
+		if nextByte < 0x80 or nextByte >= 0xC0 then
+			break
+		end
+		finish = finish - 1
+	end
+	return finish
+end
+
+-- Upload a large response body to the bridge in parts. A part that fails is
+-- sent again from wherever the bridge says it got to. Returns the upload id,
+-- or nil if the upload couldn't be finished.
+local function uploadBody(body, requestId)
+	local function post(path, payload, contentType)
+		local ok, response = pcall(function()
+			return HttpService:RequestAsync({
+				Url = apiUrl(path),
+				Method = "POST",
+				Headers = bridgeHeaders(contentType),
+				Body = payload,
+			})
+		end)
+		if not ok then
+			return nil
+		end
+		local decoded, data = pcall(jsonDecode, response.Body)
+		return decoded and data or nil
+	end
+
+	local started = post(UPLOAD_PATH, jsonEncode({ size = #body, request_id = requestId }), "application/json")
+	if not started or not started.upload_id then
+		return nil
+	end
+	local uploadPath = UPLOAD_PATH .. "/" .. started.upload_id
+	local offset = 0
+	local failures = 0
+	while offset < #body do
+		local finish = partEnd(body, offset + 1, UPLOAD_PART_SIZE)
+		local reply = post(uploadPath .. "?offset=" .. offset, string.sub(body, offset + 1, finish), "application/octet-stream")
+		if reply and type(reply.offset) == "number" and reply.offset > offset then
+			offset = reply.offset
+			failures = 0
+		else
+			-- After a 409 the bridge says where it got to
+			if reply and type(reply.offset) == "number" then
+				offset = reply.offset
+			end
+			failures = failures + 1
+			if failures > UPLOAD_MAX_RETRIES then
+				return nil
+			end
+		end
+	end
+
+	local finished = post(uploadPath .. "/finalize", "{}", "application/json")
+	return finished and finished.finalized and started.upload_id or nil
+end
+
+-- Build the messages that deliver a response: one normally, an upload
+-- reference for very large JSON bodies, or numbered parts otherwise. The
+-- request's trace id is echoed back so the bridge can tie the response to its
+-- timeline. Returns a list of { path, payload }.
+local function encodeResponse(id, result, traceId)
+	if result.nack then
+		if bridgeFeatures.nack then
+			local payload = {
+				id = id,
+				code = result.nack.code,
+				reason = result.nack.reason,
+				retryable = result.nack.retryable,
+				trace_id = traceId,
+			}
+			return { { path = NACK_PATH, payload = jsonEncode(payload) } }
+		end
+		-- Older bridges only understand responses
+		local status = result.nack.retryable and 503 or 422
+		result = { status = status, body = jsonEncode({ error = result.nack.reason, code = result.nack.code }) }
+	end
+	local body = result.body or ""
+	if #body <= RESPONSE_CHUNK_SIZE then
+		local response = {
+			status = result.status,
+			body = result.body,
+			content_type = result.content_type,
+			protocol_version = protocolVersion >= 2 and protocolVersion or nil,
+		}
+		return { { path = RESPOND_PATH, payload = jsonEncode({ id = id, response = response, trace_id = traceId }) } }
+	end
+
+	-- Binary bodies are already base64 and keep going out as parts
+	if #body > UPLOAD_THRESHOLD and protocolVersion >= 2 and not result.content_type then
+		local uploadId = uploadBody(body, id)
+		if uploadId then
+			local response = {
+				status = result.status,
+				body = jsonEncode({ stud_upload = uploadId }),
+				protocol_version = protocolVersion,
+			}
+			return { { path = RESPOND_PATH, payload = jsonEncode({ id = id, response = response, trace_id = traceId }) } }
+		end
+	end
+
+	local chunks = {}
+	local start = 1
+	while start <= #body do
+		local finish = partEnd(body, start, RESPONSE_CHUNK_SIZE)
+		table.insert(chunks, {
+			id = id,
+			seq = #chunks,
+			status = result.status,
+			data = string.sub(body, start, finish),
+			trace_id = traceId,
+		})
+		start = finish + 1
+	end
+	chunks[#chunks].last = true
+	chunks[#chunks].content_type = result.content_type
+	for _, chunk in ipairs(chunks) do
+		chunk.total = #chunks
+	end
+
+	local messages = {}
+	for _, chunk in ipairs(chunks) do
+		table.insert(messages, { path = RESPOND_CHUNK_PATH, payload = jsonEncode(chunk) })
+	end
+	return messages
+end
+
+-- Remember cancellation notices from a poll or socket message
+local function noteCancelled(data)
+	if not data or not data.cancelled then
+		return
+	end
+	if cancelledCount > 200 then
+		cancelledRequests = {}
+		cancelledCount = 0
+	end
+	for _, id in ipairs(data.cancelled) do
+		if not cancelledRequests[id] then
+			cancelledRequests[id] = true
+			cancelledCount = cancelledCount + 1
+			addActivity("Cancelled", "error", id)
+		end
+	end
+end
+
+-- Claim a delivered request, false if it was already handled
+local function claimRequest(id)
+	if handledRequests[id] then
+		return false
+	end
+	if handledCount > 500 then
+		handledRequests = {}
+		handledCount = 0
+	end
+	handledRequests[id] = true
+	handledCount = handledCount + 1
+	return true
+end
+
+-- Whether a Stud bridge answers at host
+local function isStudBridge(host)
+	local ok, response = pcall(function()
+		return HttpService:RequestAsync({
+			Url = "http://" .. host .. HEALTH_PATH,
+			Method = "GET",
+		})
+	end)
+	if not (ok and response.Success) then
+		return false
+	end
+	local decoded, data = pcall(jsonDecode, response.Body)
+	return decoded and type(data) == "table" and data.service == "stud-bridge"
+end
+
+-- Find the bridge when it isn't on bridgeHost (it moved because another app
+-- held its port). Returns true if bridgeHost now points at a bridge.
+local function discoverBridge()
+	if isStudBridge(bridgeHost) then
+		return true
+	end
+	for offset = 0, DISCOVERY_PORT_COUNT - 1 do
+		local host = "localhost:" .. tostring(DISCOVERY_FIRST_PORT + offset)
+		if host ~= bridgeHost and isStudBridge(host) then
+			print("[stud-bridge] Found Stud at " .. host)
+			bridgeHost = host
+			return true
+		end
+	end
+	return false
+end
+
+-- Agree on a protocol version with the bridge and switch to its routes.
+-- Anything but a clean answer (old bridge, not running yet) means v1.
+local function negotiateProtocol()
+	local ok, response = pcall(function()
+		return HttpService:RequestAsync({
+			Url = "http://" .. bridgeHost .. HELLO_PATH,
+			Method = "POST",
+			Headers = bridgeHeaders("application/json"),
+			Body = jsonEncode({ versions = PROTOCOL_VERSIONS, plugin_version = PLUGIN_VERSION }),
+		})
+	end)
+	apiPrefix = "/stud"
+	protocolVersion = 1
+	bridgeFeatures = {}
+	if ok and response.Success then
+		local decoded, data = pcall(jsonDecode, response.Body)
+		if decoded and data and data.prefix then
+			apiPrefix = data.prefix
+			leaseSecs = tonumber(data.lease_secs) or leaseSecs
+			protocolVersion = tonumber(data.version) or 1
+			for _, feature in ipairs(data.features or {}) do
+				bridgeFeatures[feature] = true
+			end
+			print("[stud-bridge] Using bridge protocol v" .. tostring(data.version))
+		end
+	elseif ok and response.StatusCode == 426 then
+		warn("[stud-bridge] This plugin is too old for Stud; reinstall it from the Stud app")
+	end
+end
+
+-- WebSocket transport: the bridge pushes requests as soon as they are queued.
+-- Returns once the socket closes (or never opened) so the caller can fall back to polling.
+local function runWebSocket()
+	local ok, client = pcall(function()
+		return HttpService:CreateWebStreamClient(Enum.WebStreamClientType.WebSocket, {
+			Url = "ws://" .. bridgeHost .. apiPrefix .. WS_PATH .. "?" .. sessionQuery(),
+			Headers = bridgeHeaders(),
+		})
+	end)
+	if not ok or not client then
+		return
+	end
+
+	local queue = {}
+	local open = true
+	local greeted = false
+	local deadline = os.clock() + WS_GREETING_TIMEOUT
+
+	client.MessageReceived:Connect(function(message)
+		local decoded, data = pcall(jsonDecode, message)
+		if decoded and data then
+			-- Apply cancellations immediately so already-queued requests are skipped
+			noteCancelled(data)
+			table.insert(queue, data)
+		end
+	end)
+	client.Closed:Connect(function()
+		open = false
+	end)
+	client.Error:Connect(function()
+		open = false
+	end)
+
+	while pollingEnabled and open do
+		local data = table.remove(queue, 1)
+		if data then
+			if not greeted then
+				-- First message is the bridge's greeting
+				greeted = true
+				if not isConnected then
+					isConnected = true
+					isConnecting = false
+					updateUI()
+					addActivity("Connected (live)", "success")
+					print("[stud-bridge] Connected to Stud Desktop over WebSocket")
+				end
+			end
+
+			if data.request and not cancelledRequests[data.id] and claimRequest(data.id) then
+				local result = runLeased(data.id, data.request)
+				for _, message in ipairs(encodeResponse(data.id, result, data.request.trace_id)) do
+					pcall(function()
+						client:Send(message.payload)
+					end)
+				end
+			end
+		elseif not greeted and os.clock() > deadline then
+			break
+		else
+			task.wait(0.02)
+		end
+	end
+
+	pcall(function()
+		client:Close()
+	end)
+end
+
+-- Polling loop
+local function pollServer()
+	local failCount = 0
+	local maxFails = 3
+	
+	while pollingEnabled do
+		local nextPollDelay = DEFAULT_POLL_DELAY
+		local success, response = pcall(function()
+			return HttpService:RequestAsync({
+				Url = apiUrl(POLL_PATH) .. "&" .. sessionQuery(),
+				Method = "GET",
+				Headers = bridgeHeaders(),
+			})
+		end)
+		
+		if success and response.Success then
+			-- Connected!
+			if not isConnected then
+				isConnected = true
+				isConnecting = false
+				failCount = 0
+				updateUI()
+				addActivity("Connected", "success")
+				print("[stud-bridge] Connected to Stud Desktop")
+			end
+			
+			local data = jsonDecode(response.Body)
+			
+			-- The bridge says when to come back: right away while work is queued, slower when idle
+			if data and type(data.next_poll_ms) == "number" then
+				nextPollDelay = math.clamp(data.next_poll_ms / 1000, 0, MAX_POLL_DELAY)
+			end
+			
+			-- Bridge moved ports; both listeners share one queue so switch right away
+			if data and data.port_hint then
+				local newHost = "localhost:" .. tostring(data.port_hint)
+				if newHost ~= bridgeHost then
+					print("[stud-bridge] Bridge moved to " .. newHost)
+					bridgeHost = newHost
+				end
+			end
+			
+			-- Extract project info if available
+			if data and data.project then
+				projectInfo = data.project
+				updateUI()
+			end
+			
+			-- Batched polls list every request in `requests`; older bridges only send one
+			local batch = data and data.requests
+			if not batch and data and data.request then
+				batch = { { id = data.id, request = data.request } }
+			end
+			
+			noteCancelled(data)
+			for _, item in ipairs(batch or {}) do
+				if cancelledRequests[item.id] or not claimRequest(item.id) then
+					continue
+				end
+				local result = runLeased(item.id, item.request)
+				for _, message in ipairs(encodeResponse(item.id, result, item.request.trace_id)) do
+					pcall(function()
+						HttpService:RequestAsync({
+							Url = apiUrl(message.path),
+							Method = "POST",
+							Headers = bridgeHeaders("application/json"),
+							Body = message.payload,
+							Compress = #message.payload > COMPRESS_THRESHOLD
+								and Enum.HttpCompression.Gzip
+								or Enum.HttpCompression.None,
+						})
+					end)
+				end
+			end
+			failCount = 0
+		else
+			failCount = failCount + 1
+			-- Look for the bridge on its fallback ports every few failures
+			if failCount % maxFails == 0 and discoverBridge() then
+				negotiateProtocol()
+			end
+			if isConnected and failCount >= maxFails then
+				isConnected = false
+				isConnecting = true
+				projectInfo = nil
+				updateUI()
+				addActivity("Connection lost", "error")
+				print("[stud-bridge] Connection lost, retrying...")
+			end
+		end
+		
+		if nextPollDelay > 0 then
+			task.wait(nextPollDelay)
+		end
+	end
+	
+	-- Stopped polling
+	isConnected = false
+	isConnecting = false
+	projectInfo = nil
+	updateUI()
+end
+
+-- Watch expressions: resolve a dotted path like workspace.Player1.Humanoid.Health.
+-- Each segment is tried as a child first, then as a property.
+local function evaluateWatch(expression)
+	local segments = string.split(expression, ".")
+	local current
+	if segments[1] == "game" then
+		current = game
+	elseif segments[1] == "workspace" or segments[1] == "Workspace" then
+		current = workspace
+	else
+		error("Expression must start with game or workspace")
+	end
+
+	for i = 2, #segments do
+		local segment = segments[i]
+		local nextValue = nil
+		if typeof(current) == "Instance" then
+			nextValue = current:FindFirstChild(segment)
+		end
+		if nextValue == nil then
+			local ok, value = pcall(function()
+				return current[segment]
+			end)
+			if not ok then
+				error(table.concat(segments, ".", 1, i) .. " not found")
+			end
+			nextValue = value
+		end
+		current = nextValue
+	end
+
+	return current
+end
+
+local function serializeWatchValue(value)
+	local kind = typeof(value)
+	if kind == "number" or kind == "boolean" or kind == "string" then
+		return value
+	elseif kind == "Vector3" or kind == "CFrame" then
+		return { x = value.X, y = value.Y, z = value.Z }
+	elseif kind == "Vector2" then
+		return { x = value.X, y = value.Y }
+	elseif kind == "Color3" then
+		return { r = math.floor(value.R * 255 + 0.5), g = math.floor(value.G * 255 + 0.5), b = math.floor(value.B * 255 + 0.5) }
+	elseif kind == "Instance" then
+		return value:GetFullName()
+	elseif kind == "EnumItem" then
+		return tostring(value)
+	elseif value == nil then
+		return nil
+	end
+	return tostring(value)
+end
+
+-- Runs in the plugin instance inside a playtest server: samples the watches
+-- registered in Stud on their intervals and posts the values back in batches
+local function runWatchSampler()
+	local watches = {}
+	local lastSampled = {}
+	local pending = {}
+	local lastRefresh = -math.huge
+	local lastFlush = os.clock()
+
+	while true do
+		local now = os.clock()
+
+		if now - lastRefresh >= 2 then
+			lastRefresh = now
+			local ok, response = pcall(function()
+				return HttpService:RequestAsync({
+					Url = apiUrl(WATCH_PATH),
+					Method = "GET",
+					Headers = bridgeHeaders(),
+				})
+			end)
+			if ok and response.Success then
+				local decoded, list = pcall(jsonDecode, response.Body)
+				if decoded and list then
+					watches = list
+				end
+			end
+		end
+
+		for _, watch in ipairs(watches) do
+			if now - (lastSampled[watch.id] or -math.huge) >= watch.interval_ms / 1000 then
+				lastSampled[watch.id] = now
+				local sample = {
+					watch_id = watch.id,
+					t = DateTime.now().UnixTimestampMillis,
+				}
+				local ok, value = pcall(evaluateWatch, watch.expression)
+				if ok then
+					sample.value = serializeWatchValue(value)
+				else
+					sample.error = tostring(value)
+				end
+				table.insert(pending, sample)
+			end
+		end
+
+		if #pending > 0 and now - lastFlush >= 1 then
+			lastFlush = now
+			local batch = pending
+			pending = {}
+			pcall(function()
+				HttpService:RequestAsync({
+					Url = apiUrl(WATCH_SAMPLES_PATH),
+					Method = "POST",
+					Headers = bridgeHeaders("application/json"),
+					Body = jsonEncode({ session = "playtest-server", samples = batch }),
+				})
+			end)
+		end
+
+		task.wait(0.05)
+	end
+end
+
+-- Tell the bridge something happened in Studio; fire and forget
+local function postEvent(kind, data)
+	task.spawn(function()
+		pcall(function()
+			HttpService:RequestAsync({
+				Url = apiUrl(EVENT_PATH),
+				Method = "POST",
+				Headers = bridgeHeaders("application/json"),
+				Body = jsonEncode({ type = kind, session = SESSION_ID, data = data or {} }),
+			})
+		end)
+	end)
+end
+
+-- Report selection changes while connected, coalescing bursts into one event
+local selectionEventQueued = false
+local function onSelectionChanged()
+	if not isConnected or selectionEventQueued then
+		return
+	end
+	selectionEventQueued = true
+	task.delay(SELECTION_EVENT_DELAY, function()
+		selectionEventQueued = false
+		local selected = Selection:Get()
+		local paths = {}
+		for i = 1, math.min(#selected, MAX_SELECTION_EVENT_PATHS) do
+			table.insert(paths, selected[i]:GetFullName())
+		end
+		postEvent("selection_changed", { count = #selected, paths = paths })
+	end)
+end
+
+-- Runs in the plugin instance inside a playtest server: reports the playtest
+-- starting and stopping, and any script errors raised while it runs
+local function runPlaytestEvents()
+	local ScriptContext = game:GetService("ScriptContext")
+	postEvent("playtest_started", { place_id = game.PlaceId, place_name = game.Name })
+
+	ScriptContext.Error:Connect(function(message, stackTrace, script)
+		postEvent("script_error", {
+			message = message,
+			stack = stackTrace,
+			script = script and script:GetFullName() or nil,
+		})
+	end)
+
+	game:BindToClose(function()
+		-- Sent synchronously; the server is about to go away
+		pcall(function()
+			HttpService:RequestAsync({
+				Url = apiUrl(EVENT_PATH),
+				Method = "POST",
+				Headers = bridgeHeaders("application/json"),
+				Body = jsonEncode({ type = "playtest_stopped", session = SESSION_ID, data = {} }),
+			})
+		end)
+	end)
+end
+
+-- Runs in the plugin instance inside a playtest server: forwards output from
+-- debug prints injected by Stud (tagged [StudDebug:id]) back to the bridge
+local function runLogForwarder()
+	local LogService = game:GetService("LogService")
+	local pending = {}
+
+	LogService.MessageOut:Connect(function(message)
+		if string.find(message, "[StudDebug:", 1, true) then
+			table.insert(pending, {
+				t = DateTime.now().UnixTimestampMillis,
+				message = message,
+			})
+		end
+	end)
+
+	while true do
+		task.wait(1)
+		if #pending > 0 then
+			local batch = pending
+			pending = {}
+			pcall(function()
+				HttpService:RequestAsync({
+					Url = apiUrl(LOGS_PATH),
+					Method = "POST",
+					Headers = bridgeHeaders("application/json"),
+					Body = jsonEncode({ lines = batch }),
+				})
+			end)
+		end
 	end
-	
-	-- Stopped polling
-	isConnected = false
-	isConnecting = false
-	projectInfo = nil
-	updateUI()
 end
 
 -- Toggle connection
@@ -1180,7 +2830,13 @@ function toggleConnection()
 		updateUI()
 		addActivity("Connecting", "pending")
 		print("[stud-bridge] Connecting...")
-		task.spawn(pollServer)
+		task.spawn(function()
+			discoverBridge()
+			negotiateProtocol()
+			-- Prefer the live socket, fall back to HTTP polling when it's unavailable or drops
+			runWebSocket()
+			pollServer()
+		end)
 	else
 		isConnected = false
 		isConnecting = false
@@ -1191,11 +2847,20 @@ function toggleConnection()
 	end
 end
 
+-- Inside a playtest server there's no user to press Connect, so start sampling right away
+local RunService = game:GetService("RunService")
+if RunService:IsRunning() and RunService:IsServer() then
+	task.spawn(runWatchSampler)
+	task.spawn(runLogForwarder)
+	runPlaytestEvents()
+end
+
 -- Initialize
 createWidget()
 updateUI()
 
 toggleButton.Click:Connect(toggleConnection)
+Selection.SelectionChanged:Connect(onSelectionChanged)
 
 -- Show widget when button clicked
 toggleButton.Click:Connect(function()